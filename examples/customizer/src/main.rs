@@ -3,9 +3,11 @@ use std::{
 };
 
 use async_resol_vbus::{
+    value_id_hash_by_id,
     LiveDataStream,
     Result,
     TcpClientHandshake,
+    ValueIndexLookup,
 };
 
 use async_std::net::TcpStream;
@@ -43,12 +45,6 @@ struct Transaction {
     value: Option<f64>,
 }
 
-fn value_id_hash_by_id(id: &str) -> i32 {
-    id.chars().fold(0, |acc, c| {
-        acc.wrapping_mul(0x21).wrapping_add(c as i32) & 0x7fffffff
-    })
-}
-
 fn main() -> Result<()> {
     env_logger::init();
 
@@ -251,21 +247,16 @@ fn main() -> Result<()> {
                     trace!("value_id_hash_by_id({:?}) = 0x{:08X}", id, id_hash);
 
                     let index = match stream.get_value_index_by_id_hash(peer_address, id_hash).await? {
-                        Some(dgram) => {
-                            if dgram.command == 0x0100 {
-                                needs_resync = true;
-                            }
-                            dgram.param16
+                        ValueIndexLookup::Found { index } => index,
+                        ValueIndexLookup::ResyncRequired { index } => {
+                            needs_resync = true;
+                            index
                         },
-                        None => {
-                            0
+                        ValueIndexLookup::Unknown | ValueIndexLookup::NoReply => {
+                            return Err(format!("Unable to get index for param {:?}", param.id).into());
                         },
                     };
 
-                    if index == 0 {
-                        return Err(format!("Unable to get index for param {:?}", param.id).into());
-                    }
-
                     trace!("value_index_by_id_hash(0x{:08X}) = 0x{:04X}", id_hash, index);
                     param.index = Some(index);
 
@@ -1,15 +1,7 @@
-use std::cmp::Ordering;
+use std::collections::HashSet;
 
 use async_resol_vbus::*;
 
-fn compare_devices(l: &DeviceInformation, r: &DeviceInformation) -> Ordering {
-    let mut result = l.address.ip().cmp(&r.address.ip());
-    if result == Ordering::Equal {
-        result = l.address.port().cmp(&r.address.port());
-    }
-    result
-}
-
 fn main() -> Result<()> {
     async_std::task::block_on(async {
         let discovery = DeviceDiscovery::new();
@@ -17,58 +9,35 @@ fn main() -> Result<()> {
         // discovery.set_broadcast_addr("192.168.180.255:7053".parse().unwrap());
         // discovery.set_fetch_port(3000);
 
-        let mut known_devices = Vec::<DeviceInformation>::new();
+        let mut known_devices = HashSet::<DeviceInformation>::new();
         loop {
             println!("---- Discovering... ----");
 
-            let mut found_devices = discovery.discover_devices().await?;
-            found_devices.sort_by(|l, r| compare_devices(l, r));
-
-            known_devices.sort_by(|l, r| compare_devices(l, r));
-
-            for found_device in found_devices.iter() {
-                let is_known_device = known_devices
-                    .iter()
-                    .any(|known_device| known_device.address == found_device.address);
+            let found_devices = discovery
+                .discover_devices()
+                .await?
+                .into_iter()
+                .collect::<HashSet<_>>();
 
-                if !is_known_device {
-                    let name = match &found_device.name {
-                        Some(name) => name.as_str(),
-                        None => "???",
-                    };
+            for found_device in found_devices.difference(&known_devices) {
+                let name = match &found_device.name {
+                    Some(name) => name.as_str(),
+                    None => "???",
+                };
 
-                    println!("FOUND: {} {}", found_device.address, name);
-                    known_devices.push(found_device.clone());
-                }
+                println!("FOUND: {} {}", found_device.address, name);
             }
 
-            let mut lost_device_addresses = Vec::new();
+            for known_device in known_devices.difference(&found_devices) {
+                let name = match &known_device.name {
+                    Some(name) => name.as_str(),
+                    None => "???",
+                };
 
-            for known_device in known_devices.iter() {
-                let is_found_device = found_devices
-                    .iter()
-                    .any(|found_device| known_device.address == found_device.address);
-
-                if !is_found_device {
-                    let name = match &known_device.name {
-                        Some(name) => name.as_str(),
-                        None => "???",
-                    };
-
-                    println!("LOST:  {} {}", known_device.address, name);
-                    lost_device_addresses.push(known_device.address);
-                }
+                println!("LOST:  {} {}", known_device.address, name);
             }
 
-            for lost_device_address in lost_device_addresses {
-                let pos = known_devices
-                    .iter()
-                    .position(|known_device| known_device.address == lost_device_address);
-
-                if let Some(idx) = pos {
-                    known_devices.remove(idx);
-                }
-            }
+            known_devices = found_devices;
 
             async_std::task::sleep(std::time::Duration::from_secs(10)).await;
         }
@@ -4,7 +4,7 @@ use async_std::net::{SocketAddr, TcpStream};
 
 use resol_vbus::{DataSet, RecordingWriter};
 
-use async_resol_vbus::{LiveDataStream, Result, TcpClientHandshake};
+use async_resol_vbus::{connect_live_data_stream, ConnectOptions, Result};
 
 fn main() -> Result<()> {
     async_std::task::block_on(async {
@@ -17,13 +17,7 @@ fn main() -> Result<()> {
 
         let stream = TcpStream::connect(addr).await?;
 
-        let mut hs = TcpClientHandshake::start(stream).await?;
-        hs.send_pass_command("vbus").await?;
-        let stream = hs.send_data_command().await?;
-
-        let (reader, writer) = (&stream, &stream);
-
-        let mut stream = LiveDataStream::new(reader, writer, 0, 0x0020);
+        let mut stream = connect_live_data_stream(stream, &ConnectOptions::new()).await?;
 
         while let Some(data) = stream.receive_any_data(60000).await? {
             println!("{}", data.id_string());
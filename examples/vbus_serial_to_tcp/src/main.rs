@@ -3,8 +3,14 @@ use std::{
 };
 
 use async_resol_vbus::{
+    list_serial_ports,
+    open_serial_port,
     Result,
-    LiveDataBuffer,
+    SerialOptions,
+    SerialParity,
+    SerialProfile,
+    SerialReader,
+    SerialWriter,
     TcpServerHandshake,
 };
 
@@ -17,53 +23,19 @@ use async_std::{
 
 use clap::{App, Arg};
 
-use log::{error, trace};
+use log::{error, info, trace, warn};
 
-use serialport::SerialPort;
-
-fn wrap_err<T, E: std::fmt::Debug>(message: &str, err: E) -> Result<T> {
-    Err(format!("{}: {:?}", message, err).into())
-}
-
-fn run_serial_read_loop(mut rx_port: Box<dyn SerialPort>, rx_port_sender: Sender<Vec<u8>>) -> Result<()> {
-    let mut ldb = LiveDataBuffer::new(0);
-    let mut buf = [0; 4096];
-    loop {
-        // trace!("Start reading from serial port...");
-        let size = match rx_port.read(&mut buf) {
-            Ok(size) => size,
-            Err(err) if err.kind() == std::io::ErrorKind::TimedOut => continue,
-            Err(err) => return wrap_err("Unable to read from serial port", err),
-        };
-
-        ldb.extend_from_slice(&buf [0..size]);
-        while let Some(data) = ldb.read_data() {
-            trace!("Received {}", data.id_string());
-        }
-
-        // trace!("Read {} bytes from serial port...", size);
-        let mut v = Vec::with_capacity(size);
-        v.extend_from_slice(&buf [0..size]);
-
-        // trace!("Sending {} bytes to rx port channel...", size);
-        match rx_port_sender.try_send(v) {
-            Ok(_) => {},
-            Err(err) => return wrap_err("Unable to send to rx port channel", err),
-        }
+fn parse_level(name: &str, value: Option<&str>) -> Result<Option<bool>> {
+    match value {
+        Some("on") => Ok(Some(true)),
+        Some("off") => Ok(Some(false)),
+        Some(value) => Err(format!("Unable to parse {} level {:?}", name, value).into()),
+        None => Ok(None),
     }
 }
 
-async fn run_serial_write_loop(mut tx_port: Box<dyn SerialPort>, tx_port_receiver: Receiver<Vec<u8>>) -> Result<()> {
-    loop {
-        // trace!("Start receiving from tx port channel...");
-        let buf = match tx_port_receiver.recv().await {
-            Ok(buf) => buf,
-            Err(err) => return wrap_err("Unable to receive from TX port channel", err),
-        };
-
-        // trace!("Write {} bytes to serial port...", buf.len());
-        tx_port.write_all(&buf)?;
-    }
+fn wrap_err<T, E: std::fmt::Debug>(message: &str, err: E) -> Result<T> {
+    Err(format!("{}: {:?}", message, err).into())
 }
 
 fn remove_stream_with_id(tx_clients: &mut Vec<(usize, TcpStream)>, stream_id: usize) {
@@ -155,19 +127,150 @@ async fn run_clients_write_loop(tx_clients: Arc<Mutex<Vec<(usize, TcpStream)>>>,
     }
 }
 
+async fn run_serial_read_loop(mut reader: SerialReader, rx_port_sender: &Sender<Vec<u8>>) -> Result<()> {
+    let mut buf = [0; 4096];
+    loop {
+        let size = reader.read(&mut buf).await?;
+        if size == 0 {
+            break Ok(());
+        }
+
+        if let Err(err) = rx_port_sender.try_send(buf [0..size].to_vec()) {
+            break wrap_err("Unable to send to rx port channel", err);
+        }
+    }
+}
+
+async fn run_serial_write_loop(mut writer: SerialWriter, tx_port_receiver: Receiver<Vec<u8>>) -> Result<()> {
+    loop {
+        let buf = match tx_port_receiver.recv().await {
+            Ok(buf) => buf,
+            Err(err) => return wrap_err("Unable to receive from tx port channel", err),
+        };
+
+        // writing fails while the serial port is reopened
+        if let Err(err) = writer.write_all(&buf).await {
+            warn!("Unable to write to serial port: {:?}", err);
+        }
+    }
+}
+
+async fn run_serial_loop(path: String, options: SerialOptions, rx_port_sender: Sender<Vec<u8>>, tx_port_receiver: Receiver<Vec<u8>>) {
+    loop {
+        match open_serial_port(&path, &options).await {
+            Ok((reader, writer)) => {
+                info!("Opened serial port {}", path);
+
+                let write_task = async_std::task::spawn(run_serial_write_loop(writer, tx_port_receiver.clone()));
+                let result = run_serial_read_loop(reader, &rx_port_sender).await;
+                write_task.cancel().await;
+
+                if let Err(err) = result {
+                    warn!("Serial port failed: {:?}", err);
+                }
+            },
+            Err(err) => {
+                warn!("Serial port unavailable, reconnecting: {:?}", err);
+            },
+        }
+
+        async_std::task::sleep(Duration::from_secs(2)).await;
+    }
+}
+
 async fn run_main_loop() -> Result<()> {
     let matches = App::new("vbus_serial_to_tcp")
         .arg(Arg::with_name("path")
             .index(1)
-            .required(true)
+            .required_unless("list")
             .takes_value(true))
         .arg(Arg::with_name("port")
             .index(2)
             .required(false)
             .takes_value(true))
+        .arg(Arg::with_name("list")
+            .long("list")
+            .help("Lists the available serial ports"))
+        .arg(Arg::with_name("baud-rate")
+            .long("baud-rate")
+            .takes_value(true)
+            .help("The baud rate of the serial port or \"auto\" (default: 9600)"))
+        .arg(Arg::with_name("max-framing-errors")
+            .long("max-framing-errors")
+            .takes_value(true)
+            .help("The number of consecutive invalid bytes after which the serial port is reopened (default: 4096)"))
+        .arg(Arg::with_name("profile")
+            .long("profile")
+            .takes_value(true)
+            .possible_values(&["usb", "uart"])
+            .help("The kind of serial connection (default: usb)"))
+        .arg(Arg::with_name("parity")
+            .long("parity")
+            .takes_value(true)
+            .possible_values(&["none", "even", "odd"])
+            .help("The parity of the serial port (default: none)"))
+        .arg(Arg::with_name("rts")
+            .long("rts")
+            .takes_value(true)
+            .possible_values(&["on", "off"])
+            .help("Sets the RTS line after opening the serial port"))
+        .arg(Arg::with_name("dtr")
+            .long("dtr")
+            .takes_value(true)
+            .possible_values(&["on", "off"])
+            .help("Sets the DTR line after opening the serial port"))
         .get_matches();
 
-    let path = matches.value_of("path").expect("No path provided");
+    if matches.is_present("list") {
+        for candidate in list_serial_ports()? {
+            println!("{}\t{}", candidate.port_name, candidate.description);
+        }
+        return Ok(());
+    }
+
+    let path = matches.value_of("path").expect("No path provided").to_string();
+
+    let mut options = SerialOptions::new();
+
+    let baud_rate = match matches.value_of("baud-rate") {
+        Some("auto") => None,
+        Some(baud_rate) => {
+            match baud_rate.parse::<u32>() {
+                Ok(baud_rate) => Some(baud_rate),
+                Err(err) => return wrap_err("Unable to parse baud rate", err),
+            }
+        },
+        None => Some(9600),
+    };
+
+    let max_framing_errors = match matches.value_of("max-framing-errors") {
+        Some(max_framing_errors) => {
+            match max_framing_errors.parse::<usize>() {
+                Ok(max_framing_errors) => max_framing_errors,
+                Err(err) => return wrap_err("Unable to parse max framing errors", err),
+            }
+        },
+        None => 4096,
+    };
+
+    let profile = match matches.value_of("profile") {
+        Some("uart") => SerialProfile::Uart,
+        _ => SerialProfile::Usb,
+    };
+
+    let parity = match matches.value_of("parity") {
+        Some("even") => SerialParity::Even,
+        Some("odd") => SerialParity::Odd,
+        _ => SerialParity::None,
+    };
+
+    options.set_baud_rate(baud_rate);
+    options.set_max_framing_errors(max_framing_errors);
+    options.set_profile(profile);
+    options.set_parity(parity);
+    options.set_rts(parse_level("RTS", matches.value_of("rts"))?);
+    options.set_dtr(parse_level("DTR", matches.value_of("dtr"))?);
+    options.set_reconnect_interval(Some(Duration::from_secs(2)));
 
     let port = match matches.value_of("port") {
         Some(port) => {
@@ -186,28 +289,10 @@ async fn run_main_loop() -> Result<()> {
 
     let tx_clients = Arc::new(Mutex::new(Vec::new()));
 
-    let tx_port = match serialport::new(path, 9600).timeout(Duration::from_secs(10)).open() {
-        Ok(serialport) => serialport,
-        Err(err) => return wrap_err("Unable to open serial port", err),
-    };
-
-    let rx_port = match tx_port.try_clone() {
-        Ok(serialport) => serialport,
-        Err(err) => return wrap_err("Unable to clone serial port", err),
-    };
-
     let (tx_port_sender, tx_port_receiver) = async_std::channel::bounded(10);
     let (rx_port_sender, rx_port_receiver) = async_std::channel::bounded(10);
 
-    std::thread::spawn(move || {
-        let result = run_serial_read_loop(rx_port, rx_port_sender);
-        panic!("Serial read loop should not have ended: {:?}", result);
-    });
-
-    async_std::task::spawn(async move {
-        let result = run_serial_write_loop(tx_port, tx_port_receiver).await;
-        panic!("Serial write loop should not have ended: {:?}", result);
-    });
+    async_std::task::spawn(run_serial_loop(path, options, rx_port_sender, tx_port_receiver));
 
     {
         let tx_clients = tx_clients.clone();
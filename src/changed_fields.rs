@@ -0,0 +1,40 @@
+use resol_vbus::{specification::DataSetPacketField, Data, DataSet, Specification};
+
+/// Call `f` for every field of `data` whose value differs from the
+/// `previous` `Data` with the same ID.
+///
+/// All fields of `data` are considered changed if there is no `previous`
+/// `Data`.
+pub(crate) fn changed_fields<F>(
+    previous: Option<&Data>,
+    data: &Data,
+    spec: &Specification,
+    mut f: F,
+) where
+    F: FnMut(&DataSetPacketField<'_, DataSet>),
+{
+    let mut previous_data_set = DataSet::new();
+    if let Some(previous) = previous {
+        previous_data_set.add_data(previous.clone());
+    }
+    let previous_values = spec
+        .fields_in_data_set(&previous_data_set)
+        .map(|field| {
+            (
+                field.field_spec().packet_field_id.clone(),
+                field.raw_value_f64(),
+            )
+        })
+        .collect::<Vec<_>>();
+
+    let mut data_set = DataSet::new();
+    data_set.add_data(data.clone());
+    for field in spec.fields_in_data_set(&data_set) {
+        let is_unchanged = previous_values.iter().any(|(id, value)| {
+            *id == field.field_spec().packet_field_id && *value == field.raw_value_f64()
+        });
+        if !is_unchanged {
+            f(&field);
+        }
+    }
+}
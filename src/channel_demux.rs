@@ -0,0 +1,171 @@
+use std::{
+    marker::Unpin,
+    sync::{Arc, Mutex},
+};
+
+use async_std::{channel::Receiver, prelude::*, stream::Stream};
+
+use resol_vbus::Data;
+
+use crate::{data_hub::DataHub, error::Result};
+
+/// Splits a multi-channel stream, e.g. from a DL3 or a `ChannelMux`, into
+/// one `DataHub` per channel.
+///
+/// The `DataHub` of a channel is created on first use, so subscribers can
+/// be added before any `Data` for that channel was received. Cloned
+/// `ChannelDemux`s share their hubs.
+///
+/// # Examples
+///
+/// ```no_run
+/// # fn main() -> async_resol_vbus::Result<()> { async_std::task::block_on(async {
+/// #
+/// use async_std::net::TcpStream;
+///
+/// use async_resol_vbus::{ChannelDemux, LiveDataStream};
+///
+/// let stream = TcpStream::connect("192.168.5.217:7053").await?;
+/// // ... perform handshake ...
+/// let lds = LiveDataStream::from_tcp_stream(stream, 0, 0x0020);
+///
+/// let demux = ChannelDemux::new();
+///
+/// let receiver = demux.subscribe(2);
+/// async_std::task::spawn(async move {
+///     while let Ok(data) = receiver.recv().await {
+///         println!("{}", data.id_string());
+///     }
+/// });
+///
+/// demux.run(lds.into_data_stream().await?).await?;
+/// #
+/// # Ok(()) }) }
+/// ```
+#[derive(Debug, Clone)]
+pub struct ChannelDemux {
+    hubs: Arc<Mutex<Vec<(u8, DataHub)>>>,
+    capacity: usize,
+}
+
+impl ChannelDemux {
+    /// Create a new `ChannelDemux` without hubs.
+    pub fn new() -> ChannelDemux {
+        ChannelDemux {
+            hubs: Arc::new(Mutex::new(Vec::new())),
+            capacity: 64,
+        }
+    }
+
+    /// Set the number of `Data` queued for every subscriber of the hubs
+    /// created afterwards.
+    ///
+    /// Defaults to 64.
+    pub fn set_capacity(&mut self, capacity: usize) {
+        self.capacity = capacity;
+    }
+
+    /// Get the `DataHub` for the given channel, creating it if necessary.
+    pub fn hub(&self, channel: u8) -> DataHub {
+        let mut hubs = self.hubs.lock().unwrap();
+        if let Some((_, hub)) = hubs.iter().find(|(existing, _)| *existing == channel) {
+            return hub.clone();
+        }
+
+        let mut hub = DataHub::new();
+        hub.set_capacity(self.capacity);
+        hubs.push((channel, hub.clone()));
+        hub
+    }
+
+    /// Add a subscriber for the given channel and return the `Receiver`
+    /// for its `Data`.
+    pub fn subscribe(&self, channel: u8) -> Receiver<Arc<Data>> {
+        self.hub(channel).subscribe()
+    }
+
+    /// Get the channels that have a hub, in ascending order.
+    pub fn channels(&self) -> Vec<u8> {
+        let mut channels = self
+            .hubs
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|(channel, _)| *channel)
+            .collect::<Vec<_>>();
+        channels.sort_unstable();
+        channels
+    }
+
+    /// Send `data` to the subscribers of its channel.
+    pub fn publish(&self, data: Data) -> Arc<Data> {
+        self.hub(data.as_ref().channel).publish(data)
+    }
+
+    /// Publish all `Data` from `stream` until it ends or yields an error.
+    ///
+    /// Pass the stream by mutable reference to continue using it after
+    /// `run` returned or was cancelled.
+    pub async fn run<S: Stream<Item = Result<Data>> + Unpin>(&self, mut stream: S) -> Result<()> {
+        while let Some(data) = stream.next().await {
+            self.publish(data?);
+        }
+        Ok(())
+    }
+}
+
+impl Default for ChannelDemux {
+    fn default() -> Self {
+        ChannelDemux::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use async_std::io::Cursor;
+
+    use super::*;
+
+    use crate::{
+        live_data_stream::LiveDataStream,
+        test_utils::{extend_with_empty_packet, simulate_run},
+    };
+
+    #[test]
+    fn test_channel_demux() {
+        let mut rx_buf = Vec::new();
+        extend_with_empty_packet(&mut rx_buf, 0x0010, 0x7E11, 0x0100);
+        extend_with_empty_packet(&mut rx_buf, 0x0010, 0x7E21, 0x0100);
+
+        let demux = ChannelDemux::new();
+        let receiver0 = demux.subscribe(0);
+        let receiver2 = demux.subscribe(2);
+        assert_eq!(vec![0, 2], demux.channels());
+
+        for channel in [0, 2] {
+            let lds = LiveDataStream::new(
+                Cursor::new(rx_buf.clone()),
+                Cursor::new(Vec::new()),
+                channel,
+                0x0020,
+            );
+            simulate_run(async { demux.run(lds.into_data_stream().await?).await }).unwrap();
+        }
+
+        let mut ids = Vec::new();
+        while let Ok(data) = receiver0.try_recv() {
+            ids.push(data.id_string());
+        }
+        assert_eq!(vec!["00_0010_7E11_10_0100", "00_0010_7E21_10_0100"], ids);
+
+        let mut ids = Vec::new();
+        while let Ok(data) = receiver2.try_recv() {
+            ids.push(data.id_string());
+        }
+        assert_eq!(vec!["02_0010_7E11_10_0100", "02_0010_7E21_10_0100"], ids);
+
+        let receiver1 = demux.subscribe(1);
+        assert_eq!(vec![0, 1, 2], demux.channels());
+        assert!(receiver1.try_recv().is_err());
+    }
+}
@@ -0,0 +1,265 @@
+use std::marker::Unpin;
+
+use async_std::{
+    channel::{Receiver, Sender},
+    io::{Read, Write},
+    prelude::*,
+};
+
+use resol_vbus::{Data, LiveDataBuffer};
+
+use crate::{error::Result, live_data_sender::LiveDataSender};
+
+type BoxedReader = Box<dyn Read + Unpin + Send>;
+type BoxedWriter = Box<dyn Write + Unpin + Send>;
+
+/// Combines several physical VBus connections into a single logical
+/// multi-channel stream.
+///
+/// Every source is assigned a channel. All `Data` received from a source
+/// is decoded using that channel, so that consumers can tell the sources
+/// apart like the channels of a DL3. Outgoing `Data` is routed back to the
+/// source whose channel matches the `Data`'s header. Sources can be mixed
+/// freely, e.g. a serial port and several VBus-over-TCP connections.
+///
+/// # Examples
+///
+/// ```no_run
+/// # fn main() -> async_resol_vbus::Result<()> { async_std::task::block_on(async {
+/// #
+/// use async_resol_vbus::{quick_connect, ChannelMux, DataHub};
+///
+/// let mut mux = ChannelMux::new();
+/// for (channel, url) in ["vbus+serial:///dev/ttyUSB0", "vbus+tcp://192.168.5.217"]
+///     .iter()
+///     .enumerate()
+/// {
+///     let (reader, writer) = quick_connect(url).await?.into_inner();
+///     mux.add_source(channel as u8, reader, writer)?;
+/// }
+///
+/// let (stream, writer) = mux.start();
+///
+/// let hub = DataHub::new();
+/// hub.run(stream).await?;
+/// #
+/// # Ok(()) }) }
+/// ```
+pub struct ChannelMux {
+    sources: Vec<(u8, BoxedReader, BoxedWriter)>,
+    capacity: usize,
+}
+
+impl ChannelMux {
+    /// Create a new `ChannelMux` without sources.
+    pub fn new() -> ChannelMux {
+        ChannelMux {
+            sources: Vec::new(),
+            capacity: 64,
+        }
+    }
+
+    /// Set the number of received `Data` queued before the sources stop
+    /// reading.
+    ///
+    /// Defaults to 64.
+    pub fn set_capacity(&mut self, capacity: usize) {
+        self.capacity = capacity;
+    }
+
+    /// Add a source using the given channel.
+    ///
+    /// The `reader` and `writer` must already be in data mode, e.g. after
+    /// completing a VBus-over-TCP handshake.
+    pub fn add_source<R, W>(&mut self, channel: u8, reader: R, writer: W) -> Result<()>
+    where
+        R: Read + Unpin + Send + 'static,
+        W: Write + Unpin + Send + 'static,
+    {
+        if self
+            .sources
+            .iter()
+            .any(|(existing, _, _)| *existing == channel)
+        {
+            return Err(format!("Channel {} is already assigned", channel).into());
+        }
+
+        self.sources
+            .push((channel, Box::new(reader), Box::new(writer)));
+        Ok(())
+    }
+
+    /// Start reading from all sources.
+    ///
+    /// Returns the `Receiver` yielding the `Data` of all sources, which can
+    /// be passed to `DataHub::run`, and the `ChannelMuxWriter` for outgoing
+    /// `Data`. A source that fails yields its error once and is not read
+    /// anymore. The `Receiver` ends after all sources have ended.
+    pub fn start(self) -> (Receiver<Result<Data>>, ChannelMuxWriter) {
+        let (sender, receiver) = async_std::channel::bounded(self.capacity);
+
+        let mut writers = Vec::new();
+        for (channel, reader, writer) in self.sources {
+            async_std::task::spawn(read_source(channel, reader, sender.clone()));
+            writers.push((channel, LiveDataSender::new(writer)));
+        }
+
+        (receiver, ChannelMuxWriter { writers })
+    }
+}
+
+impl Default for ChannelMux {
+    fn default() -> Self {
+        ChannelMux::new()
+    }
+}
+
+impl std::fmt::Debug for ChannelMux {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let channels = self
+            .sources
+            .iter()
+            .map(|(channel, _, _)| *channel)
+            .collect::<Vec<_>>();
+        f.debug_struct("ChannelMux")
+            .field("channels", &channels)
+            .field("capacity", &self.capacity)
+            .finish()
+    }
+}
+
+async fn read_source(channel: u8, mut reader: BoxedReader, sender: Sender<Result<Data>>) {
+    let mut buf = LiveDataBuffer::new(channel);
+    let mut chunk = [0u8; 1024];
+    loop {
+        let len = match reader.read(&mut chunk).await {
+            Ok(0) => break,
+            Ok(len) => len,
+            Err(err) => {
+                drop(sender.send(Err(err.into())).await);
+                break;
+            }
+        };
+
+        buf.extend_from_slice(&chunk[0..len]);
+        while let Some(data) = buf.read_data() {
+            if sender.send(Ok(data)).await.is_err() {
+                return;
+            }
+        }
+    }
+}
+
+/// Routes outgoing `Data` to the sources of a `ChannelMux`.
+pub struct ChannelMuxWriter {
+    writers: Vec<(u8, LiveDataSender<BoxedWriter>)>,
+}
+
+impl ChannelMuxWriter {
+    /// Send `data` to the source assigned to the channel of its header.
+    pub async fn send(&mut self, data: &Data) -> Result<()> {
+        let channel = data.as_ref().channel;
+        match self
+            .writers
+            .iter_mut()
+            .find(|(existing, _)| *existing == channel)
+        {
+            Some((_, writer)) => writer.send(data).await,
+            None => Err(format!("No source assigned to channel {}", channel).into()),
+        }
+    }
+}
+
+impl std::fmt::Debug for ChannelMuxWriter {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let channels = self
+            .writers
+            .iter()
+            .map(|(channel, _)| *channel)
+            .collect::<Vec<_>>();
+        f.debug_struct("ChannelMuxWriter")
+            .field("channels", &channels)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use async_std::net::{TcpListener, TcpStream};
+
+    use resol_vbus::{chrono::Utc, Datagram, Header};
+
+    use super::*;
+
+    use crate::test_utils::{extend_from_datagram, extend_with_empty_packet};
+
+    async fn socket_pair(listener: &TcpListener) -> Result<(TcpStream, TcpStream)> {
+        let local = TcpStream::connect(listener.local_addr()?).await?;
+        let (remote, _) = listener.accept().await?;
+        Ok((local, remote))
+    }
+
+    #[test]
+    fn test_channel_mux() -> Result<()> {
+        async_std::task::block_on(async {
+            let listener = TcpListener::bind("127.0.0.1:0").await?;
+            let (local1, mut remote1) = socket_pair(&listener).await?;
+            let (local2, mut remote2) = socket_pair(&listener).await?;
+
+            let mut mux = ChannelMux::new();
+            mux.add_source(1, local1.clone(), local1)?;
+            mux.add_source(2, local2.clone(), local2)?;
+            assert_eq!(
+                Err("Channel 2 is already assigned".into()),
+                mux.add_source(2, &b""[..], Vec::new())
+            );
+
+            let (receiver, mut writer) = mux.start();
+
+            let mut bytes = Vec::new();
+            extend_with_empty_packet(&mut bytes, 0x0010, 0x7E11, 0x0100);
+            remote1.write_all(&bytes).await?;
+            let data = receiver.recv().await.unwrap()?;
+            assert_eq!("01_0010_7E11_10_0100", data.id_string());
+
+            let mut bytes = Vec::new();
+            extend_with_empty_packet(&mut bytes, 0x0010, 0x7E21, 0x0100);
+            remote2.write_all(&bytes).await?;
+            let data = receiver.recv().await.unwrap()?;
+            assert_eq!("02_0010_7E21_10_0100", data.id_string());
+
+            let dgram = |channel| {
+                Data::Datagram(Datagram {
+                    header: Header {
+                        timestamp: Utc::now(),
+                        channel,
+                        destination_address: 0x7E21,
+                        source_address: 0x0020,
+                        protocol_version: 0x20,
+                    },
+                    command: 0x0300,
+                    param16: 0x1234,
+                    param32: 0,
+                })
+            };
+
+            writer.send(&dgram(2)).await?;
+            let mut expected = Vec::new();
+            extend_from_datagram(&mut expected, 0x7E21, 0x0020, 0x0300, 0x1234, 0);
+            let mut received = vec![0u8; expected.len()];
+            remote2.read_exact(&mut received).await?;
+            assert_eq!(expected, received);
+
+            assert_eq!(
+                Err("No source assigned to channel 3".into()),
+                writer.send(&dgram(3)).await
+            );
+
+            drop(remote1);
+            drop(remote2);
+            assert!(receiver.recv().await.is_err());
+
+            Ok(())
+        })
+    }
+}
@@ -0,0 +1,251 @@
+use clap::{Arg, ArgMatches};
+
+use crate::{connection_spec::ConnectionSpec, error::Result, serial_options::SerialOptions};
+
+/// Get the `clap` argument definitions for the connection settings.
+///
+/// The arguments are:
+///
+/// - `--url URL`: a connection URL, see `ConnectionSpec`
+/// - `--host HOST`: the host name or IP address of a VBus-over-TCP device
+/// - `--port PORT`: the TCP port, defaults to 7053
+/// - `--password PASSWORD`: the password, defaults to `vbus`
+/// - `--via-tag VIATAG`: the via tag for VBus.net connections
+/// - `--channel CHANNEL`: the channel of a multi-channel device
+/// - `--serial PATH`: the path or name of a serial port, e.g. `COM3`
+/// - `--baud-rate BAUDRATE`: the baud rate of the serial port or `auto`,
+///   defaults to 9600
+/// - `--serial-profile PROFILE`: `usb` or `uart`, selects the ports tried
+///   for `--serial auto`
+/// - `--parity PARITY`: `none`, `even` or `odd`, defaults to `none`
+/// - `--rts LEVEL`, `--dtr LEVEL`: `on` or `off`, sets the line after
+///   opening the serial port
+/// - `--reconnect-interval MS`: reopen the serial port after it failed
+///
+/// See `SerialOptions` for details on the serial port settings.
+///
+/// Use `connection_spec_from_matches` to get the corresponding
+/// `ConnectionSpec` after parsing the command line.
+///
+/// # Examples
+///
+/// ```
+/// # fn main() -> async_resol_vbus::Result<()> {
+/// use async_resol_vbus::{connection_args, connection_spec_from_matches, ConnectionSpec};
+///
+/// let command = clap::Command::new("tool").args(connection_args());
+///
+/// let matches = command.get_matches_from(["tool", "--host", "192.168.5.217", "--channel", "1"]);
+/// assert_eq!(
+///     ConnectionSpec::Tcp {
+///         host: "192.168.5.217".into(),
+///         port: 7053,
+///         password: None,
+///         via_tag: None,
+///         channel: Some(1),
+///     },
+///     connection_spec_from_matches(&matches)?
+/// );
+/// # Ok(()) }
+/// ```
+pub fn connection_args() -> Vec<Arg> {
+    vec![
+        Arg::new("url")
+            .long("url")
+            .value_name("URL")
+            .help("Set the connection URL (e.g. vbus+tcp://192.168.5.217)")
+            .conflicts_with_all(["host", "serial"]),
+        Arg::new("host")
+            .long("host")
+            .value_name("HOST")
+            .help("Set the host to communicate with")
+            .conflicts_with("serial"),
+        Arg::new("port")
+            .long("port")
+            .value_name("PORT")
+            .help("Set the port to communicate with")
+            .value_parser(clap::value_parser!(u16))
+            .requires("host"),
+        Arg::new("password")
+            .long("password")
+            .value_name("PASSWORD")
+            .help("Set the password for the RESOL VBus device")
+            .requires("host"),
+        Arg::new("via_tag")
+            .long("via-tag")
+            .alias("viaTag")
+            .value_name("VIATAG")
+            .help("Set the via tag to communicate with")
+            .requires("host"),
+        Arg::new("channel")
+            .long("channel")
+            .value_name("CHANNEL")
+            .help("Set the channel to communicate over")
+            .value_parser(clap::value_parser!(u8))
+            .requires("host"),
+        Arg::new("serial")
+            .long("serial")
+            .value_name("PATH")
+            .help("Set the serial port to communicate over"),
+        Arg::new("baud_rate")
+            .long("baud-rate")
+            .value_name("BAUDRATE")
+            .help("Set the baud rate of the serial port or \"auto\" to detect it")
+            .requires("serial"),
+        Arg::new("serial_profile")
+            .long("serial-profile")
+            .value_name("PROFILE")
+            .help("Set the kind of serial connection")
+            .value_parser(["usb", "uart"])
+            .requires("serial"),
+        Arg::new("parity")
+            .long("parity")
+            .value_name("PARITY")
+            .help("Set the parity of the serial port")
+            .value_parser(["none", "even", "odd"])
+            .requires("serial"),
+        Arg::new("rts")
+            .long("rts")
+            .value_name("LEVEL")
+            .help("Set the RTS line after opening the serial port")
+            .value_parser(["on", "off"])
+            .requires("serial"),
+        Arg::new("dtr")
+            .long("dtr")
+            .value_name("LEVEL")
+            .help("Set the DTR line after opening the serial port")
+            .value_parser(["on", "off"])
+            .requires("serial"),
+        Arg::new("reconnect_interval")
+            .long("reconnect-interval")
+            .value_name("MS")
+            .help(
+                "Reopen the serial port after it failed, waiting MS milliseconds between attempts",
+            )
+            .requires("serial"),
+    ]
+}
+
+/// Get the `ConnectionSpec` described by the arguments defined by
+/// `connection_args`.
+pub fn connection_spec_from_matches(matches: &ArgMatches) -> Result<ConnectionSpec> {
+    if let Some(url) = matches.get_one::<String>("url") {
+        ConnectionSpec::parse(url)
+    } else if let Some(path) = matches.get_one::<String>("serial") {
+        let mut options = SerialOptions::new();
+        for (id, key) in [
+            ("baud_rate", "baud"),
+            ("serial_profile", "profile"),
+            ("parity", "parity"),
+            ("rts", "rts"),
+            ("dtr", "dtr"),
+            ("reconnect_interval", "reconnect"),
+        ] {
+            if let Some(value) = matches.get_one::<String>(id) {
+                options.set_url_param(key, value)?;
+            }
+        }
+        Ok(ConnectionSpec::Serial {
+            path: path.clone(),
+            options,
+        })
+    } else if let Some(host) = matches.get_one::<String>("host") {
+        Ok(ConnectionSpec::Tcp {
+            host: host.clone(),
+            port: matches.get_one::<u16>("port").copied().unwrap_or(7053),
+            password: matches.get_one::<String>("password").cloned(),
+            via_tag: matches.get_one::<String>("via_tag").cloned(),
+            channel: matches.get_one::<u8>("channel").copied(),
+        })
+    } else {
+        Err("No URL, host or serial port provided".into())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use clap::Command;
+
+    use super::*;
+
+    fn parse(args: &[&str]) -> std::result::Result<ArgMatches, clap::Error> {
+        Command::new("tool")
+            .args(connection_args())
+            .try_get_matches_from(std::iter::once("tool").chain(args.iter().copied()))
+    }
+
+    #[test]
+    fn test_connection_spec_from_matches() {
+        let matches = parse(&[
+            "--host",
+            "vbus.net",
+            "--port",
+            "80",
+            "--password",
+            "secret",
+            "--viaTag",
+            "d01234567890.vbus.io",
+        ])
+        .unwrap();
+        assert_eq!(
+            Ok(ConnectionSpec::Tcp {
+                host: "vbus.net".into(),
+                port: 80,
+                password: Some("secret".into()),
+                via_tag: Some("d01234567890.vbus.io".into()),
+                channel: None,
+            }),
+            connection_spec_from_matches(&matches)
+        );
+
+        let matches = parse(&["--serial", "/dev/ttyUSB0"]).unwrap();
+        assert_eq!(
+            Ok(ConnectionSpec::Serial {
+                path: "/dev/ttyUSB0".into(),
+                options: SerialOptions::new(),
+            }),
+            connection_spec_from_matches(&matches)
+        );
+
+        let matches = parse(&["--serial", "auto", "--baud-rate", "auto"]).unwrap();
+        assert_eq!(
+            "vbus+serial://auto?baud=auto",
+            connection_spec_from_matches(&matches).unwrap().to_string()
+        );
+
+        let matches = parse(&[
+            "--serial",
+            "auto",
+            "--serial-profile",
+            "uart",
+            "--parity",
+            "odd",
+            "--rts",
+            "off",
+            "--reconnect-interval",
+            "2000",
+        ])
+        .unwrap();
+        assert_eq!(
+            "vbus+serial://auto?profile=uart&parity=odd&rts=off&reconnect=2000",
+            connection_spec_from_matches(&matches).unwrap().to_string()
+        );
+
+        let matches = parse(&["--url", "vbus+tcp://192.168.5.217:7054"]).unwrap();
+        assert_eq!(
+            "vbus+tcp://192.168.5.217:7054",
+            connection_spec_from_matches(&matches).unwrap().to_string()
+        );
+
+        let matches = parse(&[]).unwrap();
+        assert_eq!(
+            Err("No URL, host or serial port provided".into()),
+            connection_spec_from_matches(&matches)
+        );
+
+        assert!(parse(&["--host", "a", "--serial", "/dev/ttyUSB0"]).is_err());
+        assert!(parse(&["--channel", "1"]).is_err());
+        assert!(parse(&["--baud-rate", "auto"]).is_err());
+        assert!(parse(&["--host", "a", "--port", "x"]).is_err());
+    }
+}
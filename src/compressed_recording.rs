@@ -0,0 +1,497 @@
+use std::io::{Cursor, Read, Seek, SeekFrom, Write};
+
+use resol_vbus::{
+    chrono::{DateTime, TimeZone, Utc},
+    DataSet, RecordingReader, RecordingWriter,
+};
+
+use crate::error::Result;
+
+const CHUNK_MAGIC: &[u8; 3] = b"VBZ";
+
+const CHUNK_HEADER_LENGTH: usize = 28;
+
+/// The compression method of a `CompressedRecordingWriter`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Compression {
+    /// gzip (deflate) compression, requires the `gzip` feature.
+    #[cfg(feature = "gzip")]
+    Gzip,
+
+    /// zstd compression, requires the `zstd` feature.
+    #[cfg(feature = "zstd")]
+    Zstd,
+}
+
+impl Compression {
+    fn id(self) -> u8 {
+        match self {
+            #[cfg(feature = "gzip")]
+            Compression::Gzip => 1,
+            #[cfg(feature = "zstd")]
+            Compression::Zstd => 2,
+        }
+    }
+
+    fn from_id(id: u8) -> Result<Compression> {
+        match id {
+            #[cfg(feature = "gzip")]
+            1 => Ok(Compression::Gzip),
+            #[cfg(feature = "zstd")]
+            2 => Ok(Compression::Zstd),
+            _ => Err(format!("Unsupported recording compression 0x{:02X}", id).into()),
+        }
+    }
+
+    fn compress(self, bytes: &[u8]) -> Result<Vec<u8>> {
+        match self {
+            #[cfg(feature = "gzip")]
+            Compression::Gzip => {
+                let mut encoder =
+                    flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+                encoder.write_all(bytes)?;
+                Ok(encoder.finish()?)
+            }
+            #[cfg(feature = "zstd")]
+            Compression::Zstd => Ok(zstd::stream::encode_all(bytes, 0)?),
+        }
+    }
+
+    fn decompress(self, bytes: &[u8], length: usize) -> Result<Vec<u8>> {
+        let mut output = Vec::with_capacity(length);
+        match self {
+            #[cfg(feature = "gzip")]
+            Compression::Gzip => {
+                flate2::read::GzDecoder::new(bytes).read_to_end(&mut output)?;
+            }
+            #[cfg(feature = "zstd")]
+            Compression::Zstd => {
+                zstd::stream::Decoder::new(bytes)?.read_to_end(&mut output)?;
+            }
+        }
+        Ok(output)
+    }
+}
+
+/// Describes a single chunk of a compressed recording.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ChunkInfo {
+    /// The offset of the chunk's header in the recording.
+    pub offset: u64,
+
+    /// The compression method of the chunk.
+    pub compression: Compression,
+
+    /// The timestamp of the first `DataSet` in the chunk.
+    pub first_timestamp: DateTime<Utc>,
+
+    /// The timestamp of the last `DataSet` in the chunk.
+    pub last_timestamp: DateTime<Utc>,
+
+    /// The length of the compressed chunk data.
+    pub compressed_length: u32,
+
+    /// The length of the uncompressed chunk data.
+    pub uncompressed_length: u32,
+}
+
+impl ChunkInfo {
+    fn encode_header(&self) -> [u8; CHUNK_HEADER_LENGTH] {
+        let mut header = [0; CHUNK_HEADER_LENGTH];
+        header[0..3].copy_from_slice(CHUNK_MAGIC);
+        header[3] = self.compression.id();
+        header[4..8].copy_from_slice(&self.compressed_length.to_le_bytes());
+        header[8..12].copy_from_slice(&self.uncompressed_length.to_le_bytes());
+        header[12..20].copy_from_slice(&self.first_timestamp.timestamp_millis().to_le_bytes());
+        header[20..28].copy_from_slice(&self.last_timestamp.timestamp_millis().to_le_bytes());
+        header
+    }
+
+    fn decode_header(offset: u64, header: &[u8; CHUNK_HEADER_LENGTH]) -> Result<ChunkInfo> {
+        if &header[0..3] != CHUNK_MAGIC {
+            return Err(format!("Invalid compressed recording chunk at offset {}", offset).into());
+        }
+
+        let u32_at = |idx: usize| u32::from_le_bytes(header[idx..idx + 4].try_into().unwrap());
+        let timestamp_at = |idx: usize| {
+            let millis = i64::from_le_bytes(header[idx..idx + 8].try_into().unwrap());
+            Utc.timestamp_millis_opt(millis).single().ok_or_else(|| {
+                format!(
+                    "Invalid timestamp in compressed recording chunk at offset {}",
+                    offset
+                )
+            })
+        };
+
+        Ok(ChunkInfo {
+            offset,
+            compression: Compression::from_id(header[3])?,
+            first_timestamp: timestamp_at(12)?,
+            last_timestamp: timestamp_at(20)?,
+            compressed_length: u32_at(4),
+            uncompressed_length: u32_at(8),
+        })
+    }
+}
+
+/// Writes `DataSet`s into a compressed recording.
+///
+/// The `DataSet`s are encoded like a `RecordingWriter` does and collected
+/// into chunks. Every chunk is compressed independently and prefixed with a
+/// small header containing its length and the timestamps of its first and
+/// last `DataSet`. This allows the `CompressedRecordingReader` to seek to a
+/// timestamp by only decompressing the chunk containing it.
+///
+/// A chunk is written once its uncompressed size reaches the chunk size,
+/// when `flush` is called or when the writer is finished.
+///
+/// # Examples
+///
+/// ```no_run
+/// # fn main() -> async_resol_vbus::Result<()> { async_std::task::block_on(async {
+/// #
+/// use std::fs::File;
+///
+/// use async_std::net::TcpStream;
+///
+/// use async_resol_vbus::{Compression, CompressedRecordingWriter, DataSet, LiveDataStream};
+///
+/// let file = File::create("recording.vbus.gz")?;
+/// let mut writer = CompressedRecordingWriter::new(file, Compression::Gzip);
+///
+/// let stream = TcpStream::connect("192.168.5.217:7053").await?;
+/// // ... perform handshake ...
+/// let mut lds = LiveDataStream::from_tcp_stream(stream, 0, 0x0020);
+///
+/// while let Some(data) = lds.receive_any_data(60000).await? {
+///     let mut data_set = DataSet::new();
+///     data_set.timestamp = data.as_ref().timestamp;
+///     data_set.add_data(data);
+///     writer.write_data_set(&data_set)?;
+/// }
+///
+/// writer.finish()?;
+/// #
+/// # Ok(()) }) }
+/// ```
+#[derive(Debug)]
+pub struct CompressedRecordingWriter<W: Write> {
+    writer: W,
+    compression: Compression,
+    chunk_size: usize,
+    encoder: RecordingWriter<Vec<u8>>,
+    timestamps: Option<(DateTime<Utc>, DateTime<Utc>)>,
+}
+
+impl<W: Write> CompressedRecordingWriter<W> {
+    /// Create a new `CompressedRecordingWriter`.
+    ///
+    /// The chunk size defaults to 256 KiB of uncompressed data.
+    pub fn new(writer: W, compression: Compression) -> CompressedRecordingWriter<W> {
+        CompressedRecordingWriter {
+            writer,
+            compression,
+            chunk_size: 256 * 1024,
+            encoder: RecordingWriter::new(Vec::new()),
+            timestamps: None,
+        }
+    }
+
+    /// Set the uncompressed size after which a chunk is written.
+    ///
+    /// Smaller chunks allow more precise seeking at the cost of a lower
+    /// compression ratio.
+    pub fn set_chunk_size(&mut self, chunk_size: usize) {
+        self.chunk_size = chunk_size;
+    }
+
+    /// Get a reference to the underlying writer.
+    pub fn get_ref(&self) -> &W {
+        &self.writer
+    }
+
+    /// Write a `DataSet`.
+    pub fn write_data_set(&mut self, data_set: &DataSet) -> Result<()> {
+        self.encoder.write_data_set(data_set)?;
+
+        self.timestamps = match self.timestamps {
+            Some((first, _)) => Some((first, data_set.timestamp)),
+            None => Some((data_set.timestamp, data_set.timestamp)),
+        };
+
+        if self.encoder.get_ref().len() >= self.chunk_size {
+            self.write_chunk()?;
+        }
+
+        Ok(())
+    }
+
+    fn write_chunk(&mut self) -> Result<()> {
+        let (first_timestamp, last_timestamp) = match self.timestamps.take() {
+            Some(timestamps) => timestamps,
+            None => return Ok(()),
+        };
+
+        let bytes = std::mem::take(self.encoder.get_mut());
+        let compressed = self.compression.compress(&bytes)?;
+
+        let chunk = ChunkInfo {
+            offset: 0,
+            compression: self.compression,
+            first_timestamp,
+            last_timestamp,
+            compressed_length: compressed
+                .len()
+                .try_into()
+                .map_err(|_| "Chunk is too large")?,
+            uncompressed_length: bytes.len().try_into().map_err(|_| "Chunk is too large")?,
+        };
+
+        self.writer.write_all(&chunk.encode_header())?;
+        self.writer.write_all(&compressed)?;
+        Ok(())
+    }
+
+    /// Write the pending `DataSet`s as a chunk and flush the underlying
+    /// writer.
+    pub fn flush(&mut self) -> Result<()> {
+        self.write_chunk()?;
+        self.writer.flush()?;
+        Ok(())
+    }
+
+    /// Write the pending `DataSet`s and return the underlying writer.
+    pub fn finish(mut self) -> Result<W> {
+        self.flush()?;
+        Ok(self.writer)
+    }
+}
+
+/// Reads `DataSet`s from a recording written by a
+/// `CompressedRecordingWriter`.
+///
+/// # Examples
+///
+/// ```no_run
+/// # fn main() -> async_resol_vbus::Result<()> {
+/// use std::fs::File;
+///
+/// use async_resol_vbus::{chrono::{TimeZone, Utc}, CompressedRecordingReader};
+///
+/// let mut reader = CompressedRecordingReader::new(File::open("recording.vbus.gz")?);
+/// reader.seek(Utc.with_ymd_and_hms(2020, 6, 1, 12, 0, 0).unwrap())?;
+///
+/// while let Some(data_set) = reader.read_data_set()? {
+///     println!("{}: {} data", data_set.timestamp, data_set.iter().count());
+/// }
+/// # Ok(()) }
+/// ```
+#[derive(Debug)]
+pub struct CompressedRecordingReader<R: Read + Seek> {
+    reader: R,
+    chunk_reader: Option<RecordingReader<Cursor<Vec<u8>>>>,
+    min_timestamp: Option<DateTime<Utc>>,
+}
+
+impl<R: Read + Seek> CompressedRecordingReader<R> {
+    /// Create a new `CompressedRecordingReader`.
+    pub fn new(reader: R) -> CompressedRecordingReader<R> {
+        CompressedRecordingReader {
+            reader,
+            chunk_reader: None,
+            min_timestamp: None,
+        }
+    }
+
+    /// Get a reference to the underlying reader.
+    pub fn get_ref(&self) -> &R {
+        &self.reader
+    }
+
+    fn read_chunk_header(&mut self) -> Result<Option<ChunkInfo>> {
+        let offset = self.reader.stream_position()?;
+
+        let mut header = [0; CHUNK_HEADER_LENGTH];
+        let mut length = 0;
+        while length < header.len() {
+            match self.reader.read(&mut header[length..])? {
+                0 if length == 0 => return Ok(None),
+                0 => {
+                    return Err(format!(
+                        "Truncated compressed recording chunk at offset {}",
+                        offset
+                    )
+                    .into())
+                }
+                n => length += n,
+            }
+        }
+
+        Ok(Some(ChunkInfo::decode_header(offset, &header)?))
+    }
+
+    /// Read the headers of all chunks without decompressing them.
+    ///
+    /// Afterwards the reader continues with the first `DataSet` of the
+    /// recording.
+    pub fn read_chunk_index(&mut self) -> Result<Vec<ChunkInfo>> {
+        self.reader.seek(SeekFrom::Start(0))?;
+
+        let mut chunks = Vec::new();
+        while let Some(chunk) = self.read_chunk_header()? {
+            self.reader
+                .seek(SeekFrom::Current(i64::from(chunk.compressed_length)))?;
+            chunks.push(chunk);
+        }
+
+        self.reader.seek(SeekFrom::Start(0))?;
+        self.chunk_reader = None;
+        self.min_timestamp = None;
+        Ok(chunks)
+    }
+
+    /// Seek to the first `DataSet` with a timestamp at or after `timestamp`.
+    pub fn seek(&mut self, timestamp: DateTime<Utc>) -> Result<()> {
+        let chunks = self.read_chunk_index()?;
+
+        let offset = match chunks
+            .iter()
+            .find(|chunk| chunk.last_timestamp >= timestamp)
+        {
+            Some(chunk) => chunk.offset,
+            None => self.reader.seek(SeekFrom::End(0))?,
+        };
+
+        self.reader.seek(SeekFrom::Start(offset))?;
+        self.min_timestamp = Some(timestamp);
+        Ok(())
+    }
+
+    fn read_chunk(&mut self) -> Result<bool> {
+        let chunk = match self.read_chunk_header()? {
+            Some(chunk) => chunk,
+            None => return Ok(false),
+        };
+
+        let mut compressed = vec![0; chunk.compressed_length as usize];
+        self.reader.read_exact(&mut compressed)?;
+
+        let bytes = chunk
+            .compression
+            .decompress(&compressed, chunk.uncompressed_length as usize)?;
+
+        let mut chunk_reader = RecordingReader::new(Cursor::new(bytes));
+        chunk_reader.set_min_max_timestamps(self.min_timestamp, None);
+        self.chunk_reader = Some(chunk_reader);
+        Ok(true)
+    }
+
+    /// Read the next `DataSet`.
+    pub fn read_data_set(&mut self) -> Result<Option<DataSet>> {
+        loop {
+            if let Some(chunk_reader) = self.chunk_reader.as_mut() {
+                if let Some(data_set) = chunk_reader.read_data_set()? {
+                    return Ok(Some(data_set));
+                }
+            }
+
+            if !self.read_chunk()? {
+                self.chunk_reader = None;
+                return Ok(None);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use resol_vbus::{utils::utc_timestamp, Data, Header, Packet};
+
+    use super::*;
+
+    fn data_set(offset: i64) -> DataSet {
+        let timestamp = utc_timestamp(1485688933 + offset);
+
+        let mut data_set = DataSet::new();
+        data_set.timestamp = timestamp;
+        data_set.add_data(Data::Packet(Packet {
+            header: Header {
+                timestamp,
+                channel: 0,
+                destination_address: 0x0010,
+                source_address: 0x7E11,
+                protocol_version: 0x10,
+            },
+            command: 0x0100,
+            frame_count: 16,
+            frame_data: [0; 508],
+        }));
+        data_set
+    }
+
+    fn test_compression(compression: Compression) -> Result<()> {
+        let mut writer = CompressedRecordingWriter::new(Vec::new(), compression);
+        writer.set_chunk_size(1000);
+        for offset in 0..100 {
+            writer.write_data_set(&data_set(offset * 10))?;
+        }
+        let bytes = writer.finish()?;
+
+        let mut reader = CompressedRecordingReader::new(Cursor::new(bytes));
+
+        let chunks = reader.read_chunk_index()?;
+        assert!(chunks.len() > 2);
+        assert_eq!(0, chunks[0].offset);
+        assert_eq!(compression, chunks[0].compression);
+        assert_eq!(utc_timestamp(1485688933), chunks[0].first_timestamp);
+        assert!(chunks[0].compressed_length < chunks[0].uncompressed_length);
+        assert_eq!(
+            utc_timestamp(1485688933 + 990),
+            chunks.last().unwrap().last_timestamp
+        );
+
+        let mut timestamps = Vec::new();
+        while let Some(data_set) = reader.read_data_set()? {
+            assert_eq!(1, data_set.iter().count());
+            timestamps.push(data_set.timestamp.timestamp() - 1485688933);
+        }
+        assert_eq!((0..100).map(|i| i * 10).collect::<Vec<_>>(), timestamps);
+
+        reader.seek(utc_timestamp(1485688933 + 495))?;
+        let data_set = reader.read_data_set()?.unwrap();
+        assert_eq!(utc_timestamp(1485688933 + 500), data_set.timestamp);
+
+        reader.seek(utc_timestamp(1485688933 + 1000))?;
+        assert_eq!(None, reader.read_data_set()?.map(|ds| ds.timestamp));
+
+        Ok(())
+    }
+
+    #[cfg(feature = "gzip")]
+    #[test]
+    fn test_gzip() -> Result<()> {
+        test_compression(Compression::Gzip)
+    }
+
+    #[cfg(feature = "zstd")]
+    #[test]
+    fn test_zstd() -> Result<()> {
+        test_compression(Compression::Zstd)
+    }
+
+    #[cfg(feature = "gzip")]
+    #[test]
+    fn test_invalid_chunk() {
+        let mut reader = CompressedRecordingReader::new(Cursor::new(b"VBZ\x01abc".to_vec()));
+        assert_eq!(
+            Err("Truncated compressed recording chunk at offset 0".into()),
+            reader.read_data_set().map(|_| ())
+        );
+
+        let mut reader = CompressedRecordingReader::new(Cursor::new(vec![0; 40]));
+        assert_eq!(
+            Err("Invalid compressed recording chunk at offset 0".into()),
+            reader.read_data_set().map(|_| ())
+        );
+    }
+}
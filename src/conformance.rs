@@ -0,0 +1,372 @@
+use std::time::{Duration, Instant};
+
+use async_std::{net::TcpStream, prelude::*};
+
+use crate::error::Result;
+
+/// A behavior checked by the `ConformanceHarness`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConformanceCheck {
+    /// A new client is greeted with `+HELLO`.
+    Greeting,
+
+    /// A wrong password is rejected and the `DATA` command is refused
+    /// afterwards.
+    WrongPassword,
+
+    /// An unknown command is rejected without closing the connection.
+    UnknownCommand,
+
+    /// The `QUIT` command is acknowledged and the connection is closed.
+    Quit,
+
+    /// A command arriving in multiple pieces with pauses in between is
+    /// processed like a command arriving at once.
+    SlowCommand,
+
+    /// The handshake using the `PASS` and `DATA` commands succeeds.
+    Handshake,
+}
+
+impl ConformanceCheck {
+    /// All checks in the order they are performed by
+    /// `ConformanceHarness::run`.
+    pub const ALL: [ConformanceCheck; 6] = [
+        ConformanceCheck::Greeting,
+        ConformanceCheck::WrongPassword,
+        ConformanceCheck::UnknownCommand,
+        ConformanceCheck::Quit,
+        ConformanceCheck::SlowCommand,
+        ConformanceCheck::Handshake,
+    ];
+}
+
+/// The result of a `ConformanceCheck`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ConformanceResult {
+    /// The performed check.
+    pub check: ConformanceCheck,
+
+    /// The deviation from the expected behavior, or `None` if the check
+    /// passed.
+    pub failure: Option<String>,
+
+    /// The longest time the endpoint took to reply during the check.
+    pub max_reply_latency: Duration,
+}
+
+impl ConformanceResult {
+    /// Check whether the endpoint behaved as expected.
+    pub fn passed(&self) -> bool {
+        self.failure.is_none()
+    }
+}
+
+/// Checks whether a VBus-over-TCP endpoint conforms to the handshake
+/// behavior of RESOL devices.
+///
+/// Every `ConformanceCheck` is performed using a separate connection, so
+/// that endpoints only allowing a single connection (like the DL2) can be
+/// checked as well.
+///
+/// # Examples
+///
+/// ```no_run
+/// # fn main() -> async_resol_vbus::Result<()> { async_std::task::block_on(async {
+/// #
+/// use async_resol_vbus::ConformanceHarness;
+///
+/// let mut harness = ConformanceHarness::new("192.168.5.217:7053");
+/// harness.set_password("vbus".into());
+///
+/// for result in harness.run().await {
+///     match result.failure {
+///         None => println!("{:?}: passed", result.check),
+///         Some(failure) => println!("{:?}: {}", result.check, failure),
+///     }
+/// }
+/// #
+/// # Ok(()) }) }
+/// ```
+#[derive(Debug)]
+pub struct ConformanceHarness {
+    address: String,
+    password: String,
+    reply_timeout: Duration,
+    slow_command_delay: Duration,
+}
+
+impl ConformanceHarness {
+    /// Create a new `ConformanceHarness` checking the endpoint at
+    /// `address`.
+    pub fn new(address: &str) -> ConformanceHarness {
+        ConformanceHarness {
+            address: address.to_string(),
+            password: "vbus".into(),
+            reply_timeout: Duration::from_secs(5),
+            slow_command_delay: Duration::from_millis(500),
+        }
+    }
+
+    /// Set the password accepted by the endpoint.
+    ///
+    /// Defaults to `"vbus"`.
+    pub fn set_password(&mut self, password: String) {
+        self.password = password;
+    }
+
+    /// Set the time the endpoint has to reply to a command.
+    ///
+    /// Defaults to 5 seconds.
+    pub fn set_reply_timeout(&mut self, reply_timeout: Duration) {
+        self.reply_timeout = reply_timeout;
+    }
+
+    /// Set the pause between the pieces of the command sent by
+    /// `ConformanceCheck::SlowCommand`.
+    ///
+    /// Defaults to 500 milliseconds.
+    pub fn set_slow_command_delay(&mut self, slow_command_delay: Duration) {
+        self.slow_command_delay = slow_command_delay;
+    }
+
+    /// Perform all checks.
+    pub async fn run(&self) -> Vec<ConformanceResult> {
+        let mut results = Vec::with_capacity(ConformanceCheck::ALL.len());
+        for check in ConformanceCheck::ALL {
+            results.push(self.run_check(check).await);
+        }
+        results
+    }
+
+    /// Perform a single check.
+    pub async fn run_check(&self, check: ConformanceCheck) -> ConformanceResult {
+        let mut max_reply_latency = Duration::ZERO;
+        let result = self.perform_check(check, &mut max_reply_latency).await;
+        ConformanceResult {
+            check,
+            failure: result.err().map(|err| err.message().to_string()),
+            max_reply_latency,
+        }
+    }
+
+    async fn perform_check(
+        &self,
+        check: ConformanceCheck,
+        max_reply_latency: &mut Duration,
+    ) -> Result<()> {
+        let mut session = Session::connect(&self.address, self.reply_timeout).await?;
+        let result = self.perform_session_check(check, &mut session).await;
+        *max_reply_latency = session.max_reply_latency;
+        result
+    }
+
+    async fn perform_session_check(
+        &self,
+        check: ConformanceCheck,
+        session: &mut Session,
+    ) -> Result<()> {
+        match check {
+            // the greeting is already verified by `Session::connect`
+            ConformanceCheck::Greeting => Ok(()),
+            ConformanceCheck::WrongPassword => {
+                let wrong_password = format!("{}-wrong", self.password);
+                session.send(&format!("PASS {}", wrong_password)).await?;
+                session.expect_negative_reply("PASS").await?;
+                session.send("DATA").await?;
+                session.expect_negative_reply("DATA").await
+            }
+            ConformanceCheck::UnknownCommand => {
+                session.send("UNKNOWN").await?;
+                session.expect_negative_reply("UNKNOWN").await?;
+                session.send(&format!("PASS {}", self.password)).await?;
+                session.expect_positive_reply("PASS").await
+            }
+            ConformanceCheck::Quit => {
+                session.send("QUIT").await?;
+                session.expect_positive_reply("QUIT").await?;
+                session.expect_closed().await
+            }
+            ConformanceCheck::SlowCommand => {
+                let command = format!("PASS {}\r\n", self.password);
+                let (first, second) = command.split_at(3);
+                session.write(first).await?;
+                async_std::task::sleep(self.slow_command_delay).await;
+                session.write(second).await?;
+                session.expect_positive_reply("PASS").await
+            }
+            ConformanceCheck::Handshake => {
+                session.send(&format!("PASS {}", self.password)).await?;
+                session.expect_positive_reply("PASS").await?;
+                session.send("DATA").await?;
+                session.expect_positive_reply("DATA").await
+            }
+        }
+    }
+}
+
+struct Session {
+    stream: TcpStream,
+    buf: Vec<u8>,
+    reply_timeout: Duration,
+    sent_at: Instant,
+    max_reply_latency: Duration,
+}
+
+impl Session {
+    async fn connect(address: &str, reply_timeout: Duration) -> Result<Session> {
+        let stream = TcpStream::connect(address).await?;
+        let mut session = Session {
+            stream,
+            buf: Vec::new(),
+            reply_timeout,
+            sent_at: Instant::now(),
+            max_reply_latency: Duration::ZERO,
+        };
+
+        let greeting = session.reply().await?;
+        if !greeting.starts_with("+HELLO") {
+            return Err(format!("Expected +HELLO greeting, got {:?}", greeting).into());
+        }
+
+        Ok(session)
+    }
+
+    async fn write(&mut self, bytes: &str) -> Result<()> {
+        self.stream.write_all(bytes.as_bytes()).await?;
+        self.sent_at = Instant::now();
+        Ok(())
+    }
+
+    async fn send(&mut self, command: &str) -> Result<()> {
+        self.write(&format!("{}\r\n", command)).await
+    }
+
+    /// Read bytes, returning `None` on EOF.
+    async fn read(&mut self) -> Result<Option<usize>> {
+        let remaining = self.reply_timeout.saturating_sub(self.sent_at.elapsed());
+
+        let mut read_buf = [0u8; 256];
+        let len = match async_std::io::timeout(remaining, self.stream.read(&mut read_buf)).await {
+            Ok(len) => len,
+            Err(err) if err.kind() == std::io::ErrorKind::TimedOut => {
+                return Err(format!("No reply within {:?}", self.reply_timeout).into())
+            }
+            Err(err) => return Err(err.into()),
+        };
+        if len == 0 {
+            return Ok(None);
+        }
+
+        self.buf.extend_from_slice(&read_buf[0..len]);
+        Ok(Some(len))
+    }
+
+    async fn reply(&mut self) -> Result<String> {
+        loop {
+            if let Some(idx) = self.buf.iter().position(|b| *b == b'\n') {
+                let line = String::from_utf8_lossy(&self.buf[0..idx])
+                    .trim_end()
+                    .to_string();
+                self.buf.drain(0..idx + 1);
+
+                self.max_reply_latency = self.max_reply_latency.max(self.sent_at.elapsed());
+
+                return Ok(line);
+            }
+
+            if self.read().await?.is_none() {
+                return Err("Connection closed unexpectedly".into());
+            }
+        }
+    }
+
+    async fn expect_positive_reply(&mut self, command: &str) -> Result<()> {
+        let reply = self.reply().await?;
+        if reply.starts_with('+') {
+            Ok(())
+        } else {
+            Err(format!("Expected positive reply to {}, got {:?}", command, reply).into())
+        }
+    }
+
+    async fn expect_negative_reply(&mut self, command: &str) -> Result<()> {
+        let reply = self.reply().await?;
+        if reply.starts_with('-') {
+            Ok(())
+        } else {
+            Err(format!("Expected negative reply to {}, got {:?}", command, reply).into())
+        }
+    }
+
+    async fn expect_closed(&mut self) -> Result<()> {
+        self.sent_at = Instant::now();
+        match self.read().await {
+            Ok(None) => Ok(()),
+            Ok(Some(_)) => Err("Received data after QUIT".into()),
+            Err(_) => Err("Connection not closed after QUIT".into()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use async_std::net::{TcpListener, UdpSocket};
+
+    use crate::dl2_simulator::Dl2Simulator;
+
+    use super::*;
+
+    async fn run_harness(password: Option<String>) -> Result<Vec<ConformanceResult>> {
+        let discovery_socket = UdpSocket::bind("127.0.0.1:0").await?;
+        let web_listener = TcpListener::bind("127.0.0.1:0").await?;
+        let vbus_listener = TcpListener::bind("127.0.0.1:0").await?;
+        let vbus_addr = vbus_listener.local_addr()?;
+
+        let mut simulator = Dl2Simulator::new();
+        simulator.set_password(password);
+        let simulator_future =
+            async_std::task::spawn(simulator.run(discovery_socket, web_listener, vbus_listener));
+
+        let mut harness = ConformanceHarness::new(&vbus_addr.to_string());
+        harness.set_password("secret".into());
+        harness.set_reply_timeout(Duration::from_secs(1));
+        harness.set_slow_command_delay(Duration::from_millis(50));
+        let results = harness.run().await;
+
+        simulator_future.cancel().await;
+
+        Ok(results)
+    }
+
+    #[test]
+    fn test_conformance_harness() -> Result<()> {
+        async_std::task::block_on(async {
+            let results = run_harness(Some("secret".into())).await?;
+            assert_eq!(ConformanceCheck::ALL.len(), results.len());
+            for result in results.iter() {
+                assert_eq!(None, result.failure, "{:?}", result.check);
+            }
+            let result = &results[4];
+            assert_eq!(ConformanceCheck::SlowCommand, result.check);
+            assert!(result.max_reply_latency < Duration::from_secs(1));
+
+            // accepting any password fails the `WrongPassword` check
+            let results = run_harness(None).await?;
+            let failed = results
+                .iter()
+                .filter(|result| !result.passed())
+                .map(|result| (result.check, result.failure.clone().unwrap()))
+                .collect::<Vec<_>>();
+            assert_eq!(
+                vec![(
+                    ConformanceCheck::WrongPassword,
+                    "Expected negative reply to PASS, got \"+OK\"".to_string()
+                )],
+                failed
+            );
+
+            Ok(())
+        })
+    }
+}
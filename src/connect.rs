@@ -0,0 +1,1415 @@
+use std::{
+    future::Future,
+    marker::Unpin,
+    net::Shutdown,
+    pin::Pin,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use async_std::{
+    channel::Sender,
+    io::{Read, Write},
+    net::{TcpStream, ToSocketAddrs},
+    prelude::*,
+};
+
+use futures_lite::io::{ReadHalf, WriteHalf};
+
+use resol_vbus::LiveDataBuffer;
+
+use crate::{
+    device_information::DeviceInformation,
+    error::{Error, Result},
+    live_data_stream::{LiveDataStream, TcpLiveDataStream},
+    metrics_hook::MetricsHook,
+    socket_options::SocketOptions,
+    tcp_client_handshake::TcpClientHandshake,
+    vbus_event::{emit_event, ErrorKind, VBusEvent},
+    write_guard::WriteGuard,
+};
+
+/// The timeout for fetching the `DeviceInformation`, see
+/// `ConnectOptions::set_device_information_port`.
+const DEVICE_INFORMATION_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// A single step of the client-side VBus-over-TCP handshake.
+///
+/// The `Debug` output does not include the password of a `Pass` step.
+#[derive(Clone, PartialEq)]
+pub enum HandshakeStep {
+    /// Send the `CONNECT` command with the given via tag.
+    Connect(String),
+
+    /// Send the `PASS` command with the given password.
+    Pass(String),
+
+    /// Send the `CHANNEL` command with the given channel.
+    Channel(u8),
+}
+
+impl std::fmt::Debug for HandshakeStep {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            HandshakeStep::Connect(via_tag) => f.debug_tuple("Connect").field(via_tag).finish(),
+            HandshakeStep::Pass(_) => f.debug_tuple("Pass").field(&"..").finish(),
+            HandshakeStep::Channel(channel) => f.debug_tuple("Channel").field(channel).finish(),
+        }
+    }
+}
+
+/// An async callback providing another password after the previous one was
+/// rejected, see `ConnectOptions::set_credential_provider`.
+///
+/// It is called with the number of rejected attempts so far and returns
+/// `None` to give up.
+pub type CredentialProvider =
+    Arc<dyn Fn(usize) -> Pin<Box<dyn Future<Output = Option<String>> + Send>> + Send + Sync>;
+
+/// Options used by `connect_live_data_stream`.
+#[derive(Clone)]
+pub struct ConnectOptions {
+    via_tag: Option<String>,
+    password: Option<String>,
+    channel: Option<u8>,
+    handshake_steps: Option<Vec<HandshakeStep>>,
+    self_address: u16,
+    device_information: Option<DeviceInformation>,
+    device_information_port: Option<u16>,
+    credential_provider: Option<CredentialProvider>,
+    max_password_retries: usize,
+    connect_timeout: Option<Duration>,
+    first_frame_timeout: Option<Duration>,
+    metrics_hook: Option<Arc<dyn MetricsHook>>,
+    event_sender: Option<Sender<VBusEvent>>,
+    write_timeout: Option<Duration>,
+    write_guard: Option<WriteGuard>,
+    socket_options: SocketOptions,
+}
+
+impl ConnectOptions {
+    /// Create a new `ConnectOptions` instance using default values.
+    ///
+    /// By default the password `"vbus"` is sent, no `CONNECT` or `CHANNEL`
+    /// commands are used and the `LiveDataStream` uses the VBus address
+    /// `0x0020`.
+    pub fn new() -> ConnectOptions {
+        ConnectOptions {
+            via_tag: None,
+            password: Some("vbus".to_string()),
+            channel: None,
+            handshake_steps: None,
+            self_address: 0x0020,
+            device_information: None,
+            device_information_port: None,
+            credential_provider: None,
+            max_password_retries: 3,
+            connect_timeout: None,
+            first_frame_timeout: None,
+            metrics_hook: None,
+            event_sender: None,
+            write_timeout: None,
+            write_guard: None,
+            socket_options: SocketOptions::new(),
+        }
+    }
+
+    /// Set the via tag to send using the `CONNECT` command.
+    ///
+    /// This is required when connecting to a device through VBus.net.
+    pub fn set_via_tag(&mut self, via_tag: Option<String>) {
+        self.via_tag = via_tag;
+    }
+
+    /// Get the via tag used for VBus.net connections.
+    pub fn via_tag(&self) -> Option<&str> {
+        self.via_tag.as_deref()
+    }
+
+    /// Set the password to send using the `PASS` command.
+    pub fn set_password(&mut self, password: Option<String>) {
+        self.password = password;
+    }
+
+    /// Set the channel to select using the `CHANNEL` command.
+    ///
+    /// This is only supported by devices with multiple VBus channels (e.g.
+    /// the DL3). The channel is also used for the `LiveDataStream`.
+    pub fn set_channel(&mut self, channel: Option<u8>) {
+        self.channel = channel;
+    }
+
+    /// Set an explicit, ordered list of handshake steps.
+    ///
+    /// This is required for cascaded connections, e.g. reaching a DL3
+    /// channel through VBus.net, where multiple `CONNECT` and `CHANNEL`
+    /// commands have to be sent in a specific order. Every step must be
+    /// acknowledged by the service before the next one is sent.
+    ///
+    /// If set, the via tag, password and channel options are ignored for
+    /// the handshake. The last `Channel` step is used as the channel of the
+    /// `LiveDataStream`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use async_resol_vbus::{ConnectOptions, HandshakeStep};
+    ///
+    /// let mut options = ConnectOptions::new();
+    /// options.set_handshake_steps(Some(vec![
+    ///     HandshakeStep::Connect("d1234567890".into()),
+    ///     HandshakeStep::Pass("vbus".into()),
+    ///     HandshakeStep::Channel(2),
+    /// ]));
+    ///
+    /// assert_eq!(HandshakeStep::Channel(2), options.handshake_steps()[2]);
+    /// ```
+    pub fn set_handshake_steps(&mut self, steps: Option<Vec<HandshakeStep>>) {
+        self.handshake_steps = steps;
+    }
+
+    /// Get the ordered list of handshake steps performed by
+    /// `connect_live_data_stream`.
+    pub fn handshake_steps(&self) -> Vec<HandshakeStep> {
+        if let Some(ref steps) = self.handshake_steps {
+            return steps.clone();
+        }
+
+        let mut steps = Vec::new();
+        if let Some(ref via_tag) = self.via_tag {
+            steps.push(HandshakeStep::Connect(via_tag.clone()));
+        }
+        if let Some(ref password) = self.password {
+            steps.push(HandshakeStep::Pass(password.clone()));
+        }
+        if let Some(channel) = self.channel {
+            steps.push(HandshakeStep::Channel(channel));
+        }
+        steps
+    }
+
+    /// Set the VBus address the `LiveDataStream` uses for sending datagrams.
+    pub fn set_self_address(&mut self, self_address: u16) {
+        self.self_address = self_address;
+    }
+
+    /// Get the VBus address the `LiveDataStream` uses for sending datagrams.
+    pub fn self_address(&self) -> u16 {
+        self.self_address
+    }
+
+    /// Set the `DeviceInformation` of the device to connect to.
+    ///
+    /// If set and the device is known to provide a single VBus channel only
+    /// (see `DeviceInformation::has_multiple_channels`), a `CHANNEL` command
+    /// is not sent. Instead `connect_live_data_stream` fails before
+    /// starting the handshake with an error for which
+    /// `Error::is_unsupported_channel` returns `true`.
+    pub fn set_device_information(&mut self, device_information: Option<DeviceInformation>) {
+        self.device_information = device_information;
+    }
+
+    /// Set the port of the web server to fetch the `DeviceInformation`
+    /// from, if it was not set using `set_device_information`.
+    ///
+    /// If set and a `CHANNEL` command is used,
+    /// `connect_tcp_live_data_stream` fetches the `DeviceInformation` from
+    /// the connected host before starting the handshake (DL2 and DL3 use
+    /// port 80). If fetching fails, the channel support is not checked.
+    ///
+    /// Defaults to `None`, which does not fetch the `DeviceInformation`.
+    pub fn set_device_information_port(&mut self, port: Option<u16>) {
+        self.device_information_port = port;
+    }
+
+    /// Set the `CredentialProvider` asked for another password if the
+    /// service rejects the `PASS` command.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::sync::Arc;
+    ///
+    /// use async_resol_vbus::ConnectOptions;
+    ///
+    /// let mut options = ConnectOptions::new();
+    /// options.set_credential_provider(Some(Arc::new(|attempt| {
+    ///     Box::pin(async move {
+    ///         // ... fetch the next password from a secrets store ...
+    ///         if attempt == 1 {
+    ///             Some("rotated".to_string())
+    ///         } else {
+    ///             None
+    ///         }
+    ///     })
+    /// })));
+    /// ```
+    pub fn set_credential_provider(&mut self, provider: Option<CredentialProvider>) {
+        self.credential_provider = provider;
+    }
+
+    /// Set how many times the `CredentialProvider` is asked for another
+    /// password before the handshake fails. Defaults to `3`.
+    pub fn set_max_password_retries(&mut self, max_password_retries: usize) {
+        self.max_password_retries = max_password_retries;
+    }
+
+    /// Set the overall time budget for establishing the connection.
+    ///
+    /// The budget spans the TCP connect (if using
+    /// `connect_tcp_live_data_stream`), the greeting, all handshake commands
+    /// and the first valid VBus frame received after the `DATA` command.
+    /// If it is exceeded, the connection fails with an error reporting the
+    /// time spent in every phase. Other errors during these phases report
+    /// that breakdown as well.
+    ///
+    /// Defaults to `None`, which neither limits the duration nor waits for
+    /// the first frame.
+    pub fn set_connect_timeout(&mut self, connect_timeout: Option<Duration>) {
+        self.connect_timeout = connect_timeout;
+    }
+
+    /// Set the time window in which at least one valid VBus frame must be
+    /// received after the `DATA` command.
+    ///
+    /// Some misconfigured endpoints accept the `DATA` command but send
+    /// something else afterwards (e.g. an HTTP server listening on port
+    /// 7053). If set, the connection fails with a "not a VBus data stream"
+    /// error in that case.
+    ///
+    /// Defaults to `None`, which does not validate the data stream.
+    pub fn set_first_frame_timeout(&mut self, first_frame_timeout: Option<Duration>) {
+        self.first_frame_timeout = first_frame_timeout;
+    }
+
+    /// Set the `SocketOptions` applied to the `TcpStream` opened by
+    /// `connect_tcp_live_data_stream`.
+    pub fn set_socket_options(&mut self, socket_options: SocketOptions) {
+        self.socket_options = socket_options;
+    }
+
+    /// Set the write timeout of the `LiveDataStream`, see
+    /// `LiveDataStream::set_write_timeout`.
+    pub fn set_write_timeout(&mut self, write_timeout: Option<Duration>) {
+        self.write_timeout = write_timeout;
+    }
+
+    /// Set the `WriteGuard` of the `LiveDataStream`, see
+    /// `LiveDataStream::set_write_guard`.
+    ///
+    /// Since the guard is part of the options, it also applies to streams
+    /// created by `reconnect_live_data_stream`, `quick_connect_with_options`
+    /// and the `ConnectionManager`.
+    pub fn set_write_guard(&mut self, write_guard: Option<WriteGuard>) {
+        self.write_guard = write_guard;
+    }
+
+    /// Set the `MetricsHook` notified about the connection attempt.
+    ///
+    /// It is also passed on to the `LiveDataStream`.
+    pub fn set_metrics_hook(&mut self, metrics_hook: Option<Arc<dyn MetricsHook>>) {
+        self.metrics_hook = metrics_hook;
+    }
+
+    /// Set a channel receiving a `VBusEvent` when the connection is
+    /// established or fails.
+    ///
+    /// It is also passed on to the `LiveDataStream`.
+    pub fn set_event_sender(&mut self, sender: Option<Sender<VBusEvent>>) {
+        self.event_sender = sender;
+    }
+
+    async fn send_pass_command<S>(
+        &self,
+        hs: &mut TcpClientHandshake<S>,
+        password: String,
+    ) -> Result<()>
+    where
+        S: Read + Write + Unpin,
+    {
+        let provider = match self.credential_provider {
+            Some(ref provider) => provider,
+            None => return hs.send_pass_command(&password).await,
+        };
+
+        let mut password = password;
+        let mut attempt = 0;
+        while !hs.try_pass_command(&password).await? {
+            attempt += 1;
+
+            let next_password = if attempt <= self.max_password_retries {
+                provider(attempt).await
+            } else {
+                None
+            };
+
+            password = match next_password {
+                Some(next_password) => next_password,
+                None => {
+                    return Err(Error::negative_reply(format!(
+                        "Password rejected {} times",
+                        attempt
+                    )))
+                }
+            };
+        }
+
+        Ok(())
+    }
+
+    /// Apply the options that are passed on to the `LiveDataStream`.
+    pub(crate) fn configure_live_data_stream<R, W>(&self, lds: &mut LiveDataStream<R, W>)
+    where
+        R: Read + Unpin,
+        W: Write + Unpin,
+    {
+        lds.set_metrics_hook(self.metrics_hook.clone());
+        lds.set_event_sender(self.event_sender.clone());
+        lds.set_write_timeout(self.write_timeout);
+        lds.set_write_guard(self.write_guard.clone());
+    }
+
+    fn notify_connect_result<T>(&self, result: &Result<T>) {
+        if let Some(ref hook) = self.metrics_hook {
+            match result {
+                Ok(_) => hook.connected(),
+                Err(err) => hook.error("connect", err.message()),
+            }
+        }
+
+        let event = match result {
+            Ok(_) => VBusEvent::ConnectionUp,
+            Err(err) => VBusEvent::Error {
+                kind: ErrorKind::Connect,
+                message: err.message().to_string(),
+            },
+        };
+        emit_event(&self.event_sender, event);
+    }
+
+    fn has_channel_step(&self) -> bool {
+        self.handshake_steps()
+            .iter()
+            .any(|step| matches!(step, HandshakeStep::Channel(_)))
+    }
+
+    /// Fetch the `DeviceInformation` of the device connected using `stream`,
+    /// if required to check the channel support.
+    async fn probe_device_information(&self, stream: &TcpStream) -> Option<DeviceInformation> {
+        let port = self.device_information_port?;
+        if self.device_information.is_some() || !self.has_channel_step() {
+            return None;
+        }
+
+        let mut addr = stream.peer_addr().ok()?;
+        addr.set_port(port);
+        DeviceInformation::fetch(addr, DEVICE_INFORMATION_TIMEOUT)
+            .await
+            .ok()
+    }
+
+    fn check_channel_support(&self, device: Option<&DeviceInformation>) -> Result<()> {
+        let device = match device.or(self.device_information.as_ref()) {
+            Some(device) => device,
+            None => return Ok(()),
+        };
+
+        // devices of unknown products are left to reject the `CHANNEL`
+        // command themselves
+        if self.has_channel_step() && device.has_multiple_channels() == Some(false) {
+            let name = device
+                .name
+                .as_deref()
+                .or(device.product.as_deref())
+                .unwrap_or("unknown");
+            return Err(Error::unsupported_channel(format!(
+                "Device {} does not support multiple VBus channels (product: {})",
+                name,
+                device.product.as_deref().unwrap_or("")
+            )));
+        }
+
+        Ok(())
+    }
+}
+
+impl std::fmt::Debug for ConnectOptions {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ConnectOptions")
+            .field("via_tag", &self.via_tag)
+            .field("password", &self.password.is_some())
+            .field("channel", &self.channel)
+            .field("handshake_steps", &self.handshake_steps)
+            .field("self_address", &self.self_address)
+            .field("device_information", &self.device_information)
+            .field("device_information_port", &self.device_information_port)
+            .field("credential_provider", &self.credential_provider.is_some())
+            .field("max_password_retries", &self.max_password_retries)
+            .field("connect_timeout", &self.connect_timeout)
+            .field("first_frame_timeout", &self.first_frame_timeout)
+            .field("metrics_hook", &self.metrics_hook.is_some())
+            .field("event_sender", &self.event_sender)
+            .field("write_timeout", &self.write_timeout)
+            .field("write_guard", &self.write_guard)
+            .field("socket_options", &self.socket_options)
+            .finish()
+    }
+}
+
+impl Default for ConnectOptions {
+    fn default() -> ConnectOptions {
+        ConnectOptions::new()
+    }
+}
+
+/// Perform the client-side VBus-over-TCP handshake on `stream` and return a
+/// `LiveDataStream` using it.
+///
+/// The stream must be cloneable (like `TcpStream`), one clone is used for
+/// reading and the other one for writing. Streams that cannot be cloned can
+/// be used with `connect_split_live_data_stream`.
+///
+/// # Examples
+///
+/// ```no_run
+/// # fn main() -> async_resol_vbus::Result<()> { async_std::task::block_on(async {
+/// #
+/// use async_std::net::{SocketAddr, TcpStream};
+///
+/// use async_resol_vbus::{connect_live_data_stream, ConnectOptions};
+///
+/// let address = "192.168.5.217:7053".parse::<SocketAddr>()?;
+/// let stream = TcpStream::connect(address).await?;
+///
+/// let mut lds = connect_live_data_stream(stream, &ConnectOptions::new()).await?;
+///
+/// while let Some(data) = lds.receive_any_data(60000).await? {
+///     println!("{}", data.id_string());
+/// }
+/// #
+/// # Ok(()) }) }
+/// ```
+pub async fn connect_live_data_stream<S>(
+    stream: S,
+    options: &ConnectOptions,
+) -> Result<LiveDataStream<S, S>>
+where
+    S: Read + Write + Clone + Unpin,
+{
+    let mut budget = ConnectBudget::new(options.connect_timeout);
+    let result = handshake(stream, options, &mut budget, |stream| {
+        (stream.clone(), stream)
+    })
+    .await;
+    options.notify_connect_result(&result);
+    result
+}
+
+/// Perform the client-side VBus-over-TCP handshake on a `stream` that
+/// cannot be cloned and return a `LiveDataStream` using it.
+///
+/// After the handshake the stream is split into a `ReadHalf` and a
+/// `WriteHalf` that share the stream behind a mutex. This allows using
+/// duplex streams like TLS or serial port adapters. For cloneable streams
+/// like `TcpStream` the `connect_live_data_stream` function avoids that
+/// overhead.
+///
+/// # Examples
+///
+/// ```
+/// use async_std::io::{Read, Write};
+///
+/// use async_resol_vbus::{connect_split_live_data_stream, ConnectOptions, Result};
+///
+/// async fn print_data<S: Read + Write + Unpin>(stream: S) -> Result<()> {
+///     let mut lds = connect_split_live_data_stream(stream, &ConnectOptions::new()).await?;
+///
+///     while let Some(data) = lds.receive_any_data(60000).await? {
+///         println!("{}", data.id_string());
+///     }
+///
+///     Ok(())
+/// }
+/// # fn main() {}
+/// ```
+pub async fn connect_split_live_data_stream<S>(
+    stream: S,
+    options: &ConnectOptions,
+) -> Result<LiveDataStream<ReadHalf<S>, WriteHalf<S>>>
+where
+    S: Read + Write + Unpin,
+{
+    let mut budget = ConnectBudget::new(options.connect_timeout);
+    let result = handshake(stream, options, &mut budget, futures_lite::io::split).await;
+    options.notify_connect_result(&result);
+    result
+}
+
+/// Connect to the VBus-over-TCP service at `address`, perform the
+/// client-side handshake and return a `TcpLiveDataStream`.
+///
+/// In contrast to `connect_live_data_stream` the TCP connect is included in
+/// the budget set using `ConnectOptions::set_connect_timeout`.
+///
+/// # Examples
+///
+/// ```no_run
+/// # fn main() -> async_resol_vbus::Result<()> { async_std::task::block_on(async {
+/// #
+/// use std::time::Duration;
+///
+/// use async_resol_vbus::{connect_tcp_live_data_stream, ConnectOptions};
+///
+/// let mut options = ConnectOptions::new();
+/// options.set_connect_timeout(Some(Duration::from_secs(10)));
+///
+/// let mut lds = connect_tcp_live_data_stream("192.168.5.217:7053", &options).await?;
+/// #
+/// # Ok(()) }) }
+/// ```
+pub async fn connect_tcp_live_data_stream<A: ToSocketAddrs>(
+    address: A,
+    options: &ConnectOptions,
+) -> Result<TcpLiveDataStream> {
+    connect_tcp_live_data_stream_with(address, options, |stream| (stream.clone(), stream)).await
+}
+
+/// Like `connect_tcp_live_data_stream`, but uses the reader and writer
+/// returned by `split` for the `LiveDataStream`, e.g. to box them.
+pub(crate) async fn connect_tcp_live_data_stream_with<A, R, W, F>(
+    address: A,
+    options: &ConnectOptions,
+    split: F,
+) -> Result<LiveDataStream<R, W>>
+where
+    A: ToSocketAddrs,
+    R: Read + Unpin,
+    W: Write + Unpin,
+    F: FnOnce(TcpStream) -> (R, W),
+{
+    let mut budget = ConnectBudget::new(options.connect_timeout);
+    let result = connect_tcp(address, options, &mut budget, split).await;
+    options.notify_connect_result(&result);
+    result
+}
+
+/// A failed attempt of `try_connect_tcp_live_data_stream`.
+pub(crate) struct ConnectFailure {
+    pub(crate) error: Error,
+
+    /// Whether the service answered the `PASS` command with a negative
+    /// reply. Other failures during that phase (e.g. the connection being
+    /// closed or timing out) are not counted.
+    pub(crate) is_auth_failure: bool,
+}
+
+/// Like `connect_tcp_live_data_stream`, but also reports whether the
+/// attempt failed because the service rejected the password.
+pub(crate) async fn try_connect_tcp_live_data_stream<A: ToSocketAddrs>(
+    address: A,
+    options: &ConnectOptions,
+) -> std::result::Result<TcpLiveDataStream, ConnectFailure> {
+    let mut budget = ConnectBudget::new(options.connect_timeout);
+    let result = connect_tcp(address, options, &mut budget, |stream| {
+        (stream.clone(), stream)
+    })
+    .await;
+    options.notify_connect_result(&result);
+    result.map_err(|error| ConnectFailure {
+        is_auth_failure: error.is_negative_reply() && budget.failure == Some(("PASS", false)),
+        error,
+    })
+}
+
+async fn connect_tcp<A, R, W, F>(
+    address: A,
+    options: &ConnectOptions,
+    budget: &mut ConnectBudget,
+    split: F,
+) -> Result<LiveDataStream<R, W>>
+where
+    A: ToSocketAddrs,
+    R: Read + Unpin,
+    W: Write + Unpin,
+    F: FnOnce(TcpStream) -> (R, W),
+{
+    let stream = budget
+        .run("tcp connect", async {
+            Ok(TcpStream::connect(address).await?)
+        })
+        .await?;
+    options.socket_options.apply(&stream)?;
+    let device = options.probe_device_information(&stream).await;
+    options.check_channel_support(device.as_ref())?;
+    handshake(stream, options, budget, split).await
+}
+
+/// Perform the handshake on `stream` and use the reader and writer
+/// returned by `split` for the `LiveDataStream`.
+async fn handshake<S, R, W, F>(
+    stream: S,
+    options: &ConnectOptions,
+    budget: &mut ConnectBudget,
+    split: F,
+) -> Result<LiveDataStream<R, W>>
+where
+    S: Read + Write + Unpin,
+    R: Read + Unpin,
+    W: Write + Unpin,
+    F: FnOnce(S) -> (R, W),
+{
+    options.check_channel_support(None)?;
+
+    let mut channel = 0;
+
+    let mut hs = budget
+        .run("greeting", TcpClientHandshake::start(stream))
+        .await?;
+    for step in options.handshake_steps() {
+        match step {
+            HandshakeStep::Connect(via_tag) => {
+                budget
+                    .run("CONNECT", hs.send_connect_command(&via_tag))
+                    .await?
+            }
+            HandshakeStep::Pass(password) => {
+                budget
+                    .run("PASS", options.send_pass_command(&mut hs, password))
+                    .await?
+            }
+            HandshakeStep::Channel(step_channel) => {
+                budget
+                    .run("CHANNEL", hs.send_channel_command(step_channel))
+                    .await?;
+                channel = step_channel;
+            }
+        }
+    }
+    let (mut stream, mut bytes) = budget.run("DATA", hs.send_data_command_buffered()).await?;
+
+    if budget.is_limited() || options.first_frame_timeout.is_some() {
+        bytes = budget
+            .run(
+                "first frame",
+                receive_first_frame(&mut stream, channel, bytes, options.first_frame_timeout),
+            )
+            .await?;
+    }
+
+    let (reader, writer) = split(stream);
+    let mut lds = LiveDataStream::new(reader, writer, channel, options.self_address);
+    options.configure_live_data_stream(&mut lds);
+
+    lds.extend_buffer(&bytes)?;
+
+    Ok(lds)
+}
+
+/// Read from `reader` until the `bytes` received so far contain a valid
+/// VBus frame and return them.
+///
+/// Fails if the connection is closed or no valid frame was found within
+/// the `window`.
+async fn receive_first_frame<R: Read + Unpin>(
+    reader: &mut R,
+    channel: u8,
+    mut bytes: Vec<u8>,
+    window: Option<Duration>,
+) -> Result<Vec<u8>> {
+    let receive = async {
+        let mut buf = LiveDataBuffer::new(channel);
+        buf.extend_from_slice(&bytes);
+        loop {
+            if buf.peek_length().is_some() {
+                return Ok(());
+            }
+
+            let mut chunk = [0u8; 1024];
+            let len = reader.read(&mut chunk).await?;
+            if len == 0 {
+                return Err(Error::from(
+                    "Not a VBus data stream: connection closed before receiving a valid VBus frame",
+                ));
+            }
+
+            bytes.extend_from_slice(&chunk[0..len]);
+            buf.extend_from_slice(&chunk[0..len]);
+        }
+    };
+
+    match window {
+        Some(window) => match async_std::future::timeout(window, receive).await {
+            Ok(result) => result?,
+            Err(_) => {
+                return Err(format!(
+                    "Not a VBus data stream: no valid VBus frame within {:?} after the DATA command ({} bytes received)",
+                    window,
+                    bytes.len()
+                )
+                .into())
+            }
+        },
+        None => receive.await?,
+    }
+
+    Ok(bytes)
+}
+
+/// Tracks the phases of a connection attempt against the overall budget
+/// set using `ConnectOptions::set_connect_timeout`.
+struct ConnectBudget {
+    budget: Option<Duration>,
+    start: Instant,
+    phases: Vec<(&'static str, Duration)>,
+
+    /// The phase that failed and whether it timed out.
+    failure: Option<(&'static str, bool)>,
+}
+
+impl ConnectBudget {
+    fn new(budget: Option<Duration>) -> ConnectBudget {
+        ConnectBudget {
+            budget,
+            start: Instant::now(),
+            phases: Vec::new(),
+            failure: None,
+        }
+    }
+
+    fn is_limited(&self) -> bool {
+        self.budget.is_some()
+    }
+
+    /// Run the `future` of the given `phase` with the remaining budget.
+    async fn run<T, F>(&mut self, phase: &'static str, future: F) -> Result<T>
+    where
+        F: Future<Output = Result<T>>,
+    {
+        let phase_start = Instant::now();
+
+        let result = match self.budget {
+            Some(budget) => {
+                let remaining = budget.saturating_sub(self.start.elapsed());
+                async_std::future::timeout(remaining, future).await
+            }
+            None => Ok(future.await),
+        };
+
+        self.phases.push((phase, phase_start.elapsed()));
+
+        match result {
+            Ok(Ok(result)) => Ok(result),
+            Ok(Err(err)) => {
+                self.failure = Some((phase, false));
+                Err(err.with_message_suffix(&format!(
+                    ", failed during {} ({})",
+                    phase,
+                    self.breakdown()
+                )))
+            }
+            Err(_) => {
+                self.failure = Some((phase, true));
+                Err(self.timeout_error(phase))
+            }
+        }
+    }
+
+    /// Format the time spent in every phase so far.
+    fn breakdown(&self) -> String {
+        self.phases
+            .iter()
+            .map(|(phase, duration)| format!("{}: {:?}", phase, duration))
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+
+    fn timeout_error(&self, phase: &str) -> Error {
+        format!(
+            "Not connected within {:?}, timed out during {} ({})",
+            self.budget.unwrap_or_default(),
+            phase,
+            self.breakdown()
+        )
+        .into()
+    }
+}
+
+/// Re-establish the connection of `lds` using `options`, e.g. to switch
+/// to another channel of a multi-channel device like the DL3.
+///
+/// The VBus-over-TCP protocol does not provide a way to return from DATA
+/// mode to command mode, so the existing connection is closed and a new
+/// connection to the same peer is opened and handshaked using `options`.
+///
+/// Pending writes of `lds` are sent before closing its connection, see
+/// `LiveDataStream::close`. The settings of `lds` that do not depend on the
+/// connection are copied onto the new stream: the `WriteGuard`, the
+/// `TransactionJournal`, the keep-alive interval, the `DatagramResponder`,
+/// the write timeout, the maximum buffer size with its
+/// `BufferOverflowPolicy` and sender, the `MetricsHook` and the event
+/// sender. Settings also provided by `options` take precedence. State
+/// learned from the peer (e.g. cached value ID hashes and protocol
+/// versions) is not copied, since the new connection may reach another
+/// device.
+///
+/// # Examples
+///
+/// ```no_run
+/// # fn main() -> async_resol_vbus::Result<()> { async_std::task::block_on(async {
+/// #
+/// use async_std::net::TcpStream;
+///
+/// use async_resol_vbus::{connect_live_data_stream, reconnect_live_data_stream, ConnectOptions};
+///
+/// let mut options = ConnectOptions::new();
+/// options.set_channel(Some(1));
+///
+/// let stream = TcpStream::connect("192.168.5.217:7053").await?;
+/// let lds = connect_live_data_stream(stream, &options).await?;
+/// // ...
+///
+/// options.set_channel(Some(2));
+/// let lds = reconnect_live_data_stream(lds, &options).await?;
+/// #
+/// # Ok(()) }) }
+/// ```
+pub async fn reconnect_live_data_stream(
+    mut lds: TcpLiveDataStream,
+    options: &ConnectOptions,
+) -> Result<TcpLiveDataStream> {
+    // the peer may already have closed the connection, so ignore errors
+    let _ = lds.close().await;
+    let settings = lds.take_settings();
+
+    let (stream, _) = lds.into_inner();
+    let address = stream.peer_addr()?;
+    let _ = stream.shutdown(Shutdown::Both);
+    drop(stream);
+
+    let mut lds = connect_tcp_live_data_stream(address, options).await?;
+    lds.apply_settings(settings);
+    Ok(lds)
+}
+
+#[cfg(test)]
+mod tests {
+    use async_std::{
+        net::{SocketAddr, TcpListener, TcpStream, UdpSocket},
+        prelude::*,
+    };
+
+    use crate::{
+        dl2_simulator::Dl2Simulator, tcp_server_handshake::TcpServerHandshake,
+        test_utils::extend_from_datagram,
+    };
+
+    use super::*;
+
+    #[test]
+    fn test_connect_live_data_stream() -> Result<()> {
+        async_std::task::block_on(async {
+            let addr = "127.0.0.1:0".parse::<SocketAddr>()?;
+            let listener = TcpListener::bind(&addr).await?;
+            let addr = listener.local_addr()?;
+
+            let server_future = async_std::task::spawn::<_, Result<(String, u8)>>(async move {
+                let (stream, _) = listener.accept().await?;
+
+                let mut hs = TcpServerHandshake::start(stream).await?;
+                let password = hs.receive_pass_command().await?;
+                let channel = hs.receive_channel_command().await?;
+                let mut stream = hs.receive_data_command().await?;
+
+                // give the client time to finish its handshake before sending data
+                async_std::task::sleep(std::time::Duration::from_millis(100)).await;
+
+                let mut bytes = Vec::new();
+                extend_from_datagram(&mut bytes, 0x0020, 0x7E11, 0x0100, 0x1234, 0x5678);
+                stream.write_all(&bytes).await?;
+
+                Ok((password, channel))
+            });
+
+            let stream = TcpStream::connect(addr).await?;
+
+            let mut options = ConnectOptions::new();
+            options.set_password(Some("secret".to_string()));
+            options.set_channel(Some(1));
+
+            let mut lds = connect_live_data_stream(stream, &options).await?;
+
+            let data = lds.receive_any_data(1000).await?.unwrap();
+            assert_eq!(1, data.as_ref().channel);
+            assert_eq!(0x5678, data.as_datagram().param32);
+
+            let (password, channel) = server_future.await?;
+            assert_eq!("secret", password);
+            assert_eq!(1, channel);
+
+            Ok(())
+        })
+    }
+
+    /// A duplex stream that is not `Clone`.
+    struct DuplexStream(TcpStream);
+
+    impl Read for DuplexStream {
+        fn poll_read(
+            mut self: Pin<&mut Self>,
+            cx: &mut std::task::Context<'_>,
+            buf: &mut [u8],
+        ) -> std::task::Poll<std::io::Result<usize>> {
+            Pin::new(&mut self.0).poll_read(cx, buf)
+        }
+    }
+
+    impl Write for DuplexStream {
+        fn poll_write(
+            mut self: Pin<&mut Self>,
+            cx: &mut std::task::Context<'_>,
+            buf: &[u8],
+        ) -> std::task::Poll<std::io::Result<usize>> {
+            Pin::new(&mut self.0).poll_write(cx, buf)
+        }
+
+        fn poll_flush(
+            mut self: Pin<&mut Self>,
+            cx: &mut std::task::Context<'_>,
+        ) -> std::task::Poll<std::io::Result<()>> {
+            Pin::new(&mut self.0).poll_flush(cx)
+        }
+
+        fn poll_close(
+            mut self: Pin<&mut Self>,
+            cx: &mut std::task::Context<'_>,
+        ) -> std::task::Poll<std::io::Result<()>> {
+            Pin::new(&mut self.0).poll_close(cx)
+        }
+    }
+
+    #[test]
+    fn test_connect_split_live_data_stream() -> Result<()> {
+        async_std::task::block_on(async {
+            let listener = TcpListener::bind("127.0.0.1:0").await?;
+            let addr = listener.local_addr()?;
+
+            let server_future = async_std::task::spawn::<_, Result<Vec<u8>>>(async move {
+                let (stream, _) = listener.accept().await?;
+
+                let mut hs = TcpServerHandshake::start(stream).await?;
+                hs.receive_pass_command().await?;
+                let mut stream = hs.receive_data_command().await?;
+
+                let mut bytes = Vec::new();
+                extend_from_datagram(&mut bytes, 0x0020, 0x7E11, 0x0100, 0x1234, 0x5678);
+                stream.write_all(&bytes).await?;
+
+                let mut buf = [0u8; 16];
+                let len = stream.read(&mut buf).await?;
+                Ok(buf[0..len].to_vec())
+            });
+
+            let stream = DuplexStream(TcpStream::connect(addr).await?);
+
+            let mut options = ConnectOptions::new();
+            options.set_first_frame_timeout(Some(Duration::from_millis(1000)));
+
+            let mut lds = connect_split_live_data_stream(stream, &options).await?;
+
+            let data = lds.receive_any_data(1000).await?.unwrap();
+            assert_eq!(0x5678, data.as_datagram().param32);
+
+            lds.release_bus(0x7E11).await?;
+
+            let bytes = server_future.await?;
+            assert_eq!(16, bytes.len());
+            assert_eq!(&[0xAA, 0x11, 0x7E, 0x20, 0x00], &bytes[0..5]);
+
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn test_reconnect_live_data_stream() -> Result<()> {
+        async_std::task::block_on(async {
+            let listener = TcpListener::bind("127.0.0.1:0").await?;
+            let addr = listener.local_addr()?;
+
+            let server_future = async_std::task::spawn::<_, Result<Vec<u8>>>(async move {
+                let mut channels = Vec::new();
+                for _ in 0..2 {
+                    let (stream, _) = listener.accept().await?;
+
+                    let mut hs = TcpServerHandshake::start(stream).await?;
+                    hs.receive_pass_command().await?;
+                    channels.push(hs.receive_channel_command().await?);
+                    hs.receive_data_command().await?;
+                }
+                Ok(channels)
+            });
+
+            let mut options = ConnectOptions::new();
+            options.set_channel(Some(1));
+
+            let stream = TcpStream::connect(addr).await?;
+            let mut lds = connect_live_data_stream(stream, &options).await?;
+            assert_eq!(1, lds.channel());
+            lds.set_write_guard(Some(WriteGuard::deny_all()));
+
+            options.set_channel(Some(2));
+            let mut lds = reconnect_live_data_stream(lds, &options).await?;
+            assert_eq!(2, lds.channel());
+
+            // the write guard was copied onto the new stream
+            let err = lds
+                .set_value_by_index(0x7E11, 0x1234, 0, 1)
+                .await
+                .unwrap_err();
+            assert!(err.is_write_denied());
+
+            assert_eq!(vec![1, 2], server_future.await?);
+
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn test_credential_provider() -> Result<()> {
+        async_std::task::block_on(async {
+            let listener = TcpListener::bind("127.0.0.1:0").await?;
+            let addr = listener.local_addr()?;
+
+            let server_future = async_std::task::spawn::<_, Result<String>>(async move {
+                let mut passwords = Vec::new();
+                for _ in 0..2 {
+                    let (stream, _) = listener.accept().await?;
+
+                    let mut hs = TcpServerHandshake::start(stream).await?;
+                    let result = hs
+                        .receive_pass_command_and_verify_password(|password| async move {
+                            if password == "secret" {
+                                Ok(password)
+                            } else {
+                                Err("-ERROR: Wrong password\r\n")
+                            }
+                        })
+                        .await;
+                    if let Ok(password) = result {
+                        hs.receive_data_command().await?;
+                        passwords.push(password);
+                    }
+                }
+                Ok(passwords.join(","))
+            });
+
+            let mut options = ConnectOptions::new();
+            options.set_password(Some("wrong".into()));
+            options.set_credential_provider(Some(Arc::new(|attempt| {
+                Box::pin(async move {
+                    match attempt {
+                        1 => Some("old".to_string()),
+                        2 => Some("secret".to_string()),
+                        _ => None,
+                    }
+                })
+            })));
+
+            let stream = TcpStream::connect(addr).await?;
+            let lds = connect_live_data_stream(stream, &options).await?;
+            drop(lds);
+
+            options.set_max_password_retries(1);
+
+            let stream = TcpStream::connect(addr).await?;
+            let err = connect_live_data_stream(stream, &options)
+                .await
+                .err()
+                .unwrap();
+            assert!(err
+                .message()
+                .starts_with("Password rejected 2 times, failed during PASS (greeting: "));
+            assert!(err.is_negative_reply());
+
+            assert_eq!("secret", server_future.await?);
+
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn test_check_channel_support() -> Result<()> {
+        let address = "192.168.5.217:80".parse()?;
+
+        // the responses of `/cgi-bin/get_resol_device_information`
+        let dl2 = DeviceInformation::parse(
+            address,
+            "vendor = \"RESOL\"\r\nproduct = \"DL2\"\r\nserial = \"001E66xxxxxx\"\r\nversion = \"2.2.0\"\r\nbuild = \"rc1\"\r\nname = \"DL2-001E66xxxxxx\"\r\nfeatures = \"vbus,dl2\"\r\n",
+        )?;
+        let dl3 = DeviceInformation::parse(
+            address,
+            "vendor = \"RESOL\"\r\nproduct = \"DL3\"\r\nserial = \"001E66xxxxxx\"\r\nversion = \"2.2.0\"\r\nbuild = \"rc1\"\r\nname = \"DL3-001E66xxxxxx\"\r\nfeatures = \"vbus,dl2,dl3\"\r\n",
+        )?;
+
+        let mut options = ConnectOptions::new();
+        options.set_device_information(Some(dl2.clone()));
+        assert_eq!(Ok(()), options.check_channel_support(None));
+
+        options.set_channel(Some(1));
+        let err = options.check_channel_support(None).unwrap_err();
+        assert!(err.is_unsupported_channel());
+        assert_eq!(
+            "Device DL2-001E66xxxxxx does not support multiple VBus channels (product: DL2)",
+            err.message()
+        );
+
+        assert_eq!(Ok(()), options.check_channel_support(Some(&dl3)));
+
+        options.set_device_information(Some(dl3));
+        assert_eq!(Ok(()), options.check_channel_support(None));
+        assert!(options.check_channel_support(Some(&dl2)).is_err());
+
+        // unknown products are not rejected
+        options.set_device_information(Some(DeviceInformation::parse(
+            address,
+            "product = \"DL4\"\r\nfeatures = \"vbus\"\r\n",
+        )?));
+        assert_eq!(Ok(()), options.check_channel_support(None));
+
+        options.set_device_information(Some(DeviceInformation::parse(address, "")?));
+        assert_eq!(Ok(()), options.check_channel_support(None));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_device_information_port() -> Result<()> {
+        async_std::task::block_on(async {
+            let web_listener = TcpListener::bind("127.0.0.1:0").await?;
+            let web_port = web_listener.local_addr()?.port();
+            let vbus_listener = TcpListener::bind("127.0.0.1:0").await?;
+            let vbus_addr = vbus_listener.local_addr()?;
+
+            let simulator = Dl2Simulator::new();
+            async_std::task::spawn(simulator.run(
+                UdpSocket::bind("127.0.0.1:0").await?,
+                web_listener,
+                vbus_listener,
+            ));
+
+            let mut options = ConnectOptions::new();
+            options.set_channel(Some(1));
+            options.set_device_information_port(Some(web_port));
+
+            let err = connect_tcp_live_data_stream(vbus_addr, &options)
+                .await
+                .unwrap_err();
+            assert!(err.is_unsupported_channel());
+
+            options.set_channel(None);
+            connect_tcp_live_data_stream(vbus_addr, &options).await?;
+
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn test_debug_hides_password() {
+        let mut options = ConnectOptions::new();
+        options.set_password(Some("secret".into()));
+        options.set_handshake_steps(Some(vec![
+            HandshakeStep::Connect("via".into()),
+            HandshakeStep::Pass("secret".into()),
+        ]));
+
+        let debug = format!("{:?}", options);
+        assert!(!debug.contains("secret"));
+        assert!(debug.contains("[Connect(\"via\"), Pass(\"..\")]"));
+    }
+
+    #[test]
+    fn test_handshake_steps() -> Result<()> {
+        let mut options = ConnectOptions::new();
+        assert_eq!(
+            vec![HandshakeStep::Pass("vbus".into())],
+            options.handshake_steps()
+        );
+
+        options.set_via_tag(Some("via".into()));
+        options.set_channel(Some(1));
+        assert_eq!(
+            vec![
+                HandshakeStep::Connect("via".into()),
+                HandshakeStep::Pass("vbus".into()),
+                HandshakeStep::Channel(1),
+            ],
+            options.handshake_steps()
+        );
+
+        options.set_handshake_steps(Some(vec![
+            HandshakeStep::Connect("outer".into()),
+            HandshakeStep::Channel(2),
+            HandshakeStep::Connect("inner".into()),
+            HandshakeStep::Pass("secret".into()),
+        ]));
+
+        async_std::task::block_on(async {
+            let addr = "127.0.0.1:0".parse::<SocketAddr>()?;
+            let listener = TcpListener::bind(&addr).await?;
+            let addr = listener.local_addr()?;
+
+            let server_future = async_std::task::spawn::<_, Result<Vec<String>>>(async move {
+                let (stream, _) = listener.accept().await?;
+
+                let mut hs = TcpServerHandshake::start(stream).await?;
+                let outer = hs.receive_connect_command().await?;
+                let channel = hs.receive_channel_command().await?;
+                let inner = hs.receive_connect_command().await?;
+                let password = hs.receive_pass_command().await?;
+                hs.receive_data_command().await?;
+
+                Ok(vec![outer, channel.to_string(), inner, password])
+            });
+
+            let stream = TcpStream::connect(addr).await?;
+            let lds = connect_live_data_stream(stream, &options).await?;
+            drop(lds);
+
+            assert_eq!(vec!["outer", "2", "inner", "secret"], server_future.await?);
+
+            let listener = TcpListener::bind(&addr).await?;
+            let addr = listener.local_addr()?;
+
+            let server_future = async_std::task::spawn::<_, Result<()>>(async move {
+                let (stream, _) = listener.accept().await?;
+
+                let mut hs = TcpServerHandshake::start(stream).await?;
+                drop(
+                    hs.receive_connect_command_and_verify_via_tag(|_| async {
+                        Err("-ERROR Unknown via tag\r\n")
+                    })
+                    .await,
+                );
+
+                Ok(())
+            });
+
+            let stream = TcpStream::connect(addr).await?;
+            let err = connect_live_data_stream(stream, &options)
+                .await
+                .err()
+                .unwrap();
+            assert!(err
+                .message()
+                .starts_with("Negative reply, failed during CONNECT (greeting: "));
+            assert!(err.is_negative_reply());
+
+            server_future.await?;
+
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn test_connect_timeout() -> Result<()> {
+        async_std::task::block_on(async {
+            let listener = TcpListener::bind("127.0.0.1:0").await?;
+            let addr = listener.local_addr()?;
+
+            let server_future = async_std::task::spawn::<_, Result<()>>(async move {
+                for send_data in [true, false] {
+                    let (stream, _) = listener.accept().await?;
+
+                    let mut hs = TcpServerHandshake::start(stream).await?;
+                    hs.receive_pass_command().await?;
+                    let mut stream = hs.receive_data_command().await?;
+
+                    if send_data {
+                        let mut bytes = b"garbage".to_vec();
+                        extend_from_datagram(&mut bytes, 0x0020, 0x7E11, 0x0100, 0x1234, 0x5678);
+                        stream.write_all(&bytes).await?;
+                    }
+
+                    let mut buf = [0u8; 16];
+                    while stream.read(&mut buf).await? > 0 {}
+                }
+                Ok(())
+            });
+
+            let mut options = ConnectOptions::new();
+            options.set_connect_timeout(Some(Duration::from_millis(500)));
+
+            let mut lds = connect_tcp_live_data_stream(addr, &options).await?;
+            let data = lds.receive_any_data(1000).await?.unwrap();
+            assert_eq!(0x5678, data.as_datagram().param32);
+            drop(lds);
+
+            let err = connect_tcp_live_data_stream(addr, &options)
+                .await
+                .err()
+                .unwrap();
+            let message = format!("{:?}", err);
+            assert!(message.contains(
+                "Not connected within 500ms, timed out during first frame (tcp connect: "
+            ));
+            assert!(message.contains(", greeting: "));
+            assert!(message.contains(", PASS: "));
+            assert!(message.contains(", DATA: "));
+
+            server_future.await?;
+
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn test_first_frame_timeout() -> Result<()> {
+        async_std::task::block_on(async {
+            let listener = TcpListener::bind("127.0.0.1:0").await?;
+            let addr = listener.local_addr()?;
+
+            let server_future = async_std::task::spawn::<_, Result<()>>(async move {
+                for keep_open in [true, false] {
+                    let (stream, _) = listener.accept().await?;
+
+                    let mut hs = TcpServerHandshake::start(stream).await?;
+                    hs.receive_pass_command().await?;
+                    let mut stream = hs.receive_data_command().await?;
+
+                    stream
+                        .write_all(b"HTTP/1.1 400 Bad Request\r\n\r\n")
+                        .await?;
+
+                    if keep_open {
+                        let mut buf = [0u8; 16];
+                        while stream.read(&mut buf).await? > 0 {}
+                    }
+                }
+                Ok(())
+            });
+
+            let mut options = ConnectOptions::new();
+            options.set_first_frame_timeout(Some(Duration::from_millis(200)));
+
+            let err = connect_tcp_live_data_stream(addr, &options)
+                .await
+                .err()
+                .unwrap();
+            assert!(err.message().starts_with(
+                "Not a VBus data stream: no valid VBus frame within 200ms after the DATA command (28 bytes received), failed during first frame (tcp connect: "
+            ));
+            assert!(err.message().contains(", DATA: "));
+
+            let err = connect_tcp_live_data_stream(addr, &options)
+                .await
+                .err()
+                .unwrap();
+            assert!(err.message().starts_with(
+                "Not a VBus data stream: connection closed before receiving a valid VBus frame, failed during first frame (tcp connect: "
+            ));
+
+            server_future.await?;
+
+            Ok(())
+        })
+    }
+}
@@ -0,0 +1,489 @@
+use std::{
+    path::PathBuf,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use async_std::channel::Sender;
+
+use crate::{
+    connect::{try_connect_tcp_live_data_stream, ConnectOptions},
+    device_lease::DeviceLease,
+    error::{Error, Result},
+    live_data_stream::TcpLiveDataStream,
+    shutdown::ShutdownSignal,
+};
+
+/// Sent by a `ConnectionManager` for every connection attempt.
+#[derive(Debug, PartialEq)]
+pub enum ReconnectEvent {
+    /// The connection was established after the contained number of
+    /// failed attempts.
+    Connected {
+        /// The number of failed attempts before.
+        failed_attempts: usize,
+    },
+
+    /// An attempt failed and the next one is delayed.
+    Backoff {
+        /// The number of consecutive failed attempts.
+        attempt: usize,
+
+        /// The delay until the next attempt.
+        delay: Duration,
+
+        /// The reason of the failed attempt.
+        error: Error,
+    },
+
+    /// The service rejected the password too many times in a row, so no
+    /// further attempts are made until the `duration` passed.
+    CircuitOpen {
+        /// The number of consecutive rejected passwords.
+        auth_failures: usize,
+
+        /// The duration until the next attempt.
+        duration: Duration,
+
+        /// The reason of the last failed attempt.
+        error: Error,
+    },
+}
+
+/// Establishes a VBus-over-TCP connection, retrying without overloading
+/// the service.
+///
+/// Failed attempts are retried using a jittered exponential backoff: the
+/// n-th delay is chosen randomly between half and the full value of
+/// `initial_delay * 2^(n - 1)`, capped at `max_delay`. If the service
+/// rejects the password `max_auth_failures` times in a row, the circuit
+/// opens and the next attempt is only made after `circuit_open_duration`.
+/// If that single attempt is rejected again, the circuit opens again
+/// immediately.
+///
+/// Every attempt is reported to the optional `Sender` as a
+/// `ReconnectEvent`, so that applications can tell why no connection is
+/// established.
+///
+/// Devices like the DL2 only allow a single data connection. The manager
+/// therefore acquires a `DeviceLease` for its device before the first
+/// attempt and holds it until it is dropped, so that a second manager
+/// for the same device within this process fails to connect with a clear
+/// error instead of starving the first connection.
+///
+/// # Examples
+///
+/// ```no_run
+/// # fn main() -> async_resol_vbus::Result<()> { async_std::task::block_on(async {
+/// #
+/// use async_resol_vbus::{ConnectOptions, ConnectionManager, ReconnectEvent};
+///
+/// let mut options = ConnectOptions::new();
+/// options.set_via_tag(Some("d1234567890.vbus.io".into()));
+///
+/// let (sender, events) = async_std::channel::unbounded();
+///
+/// let mut manager = ConnectionManager::new("vbus.io:7053", options);
+/// manager.set_sender(Some(sender));
+///
+/// async_std::task::spawn(async move {
+///     while let Ok(event) = events.recv().await {
+///         if let ReconnectEvent::CircuitOpen { duration, .. } = event {
+///             println!("Password rejected, pausing reconnects for {:?}", duration);
+///         }
+///     }
+/// });
+///
+/// loop {
+///     let mut lds = manager.connect().await?;
+///     while let Some(data) = lds.receive_any_data(60000).await? {
+///         println!("{}", data.id_string());
+///     }
+/// }
+/// #
+/// # }) }
+/// ```
+#[derive(Debug)]
+pub struct ConnectionManager {
+    address: String,
+    options: ConnectOptions,
+    initial_delay: Duration,
+    max_delay: Duration,
+    max_auth_failures: usize,
+    circuit_open_duration: Duration,
+    sender: Option<Sender<ReconnectEvent>>,
+    device_key: Option<String>,
+    lock_dir: Option<PathBuf>,
+    lease: Option<DeviceLease>,
+    failed_attempts: usize,
+    auth_failures: usize,
+    rng_state: u64,
+}
+
+impl ConnectionManager {
+    /// Create a new `ConnectionManager` connecting to `address` (e.g.
+    /// `"192.168.5.217:7053"`) using `options`.
+    pub fn new(address: &str, options: ConnectOptions) -> ConnectionManager {
+        ConnectionManager {
+            address: address.to_string(),
+            options,
+            initial_delay: Duration::from_secs(1),
+            max_delay: Duration::from_secs(300),
+            max_auth_failures: 3,
+            circuit_open_duration: Duration::from_secs(1800),
+            sender: None,
+            device_key: None,
+            lock_dir: None,
+            lease: None,
+            failed_attempts: 0,
+            auth_failures: 0,
+            rng_state: 0,
+        }
+    }
+
+    /// Set the delay after the first failed attempt.
+    ///
+    /// Defaults to one second.
+    pub fn set_initial_delay(&mut self, initial_delay: Duration) {
+        self.initial_delay = initial_delay;
+    }
+
+    /// Set the maximum delay between two attempts.
+    ///
+    /// Defaults to five minutes.
+    pub fn set_max_delay(&mut self, max_delay: Duration) {
+        self.max_delay = max_delay;
+    }
+
+    /// Set the number of rejected passwords in a row that open the circuit.
+    ///
+    /// Defaults to `3`.
+    pub fn set_max_auth_failures(&mut self, max_auth_failures: usize) {
+        self.max_auth_failures = max_auth_failures;
+    }
+
+    /// Set the duration no attempts are made after the circuit opened.
+    ///
+    /// Defaults to 30 minutes.
+    pub fn set_circuit_open_duration(&mut self, circuit_open_duration: Duration) {
+        self.circuit_open_duration = circuit_open_duration;
+    }
+
+    /// Set the `Sender` that receives every `ReconnectEvent`.
+    ///
+    /// Defaults to `None`.
+    pub fn set_sender(&mut self, sender: Option<Sender<ReconnectEvent>>) {
+        self.sender = sender;
+    }
+
+    /// Set the key identifying the device for the `DeviceLease`, e.g. its
+    /// serial number.
+    ///
+    /// Defaults to `None`, which uses the address, followed by the via tag
+    /// if one is set.
+    pub fn set_device_key(&mut self, device_key: Option<String>) {
+        self.device_key = device_key;
+    }
+
+    /// Set the directory to place the lock file for the device in, see
+    /// `DeviceLease::acquire_with_lock_file`.
+    ///
+    /// Defaults to `None`, which only prevents duplicate connections
+    /// within this process.
+    pub fn set_lock_dir(&mut self, lock_dir: Option<PathBuf>) {
+        self.lock_dir = lock_dir;
+    }
+
+    /// Get the key identifying the device for the `DeviceLease`.
+    pub fn device_key(&self) -> String {
+        match (&self.device_key, self.options.via_tag()) {
+            (Some(device_key), _) => device_key.clone(),
+            (None, Some(via_tag)) => format!("{}/{}", self.address, via_tag),
+            (None, None) => self.address.clone(),
+        }
+    }
+
+    /// Get the `ConnectOptions`, e.g. to update the password after the
+    /// circuit opened.
+    pub fn options_mut(&mut self) -> &mut ConnectOptions {
+        &mut self.options
+    }
+
+    /// Check whether the circuit is open because of rejected passwords.
+    pub fn is_circuit_open(&self) -> bool {
+        self.max_auth_failures > 0 && self.auth_failures >= self.max_auth_failures
+    }
+
+    /// Forget all previously failed attempts and close the circuit.
+    pub fn reset(&mut self) {
+        self.failed_attempts = 0;
+        self.auth_failures = 0;
+    }
+
+    /// Connect to the service, retrying until a connection is established.
+    ///
+    /// The failed attempts are remembered across calls until a connection
+    /// is established, so calling `connect` again right after the previous
+    /// connection broke does not bypass the backoff.
+    ///
+    /// Fails immediately if another `ConnectionManager` or `DeviceLease`
+    /// within this process already holds the lease for the device, or if
+    /// another process holds its lock file (see `set_lock_dir`).
+    pub async fn connect(&mut self) -> Result<TcpLiveDataStream> {
+        if self.lease.is_none() {
+            let device_key = self.device_key();
+            let lease = match self.lock_dir {
+                Some(ref lock_dir) => DeviceLease::acquire_with_lock_file(&device_key, lock_dir)?,
+                None => DeviceLease::acquire(&device_key)?,
+            };
+            self.lease = Some(lease);
+        }
+
+        loop {
+            match try_connect_tcp_live_data_stream(self.address.as_str(), &self.options).await {
+                Ok(lds) => {
+                    let failed_attempts = self.failed_attempts;
+                    self.reset();
+                    self.send_event(ReconnectEvent::Connected { failed_attempts });
+                    return Ok(lds);
+                }
+                Err(failure) => {
+                    self.failed_attempts += 1;
+                    if failure.is_auth_failure {
+                        self.auth_failures += 1;
+                    } else {
+                        self.auth_failures = 0;
+                    }
+
+                    let (delay, event) = if self.is_circuit_open() {
+                        let duration = self.circuit_open_duration;
+                        let event = ReconnectEvent::CircuitOpen {
+                            auth_failures: self.auth_failures,
+                            duration,
+                            error: failure.error,
+                        };
+                        (duration, event)
+                    } else {
+                        let delay = self.next_delay();
+                        let event = ReconnectEvent::Backoff {
+                            attempt: self.failed_attempts,
+                            delay,
+                            error: failure.error,
+                        };
+                        (delay, event)
+                    };
+
+                    self.send_event(event);
+                    async_std::task::sleep(delay).await;
+                }
+            }
+        }
+    }
+
+    /// Like `connect`, but gives up once the shutdown is triggered.
+    ///
+    /// Returns `None` if the shutdown was triggered before a connection
+    /// was established. In that case the lease for the device is released,
+    /// so that the lock file is unlocked before the process exits. Calling
+    /// `ShutdownSignal::complete` is left to the component using the
+    /// connection.
+    pub async fn connect_until_shutdown(
+        &mut self,
+        signal: &ShutdownSignal,
+    ) -> Result<Option<TcpLiveDataStream>> {
+        match signal.run_until(self.connect()).await {
+            Some(result) => result.map(Some),
+            None => {
+                self.lease = None;
+                Ok(None)
+            }
+        }
+    }
+
+    fn send_event(&self, event: ReconnectEvent) {
+        if let Some(ref sender) = self.sender {
+            drop(sender.try_send(event));
+        }
+    }
+
+    /// Get the jittered delay after the current number of failed attempts.
+    fn next_delay(&mut self) -> Duration {
+        let exponent = self.failed_attempts.saturating_sub(1).min(31) as u32;
+        let base = self
+            .initial_delay
+            .checked_mul(1 << exponent)
+            .unwrap_or(self.max_delay)
+            .min(self.max_delay);
+
+        let half = base / 2;
+        let range = (base - half).as_micros() as u64;
+        if range == 0 {
+            return base;
+        }
+
+        if self.rng_state == 0 {
+            self.rng_state = SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|duration| duration.as_nanos() as u64)
+                .unwrap_or(0)
+                | 1;
+        }
+
+        // xorshift64
+        let mut x = self.rng_state;
+        x ^= x << 13;
+        x ^= x >> 7;
+        x ^= x << 17;
+        self.rng_state = x;
+
+        half + Duration::from_micros(x % (range + 1))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use async_std::net::TcpListener;
+
+    use crate::{
+        device_lease::DeviceLease, shutdown::Shutdown, tcp_server_handshake::TcpServerHandshake,
+    };
+
+    use super::*;
+
+    #[test]
+    fn test_next_delay() {
+        let mut manager = ConnectionManager::new("127.0.0.1:7053", ConnectOptions::new());
+        manager.set_initial_delay(Duration::from_millis(100));
+        manager.set_max_delay(Duration::from_millis(1000));
+
+        for (failed_attempts, min, max) in [
+            (1, 50, 100),
+            (2, 100, 200),
+            (3, 200, 400),
+            (4, 400, 800),
+            (5, 500, 1000),
+            (100, 500, 1000),
+        ] {
+            manager.failed_attempts = failed_attempts;
+            for _ in 0..10 {
+                let delay = manager.next_delay();
+                assert!(delay >= Duration::from_millis(min));
+                assert!(delay <= Duration::from_millis(max));
+            }
+        }
+    }
+
+    #[test]
+    fn test_connection_manager() -> Result<()> {
+        async_std::task::block_on(async {
+            let listener = TcpListener::bind("127.0.0.1:0").await?;
+            let addr = listener.local_addr()?;
+
+            let server_future = async_std::task::spawn::<_, Result<()>>(async move {
+                // "close" drops the connection after receiving the password,
+                // which must not count as a rejected password
+                for reply in ["reject", "close", "reject", "reject", "reject", "accept"] {
+                    let (stream, _) = listener.accept().await?;
+
+                    let mut hs = TcpServerHandshake::start(stream).await?;
+                    if reply == "close" {
+                        hs.receive_pass_command().await?;
+                        continue;
+                    }
+
+                    let accept = reply == "accept";
+                    let result = hs
+                        .receive_pass_command_and_verify_password(|password| async move {
+                            if accept {
+                                Ok(password)
+                            } else {
+                                Err("-ERROR: Wrong password\r\n")
+                            }
+                        })
+                        .await;
+                    if result.is_ok() {
+                        hs.receive_data_command().await?;
+                    }
+                }
+                Ok(())
+            });
+
+            let (sender, events) = async_std::channel::unbounded();
+
+            let mut manager = ConnectionManager::new(&addr.to_string(), ConnectOptions::new());
+            manager.set_initial_delay(Duration::from_millis(10));
+            manager.set_max_auth_failures(2);
+            manager.set_circuit_open_duration(Duration::from_millis(50));
+            manager.set_sender(Some(sender));
+
+            manager.connect().await?;
+            assert!(!manager.is_circuit_open());
+
+            let mut duplicate = ConnectionManager::new(&addr.to_string(), ConnectOptions::new());
+            assert_eq!(
+                Err(format!("Device {} is already connected within this process", addr).into()),
+                duplicate.connect().await.map(|_| ())
+            );
+
+            server_future.await?;
+
+            let mut summary = Vec::new();
+            while let Ok(event) = events.try_recv() {
+                summary.push(match event {
+                    ReconnectEvent::Backoff { attempt, .. } => format!("backoff {}", attempt),
+                    ReconnectEvent::CircuitOpen {
+                        auth_failures,
+                        duration,
+                        ..
+                    } => format!("open {} {:?}", auth_failures, duration),
+                    ReconnectEvent::Connected { failed_attempts } => {
+                        format!("connected {}", failed_attempts)
+                    }
+                });
+            }
+            assert_eq!(
+                vec![
+                    "backoff 1",
+                    "backoff 2",
+                    "backoff 3",
+                    "open 2 50ms",
+                    "open 3 50ms",
+                    "connected 5"
+                ],
+                summary
+            );
+
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn test_connect_until_shutdown() -> Result<()> {
+        async_std::task::block_on(async {
+            // reserve a port that refuses connections
+            let addr = TcpListener::bind("127.0.0.1:0").await?.local_addr()?;
+
+            let mut manager = ConnectionManager::new(&addr.to_string(), ConnectOptions::new());
+            manager.set_initial_delay(Duration::from_secs(60));
+
+            let shutdown = Shutdown::new();
+            let signal = shutdown.register("connection");
+
+            let shutdown_future = async_std::task::spawn(async move {
+                async_std::task::sleep(Duration::from_millis(100)).await;
+                shutdown.shutdown().await
+            });
+
+            assert!(manager.connect_until_shutdown(&signal).await?.is_none());
+            signal.complete(Ok(()));
+
+            assert_eq!(
+                vec![("connection".to_string(), Ok(()))],
+                shutdown_future.await
+            );
+
+            let _lease = DeviceLease::acquire(&addr.to_string())?;
+
+            Ok(())
+        })
+    }
+}
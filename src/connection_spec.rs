@@ -0,0 +1,706 @@
+use std::{fmt, str::FromStr};
+
+use async_std::io::{Read, Write};
+
+use crate::{
+    connect::{connect_tcp_live_data_stream_with, ConnectOptions},
+    error::{Error, Result},
+    live_data_stream::LiveDataStream,
+    serial_options::SerialOptions,
+};
+
+/// A `LiveDataStream` using boxed I/O, returned by `quick_connect`.
+pub type BoxedLiveDataStream =
+    LiveDataStream<Box<dyn Read + Unpin + Send>, Box<dyn Write + Unpin + Send>>;
+
+/// A VBus endpoint described by a connection URL.
+///
+/// The following URLs are supported:
+///
+/// - `vbus+tcp://[password@]host[:port][/][?channel=N&via=TAG]`: a
+///   VBus-over-TCP device. The port defaults to 7053, the password to
+///   `vbus`
+/// - `vbus+serial://PATH[?baud=N&max_framing_errors=N&profile=usb|uart&
+///   parity=none|even|odd&rts=on|off&dtr=on|off&reconnect=MS]`: a serial
+///   port, e.g. `vbus+serial:///dev/ttyUSB0` or `vbus+serial://COM3`. The
+///   port is configured for 8N1 at 9600 baud unless `baud` is given,
+///   `baud=auto` detects the baud rate. `reconnect` is the interval in
+///   milliseconds to reopen the port after it failed. See `SerialOptions`
+/// - `vbus+serial://auto`: the serial port most likely connected to a VBus
+///   adapter (or the UART for `profile=uart`), see
+///   `resolve_serial_port_name`
+///
+/// Configuring the serial port and `auto` require the `serial` feature.
+/// Without it the port is opened as a plain file and must already be set to
+/// 9600 baud, 8N1 in raw mode, e.g. using `stty -F /dev/ttyUSB0 9600 cs8
+/// -cstopb -parenb raw` or `mode COM3 BAUD=9600 PARITY=n DATA=8 STOP=1`.
+///
+/// Formatting a `ConnectionSpec` using `Display` returns a URL that parses
+/// into the same `ConnectionSpec`. If the `serde` feature is enabled, it is
+/// serialized as that URL string.
+///
+/// # Examples
+///
+/// ```
+/// # fn main() -> async_resol_vbus::Result<()> {
+/// use async_resol_vbus::ConnectionSpec;
+///
+/// let spec = "vbus+tcp://secret@192.168.5.217/?channel=1".parse::<ConnectionSpec>()?;
+/// assert_eq!(
+///     ConnectionSpec::Tcp {
+///         host: "192.168.5.217".into(),
+///         port: 7053,
+///         password: Some("secret".into()),
+///         via_tag: None,
+///         channel: Some(1),
+///     },
+///     spec
+/// );
+/// assert_eq!("vbus+tcp://secret@192.168.5.217/?channel=1", spec.to_string());
+/// # Ok(()) }
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub enum ConnectionSpec {
+    /// A VBus-over-TCP device.
+    Tcp {
+        /// The host name or IP address.
+        host: String,
+
+        /// The TCP port.
+        port: u16,
+
+        /// The password sent using the `PASS` command, `None` to use the
+        /// default password.
+        password: Option<String>,
+
+        /// The via tag sent using the `CONNECT` command.
+        via_tag: Option<String>,
+
+        /// The channel selected using the `CHANNEL` command.
+        channel: Option<u8>,
+    },
+
+    /// A serial port.
+    ///
+    /// The device is opened once and its I/O is performed on the blocking
+    /// thread pool of `async_std`. If the adapter is unplugged, reading
+    /// fails with an I/O error. Connecting again (e.g. using `connect`)
+    /// once it is plugged back in resumes the connection, resolving `auto`
+    /// again.
+    Serial {
+        /// The path or name of the serial device, e.g. `/dev/ttyUSB0` or
+        /// `COM3`, or `auto`.
+        path: String,
+
+        /// The settings used to open the port.
+        options: SerialOptions,
+    },
+}
+
+impl ConnectionSpec {
+    /// Parse a connection URL.
+    pub fn parse(url: &str) -> Result<ConnectionSpec> {
+        let (scheme, rest) = match url.split_once("://") {
+            Some(parts) => parts,
+            None => return Err(format!("Invalid connection URL {:?}", url).into()),
+        };
+
+        let (rest, query) = match rest.split_once('?') {
+            Some((rest, query)) => (rest, Some(query)),
+            None => (rest, None),
+        };
+
+        match scheme {
+            "vbus+tcp" => {
+                let authority = rest.trim_end_matches('/');
+                if authority.contains('/') {
+                    return Err(format!("Unexpected path in connection URL {:?}", url).into());
+                }
+
+                let (password, host_port) = match authority.rsplit_once('@') {
+                    Some((password, host_port)) => (Some(percent_decode(password)?), host_port),
+                    None => (None, authority),
+                };
+
+                let (host, port) = if let Some(host) = host_port.strip_prefix('[') {
+                    match host.split_once(']') {
+                        Some((host, "")) => (host, None),
+                        Some((host, port)) => (host, port.strip_prefix(':')),
+                        None => return Err(format!("Invalid host in {:?}", url).into()),
+                    }
+                } else {
+                    match host_port.rsplit_once(':') {
+                        Some((host, port)) => (host, Some(port)),
+                        None => (host_port, None),
+                    }
+                };
+
+                if host.is_empty() {
+                    return Err(format!("Missing host in connection URL {:?}", url).into());
+                }
+
+                let port = match port {
+                    Some(port) => port
+                        .parse::<u16>()
+                        .map_err(|_| format!("Invalid port in connection URL {:?}", url))?,
+                    None => 7053,
+                };
+
+                let mut via_tag = None;
+                let mut channel = None;
+                for (key, value) in query_pairs(query)? {
+                    match key.as_str() {
+                        "via" => via_tag = Some(value),
+                        "channel" => {
+                            channel = Some(value.parse::<u8>().map_err(|_| {
+                                format!("Invalid channel in connection URL {:?}", url)
+                            })?)
+                        }
+                        _ => {
+                            return Err(format!(
+                                "Unknown parameter {:?} in connection URL {:?}",
+                                key, url
+                            )
+                            .into())
+                        }
+                    }
+                }
+
+                Ok(ConnectionSpec::Tcp {
+                    host: host.to_string(),
+                    port,
+                    password,
+                    via_tag,
+                    channel,
+                })
+            }
+            "vbus+serial" => {
+                if rest.is_empty() {
+                    return Err(format!("Missing device path in connection URL {:?}", url).into());
+                }
+
+                let mut options = SerialOptions::new();
+                for (key, value) in query_pairs(query)? {
+                    if !options.set_url_param(&key, &value)? {
+                        return Err(format!(
+                            "Unknown parameter {:?} in connection URL {:?}",
+                            key, url
+                        )
+                        .into());
+                    }
+                }
+
+                Ok(ConnectionSpec::Serial {
+                    path: percent_decode(rest)?,
+                    options,
+                })
+            }
+            _ => Err(format!("Unsupported connection URL scheme {:?}", scheme).into()),
+        }
+    }
+
+    /// Get the `ConnectOptions` for a `ConnectionSpec::Tcp`.
+    ///
+    /// Returns `None` for other transports.
+    pub fn connect_options(&self) -> Option<ConnectOptions> {
+        self.merge_connect_options(ConnectOptions::new())
+    }
+
+    /// Apply the password, via tag and channel of a `ConnectionSpec::Tcp`
+    /// to `options`.
+    fn merge_connect_options(&self, mut options: ConnectOptions) -> Option<ConnectOptions> {
+        match self {
+            ConnectionSpec::Tcp {
+                password,
+                via_tag,
+                channel,
+                ..
+            } => {
+                if password.is_some() {
+                    options.set_password(password.clone());
+                }
+                options.set_via_tag(via_tag.clone());
+                options.set_channel(*channel);
+                Some(options)
+            }
+            ConnectionSpec::Serial { .. } => None,
+        }
+    }
+
+    /// Connect to the endpoint and return a `LiveDataStream`.
+    pub async fn connect(&self) -> Result<BoxedLiveDataStream> {
+        self.connect_with_options(&ConnectOptions::new()).await
+    }
+
+    /// Connect to the endpoint using `options` and return a
+    /// `LiveDataStream`.
+    ///
+    /// The password, via tag and channel of a `ConnectionSpec::Tcp`
+    /// override the ones of `options`. For serial ports only the options
+    /// passed on to the `LiveDataStream` (e.g. the self address and the
+    /// `WriteGuard`) are used.
+    pub async fn connect_with_options(
+        &self,
+        options: &ConnectOptions,
+    ) -> Result<BoxedLiveDataStream> {
+        match self {
+            ConnectionSpec::Tcp { host, port, .. } => {
+                let options = self
+                    .merge_connect_options(options.clone())
+                    .unwrap_or_default();
+
+                connect_tcp_live_data_stream_with((host.as_str(), *port), &options, |stream| {
+                    let reader: Box<dyn Read + Unpin + Send> = Box::new(stream.clone());
+                    let writer: Box<dyn Write + Unpin + Send> = Box::new(stream);
+                    (reader, writer)
+                })
+                .await
+            }
+            ConnectionSpec::Serial {
+                path,
+                options: serial_options,
+            } => {
+                let (reader, writer) = open_serial(path, serial_options).await?;
+                let mut lds = LiveDataStream::new(reader, writer, 0, options.self_address());
+                options.configure_live_data_stream(&mut lds);
+                Ok(lds)
+            }
+        }
+    }
+}
+
+impl fmt::Display for ConnectionSpec {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConnectionSpec::Tcp {
+                host,
+                port,
+                password,
+                via_tag,
+                channel,
+            } => {
+                write!(f, "vbus+tcp://")?;
+                if let Some(password) = password {
+                    write!(f, "{}@", percent_encode(password, ""))?;
+                }
+                if host.contains(':') {
+                    write!(f, "[{}]", host)?;
+                } else {
+                    write!(f, "{}", host)?;
+                }
+                if *port != 7053 {
+                    write!(f, ":{}", port)?;
+                }
+
+                let mut query = Vec::new();
+                if let Some(channel) = channel {
+                    query.push(format!("channel={}", channel));
+                }
+                if let Some(via_tag) = via_tag {
+                    query.push(format!("via={}", percent_encode(via_tag, "")));
+                }
+                if !query.is_empty() {
+                    write!(f, "/?{}", query.join("&"))?;
+                }
+                Ok(())
+            }
+            ConnectionSpec::Serial { path, options } => {
+                write!(f, "vbus+serial://{}", percent_encode(path, "/"))?;
+
+                let query = options.url_params();
+                if !query.is_empty() {
+                    write!(f, "?{}", query.join("&"))?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+impl FromStr for ConnectionSpec {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<ConnectionSpec> {
+        ConnectionSpec::parse(s)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl serde::Serialize for ConnectionSpec {
+    fn serialize<S: serde::Serializer>(
+        &self,
+        serializer: S,
+    ) -> std::result::Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for ConnectionSpec {
+    fn deserialize<D: serde::Deserializer<'de>>(
+        deserializer: D,
+    ) -> std::result::Result<ConnectionSpec, D::Error> {
+        let url = String::deserialize(deserializer)?;
+        ConnectionSpec::parse(&url).map_err(|err| serde::de::Error::custom(format!("{:?}", err)))
+    }
+}
+
+type BoxedIo = (Box<dyn Read + Unpin + Send>, Box<dyn Write + Unpin + Send>);
+
+/// Open and configure a serial port.
+#[cfg(feature = "serial")]
+async fn open_serial(path: &str, options: &SerialOptions) -> Result<BoxedIo> {
+    let (reader, writer) = crate::serial_transport::open_serial_port(path, options).await?;
+    Ok((Box::new(reader), Box::new(writer)))
+}
+
+/// Open an already configured serial port as a file.
+#[cfg(not(feature = "serial"))]
+async fn open_serial(path: &str, options: &SerialOptions) -> Result<BoxedIo> {
+    if path == "auto" {
+        return Err("Picking the serial port automatically requires the `serial` feature".into());
+    }
+    if *options != SerialOptions::default() {
+        return Err("Configuring the serial port requires the `serial` feature".into());
+    }
+
+    let path = if cfg!(windows) {
+        windows_device_path(path)
+    } else {
+        path.to_string()
+    };
+    let file = async_std::task::spawn_blocking(move || {
+        std::fs::OpenOptions::new()
+            .read(true)
+            .write(true)
+            .open(path)
+    })
+    .await?;
+    let reader = async_std::fs::File::from(file.try_clone()?);
+    let writer = async_std::fs::File::from(file);
+    Ok((Box::new(reader), Box::new(writer)))
+}
+
+/// Prefix COM port names with the `\\.\` device namespace, which is
+/// required to open `COM10` and above. (The `serialport` crate does this
+/// itself.)
+#[cfg_attr(feature = "serial", allow(dead_code))]
+fn windows_device_path(path: &str) -> String {
+    let is_com_port = path
+        .strip_prefix("COM")
+        .is_some_and(|number| !number.is_empty() && number.bytes().all(|b| b.is_ascii_digit()));
+    if is_com_port {
+        format!(r"\\.\{}", path)
+    } else {
+        path.to_string()
+    }
+}
+
+fn query_pairs(query: Option<&str>) -> Result<Vec<(String, String)>> {
+    let mut pairs = Vec::new();
+    for pair in query
+        .unwrap_or("")
+        .split('&')
+        .filter(|pair| !pair.is_empty())
+    {
+        let (key, value) = pair.split_once('=').unwrap_or((pair, ""));
+        pairs.push((percent_decode(key)?, percent_decode(value)?));
+    }
+    Ok(pairs)
+}
+
+fn percent_decode(s: &str) -> Result<String> {
+    let bytes = s.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut idx = 0;
+    while idx < bytes.len() {
+        if bytes[idx] == b'%' {
+            let byte = s
+                .get(idx + 1..idx + 3)
+                .and_then(|hex| u8::from_str_radix(hex, 16).ok())
+                .ok_or_else(|| format!("Invalid percent-encoding in {:?}", s))?;
+            decoded.push(byte);
+            idx += 3;
+        } else {
+            decoded.push(bytes[idx]);
+            idx += 1;
+        }
+    }
+    String::from_utf8(decoded).map_err(|_| format!("Invalid UTF-8 in {:?}", s).into())
+}
+
+/// Percent-encode all characters except the unreserved ones and `keep`.
+fn percent_encode(s: &str, keep: &str) -> String {
+    let mut encoded = String::with_capacity(s.len());
+    for byte in s.bytes() {
+        if byte.is_ascii_alphanumeric()
+            || b"-._~".contains(&byte)
+            || keep.as_bytes().contains(&byte)
+        {
+            encoded.push(char::from(byte));
+        } else {
+            encoded.push_str(&format!("%{:02X}", byte));
+        }
+    }
+    encoded
+}
+
+/// Parse a connection URL, connect to the VBus and return a
+/// `LiveDataStream`.
+///
+/// See `ConnectionSpec` for the supported URLs. This allows small tools to
+/// support both transports using a single command line argument.
+///
+/// # Examples
+///
+/// ```no_run
+/// # fn main() -> async_resol_vbus::Result<()> { async_std::task::block_on(async {
+/// #
+/// use async_resol_vbus::quick_connect;
+///
+/// let url = std::env::args().nth(1).unwrap_or("vbus+tcp://192.168.5.217".into());
+/// let mut lds = quick_connect(&url).await?;
+///
+/// while let Some(data) = lds.receive_any_data(60000).await? {
+///     println!("{}", data.id_string());
+/// }
+/// #
+/// # Ok(()) }) }
+/// ```
+pub async fn quick_connect(url: &str) -> Result<BoxedLiveDataStream> {
+    ConnectionSpec::parse(url)?.connect().await
+}
+
+/// Like `quick_connect`, but uses `options`, e.g. to set a `WriteGuard`.
+///
+/// See `ConnectionSpec::connect_with_options`.
+pub async fn quick_connect_with_options(
+    url: &str,
+    options: &ConnectOptions,
+) -> Result<BoxedLiveDataStream> {
+    ConnectionSpec::parse(url)?
+        .connect_with_options(options)
+        .await
+}
+
+#[cfg(test)]
+mod tests {
+    use async_std::{net::TcpListener, prelude::*};
+
+    use crate::{tcp_server_handshake::TcpServerHandshake, test_utils::extend_from_datagram};
+
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(
+            ConnectionSpec::Tcp {
+                host: "192.168.5.217".to_string(),
+                port: 7053,
+                password: None,
+                via_tag: None,
+                channel: None,
+            },
+            ConnectionSpec::parse("vbus+tcp://192.168.5.217").unwrap()
+        );
+        assert_eq!(
+            ConnectionSpec::Tcp {
+                host: "::1".to_string(),
+                port: 7054,
+                password: Some("p@ss".to_string()),
+                via_tag: Some("d01234567890.vbus.io".to_string()),
+                channel: Some(1),
+            },
+            ConnectionSpec::parse(
+                "vbus+tcp://p%40ss@[::1]:7054/?channel=1&via=d01234567890.vbus.io"
+            )
+            .unwrap()
+        );
+        assert_eq!(
+            ConnectionSpec::Serial {
+                path: "/dev/ttyUSB0".to_string(),
+                options: SerialOptions::new(),
+            },
+            ConnectionSpec::parse("vbus+serial:///dev/ttyUSB0").unwrap()
+        );
+        assert_eq!(
+            ConnectionSpec::Serial {
+                path: "COM3".to_string(),
+                options: SerialOptions::new(),
+            },
+            ConnectionSpec::parse("vbus+serial://COM3").unwrap()
+        );
+
+        let mut options = SerialOptions::new();
+        options.set_baud_rate(None);
+        options.set_max_framing_errors(100);
+        assert_eq!(
+            ConnectionSpec::Serial {
+                path: "auto".to_string(),
+                options,
+            },
+            ConnectionSpec::parse("vbus+serial://auto?baud=auto&max_framing_errors=100").unwrap()
+        );
+
+        assert_eq!(
+            Err("Unsupported connection URL scheme \"http\"".into()),
+            ConnectionSpec::parse("http://192.168.5.217")
+        );
+        assert_eq!(
+            Err("Invalid port in connection URL \"vbus+tcp://host:x\"".into()),
+            ConnectionSpec::parse("vbus+tcp://host:x")
+        );
+        assert_eq!(
+            Err("Unknown parameter \"speed\" in connection URL \"vbus+serial:///dev/ttyS0?speed=9600\"".into()),
+            ConnectionSpec::parse("vbus+serial:///dev/ttyS0?speed=9600")
+        );
+        assert_eq!(
+            Err("Invalid baud rate \"fast\"".into()),
+            ConnectionSpec::parse("vbus+serial:///dev/ttyS0?baud=fast")
+        );
+        assert_eq!(
+            Err("Missing device path in connection URL \"vbus+serial://\"".into()),
+            ConnectionSpec::parse("vbus+serial://")
+        );
+    }
+
+    #[test]
+    fn test_format() {
+        for url in [
+            "vbus+tcp://192.168.5.217",
+            "vbus+tcp://p%40ss@[::1]:7054/?channel=1&via=d01234567890.vbus.io",
+            "vbus+serial:///dev/ttyUSB0",
+            "vbus+serial:///tmp/my%20device",
+            "vbus+serial://COM3",
+            "vbus+serial://COM3?baud=19200",
+            "vbus+serial://auto?baud=auto&max_framing_errors=100",
+            "vbus+serial://auto?profile=uart&parity=even&rts=on&dtr=off&reconnect=2000",
+        ] {
+            let spec = ConnectionSpec::parse(url).unwrap();
+            assert_eq!(url, spec.to_string());
+            assert_eq!(Ok(spec.clone()), spec.to_string().parse());
+        }
+    }
+
+    #[test]
+    fn test_windows_device_path() {
+        assert_eq!(r"\\.\COM3", windows_device_path("COM3"));
+        assert_eq!(r"\\.\COM10", windows_device_path("COM10"));
+        assert_eq!(r"\\.\COM10", windows_device_path(r"\\.\COM10"));
+        assert_eq!("COMX", windows_device_path("COMX"));
+        assert_eq!("/dev/ttyUSB0", windows_device_path("/dev/ttyUSB0"));
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_serde() {
+        let spec = ConnectionSpec::Tcp {
+            host: "192.168.5.217".to_string(),
+            port: 7053,
+            password: Some("secret".to_string()),
+            via_tag: None,
+            channel: Some(2),
+        };
+
+        let json = serde_json::to_string(&spec).unwrap();
+        assert_eq!("\"vbus+tcp://secret@192.168.5.217/?channel=2\"", json);
+        assert_eq!(spec, serde_json::from_str(&json).unwrap());
+
+        assert!(serde_json::from_str::<ConnectionSpec>("\"http://host\"").is_err());
+    }
+
+    #[test]
+    fn test_quick_connect_tcp() -> Result<()> {
+        async_std::task::block_on(async {
+            let listener = TcpListener::bind("127.0.0.1:0").await?;
+            let addr = listener.local_addr()?;
+
+            let server_future = async_std::task::spawn::<_, Result<(String, u8)>>(async move {
+                let (stream, _) = listener.accept().await?;
+
+                let mut hs = TcpServerHandshake::start(stream).await?;
+                let password = hs.receive_pass_command().await?;
+                let channel = hs.receive_channel_command().await?;
+                let mut stream = hs.receive_data_command().await?;
+
+                let mut bytes = Vec::new();
+                extend_from_datagram(&mut bytes, 0x0020, 0x7E11, 0x0100, 0x1234, 0x5678);
+                stream.write_all(&bytes).await?;
+
+                Ok((password, channel))
+            });
+
+            let url = format!("vbus+tcp://secret@{}/?channel=1", addr);
+            let mut lds = quick_connect(&url).await?;
+
+            let data = lds.receive_any_data(1000).await?.unwrap();
+            assert_eq!(1, data.as_ref().channel);
+            assert_eq!(0x5678, data.as_datagram().param32);
+
+            let (password, channel) = server_future.await?;
+            assert_eq!("secret", password);
+            assert_eq!(1, channel);
+
+            Ok(())
+        })
+    }
+
+    #[cfg(not(feature = "serial"))]
+    #[test]
+    fn test_quick_connect_serial() -> Result<()> {
+        async_std::task::block_on(async {
+            let path = std::env::temp_dir().join(format!(
+                "async-resol-vbus-quick-connect-{}",
+                std::process::id()
+            ));
+
+            let mut bytes = Vec::new();
+            extend_from_datagram(&mut bytes, 0x0020, 0x7E11, 0x0100, 0x1234, 0x5678);
+            async_std::fs::write(&path, &bytes).await?;
+
+            let url = format!("vbus+serial://{}", path.display());
+            let mut lds = quick_connect(&url).await?;
+
+            let data = lds.receive_any_data(1000).await?.unwrap();
+            assert_eq!(0x5678, data.as_datagram().param32);
+
+            drop(lds);
+            async_std::fs::remove_file(&path).await?;
+
+            assert_eq!(
+                Err("Configuring the serial port requires the `serial` feature".into()),
+                quick_connect(&format!("{}?baud=auto", url))
+                    .await
+                    .map(|_| ())
+            );
+
+            Ok(())
+        })
+    }
+
+    #[cfg(all(unix, feature = "serial"))]
+    #[test]
+    fn test_quick_connect_serial() -> Result<()> {
+        use std::io::Write as _;
+
+        use serialport::SerialPort;
+
+        async_std::task::block_on(async {
+            let (mut master, slave) = serialport::TTYPort::pair()?;
+            let path = slave.name().unwrap();
+            drop(slave);
+
+            let url = format!("vbus+serial://{}?baud=19200", path);
+            let mut lds = quick_connect(&url).await?;
+
+            let mut bytes = Vec::new();
+            extend_from_datagram(&mut bytes, 0x0020, 0x7E11, 0x0100, 0x1234, 0x5678);
+            master.write_all(&bytes)?;
+
+            let data = lds.receive_any_data(1000).await?.unwrap();
+            assert_eq!(0x5678, data.as_datagram().param32);
+
+            Ok(())
+        })
+    }
+}
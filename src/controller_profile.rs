@@ -0,0 +1,312 @@
+use std::path::Path;
+
+use crate::{
+    error::Result,
+    json::{parse_json, JsonValue},
+    relay_control::RelayProfile,
+};
+
+/// A value known to a `ControllerProfile`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ProfileValue {
+    /// The value ID, e.g. `Relais1Handbetrieb`.
+    pub id: String,
+
+    /// The index of the value.
+    pub index: i16,
+
+    /// The factor to convert the raw value into its unit.
+    pub factor: f64,
+}
+
+/// Known indices, scaling and capabilities of a specific controller model.
+///
+/// A profile applies to the controller with the VBus `address` and, if
+/// set, a specific `changeset` (the version of the controller's parameter
+/// layout). Profiles are used by the high-level methods of
+/// `ControllerSession` (e.g. `get_value_by_id`), so that applications do not
+/// have to hard-code value indices.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ControllerProfile {
+    /// The name of the controller model.
+    pub name: String,
+
+    /// The VBus address of the controller model.
+    pub address: u16,
+
+    /// The changeset this profile applies to, `None` for all changesets.
+    pub changeset: Option<i32>,
+
+    /// The known values.
+    pub values: Vec<ProfileValue>,
+
+    /// The relays of the controller.
+    pub relays: RelayProfile,
+
+    /// The capabilities of the controller, e.g. `bulkValues`.
+    pub capabilities: Vec<String>,
+}
+
+impl ControllerProfile {
+    /// Create a new `ControllerProfile` without any values, relays or
+    /// capabilities.
+    pub fn new(name: &str, address: u16) -> ControllerProfile {
+        ControllerProfile {
+            name: name.to_string(),
+            address,
+            changeset: None,
+            values: Vec::new(),
+            relays: RelayProfile::new(),
+            capabilities: Vec::new(),
+        }
+    }
+
+    /// Get a known value by its ID.
+    pub fn value(&self, id: &str) -> Option<&ProfileValue> {
+        self.values.iter().find(|value| value.id == id)
+    }
+
+    /// Check whether the controller has a capability.
+    pub fn has_capability(&self, capability: &str) -> bool {
+        self.capabilities.iter().any(|c| c == capability)
+    }
+}
+
+/// A collection of `ControllerProfile`s, loadable from a JSON file.
+///
+/// The file contains an object with a `profiles` array:
+///
+/// ```json
+/// {"profiles": [{
+///     "name": "DeltaSol MX",
+///     "address": 32273,
+///     "changeset": 1234,
+///     "capabilities": ["bulkValues"],
+///     "values": [{"id": "Relais1Handbetrieb", "index": 4096, "factor": 1}],
+///     "relays": {"modeValues": [0, 1, 2], "relays": [{"modeIndex": 4096, "stateIndex": 512}]}
+/// }]}
+/// ```
+///
+/// Only `name` and `address` are required.
+///
+/// # Examples
+///
+/// ```no_run
+/// # fn main() -> async_resol_vbus::Result<()> { async_std::task::block_on(async {
+/// #
+/// use async_std::net::TcpStream;
+///
+/// use async_resol_vbus::{ControllerProfileRegistry, LiveDataStream};
+///
+/// let registry = ControllerProfileRegistry::load("profiles.json").await?;
+///
+/// let stream = TcpStream::connect("192.168.5.217:7053").await?;
+/// // ... perform handshake ...
+/// let mut lds = LiveDataStream::from_tcp_stream(stream, 0, 0x0020);
+///
+/// let mut session = lds.acquire_bus(0x7E11).await?;
+/// if let Some(profile) = session.select_profile(&registry).await? {
+///     let value = session.get_value_by_id(profile, "Relais1Handbetrieb").await?;
+///     println!("{}: {:?}", profile.name, value);
+/// }
+/// session.release().await?;
+/// #
+/// # Ok(()) }) }
+/// ```
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ControllerProfileRegistry {
+    profiles: Vec<ControllerProfile>,
+}
+
+impl ControllerProfileRegistry {
+    /// Create a new empty `ControllerProfileRegistry`.
+    pub fn new() -> ControllerProfileRegistry {
+        ControllerProfileRegistry::default()
+    }
+
+    /// Parse a `ControllerProfileRegistry` from its JSON representation.
+    pub fn parse(content: &str) -> Result<ControllerProfileRegistry> {
+        let value = parse_json(content)?;
+
+        let profiles = match value.get("profiles").and_then(|v| v.as_array()) {
+            Some(profiles) => profiles,
+            None => return Err("Controller profiles are missing the \"profiles\" array".into()),
+        };
+
+        let profiles = profiles
+            .iter()
+            .map(profile_from_json)
+            .collect::<Result<Vec<_>>>()?;
+
+        Ok(ControllerProfileRegistry { profiles })
+    }
+
+    /// Load a `ControllerProfileRegistry` from a JSON file.
+    pub async fn load<P: AsRef<Path>>(path: P) -> Result<ControllerProfileRegistry> {
+        let content = async_std::fs::read_to_string(path.as_ref()).await?;
+        ControllerProfileRegistry::parse(&content)
+    }
+
+    /// Add a profile.
+    pub fn add(&mut self, profile: ControllerProfile) {
+        self.profiles.push(profile);
+    }
+
+    /// Get all profiles.
+    pub fn profiles(&self) -> &[ControllerProfile] {
+        &self.profiles
+    }
+
+    /// Find the profile for a controller.
+    ///
+    /// Profiles for the specific `changeset` are preferred over profiles
+    /// applying to all changesets.
+    pub fn find(&self, address: u16, changeset: Option<i32>) -> Option<&ControllerProfile> {
+        let mut candidates = self
+            .profiles
+            .iter()
+            .filter(|profile| profile.address == address);
+
+        candidates
+            .clone()
+            .find(|profile| changeset.is_some() && profile.changeset == changeset)
+            .or_else(|| candidates.find(|profile| profile.changeset.is_none()))
+    }
+}
+
+fn get_number<T: TryFrom<i64>>(value: &JsonValue, key: &str) -> Result<Option<T>> {
+    match value.get(key) {
+        Some(member) => match member.as_f64().and_then(|n| T::try_from(n as i64).ok()) {
+            Some(n) => Ok(Some(n)),
+            None => Err(format!("Invalid {:?} in controller profile", key).into()),
+        },
+        None => Ok(None),
+    }
+}
+
+fn profile_from_json(value: &JsonValue) -> Result<ControllerProfile> {
+    let name = value
+        .get("name")
+        .and_then(|v| v.as_str())
+        .ok_or("Controller profile is missing the name")?;
+    let address =
+        get_number(value, "address")?.ok_or("Controller profile is missing the address")?;
+
+    let mut profile = ControllerProfile::new(name, address);
+    profile.changeset = get_number(value, "changeset")?;
+
+    if let Some(capabilities) = value.get("capabilities").and_then(|v| v.as_array()) {
+        profile.capabilities = capabilities
+            .iter()
+            .filter_map(|c| c.as_str())
+            .map(|c| c.to_string())
+            .collect();
+    }
+
+    if let Some(values) = value.get("values").and_then(|v| v.as_array()) {
+        for value in values {
+            let id = value
+                .get("id")
+                .and_then(|v| v.as_str())
+                .ok_or("Controller profile value is missing the ID")?;
+            let index = get_number(value, "index")?
+                .ok_or("Controller profile value is missing the index")?;
+            let factor = value.get("factor").and_then(|v| v.as_f64()).unwrap_or(1.0);
+
+            profile.values.push(ProfileValue {
+                id: id.to_string(),
+                index,
+                factor,
+            });
+        }
+    }
+
+    if let Some(relays) = value.get("relays") {
+        if let Some(mode_values) = relays.get("modeValues").and_then(|v| v.as_array()) {
+            match mode_values
+                .iter()
+                .map(|v| v.as_f64().map(|n| n as i32))
+                .collect::<Option<Vec<_>>>()
+                .as_deref()
+            {
+                Some(&[off, on, auto]) => profile.relays.set_mode_values(off, on, auto),
+                _ => return Err("Invalid \"modeValues\" in controller profile".into()),
+            }
+        }
+
+        for relay in relays
+            .get("relays")
+            .and_then(|v| v.as_array())
+            .unwrap_or(&[])
+        {
+            let mode_index = get_number(relay, "modeIndex")?;
+            let state_index = get_number(relay, "stateIndex")?;
+            match (mode_index, state_index) {
+                (Some(mode_index), Some(state_index)) => {
+                    profile.relays.add_relay(mode_index, state_index)
+                }
+                _ => return Err("Controller profile relay is missing its indices".into()),
+            }
+        }
+    }
+
+    Ok(profile)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PROFILES: &str = r#"{"profiles": [
+        {"name": "Generic", "address": 32273},
+        {
+            "name": "Changeset 1234",
+            "address": 32273,
+            "changeset": 1234,
+            "capabilities": ["bulkValues"],
+            "values": [{"id": "Temperature", "index": 4096, "factor": 0.1}],
+            "relays": {"modeValues": [4, 5, 6], "relays": [{"modeIndex": 4097, "stateIndex": 512}]}
+        }
+    ]}"#;
+
+    #[test]
+    fn test_registry() -> Result<()> {
+        let registry = ControllerProfileRegistry::parse(PROFILES)?;
+        assert_eq!(2, registry.profiles().len());
+
+        let profile = registry.find(0x7E11, Some(1234)).unwrap();
+        assert_eq!("Changeset 1234", profile.name);
+        assert!(profile.has_capability("bulkValues"));
+        assert!(!profile.has_capability("relays"));
+        assert_eq!(
+            Some(&ProfileValue {
+                id: "Temperature".to_string(),
+                index: 0x1000,
+                factor: 0.1,
+            }),
+            profile.value("Temperature")
+        );
+        assert_eq!(Ok(0x1001), profile.relays.mode_index(1));
+        assert_eq!(
+            5,
+            profile
+                .relays
+                .mode_value(crate::relay_control::RelayMode::On)
+        );
+
+        assert_eq!("Generic", registry.find(0x7E11, Some(1)).unwrap().name);
+        assert_eq!("Generic", registry.find(0x7E11, None).unwrap().name);
+        assert_eq!(None, registry.find(0x7E21, Some(1234)));
+
+        assert_eq!(
+            Err("Controller profile is missing the address".into()),
+            ControllerProfileRegistry::parse(r#"{"profiles": [{"name": "X"}]}"#)
+        );
+        assert_eq!(
+            Err("Invalid \"address\" in controller profile".into()),
+            ControllerProfileRegistry::parse(r#"{"profiles": [{"name": "X", "address": -1}]}"#)
+        );
+
+        Ok(())
+    }
+}
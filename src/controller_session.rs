@@ -0,0 +1,840 @@
+use std::marker::Unpin;
+
+use async_std::io::{Read, Write};
+
+use resol_vbus::{Data, Datagram};
+
+use crate::{
+    controller_profile::{ControllerProfile, ControllerProfileRegistry, ProfileValue},
+    error::Result,
+    live_data_stream::{AppliedParameters, LiveDataStream, ValueIndexLookup, VerifiedWrite},
+    relay_control::{RelayMode, RelayProfile},
+    schedule::{schedule_from_values, schedule_to_values, ScheduleEntry},
+    value_id_hash::value_id_hash_by_id,
+};
+
+fn string_value_parts(value: &str, len: usize) -> Result<Vec<i32>> {
+    let bytes = value.as_bytes();
+    if bytes.len() > len {
+        return Err(format!("String value {:?} exceeds {} bytes", value, len).into());
+    }
+
+    let mut buf = bytes.to_vec();
+    buf.resize(len.div_ceil(4) * 4, 0);
+
+    Ok(buf
+        .chunks(4)
+        .map(|chunk| i32::from_le_bytes([chunk[0], chunk[1], chunk[2], chunk[3]]))
+        .collect())
+}
+
+fn profile_value<'p>(profile: &'p ControllerProfile, id: &str) -> Result<&'p ProfileValue> {
+    match profile.value(id) {
+        Some(value) => Ok(value),
+        None => Err(format!("Unknown value ID {:?} in profile {:?}", id, profile.name).into()),
+    }
+}
+
+/// A guard object representing VBus control over a single VBus device.
+///
+/// A `ControllerSession` is created by `LiveDataStream::acquire_bus`. It
+/// provides the value-related methods of the `LiveDataStream` without the
+/// need to pass the device's address to every call.
+///
+/// The bus is given back to the VBus master by calling `release`. If the
+/// session is dropped without being released (e.g. by an early return
+/// using `?`), the release datagram is written on a best-effort basis
+/// without waiting. If the writer is not ready to accept it, the release
+/// is sent by the next operation performed on the underlying
+/// `LiveDataStream` or by `LiveDataStream::close`, so dropping the
+/// `LiveDataStream` right afterwards can still leave the device waiting
+/// until its bus timeout elapses. `LiveDataStream::with_bus` awaits the
+/// release in any case.
+#[derive(Debug)]
+pub struct ControllerSession<'a, R: Read + Unpin, W: Write + Unpin> {
+    stream: &'a mut LiveDataStream<R, W>,
+    address: u16,
+    released: bool,
+}
+
+impl<'a, R: Read + Unpin, W: Write + Unpin> ControllerSession<'a, R, W> {
+    pub(crate) fn new(
+        stream: &'a mut LiveDataStream<R, W>,
+        address: u16,
+    ) -> ControllerSession<'a, R, W> {
+        ControllerSession {
+            stream,
+            address,
+            released: false,
+        }
+    }
+
+    /// Get the VBus address of the device that offered bus control.
+    pub fn address(&self) -> u16 {
+        self.address
+    }
+
+    /// Get a mutable reference to the underlying `LiveDataStream`.
+    pub fn stream_mut(&mut self) -> &mut LiveDataStream<R, W> {
+        self.stream
+    }
+
+    /// Get a value by its index.
+    pub async fn get_value_by_index(
+        &mut self,
+        index: i16,
+        subindex: u8,
+    ) -> Result<Option<Datagram>> {
+        self.stream
+            .get_value_by_index(self.address, index, subindex)
+            .await
+    }
+
+    /// Set a value by its index.
+    pub async fn set_value_by_index(
+        &mut self,
+        index: i16,
+        subindex: u8,
+        value: i32,
+    ) -> Result<Option<Datagram>> {
+        self.stream
+            .set_value_by_index(self.address, index, subindex, value)
+            .await
+    }
+
+    /// Set a value by its index and verify it by reading it back.
+    ///
+    /// See `LiveDataStream::set_value_by_index_verified` for details.
+    pub async fn set_value_by_index_verified(
+        &mut self,
+        index: i16,
+        subindex: u8,
+        value: i32,
+        tolerance: i32,
+    ) -> Result<VerifiedWrite> {
+        self.stream
+            .set_value_by_index_verified(self.address, index, subindex, value, tolerance)
+            .await
+    }
+
+    /// Get a value's ID hash by its index.
+    pub async fn get_value_id_hash_by_index(&mut self, index: i16) -> Result<Option<Datagram>> {
+        self.stream
+            .get_value_id_hash_by_index(self.address, index)
+            .await
+    }
+
+    /// Get a value's index by its ID hash.
+    pub async fn get_value_index_by_id_hash(&mut self, id_hash: i32) -> Result<ValueIndexLookup> {
+        self.stream
+            .get_value_index_by_id_hash(self.address, id_hash)
+            .await
+    }
+
+    /// Get a value's index by its value ID.
+    ///
+    /// The ID hash of `id` is calculated using `value_id_hash_by_id` and
+    /// looked up on the device. Returns `None` if the device does not know
+    /// the value ID.
+    pub async fn get_value_index_by_id(&mut self, id: &str) -> Result<Option<i16>> {
+        let id_hash = value_id_hash_by_id(id);
+        let lookup = self.get_value_index_by_id_hash(id_hash).await?;
+
+        if let ValueIndexLookup::ResyncRequired { .. } = lookup {
+            self.get_value_by_index(0, 0).await?;
+        }
+
+        Ok(lookup.index())
+    }
+
+    /// Get the indices of the known value IDs `ids`.
+    ///
+    /// VBus devices do not transfer the names of their values, so this
+    /// builds an index to value ID table from a list of candidate IDs
+    /// (e.g. taken from a `ControllerProfile` or a parameter file) using
+    /// one `get_value_index_by_id` request per ID. The `(index, id)` pairs
+    /// are returned sorted by index, IDs unknown to the device are
+    /// omitted.
+    pub async fn get_value_id_table<'i>(&mut self, ids: &[&'i str]) -> Result<Vec<(i16, &'i str)>> {
+        let mut table = Vec::new();
+        for id in ids {
+            if let Some(index) = self.get_value_index_by_id(id).await? {
+                table.push((index, *id));
+            }
+        }
+        table.sort_by_key(|(index, _)| *index);
+
+        Ok(table)
+    }
+
+    /// Get the capabilities (part 1) from the VBus device.
+    pub async fn get_caps1(&mut self) -> Result<Option<Datagram>> {
+        self.stream.get_caps1(self.address).await
+    }
+
+    /// Begin a bulk value transaction.
+    pub async fn begin_bulk_value_transaction(
+        &mut self,
+        tx_timeout: i32,
+    ) -> Result<Option<Datagram>> {
+        self.stream
+            .begin_bulk_value_transaction(self.address, tx_timeout)
+            .await
+    }
+
+    /// Commit a bulk value transaction.
+    pub async fn commit_bulk_value_transaction(&mut self) -> Result<Option<Datagram>> {
+        self.stream
+            .commit_bulk_value_transaction(self.address)
+            .await
+    }
+
+    /// Rollback a bulk value transaction.
+    pub async fn rollback_bulk_value_transaction(&mut self) -> Result<Option<Datagram>> {
+        self.stream
+            .rollback_bulk_value_transaction(self.address)
+            .await
+    }
+
+    /// Set a value by its index while inside a bulk value transaction.
+    pub async fn set_bulk_value_by_index(
+        &mut self,
+        index: i16,
+        subindex: u8,
+        value: i32,
+    ) -> Result<Option<Datagram>> {
+        self.stream
+            .set_bulk_value_by_index(self.address, index, subindex, value)
+            .await
+    }
+
+    /// Write multiple values within a bulk value transaction.
+    ///
+    /// See `LiveDataStream::apply_parameters` for details.
+    pub async fn apply_parameters(
+        &mut self,
+        values: &[(i16, i32)],
+        tx_timeout: i32,
+    ) -> Result<AppliedParameters> {
+        self.stream
+            .apply_parameters(self.address, values, tx_timeout)
+            .await
+    }
+
+    /// Get a string value spread over multiple indices.
+    ///
+    /// Starting at `index`, every index holds four bytes of the string in
+    /// its 32 bit value (little-endian). The string occupies `len` bytes
+    /// and ends at the first NUL byte. Returns `None` if the device did
+    /// not reply to one of the parts.
+    pub async fn get_string_value(&mut self, index: i16, len: usize) -> Result<Option<String>> {
+        let mut bytes = Vec::with_capacity(len.div_ceil(4) * 4);
+        for part in 0..len.div_ceil(4) {
+            let part_index = index.wrapping_add(part as i16);
+            match self.get_value_by_index(part_index, 0).await? {
+                Some(dgram) => bytes.extend_from_slice(&dgram.param32.to_le_bytes()),
+                None => return Ok(None),
+            }
+        }
+
+        bytes.truncate(len);
+        if let Some(end) = bytes.iter().position(|&b| b == 0) {
+            bytes.truncate(end);
+        }
+
+        Ok(Some(std::str::from_utf8(&bytes)?.to_string()))
+    }
+
+    /// Set a string value spread over multiple indices.
+    ///
+    /// See `get_string_value` for the layout of the parts. Shorter strings
+    /// are padded with NUL bytes. Returns whether the device acknowledged
+    /// all parts, stopping at the first part that was not acknowledged.
+    pub async fn set_string_value(&mut self, index: i16, len: usize, value: &str) -> Result<bool> {
+        let parts = string_value_parts(value, len)?;
+        for (part, part_value) in parts.into_iter().enumerate() {
+            let part_index = index.wrapping_add(part as i16);
+            if self
+                .set_value_by_index(part_index, 0, part_value)
+                .await?
+                .is_none()
+            {
+                return Ok(false);
+            }
+        }
+
+        Ok(true)
+    }
+
+    /// Get a weekly time program.
+    ///
+    /// The time program is stored in `7 * slots_per_day` consecutive indices
+    /// starting at `index`, see `ScheduleEntry` for details. Returns `None`
+    /// if the device did not reply to one of the slots.
+    pub async fn get_schedule(
+        &mut self,
+        index: i16,
+        slots_per_day: usize,
+    ) -> Result<Option<Vec<ScheduleEntry>>> {
+        let mut values = Vec::with_capacity(slots_per_day * 7);
+        for slot in 0..slots_per_day * 7 {
+            let slot_index = index.wrapping_add(slot as i16);
+            match self.get_value_by_index(slot_index, 0).await? {
+                Some(dgram) => values.push(dgram.param32),
+                None => return Ok(None),
+            }
+        }
+
+        Ok(Some(schedule_from_values(&values, slots_per_day)))
+    }
+
+    /// Replace a weekly time program.
+    ///
+    /// See `get_schedule` for the layout. All slots are written, unused
+    /// slots are cleared. If the device supports bulk value transactions,
+    /// the slots are written within one using `tx_timeout`, so that the
+    /// device never runs a partially written program. Returns whether the
+    /// device acknowledged all slots.
+    pub async fn set_schedule(
+        &mut self,
+        index: i16,
+        slots_per_day: usize,
+        entries: &[ScheduleEntry],
+        tx_timeout: i32,
+    ) -> Result<bool> {
+        let values = schedule_to_values(entries, slots_per_day)?;
+        let values = values
+            .into_iter()
+            .enumerate()
+            .map(|(slot, value)| (index.wrapping_add(slot as i16), value))
+            .collect::<Vec<_>>();
+
+        if self
+            .begin_bulk_value_transaction(tx_timeout)
+            .await?
+            .is_some()
+        {
+            let applied = self.stream.write_bulk_values(self.address, &values).await?;
+            return Ok(applied.committed);
+        }
+
+        for (slot_index, value) in values {
+            if self
+                .set_value_by_index(slot_index, 0, value)
+                .await?
+                .is_none()
+            {
+                return Ok(false);
+            }
+        }
+
+        Ok(true)
+    }
+
+    /// Select the `ControllerProfile` for the device from the `registry`.
+    ///
+    /// The changeset of the device is read using the value ID `Changeset`.
+    /// If the device does not know that value, only profiles applying to
+    /// all changesets are considered.
+    pub async fn select_profile<'p>(
+        &mut self,
+        registry: &'p ControllerProfileRegistry,
+    ) -> Result<Option<&'p ControllerProfile>> {
+        let changeset = match self.get_value_index_by_id("Changeset").await? {
+            Some(index) => self
+                .get_value_by_index(index, 0)
+                .await?
+                .map(|dgram| dgram.param32),
+            None => None,
+        };
+
+        Ok(registry.find(self.address, changeset))
+    }
+
+    /// Get a value known to the `profile` by its ID, scaled using the
+    /// value's factor.
+    ///
+    /// Returns `None` if the device did not reply.
+    pub async fn get_value_by_id(
+        &mut self,
+        profile: &ControllerProfile,
+        id: &str,
+    ) -> Result<Option<f64>> {
+        let value = profile_value(profile, id)?;
+        let rx_dgram = self.get_value_by_index(value.index, 0).await?;
+        Ok(rx_dgram.map(|dgram| f64::from(dgram.param32) * value.factor))
+    }
+
+    /// Set a value known to the `profile` by its ID, scaled using the
+    /// value's factor.
+    ///
+    /// Returns the scaled value acknowledged by the device or `None` if
+    /// the device did not reply. Fails without writing if the value's
+    /// factor is zero or the scaled value does not fit into an `i32`.
+    pub async fn set_value_by_id(
+        &mut self,
+        profile: &ControllerProfile,
+        id: &str,
+        value: f64,
+    ) -> Result<Option<f64>> {
+        let profile_value = profile_value(profile, id)?;
+        if profile_value.factor == 0.0 {
+            return Err(format!("Value ID {:?} has a factor of zero", id).into());
+        }
+        let raw_value = (value / profile_value.factor).round();
+        if !(f64::from(i32::MIN)..=f64::from(i32::MAX)).contains(&raw_value) {
+            return Err(format!("Value {} is out of range for value ID {:?}", value, id).into());
+        }
+        let raw_value = raw_value as i32;
+        let rx_dgram = self
+            .set_value_by_index(profile_value.index, 0, raw_value)
+            .await?;
+        Ok(rx_dgram.map(|dgram| f64::from(dgram.param32) * profile_value.factor))
+    }
+
+    /// Set the mode of a relay, e.g. to switch it on manually.
+    ///
+    /// The `relay` is numbered starting at 1, see `RelayProfile`. Returns
+    /// whether the device acknowledged the mode.
+    pub async fn set_relay_mode(
+        &mut self,
+        profile: &RelayProfile,
+        relay: usize,
+        mode: RelayMode,
+    ) -> Result<bool> {
+        let index = profile.mode_index(relay)?;
+        let value = profile.mode_value(mode);
+        let rx_dgram = self.set_value_by_index(index, 0, value).await?;
+        Ok(rx_dgram.is_some())
+    }
+
+    /// Get the mode of a relay.
+    ///
+    /// Returns `None` if the device did not reply.
+    pub async fn get_relay_mode(
+        &mut self,
+        profile: &RelayProfile,
+        relay: usize,
+    ) -> Result<Option<RelayMode>> {
+        let index = profile.mode_index(relay)?;
+        match self.get_value_by_index(index, 0).await? {
+            Some(dgram) => Ok(Some(profile.mode_from_value(dgram.param32)?)),
+            None => Ok(None),
+        }
+    }
+
+    /// Get the states of all relays, e.g. their speeds in percent.
+    ///
+    /// Returns `None` if the device did not reply to one of the states.
+    pub async fn get_relay_states(&mut self, profile: &RelayProfile) -> Result<Option<Vec<i32>>> {
+        let mut states = Vec::with_capacity(profile.relay_count());
+        for relay in 1..=profile.relay_count() {
+            let index = profile.state_index(relay)?;
+            match self.get_value_by_index(index, 0).await? {
+                Some(dgram) => states.push(dgram.param32),
+                None => return Ok(None),
+            }
+        }
+
+        Ok(Some(states))
+    }
+
+    /// Give back bus control to the regular VBus master and end the session.
+    pub async fn release(mut self) -> Result<Option<Data>> {
+        self.released = true;
+        self.stream.release_bus(self.address).await
+    }
+}
+
+impl<'a, R: Read + Unpin, W: Write + Unpin> Drop for ControllerSession<'a, R, W> {
+    fn drop(&mut self) {
+        if !self.released {
+            self.stream.release_bus_without_waiting(self.address);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use async_std::io::Cursor;
+
+    use resol_vbus::chrono::Weekday;
+
+    use super::*;
+
+    use crate::{
+        test_utils::{extend_from_datagram, extend_with_empty_packet, hex_encode, simulate_run},
+        write_guard::{write_denied_error, WriteGuard},
+    };
+
+    #[test]
+    fn test_acquire_and_release() {
+        let mut rx_buf = Vec::new();
+        let tx_buf = Cursor::new(Vec::new());
+
+        extend_from_datagram(&mut rx_buf, 0x0000, 0x7E10, 0x0500, 0, 0);
+        extend_from_datagram(&mut rx_buf, 0x0000, 0x7E11, 0x0500, 0, 0);
+        extend_from_datagram(&mut rx_buf, 0x0020, 0x7E11, 0x0156, 0x1234, 0x789abcde);
+        extend_with_empty_packet(&mut rx_buf, 0x0010, 0x7E11, 0x0100);
+
+        let mut lds = LiveDataStream::new(&rx_buf[..], tx_buf, 0, 0x0020);
+
+        simulate_run(async {
+            let mut session = lds.acquire_bus(0x7E11).await?;
+
+            assert_eq!(0x7E11, session.address());
+
+            let dgram = session.get_value_by_index(0x1234, 0x56).await?.unwrap();
+            assert_eq!(0x789abcde, dgram.param32);
+
+            session.release().await?;
+
+            Result::Ok(())
+        })
+        .unwrap();
+
+        assert_eq!(
+            "aa117e20002056033412000000000011aa117e2000200006000000000000002a",
+            hex_encode(lds.writer_ref())
+        );
+    }
+
+    #[test]
+    fn test_release_on_drop() {
+        let mut rx_buf = Vec::new();
+        let tx_buf = Cursor::new(Vec::new());
+
+        extend_from_datagram(&mut rx_buf, 0x0000, 0x7E11, 0x0500, 0, 0);
+        extend_with_empty_packet(&mut rx_buf, 0x0010, 0x7E11, 0x0100);
+
+        let mut lds = LiveDataStream::new(&rx_buf[..], tx_buf, 0, 0x0020);
+
+        simulate_run(async {
+            let session = lds.acquire_bus(0x7E11).await?;
+            drop(session);
+
+            assert_eq!(
+                "aa117e2000200006000000000000002a",
+                hex_encode(lds.writer_ref())
+            );
+
+            lds.receive_any_data(100).await?;
+
+            Result::Ok(())
+        })
+        .unwrap();
+
+        assert_eq!(
+            "aa117e2000200006000000000000002a",
+            hex_encode(lds.writer_ref())
+        );
+    }
+
+    #[test]
+    fn test_release_on_close() {
+        let mut rx_buf = Vec::new();
+        let tx_buf = Cursor::new(Vec::new());
+
+        extend_from_datagram(&mut rx_buf, 0x0000, 0x7E11, 0x0500, 0, 0);
+
+        let mut lds = LiveDataStream::new(&rx_buf[..], tx_buf, 0, 0x0020);
+
+        simulate_run(async {
+            let session = lds.acquire_bus(0x7E11).await?;
+            drop(session);
+
+            lds.close().await
+        })
+        .unwrap();
+
+        let (_, writer) = lds.into_inner();
+
+        assert_eq!("aa117e2000200006000000000000002a", hex_encode(&writer));
+    }
+
+    #[test]
+    fn test_with_bus() {
+        let mut rx_buf = Vec::new();
+        let tx_buf = Cursor::new(Vec::new());
+
+        extend_from_datagram(&mut rx_buf, 0x0000, 0x7E11, 0x0500, 0, 0);
+        extend_with_empty_packet(&mut rx_buf, 0x0010, 0x7E11, 0x0100);
+
+        let mut lds = LiveDataStream::new(&rx_buf[..], tx_buf, 0, 0x0020);
+
+        let result = simulate_run(lds.with_bus(0x7E11, |session| {
+            Box::pin(async move {
+                assert_eq!(0x7E11, session.address());
+                Result::<()>::Err("Failed".into())
+            })
+        }));
+
+        assert_eq!(Err("Failed".into()), result);
+        assert_eq!(
+            "aa117e2000200006000000000000002a",
+            hex_encode(lds.writer_ref())
+        );
+    }
+
+    #[test]
+    fn test_string_value() {
+        let mut rx_buf = Vec::new();
+
+        extend_from_datagram(&mut rx_buf, 0x0000, 0x7E11, 0x0500, 0, 0);
+        extend_from_datagram(&mut rx_buf, 0x0020, 0x7E11, 0x0100, 0x1000, 0x6C6C6548);
+        extend_from_datagram(&mut rx_buf, 0x0020, 0x7E11, 0x0100, 0x1001, 0x0000006F);
+        extend_from_datagram(&mut rx_buf, 0x0020, 0x7E11, 0x0100, 0x1000, 0x00006948);
+        extend_from_datagram(&mut rx_buf, 0x0020, 0x7E11, 0x0100, 0x1001, 0);
+
+        let mut lds = LiveDataStream::new(&rx_buf[..], Cursor::new(Vec::new()), 0, 0x0020);
+
+        simulate_run(async {
+            let mut session = lds.acquire_bus(0x7E11).await?;
+
+            let value = session.get_string_value(0x1000, 8).await?;
+            assert_eq!(Some("Hello".to_string()), value);
+
+            assert!(session.set_string_value(0x1000, 8, "Hi").await?);
+
+            assert_eq!(
+                Err("String value \"Too long\" exceeds 6 bytes".into()),
+                session.set_string_value(0x1000, 6, "Too long").await
+            );
+
+            assert_eq!(None, session.get_string_value(0x1000, 8).await?);
+
+            Result::Ok(())
+        })
+        .unwrap();
+    }
+
+    #[test]
+    fn test_schedule() {
+        let mut rx_buf = Vec::new();
+
+        extend_from_datagram(&mut rx_buf, 0x0000, 0x7E11, 0x0500, 0, 0);
+        for slot in 0..7 {
+            let value = if slot == 2 { 0x01E0_0168 } else { 0 };
+            extend_from_datagram(&mut rx_buf, 0x0020, 0x7E11, 0x0100, 0x2000 + slot, value);
+        }
+        extend_from_datagram(&mut rx_buf, 0x0020, 0x7E11, 0x1401, 0, 0);
+        for slot in 0..7 {
+            let value = if slot == 6 { 0x05A0_0000 } else { 0 };
+            extend_from_datagram(&mut rx_buf, 0x0020, 0x7E11, 0x1600, 0x2000 + slot, value);
+        }
+        extend_from_datagram(&mut rx_buf, 0x0020, 0x7E11, 0x1403, 0, 0);
+
+        let mut lds = LiveDataStream::new(&rx_buf[..], Cursor::new(Vec::new()), 0, 0x0020);
+
+        simulate_run(async {
+            let mut session = lds.acquire_bus(0x7E11).await?;
+
+            let entries = session.get_schedule(0x2000, 1).await?;
+            assert_eq!(
+                Some(vec![ScheduleEntry {
+                    day: Weekday::Wed,
+                    start: 360,
+                    end: 480,
+                }]),
+                entries
+            );
+
+            let entries = [ScheduleEntry {
+                day: Weekday::Sun,
+                start: 0,
+                end: 1440,
+            }];
+            assert!(session.set_schedule(0x2000, 1, &entries, 10).await?);
+
+            Result::Ok(())
+        })
+        .unwrap();
+
+        let tx = hex_encode(lds.writer_ref());
+        assert!(tx.contains("aa117e200020021400"));
+    }
+
+    #[test]
+    fn test_schedule_rollback_on_error() {
+        let mut rx_buf = Vec::new();
+
+        extend_from_datagram(&mut rx_buf, 0x0000, 0x7E11, 0x0500, 0, 0);
+        extend_from_datagram(&mut rx_buf, 0x0020, 0x7E11, 0x1401, 0, 0);
+        extend_from_datagram(&mut rx_buf, 0x0020, 0x7E11, 0x1405, 0, 0);
+
+        let mut lds = LiveDataStream::new(&rx_buf[..], Cursor::new(Vec::new()), 0, 0x0020);
+
+        let mut guard = WriteGuard::allow_all();
+        guard.deny(0x2000..=0x2000);
+        lds.set_write_guard(Some(guard));
+
+        let result = simulate_run(async {
+            let mut session = lds.acquire_bus(0x7E11).await?;
+            session.set_schedule(0x2000, 1, &[], 10).await
+        });
+        assert_eq!(Err(write_denied_error(0x2000)), result);
+
+        let tx = hex_encode(lds.writer_ref());
+        assert!(tx.contains("aa117e200020041400"));
+    }
+
+    #[test]
+    fn test_relay_control() {
+        let mut rx_buf = Vec::new();
+
+        extend_from_datagram(&mut rx_buf, 0x0000, 0x7E11, 0x0500, 0, 0);
+        extend_from_datagram(&mut rx_buf, 0x0020, 0x7E11, 0x0100, 0x1001, 1);
+        extend_from_datagram(&mut rx_buf, 0x0020, 0x7E11, 0x0100, 0x1001, 1);
+        extend_from_datagram(&mut rx_buf, 0x0020, 0x7E11, 0x0100, 0x0200, 0);
+        extend_from_datagram(&mut rx_buf, 0x0020, 0x7E11, 0x0100, 0x0201, 100);
+
+        let mut lds = LiveDataStream::new(&rx_buf[..], Cursor::new(Vec::new()), 0, 0x0020);
+
+        let mut profile = RelayProfile::new();
+        profile.add_relay(0x1000, 0x0200);
+        profile.add_relay(0x1001, 0x0201);
+
+        simulate_run(async {
+            let mut session = lds.acquire_bus(0x7E11).await?;
+
+            assert!(session.set_relay_mode(&profile, 2, RelayMode::On).await?);
+            assert_eq!(
+                Some(RelayMode::On),
+                session.get_relay_mode(&profile, 2).await?
+            );
+            assert_eq!(
+                Some(vec![0, 100]),
+                session.get_relay_states(&profile).await?
+            );
+            assert_eq!(
+                Err("Unknown relay R3".into()),
+                session.set_relay_mode(&profile, 3, RelayMode::Off).await
+            );
+
+            Result::Ok(())
+        })
+        .unwrap();
+
+        let tx = hex_encode(lds.writer_ref());
+        assert!(tx.contains("aa117e200020000201100100000000"));
+    }
+
+    #[test]
+    fn test_get_value_id_table() {
+        let mut rx_buf = Vec::new();
+
+        extend_from_datagram(&mut rx_buf, 0x0000, 0x7E11, 0x0500, 0, 0);
+        for (command, index, id) in [
+            (0x1101, 0x0010, "Foo"),
+            (0x1101, 0x0000, "Bar"),
+            (0x0100, 0x0005, "Baz"),
+        ] {
+            extend_from_datagram(
+                &mut rx_buf,
+                0x0020,
+                0x7E11,
+                command,
+                index,
+                value_id_hash_by_id(id),
+            );
+        }
+        // the value read to get back in sync after "Baz"
+        extend_from_datagram(&mut rx_buf, 0x0020, 0x7E11, 0x0100, 0x0000, 0);
+
+        let mut lds = LiveDataStream::new(&rx_buf[..], Cursor::new(Vec::new()), 0, 0x0020);
+
+        simulate_run(async {
+            let mut session = lds.acquire_bus(0x7E11).await?;
+
+            let table = session.get_value_id_table(&["Foo", "Bar", "Baz"]).await?;
+            assert_eq!(vec![(0x0005, "Baz"), (0x0010, "Foo")], table);
+
+            Result::Ok(())
+        })
+        .unwrap();
+    }
+
+    #[test]
+    fn test_controller_profile() {
+        let mut rx_buf = Vec::new();
+
+        extend_from_datagram(&mut rx_buf, 0x0000, 0x7E11, 0x0500, 0, 0);
+        extend_from_datagram(
+            &mut rx_buf,
+            0x0020,
+            0x7E11,
+            0x1101,
+            0x0042,
+            value_id_hash_by_id("Changeset"),
+        );
+        extend_from_datagram(&mut rx_buf, 0x0020, 0x7E11, 0x0100, 0x0042, 1234);
+        extend_from_datagram(&mut rx_buf, 0x0020, 0x7E11, 0x0100, 0x1000, 215);
+        extend_from_datagram(&mut rx_buf, 0x0020, 0x7E11, 0x0100, 0x1000, 220);
+
+        let mut lds = LiveDataStream::new(&rx_buf[..], Cursor::new(Vec::new()), 0, 0x0020);
+
+        let mut registry = ControllerProfileRegistry::new();
+        registry.add(ControllerProfile::new("Generic", 0x7E11));
+        let mut profile = ControllerProfile::new("Changeset 1234", 0x7E11);
+        profile.changeset = Some(1234);
+        profile.values.push(ProfileValue {
+            id: "Temperature".to_string(),
+            index: 0x1000,
+            factor: 0.1,
+        });
+        profile.values.push(ProfileValue {
+            id: "Unscaled".to_string(),
+            index: 0x1001,
+            factor: 0.0,
+        });
+        registry.add(profile);
+
+        simulate_run(async {
+            let mut session = lds.acquire_bus(0x7E11).await?;
+
+            let profile = session.select_profile(&registry).await?.unwrap();
+            assert_eq!("Changeset 1234", profile.name);
+
+            let value = session.get_value_by_id(profile, "Temperature").await?;
+            assert_eq!(Some(21.5), value);
+
+            let value = session
+                .set_value_by_id(profile, "Temperature", 22.0)
+                .await?;
+            assert_eq!(Some(22.0), value);
+
+            assert_eq!(
+                Err("Unknown value ID \"Pressure\" in profile \"Changeset 1234\"".into()),
+                session.get_value_by_id(profile, "Pressure").await
+            );
+            assert_eq!(
+                Err("Value ID \"Unscaled\" has a factor of zero".into()),
+                session.set_value_by_id(profile, "Unscaled", 1.0).await
+            );
+            assert_eq!(
+                Err("Value 10000000000 is out of range for value ID \"Temperature\"".into()),
+                session.set_value_by_id(profile, "Temperature", 1e10).await
+            );
+
+            Result::Ok(())
+        })
+        .unwrap();
+
+        let tx = hex_encode(lds.writer_ref());
+        assert!(tx.contains("aa117e200020000200105c000000"));
+    }
+
+    #[test]
+    fn test_acquire_bus_without_offer() {
+        let mut rx_buf = Vec::new();
+        let tx_buf = Cursor::new(Vec::new());
+
+        extend_from_datagram(&mut rx_buf, 0x0000, 0x7E10, 0x0500, 0, 0);
+
+        let mut lds = LiveDataStream::new(&rx_buf[..], tx_buf, 0, 0x0020);
+
+        let result = simulate_run(async { lds.acquire_bus(0x7E11).await.map(|_| ()) });
+
+        assert_eq!(Err("Unable to acquire bus from 0x7E11".into()), result);
+    }
+}
@@ -0,0 +1,326 @@
+use std::marker::Unpin;
+
+use async_std::io::{Read, Write};
+
+use resol_vbus::{chrono::Utc, Data, Datagram, Header, Packet};
+
+use crate::{error::Result, live_data_stream::LiveDataStream};
+
+const DEFAULT_SOURCE_ADDRESS: u16 = 0x0020;
+
+fn check_addresses(destination_address: u16, source_address: u16) -> Result<()> {
+    if destination_address == source_address {
+        Err(format!(
+            "Destination address 0x{:04X} must differ from source address",
+            destination_address
+        )
+        .into())
+    } else {
+        Ok(())
+    }
+}
+
+fn check_channel<R: Read + Unpin, W: Write + Unpin>(
+    channel: Option<u8>,
+    stream: &LiveDataStream<R, W>,
+) -> Result<u8> {
+    match channel {
+        Some(channel) if channel != stream.channel() => Err(format!(
+            "Channel {} does not match the stream's channel {}",
+            channel,
+            stream.channel()
+        )
+        .into()),
+        _ => Ok(stream.channel()),
+    }
+}
+
+/// A builder for arbitrary protocol version 2.0 `Datagram`s.
+///
+/// # Examples
+///
+/// ```no_run
+/// # fn main() -> async_resol_vbus::Result<()> { async_std::task::block_on(async {
+/// #
+/// use async_std::net::TcpStream;
+///
+/// use async_resol_vbus::{DatagramBuilder, LiveDataStream};
+///
+/// let stream = TcpStream::connect("192.168.5.217:7053").await?;
+/// // ... perform handshake ...
+/// let mut lds = LiveDataStream::from_tcp_stream(stream, 0, 0x0020);
+///
+/// DatagramBuilder::new(0x7E11, 0x1300)
+///     .param16(0x1234)
+///     .send(&mut lds)
+///     .await?;
+/// #
+/// # Ok(()) }) }
+/// ```
+#[derive(Debug, Clone)]
+pub struct DatagramBuilder {
+    channel: Option<u8>,
+    destination_address: u16,
+    source_address: Option<u16>,
+    command: u16,
+    param16: i16,
+    param32: i32,
+}
+
+impl DatagramBuilder {
+    /// Create a new `DatagramBuilder` for `command` sent to `destination_address`.
+    pub fn new(destination_address: u16, command: u16) -> DatagramBuilder {
+        DatagramBuilder {
+            channel: None,
+            destination_address,
+            source_address: None,
+            command,
+            param16: 0,
+            param32: 0,
+        }
+    }
+
+    /// Set the VBus channel. Defaults to the stream's channel when sent.
+    pub fn channel(mut self, channel: u8) -> Self {
+        self.channel = Some(channel);
+        self
+    }
+
+    /// Set the source address. Defaults to the stream's own address when sent.
+    pub fn source_address(mut self, address: u16) -> Self {
+        self.source_address = Some(address);
+        self
+    }
+
+    /// Set the 16-bit parameter.
+    pub fn param16(mut self, param16: i16) -> Self {
+        self.param16 = param16;
+        self
+    }
+
+    /// Set the 32-bit parameter.
+    pub fn param32(mut self, param32: i32) -> Self {
+        self.param32 = param32;
+        self
+    }
+
+    /// Validate the settings and build the `Datagram`.
+    ///
+    /// Unset channel and source address default to `0` and `0x0020`.
+    pub fn build(&self) -> Result<Datagram> {
+        self.build_with(
+            self.channel.unwrap_or(0),
+            self.source_address.unwrap_or(DEFAULT_SOURCE_ADDRESS),
+        )
+    }
+
+    fn build_with(&self, channel: u8, source_address: u16) -> Result<Datagram> {
+        check_addresses(self.destination_address, source_address)?;
+
+        Ok(Datagram {
+            header: Header {
+                timestamp: Utc::now(),
+                channel,
+                destination_address: self.destination_address,
+                source_address,
+                protocol_version: 0x20,
+            },
+            command: self.command,
+            param16: self.param16,
+            param32: self.param32,
+        })
+    }
+
+    /// Build the `Datagram` and send it using `stream`.
+    pub async fn send<R: Read + Unpin, W: Write + Unpin>(
+        &self,
+        stream: &mut LiveDataStream<R, W>,
+    ) -> Result<Datagram> {
+        let channel = check_channel(self.channel, stream)?;
+        let source_address = self.source_address.unwrap_or_else(|| stream.self_address());
+        let dgram = self.build_with(channel, source_address)?;
+        stream.send_data(&Data::Datagram(dgram.clone())).await?;
+        Ok(dgram)
+    }
+}
+
+/// A builder for arbitrary protocol version 1.0 `Packet`s.
+///
+/// The frame count is derived from the payload length unless it is set
+/// explicitly, in which case the payload must fit into the frames.
+#[derive(Debug, Clone)]
+pub struct PacketBuilder {
+    channel: Option<u8>,
+    destination_address: u16,
+    source_address: Option<u16>,
+    command: u16,
+    frame_count: Option<u8>,
+    payload: Vec<u8>,
+}
+
+impl PacketBuilder {
+    /// Create a new `PacketBuilder` for `command` sent to `destination_address`.
+    pub fn new(destination_address: u16, command: u16) -> PacketBuilder {
+        PacketBuilder {
+            channel: None,
+            destination_address,
+            source_address: None,
+            command,
+            frame_count: None,
+            payload: Vec::new(),
+        }
+    }
+
+    /// Set the VBus channel. Defaults to the stream's channel when sent.
+    pub fn channel(mut self, channel: u8) -> Self {
+        self.channel = Some(channel);
+        self
+    }
+
+    /// Set the source address. Defaults to the stream's own address when sent.
+    pub fn source_address(mut self, address: u16) -> Self {
+        self.source_address = Some(address);
+        self
+    }
+
+    /// Set the number of 4-byte frames.
+    pub fn frame_count(mut self, frame_count: u8) -> Self {
+        self.frame_count = Some(frame_count);
+        self
+    }
+
+    /// Set the payload. Must not exceed 508 bytes.
+    pub fn payload(mut self, payload: &[u8]) -> Self {
+        self.payload = payload.to_vec();
+        self
+    }
+
+    /// Validate the settings and build the `Packet`.
+    ///
+    /// Unset channel and source address default to `0` and `0x0020`.
+    pub fn build(&self) -> Result<Packet> {
+        self.build_with(
+            self.channel.unwrap_or(0),
+            self.source_address.unwrap_or(DEFAULT_SOURCE_ADDRESS),
+        )
+    }
+
+    fn build_with(&self, channel: u8, source_address: u16) -> Result<Packet> {
+        check_addresses(self.destination_address, source_address)?;
+
+        let mut frame_data = [0; 508];
+        if self.payload.len() > frame_data.len() {
+            return Err(format!(
+                "Payload of {} bytes exceeds the maximum of 508 bytes",
+                self.payload.len()
+            )
+            .into());
+        }
+
+        let frame_count = match self.frame_count {
+            Some(frame_count) if self.payload.len() > usize::from(frame_count) * 4 => {
+                return Err(format!(
+                    "Payload of {} bytes does not fit into {} frames",
+                    self.payload.len(),
+                    frame_count
+                )
+                .into());
+            }
+            Some(frame_count) if frame_count > 127 => {
+                return Err(
+                    format!("Frame count {} exceeds the maximum of 127", frame_count).into(),
+                );
+            }
+            Some(frame_count) => frame_count,
+            None => self.payload.len().div_ceil(4) as u8,
+        };
+
+        frame_data[0..self.payload.len()].copy_from_slice(&self.payload);
+
+        Ok(Packet {
+            header: Header {
+                timestamp: Utc::now(),
+                channel,
+                destination_address: self.destination_address,
+                source_address,
+                protocol_version: 0x10,
+            },
+            command: self.command,
+            frame_count,
+            frame_data,
+        })
+    }
+
+    /// Build the `Packet` and send it using `stream`.
+    pub async fn send<R: Read + Unpin, W: Write + Unpin>(
+        &self,
+        stream: &mut LiveDataStream<R, W>,
+    ) -> Result<Packet> {
+        let channel = check_channel(self.channel, stream)?;
+        let source_address = self.source_address.unwrap_or_else(|| stream.self_address());
+        let packet = self.build_with(channel, source_address)?;
+        stream.send_data(&Data::Packet(packet.clone())).await?;
+        Ok(packet)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use async_std::io::Cursor;
+
+    use super::*;
+
+    use crate::test_utils::{hex_encode, simulate_run};
+
+    #[test]
+    fn test_datagram_builder() {
+        let builder = DatagramBuilder::new(0x7E11, 0x0300)
+            .param16(0x1234)
+            .param32(0x789abcde);
+
+        let dgram = builder.build().unwrap();
+        assert_eq!(0, dgram.header.channel);
+        assert_eq!(0x0020, dgram.header.source_address);
+        assert_eq!(0x1234, dgram.param16);
+
+        assert!(builder.clone().source_address(0x7E11).build().is_err());
+
+        let mut lds = LiveDataStream::new(&[][..], Cursor::new(Vec::new()), 1, 0x0021);
+
+        assert!(simulate_run(builder.clone().channel(0).send(&mut lds)).is_err());
+        assert_eq!("", hex_encode(lds.writer_ref()));
+
+        let dgram = simulate_run(builder.send(&mut lds)).unwrap();
+        assert_eq!(1, dgram.header.channel);
+        assert_eq!(0x0021, dgram.header.source_address);
+        assert!(hex_encode(lds.writer_ref()).starts_with("aa117e21002000033412"));
+    }
+
+    #[test]
+    fn test_packet_builder() {
+        let packet = PacketBuilder::new(0x0010, 0x0100)
+            .source_address(0x7E11)
+            .payload(&[1, 2, 3, 4, 5])
+            .build()
+            .unwrap();
+        assert_eq!(2, packet.frame_count);
+        assert_eq!(&[1, 2, 3, 4, 5, 0, 0, 0], &packet.frame_data[0..8]);
+        assert_eq!(0x10, packet.header.protocol_version);
+
+        let builder = PacketBuilder::new(0x0010, 0x0100).payload(&[0; 9]);
+        assert!(builder.clone().frame_count(2).build().is_err());
+        assert_eq!(
+            4,
+            builder.clone().frame_count(4).build().unwrap().frame_count
+        );
+        assert!(PacketBuilder::new(0x0010, 0x0100)
+            .payload(&[0; 509])
+            .build()
+            .is_err());
+
+        let mut lds = LiveDataStream::new(&[][..], Cursor::new(Vec::new()), 0, 0x7E11);
+
+        simulate_run(PacketBuilder::new(0x0010, 0x0100).send(&mut lds)).unwrap();
+        assert_eq!("aa1000117e100001004f", hex_encode(lds.writer_ref()));
+    }
+}
@@ -0,0 +1,273 @@
+use resol_vbus::Data;
+
+use crate::error::Result;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Field {
+    Channel,
+    DestinationAddress,
+    SourceAddress,
+    ProtocolVersion,
+    Command,
+}
+
+impl Field {
+    fn from_key(key: &str) -> Option<Field> {
+        match key {
+            "channel" => Some(Field::Channel),
+            "dst" => Some(Field::DestinationAddress),
+            "src" => Some(Field::SourceAddress),
+            "proto" => Some(Field::ProtocolVersion),
+            "cmd" => Some(Field::Command),
+            _ => None,
+        }
+    }
+
+    fn value(&self, data: &Data) -> u16 {
+        let header = data.as_ref();
+        match self {
+            Field::Channel => u16::from(header.channel),
+            Field::DestinationAddress => header.destination_address,
+            Field::SourceAddress => header.source_address,
+            Field::ProtocolVersion => u16::from(header.protocol_version),
+            Field::Command => match data {
+                Data::Packet(packet) => packet.command,
+                Data::Datagram(dgram) => dgram.command,
+                Data::Telegram(tgram) => u16::from(tgram.command),
+            },
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Matcher {
+    /// The parts of an ID pattern between its wildcards.
+    Id(Vec<String>),
+
+    /// The field values that must all match.
+    Fields(Vec<(Field, u16)>),
+}
+
+impl Matcher {
+    fn parse(pattern: &str) -> Result<Matcher> {
+        if pattern.contains('=') {
+            let mut fields = Vec::new();
+            for term in pattern.split('&') {
+                let (key, value) = match term.split_once('=') {
+                    Some((key, value)) => (key.trim(), value.trim()),
+                    None => return Err(format!("Invalid filter term {:?}", term).into()),
+                };
+
+                let field = match Field::from_key(key) {
+                    Some(field) => field,
+                    None => return Err(format!("Unknown filter key {:?}", key).into()),
+                };
+
+                let value = match value
+                    .strip_prefix("0x")
+                    .or_else(|| value.strip_prefix("0X"))
+                {
+                    Some(hex) => u16::from_str_radix(hex, 16),
+                    None => value.parse::<u16>(),
+                };
+                match value {
+                    Ok(value) => fields.push((field, value)),
+                    Err(_) => return Err(format!("Invalid filter value in {:?}", term).into()),
+                }
+            }
+            Ok(Matcher::Fields(fields))
+        } else if pattern.is_empty() {
+            Err("Empty filter pattern".into())
+        } else {
+            let parts = pattern
+                .to_uppercase()
+                .split('*')
+                .map(|part| part.to_string())
+                .collect();
+            Ok(Matcher::Id(parts))
+        }
+    }
+
+    fn matches(&self, data: &Data) -> bool {
+        match self {
+            Matcher::Id(parts) => matches_id(parts, &data.id_string()),
+            Matcher::Fields(fields) => fields
+                .iter()
+                .all(|(field, value)| field.value(data) == *value),
+        }
+    }
+}
+
+fn matches_id(parts: &[String], id: &str) -> bool {
+    let (first, rest) = match parts.split_first() {
+        Some(split) => split,
+        None => return false,
+    };
+
+    let (last, middle) = match rest.split_last() {
+        Some(split) => split,
+        None => return id == first,
+    };
+
+    if id.len() < first.len() + last.len() || !id.starts_with(first.as_str()) {
+        return false;
+    }
+
+    let end = id.len() - last.len();
+    if !id.ends_with(last.as_str()) {
+        return false;
+    }
+
+    let mut start = first.len();
+    for part in middle {
+        match id[start..end].find(part.as_str()) {
+            Some(idx) => start += idx + part.len(),
+            None => return false,
+        }
+    }
+
+    true
+}
+
+/// A filter for `Data` compiled from a textual pattern, so that filters
+/// can be loaded from configuration files.
+///
+/// The following patterns are supported:
+///
+/// - ID patterns like `00_0010_7E11_10_0100` or `*_7E11_10_0100`, matched
+///   against `Data::id_string` with `*` matching any number of characters
+/// - field patterns like `channel=1&src=0x7E11`, where all terms must
+///   match. The supported keys are `channel`, `dst`, `src`, `proto` and
+///   `cmd`, values can be decimal or `0x` prefixed hexadecimal
+///
+/// Multiple patterns can be combined using `|`, matching `Data` that
+/// matches any of them.
+///
+/// # Examples
+///
+/// ```
+/// # fn main() -> async_resol_vbus::Result<()> {
+/// use async_resol_vbus::{DataFilter, Datagram, Data, Header};
+///
+/// let filter = DataFilter::parse("*_7E11_10_0100 | src=0x7E11&cmd=0x0500")?;
+///
+/// let datagram = Datagram {
+///     header: Header {
+///         source_address: 0x7E11,
+///         protocol_version: 0x20,
+///         ..Header::default()
+///     },
+///     command: 0x0500,
+///     param16: 0,
+///     param32: 0,
+/// };
+/// assert!(filter.matches(&Data::Datagram(datagram)));
+/// # Ok(()) }
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct DataFilter {
+    matchers: Vec<Matcher>,
+}
+
+impl DataFilter {
+    /// Compile a `DataFilter` from its pattern.
+    pub fn parse(pattern: &str) -> Result<DataFilter> {
+        let matchers = pattern
+            .split('|')
+            .map(|pattern| Matcher::parse(pattern.trim()))
+            .collect::<Result<Vec<_>>>()?;
+
+        Ok(DataFilter { matchers })
+    }
+
+    /// Create a `DataFilter` matching exactly one ID string.
+    pub(crate) fn for_id(id: &str) -> DataFilter {
+        DataFilter {
+            matchers: vec![Matcher::Id(vec![id.to_uppercase()])],
+        }
+    }
+
+    /// Check whether the `Data` matches the filter.
+    pub fn matches(&self, data: &Data) -> bool {
+        self.matchers.iter().any(|matcher| matcher.matches(data))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use resol_vbus::{Datagram, Header, Packet};
+
+    use super::*;
+
+    fn packet(channel: u8, source_address: u16) -> Data {
+        Data::Packet(Packet {
+            header: Header {
+                channel,
+                destination_address: 0x0010,
+                source_address,
+                protocol_version: 0x10,
+                ..Header::default()
+            },
+            command: 0x0100,
+            frame_count: 0,
+            frame_data: [0u8; 508],
+        })
+    }
+
+    #[test]
+    fn test_id_patterns() -> Result<()> {
+        let filter = DataFilter::parse("*_7e11_10_0100")?;
+        assert!(filter.matches(&packet(0, 0x7E11)));
+        assert!(filter.matches(&packet(1, 0x7E11)));
+        assert!(!filter.matches(&packet(0, 0x7E21)));
+
+        let filter = DataFilter::parse("01_*_10_*")?;
+        assert!(filter.matches(&packet(1, 0x7E21)));
+        assert!(!filter.matches(&packet(0, 0x7E21)));
+
+        let filter = DataFilter::parse("00_0010_7E11_10_0100")?;
+        assert!(filter.matches(&packet(0, 0x7E11)));
+        assert!(!filter.matches(&packet(1, 0x7E11)));
+
+        let filter = DataFilter::parse("00_0010_7E11*10_0100")?;
+        assert!(filter.matches(&packet(0, 0x7E11)));
+
+        let filter = DataFilter::parse("00_0010_7E11_10*10_0100")?;
+        assert!(!filter.matches(&packet(0, 0x7E11)));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_field_patterns() -> Result<()> {
+        let filter = DataFilter::parse("channel=1&src=0x7E11")?;
+        assert!(filter.matches(&packet(1, 0x7E11)));
+        assert!(!filter.matches(&packet(0, 0x7E11)));
+        assert!(!filter.matches(&packet(1, 0x7E21)));
+
+        let filter = DataFilter::parse("cmd=0x0500 | channel=2")?;
+        assert!(filter.matches(&packet(2, 0x7E11)));
+        assert!(!filter.matches(&packet(1, 0x7E11)));
+        assert!(filter.matches(&Data::Datagram(Datagram {
+            header: Header::default(),
+            command: 0x0500,
+            param16: 0,
+            param32: 0,
+        })));
+
+        assert_eq!(
+            Some("Unknown filter key \"address\"".into()),
+            DataFilter::parse("address=1").err()
+        );
+        assert_eq!(
+            Some("Invalid filter value in \"src=0xZZ\"".into()),
+            DataFilter::parse("src=0xZZ").err()
+        );
+        assert_eq!(
+            Some("Empty filter pattern".into()),
+            DataFilter::parse("src=1 |").err()
+        );
+
+        Ok(())
+    }
+}
@@ -0,0 +1,290 @@
+use std::{
+    marker::Unpin,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
+    },
+};
+
+use async_std::{
+    channel::{Receiver, Sender, TrySendError},
+    prelude::*,
+    stream::Stream,
+};
+
+use resol_vbus::Data;
+
+use crate::{data_filter::DataFilter, error::Result, health::HealthMonitor};
+
+type Subscriber = (Option<DataFilter>, Sender<Arc<Data>>);
+
+/// Distributes received `Data` to multiple subscribers.
+///
+/// Every `Data` is wrapped into an `Arc` once and shared by all
+/// subscribers, so that large packets are not copied for every consumer.
+/// Each subscriber has a bounded queue: if a subscriber does not keep up,
+/// `Data` is dropped for that subscriber only. Subscribers whose `Receiver`
+/// was dropped are removed automatically. Subscribers can restrict the
+/// `Data` they receive using a `DataFilter`.
+///
+/// Cloned `DataHub`s share their subscribers and their paused state.
+///
+/// # Examples
+///
+/// ```no_run
+/// # fn main() -> async_resol_vbus::Result<()> { async_std::task::block_on(async {
+/// #
+/// use async_std::net::TcpStream;
+///
+/// use async_resol_vbus::{DataHub, LiveDataStream};
+///
+/// let stream = TcpStream::connect("192.168.5.217:7053").await?;
+/// // ... perform handshake ...
+/// let lds = LiveDataStream::from_tcp_stream(stream, 0, 0x0020);
+///
+/// let hub = DataHub::new();
+///
+/// let receiver = hub.subscribe();
+/// async_std::task::spawn(async move {
+///     while let Ok(data) = receiver.recv().await {
+///         println!("{}", data.id_string());
+///     }
+/// });
+///
+/// hub.run(lds.into_data_stream().await?).await?;
+/// #
+/// # Ok(()) }) }
+/// ```
+#[derive(Debug, Clone)]
+pub struct DataHub {
+    subscribers: Arc<Mutex<Vec<Subscriber>>>,
+    capacity: usize,
+    paused: Arc<AtomicBool>,
+    resume_sender: Sender<()>,
+    resume_receiver: Receiver<()>,
+    health_monitor: Option<HealthMonitor>,
+}
+
+impl DataHub {
+    /// Create a new `DataHub` without subscribers.
+    pub fn new() -> DataHub {
+        let (resume_sender, resume_receiver) = async_std::channel::bounded(1);
+
+        DataHub {
+            subscribers: Arc::new(Mutex::new(Vec::new())),
+            capacity: 64,
+            paused: Arc::new(AtomicBool::new(false)),
+            resume_sender,
+            resume_receiver,
+            health_monitor: None,
+        }
+    }
+
+    /// Set the number of `Data` queued for every subscriber created
+    /// afterwards.
+    ///
+    /// Defaults to 64.
+    pub fn set_capacity(&mut self, capacity: usize) {
+        self.capacity = capacity;
+    }
+
+    /// Set the `HealthMonitor` that every published `Data` and every error
+    /// of the stream passed to `run` is reported to.
+    pub fn set_health_monitor(&mut self, health_monitor: Option<HealthMonitor>) {
+        self.health_monitor = health_monitor;
+    }
+
+    /// Add a subscriber and return the `Receiver` for its `Data`.
+    pub fn subscribe(&self) -> Receiver<Arc<Data>> {
+        self.add_subscriber(None)
+    }
+
+    /// Add a subscriber only receiving `Data` matching the `filter` and
+    /// return the `Receiver` for its `Data`.
+    pub fn subscribe_matching(&self, filter: DataFilter) -> Receiver<Arc<Data>> {
+        self.add_subscriber(Some(filter))
+    }
+
+    fn add_subscriber(&self, filter: Option<DataFilter>) -> Receiver<Arc<Data>> {
+        let (sender, receiver) = async_std::channel::bounded(self.capacity);
+        self.subscribers.lock().unwrap().push((filter, sender));
+        receiver
+    }
+
+    /// Get the number of subscribers.
+    pub fn subscriber_count(&self) -> usize {
+        self.subscribers.lock().unwrap().len()
+    }
+
+    /// Send `data` to all subscribers and return the shared `Data`.
+    pub fn publish(&self, data: Data) -> Arc<Data> {
+        if let Some(ref health_monitor) = self.health_monitor {
+            health_monitor.record_data(data.as_ref().timestamp);
+        }
+
+        let data = Arc::new(data);
+
+        self.subscribers.lock().unwrap().retain(|(filter, sender)| {
+            if let Some(filter) = filter {
+                if !filter.matches(&data) {
+                    return !sender.is_closed();
+                }
+            }
+
+            match sender.try_send(data.clone()) {
+                Ok(()) | Err(TrySendError::Full(_)) => true,
+                Err(TrySendError::Closed(_)) => false,
+            }
+        });
+
+        data
+    }
+
+    /// Stop pulling `Data` from the stream passed to `run`.
+    ///
+    /// The `Data` that is currently being received is still published.
+    /// Afterwards no more bytes are read from the stream, so that the
+    /// TCP backpressure applies to the sender, while everything already
+    /// buffered by the stream is kept until `resume` is called.
+    pub fn pause(&self) {
+        self.paused.store(true, Ordering::SeqCst);
+    }
+
+    /// Continue pulling `Data` after `pause` was called.
+    pub fn resume(&self) {
+        self.paused.store(false, Ordering::SeqCst);
+        let _ = self.resume_sender.try_send(());
+    }
+
+    /// Check whether the `DataHub` is paused.
+    pub fn is_paused(&self) -> bool {
+        self.paused.load(Ordering::SeqCst)
+    }
+
+    /// Publish all `Data` from `stream` until it ends or yields an error.
+    ///
+    /// Pass the stream by mutable reference to continue using it after
+    /// `run` returned or was cancelled.
+    pub async fn run<S: Stream<Item = Result<Data>> + Unpin>(&self, mut stream: S) -> Result<()> {
+        loop {
+            while self.is_paused() {
+                let _ = self.resume_receiver.recv().await;
+            }
+
+            let data = match stream.next().await {
+                Some(Ok(data)) => data,
+                Some(Err(err)) => {
+                    if let Some(ref health_monitor) = self.health_monitor {
+                        health_monitor.record_error("hub");
+                    }
+                    return Err(err);
+                }
+                None => break,
+            };
+
+            self.publish(data);
+        }
+
+        Ok(())
+    }
+}
+
+impl Default for DataHub {
+    fn default() -> Self {
+        DataHub::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use async_std::io::Cursor;
+
+    use super::*;
+
+    use crate::{
+        live_data_stream::LiveDataStream,
+        test_utils::{extend_from_datagram, extend_with_empty_packet, simulate_run},
+    };
+
+    #[test]
+    fn test_data_hub() {
+        let mut rx_buf = Vec::new();
+        extend_with_empty_packet(&mut rx_buf, 0x0010, 0x7E11, 0x0100);
+        extend_from_datagram(&mut rx_buf, 0x0000, 0x7E11, 0x0500, 0, 0);
+        extend_with_empty_packet(&mut rx_buf, 0x0015, 0x7E11, 0x0100);
+
+        let lds = LiveDataStream::new(Cursor::new(rx_buf), Cursor::new(Vec::new()), 0, 0x0020);
+
+        let mut hub = DataHub::new();
+        let receiver1 = hub.subscribe();
+        hub.set_capacity(2);
+        let receiver2 = hub.subscribe();
+        drop(hub.subscribe());
+        let receiver3 = hub.subscribe_matching(DataFilter::parse("*_7E11_20_0500_0000").unwrap());
+        assert_eq!(4, hub.subscriber_count());
+
+        let health_monitor = HealthMonitor::new();
+        hub.set_health_monitor(Some(health_monitor.clone()));
+
+        simulate_run(async { hub.run(lds.into_data_stream().await?).await }).unwrap();
+
+        assert_eq!(3, hub.subscriber_count());
+        assert!(health_monitor.report().last_data.is_some());
+
+        let mut ids = Vec::new();
+        while let Ok(data) = receiver1.try_recv() {
+            ids.push(data.id_string());
+        }
+        assert_eq!(
+            vec![
+                "00_0010_7E11_10_0100",
+                "00_0000_7E11_20_0500_0000",
+                "00_0015_7E11_10_0100"
+            ],
+            ids
+        );
+
+        let data = receiver2.try_recv().unwrap();
+        assert_eq!("00_0010_7E11_10_0100", data.id_string());
+        assert!(receiver2.try_recv().is_ok());
+        assert!(receiver2.try_recv().is_err());
+
+        let data = receiver3.try_recv().unwrap();
+        assert_eq!("00_0000_7E11_20_0500_0000", data.id_string());
+        assert!(receiver3.try_recv().is_err());
+    }
+
+    #[test]
+    fn test_pause() {
+        let mut rx_buf = Vec::new();
+        extend_with_empty_packet(&mut rx_buf, 0x0010, 0x7E11, 0x0100);
+        extend_with_empty_packet(&mut rx_buf, 0x0015, 0x7E11, 0x0100);
+
+        let lds = LiveDataStream::new(Cursor::new(rx_buf), Cursor::new(Vec::new()), 0, 0x0020);
+        let mut data_stream = simulate_run(lds.into_data_stream()).unwrap();
+
+        let hub = DataHub::new();
+        let receiver = hub.subscribe();
+
+        hub.pause();
+        assert!(hub.is_paused());
+
+        let result = simulate_run(async_std::future::timeout(
+            std::time::Duration::from_millis(50),
+            hub.run(&mut data_stream),
+        ));
+        assert!(result.is_err());
+        assert!(receiver.try_recv().is_err());
+
+        hub.resume();
+        assert!(!hub.is_paused());
+
+        simulate_run(hub.run(&mut data_stream)).unwrap();
+
+        let mut ids = Vec::new();
+        while let Ok(data) = receiver.try_recv() {
+            ids.push(data.id_string());
+        }
+        assert_eq!(vec!["00_0010_7E11_10_0100", "00_0015_7E11_10_0100"], ids);
+    }
+}
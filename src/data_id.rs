@@ -0,0 +1,291 @@
+use std::{fmt, str::FromStr};
+
+use resol_vbus::{Data, PacketId, ToPacketId};
+
+use crate::{
+    data_filter::DataFilter,
+    error::{Error, Result},
+};
+
+/// The parsed components of a `Data` ID string like
+/// `01_0010_7E21_10_0100`.
+///
+/// The protocol version determines the format of the ID string:
+///
+/// - packets (`0x1X`): `CC_DDDD_SSSS_PP_CCCC`
+/// - datagrams (`0x2X`): `CC_DDDD_SSSS_PP_CCCC_IIII`, where `IIII` is the
+///   `param16` of datagrams with command `0x0900` and `0000` otherwise
+/// - telegrams (`0x3X`): `CC_DDDD_SSSS_PP_CC`
+///
+/// # Examples
+///
+/// ```
+/// # fn main() -> async_resol_vbus::Result<()> {
+/// use async_resol_vbus::{DataFilter, DataId};
+///
+/// let id = DataId::parse("01_0010_7E21_10_0100")?;
+/// assert_eq!(1, id.channel);
+/// assert_eq!(0x7E21, id.source_address);
+/// assert_eq!(0x0100, id.command);
+/// assert_eq!("01_0010_7E21_10_0100", id.to_string());
+///
+/// let filter = DataFilter::from(id);
+/// # Ok(()) }
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct DataId {
+    /// The VBus channel.
+    pub channel: u8,
+
+    /// The destination address.
+    pub destination_address: u16,
+
+    /// The source address.
+    pub source_address: u16,
+
+    /// The protocol version.
+    pub protocol_version: u8,
+
+    /// The command.
+    pub command: u16,
+
+    /// The additional info of datagram IDs, `None` for other protocols.
+    pub info: Option<u16>,
+}
+
+impl DataId {
+    /// Parse an ID string.
+    pub fn parse(id: &str) -> Result<DataId> {
+        let invalid = || Error::from(format!("Invalid ID string {:?}", id));
+
+        let parts = id.split('_').collect::<Vec<_>>();
+        let hex = |idx: usize, len: usize| -> Result<u16> {
+            match parts.get(idx) {
+                Some(part) if part.len() == len => {
+                    u16::from_str_radix(part, 16).map_err(|_| invalid())
+                }
+                _ => Err(invalid()),
+            }
+        };
+
+        let channel = hex(0, 2)? as u8;
+        let destination_address = hex(1, 4)?;
+        let source_address = hex(2, 4)?;
+        let protocol_version = hex(3, 2)? as u8;
+
+        let (command, info, part_count) = match protocol_version & 0xF0 {
+            0x10 => (hex(4, 4)?, None, 5),
+            0x20 => (hex(4, 4)?, Some(hex(5, 4)?), 6),
+            0x30 => (hex(4, 2)?, None, 5),
+            _ => {
+                return Err(format!(
+                    "Unsupported protocol version 0x{:02X} in ID string {:?}",
+                    protocol_version, id
+                )
+                .into())
+            }
+        };
+        if parts.len() != part_count {
+            return Err(invalid());
+        }
+
+        Ok(DataId {
+            channel,
+            destination_address,
+            source_address,
+            protocol_version,
+            command,
+            info,
+        })
+    }
+
+    /// Get the `DataId` of a `Data`.
+    pub fn from_data(data: &Data) -> DataId {
+        let header = data.as_ref();
+        let (command, info) = match data {
+            Data::Packet(packet) => (packet.command, None),
+            Data::Datagram(dgram) => {
+                let info = match dgram.command {
+                    0x0900 => dgram.param16 as u16,
+                    _ => 0,
+                };
+                (dgram.command, Some(info))
+            }
+            Data::Telegram(tgram) => (u16::from(tgram.command), None),
+        };
+
+        DataId {
+            channel: header.channel,
+            destination_address: header.destination_address,
+            source_address: header.source_address,
+            protocol_version: header.protocol_version,
+            command,
+            info,
+        }
+    }
+
+    /// Check whether the `Data` has this ID.
+    pub fn matches(&self, data: &Data) -> bool {
+        DataId::from_data(data) == *self
+    }
+
+    /// Check whether this is the ID of a packet.
+    pub fn is_packet(&self) -> bool {
+        self.protocol_version & 0xF0 == 0x10
+    }
+}
+
+impl fmt::Display for DataId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{:02X}_{:04X}_{:04X}_{:02X}",
+            self.channel, self.destination_address, self.source_address, self.protocol_version
+        )?;
+        if self.protocol_version & 0xF0 == 0x30 {
+            write!(f, "_{:02X}", self.command)?;
+        } else {
+            write!(f, "_{:04X}", self.command)?;
+        }
+        if let Some(info) = self.info {
+            write!(f, "_{:04X}", info)?;
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for DataId {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<DataId> {
+        DataId::parse(s)
+    }
+}
+
+impl ToPacketId for DataId {
+    /// Convert into a `PacketId`, failing for IDs of other protocols.
+    fn to_packet_id(&self) -> resol_vbus::Result<PacketId> {
+        if self.is_packet() {
+            Ok(PacketId(
+                self.channel,
+                self.destination_address,
+                self.source_address,
+                self.command,
+            ))
+        } else {
+            Err(format!("{} is not a packet ID", self).into())
+        }
+    }
+}
+
+impl From<DataId> for DataFilter {
+    fn from(id: DataId) -> DataFilter {
+        DataFilter::for_id(&id.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use resol_vbus::{Datagram, Header, Packet, Telegram};
+
+    use super::*;
+
+    #[test]
+    fn test_parse() -> Result<()> {
+        assert_eq!(
+            DataId {
+                channel: 1,
+                destination_address: 0x0010,
+                source_address: 0x7E21,
+                protocol_version: 0x10,
+                command: 0x0100,
+                info: None,
+            },
+            DataId::parse("01_0010_7E21_10_0100")?
+        );
+        assert_eq!(
+            Some(0x191A),
+            DataId::parse("11_1213_1415_26_0900_191A")?.info
+        );
+        assert_eq!(0x17, DataId::parse("11_1213_1415_36_17")?.command);
+
+        for id in [
+            "01_0010_7E21_10_0100",
+            "11_1213_1415_26_0900_191A",
+            "11_1213_1415_36_17",
+        ] {
+            assert_eq!(id, id.parse::<DataId>()?.to_string());
+        }
+
+        assert_eq!(
+            Err("Invalid ID string \"01_0010_7E21_10\"".into()),
+            DataId::parse("01_0010_7E21_10")
+        );
+        assert_eq!(
+            Err("Invalid ID string \"01_0010_7E21_10_0100_0000\"".into()),
+            DataId::parse("01_0010_7E21_10_0100_0000")
+        );
+        assert_eq!(
+            Err("Invalid ID string \"01_0010_7EZZ_10_0100\"".into()),
+            DataId::parse("01_0010_7EZZ_10_0100")
+        );
+        assert_eq!(
+            Err("Unsupported protocol version 0x40 in ID string \"01_0010_7E21_40_0100\"".into()),
+            DataId::parse("01_0010_7E21_40_0100")
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_from_data() -> Result<()> {
+        let header = Header {
+            channel: 0x11,
+            destination_address: 0x1213,
+            source_address: 0x1415,
+            protocol_version: 0x10,
+            ..Header::default()
+        };
+
+        let packet = Data::Packet(Packet {
+            header: header.clone(),
+            command: 0x1718,
+            frame_count: 0,
+            frame_data: [0u8; 508],
+        });
+        let dgram = Data::Datagram(Datagram {
+            header: Header {
+                protocol_version: 0x20,
+                ..header.clone()
+            },
+            command: 0x0900,
+            param16: 0x191A,
+            param32: 0,
+        });
+        let tgram = Data::Telegram(Telegram {
+            header: Header {
+                protocol_version: 0x30,
+                ..header
+            },
+            command: 0x17,
+            frame_data: [0u8; 21],
+        });
+
+        for data in [&packet, &dgram, &tgram] {
+            let id = DataId::from_data(data);
+            assert_eq!(data.id_string(), id.to_string());
+            assert!(id.matches(data));
+            assert!(DataFilter::from(id).matches(data));
+        }
+
+        let id = DataId::from_data(&packet);
+        assert!(!id.matches(&dgram));
+        assert_eq!(
+            PacketId(0x11, 0x1213, 0x1415, 0x1718),
+            id.to_packet_id().unwrap()
+        );
+        assert!(DataId::from_data(&dgram).to_packet_id().is_err());
+
+        Ok(())
+    }
+}
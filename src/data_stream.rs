@@ -0,0 +1,107 @@
+use std::{
+    marker::Unpin,
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+use async_std::{io::Read, stream::Stream};
+
+use resol_vbus::{Data, LiveDataBuffer};
+
+use crate::error::Result;
+
+/// A `Stream` of the `Data` received over the VBus, owning the connection.
+///
+/// See `LiveDataStream::into_data_stream` for details.
+#[derive(Debug)]
+pub struct DataStream<R: Read + Unpin, W> {
+    reader: R,
+    writer: W,
+    buf: LiveDataBuffer,
+    done: bool,
+}
+
+impl<R: Read + Unpin, W> DataStream<R, W> {
+    pub(crate) fn new(reader: R, writer: W, buf: LiveDataBuffer) -> DataStream<R, W> {
+        DataStream {
+            reader,
+            writer,
+            buf,
+            done: false,
+        }
+    }
+
+    /// Consume `self` and return the underlying I/O pair.
+    pub fn into_inner(self) -> (R, W) {
+        (self.reader, self.writer)
+    }
+}
+
+impl<R: Read + Unpin, W: Unpin> Stream for DataStream<R, W> {
+    type Item = Result<Data>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = self.get_mut();
+
+        loop {
+            if let Some(data) = this.buf.read_data() {
+                return Poll::Ready(Some(Ok(data)));
+            }
+
+            if this.done {
+                return Poll::Ready(None);
+            }
+
+            let mut buf = [0u8; 256];
+            match Pin::new(&mut this.reader).poll_read(cx, &mut buf) {
+                Poll::Ready(Ok(0)) => this.done = true,
+                Poll::Ready(Ok(len)) => this.buf.extend_from_slice(&buf[0..len]),
+                Poll::Ready(Err(err)) => {
+                    this.done = true;
+                    return Poll::Ready(Some(Err(err.into())));
+                }
+                Poll::Pending => return Poll::Pending,
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use async_std::{io::Cursor, prelude::*};
+
+    use crate::{
+        test_utils::{extend_from_datagram, extend_with_empty_packet, simulate_run},
+        LiveDataStream,
+    };
+
+    fn assert_send_static<T: Send + 'static>(_: &T) {}
+
+    #[test]
+    fn test_into_data_stream() {
+        let mut rx_buf = Vec::new();
+        extend_with_empty_packet(&mut rx_buf, 0x0010, 0x7E11, 0x0100);
+        extend_from_datagram(&mut rx_buf, 0x0000, 0x7E11, 0x0500, 0, 0);
+
+        let lds = LiveDataStream::new(Cursor::new(rx_buf), Cursor::new(Vec::new()), 0, 0x0020);
+
+        let mut stream = simulate_run(lds.into_data_stream()).unwrap();
+        assert_send_static(&stream);
+
+        let ids = simulate_run(async {
+            let mut ids = Vec::new();
+            while let Some(data) = stream.next().await {
+                ids.push(data.unwrap().id_string());
+            }
+            ids
+        });
+
+        assert_eq!(
+            vec![
+                "00_0010_7E11_10_0100".to_string(),
+                "00_0000_7E11_20_0500_0000".to_string(),
+            ],
+            ids
+        );
+    }
+}
@@ -0,0 +1,150 @@
+use std::collections::HashMap;
+
+/// A callback producing the reply value for a value request.
+///
+/// It is called with `None` for a get request and with the requested value
+/// for a set request. Returning `None` suppresses the reply.
+pub type ValueHandler = Box<dyn FnMut(Option<i32>) -> Option<i32> + Send>;
+
+enum ValueSource {
+    Value(i32),
+    Handler(ValueHandler),
+}
+
+/// Answers value get / set requests, emulating a controller on the VBus.
+///
+/// If set using `LiveDataStream::set_responder`, every get (`0x03xx`) or
+/// set (`0x02xx`) request datagram addressed to the stream's own address
+/// is answered while the stream receives data, e.g. in `receive` or
+/// `transceive`. Requests for unknown indices are not answered.
+///
+/// # Examples
+///
+/// ```no_run
+/// # fn main() -> async_resol_vbus::Result<()> { async_std::task::block_on(async {
+/// #
+/// use async_std::net::TcpStream;
+///
+/// use async_resol_vbus::{DatagramResponder, LiveDataStream};
+///
+/// let mut responder = DatagramResponder::new();
+/// responder.set_value(0x1234, 0, 42);
+/// responder.set_handler(0x1235, 0, |value| value.or(Some(0)));
+///
+/// let stream = TcpStream::connect("192.168.5.217:7053").await?;
+/// // ... perform handshake ...
+/// let mut lds = LiveDataStream::from_tcp_stream(stream, 0, 0x7E11);
+/// lds.set_responder(Some(responder));
+///
+/// // answer requests for the next minute
+/// lds.receive(60000, |_| false).await?;
+/// #
+/// # Ok(()) }) }
+/// ```
+#[derive(Default)]
+pub struct DatagramResponder {
+    values: HashMap<(i16, u8), ValueSource>,
+}
+
+impl DatagramResponder {
+    /// Create a new `DatagramResponder`.
+    pub fn new() -> DatagramResponder {
+        DatagramResponder::default()
+    }
+
+    /// Answer requests for the index with a stored value.
+    ///
+    /// Set requests update the stored value.
+    pub fn set_value(&mut self, index: i16, subindex: u8, value: i32) {
+        self.values
+            .insert((index, subindex), ValueSource::Value(value));
+    }
+
+    /// Answer requests for the index by calling the `handler`.
+    pub fn set_handler<F>(&mut self, index: i16, subindex: u8, handler: F)
+    where
+        F: FnMut(Option<i32>) -> Option<i32> + Send + 'static,
+    {
+        self.values
+            .insert((index, subindex), ValueSource::Handler(Box::new(handler)));
+    }
+
+    /// Stop answering requests for the index.
+    pub fn remove(&mut self, index: i16, subindex: u8) {
+        self.values.remove(&(index, subindex));
+    }
+
+    /// Get the stored value for the index.
+    ///
+    /// Returns `None` for unknown indices and indices answered by a handler.
+    pub fn value(&self, index: i16, subindex: u8) -> Option<i32> {
+        match self.values.get(&(index, subindex)) {
+            Some(ValueSource::Value(value)) => Some(*value),
+            _ => None,
+        }
+    }
+
+    /// Get the reply command and value for a request command, or `None` if
+    /// the request should not be answered.
+    pub(crate) fn answer(&mut self, command: u16, index: i16, value: i32) -> Option<(u16, i32)> {
+        let subindex = (command & 0x00FF) as u8;
+        let request_value = match command & 0xFF00 {
+            0x0200 => Some(value),
+            0x0300 => None,
+            _ => return None,
+        };
+
+        let reply_value = match self.values.get_mut(&(index, subindex))? {
+            ValueSource::Value(stored) => {
+                if let Some(value) = request_value {
+                    *stored = value;
+                }
+                Some(*stored)
+            }
+            ValueSource::Handler(handler) => handler(request_value),
+        }?;
+
+        Some((0x0100 | u16::from(subindex), reply_value))
+    }
+}
+
+impl std::fmt::Debug for DatagramResponder {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let mut indices = self.values.keys().collect::<Vec<_>>();
+        indices.sort();
+        f.debug_struct("DatagramResponder")
+            .field("indices", &indices)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_answer() {
+        let mut responder = DatagramResponder::new();
+        responder.set_value(0x1234, 0, 42);
+        responder.set_handler(0x1235, 0x56, |value| match value {
+            Some(value) if value < 0 => None,
+            Some(value) => Some(value * 2),
+            None => Some(7),
+        });
+
+        assert_eq!(Some((0x0100, 42)), responder.answer(0x0300, 0x1234, 0));
+        assert_eq!(Some((0x0100, 43)), responder.answer(0x0200, 0x1234, 43));
+        assert_eq!(Some(43), responder.value(0x1234, 0));
+
+        assert_eq!(Some((0x0156, 7)), responder.answer(0x0356, 0x1235, 0));
+        assert_eq!(Some((0x0156, 10)), responder.answer(0x0256, 0x1235, 5));
+        assert_eq!(None, responder.answer(0x0256, 0x1235, -1));
+        assert_eq!(None, responder.value(0x1235, 0x56));
+
+        assert_eq!(None, responder.answer(0x0300, 0x1236, 0));
+        assert_eq!(None, responder.answer(0x0500, 0x1234, 0));
+
+        responder.remove(0x1234, 0);
+        assert_eq!(None, responder.answer(0x0300, 0x1234, 0));
+    }
+}
@@ -1,12 +1,77 @@
 use std::{
     collections::HashSet,
     net::{Ipv4Addr, SocketAddr, SocketAddrV4},
-    time::Duration,
+    time::{Duration, Instant},
 };
 
 use async_std::net::UdpSocket;
 
-use crate::{device_information::DeviceInformation, error::Result};
+use socket2::SockRef;
+
+use crate::{
+    device_information::DeviceInformation, device_registry::DeviceRegistry, error::Result,
+    network_scanner::NetworkScanner,
+};
+
+pub(crate) const QUERY_BYTES: &[u8] = b"---RESOL-BROADCAST-QUERY---";
+pub(crate) const REPLY_BYTES: &[u8] = b"---RESOL-BROADCAST-REPLY---";
+
+/// The progress of a discovery, reported to the observer passed to
+/// `DeviceDiscovery::discover_device_addresses_with_progress`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum DiscoveryProgress {
+    /// A discovery round (starting at 1) is about to send its broadcast.
+    RoundStarted {
+        /// The number of the round, starting at 1.
+        round: u8,
+        /// The total number of rounds.
+        rounds: u8,
+    },
+
+    /// A device was found that was not known from previous replies.
+    AddressFound(SocketAddr),
+
+    /// A discovery round has finished waiting for replies.
+    RoundFinished {
+        /// The number of the round, starting at 1.
+        round: u8,
+        /// The total number of rounds.
+        rounds: u8,
+        /// The number of distinct devices found so far.
+        address_count: usize,
+    },
+}
+
+/// The timing of a single discovery round, see `DiscoveryReport`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DiscoveryRound {
+    /// The number of the round, starting at 1.
+    pub round: u8,
+
+    /// The timeout used to wait for replies in this round.
+    pub timeout: Duration,
+
+    /// The time this round took.
+    pub duration: Duration,
+
+    /// The number of devices found in this round that were not known before.
+    pub new_address_count: usize,
+
+    /// The longest time between the broadcast and a reply in this round.
+    pub max_latency: Option<Duration>,
+}
+
+/// The result of `DeviceDiscovery::discover_device_addresses_with_report`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DiscoveryReport {
+    /// The addresses of all devices found.
+    pub addresses: Vec<SocketAddr>,
+
+    /// The timing of each round that was performed.
+    pub rounds: Vec<DiscoveryRound>,
+}
+
+const MIN_ADAPTIVE_TIMEOUT: Duration = Duration::from_millis(50);
 
 /// Allows discovery of VBus-over-TCP devices in a local network.
 ///
@@ -16,13 +81,19 @@ use crate::{device_information::DeviceInformation, error::Result};
 ///
 /// The `DeviceDiscovery` type allows to send such broadcasts and collect all
 /// associated replies.
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct DeviceDiscovery {
+    bind_addr: SocketAddr,
+    interface: Option<String>,
+    multicast_interface: Option<Ipv4Addr>,
+    broadcast: bool,
     broadcast_addr: SocketAddr,
     rounds: u8,
     broadcast_timeout: Duration,
     fetch_port: u16,
     fetch_timeout: Duration,
+    stop_early: bool,
+    adaptive_timeout: bool,
 }
 
 impl DeviceDiscovery {
@@ -41,18 +112,153 @@ impl DeviceDiscovery {
     /// # Ok(()) }) }
     /// ```
     pub fn new() -> DeviceDiscovery {
+        let ip_addr = Ipv4Addr::new(0, 0, 0, 0);
+        let bind_addr = SocketAddr::V4(SocketAddrV4::new(ip_addr, 0));
+
         let ip_addr = Ipv4Addr::new(255, 255, 255, 255);
         let broadcast_addr = SocketAddr::V4(SocketAddrV4::new(ip_addr, 7053));
 
         DeviceDiscovery {
+            bind_addr,
+            interface: None,
+            multicast_interface: None,
+            broadcast: true,
             broadcast_addr,
             rounds: 3,
             broadcast_timeout: Duration::from_millis(500),
             fetch_port: 80,
             fetch_timeout: Duration::from_millis(2000),
+            stop_early: false,
+            adaptive_timeout: false,
+        }
+    }
+
+    /// Create a new `DeviceDiscoveryBuilder` using default values.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # fn main() -> async_resol_vbus::Result<()> {
+    /// use std::time::Duration;
+    ///
+    /// use async_resol_vbus::DeviceDiscovery;
+    ///
+    /// let discovery = DeviceDiscovery::builder()
+    ///     .rounds(1)
+    ///     .broadcast_timeout(Duration::from_millis(200))
+    ///     .build()?;
+    ///
+    /// assert_eq!(1, discovery.rounds());
+    /// # Ok(()) }
+    /// ```
+    pub fn builder() -> DeviceDiscoveryBuilder {
+        DeviceDiscoveryBuilder {
+            discovery: DeviceDiscovery::new(),
         }
     }
 
+    /// Get the local address the discovery socket is bound to.
+    pub fn bind_addr(&self) -> SocketAddr {
+        self.bind_addr
+    }
+
+    /// Get the name of the network interface the discovery socket is bound
+    /// to.
+    pub fn interface(&self) -> Option<&str> {
+        self.interface.as_deref()
+    }
+
+    /// Get the address of the interface used for outgoing multicast
+    /// messages.
+    pub fn multicast_interface(&self) -> Option<Ipv4Addr> {
+        self.multicast_interface
+    }
+
+    /// Get whether sending to broadcast addresses is enabled.
+    pub fn broadcast(&self) -> bool {
+        self.broadcast
+    }
+
+    /// Get the broadcast address.
+    pub fn broadcast_addr(&self) -> SocketAddr {
+        self.broadcast_addr
+    }
+
+    /// Get the number of discovery rounds.
+    pub fn rounds(&self) -> u8 {
+        self.rounds
+    }
+
+    /// Get the timeout used to wait for replies after each round's broadcast.
+    pub fn broadcast_timeout(&self) -> Duration {
+        self.broadcast_timeout
+    }
+
+    /// Get the port number used for fetching the device information.
+    pub fn fetch_port(&self) -> u16 {
+        self.fetch_port
+    }
+
+    /// Get the timeout used for fetching the device information.
+    pub fn fetch_timeout(&self) -> Duration {
+        self.fetch_timeout
+    }
+
+    /// Get whether the discovery stops after a round without new devices.
+    pub fn stop_early(&self) -> bool {
+        self.stop_early
+    }
+
+    /// Get whether the timeout of later rounds adapts to the reply latency.
+    pub fn adaptive_timeout(&self) -> bool {
+        self.adaptive_timeout
+    }
+
+    /// Set the local address the discovery socket is bound to.
+    ///
+    /// Defaults to `0.0.0.0:0`, leaving the choice of the network interface
+    /// to the operating system. On hosts with multiple network interfaces
+    /// the address of a specific interface can be used to send the broadcast
+    /// over that interface. In that case the broadcast address should be set
+    /// to the directed broadcast address of that interface's network (e.g.
+    /// `192.168.5.255:7053`) or the interface should be selected using
+    /// `set_interface`.
+    pub fn set_bind_addr(&mut self, addr: SocketAddr) {
+        self.bind_addr = addr;
+    }
+
+    /// Set the name of the network interface the discovery socket is bound
+    /// to (e.g. `eth1`).
+    ///
+    /// In contrast to `set_bind_addr` this uses `SO_BINDTODEVICE`, so that
+    /// the limited broadcast address `255.255.255.255` is sent over the
+    /// given interface instead of the one of the default route. This is
+    /// only supported on Linux and Android and may require the
+    /// `CAP_NET_RAW` capability, on other platforms the discovery fails.
+    ///
+    /// Defaults to `None`.
+    pub fn set_interface(&mut self, interface: Option<String>) {
+        self.interface = interface;
+    }
+
+    /// Set the address of the interface used for outgoing multicast
+    /// messages (`IP_MULTICAST_IF`).
+    ///
+    /// Only used if the broadcast address is a multicast address. Defaults
+    /// to `None`, leaving the choice to the operating system.
+    pub fn set_multicast_interface(&mut self, addr: Option<Ipv4Addr>) {
+        self.multicast_interface = addr;
+    }
+
+    /// Set whether sending to broadcast addresses is enabled
+    /// (`SO_BROADCAST`).
+    ///
+    /// Can be disabled if the broadcast address is a unicast or multicast
+    /// address. Defaults to `true`.
+    pub fn set_broadcast(&mut self, broadcast: bool) {
+        self.broadcast = broadcast;
+    }
+
     /// Set the broadcast address.
     pub fn set_broadcast_addr(&mut self, addr: SocketAddr) {
         self.broadcast_addr = addr;
@@ -78,6 +284,23 @@ impl DeviceDiscovery {
         self.fetch_timeout = timeout;
     }
 
+    /// Set whether the discovery stops after a round without new devices.
+    ///
+    /// The first round is always followed by a second one, in case the
+    /// broadcast itself got lost. Defaults to `false`.
+    pub fn set_stop_early(&mut self, stop_early: bool) {
+        self.stop_early = stop_early;
+    }
+
+    /// Set whether the timeout of later rounds adapts to the reply latency.
+    ///
+    /// If enabled, every round after a reply was received waits for twice
+    /// the longest latency observed so far (but at least 50 ms and at most
+    /// the broadcast timeout). Defaults to `false`.
+    pub fn set_adaptive_timeout(&mut self, adaptive_timeout: bool) {
+        self.adaptive_timeout = adaptive_timeout;
+    }
+
     /// Discover all VBus-over-TCP devices and return their device information.
     ///
     /// # Examples
@@ -107,6 +330,39 @@ impl DeviceDiscovery {
         Ok(devices)
     }
 
+    /// Discover all VBus-over-TCP devices, falling back to scanning the
+    /// network using `scanner` if no device replied to the broadcasts.
+    pub async fn discover_devices_with_fallback(
+        &self,
+        scanner: &NetworkScanner,
+    ) -> Result<Vec<DeviceInformation>> {
+        let devices = self.discover_devices().await?;
+        if devices.is_empty() {
+            scanner.scan_devices().await
+        } else {
+            Ok(devices)
+        }
+    }
+
+    /// Discover all VBus-over-TCP devices, update their entries in
+    /// `registry` and save it.
+    ///
+    /// Devices that are not found keep their previous entries, so that the
+    /// registry can still be used to reconnect to them.
+    pub async fn discover_devices_into_registry(
+        &self,
+        registry: &mut DeviceRegistry,
+    ) -> Result<Vec<DeviceInformation>> {
+        let devices = self.discover_devices().await?;
+
+        for device in &devices {
+            registry.update(device);
+        }
+        registry.save().await?;
+
+        Ok(devices)
+    }
+
     /// Discover all VBus-over-TCP devices and return their addresses.
     ///
     /// # Examples
@@ -122,43 +378,362 @@ impl DeviceDiscovery {
     /// # Ok(()) }) }
     /// ```
     pub async fn discover_device_addresses(&self) -> Result<Vec<SocketAddr>> {
-        let broadcast_socket = UdpSocket::bind("0.0.0.0:0").await?;
-        broadcast_socket.set_broadcast(true)?;
+        self.discover_device_addresses_with_progress(|_| {}).await
+    }
+
+    /// Discover all VBus-over-TCP devices and return their addresses, reporting
+    /// the progress to `observer`.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # fn main() -> async_resol_vbus::Result<()> { async_std::task::block_on(async {
+    /// #
+    /// use async_resol_vbus::{DeviceDiscovery, DiscoveryProgress};
+    ///
+    /// let discovery = DeviceDiscovery::new();
+    /// let addresses = discovery
+    ///     .discover_device_addresses_with_progress(|progress| {
+    ///         if let DiscoveryProgress::RoundFinished { round, rounds, address_count } = progress {
+    ///             println!("round {}/{}, {} devices so far", round, rounds, address_count);
+    ///         }
+    ///     })
+    ///     .await?;
+    /// #
+    /// # Ok(()) }) }
+    /// ```
+    pub async fn discover_device_addresses_with_progress<F>(
+        &self,
+        observer: F,
+    ) -> Result<Vec<SocketAddr>>
+    where
+        F: FnMut(DiscoveryProgress),
+    {
+        Ok(self.discover(observer).await?.addresses)
+    }
+
+    /// Discover all VBus-over-TCP devices and return their addresses together
+    /// with the timing of each round.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # fn main() -> async_resol_vbus::Result<()> { async_std::task::block_on(async {
+    /// #
+    /// use async_resol_vbus::DeviceDiscovery;
+    ///
+    /// let mut discovery = DeviceDiscovery::new();
+    /// discovery.set_stop_early(true);
+    /// discovery.set_adaptive_timeout(true);
+    ///
+    /// let report = discovery.discover_device_addresses_with_report().await?;
+    /// for round in report.rounds {
+    ///     println!("round {} took {:?}", round.round, round.duration);
+    /// }
+    /// #
+    /// # Ok(()) }) }
+    /// ```
+    pub async fn discover_device_addresses_with_report(&self) -> Result<DiscoveryReport> {
+        self.discover(|_| {}).await
+    }
 
-        let query_bytes = b"---RESOL-BROADCAST-QUERY---";
-        let reply_bytes = b"---RESOL-BROADCAST-REPLY---";
+    async fn discover<F>(&self, mut observer: F) -> Result<DiscoveryReport>
+    where
+        F: FnMut(DiscoveryProgress),
+    {
+        let broadcast_socket = self.bind_socket().await?;
 
         let mut addresses = HashSet::new();
-        for _ in 0..self.rounds {
+        let mut rounds = Vec::new();
+        let mut max_latency: Option<Duration> = None;
+        for round in 1..=self.rounds {
+            observer(DiscoveryProgress::RoundStarted {
+                round,
+                rounds: self.rounds,
+            });
+
+            let timeout = match max_latency {
+                Some(latency) if self.adaptive_timeout => (latency * 2)
+                    .max(MIN_ADAPTIVE_TIMEOUT)
+                    .min(self.broadcast_timeout),
+                _ => self.broadcast_timeout,
+            };
+
             broadcast_socket
-                .send_to(query_bytes, &self.broadcast_addr)
+                .send_to(QUERY_BYTES, &self.broadcast_addr)
                 .await?;
 
-            let future = async_std::io::timeout::<_, ()>(self.broadcast_timeout, async {
-                let mut buf = [0u8; 64];
-                loop {
-                    let (len, address) = broadcast_socket.recv_from(&mut buf).await?;
-                    if len == reply_bytes.len() && &buf[0..len] == reply_bytes {
-                        addresses.insert(address);
-                    }
+            let start = Instant::now();
+            let deadline = start + timeout;
+
+            let mut round_latency: Option<Duration> = None;
+            let mut new_address_count = 0;
+            let mut buf = [0u8; 64];
+            loop {
+                let now = Instant::now();
+                if now >= deadline {
+                    break;
                 }
+
+                let (len, address) = match async_std::io::timeout(
+                    deadline - now,
+                    broadcast_socket.recv_from(&mut buf),
+                )
+                .await
+                {
+                    Ok(result) => result,
+                    Err(_) => break,
+                };
+
+                if &buf[0..len] != REPLY_BYTES {
+                    continue;
+                }
+
+                let latency = start.elapsed();
+                round_latency = Some(round_latency.map_or(latency, |l| l.max(latency)));
+
+                if addresses.insert(address) {
+                    new_address_count += 1;
+                    observer(DiscoveryProgress::AddressFound(address));
+                }
+            }
+
+            if let Some(latency) = round_latency {
+                max_latency = Some(max_latency.map_or(latency, |l| l.max(latency)));
+            }
+
+            rounds.push(DiscoveryRound {
+                round,
+                timeout,
+                duration: start.elapsed(),
+                new_address_count,
+                max_latency: round_latency,
+            });
+
+            observer(DiscoveryProgress::RoundFinished {
+                round,
+                rounds: self.rounds,
+                address_count: addresses.len(),
             });
 
-            drop(future.await);
+            if self.stop_early && round > 1 && new_address_count == 0 {
+                break;
+            }
+        }
+
+        Ok(DiscoveryReport {
+            addresses: addresses.into_iter().collect(),
+            rounds,
+        })
+    }
+
+    /// Discover VBus-over-TCP devices until one matches the `predicate`.
+    ///
+    /// The device information of every newly discovered device is fetched
+    /// immediately and passed to the `predicate`. The discovery stops as soon
+    /// as the `predicate` returns `true` for a device, returning its device
+    /// information. If no matching device was found after all rounds, `None`
+    /// is returned.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # fn main() -> async_resol_vbus::Result<()> { async_std::task::block_on(async {
+    /// #
+    /// use async_resol_vbus::DeviceDiscovery;
+    ///
+    /// let discovery = DeviceDiscovery::new();
+    /// let device = discovery
+    ///     .discover_first(|device| device.serial.as_deref() == Some("001E66000000"))
+    ///     .await?;
+    /// #
+    /// # Ok(()) }) }
+    /// ```
+    pub async fn discover_first<F>(&self, mut predicate: F) -> Result<Option<DeviceInformation>>
+    where
+        F: FnMut(&DeviceInformation) -> bool,
+    {
+        let broadcast_socket = self.bind_socket().await?;
+
+        let mut addresses = HashSet::new();
+        for _ in 0..self.rounds {
+            broadcast_socket
+                .send_to(QUERY_BYTES, &self.broadcast_addr)
+                .await?;
+
+            let deadline = Instant::now() + self.broadcast_timeout;
+
+            let mut buf = [0u8; 64];
+            loop {
+                let now = Instant::now();
+                if now >= deadline {
+                    break;
+                }
+
+                let (len, mut address) = match async_std::io::timeout(
+                    deadline - now,
+                    broadcast_socket.recv_from(&mut buf),
+                )
+                .await
+                {
+                    Ok(result) => result,
+                    Err(_) => break,
+                };
+
+                if &buf[0..len] != REPLY_BYTES || !addresses.insert(address) {
+                    continue;
+                }
+
+                address.set_port(self.fetch_port);
+
+                if let Ok(device) = DeviceInformation::fetch(address, self.fetch_timeout).await {
+                    if predicate(&device) {
+                        return Ok(Some(device));
+                    }
+                }
+            }
         }
 
-        let addresses = addresses.into_iter().collect();
+        Ok(None)
+    }
+
+    async fn bind_socket(&self) -> Result<UdpSocket> {
+        let socket = UdpSocket::bind(self.bind_addr).await?;
+        let sock_ref = SockRef::from(&socket);
+        sock_ref.set_broadcast(self.broadcast)?;
+        if let Some(ref addr) = self.multicast_interface {
+            sock_ref.set_multicast_if_v4(addr)?;
+        }
+        if let Some(ref interface) = self.interface {
+            bind_device(&sock_ref, interface)?;
+        }
+        Ok(socket)
+    }
+}
 
-        Ok(addresses)
+#[cfg(any(target_os = "android", target_os = "fuchsia", target_os = "linux"))]
+fn bind_device(socket: &SockRef<'_>, interface: &str) -> Result<()> {
+    match socket.bind_device(Some(interface.as_bytes())) {
+        Ok(()) => Ok(()),
+        Err(err) => Err(format!(
+            "Unable to bind discovery socket to interface {:?}: {}",
+            interface, err
+        )
+        .into()),
     }
 }
 
+#[cfg(not(any(target_os = "android", target_os = "fuchsia", target_os = "linux")))]
+fn bind_device(_socket: &SockRef<'_>, _interface: &str) -> Result<()> {
+    Err("Binding to a network interface is not supported on this platform".into())
+}
+
 impl Default for DeviceDiscovery {
     fn default() -> DeviceDiscovery {
         DeviceDiscovery::new()
     }
 }
 
+/// A builder for `DeviceDiscovery` instances, see `DeviceDiscovery::builder`.
+#[derive(Debug, Clone)]
+pub struct DeviceDiscoveryBuilder {
+    discovery: DeviceDiscovery,
+}
+
+impl DeviceDiscoveryBuilder {
+    /// Set the local address the discovery socket is bound to.
+    ///
+    /// See `DeviceDiscovery::set_bind_addr` for details.
+    pub fn bind_addr(mut self, addr: SocketAddr) -> Self {
+        self.discovery.bind_addr = addr;
+        self
+    }
+
+    /// Set the name of the network interface the discovery socket is bound
+    /// to.
+    ///
+    /// See `DeviceDiscovery::set_interface` for details.
+    pub fn interface(mut self, interface: &str) -> Self {
+        self.discovery.interface = Some(interface.to_string());
+        self
+    }
+
+    /// Set the address of the interface used for outgoing multicast
+    /// messages.
+    ///
+    /// See `DeviceDiscovery::set_multicast_interface` for details.
+    pub fn multicast_interface(mut self, addr: Ipv4Addr) -> Self {
+        self.discovery.multicast_interface = Some(addr);
+        self
+    }
+
+    /// Set whether sending to broadcast addresses is enabled.
+    ///
+    /// See `DeviceDiscovery::set_broadcast` for details.
+    pub fn broadcast(mut self, broadcast: bool) -> Self {
+        self.discovery.broadcast = broadcast;
+        self
+    }
+
+    /// Set the broadcast address.
+    pub fn broadcast_addr(mut self, addr: SocketAddr) -> Self {
+        self.discovery.broadcast_addr = addr;
+        self
+    }
+
+    /// Set the number of discovery rounds. Must be at least 1.
+    pub fn rounds(mut self, rounds: u8) -> Self {
+        self.discovery.rounds = rounds;
+        self
+    }
+
+    /// Set the timeout used to wait for replies after each round's broadcast.
+    pub fn broadcast_timeout(mut self, timeout: Duration) -> Self {
+        self.discovery.broadcast_timeout = timeout;
+        self
+    }
+
+    /// Set the port number used for fetching the device information.
+    pub fn fetch_port(mut self, port: u16) -> Self {
+        self.discovery.fetch_port = port;
+        self
+    }
+
+    /// Set the timeout used for fetching the device information.
+    pub fn fetch_timeout(mut self, timeout: Duration) -> Self {
+        self.discovery.fetch_timeout = timeout;
+        self
+    }
+
+    /// Set whether the discovery stops after a round without new devices.
+    ///
+    /// See `DeviceDiscovery::set_stop_early` for details.
+    pub fn stop_early(mut self, stop_early: bool) -> Self {
+        self.discovery.stop_early = stop_early;
+        self
+    }
+
+    /// Set whether the timeout of later rounds adapts to the reply latency.
+    ///
+    /// See `DeviceDiscovery::set_adaptive_timeout` for details.
+    pub fn adaptive_timeout(mut self, adaptive_timeout: bool) -> Self {
+        self.discovery.adaptive_timeout = adaptive_timeout;
+        self
+    }
+
+    /// Validate the configuration and create the `DeviceDiscovery`.
+    pub fn build(self) -> Result<DeviceDiscovery> {
+        if self.discovery.rounds < 1 {
+            return Err("Number of discovery rounds must be at least 1".into());
+        }
+        if self.discovery.broadcast_timeout == Duration::from_secs(0) {
+            return Err("Broadcast timeout must not be zero".into());
+        }
+
+        Ok(self.discovery)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use async_std::net::{SocketAddr, TcpListener, UdpSocket};
@@ -216,6 +791,18 @@ mod tests {
 
                 assert_eq!(1, devices.len());
 
+                let device = discovery
+                    .discover_first(|device| device.serial.as_deref() == Some("001E66xxxxxx"))
+                    .await?;
+
+                assert_eq!(Some("DL2"), device.unwrap().product.as_deref());
+
+                discovery.set_rounds(1);
+
+                let device = discovery.discover_first(|_| false).await?;
+
+                assert!(device.is_none());
+
                 Ok(())
             });
 
@@ -226,4 +813,165 @@ mod tests {
             Ok(())
         })
     }
+
+    #[test]
+    fn test_bind_addr() -> Result<()> {
+        async_std::task::block_on(async {
+            let device_socket = UdpSocket::bind("127.0.0.1:0").await?;
+            let device_addr = device_socket.local_addr()?;
+
+            let bind_addr = UdpSocket::bind("127.0.0.1:0").await?.local_addr()?;
+
+            let device_future = async_std::task::spawn::<_, Result<SocketAddr>>(async move {
+                let mut buf = [0u8; 256];
+
+                let (len, addr) = device_socket.recv_from(&mut buf).await?;
+                assert_eq!(b"---RESOL-BROADCAST-QUERY---", &buf[0..len]);
+
+                device_socket
+                    .send_to(b"---RESOL-BROADCAST-REPLY---", addr)
+                    .await?;
+
+                Ok(addr)
+            });
+
+            let mut discovery = DeviceDiscovery::new();
+            discovery.set_bind_addr(bind_addr);
+            discovery.set_broadcast_addr(device_addr);
+            discovery.set_rounds(1);
+            discovery.set_broadcast_timeout(Duration::from_millis(100));
+
+            let mut progress = Vec::new();
+            let addresses = discovery
+                .discover_device_addresses_with_progress(|p| progress.push(p))
+                .await?;
+
+            assert_eq!(vec![device_addr], addresses);
+            assert_eq!(
+                vec![
+                    DiscoveryProgress::RoundStarted {
+                        round: 1,
+                        rounds: 1
+                    },
+                    DiscoveryProgress::AddressFound(device_addr),
+                    DiscoveryProgress::RoundFinished {
+                        round: 1,
+                        rounds: 1,
+                        address_count: 1
+                    },
+                ],
+                progress
+            );
+            assert_eq!(bind_addr, device_future.await?);
+
+            Ok(())
+        })
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_interface_options() -> Result<()> {
+        async_std::task::block_on(async {
+            let discovery = DeviceDiscovery::builder()
+                .bind_addr("127.0.0.1:0".parse()?)
+                .interface("lo")
+                .multicast_interface(Ipv4Addr::new(127, 0, 0, 1))
+                .broadcast(false)
+                .build()?;
+
+            assert_eq!(Some("lo"), discovery.interface());
+
+            let socket = discovery.bind_socket().await?;
+            let sock_ref = SockRef::from(&socket);
+            assert_eq!(Some(b"lo".to_vec()), sock_ref.device()?);
+            assert_eq!(Ipv4Addr::new(127, 0, 0, 1), sock_ref.multicast_if_v4()?);
+            assert!(!sock_ref.broadcast()?);
+
+            let mut discovery = discovery;
+            discovery.set_interface(Some("does-not-exist".into()));
+            let result = discovery.bind_socket().await;
+            assert!(result.is_err());
+
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn test_report() -> Result<()> {
+        async_std::task::block_on(async {
+            let device_socket = UdpSocket::bind("127.0.0.1:0").await?;
+            let device_addr = device_socket.local_addr()?;
+
+            let device_future = async_std::task::spawn::<_, Result<()>>(async move {
+                let mut buf = [0u8; 256];
+                loop {
+                    let (_, addr) = device_socket.recv_from(&mut buf).await?;
+                    device_socket.send_to(REPLY_BYTES, addr).await?;
+                }
+            });
+
+            let discovery = DeviceDiscovery::builder()
+                .bind_addr("127.0.0.1:0".parse()?)
+                .broadcast_addr(device_addr)
+                .rounds(5)
+                .broadcast_timeout(Duration::from_millis(500))
+                .stop_early(true)
+                .adaptive_timeout(true)
+                .build()?;
+
+            let start = Instant::now();
+            let report = discovery.discover_device_addresses_with_report().await?;
+
+            assert_eq!(vec![device_addr], report.addresses);
+            assert_eq!(2, report.rounds.len());
+            assert_eq!(1, report.rounds[0].new_address_count);
+            assert_eq!(Duration::from_millis(500), report.rounds[0].timeout);
+            assert!(report.rounds[0].max_latency.is_some());
+            assert_eq!(0, report.rounds[1].new_address_count);
+            assert!(report.rounds[1].timeout < Duration::from_millis(500));
+            assert!(start.elapsed() < Duration::from_millis(1000));
+
+            drop(device_future);
+
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn test_builder() -> Result<()> {
+        let bind_addr = "127.0.0.1:0".parse::<SocketAddr>()?;
+
+        let discovery = DeviceDiscovery::builder()
+            .bind_addr(bind_addr)
+            .rounds(2)
+            .fetch_port(8080)
+            .fetch_timeout(Duration::from_millis(300))
+            .build()?;
+
+        assert_eq!(bind_addr, discovery.bind_addr());
+        assert_eq!(
+            "255.255.255.255:7053",
+            discovery.broadcast_addr().to_string()
+        );
+        assert_eq!(2, discovery.rounds());
+        assert_eq!(Duration::from_millis(500), discovery.broadcast_timeout());
+        assert_eq!(8080, discovery.fetch_port());
+        assert_eq!(Duration::from_millis(300), discovery.fetch_timeout());
+
+        let result = DeviceDiscovery::builder().rounds(0).build();
+        assert_eq!(
+            Err("Number of discovery rounds must be at least 1".into()),
+            result.map(|_| ())
+        );
+
+        let result = DeviceDiscovery::builder()
+            .broadcast_timeout(Duration::from_secs(0))
+            .build();
+        assert_eq!(
+            Err("Broadcast timeout must not be zero".into()),
+            result.map(|_| ())
+        );
+
+        Ok(())
+    }
 }
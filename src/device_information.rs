@@ -1,11 +1,36 @@
-use std::{net::SocketAddr, time::Duration};
-
-use async_std::{net::TcpStream, prelude::*};
-
-use crate::error::Result;
+use std::{
+    hash::{Hash, Hasher},
+    net::SocketAddr,
+    time::Duration,
+};
+
+use crate::{error::Result, http_client::HttpClient};
+
+/// The products providing multiple VBus channels, selected using the
+/// `CHANNEL` command.
+const MULTI_CHANNEL_PRODUCTS: &[&str] = &["DL3"];
+
+/// The products providing a single VBus channel.
+const SINGLE_CHANNEL_PRODUCTS: &[&str] = &["DL2", "KM1", "KM2"];
+
+/// The identity of a VBus-over-TCP device, see `DeviceInformation::identity_key`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum DeviceIdentity {
+    /// The device is identified by its serial number.
+    Serial(String),
+
+    /// The device did not report a serial number and is identified by its address.
+    Address(SocketAddr),
+}
 
 /// A struct containing information about a VBus-over-TCP device.
+///
+/// Two `DeviceInformation` values are considered equal if their
+/// `identity_key`s are equal. All other information is ignored for the
+/// `PartialEq` and `Hash` implementations.
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct DeviceInformation {
     /// The `SocketAddr` of the web server.
     pub address: SocketAddr,
@@ -33,6 +58,66 @@ pub struct DeviceInformation {
 }
 
 impl DeviceInformation {
+    /// Get the key identifying this device.
+    ///
+    /// The serial number is used if it is present, otherwise the address
+    /// of the web server is used.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # fn main() -> async_resol_vbus::Result<()> {
+    /// use async_resol_vbus::{DeviceIdentity, DeviceInformation};
+    ///
+    /// let address = "192.168.5.217:80".parse()?;
+    /// let device = DeviceInformation::parse(address, "serial = \"001E66000000\"")?;
+    /// assert_eq!(DeviceIdentity::Serial("001E66000000".into()), device.identity_key());
+    ///
+    /// let device = DeviceInformation::parse(address, "")?;
+    /// assert_eq!(DeviceIdentity::Address(address), device.identity_key());
+    /// # Ok(()) }
+    /// ```
+    pub fn identity_key(&self) -> DeviceIdentity {
+        match self.serial {
+            Some(ref serial) => DeviceIdentity::Serial(serial.clone()),
+            None => DeviceIdentity::Address(self.address),
+        }
+    }
+
+    /// Check whether the device provides multiple VBus channels, based on
+    /// its `product`.
+    ///
+    /// Returns `None` if the product is unknown. The features are not
+    /// used, since e.g. the DL3 also reports the `dl2` feature.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # fn main() -> async_resol_vbus::Result<()> {
+    /// use async_resol_vbus::DeviceInformation;
+    ///
+    /// let address = "192.168.5.217:80".parse()?;
+    /// let device = DeviceInformation::parse(address, "product = \"DL3\"")?;
+    /// assert_eq!(Some(true), device.has_multiple_channels());
+    ///
+    /// let device = DeviceInformation::parse(address, "product = \"DL2\"")?;
+    /// assert_eq!(Some(false), device.has_multiple_channels());
+    ///
+    /// let device = DeviceInformation::parse(address, "")?;
+    /// assert_eq!(None, device.has_multiple_channels());
+    /// # Ok(()) }
+    /// ```
+    pub fn has_multiple_channels(&self) -> Option<bool> {
+        let product = self.product.as_deref()?;
+        if MULTI_CHANNEL_PRODUCTS.contains(&product) {
+            Some(true)
+        } else if SINGLE_CHANNEL_PRODUCTS.contains(&product) {
+            Some(false)
+        } else {
+            None
+        }
+    }
+
     pub(crate) fn find_http_body_idx(buf: &[u8]) -> Option<usize> {
         let mut body_idx = None;
 
@@ -71,39 +156,12 @@ impl DeviceInformation {
     /// # Ok(()) }) }
     /// ```
     pub async fn fetch(addr: SocketAddr, timeout: Duration) -> Result<DeviceInformation> {
-        let f = async {
-            let mut stream = TcpStream::connect(addr).await?;
-
-            let host = if addr.port() == 80 {
-                format!("{}", addr.ip())
-            } else {
-                format!("{}:{}", addr.ip(), addr.port())
-            };
-
-            let request_string = format!("GET /cgi-bin/get_resol_device_information HTTP/1.0\r\nHost: {}\r\nUser-Agent: async-resol-vbus.rs\r\n\r\n", host);
-
-            stream.write_all(request_string.as_bytes()).await?;
-
-            stream.flush().await?;
-
-            let mut buf = Vec::with_capacity(1024);
-            let len = stream.read_to_end(&mut buf).await?;
+        let mut client = HttpClient::new(addr);
+        client.set_timeout(timeout);
 
-            std::io::Result::Ok((buf, len))
-        };
+        let response = client.get("/cgi-bin/get_resol_device_information").await?;
 
-        // let (buf, len) = f.await?;
-        let (buf, len) = async_std::io::timeout(timeout, f).await?;
-
-        let buf = &buf[0..len];
-
-        let body_idx = match DeviceInformation::find_http_body_idx(buf) {
-            Some(idx) => idx,
-            None => return Err("No HTTP header separator found".into()),
-        };
-
-        let body_bytes = &buf[body_idx..];
-        let body = std::str::from_utf8(body_bytes)?;
+        let body = response.body_str()?;
 
         DeviceInformation::parse(addr, body)
     }
@@ -229,7 +287,22 @@ impl DeviceInformation {
     }
 }
 
+impl PartialEq for DeviceInformation {
+    fn eq(&self, other: &DeviceInformation) -> bool {
+        self.identity_key() == other.identity_key()
+    }
+}
+
+impl Eq for DeviceInformation {}
+
+impl Hash for DeviceInformation {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.identity_key().hash(state);
+    }
+}
+
 #[cfg(test)]
+#[allow(clippy::option_as_ref_deref)]
 mod tests {
     use async_std::net::{SocketAddr, TcpListener};
 
@@ -275,4 +348,32 @@ mod tests {
             Ok(())
         })
     }
+
+    #[test]
+    fn test_identity() -> Result<()> {
+        use std::collections::HashSet;
+
+        let addr1 = "192.168.5.217:80".parse::<SocketAddr>()?;
+        let addr2 = "192.168.5.218:80".parse::<SocketAddr>()?;
+
+        let device1 = DeviceInformation::parse(addr1, "serial = \"001E66000001\"")?;
+        let device2 =
+            DeviceInformation::parse(addr2, "serial = \"001E66000001\"\nname = \"Moved\"")?;
+        let device3 = DeviceInformation::parse(addr1, "")?;
+        let device4 = DeviceInformation::parse(addr1, "name = \"Other\"")?;
+        let device5 = DeviceInformation::parse(addr2, "")?;
+
+        assert_eq!(device1, device2);
+        assert_ne!(device1, device3);
+        assert_eq!(device3, device4);
+        assert_ne!(device3, device5);
+
+        let set = vec![device1, device2, device3, device4, device5]
+            .into_iter()
+            .collect::<HashSet<_>>();
+
+        assert_eq!(3, set.len());
+
+        Ok(())
+    }
 }
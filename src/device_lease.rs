@@ -0,0 +1,187 @@
+use std::{
+    fs::{File, OpenOptions, TryLockError},
+    io::{Read, Seek, SeekFrom, Write},
+    path::{Path, PathBuf},
+    sync::Mutex,
+};
+
+use crate::error::Result;
+
+static LEASED_DEVICES: Mutex<Vec<String>> = Mutex::new(Vec::new());
+
+/// An exclusive claim on a device within the current process.
+///
+/// Devices like the DL2 only allow a single data connection: a second one
+/// silently starves the first one. A `DeviceLease` is acquired before
+/// connecting and released when it is dropped, so that a second attempt
+/// to connect to the same device fails with a clear error instead.
+///
+/// The `ConnectionManager` acquires a lease for its device automatically.
+///
+/// The lease only covers the current process by default. Deployments
+/// running multiple processes on the same host can additionally use
+/// `acquire_with_lock_file` to hold an advisory lock on a file named after
+/// the device. The OS releases that lock when the process exits, so a
+/// crashed process does not leave a stale lock behind.
+///
+/// # Examples
+///
+/// ```
+/// # fn main() -> async_resol_vbus::Result<()> {
+/// use async_resol_vbus::DeviceLease;
+///
+/// let lease = DeviceLease::acquire("192.168.5.217:7053")?;
+/// assert!(DeviceLease::acquire("192.168.5.217:7053").is_err());
+///
+/// drop(lease);
+/// assert!(DeviceLease::acquire("192.168.5.217:7053").is_ok());
+/// # Ok(()) }
+/// ```
+#[derive(Debug)]
+pub struct DeviceLease {
+    key: String,
+    lock_file: Option<File>,
+}
+
+impl DeviceLease {
+    /// Acquire the lease for the device identified by `key`, e.g. its
+    /// address or serial number.
+    pub fn acquire(key: &str) -> Result<DeviceLease> {
+        let mut leased_devices = LEASED_DEVICES.lock().unwrap();
+        if leased_devices.iter().any(|leased| leased == key) {
+            return Err(format!("Device {} is already connected within this process", key).into());
+        }
+
+        leased_devices.push(key.to_string());
+        Ok(DeviceLease {
+            key: key.to_string(),
+            lock_file: None,
+        })
+    }
+
+    /// Acquire the lease for the device identified by `key` and lock the
+    /// corresponding lock file in `lock_dir`.
+    ///
+    /// Fails with an error naming the PID of the holder if another process
+    /// holds the lock file.
+    pub fn acquire_with_lock_file(key: &str, lock_dir: &Path) -> Result<DeviceLease> {
+        let mut lease = DeviceLease::acquire(key)?;
+
+        let mut file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(lock_file_path(key, lock_dir))?;
+
+        match file.try_lock() {
+            Ok(()) => {}
+            Err(TryLockError::WouldBlock) => {
+                let mut pid = String::new();
+                file.read_to_string(&mut pid)?;
+                return Err(format!("Device {} is busy (held by pid {})", key, pid.trim()).into());
+            }
+            Err(TryLockError::Error(err)) => return Err(err.into()),
+        }
+
+        file.set_len(0)?;
+        file.seek(SeekFrom::Start(0))?;
+        write!(file, "{}", std::process::id())?;
+
+        lease.lock_file = Some(file);
+        Ok(lease)
+    }
+
+    /// Get the key of the leased device.
+    pub fn key(&self) -> &str {
+        &self.key
+    }
+}
+
+/// Get the path of the lock file for the device identified by `key`.
+///
+/// All bytes of the key except ASCII alphanumerics, `-` and `.` are
+/// escaped as `_` followed by their hex value, so that different keys
+/// never share a lock file.
+fn lock_file_path(key: &str, lock_dir: &Path) -> PathBuf {
+    let mut name = String::with_capacity(key.len());
+    for b in key.bytes() {
+        if b.is_ascii_alphanumeric() || b == b'-' || b == b'.' {
+            name.push(b as char);
+        } else {
+            name.push_str(&format!("_{:02x}", b));
+        }
+    }
+    lock_dir.join(format!("{}.lock", name))
+}
+
+impl Drop for DeviceLease {
+    fn drop(&mut self) {
+        let mut leased_devices = LEASED_DEVICES.lock().unwrap();
+        leased_devices.retain(|leased| *leased != self.key);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_acquire() -> Result<()> {
+        let lease = DeviceLease::acquire("test_acquire")?;
+        assert_eq!("test_acquire", lease.key());
+        assert_eq!(
+            Err("Device test_acquire is already connected within this process".into()),
+            DeviceLease::acquire("test_acquire").map(|_| ())
+        );
+
+        let other = DeviceLease::acquire("test_acquire_other")?;
+        drop(lease);
+
+        let _lease = DeviceLease::acquire("test_acquire")?;
+        drop(other);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_acquire_with_lock_file() -> Result<()> {
+        let lock_dir = std::env::temp_dir().join(format!(
+            "async-resol-vbus-device-lease-{}",
+            std::process::id()
+        ));
+        std::fs::create_dir_all(&lock_dir)?;
+
+        let path = lock_file_path("192.168.5.217:7053", &lock_dir);
+        assert_eq!(lock_dir.join("192.168.5.217_3a7053.lock"), path);
+        assert_ne!(
+            lock_file_path("a.b:1", &lock_dir),
+            lock_file_path("a_b_1", &lock_dir)
+        );
+
+        let lease = DeviceLease::acquire_with_lock_file("192.168.5.217:7053", &lock_dir)?;
+        assert_eq!(
+            std::process::id().to_string(),
+            std::fs::read_to_string(&path)?
+        );
+        drop(lease);
+
+        // simulate another process holding the lock
+        let mut other = OpenOptions::new().write(true).open(&path)?;
+        other.try_lock().unwrap();
+        other.set_len(0)?;
+        write!(other, "12345")?;
+
+        assert_eq!(
+            Err("Device 192.168.5.217:7053 is busy (held by pid 12345)".into()),
+            DeviceLease::acquire_with_lock_file("192.168.5.217:7053", &lock_dir).map(|_| ())
+        );
+        drop(other);
+
+        let _lease = DeviceLease::acquire_with_lock_file("192.168.5.217:7053", &lock_dir)?;
+
+        std::fs::remove_dir_all(&lock_dir)?;
+
+        Ok(())
+    }
+}
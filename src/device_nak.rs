@@ -0,0 +1,71 @@
+use resol_vbus::Datagram;
+
+/// A negative reply of a VBus device to a request, decoded by a
+/// `NakDecoder`.
+///
+/// Transactions fail with an error carrying the `DeviceNak`, see
+/// `Error::device_nak`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DeviceNak {
+    /// The device-specific error code.
+    pub code: i32,
+
+    /// The description of the code, if the `NakDecoder` knows it.
+    pub meaning: Option<String>,
+}
+
+impl std::fmt::Display for DeviceNak {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.meaning {
+            Some(ref meaning) => write!(f, "code {}: {}", self.code, meaning),
+            None => write!(f, "code {}", self.code),
+        }
+    }
+}
+
+/// Decodes the negative replies a VBus device sends for invalid requests.
+///
+/// The VBus protocol does not define a common negative reply, so the
+/// commands and codes used depend on the device. A `NakDecoder` set using
+/// `LiveDataStream::set_nak_decoder` is called for every datagram the
+/// addressed device sends back during a transaction that is not the
+/// expected reply. If it returns a `DeviceNak`, the transaction fails
+/// right away instead of waiting for its timeout.
+///
+/// # Examples
+///
+/// ```
+/// use async_resol_vbus::{Datagram, DeviceNak, NakDecoder};
+///
+/// /// A device answering rejected requests with command `0x7F00` and the
+/// /// error code in `param16`.
+/// struct MyDeviceNaks;
+///
+/// impl NakDecoder for MyDeviceNaks {
+///     fn decode(&self, _request: &Datagram, reply: &Datagram) -> Option<DeviceNak> {
+///         if reply.command != 0x7F00 {
+///             return None;
+///         }
+///
+///         let meaning = match reply.param16 {
+///             1 => Some("Unknown index".to_string()),
+///             _ => None,
+///         };
+///         Some(DeviceNak {
+///             code: i32::from(reply.param16),
+///             meaning,
+///         })
+///     }
+/// }
+/// ```
+pub trait NakDecoder: Send + Sync {
+    /// Decode the `reply` to `request`, returning `None` if it is not a
+    /// negative reply.
+    fn decode(&self, request: &Datagram, reply: &Datagram) -> Option<DeviceNak>;
+}
+
+impl std::fmt::Debug for dyn NakDecoder {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("NakDecoder")
+    }
+}
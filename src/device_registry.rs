@@ -0,0 +1,301 @@
+use std::{
+    io::ErrorKind,
+    path::{Path, PathBuf},
+};
+
+use resol_vbus::chrono::{DateTime, Utc};
+
+use crate::{
+    device_information::{DeviceIdentity, DeviceInformation},
+    error::Result,
+    json::{parse_json, push_json_string, JsonValue},
+};
+
+/// A device stored in a `DeviceRegistry`.
+#[derive(Debug, Clone)]
+pub struct DeviceRegistryEntry {
+    /// The most recent information about the device.
+    pub device: DeviceInformation,
+
+    /// The timestamp when the device was last seen.
+    pub last_seen: DateTime<Utc>,
+}
+
+/// A registry of previously seen VBus-over-TCP devices, persisted as a
+/// JSON file.
+///
+/// The registry allows to reconnect to a known device by its serial number
+/// even if the device discovery is momentarily failing.
+///
+/// # Examples
+///
+/// ```no_run
+/// # fn main() -> async_resol_vbus::Result<()> { async_std::task::block_on(async {
+/// #
+/// use async_resol_vbus::{DeviceDiscovery, DeviceRegistry};
+///
+/// let mut registry = DeviceRegistry::load("devices.json").await?;
+///
+/// let discovery = DeviceDiscovery::new();
+/// if let Err(err) = discovery.discover_devices_into_registry(&mut registry).await {
+///     println!("Discovery failed: {:?}", err);
+/// }
+///
+/// if let Some(entry) = registry.find_by_serial("001E66000000") {
+///     let mut address = entry.device.address;
+///     address.set_port(7053);
+///     // ... connect to `address` ...
+/// }
+/// #
+/// # Ok(()) }) }
+/// ```
+#[derive(Debug, Clone)]
+pub struct DeviceRegistry {
+    path: PathBuf,
+    entries: Vec<DeviceRegistryEntry>,
+}
+
+impl DeviceRegistry {
+    /// Create a new empty `DeviceRegistry` that is persisted to `path`.
+    pub fn new<P: AsRef<Path>>(path: P) -> DeviceRegistry {
+        DeviceRegistry {
+            path: path.as_ref().to_path_buf(),
+            entries: Vec::new(),
+        }
+    }
+
+    /// Load a `DeviceRegistry` from `path`.
+    ///
+    /// Returns an empty registry if the file does not exist yet.
+    pub async fn load<P: AsRef<Path>>(path: P) -> Result<DeviceRegistry> {
+        let mut registry = DeviceRegistry::new(path);
+
+        match async_std::fs::read_to_string(&registry.path).await {
+            Ok(content) => registry.entries = entries_from_json(&content)?,
+            Err(err) if err.kind() == ErrorKind::NotFound => {}
+            Err(err) => return Err(err.into()),
+        }
+
+        Ok(registry)
+    }
+
+    /// Write the registry to its file.
+    ///
+    /// The content is written to a temporary file first which is then
+    /// renamed, so that a crash does not leave a truncated registry behind.
+    pub async fn save(&self) -> Result<()> {
+        let mut tmp_path = self.path.clone().into_os_string();
+        tmp_path.push(".tmp");
+
+        async_std::fs::write(&tmp_path, entries_to_json(&self.entries)).await?;
+        async_std::fs::rename(&tmp_path, &self.path).await?;
+
+        Ok(())
+    }
+
+    /// Get the path of the registry file.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Get all entries of the registry.
+    pub fn entries(&self) -> &[DeviceRegistryEntry] {
+        &self.entries
+    }
+
+    /// Add or update the entry for `device`, marking it as seen now.
+    pub fn update(&mut self, device: &DeviceInformation) {
+        self.update_at(device, Utc::now());
+    }
+
+    /// Add or update the entry for `device`, marking it as seen at `timestamp`.
+    pub fn update_at(&mut self, device: &DeviceInformation, timestamp: DateTime<Utc>) {
+        let key = device.identity_key();
+
+        match self
+            .entries
+            .iter_mut()
+            .find(|entry| entry.device.identity_key() == key)
+        {
+            Some(entry) => {
+                entry.device = device.clone();
+                if entry.last_seen < timestamp {
+                    entry.last_seen = timestamp;
+                }
+            }
+            None => self.entries.push(DeviceRegistryEntry {
+                device: device.clone(),
+                last_seen: timestamp,
+            }),
+        }
+    }
+
+    /// Find the entry for the device with the given identity.
+    pub fn find(&self, key: &DeviceIdentity) -> Option<&DeviceRegistryEntry> {
+        self.entries
+            .iter()
+            .find(|entry| &entry.device.identity_key() == key)
+    }
+
+    /// Find the entry for the device with the given serial number.
+    pub fn find_by_serial(&self, serial: &str) -> Option<&DeviceRegistryEntry> {
+        self.find(&DeviceIdentity::Serial(serial.to_string()))
+    }
+
+    /// Remove the entry for the device with the given identity.
+    pub fn remove(&mut self, key: &DeviceIdentity) -> Option<DeviceRegistryEntry> {
+        let idx = self
+            .entries
+            .iter()
+            .position(|entry| &entry.device.identity_key() == key)?;
+        Some(self.entries.remove(idx))
+    }
+}
+
+fn push_json_member(out: &mut String, key: &str, value: Option<&str>) {
+    if let Some(value) = value {
+        out.push(',');
+        push_json_string(out, key);
+        out.push(':');
+        push_json_string(out, value);
+    }
+}
+
+fn entries_to_json(entries: &[DeviceRegistryEntry]) -> String {
+    let mut content = String::new();
+    content.push_str("{\"devices\":[");
+    for (idx, entry) in entries.iter().enumerate() {
+        if idx > 0 {
+            content.push(',');
+        }
+
+        let device = &entry.device;
+        content.push_str("{\"address\":");
+        push_json_string(&mut content, &device.address.to_string());
+        push_json_member(
+            &mut content,
+            "lastSeen",
+            Some(&entry.last_seen.to_rfc3339()),
+        );
+        push_json_member(&mut content, "vendor", device.vendor.as_deref());
+        push_json_member(&mut content, "product", device.product.as_deref());
+        push_json_member(&mut content, "serial", device.serial.as_deref());
+        push_json_member(&mut content, "version", device.version.as_deref());
+        push_json_member(&mut content, "build", device.build.as_deref());
+        push_json_member(&mut content, "name", device.name.as_deref());
+        push_json_member(&mut content, "features", device.features.as_deref());
+        content.push('}');
+    }
+    content.push_str("]}\n");
+    content
+}
+
+fn entries_from_json(content: &str) -> Result<Vec<DeviceRegistryEntry>> {
+    let value = parse_json(content)?;
+
+    let devices = match value.get("devices") {
+        Some(JsonValue::Array(devices)) => devices,
+        _ => return Err("Device registry is missing the \"devices\" array".into()),
+    };
+
+    let mut entries = Vec::with_capacity(devices.len());
+    for device in devices {
+        let get_string = |key: &str| device.get(key).and_then(|v| v.as_str());
+        let get_owned = |key: &str| get_string(key).map(|s| s.to_string());
+
+        let address = get_string("address")
+            .ok_or("Device registry entry is missing the address")?
+            .parse()
+            .map_err(|_| "Invalid address in device registry entry")?;
+        let last_seen = get_string("lastSeen")
+            .ok_or("Device registry entry is missing the last seen timestamp")?;
+        let last_seen = DateTime::parse_from_rfc3339(last_seen)
+            .map_err(|_| "Invalid last seen timestamp in device registry entry")?
+            .with_timezone(&Utc);
+
+        entries.push(DeviceRegistryEntry {
+            device: DeviceInformation {
+                address,
+                vendor: get_owned("vendor"),
+                product: get_owned("product"),
+                serial: get_owned("serial"),
+                version: get_owned("version"),
+                build: get_owned("build"),
+                name: get_owned("name"),
+                features: get_owned("features"),
+            },
+            last_seen,
+        });
+    }
+
+    Ok(entries)
+}
+
+#[cfg(test)]
+mod tests {
+    use resol_vbus::chrono::TimeZone;
+
+    use super::*;
+
+    #[test]
+    fn test_update_and_find() -> Result<()> {
+        let address = "192.168.5.217:80".parse()?;
+        let device = DeviceInformation::parse(address, "serial = \"001E66000000\"")?;
+
+        let mut registry = DeviceRegistry::new("devices.json");
+        registry.update_at(&device, Utc.timestamp_opt(1_000, 0).unwrap());
+
+        let mut device = device.clone();
+        device.address = "192.168.5.218:80".parse()?;
+        registry.update_at(&device, Utc.timestamp_opt(2_000, 0).unwrap());
+
+        assert_eq!(1, registry.entries().len());
+
+        let entry = registry.find_by_serial("001E66000000").unwrap();
+        assert_eq!(device.address, entry.device.address);
+        assert_eq!(2_000, entry.last_seen.timestamp());
+
+        assert!(registry.find_by_serial("001E66000001").is_none());
+
+        assert!(registry.remove(&device.identity_key()).is_some());
+        assert!(registry.entries().is_empty());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_load_and_save() -> Result<()> {
+        async_std::task::block_on(async {
+            let path = std::env::temp_dir().join(format!(
+                "async-resol-vbus-registry-{}.json",
+                std::process::id()
+            ));
+
+            let registry = DeviceRegistry::load(&path).await?;
+            assert!(registry.entries().is_empty());
+
+            let address = "192.168.5.217:80".parse()?;
+            let device = DeviceInformation::parse(
+                address,
+                "serial = \"001E66000000\"\r\nname = \"Roof East\"\r\n",
+            )?;
+
+            let mut registry = DeviceRegistry::new(&path);
+            registry.update_at(&device, Utc.timestamp_opt(1_000, 0).unwrap());
+            registry.save().await?;
+
+            let registry = DeviceRegistry::load(&path).await?;
+            async_std::fs::remove_file(&path).await?;
+
+            assert_eq!(1, registry.entries().len());
+            let entry = &registry.entries()[0];
+            assert_eq!(address, entry.device.address);
+            assert_eq!(device.serial, entry.device.serial);
+            assert_eq!(device.name, entry.device.name);
+            assert_eq!(None, entry.device.vendor);
+            assert_eq!(1_000, entry.last_seen.timestamp());
+
+            Ok(())
+        })
+    }
+}
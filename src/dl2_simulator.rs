@@ -0,0 +1,378 @@
+use std::{net::Shutdown, sync::Arc};
+
+use async_std::{
+    net::{TcpListener, TcpStream, UdpSocket},
+    prelude::*,
+    sync::Mutex,
+};
+
+use crate::{
+    data_hub::DataHub,
+    device_discovery::{QUERY_BYTES, REPLY_BYTES},
+    device_information::DeviceInformation,
+    error::Result,
+    live_data_stream::bytes_from_data,
+    shutdown::ShutdownSignal,
+    tcp_server_handshake::TcpServerHandshake,
+};
+
+/// Simulates a DL2 data logger, e.g. for integration tests and demos.
+///
+/// The simulator provides the three services of a DL2:
+///
+/// - the discovery responder replying to `DeviceDiscovery` broadcasts
+/// - the web server providing `/cgi-bin/get_resol_device_information`
+/// - the VBus-over-TCP service, which sends every `Data` published to
+///   the simulator's `DataHub` to all connected clients
+///
+/// Data sent by the clients is ignored.
+///
+/// # Examples
+///
+/// ```no_run
+/// # fn main() -> async_resol_vbus::Result<()> { async_std::task::block_on(async {
+/// #
+/// use std::time::Duration;
+///
+/// use async_std::net::{TcpListener, UdpSocket};
+///
+/// use async_resol_vbus::{Data, Dl2Simulator, PacketBuilder};
+///
+/// let mut simulator = Dl2Simulator::new();
+/// simulator.set_serial(Some("001E66000000".into()));
+///
+/// let hub = simulator.hub();
+/// async_std::task::spawn(async move {
+///     loop {
+///         let packet = PacketBuilder::new(0x0010, 0x0100)
+///             .source_address(0x7E11)
+///             .payload(&[0xC7, 0x01, 0x00, 0x00])
+///             .build()
+///             .unwrap();
+///         hub.publish(Data::Packet(packet));
+///         async_std::task::sleep(Duration::from_secs(1)).await;
+///     }
+/// });
+///
+/// simulator
+///     .run(
+///         UdpSocket::bind("0.0.0.0:7053").await?,
+///         TcpListener::bind("0.0.0.0:80").await?,
+///         TcpListener::bind("0.0.0.0:7053").await?,
+///     )
+///     .await?;
+/// #
+/// # Ok(()) }) }
+/// ```
+#[derive(Debug)]
+pub struct Dl2Simulator {
+    serial: Option<String>,
+    name: Option<String>,
+    password: Option<String>,
+    hub: DataHub,
+}
+
+impl Dl2Simulator {
+    /// Create a new `Dl2Simulator`.
+    pub fn new() -> Dl2Simulator {
+        Dl2Simulator {
+            serial: None,
+            name: None,
+            password: Some("vbus".into()),
+            hub: DataHub::new(),
+        }
+    }
+
+    /// Set the serial number reported by the web server.
+    ///
+    /// Defaults to `None`.
+    pub fn set_serial(&mut self, serial: Option<String>) {
+        self.serial = serial;
+    }
+
+    /// Set the device name reported by the web server.
+    ///
+    /// Defaults to `None`.
+    pub fn set_name(&mut self, name: Option<String>) {
+        self.name = name;
+    }
+
+    /// Set the password clients have to provide using the `PASS` command.
+    ///
+    /// Defaults to `"vbus"`. `None` accepts clients with any or no
+    /// password.
+    pub fn set_password(&mut self, password: Option<String>) {
+        self.password = password;
+    }
+
+    /// Get the `DataHub` whose published `Data` is sent to the clients.
+    pub fn hub(&self) -> DataHub {
+        self.hub.clone()
+    }
+
+    /// Get the body of the `/cgi-bin/get_resol_device_information` reply.
+    fn device_information(&self) -> String {
+        let mut body = String::new();
+        body.push_str("vendor = \"RESOL\"\n");
+        body.push_str("product = \"DL2\"\n");
+        if let Some(ref serial) = self.serial {
+            body.push_str(&format!("serial = \"{}\"\n", serial));
+        }
+        body.push_str("version = \"2.2.0\"\n");
+        if let Some(ref name) = self.name {
+            body.push_str(&format!("name = \"{}\"\n", name));
+        }
+        body.push_str("features = \"vbus,dl2\"\n");
+        body
+    }
+
+    /// Serve the discovery responder on `discovery_socket`, the web server
+    /// on `web_listener` and the VBus-over-TCP service on `vbus_listener`.
+    ///
+    /// A real DL2 uses UDP port 7053, TCP port 80 and TCP port 7053.
+    /// Returns after accepting a VBus-over-TCP client failed.
+    pub async fn run(
+        self,
+        discovery_socket: UdpSocket,
+        web_listener: TcpListener,
+        vbus_listener: TcpListener,
+    ) -> Result<()> {
+        self.run_with_signal(discovery_socket, web_listener, vbus_listener, None)
+            .await
+    }
+
+    /// Like `run`, but also returns once the shutdown is triggered.
+    ///
+    /// After the shutdown was triggered, all sockets are closed, all
+    /// VBus-over-TCP clients are disconnected and the result is reported
+    /// to the `ShutdownSignal`.
+    pub async fn run_until_shutdown(
+        self,
+        discovery_socket: UdpSocket,
+        web_listener: TcpListener,
+        vbus_listener: TcpListener,
+        signal: ShutdownSignal,
+    ) {
+        let result = self
+            .run_with_signal(discovery_socket, web_listener, vbus_listener, Some(&signal))
+            .await;
+        signal.complete(result);
+    }
+
+    async fn run_with_signal(
+        self,
+        discovery_socket: UdpSocket,
+        web_listener: TcpListener,
+        vbus_listener: TcpListener,
+        signal: Option<&ShutdownSignal>,
+    ) -> Result<()> {
+        let simulator = Arc::new(self);
+        let clients = Arc::new(Mutex::new(Vec::new()));
+
+        let discovery_task = async_std::task::spawn(respond_to_discovery(discovery_socket));
+        let web_task = async_std::task::spawn(serve_web(simulator.clone(), web_listener));
+
+        let serve = serve_vbus(simulator, vbus_listener, clients.clone());
+        let result = match signal {
+            Some(signal) => signal.run_until(serve).await.unwrap_or(Ok(())),
+            None => serve.await,
+        };
+
+        discovery_task.cancel().await;
+        web_task.cancel().await;
+
+        for (_, stream) in clients.lock().await.drain(..) {
+            let _ = stream.shutdown(Shutdown::Both);
+        }
+
+        result
+    }
+
+    async fn accept_client(&self, stream: TcpStream) -> Result<TcpStream> {
+        TcpServerHandshake::start(stream)
+            .await?
+            .receive_pass_and_data_commands(self.password.as_deref())
+            .await
+    }
+}
+
+impl Default for Dl2Simulator {
+    fn default() -> Self {
+        Dl2Simulator::new()
+    }
+}
+
+async fn respond_to_discovery(socket: UdpSocket) -> Result<()> {
+    let mut buf = [0u8; 64];
+    loop {
+        let (len, addr) = socket.recv_from(&mut buf).await?;
+        if &buf[0..len] == QUERY_BYTES {
+            socket.send_to(REPLY_BYTES, addr).await?;
+        }
+    }
+}
+
+async fn serve_web(simulator: Arc<Dl2Simulator>, listener: TcpListener) -> Result<()> {
+    loop {
+        let (stream, _) = listener.accept().await?;
+        let simulator = simulator.clone();
+        async_std::task::spawn(async move {
+            let _ = serve_web_request(&simulator, stream).await;
+        });
+    }
+}
+
+async fn serve_web_request(simulator: &Dl2Simulator, mut stream: TcpStream) -> Result<()> {
+    let mut buf = Vec::new();
+    let mut read_buf = [0u8; 1024];
+    while DeviceInformation::find_http_body_idx(&buf).is_none() {
+        let len = stream.read(&mut read_buf).await?;
+        if len == 0 || buf.len() > 8192 {
+            return Ok(());
+        }
+        buf.extend_from_slice(&read_buf[0..len]);
+    }
+
+    let request = String::from_utf8_lossy(&buf);
+    let path = request.split(' ').nth(1).unwrap_or("");
+
+    let response = if path == "/cgi-bin/get_resol_device_information" {
+        let body = simulator.device_information();
+        format!(
+            "HTTP/1.1 200 OK\r\nContent-Type: text/plain\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+            body.len(),
+            body
+        )
+    } else {
+        "HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\nConnection: close\r\n\r\n".to_string()
+    };
+
+    stream.write_all(response.as_bytes()).await?;
+    Ok(())
+}
+
+async fn serve_vbus(
+    simulator: Arc<Dl2Simulator>,
+    listener: TcpListener,
+    clients: Arc<Mutex<Vec<(usize, TcpStream)>>>,
+) -> Result<()> {
+    let mut next_id = 0;
+    loop {
+        let (stream, _) = listener.accept().await?;
+
+        let id = next_id;
+        next_id += 1;
+
+        // subscribe before the handshake, so that no data published after
+        // the `DATA` command was acknowledged is missed
+        let receiver = simulator.hub.subscribe();
+
+        let simulator = simulator.clone();
+        let clients = clients.clone();
+        async_std::task::spawn(async move {
+            let mut stream = match simulator.accept_client(stream).await {
+                Ok(stream) => stream,
+                Err(_) => return,
+            };
+
+            clients.lock().await.push((id, stream.clone()));
+
+            while let Ok(data) = receiver.recv().await {
+                if stream.write_all(&bytes_from_data(&data)).await.is_err() {
+                    break;
+                }
+            }
+
+            clients
+                .lock()
+                .await
+                .retain(|(client_id, _)| *client_id != id);
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use resol_vbus::Data;
+
+    use crate::{
+        connect::{connect_tcp_live_data_stream, ConnectOptions},
+        data_builder::DatagramBuilder,
+        device_discovery::DeviceDiscovery,
+        shutdown::Shutdown,
+    };
+
+    use super::*;
+
+    #[test]
+    fn test_dl2_simulator() -> Result<()> {
+        async_std::task::block_on(async {
+            let discovery_socket = UdpSocket::bind("127.0.0.1:0").await?;
+            let discovery_addr = discovery_socket.local_addr()?;
+            let web_listener = TcpListener::bind("127.0.0.1:0").await?;
+            let web_addr = web_listener.local_addr()?;
+            let vbus_listener = TcpListener::bind("127.0.0.1:0").await?;
+            let vbus_addr = vbus_listener.local_addr()?;
+
+            let mut simulator = Dl2Simulator::new();
+            simulator.set_serial(Some("001E66000000".into()));
+            simulator.set_name(Some("Simulator".into()));
+            simulator.set_password(Some("secret".into()));
+            let hub = simulator.hub();
+
+            let shutdown = Shutdown::new();
+            async_std::task::spawn(simulator.run_until_shutdown(
+                discovery_socket,
+                web_listener,
+                vbus_listener,
+                shutdown.register("dl2"),
+            ));
+
+            let discovery = DeviceDiscovery::builder()
+                .bind_addr("127.0.0.1:0".parse()?)
+                .broadcast_addr(discovery_addr)
+                .rounds(1)
+                .broadcast_timeout(Duration::from_millis(200))
+                .build()?;
+            assert_eq!(
+                vec![discovery_addr],
+                discovery.discover_device_addresses().await?
+            );
+
+            let info = DeviceInformation::fetch(web_addr, Duration::from_secs(1)).await?;
+            assert_eq!(Some("DL2"), info.product.as_deref());
+            assert_eq!(Some("001E66000000"), info.serial.as_deref());
+            assert_eq!(Some("Simulator"), info.name.as_deref());
+            assert_eq!(Some("vbus,dl2"), info.features.as_deref());
+
+            let mut options = ConnectOptions::new();
+            assert!(
+                connect_tcp_live_data_stream(&vbus_addr.to_string(), &options)
+                    .await
+                    .is_err()
+            );
+
+            options.set_password(Some("secret".into()));
+            let mut lds = connect_tcp_live_data_stream(&vbus_addr.to_string(), &options).await?;
+
+            let dgram = DatagramBuilder::new(0x0020, 0x0100)
+                .source_address(0x7E11)
+                .param16(0x1234)
+                .param32(0x5678)
+                .build()?;
+            hub.publish(Data::Datagram(dgram));
+
+            let data = lds.receive_any_data(1000).await?.unwrap();
+            assert_eq!(0x5678, data.as_datagram().param32);
+
+            let results = shutdown.shutdown().await;
+            assert_eq!(vec![("dl2".to_string(), Ok(()))], results);
+
+            assert_eq!(None, lds.receive_any_data(1000).await?);
+
+            Ok(())
+        })
+    }
+}
@@ -1,7 +1,14 @@
+use crate::{device_nak::DeviceNak, write_stall::WriteStall};
+
 /// A common error type.
 #[derive(Debug, PartialEq)]
 pub struct Error {
     message: String,
+    write_stall: Option<WriteStall>,
+    unsupported_channel: bool,
+    write_denied: bool,
+    negative_reply: bool,
+    device_nak: Option<DeviceNak>,
 }
 
 /// A common result type.
@@ -12,13 +19,142 @@ pub trait IntoError: std::fmt::Display {}
 impl<T: IntoError> From<T> for Error {
     fn from(other: T) -> Error {
         let message = format!("{}", other);
-        Error { message }
+        Error {
+            message,
+            write_stall: None,
+            unsupported_channel: false,
+            write_denied: false,
+            negative_reply: false,
+            device_nak: None,
+        }
+    }
+}
+
+impl From<std::io::Error> for Error {
+    fn from(other: std::io::Error) -> Error {
+        let write_stall = other
+            .get_ref()
+            .and_then(|inner| inner.downcast_ref::<WriteStall>())
+            .copied();
+        Error {
+            message: format!("{}", other),
+            write_stall,
+            unsupported_channel: false,
+            write_denied: false,
+            negative_reply: false,
+            device_nak: None,
+        }
+    }
+}
+
+impl std::fmt::Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.message)
     }
 }
 
 impl IntoError for &str {}
 impl IntoError for String {}
-impl IntoError for std::io::Error {}
 impl IntoError for std::net::AddrParseError {}
 impl IntoError for std::str::Utf8Error {}
 impl IntoError for async_std::future::TimeoutError {}
+impl IntoError for resol_vbus::Error {}
+#[cfg(all(target_os = "linux", feature = "systemd"))]
+impl IntoError for zbus::Error {}
+#[cfg(feature = "serial")]
+impl IntoError for serialport::Error {}
+
+impl Error {
+    /// Create an error reporting that the device does not support the
+    /// requested VBus channel.
+    pub(crate) fn unsupported_channel(message: String) -> Error {
+        Error {
+            message,
+            write_stall: None,
+            unsupported_channel: true,
+            write_denied: false,
+            negative_reply: false,
+            device_nak: None,
+        }
+    }
+
+    /// Create an error reporting that a `WriteGuard` denied a write.
+    pub(crate) fn write_denied(message: String) -> Error {
+        Error {
+            message,
+            write_stall: None,
+            unsupported_channel: false,
+            write_denied: true,
+            negative_reply: false,
+            device_nak: None,
+        }
+    }
+
+    /// Create an error reporting that the service answered a command of
+    /// the VBus-over-TCP handshake with a negative reply.
+    pub(crate) fn negative_reply(message: String) -> Error {
+        Error {
+            message,
+            write_stall: None,
+            unsupported_channel: false,
+            write_denied: false,
+            negative_reply: true,
+            device_nak: None,
+        }
+    }
+
+    /// Create an error reporting that the device answered a request with
+    /// a negative reply.
+    pub(crate) fn from_device_nak(nak: DeviceNak) -> Error {
+        Error {
+            message: format!("Request rejected by the device ({})", nak),
+            write_stall: None,
+            unsupported_channel: false,
+            write_denied: false,
+            negative_reply: false,
+            device_nak: Some(nak),
+        }
+    }
+
+    /// Append `suffix` to the message, keeping the other details of the
+    /// error.
+    pub(crate) fn with_message_suffix(mut self, suffix: &str) -> Error {
+        self.message.push_str(suffix);
+        self
+    }
+
+    /// Get the message of the error.
+    pub(crate) fn message(&self) -> &str {
+        &self.message
+    }
+
+    /// Get the details of the write that did not complete within the write
+    /// timeout, if this error was caused by one.
+    pub fn write_stall(&self) -> Option<&WriteStall> {
+        self.write_stall.as_ref()
+    }
+
+    /// Check whether this error was caused by requesting a VBus channel
+    /// from a device that does not support multiple channels.
+    pub fn is_unsupported_channel(&self) -> bool {
+        self.unsupported_channel
+    }
+
+    /// Check whether this error was caused by a write denied by a
+    /// `WriteGuard`.
+    pub fn is_write_denied(&self) -> bool {
+        self.write_denied
+    }
+
+    /// Check whether this error was caused by a negative reply to a
+    /// command of the VBus-over-TCP handshake, e.g. a rejected password.
+    pub fn is_negative_reply(&self) -> bool {
+        self.negative_reply
+    }
+
+    /// Get the negative reply of the device, if this error was caused by
+    /// one, see `NakDecoder`.
+    pub fn device_nak(&self) -> Option<&DeviceNak> {
+        self.device_nak.as_ref()
+    }
+}
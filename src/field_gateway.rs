@@ -0,0 +1,353 @@
+use async_std::{
+    channel::{Receiver, Sender},
+    sync::Arc,
+};
+
+use resol_vbus::{specification::PacketFieldSpec, Data, DataSet, Language};
+
+use crate::{
+    changed_fields::changed_fields,
+    error::Result,
+    param_request::{request_param, ParamRequest},
+    shutdown::ShutdownSignal,
+    spec_cache::default_specification,
+};
+
+/// A bridge between VBus fields and another protocol, e.g. a KNX or BACnet
+/// stack.
+///
+/// Implementations are added to a `GatewayBridge`, which calls
+/// `value_changed` for every field whose value changed and hands out a
+/// `ParamWriter` to forward external writes to the VBus device.
+pub trait FieldGateway: Send {
+    /// Called for every field whose value changed, including the first
+    /// time it was received. The `value` is `None` if the field has no
+    /// valid value.
+    fn value_changed(&mut self, field_spec: &PacketFieldSpec, value: Option<f64>);
+
+    /// Called once when the gateway is added to a `GatewayBridge`.
+    ///
+    /// The `ParamWriter` can be cloned and moved into the task receiving
+    /// the external writes. The default implementation ignores it.
+    fn attach(&mut self, _writer: ParamWriter) {}
+}
+
+/// Reads and writes parameters on behalf of a `FieldGateway`.
+///
+/// The requests are sent as `ParamRequest`s to the `Receiver` returned by
+/// `GatewayBridge::new`, so that they are serialized with all other
+/// operations performed on the `LiveDataStream`.
+#[derive(Debug, Clone)]
+pub struct ParamWriter {
+    param_sender: Sender<ParamRequest>,
+}
+
+impl ParamWriter {
+    /// Read the parameter with the given index (decimal or `0x` prefixed
+    /// hexadecimal) or value ID.
+    pub async fn get_param(&self, id: &str) -> Result<i32> {
+        let (_, value) = request_param(&self.param_sender, id, None, None).await?;
+        Ok(value)
+    }
+
+    /// Write the parameter with the given index (decimal or `0x` prefixed
+    /// hexadecimal) or value ID, returning the value confirmed by the
+    /// device.
+    pub async fn set_param(&self, id: &str, value: i32) -> Result<i32> {
+        let (_, value) = request_param(&self.param_sender, id, Some(value), None).await?;
+        Ok(value)
+    }
+}
+
+/// Connects `FieldGateway`s to a `DataHub` and the parameter requests of
+/// a `LiveDataStream`.
+///
+/// # Examples
+///
+/// ```no_run
+/// # fn main() -> async_resol_vbus::Result<()> { async_std::task::block_on(async {
+/// #
+/// use async_std::net::TcpStream;
+///
+/// use async_resol_vbus::{
+///     DataHub, FieldGateway, GatewayBridge, LiveDataStream, ParamWriter,
+/// };
+/// use resol_vbus::specification::PacketFieldSpec;
+///
+/// struct KnxGateway {
+///     writer: Option<ParamWriter>,
+/// }
+///
+/// impl FieldGateway for KnxGateway {
+///     fn value_changed(&mut self, field_spec: &PacketFieldSpec, value: Option<f64>) {
+///         // send a group telegram...
+///     }
+///
+///     fn attach(&mut self, writer: ParamWriter) {
+///         self.writer = Some(writer);
+///     }
+/// }
+///
+/// let stream = TcpStream::connect("192.168.5.217:7053").await?;
+/// // ... perform handshake ...
+/// let mut lds = LiveDataStream::new(stream.clone(), stream, 0, 0x0020);
+///
+/// let hub = DataHub::new();
+///
+/// let (mut bridge, param_requests) = GatewayBridge::new();
+/// bridge.add_gateway(Box::new(KnxGateway { writer: None }));
+/// async_std::task::spawn(bridge.run(hub.subscribe()));
+///
+/// loop {
+///     while let Ok(request) = param_requests.try_recv() {
+///         request.process(&mut lds, 0x7E11).await?;
+///     }
+///
+///     if let Some(data) = lds.receive_any_data(1000).await? {
+///         hub.publish(data);
+///     }
+/// }
+/// #
+/// # }) }
+/// ```
+pub struct GatewayBridge {
+    gateways: Vec<Box<dyn FieldGateway>>,
+    param_sender: Sender<ParamRequest>,
+    language: Language,
+    data_set: DataSet,
+}
+
+impl GatewayBridge {
+    /// Create a new `GatewayBridge` and the `Receiver` for the
+    /// `ParamRequest`s of its gateways.
+    pub fn new() -> (GatewayBridge, Receiver<ParamRequest>) {
+        let (param_sender, param_receiver) = async_std::channel::bounded(10);
+
+        let bridge = GatewayBridge {
+            gateways: Vec::new(),
+            param_sender,
+            language: Language::En,
+            data_set: DataSet::new(),
+        };
+
+        (bridge, param_receiver)
+    }
+
+    /// Set the language of the `PacketFieldSpec`s passed to the gateways.
+    ///
+    /// Defaults to `Language::En`.
+    pub fn set_language(&mut self, language: Language) {
+        self.language = language;
+    }
+
+    /// Add a `FieldGateway`, attaching a `ParamWriter` to it.
+    pub fn add_gateway(&mut self, mut gateway: Box<dyn FieldGateway>) {
+        gateway.attach(ParamWriter {
+            param_sender: self.param_sender.clone(),
+        });
+        self.gateways.push(gateway);
+    }
+
+    /// Get a `ParamWriter` sending requests to the same `Receiver` as the
+    /// ones attached to the gateways.
+    pub fn param_writer(&self) -> ParamWriter {
+        ParamWriter {
+            param_sender: self.param_sender.clone(),
+        }
+    }
+
+    /// Pass the changed fields of `data` to all gateways.
+    pub fn dispatch(&mut self, data: &Data) {
+        let id = data.id_string();
+        let previous = self.data_set.iter().find(|data| data.id_string() == id);
+        let gateways = &mut self.gateways;
+        changed_fields(
+            previous,
+            data,
+            &default_specification(self.language),
+            |field| {
+                let value = field.raw_value_f64();
+                for gateway in gateways.iter_mut() {
+                    gateway.value_changed(field.field_spec(), value);
+                }
+            },
+        );
+
+        self.data_set.add_data(data.clone());
+    }
+
+    /// Dispatch all `Data` received from a `DataHub` subscription until
+    /// the `DataHub` is dropped.
+    pub async fn run(mut self, receiver: Receiver<Arc<Data>>) -> Result<()> {
+        while let Ok(data) = receiver.recv().await {
+            self.dispatch(&data);
+        }
+        Ok(())
+    }
+
+    /// Like `run`, but also returns once the shutdown is triggered.
+    ///
+    /// `Data` that was already received is dispatched before the result is
+    /// reported to the `ShutdownSignal`.
+    pub async fn run_until_shutdown(
+        mut self,
+        receiver: Receiver<Arc<Data>>,
+        signal: ShutdownSignal,
+    ) {
+        while let Some(Ok(data)) = signal.run_until(receiver.recv()).await {
+            self.dispatch(&data);
+        }
+        while let Ok(data) = receiver.try_recv() {
+            self.dispatch(&data);
+        }
+        signal.complete(Ok(()));
+    }
+}
+
+impl std::fmt::Debug for GatewayBridge {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("GatewayBridge")
+            .field("gateways", &self.gateways.len())
+            .field("language", &self.language)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Mutex;
+
+    use async_std::io::Cursor;
+
+    use resol_vbus::{chrono::Utc, Header, Packet};
+
+    use super::*;
+
+    use crate::{
+        data_hub::DataHub,
+        live_data_stream::LiveDataStream,
+        shutdown::Shutdown,
+        test_utils::{extend_from_datagram, extend_with_empty_packet},
+    };
+
+    type Changes = Arc<Mutex<Vec<(String, Option<f64>)>>>;
+
+    #[derive(Default)]
+    struct TestGateway {
+        changes: Changes,
+        writer: Arc<Mutex<Option<ParamWriter>>>,
+    }
+
+    impl FieldGateway for TestGateway {
+        fn value_changed(&mut self, field_spec: &PacketFieldSpec, value: Option<f64>) {
+            self.changes
+                .lock()
+                .unwrap()
+                .push((field_spec.name.clone(), value));
+        }
+
+        fn attach(&mut self, writer: ParamWriter) {
+            *self.writer.lock().unwrap() = Some(writer);
+        }
+    }
+
+    fn packet(temperature: i16) -> Data {
+        let mut frame_data = [0u8; 508];
+        frame_data[0..2].copy_from_slice(&temperature.to_le_bytes());
+        Data::Packet(Packet {
+            header: Header {
+                timestamp: Utc::now(),
+                channel: 0,
+                destination_address: 0x0010,
+                source_address: 0x7E11,
+                protocol_version: 0x10,
+            },
+            command: 0x0100,
+            frame_count: 1,
+            frame_data,
+        })
+    }
+
+    #[test]
+    fn test_gateway_bridge() -> Result<()> {
+        async_std::task::block_on(async {
+            let gateway = TestGateway::default();
+            let changes = gateway.changes.clone();
+            let writer = gateway.writer.clone();
+
+            let (mut bridge, param_requests) = GatewayBridge::new();
+            bridge.add_gateway(Box::new(gateway));
+
+            let hub = DataHub::new();
+            let bridge_task = async_std::task::spawn(bridge.run(hub.subscribe()));
+
+            hub.publish(packet(872));
+            hub.publish(packet(872));
+            hub.publish(packet(901));
+            drop(hub);
+
+            bridge_task.await?;
+
+            let changes = changes.lock().unwrap().clone();
+            let temperature1 = changes
+                .iter()
+                .filter(|(name, _)| name == "Temperature sensor 1")
+                .map(|(_, value)| value.map(|value| (value * 10.0).round()))
+                .collect::<Vec<_>>();
+            assert_eq!(vec![Some(872.0), Some(901.0)], temperature1);
+            assert_eq!(
+                1,
+                changes
+                    .iter()
+                    .filter(|(name, _)| name == "Temperature sensor 2")
+                    .count()
+            );
+
+            let writer = writer.lock().unwrap().take().unwrap();
+            let client_future =
+                async_std::task::spawn(async move { writer.set_param("0x1234", 456).await });
+
+            let request = param_requests.recv().await.unwrap();
+            assert_eq!("0x1234", request.id());
+            assert_eq!(Some(456), request.value());
+
+            let mut rx_buf = Vec::new();
+            extend_from_datagram(&mut rx_buf, 0x0000, 0x7E11, 0x0500, 0, 0);
+            extend_from_datagram(&mut rx_buf, 0x0020, 0x7E11, 0x0100, 0x1234, 456);
+            extend_with_empty_packet(&mut rx_buf, 0x0010, 0x7E11, 0x0100);
+
+            let mut lds = LiveDataStream::new(&rx_buf[..], Cursor::new(Vec::new()), 0, 0x0020);
+
+            request.process(&mut lds, 0x7E11).await?;
+
+            assert_eq!(456, client_future.await?);
+
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn test_run_until_shutdown() -> Result<()> {
+        async_std::task::block_on(async {
+            let gateway = TestGateway::default();
+            let changes = gateway.changes.clone();
+
+            let (mut bridge, _param_requests) = GatewayBridge::new();
+            bridge.add_gateway(Box::new(gateway));
+
+            let hub = DataHub::new();
+            let shutdown = Shutdown::new();
+            async_std::task::spawn(
+                bridge.run_until_shutdown(hub.subscribe(), shutdown.register("gateway")),
+            );
+
+            hub.publish(packet(872));
+
+            let results = shutdown.shutdown().await;
+            assert_eq!(vec![("gateway".to_string(), Ok(()))], results);
+            assert!(!changes.lock().unwrap().is_empty());
+
+            Ok(())
+        })
+    }
+}
@@ -0,0 +1,168 @@
+use std::fmt;
+
+#[cfg(feature = "specification")]
+use resol_vbus::specification::PacketFieldSpec;
+
+use crate::error::Result;
+
+/// A raw integer value together with its precision and unit, e.g. a
+/// temperature reading of `455` with precision 1 and unit `°C` that is
+/// displayed as `45.5 °C`.
+///
+/// # Examples
+///
+/// ```rust
+/// # fn main() -> async_resol_vbus::Result<()> {
+/// use async_resol_vbus::FormattedValue;
+///
+/// let value = FormattedValue::new(455, 1, "°C");
+/// assert_eq!("45.5 °C", value.to_string());
+///
+/// let value = FormattedValue::parse("47,25 °C", 1, "°C")?;
+/// assert_eq!(473, value.raw());
+/// # Ok(()) }
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct FormattedValue {
+    raw: i32,
+    precision: u8,
+    unit: String,
+}
+
+impl FormattedValue {
+    /// Create a new `FormattedValue`.
+    ///
+    /// The `precision` is the number of decimal places, i.e. the raw value
+    /// is the displayed value multiplied by `10^precision`.
+    pub fn new(raw: i32, precision: u8, unit: &str) -> FormattedValue {
+        FormattedValue {
+            raw,
+            precision,
+            unit: unit.trim().to_string(),
+        }
+    }
+
+    /// Create a new `FormattedValue` using the precision and unit of a
+    /// `PacketFieldSpec`.
+    #[cfg(feature = "specification")]
+    pub fn from_field_spec(raw: i32, field_spec: &PacketFieldSpec) -> FormattedValue {
+        let precision = field_spec.precision.clamp(0, 9) as u8;
+        FormattedValue::new(raw, precision, &field_spec.unit_text)
+    }
+
+    /// Parse user input like `45.5`, `45,5` or `45.5 °C` into a `FormattedValue`.
+    ///
+    /// Additional decimal places are rounded to the given `precision`.
+    pub fn parse(input: &str, precision: u8, unit: &str) -> Result<FormattedValue> {
+        let unit = unit.trim();
+
+        let mut number = input.trim();
+        if !unit.is_empty() {
+            number = number.strip_suffix(unit).unwrap_or(number).trim_end();
+        }
+
+        let number = number
+            .replace(',', ".")
+            .parse::<f64>()
+            .map_err(|_| format!("Unable to parse {:?} as a number", input))?;
+
+        let raw = (number * 10f64.powi(i32::from(precision))).round();
+        if !raw.is_finite() || raw < f64::from(i32::MIN) || raw > f64::from(i32::MAX) {
+            return Err(format!("Value {:?} is out of range", input).into());
+        }
+
+        Ok(FormattedValue::new(raw as i32, precision, unit))
+    }
+
+    /// Get the raw value.
+    pub fn raw(&self) -> i32 {
+        self.raw
+    }
+
+    /// Get the number of decimal places.
+    pub fn precision(&self) -> u8 {
+        self.precision
+    }
+
+    /// Get the unit text.
+    pub fn unit(&self) -> &str {
+        &self.unit
+    }
+
+    /// Get the value as a floating point number.
+    pub fn value(&self) -> f64 {
+        f64::from(self.raw) / 10f64.powi(i32::from(self.precision))
+    }
+}
+
+impl fmt::Display for FormattedValue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let divisor = 10i64.pow(u32::from(self.precision));
+        let raw = i64::from(self.raw);
+        let sign = if raw < 0 { "-" } else { "" };
+        let int_part = raw.abs() / divisor;
+        let frac_part = raw.abs() % divisor;
+
+        if self.precision > 0 {
+            write!(
+                f,
+                "{}{}.{:0width$}",
+                sign,
+                int_part,
+                frac_part,
+                width = usize::from(self.precision)
+            )?;
+        } else {
+            write!(f, "{}{}", sign, int_part)?;
+        }
+
+        if !self.unit.is_empty() {
+            write!(f, " {}", self.unit)?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_display() {
+        assert_eq!("45.5 °C", FormattedValue::new(455, 1, " °C").to_string());
+        assert_eq!("-0.05 K", FormattedValue::new(-5, 2, "K").to_string());
+        assert_eq!("42", FormattedValue::new(42, 0, "").to_string());
+        assert_eq!(4.2, FormattedValue::new(42, 1, "").value());
+    }
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(455, FormattedValue::parse("45.5", 1, "°C").unwrap().raw());
+        assert_eq!(
+            455,
+            FormattedValue::parse(" 45,5 °C ", 1, "°C").unwrap().raw()
+        );
+        assert_eq!(-5, FormattedValue::parse("-0.049", 2, "K").unwrap().raw());
+        assert!(FormattedValue::parse("abc", 1, "°C").is_err());
+        assert!(FormattedValue::parse("1e12", 0, "").is_err());
+    }
+
+    #[cfg(feature = "specification")]
+    #[test]
+    fn test_from_field_spec() {
+        use resol_vbus::{Language, Specification, SpecificationFile};
+
+        let spec = Specification::from_file(SpecificationFile::new_default(), Language::En);
+
+        let packet_spec = spec.get_packet_spec(0x00, 0x0010, 0x7E11, 0x0100);
+        let field_spec = packet_spec
+            .fields
+            .iter()
+            .find(|field| field.field_id == "000_2_0")
+            .unwrap();
+
+        let value = FormattedValue::from_field_spec(455, field_spec);
+        assert_eq!("45.5 °C", value.to_string());
+    }
+}
@@ -0,0 +1,247 @@
+use resol_vbus::Packet;
+
+use crate::error::Result;
+
+/// A helper to compose and read the `frame_data` of a `Packet` using
+/// typed little-endian field accesses.
+///
+/// The offsets are byte offsets into the payload, matching the offsets
+/// used by the `Specification`. The frame count grows automatically to
+/// cover the highest byte written.
+///
+/// # Examples
+///
+/// ```rust
+/// # fn main() -> async_resol_vbus::Result<()> {
+/// use async_resol_vbus::{FrameData, PacketBuilder};
+///
+/// let mut frame_data = FrameData::new();
+/// frame_data.set_temperature(0, 23.4)?;
+/// frame_data.set_u8(8, 100)?;
+///
+/// let packet = PacketBuilder::new(0x0010, 0x0100)
+///     .source_address(0x7E11)
+///     .payload(frame_data.as_bytes())
+///     .build()?;
+///
+/// let frame_data = FrameData::from_packet(&packet);
+/// assert_eq!(23.4, frame_data.temperature(0)?);
+/// # Ok(()) }
+/// ```
+#[derive(Debug, Clone)]
+pub struct FrameData {
+    bytes: [u8; 508],
+    len: usize,
+}
+
+impl FrameData {
+    /// Create a new empty `FrameData`.
+    pub fn new() -> FrameData {
+        FrameData {
+            bytes: [0; 508],
+            len: 0,
+        }
+    }
+
+    /// Create a `FrameData` from the payload of `packet`.
+    pub fn from_packet(packet: &Packet) -> FrameData {
+        FrameData {
+            bytes: packet.frame_data,
+            len: packet.valid_frame_data_len(),
+        }
+    }
+
+    /// Get the number of 4-byte frames needed for the payload.
+    pub fn frame_count(&self) -> u8 {
+        self.len.div_ceil(4) as u8
+    }
+
+    /// Get the payload bytes covered by the frame count.
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.bytes[0..usize::from(self.frame_count()) * 4]
+    }
+
+    /// Copy the payload and frame count into `packet`.
+    pub fn apply_to(&self, packet: &mut Packet) {
+        packet.frame_data = self.bytes;
+        packet.frame_count = self.frame_count();
+    }
+
+    fn range(offset: usize, size: usize) -> Result<std::ops::Range<usize>> {
+        if offset + size > 508 {
+            Err(format!(
+                "Field of {} bytes at offset {} exceeds the frame data",
+                size, offset
+            )
+            .into())
+        } else {
+            Ok(offset..offset + size)
+        }
+    }
+
+    fn get<const N: usize>(&self, offset: usize) -> Result<[u8; N]> {
+        let mut bytes = [0; N];
+        bytes.copy_from_slice(&self.bytes[FrameData::range(offset, N)?]);
+        Ok(bytes)
+    }
+
+    fn set<const N: usize>(&mut self, offset: usize, bytes: [u8; N]) -> Result<()> {
+        let range = FrameData::range(offset, N)?;
+        self.len = self.len.max(range.end);
+        self.bytes[range].copy_from_slice(&bytes);
+        Ok(())
+    }
+
+    /// Get the `u8` at `offset`.
+    pub fn u8(&self, offset: usize) -> Result<u8> {
+        Ok(u8::from_le_bytes(self.get(offset)?))
+    }
+
+    /// Set the `u8` at `offset`.
+    pub fn set_u8(&mut self, offset: usize, value: u8) -> Result<()> {
+        self.set(offset, value.to_le_bytes())
+    }
+
+    /// Get the `i8` at `offset`.
+    pub fn i8(&self, offset: usize) -> Result<i8> {
+        Ok(i8::from_le_bytes(self.get(offset)?))
+    }
+
+    /// Set the `i8` at `offset`.
+    pub fn set_i8(&mut self, offset: usize, value: i8) -> Result<()> {
+        self.set(offset, value.to_le_bytes())
+    }
+
+    /// Get the little-endian `u16` at `offset`.
+    pub fn u16(&self, offset: usize) -> Result<u16> {
+        Ok(u16::from_le_bytes(self.get(offset)?))
+    }
+
+    /// Set the little-endian `u16` at `offset`.
+    pub fn set_u16(&mut self, offset: usize, value: u16) -> Result<()> {
+        self.set(offset, value.to_le_bytes())
+    }
+
+    /// Get the little-endian `i16` at `offset`.
+    pub fn i16(&self, offset: usize) -> Result<i16> {
+        Ok(i16::from_le_bytes(self.get(offset)?))
+    }
+
+    /// Set the little-endian `i16` at `offset`.
+    pub fn set_i16(&mut self, offset: usize, value: i16) -> Result<()> {
+        self.set(offset, value.to_le_bytes())
+    }
+
+    /// Get the little-endian `u32` at `offset`.
+    pub fn u32(&self, offset: usize) -> Result<u32> {
+        Ok(u32::from_le_bytes(self.get(offset)?))
+    }
+
+    /// Set the little-endian `u32` at `offset`.
+    pub fn set_u32(&mut self, offset: usize, value: u32) -> Result<()> {
+        self.set(offset, value.to_le_bytes())
+    }
+
+    /// Get the little-endian `i32` at `offset`.
+    pub fn i32(&self, offset: usize) -> Result<i32> {
+        Ok(i32::from_le_bytes(self.get(offset)?))
+    }
+
+    /// Set the little-endian `i32` at `offset`.
+    pub fn set_i32(&mut self, offset: usize, value: i32) -> Result<()> {
+        self.set(offset, value.to_le_bytes())
+    }
+
+    /// Get the `i16` at `offset` multiplied by `factor`.
+    pub fn scaled_i16(&self, offset: usize, factor: f64) -> Result<f64> {
+        Ok(f64::from(self.i16(offset)?) * factor)
+    }
+
+    /// Set the `i16` at `offset` to `value` divided by `factor`, rounded to
+    /// the nearest integer.
+    pub fn set_scaled_i16(&mut self, offset: usize, value: f64, factor: f64) -> Result<()> {
+        let raw = (value / factor).round();
+        if raw < f64::from(i16::MIN) || raw > f64::from(i16::MAX) {
+            return Err(format!("Value {} is out of range for a 16-bit field", value).into());
+        }
+        self.set_i16(offset, raw as i16)
+    }
+
+    /// Get the temperature in °C stored as `i16` with factor 0.1 at `offset`.
+    pub fn temperature(&self, offset: usize) -> Result<f64> {
+        // Divide instead of multiplying by 0.1 to avoid representation errors.
+        Ok(f64::from(self.i16(offset)?) / 10.0)
+    }
+
+    /// Set the temperature in °C stored as `i16` with factor 0.1 at `offset`.
+    pub fn set_temperature(&mut self, offset: usize, value: f64) -> Result<()> {
+        self.set_scaled_i16(offset, value, 0.1)
+    }
+}
+
+impl Default for FrameData {
+    fn default() -> FrameData {
+        FrameData::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use resol_vbus::{Data, DataSet, Language, Specification, SpecificationFile};
+
+    use super::*;
+
+    use crate::PacketBuilder;
+
+    #[test]
+    fn test_typed_fields() {
+        let mut frame_data = FrameData::new();
+        assert_eq!(0, frame_data.frame_count());
+
+        frame_data.set_i16(2, -2).unwrap();
+        frame_data.set_u32(4, 0x12345678).unwrap();
+        frame_data.set_u8(9, 0xAB).unwrap();
+
+        assert_eq!(3, frame_data.frame_count());
+        assert_eq!(
+            &[0, 0, 0xFE, 0xFF, 0x78, 0x56, 0x34, 0x12, 0, 0xAB, 0, 0],
+            frame_data.as_bytes()
+        );
+        assert_eq!(-2, frame_data.i16(2).unwrap());
+        assert_eq!(0xFFFE, frame_data.u16(2).unwrap());
+        assert_eq!(0x12345678, frame_data.i32(4).unwrap());
+        assert_eq!(-85, frame_data.i8(9).unwrap());
+
+        assert!(frame_data.set_u32(506, 0).is_err());
+        assert!(frame_data.u16(507).is_err());
+        assert!(frame_data.set_temperature(0, 4000.0).is_err());
+    }
+
+    #[test]
+    fn test_specification_roundtrip() {
+        let spec = Specification::from_file(SpecificationFile::new_default(), Language::En);
+
+        let mut frame_data = FrameData::new();
+        frame_data.set_temperature(0, -12.3).unwrap();
+        frame_data.set_temperature(2, 87.6).unwrap();
+
+        let packet = PacketBuilder::new(0x0010, 0x0100)
+            .source_address(0x7E11)
+            .payload(frame_data.as_bytes())
+            .build()
+            .unwrap();
+
+        let mut data_set = DataSet::new();
+        data_set.add_data(Data::Packet(packet.clone()));
+
+        let values = spec
+            .fields_in_data_set(&data_set)
+            .filter(|field| field.field_spec().packet_field_id.ends_with("_000_2_0"))
+            .map(|field| field.raw_value_f64())
+            .collect::<Vec<_>>();
+        assert_eq!(vec![Some(-12.3)], values);
+
+        let frame_data = FrameData::from_packet(&packet);
+        assert_eq!(87.6, frame_data.temperature(2).unwrap());
+    }
+}
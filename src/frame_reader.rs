@@ -0,0 +1,133 @@
+use std::{marker::Unpin, ops::Range};
+
+use async_std::io::{Read, ReadExt};
+
+use resol_vbus::{live_data_decoder::length_from_bytes, StreamBlobLength};
+
+use crate::error::Result;
+
+/// The size of the fixed buffer of a `FrameReader`, large enough for the
+/// longest possible VBus packet (772 bytes).
+const BUFFER_SIZE: usize = 1024;
+
+/// A reader for VBus frames using a fixed-size buffer.
+///
+/// In contrast to `LiveDataStream` it does not decode the received bytes
+/// into `Data` values and performs no heap allocation after construction.
+/// Every valid frame is returned as a slice into the internal buffer, which
+/// is valid until the next call to `read_frame`. This makes it suitable for
+/// forwarding live data on memory-constrained gateways.
+///
+/// # Examples
+///
+/// ```no_run
+/// # fn main() -> async_resol_vbus::Result<()> { async_std::task::block_on(async {
+/// #
+/// use async_std::{net::TcpStream, prelude::*};
+///
+/// use async_resol_vbus::FrameReader;
+///
+/// let mut upstream = TcpStream::connect("192.168.5.217:7053").await?;
+/// // ... perform handshake ...
+/// let mut downstream = TcpStream::connect("192.168.5.218:7053").await?;
+/// // ... perform handshake ...
+///
+/// let mut reader = FrameReader::new(upstream);
+/// while let Some(frame) = reader.read_frame().await? {
+///     downstream.write_all(frame).await?;
+/// }
+/// #
+/// # Ok(()) }) }
+/// ```
+#[derive(Debug)]
+pub struct FrameReader<R: Read + Unpin> {
+    reader: R,
+    buf: [u8; BUFFER_SIZE],
+    start: usize,
+    end: usize,
+}
+
+impl<R: Read + Unpin> FrameReader<R> {
+    /// Create a new `FrameReader`.
+    pub fn new(reader: R) -> FrameReader<R> {
+        FrameReader {
+            reader,
+            buf: [0; BUFFER_SIZE],
+            start: 0,
+            end: 0,
+        }
+    }
+
+    /// Read the next valid frame.
+    ///
+    /// Bytes that do not belong to a valid frame are skipped. Returns
+    /// `None` if the reader reached its end.
+    pub async fn read_frame(&mut self) -> Result<Option<&[u8]>> {
+        let range = loop {
+            if let Some(range) = self.find_frame() {
+                break range;
+            }
+
+            if self.start > 0 {
+                self.buf.copy_within(self.start..self.end, 0);
+                self.end -= self.start;
+                self.start = 0;
+            }
+
+            let len = self.reader.read(&mut self.buf[self.end..]).await?;
+            if len == 0 {
+                return Ok(None);
+            }
+            self.end += len;
+        };
+
+        Ok(Some(&self.buf[range]))
+    }
+
+    fn find_frame(&mut self) -> Option<Range<usize>> {
+        while self.start < self.end {
+            match length_from_bytes(&self.buf[self.start..self.end]) {
+                StreamBlobLength::BlobLength(len) => {
+                    let range = self.start..self.start + len;
+                    self.start += len;
+                    return Some(range);
+                }
+                StreamBlobLength::Partial => return None,
+                StreamBlobLength::Malformed => self.start += 1,
+            }
+        }
+        None
+    }
+
+    /// Consume `self` and return the underlying reader.
+    pub fn into_inner(self) -> R {
+        self.reader
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::test_utils::{extend_from_datagram, extend_with_empty_packet, simulate_run};
+
+    #[test]
+    fn test_read_frame() {
+        let mut rx_buf = vec![0x00, 0xAA, 0x10];
+        extend_with_empty_packet(&mut rx_buf, 0x0010, 0x7E11, 0x0100);
+        rx_buf.extend_from_slice(&[0xFF; 2000]);
+        extend_from_datagram(&mut rx_buf, 0x0020, 0x7E11, 0x0100, 0x1234, 42);
+
+        let mut reader = FrameReader::new(&rx_buf[..]);
+
+        simulate_run(async {
+            let frame = reader.read_frame().await.unwrap().unwrap();
+            assert_eq!(&rx_buf[3..13], frame);
+
+            let frame = reader.read_frame().await.unwrap().unwrap();
+            assert_eq!(&rx_buf[2013..], frame);
+
+            assert!(reader.read_frame().await.unwrap().is_none());
+        });
+    }
+}
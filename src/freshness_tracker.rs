@@ -0,0 +1,258 @@
+use std::{cell::RefCell, collections::BTreeMap, marker::Unpin, time::Duration};
+
+use async_std::{
+    channel::Sender,
+    io::{Read, Write},
+};
+
+use resol_vbus::{
+    chrono::{DateTime, Utc},
+    Data, PacketId, ToPacketId,
+};
+
+use crate::{error::Result, live_data_stream::LiveDataStream};
+
+/// The freshness of a single packet, see `FreshnessTracker`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PacketFreshness {
+    /// The ID of the packet.
+    pub packet_id: PacketId,
+
+    /// The timestamp of the last reception.
+    pub last_updated: DateTime<Utc>,
+
+    /// Whether the packet was not received for longer than the maximum age.
+    pub is_stale: bool,
+}
+
+/// Tracks when packets were last received and detects packets that became
+/// stale, e.g. because their controller dropped off the bus.
+///
+/// A packet becomes stale if it was not received for longer than the
+/// maximum age. Every transition between fresh and stale is sent to the
+/// optional `Sender`, so that dashboards can gray out the affected values.
+///
+/// # Examples
+///
+/// ```no_run
+/// # fn main() -> async_resol_vbus::Result<()> { async_std::task::block_on(async {
+/// #
+/// use std::time::Duration;
+///
+/// use async_std::net::TcpStream;
+///
+/// use async_resol_vbus::{FreshnessTracker, LiveDataStream};
+///
+/// let stream = TcpStream::connect("192.168.5.217:7053").await?;
+/// // ... perform handshake ...
+/// let mut lds = LiveDataStream::from_tcp_stream(stream, 0, 0x0020);
+///
+/// let (sender, receiver) = async_std::channel::unbounded();
+///
+/// let mut tracker = FreshnessTracker::new(Duration::from_secs(60));
+/// tracker.set_sender(Some(sender));
+///
+/// async_std::task::spawn(async move {
+///     while let Ok(change) = receiver.recv().await {
+///         println!("{:?} is stale: {}", change.packet_id, change.is_stale);
+///     }
+/// });
+///
+/// loop {
+///     tracker.observe(&mut lds, 10000).await?;
+/// }
+/// #
+/// # }) }
+/// ```
+#[derive(Debug)]
+pub struct FreshnessTracker {
+    max_age: Duration,
+    entries: BTreeMap<PacketId, PacketFreshness>,
+    sender: Option<Sender<PacketFreshness>>,
+}
+
+impl FreshnessTracker {
+    /// Create a new `FreshnessTracker` considering packets stale after
+    /// `max_age`.
+    pub fn new(max_age: Duration) -> FreshnessTracker {
+        FreshnessTracker {
+            max_age,
+            entries: BTreeMap::new(),
+            sender: None,
+        }
+    }
+
+    /// Set the `Sender` that receives every transition between fresh and
+    /// stale.
+    ///
+    /// Defaults to `None`.
+    pub fn set_sender(&mut self, sender: Option<Sender<PacketFreshness>>) {
+        self.sender = sender;
+    }
+
+    /// Update the freshness of the packet contained in `data`.
+    ///
+    /// Datagrams and telegrams are ignored.
+    pub fn add_data(&mut self, data: &Data) {
+        let packet = match data {
+            Data::Packet(packet) => packet,
+            _ => return,
+        };
+
+        let packet_id = packet.packet_id();
+        let last_updated = packet.header.timestamp;
+
+        let entry = self
+            .entries
+            .entry(packet_id)
+            .or_insert_with(|| PacketFreshness {
+                packet_id,
+                last_updated,
+                is_stale: false,
+            });
+
+        entry.last_updated = last_updated;
+        if entry.is_stale {
+            entry.is_stale = false;
+            let entry = entry.clone();
+            self.notify(entry);
+        }
+    }
+
+    /// Mark all packets that were not received for longer than the maximum
+    /// age at `now` as stale and return them.
+    pub fn check(&mut self, now: DateTime<Utc>) -> Vec<PacketFreshness> {
+        let max_age = resol_vbus::chrono::Duration::from_std(self.max_age)
+            .unwrap_or(resol_vbus::chrono::Duration::MAX);
+
+        let mut changes = Vec::new();
+        for entry in self.entries.values_mut() {
+            if !entry.is_stale && now - entry.last_updated > max_age {
+                entry.is_stale = true;
+                changes.push(entry.clone());
+            }
+        }
+
+        for change in changes.iter() {
+            self.notify(change.clone());
+        }
+
+        changes
+    }
+
+    fn notify(&self, change: PacketFreshness) {
+        if let Some(ref sender) = self.sender {
+            drop(sender.try_send(change));
+        }
+    }
+
+    /// Receive data from `stream` for `timeout_ms` milliseconds, update the
+    /// freshness of the received packets and check for stale packets.
+    pub async fn observe<R: Read + Unpin, W: Write + Unpin>(
+        &mut self,
+        stream: &mut LiveDataStream<R, W>,
+        timeout_ms: u64,
+    ) -> Result<()> {
+        let tracker = RefCell::new(&mut *self);
+
+        stream
+            .receive(timeout_ms, |data| {
+                let mut tracker = tracker.borrow_mut();
+                tracker.add_data(data);
+                tracker.check(Utc::now());
+                false
+            })
+            .await?;
+
+        self.check(Utc::now());
+
+        Ok(())
+    }
+
+    /// Get the freshness of a packet.
+    ///
+    /// The `id` can be a packet ID string like `00_0010_7E11_10_0100` or a
+    /// `PacketId`. Returns `None` if the packet was never received.
+    pub fn get<T: ToPacketId + ?Sized>(&self, id: &T) -> Result<Option<&PacketFreshness>> {
+        Ok(self.entries.get(&id.to_packet_id()?))
+    }
+
+    /// Get the freshness of all received packets, sorted by their IDs.
+    pub fn entries(&self) -> Vec<&PacketFreshness> {
+        self.entries.values().collect()
+    }
+
+    /// Get the IDs of all stale packets.
+    pub fn stale_packet_ids(&self) -> Vec<PacketId> {
+        self.entries
+            .values()
+            .filter(|entry| entry.is_stale)
+            .map(|entry| entry.packet_id)
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use resol_vbus::{Header, Packet};
+
+    use super::*;
+
+    fn packet(source_address: u16, timestamp: DateTime<Utc>) -> Data {
+        Data::Packet(Packet {
+            header: Header {
+                timestamp,
+                channel: 0,
+                destination_address: 0x0010,
+                source_address,
+                protocol_version: 0x10,
+            },
+            command: 0x0100,
+            frame_count: 0,
+            frame_data: [0u8; 508],
+        })
+    }
+
+    #[test]
+    fn test_freshness_tracker() -> Result<()> {
+        let (sender, receiver) = async_std::channel::unbounded();
+
+        let mut tracker = FreshnessTracker::new(Duration::from_secs(60));
+        tracker.set_sender(Some(sender));
+
+        let start = Utc::now();
+        let seconds = resol_vbus::chrono::Duration::seconds;
+
+        tracker.add_data(&packet(0x7E11, start));
+        tracker.add_data(&packet(0x7E21, start + seconds(30)));
+
+        assert!(tracker.check(start + seconds(60)).is_empty());
+
+        let changes = tracker.check(start + seconds(61));
+        assert_eq!(1, changes.len());
+        assert_eq!(PacketId(0, 0x0010, 0x7E11, 0x0100), changes[0].packet_id);
+        assert!(changes[0].is_stale);
+        assert_eq!(changes[0], receiver.try_recv().unwrap());
+
+        assert!(tracker.check(start + seconds(62)).is_empty());
+        assert_eq!(
+            vec![PacketId(0, 0x0010, 0x7E11, 0x0100)],
+            tracker.stale_packet_ids()
+        );
+
+        tracker.add_data(&packet(0x7E11, start + seconds(63)));
+
+        let change = receiver.try_recv().unwrap();
+        assert!(!change.is_stale);
+        assert_eq!(start + seconds(63), change.last_updated);
+
+        let entry = tracker.get("00_0010_7E21_10_0100")?.unwrap();
+        assert_eq!(start + seconds(30), entry.last_updated);
+        assert!(!entry.is_stale);
+
+        assert_eq!(None, tracker.get("00_0010_7E31_10_0100")?);
+        assert!(receiver.try_recv().is_err());
+
+        Ok(())
+    }
+}
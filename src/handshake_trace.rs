@@ -0,0 +1,55 @@
+use resol_vbus::chrono::{DateTime, Utc};
+
+/// The direction of a line in a `HandshakeTrace`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HandshakeDirection {
+    /// The line was sent by this side of the handshake.
+    Sent,
+
+    /// The line was received from the other side of the handshake.
+    Received,
+}
+
+/// A single command or reply line exchanged during a VBus-over-TCP handshake.
+#[derive(Debug, Clone, PartialEq)]
+pub struct HandshakeTrace {
+    /// The time the line was sent or received.
+    pub timestamp: DateTime<Utc>,
+
+    /// Whether the line was sent or received.
+    pub direction: HandshakeDirection,
+
+    /// The line without its line ending.
+    pub line: String,
+}
+
+/// A callback receiving every `HandshakeTrace` of a handshake.
+pub type HandshakeObserver = Box<dyn FnMut(&HandshakeTrace) + Send>;
+
+#[derive(Default)]
+pub(crate) struct ObserverSlot(Option<HandshakeObserver>);
+
+impl ObserverSlot {
+    pub(crate) fn new(observer: Option<HandshakeObserver>) -> ObserverSlot {
+        ObserverSlot(observer)
+    }
+
+    pub(crate) fn notify(&mut self, direction: HandshakeDirection, line: &str) {
+        if let Some(ref mut observer) = self.0 {
+            observer(&HandshakeTrace {
+                timestamp: Utc::now(),
+                direction,
+                line: line.trim_end_matches(['\r', '\n']).to_string(),
+            });
+        }
+    }
+}
+
+impl std::fmt::Debug for ObserverSlot {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.0 {
+            Some(_) => f.write_str("Some(HandshakeObserver)"),
+            None => f.write_str("None"),
+        }
+    }
+}
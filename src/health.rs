@@ -0,0 +1,200 @@
+use std::{
+    collections::BTreeMap,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use resol_vbus::chrono::{DateTime, Utc};
+
+/// A snapshot of the health of an application, see `HealthMonitor`.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct HealthReport {
+    /// Whether the connection to the VBus device is established.
+    pub connected: bool,
+
+    /// The timestamp of the last received `Data`.
+    pub last_data: Option<DateTime<Utc>>,
+
+    /// The number of times the connection was re-established.
+    pub reconnect_count: usize,
+
+    /// The `(name, lag)` pairs of all sinks, sorted by name.
+    pub sink_lags: Vec<(String, Duration)>,
+
+    /// The `(subsystem, count)` pairs of all errors, sorted by subsystem.
+    pub error_counts: Vec<(String, usize)>,
+}
+
+impl HealthReport {
+    /// Check whether the application is ready, i.e. it is connected and
+    /// has received data.
+    pub fn is_ready(&self) -> bool {
+        self.connected && self.last_data.is_some()
+    }
+
+    /// Check whether data was received within `max_data_age` before `now`.
+    pub fn is_live(&self, now: DateTime<Utc>, max_data_age: Duration) -> bool {
+        let max_data_age = resol_vbus::chrono::Duration::from_std(max_data_age)
+            .unwrap_or(resol_vbus::chrono::Duration::MAX);
+
+        match self.last_data {
+            Some(last_data) => now - last_data <= max_data_age,
+            None => false,
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+struct HealthState {
+    connected: bool,
+    was_connected: bool,
+    last_data: Option<DateTime<Utc>>,
+    reconnect_count: usize,
+    sink_lags: BTreeMap<String, Duration>,
+    error_counts: BTreeMap<String, usize>,
+}
+
+/// Collects the status of multiple subsystems into a `HealthReport`.
+///
+/// The subsystems of an application (e.g. the connection handling, a
+/// `DataHub` or a recorder) report their status to a shared
+/// `HealthMonitor`, so that the application can answer readiness and
+/// liveness probes (e.g. using the `/api/health` endpoint of the `HttpApi`)
+/// without tracking these details itself.
+///
+/// Cloned `HealthMonitor`s share their state.
+///
+/// # Examples
+///
+/// ```
+/// use std::time::Duration;
+///
+/// use async_resol_vbus::{chrono::Utc, HealthMonitor};
+///
+/// let monitor = HealthMonitor::new();
+/// monitor.set_connected(true);
+/// monitor.record_data(Utc::now());
+/// monitor.set_sink_lag("recorder", Duration::from_millis(200));
+///
+/// let report = monitor.report();
+/// assert!(report.is_ready());
+/// assert!(report.is_live(Utc::now(), Duration::from_secs(60)));
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct HealthMonitor {
+    state: Arc<Mutex<HealthState>>,
+}
+
+impl HealthMonitor {
+    /// Create a new `HealthMonitor`.
+    pub fn new() -> HealthMonitor {
+        HealthMonitor::default()
+    }
+
+    /// Report whether the connection to the VBus device is established.
+    ///
+    /// Every transition from disconnected to connected after the first
+    /// connection counts as a reconnect.
+    pub fn set_connected(&self, connected: bool) {
+        let mut state = self.state.lock().unwrap();
+        if connected && !state.connected {
+            if state.was_connected {
+                state.reconnect_count += 1;
+            }
+            state.was_connected = true;
+        }
+        state.connected = connected;
+    }
+
+    /// Report that `Data` with the `timestamp` was received.
+    pub fn record_data(&self, timestamp: DateTime<Utc>) {
+        self.state.lock().unwrap().last_data = Some(timestamp);
+    }
+
+    /// Report how far a sink (e.g. a recorder) lags behind the received
+    /// data.
+    pub fn set_sink_lag(&self, name: &str, lag: Duration) {
+        self.state
+            .lock()
+            .unwrap()
+            .sink_lags
+            .insert(name.to_string(), lag);
+    }
+
+    /// Report that an error occurred in a subsystem.
+    pub fn record_error(&self, subsystem: &str) {
+        *self
+            .state
+            .lock()
+            .unwrap()
+            .error_counts
+            .entry(subsystem.to_string())
+            .or_insert(0) += 1;
+    }
+
+    /// Get a snapshot of the reported status.
+    pub fn report(&self) -> HealthReport {
+        let state = self.state.lock().unwrap();
+        HealthReport {
+            connected: state.connected,
+            last_data: state.last_data,
+            reconnect_count: state.reconnect_count,
+            sink_lags: state
+                .sink_lags
+                .iter()
+                .map(|(name, lag)| (name.clone(), *lag))
+                .collect(),
+            error_counts: state
+                .error_counts
+                .iter()
+                .map(|(subsystem, count)| (subsystem.clone(), *count))
+                .collect(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_health_monitor() {
+        let monitor = HealthMonitor::new();
+
+        let report = monitor.report();
+        assert_eq!(HealthReport::default(), report);
+        assert!(!report.is_ready());
+
+        let start = Utc::now();
+        let seconds = resol_vbus::chrono::Duration::seconds;
+
+        monitor.set_connected(true);
+        monitor.record_data(start);
+        monitor.set_connected(false);
+        monitor.record_error("connection");
+        monitor.set_connected(true);
+        monitor.set_connected(true);
+        monitor.record_error("hub");
+        monitor.record_error("connection");
+        monitor
+            .clone()
+            .set_sink_lag("recorder", Duration::from_secs(2));
+
+        let report = monitor.report();
+        assert!(report.connected);
+        assert_eq!(Some(start), report.last_data);
+        assert_eq!(1, report.reconnect_count);
+        assert_eq!(
+            vec![("recorder".to_string(), Duration::from_secs(2))],
+            report.sink_lags
+        );
+        assert_eq!(
+            vec![("connection".to_string(), 2), ("hub".to_string(), 1)],
+            report.error_counts
+        );
+
+        assert!(report.is_ready());
+        assert!(report.is_live(start + seconds(60), Duration::from_secs(60)));
+        assert!(!report.is_live(start + seconds(61), Duration::from_secs(60)));
+    }
+}
@@ -0,0 +1,318 @@
+use resol_vbus::{
+    specification::DataSetPacketField,
+    specification_file::{Type, UnitFamily},
+    Data, DataSet, Specification,
+};
+
+use crate::json::push_json_string;
+#[cfg(feature = "otel")]
+use crate::trace_context::TraceContext;
+
+/// A single MQTT message consisting of a topic and its payload.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DiscoveryMessage {
+    /// The topic the payload should be published to.
+    pub topic: String,
+
+    /// The payload of the message.
+    pub payload: String,
+
+    /// The `TraceContext` that was active when the message was created,
+    /// e.g. to publish it as an MQTT user property.
+    #[cfg(feature = "otel")]
+    pub trace_context: Option<TraceContext>,
+}
+
+/// Generates Home Assistant MQTT discovery messages for VBus fields.
+///
+/// Every numeric field of the `DataSet` is announced as a sensor. Its
+/// `unique_id` is derived from the node ID and the packet field ID, so it
+/// stays stable across restarts. Temperature, energy, power and a few other
+/// unit families are mapped to the matching Home Assistant device classes.
+///
+/// This type does not publish anything by itself, the messages have to be
+/// sent using an MQTT client of the application's choice. The config
+/// messages should be published with the retain flag set.
+///
+/// # Examples
+///
+/// ```no_run
+/// use async_resol_vbus::HomeAssistantDiscovery;
+/// use resol_vbus::{DataSet, Language, Specification, SpecificationFile};
+///
+/// let spec = Specification::from_file(SpecificationFile::new_default(), Language::En);
+/// let data_set = DataSet::new();
+///
+/// let discovery = HomeAssistantDiscovery::new("vbus_dl2");
+/// for message in discovery.config_messages(&spec, &data_set) {
+///     // publish `message.payload` to `message.topic` with retain flag...
+/// }
+/// for message in discovery.state_messages(&spec, &data_set) {
+///     // publish `message.payload` to `message.topic`...
+/// }
+/// ```
+#[derive(Debug, Clone)]
+pub struct HomeAssistantDiscovery {
+    node_id: String,
+    discovery_prefix: String,
+    base_topic: String,
+    availability: bool,
+}
+
+impl HomeAssistantDiscovery {
+    /// Create a new `HomeAssistantDiscovery` for the given node ID.
+    pub fn new(node_id: &str) -> HomeAssistantDiscovery {
+        HomeAssistantDiscovery {
+            node_id: sanitize_id(node_id),
+            discovery_prefix: "homeassistant".to_string(),
+            base_topic: "vbus".to_string(),
+            availability: false,
+        }
+    }
+
+    /// Set the discovery prefix, defaults to `homeassistant`.
+    pub fn set_discovery_prefix(&mut self, prefix: &str) {
+        self.discovery_prefix = prefix.trim_end_matches('/').to_string();
+    }
+
+    /// Set the base topic the state messages are published under, defaults
+    /// to `vbus`.
+    pub fn set_base_topic(&mut self, base_topic: &str) {
+        self.base_topic = base_topic.trim_end_matches('/').to_string();
+    }
+
+    /// Set whether the sensors should reference the availability topic.
+    pub fn set_availability(&mut self, availability: bool) {
+        self.availability = availability;
+    }
+
+    /// Get the availability topic, expecting `online` or `offline` as
+    /// payload.
+    pub fn availability_topic(&self) -> String {
+        format!("{}/{}/status", self.base_topic, self.node_id)
+    }
+
+    /// Create the discovery config messages for all numeric fields of the
+    /// `DataSet`.
+    pub fn config_messages(
+        &self,
+        spec: &Specification,
+        data_set: &DataSet,
+    ) -> Vec<DiscoveryMessage> {
+        spec.fields_in_data_set(data_set)
+            .filter(|field| field.field_spec().typ == Type::Number)
+            .map(|field| self.config_message(&field))
+            .collect()
+    }
+
+    /// Create the state messages for all numeric fields of the `DataSet`
+    /// that currently have a value.
+    pub fn state_messages(
+        &self,
+        spec: &Specification,
+        data_set: &DataSet,
+    ) -> Vec<DiscoveryMessage> {
+        spec.fields_in_data_set(data_set)
+            .filter(|field| field.field_spec().typ == Type::Number)
+            .filter(|field| field.raw_value_f64().is_some())
+            .map(|field| DiscoveryMessage {
+                topic: self.state_topic(&field),
+                payload: format!("{}", field.fmt_raw_value(false)),
+                #[cfg(feature = "otel")]
+                trace_context: TraceContext::current(),
+            })
+            .collect()
+    }
+
+    fn object_id<T: AsRef<[Data]>>(&self, field: &DataSetPacketField<'_, T>) -> String {
+        sanitize_id(&field.field_spec().packet_field_id)
+    }
+
+    fn state_topic<T: AsRef<[Data]>>(&self, field: &DataSetPacketField<'_, T>) -> String {
+        format!(
+            "{}/{}/{}",
+            self.base_topic,
+            self.node_id,
+            self.object_id(field)
+        )
+    }
+
+    fn config_message<T: AsRef<[Data]>>(
+        &self,
+        field: &DataSetPacketField<'_, T>,
+    ) -> DiscoveryMessage {
+        let packet_spec = field.packet_spec();
+        let field_spec = field.field_spec();
+        let object_id = self.object_id(field);
+
+        let mut payload = String::from("{\"name\":");
+        push_json_string(&mut payload, &field_spec.name);
+        payload.push_str(",\"unique_id\":");
+        push_json_string(&mut payload, &format!("{}_{}", self.node_id, object_id));
+        payload.push_str(",\"state_topic\":");
+        push_json_string(&mut payload, &self.state_topic(field));
+
+        let unit_text = field_spec.unit_text.trim();
+        if !unit_text.is_empty() {
+            payload.push_str(",\"unit_of_measurement\":");
+            push_json_string(&mut payload, unit_text);
+        }
+
+        let (device_class, state_class) = classes(&field_spec.unit_family);
+        if let Some(device_class) = device_class {
+            payload.push_str(",\"device_class\":");
+            push_json_string(&mut payload, device_class);
+        }
+        if let Some(state_class) = state_class {
+            payload.push_str(",\"state_class\":");
+            push_json_string(&mut payload, state_class);
+        }
+
+        if self.availability {
+            payload.push_str(",\"availability_topic\":");
+            push_json_string(&mut payload, &self.availability_topic());
+        }
+
+        payload.push_str(",\"device\":{\"identifiers\":[");
+        push_json_string(
+            &mut payload,
+            &format!("{}_{}", self.node_id, sanitize_id(&packet_spec.packet_id)),
+        );
+        payload.push_str("],\"name\":");
+        let device_name = if packet_spec.source_device.name.is_empty() {
+            &packet_spec.name
+        } else {
+            &packet_spec.source_device.name
+        };
+        push_json_string(&mut payload, device_name);
+        payload.push_str(",\"manufacturer\":\"RESOL\"}}");
+
+        DiscoveryMessage {
+            topic: format!(
+                "{}/sensor/{}/{}/config",
+                self.discovery_prefix, self.node_id, object_id
+            ),
+            payload,
+            #[cfg(feature = "otel")]
+            trace_context: TraceContext::current(),
+        }
+    }
+}
+
+/// Map a `UnitFamily` to the Home Assistant device and state classes.
+fn classes(unit_family: &UnitFamily) -> (Option<&'static str>, Option<&'static str>) {
+    match unit_family {
+        UnitFamily::Temperature => (Some("temperature"), Some("measurement")),
+        UnitFamily::Energy => (Some("energy"), Some("total_increasing")),
+        UnitFamily::Power => (Some("power"), Some("measurement")),
+        UnitFamily::Pressure => (Some("pressure"), Some("measurement")),
+        UnitFamily::Volume => (Some("volume"), Some("total_increasing")),
+        UnitFamily::Time => (Some("duration"), Some("total_increasing")),
+        UnitFamily::VolumeFlow => (None, Some("measurement")),
+        UnitFamily::None => (None, None),
+    }
+}
+
+/// Reduce an ID to the characters allowed in MQTT discovery topics.
+fn sanitize_id(id: &str) -> String {
+    id.chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '-' {
+                c.to_ascii_lowercase()
+            } else {
+                '_'
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use resol_vbus::{chrono::Utc, Header, Language, Packet, SpecificationFile};
+
+    use crate::json::parse_json;
+
+    use super::*;
+
+    fn data_set() -> DataSet {
+        let mut frame_data = [0u8; 508];
+        frame_data[0..2].copy_from_slice(&872i16.to_le_bytes());
+        let mut data_set = DataSet::new();
+        data_set.add_data(Data::Packet(Packet {
+            header: Header {
+                timestamp: Utc::now(),
+                channel: 0,
+                destination_address: 0x0010,
+                source_address: 0x7E11,
+                protocol_version: 0x10,
+            },
+            command: 0x0100,
+            frame_count: 1,
+            frame_data,
+        }));
+        data_set
+    }
+
+    #[test]
+    fn test_home_assistant_discovery() {
+        let spec = Specification::from_file(SpecificationFile::new_default(), Language::En);
+        let data_set = data_set();
+
+        let mut discovery = HomeAssistantDiscovery::new("DL2 Roof");
+        discovery.set_availability(true);
+        assert_eq!("vbus/dl2_roof/status", discovery.availability_topic());
+
+        let messages = discovery.config_messages(&spec, &data_set);
+        let message = &messages[0];
+        assert_eq!(
+            "homeassistant/sensor/dl2_roof/00_0010_7e11_10_0100_000_2_0/config",
+            message.topic
+        );
+
+        let payload = parse_json(&message.payload).unwrap();
+        assert_eq!(
+            Some("Temperature sensor 1"),
+            payload.get("name").and_then(|v| v.as_str())
+        );
+        assert_eq!(
+            Some("dl2_roof_00_0010_7e11_10_0100_000_2_0"),
+            payload.get("unique_id").and_then(|v| v.as_str())
+        );
+        assert_eq!(
+            Some("vbus/dl2_roof/00_0010_7e11_10_0100_000_2_0"),
+            payload.get("state_topic").and_then(|v| v.as_str())
+        );
+        assert_eq!(
+            Some("°C"),
+            payload.get("unit_of_measurement").and_then(|v| v.as_str())
+        );
+        assert_eq!(
+            Some("temperature"),
+            payload.get("device_class").and_then(|v| v.as_str())
+        );
+        assert_eq!(
+            Some("measurement"),
+            payload.get("state_class").and_then(|v| v.as_str())
+        );
+        assert_eq!(
+            Some("vbus/dl2_roof/status"),
+            payload.get("availability_topic").and_then(|v| v.as_str())
+        );
+        assert_eq!(
+            Some("RESOL"),
+            payload
+                .get("device")
+                .and_then(|v| v.get("manufacturer"))
+                .and_then(|v| v.as_str())
+        );
+
+        let mut discovery = HomeAssistantDiscovery::new("dl2");
+        discovery.set_base_topic("home/solar/");
+        let messages = discovery.state_messages(&spec, &data_set);
+        assert_eq!(
+            "home/solar/dl2/00_0010_7e11_10_0100_000_2_0",
+            messages[0].topic
+        );
+        assert_eq!("87.2", messages[0].payload);
+    }
+}
@@ -0,0 +1,962 @@
+use std::{
+    io::ErrorKind,
+    path::Path,
+    time::{Duration, Instant},
+};
+
+use async_std::{
+    channel::{Receiver, Sender, TrySendError},
+    net::{TcpListener, TcpStream},
+    prelude::*,
+    sync::{Arc, Mutex},
+};
+
+use resol_vbus::{
+    chrono::Utc, specification::DataSetPacketField, Data, DataSet, Language, RecordingReader,
+    RecordingWriter,
+};
+
+use crate::{
+    changed_fields::changed_fields,
+    device_information::DeviceInformation,
+    error::Result,
+    health::{HealthMonitor, HealthReport},
+    json::{push_json_number, push_json_string},
+    param_request::{request_param, ParamRequest},
+    shutdown::ShutdownSignal,
+    spec_cache::default_specification,
+    write_guard::WriteGuard,
+};
+
+/// The maximum accepted length of a request body in bytes.
+const MAX_BODY_LENGTH: usize = 16384;
+
+/// A minimal embedded HTTP server providing access to live data and
+/// parameters of a VBus device.
+///
+/// The following endpoints are provided:
+///
+/// - `GET /api/live`: the latest accumulated `DataSet` decoded into JSON,
+///   using the language selected with `set_language`. Fields restored from
+///   a snapshot that were not received again yet are marked with
+///   `"stale":true` and their `"age"` in seconds
+/// - `GET /api/param/<id>`: read a parameter by its index (decimal or `0x`
+///   prefixed hexadecimal) or value ID
+/// - `PUT /api/param/<id>`: write a parameter, the request body contains
+///   the raw integer value. Writing is disabled unless enabled using
+///   `set_writes_enabled`. Disabled writes and writes denied by the
+///   `WriteGuard` set using `set_write_guard` are rejected with status 403
+/// - `GET /api/health`: the `HealthReport` of the `HealthMonitor` set
+///   using `set_health_monitor`, responding with status 503 if the
+///   application is not ready
+/// - `GET /api/events`: a Server-Sent Events stream of `field` events, one
+///   for every field of the accumulated `DataSet` after connecting and one
+///   for every field whose value changed afterwards. Every event contains
+///   the field in the same format as `/api/live`
+///
+/// The `HttpApi` does not own the `LiveDataStream`. Instead the application
+/// feeds received `Data` into it using `add_data` and processes the
+/// `ParamRequest`s received from the `Receiver` returned by `HttpApi::new`.
+/// This way the parameter transactions are serialized with all other
+/// operations performed on the stream.
+///
+/// Requests with a body larger than 16 KiB are rejected with status 413.
+/// Connections that do not send their complete request within the request
+/// timeout (see `set_request_timeout`) are closed.
+///
+/// The accumulated `DataSet` can be persisted using `save_snapshot` or
+/// `persist_snapshots` and restored on startup using `restore_snapshot`,
+/// so that a restarted gateway serves the last known values immediately.
+///
+/// This type is only available if the `http-api` feature is enabled.
+///
+/// # Examples
+///
+/// ```no_run
+/// # fn main() -> async_resol_vbus::Result<()> { async_std::task::block_on(async {
+/// #
+/// use async_std::net::{TcpListener, TcpStream};
+///
+/// use async_resol_vbus::{HttpApi, LiveDataStream};
+///
+/// let stream = TcpStream::connect("192.168.5.217:7053").await?;
+/// // ... perform handshake ...
+/// let mut lds = LiveDataStream::new(stream.clone(), stream, 0, 0x0020);
+///
+/// let (api, param_requests) = HttpApi::new();
+///
+/// let listener = TcpListener::bind("127.0.0.1:8080").await?;
+/// async_std::task::spawn(api.clone().serve(listener));
+///
+/// loop {
+///     while let Ok(request) = param_requests.try_recv() {
+///         request.process(&mut lds, 0x7E11).await?;
+///     }
+///
+///     if let Some(data) = lds.receive_any_data(1000).await? {
+///         api.add_data(data).await;
+///     }
+/// }
+/// #
+/// # }) }
+/// ```
+#[derive(Debug, Clone)]
+pub struct HttpApi {
+    data_set: Arc<Mutex<DataSet>>,
+    stale_ids: Arc<Mutex<Vec<String>>>,
+    param_sender: Sender<ParamRequest>,
+    language: Language,
+    health_monitor: Option<HealthMonitor>,
+    event_senders: Arc<Mutex<Vec<Sender<String>>>>,
+    write_guard: Option<WriteGuard>,
+    writes_enabled: bool,
+    request_timeout: Duration,
+}
+
+impl HttpApi {
+    /// Create a new `HttpApi` and the `Receiver` for its `ParamRequest`s.
+    pub fn new() -> (HttpApi, Receiver<ParamRequest>) {
+        let (param_sender, param_receiver) = async_std::channel::bounded(10);
+
+        let api = HttpApi {
+            data_set: Arc::new(Mutex::new(DataSet::new())),
+            stale_ids: Arc::new(Mutex::new(Vec::new())),
+            param_sender,
+            language: Language::En,
+            health_monitor: None,
+            event_senders: Arc::new(Mutex::new(Vec::new())),
+            write_guard: None,
+            writes_enabled: false,
+            request_timeout: Duration::from_secs(10),
+        };
+
+        (api, param_receiver)
+    }
+
+    /// Set the language used for the packet and field names and the unit
+    /// texts of the live data.
+    ///
+    /// Defaults to `Language::En`.
+    pub fn set_language(&mut self, language: Language) {
+        self.language = language;
+    }
+
+    /// Set the `HealthMonitor` served by the `/api/health` endpoint.
+    ///
+    /// Defaults to `None`, which disables the endpoint.
+    pub fn set_health_monitor(&mut self, health_monitor: Option<HealthMonitor>) {
+        self.health_monitor = health_monitor;
+    }
+
+    /// Enable writing parameters using `PUT /api/param/<id>`.
+    ///
+    /// The API does not authenticate its clients, so only enable writes if
+    /// the listener is not reachable by untrusted clients.
+    ///
+    /// Defaults to `false`, which rejects writes with status 403.
+    pub fn set_writes_enabled(&mut self, writes_enabled: bool) {
+        self.writes_enabled = writes_enabled;
+    }
+
+    /// Set the time a client has to send its complete request after
+    /// connecting.
+    ///
+    /// Defaults to 10 seconds.
+    pub fn set_request_timeout(&mut self, request_timeout: Duration) {
+        self.request_timeout = request_timeout;
+    }
+
+    /// Set the `WriteGuard` restricting the parameters that can be written.
+    ///
+    /// The guard is checked by `ParamRequest::process` before writing, so
+    /// it applies even if the `LiveDataStream` has no `WriteGuard` of its
+    /// own.
+    ///
+    /// Defaults to `None`, which allows writing all parameters.
+    pub fn set_write_guard(&mut self, write_guard: Option<WriteGuard>) {
+        self.write_guard = write_guard;
+    }
+
+    /// Add a received `Data` to the accumulated `DataSet`.
+    pub async fn add_data(&self, data: Data) {
+        let id = data.id_string();
+        self.stale_ids
+            .lock()
+            .await
+            .retain(|stale_id| *stale_id != id);
+
+        let mut data_set = self.data_set.lock().await;
+
+        let mut event_senders = self.event_senders.lock().await;
+        if !event_senders.is_empty() {
+            let previous = data_set.iter().find(|previous| previous.id_string() == id);
+            let events = changed_field_events(previous, &data, self.language);
+            event_senders.retain(|sender| {
+                events.iter().all(|event| {
+                    !matches!(sender.try_send(event.clone()), Err(TrySendError::Closed(_)))
+                })
+            });
+        }
+        drop(event_senders);
+
+        data_set.timestamp = data.as_ref().timestamp;
+        data_set.add_data(data);
+    }
+
+    /// Write the accumulated `DataSet` to a snapshot file at `path`.
+    ///
+    /// The snapshot is written to a temporary file first which is then
+    /// renamed, so that a crash does not leave a truncated snapshot behind.
+    pub async fn save_snapshot<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        let data_set = self.data_set.lock().await.clone();
+
+        let mut rw = RecordingWriter::new(Vec::new());
+        rw.write_data_set(&data_set)?;
+
+        let mut tmp_path = path.as_ref().to_path_buf().into_os_string();
+        tmp_path.push(".tmp");
+
+        async_std::fs::write(&tmp_path, rw.get_ref()).await?;
+        async_std::fs::rename(&tmp_path, path.as_ref()).await?;
+
+        Ok(())
+    }
+
+    /// Save a snapshot to `path` every `interval` until an error occurs.
+    pub async fn persist_snapshots<P: AsRef<Path>>(
+        self,
+        path: P,
+        interval: Duration,
+    ) -> Result<()> {
+        loop {
+            async_std::task::sleep(interval).await;
+            self.save_snapshot(path.as_ref()).await?;
+        }
+    }
+
+    /// Restore the accumulated `DataSet` from a snapshot file at `path`.
+    ///
+    /// The restored `Data` keeps its original timestamp and is reported as
+    /// stale until it is received again using `add_data`. Does nothing if
+    /// the file does not exist yet.
+    pub async fn restore_snapshot<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        let bytes = match async_std::fs::read(path.as_ref()).await {
+            Ok(bytes) => bytes,
+            Err(err) if err.kind() == ErrorKind::NotFound => return Ok(()),
+            Err(err) => return Err(err.into()),
+        };
+
+        let mut rr = RecordingReader::new(&bytes[..]);
+        if let Some(snapshot) = rr.read_data_set()? {
+            let mut stale_ids = self.stale_ids.lock().await;
+            let mut data_set = self.data_set.lock().await;
+            for data in snapshot.iter() {
+                stale_ids.push(data.id_string());
+            }
+            data_set.timestamp = snapshot.timestamp;
+            data_set.add_data_set(snapshot);
+        }
+
+        Ok(())
+    }
+
+    /// Accept and handle HTTP connections until an error occurs.
+    pub async fn serve(self, listener: TcpListener) -> Result<()> {
+        loop {
+            let (stream, _) = listener.accept().await?;
+
+            let api = self.clone();
+            async_std::task::spawn(async move {
+                drop(api.handle_connection(stream).await);
+            });
+        }
+    }
+
+    /// Accept and handle HTTP connections until the shutdown is triggered.
+    ///
+    /// New connections are no longer accepted once the shutdown was
+    /// triggered, the listener is closed and the result is reported to
+    /// the `ShutdownSignal`.
+    pub async fn serve_until_shutdown(self, listener: TcpListener, signal: ShutdownSignal) {
+        let result = signal.run_until(self.serve(listener)).await;
+        signal.complete(result.unwrap_or(Ok(())));
+    }
+
+    async fn handle_connection(&self, mut stream: TcpStream) -> Result<()> {
+        let deadline = Instant::now() + self.request_timeout;
+
+        let mut buf = Vec::with_capacity(1024);
+        let body_idx = loop {
+            if let Some(idx) = DeviceInformation::find_http_body_idx(&buf) {
+                break idx;
+            }
+
+            if buf.len() > 16384 {
+                return Err("HTTP request header too large".into());
+            }
+
+            let mut chunk = [0u8; 1024];
+            let len = read_before(&mut stream, &mut chunk, deadline).await?;
+            if len == 0 {
+                return Err("EOF before HTTP header".into());
+            }
+
+            buf.extend_from_slice(&chunk[0..len]);
+        };
+
+        let header = std::str::from_utf8(&buf[0..body_idx])?.to_string();
+
+        let content_length = header
+            .lines()
+            .filter_map(|line| line.split_once(':'))
+            .find(|(key, _)| key.trim().eq_ignore_ascii_case("content-length"))
+            .and_then(|(_, value)| value.trim().parse::<usize>().ok())
+            .unwrap_or(0);
+
+        if content_length > MAX_BODY_LENGTH {
+            let content = error_to_json("Request body too large");
+            return write_response(&mut stream, "413 Payload Too Large", &content).await;
+        }
+
+        while buf.len() < body_idx + content_length {
+            let mut chunk = [0u8; 1024];
+            let len = read_before(&mut stream, &mut chunk, deadline).await?;
+            if len == 0 {
+                break;
+            }
+
+            buf.extend_from_slice(&chunk[0..len]);
+        }
+
+        let body = std::str::from_utf8(&buf[body_idx..])?;
+
+        let mut request_line = header.lines().next().unwrap_or("").split_whitespace();
+        let method = request_line.next().unwrap_or("");
+        let path = request_line.next().unwrap_or("");
+
+        if method == "GET" && path == "/api/events" {
+            return self.stream_events(stream).await;
+        }
+
+        let (status, content) = self.handle_request(method, path, body).await;
+
+        write_response(&mut stream, status, &content).await
+    }
+
+    async fn stream_events(&self, mut stream: TcpStream) -> Result<()> {
+        let (sender, receiver) = async_std::channel::bounded(256);
+
+        let initial_events = {
+            let data_set = self.data_set.lock().await;
+            self.event_senders.lock().await.push(sender);
+
+            let spec = default_specification(self.language);
+            spec.fields_in_data_set(&*data_set)
+                .map(|field| field_event(&field))
+                .collect::<Vec<_>>()
+        };
+
+        let mut content = String::from(
+            "HTTP/1.0 200 OK\r\nContent-Type: text/event-stream\r\nCache-Control: no-cache\r\nConnection: close\r\n\r\n",
+        );
+        for event in initial_events {
+            content.push_str(&event);
+        }
+        stream.write_all(content.as_bytes()).await?;
+        stream.flush().await?;
+
+        loop {
+            // send a comment regularly to detect closed connections
+            let content =
+                match async_std::future::timeout(Duration::from_secs(15), receiver.recv()).await {
+                    Ok(Ok(event)) => event,
+                    Ok(Err(_)) => return Ok(()),
+                    Err(_) => ": keep-alive\n\n".to_string(),
+                };
+
+            stream.write_all(content.as_bytes()).await?;
+            stream.flush().await?;
+        }
+    }
+
+    async fn handle_request(&self, method: &str, path: &str, body: &str) -> (&'static str, String) {
+        if path == "/api/live" {
+            if method == "GET" {
+                let data_set = self.data_set.lock().await.clone();
+                let stale_ids = self.stale_ids.lock().await.clone();
+                (
+                    "200 OK",
+                    live_data_to_json(&data_set, &stale_ids, self.language),
+                )
+            } else {
+                (
+                    "405 Method Not Allowed",
+                    error_to_json("Method not allowed"),
+                )
+            }
+        } else if path == "/api/health" {
+            match (method, &self.health_monitor) {
+                ("GET", Some(health_monitor)) => {
+                    let report = health_monitor.report();
+                    let status = if report.is_ready() {
+                        "200 OK"
+                    } else {
+                        "503 Service Unavailable"
+                    };
+                    (status, health_report_to_json(&report))
+                }
+                ("GET", None) => ("404 Not Found", error_to_json("Not found")),
+                _ => (
+                    "405 Method Not Allowed",
+                    error_to_json("Method not allowed"),
+                ),
+            }
+        } else if let Some(id) = path.strip_prefix("/api/param/") {
+            let value = match method {
+                "GET" => None,
+                "PUT" | "POST" if !self.writes_enabled => {
+                    return (
+                        "403 Forbidden",
+                        error_to_json("Writing parameters is disabled"),
+                    )
+                }
+                "PUT" | "POST" => match body.trim().parse::<i32>() {
+                    Ok(value) => Some(value),
+                    Err(_) => return ("400 Bad Request", error_to_json("Invalid value")),
+                },
+                _ => {
+                    return (
+                        "405 Method Not Allowed",
+                        error_to_json("Method not allowed"),
+                    )
+                }
+            };
+
+            match request_param(&self.param_sender, id, value, self.write_guard.as_ref()).await {
+                Ok((index, value)) => {
+                    let mut content = String::new();
+                    content.push_str("{\"id\":");
+                    push_json_string(&mut content, id);
+                    content.push_str(&format!(",\"index\":{},\"value\":{}}}", index, value));
+                    ("200 OK", content)
+                }
+                Err(err) if err.is_write_denied() => {
+                    ("403 Forbidden", error_to_json(&err.to_string()))
+                }
+                Err(err) => ("502 Bad Gateway", error_to_json(&err.to_string())),
+            }
+        } else {
+            ("404 Not Found", error_to_json("Not found"))
+        }
+    }
+}
+
+fn live_data_to_json(data_set: &DataSet, stale_ids: &[String], language: Language) -> String {
+    let now = Utc::now();
+    let spec = default_specification(language);
+
+    let mut content = String::new();
+    content.push_str("{\"timestamp\":");
+    push_json_string(&mut content, &data_set.timestamp.to_rfc3339());
+    content.push_str(",\"fields\":[");
+    for (idx, field) in spec.fields_in_data_set(data_set).enumerate() {
+        if idx > 0 {
+            content.push(',');
+        }
+        push_field_members(&mut content, &field);
+        if stale_ids.contains(&field.data().id_string()) {
+            let age = now - field.data().as_ref().timestamp;
+            content.push_str(&format!(",\"stale\":true,\"age\":{}", age.num_seconds()));
+        }
+        content.push('}');
+    }
+    content.push_str("]}");
+    content
+}
+
+fn push_field_members<T: AsRef<[Data]>>(content: &mut String, field: &DataSetPacketField<'_, T>) {
+    content.push_str("{\"id\":");
+    push_json_string(content, &field.field_spec().packet_field_id);
+    content.push_str(",\"packet\":");
+    push_json_string(content, &field.packet_spec().name);
+    content.push_str(",\"name\":");
+    push_json_string(content, &field.field_spec().name);
+    content.push_str(",\"value\":");
+    push_json_number(content, field.raw_value_f64());
+    content.push_str(",\"unit\":");
+    push_json_string(content, &field.field_spec().unit_code);
+    content.push_str(",\"unitText\":");
+    push_json_string(content, field.field_spec().unit_text.trim());
+}
+
+fn field_event<T: AsRef<[Data]>>(field: &DataSetPacketField<'_, T>) -> String {
+    let mut content = String::from("event: field\ndata: ");
+    push_field_members(&mut content, field);
+    content.push_str("}\n\n");
+    content
+}
+
+/// Create the events for all fields of `data` whose values differ from
+/// the `previous` `Data` with the same ID.
+fn changed_field_events(previous: Option<&Data>, data: &Data, language: Language) -> Vec<String> {
+    let mut events = Vec::new();
+    changed_fields(previous, data, &default_specification(language), |field| {
+        events.push(field_event(field));
+    });
+    events
+}
+
+fn health_report_to_json(report: &HealthReport) -> String {
+    let mut content = String::new();
+    content.push_str(&format!(
+        "{{\"connected\":{},\"ready\":{},\"lastData\":",
+        report.connected,
+        report.is_ready()
+    ));
+    match report.last_data {
+        Some(last_data) => push_json_string(&mut content, &last_data.to_rfc3339()),
+        None => content.push_str("null"),
+    }
+    content.push_str(&format!(
+        ",\"reconnectCount\":{},\"sinkLags\":{{",
+        report.reconnect_count
+    ));
+    for (idx, (name, lag)) in report.sink_lags.iter().enumerate() {
+        if idx > 0 {
+            content.push(',');
+        }
+        push_json_string(&mut content, name);
+        content.push(':');
+        push_json_number(&mut content, Some(lag.as_secs_f64()));
+    }
+    content.push_str("},\"errorCounts\":{");
+    for (idx, (subsystem, count)) in report.error_counts.iter().enumerate() {
+        if idx > 0 {
+            content.push(',');
+        }
+        push_json_string(&mut content, subsystem);
+        content.push_str(&format!(":{}", count));
+    }
+    content.push_str("}}");
+    content
+}
+
+/// Read from `stream`, failing if the `deadline` of the request has passed.
+async fn read_before(stream: &mut TcpStream, buf: &mut [u8], deadline: Instant) -> Result<usize> {
+    let remaining = deadline.saturating_duration_since(Instant::now());
+    Ok(async_std::io::timeout(remaining, stream.read(buf)).await?)
+}
+
+async fn write_response(stream: &mut TcpStream, status: &str, content: &str) -> Result<()> {
+    let response = format!(
+        "HTTP/1.0 {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        status,
+        content.len(),
+        content
+    );
+
+    stream.write_all(response.as_bytes()).await?;
+    stream.flush().await?;
+
+    Ok(())
+}
+
+fn error_to_json(message: &str) -> String {
+    let mut content = String::new();
+    content.push_str("{\"error\":");
+    push_json_string(&mut content, message);
+    content.push('}');
+    content
+}
+
+#[cfg(test)]
+mod tests {
+    use async_std::{io::Cursor, net::SocketAddr};
+
+    use resol_vbus::{chrono::Utc, Header, Packet};
+
+    use super::*;
+
+    use crate::{
+        live_data_stream::LiveDataStream,
+        test_utils::{extend_from_datagram, extend_with_empty_packet, hex_encode},
+    };
+
+    async fn http_request(addr: SocketAddr, request: &str) -> Result<String> {
+        let mut stream = TcpStream::connect(addr).await?;
+        stream.write_all(request.as_bytes()).await?;
+
+        let mut response = String::new();
+        stream.read_to_string(&mut response).await?;
+
+        Ok(response)
+    }
+
+    async fn read_until(
+        stream: &mut TcpStream,
+        response: &mut Vec<u8>,
+        needle: &str,
+    ) -> Result<String> {
+        while !String::from_utf8_lossy(response).contains(needle) {
+            let mut chunk = [0u8; 1024];
+            let len = stream.read(&mut chunk).await?;
+            if len == 0 {
+                return Err("EOF before expected content".into());
+            }
+            response.extend_from_slice(&chunk[0..len]);
+        }
+        Ok(String::from_utf8_lossy(response).to_string())
+    }
+
+    #[test]
+    fn test_live() -> Result<()> {
+        async_std::task::block_on(async {
+            let listener = TcpListener::bind("127.0.0.1:0").await?;
+            let addr = listener.local_addr()?;
+
+            let (api, _param_requests) = HttpApi::new();
+
+            let mut frame_data = [0u8; 508];
+            frame_data[0..2].copy_from_slice(&872i16.to_le_bytes());
+
+            api.add_data(Data::Packet(Packet {
+                header: Header {
+                    timestamp: Utc::now(),
+                    channel: 0,
+                    destination_address: 0x0010,
+                    source_address: 0x7E11,
+                    protocol_version: 0x10,
+                },
+                command: 0x0100,
+                frame_count: 1,
+                frame_data,
+            }))
+            .await;
+
+            let mut api_de = api.clone();
+            api_de.set_language(Language::De);
+            let listener_de = TcpListener::bind("127.0.0.1:0").await?;
+            let addr_de = listener_de.local_addr()?;
+
+            async_std::task::spawn(api.serve(listener));
+            async_std::task::spawn(api_de.serve(listener_de));
+
+            let response = http_request(addr, "GET /api/live HTTP/1.0\r\n\r\n").await?;
+
+            assert!(response.starts_with("HTTP/1.0 200 OK\r\n"));
+            assert!(response.contains("{\"id\":\"00_0010_7E11_10_0100_000_2_0\",\"packet\":\"DeltaSol MX [Controller]\",\"name\":\"Temperature sensor 1\",\"value\":87.2,\"unit\":\"DegreesCelsius\",\"unitText\":\"°C\"}"));
+
+            let response = http_request(addr_de, "GET /api/live HTTP/1.0\r\n\r\n").await?;
+
+            assert!(response.contains("\"name\":\"Temperatur Sensor 1\""));
+
+            let response = http_request(addr, "GET /api/unknown HTTP/1.0\r\n\r\n").await?;
+
+            assert!(response.starts_with("HTTP/1.0 404 Not Found\r\n"));
+
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn test_snapshot() -> Result<()> {
+        async_std::task::block_on(async {
+            let path = std::env::temp_dir().join(format!(
+                "async-resol-vbus-snapshot-{}.vbus",
+                std::process::id()
+            ));
+
+            let packet = Packet {
+                header: Header {
+                    timestamp: Utc::now() - resol_vbus::chrono::Duration::seconds(60),
+                    channel: 0,
+                    destination_address: 0x0010,
+                    source_address: 0x7E11,
+                    protocol_version: 0x10,
+                },
+                command: 0x0100,
+                frame_count: 1,
+                frame_data: [0u8; 508],
+            };
+
+            let (api, _param_requests) = HttpApi::new();
+            api.restore_snapshot(&path).await?;
+            api.add_data(Data::Packet(packet.clone())).await;
+            api.save_snapshot(&path).await?;
+
+            let (api, _param_requests) = HttpApi::new();
+            api.restore_snapshot(&path).await?;
+            async_std::fs::remove_file(&path).await?;
+
+            let listener = TcpListener::bind("127.0.0.1:0").await?;
+            let addr = listener.local_addr()?;
+            async_std::task::spawn(api.clone().serve(listener));
+
+            let response = http_request(addr, "GET /api/live HTTP/1.0\r\n\r\n").await?;
+            let age = response
+                .split("\"unitText\":\"°C\",\"stale\":true,\"age\":")
+                .nth(1)
+                .and_then(|rest| rest.split('}').next())
+                .and_then(|age| age.parse::<i64>().ok())
+                .unwrap();
+            assert!((60..=62).contains(&age), "unexpected age {}", age);
+
+            let mut packet = packet;
+            packet.header.timestamp = Utc::now();
+            api.add_data(Data::Packet(packet)).await;
+
+            let response = http_request(addr, "GET /api/live HTTP/1.0\r\n\r\n").await?;
+            assert!(response.contains("\"name\":\"Temperature sensor 1\""));
+            assert!(!response.contains("\"stale\""));
+
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn test_param() -> Result<()> {
+        async_std::task::block_on(async {
+            let listener = TcpListener::bind("127.0.0.1:0").await?;
+            let addr = listener.local_addr()?;
+
+            let (mut api, param_requests) = HttpApi::new();
+            api.set_writes_enabled(true);
+
+            async_std::task::spawn(api.serve(listener));
+
+            let client_future = async_std::task::spawn(async move {
+                http_request(
+                    addr,
+                    "PUT /api/param/0x1234 HTTP/1.0\r\nContent-Length: 3\r\n\r\n456",
+                )
+                .await
+            });
+
+            let request = param_requests.recv().await.unwrap();
+            assert_eq!("0x1234", request.id());
+            assert_eq!(Some(456), request.value());
+
+            let mut rx_buf = Vec::new();
+            extend_from_datagram(&mut rx_buf, 0x0000, 0x7E11, 0x0500, 0, 0);
+            extend_from_datagram(&mut rx_buf, 0x0020, 0x7E11, 0x0100, 0x1234, 456);
+            extend_with_empty_packet(&mut rx_buf, 0x0010, 0x7E11, 0x0100);
+
+            let mut lds = LiveDataStream::new(&rx_buf[..], Cursor::new(Vec::new()), 0, 0x0020);
+
+            request.process(&mut lds, 0x7E11).await?;
+
+            let response = client_future.await?;
+
+            assert!(response.starts_with("HTTP/1.0 200 OK\r\n"));
+            assert!(response.ends_with("\r\n\r\n{\"id\":\"0x1234\",\"index\":4660,\"value\":456}"));
+
+            let response = http_request(
+                addr,
+                "PUT /api/param/0x1234 HTTP/1.0\r\nContent-Length: 1000000000\r\n\r\n456",
+            )
+            .await?;
+            assert!(response.starts_with("HTTP/1.0 413 Payload Too Large\r\n"));
+            assert!(param_requests.try_recv().is_err());
+
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn test_param_writes_disabled() -> Result<()> {
+        async_std::task::block_on(async {
+            let listener = TcpListener::bind("127.0.0.1:0").await?;
+            let addr = listener.local_addr()?;
+
+            let (api, param_requests) = HttpApi::new();
+
+            async_std::task::spawn(api.serve(listener));
+
+            let response = http_request(
+                addr,
+                "PUT /api/param/0x1234 HTTP/1.0\r\nContent-Length: 3\r\n\r\n456",
+            )
+            .await?;
+            assert!(response.starts_with("HTTP/1.0 403 Forbidden\r\n"));
+            assert!(response.ends_with("{\"error\":\"Writing parameters is disabled\"}"));
+            assert!(param_requests.try_recv().is_err());
+
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn test_request_timeout() -> Result<()> {
+        async_std::task::block_on(async {
+            let listener = TcpListener::bind("127.0.0.1:0").await?;
+            let addr = listener.local_addr()?;
+
+            let (mut api, _param_requests) = HttpApi::new();
+            api.set_request_timeout(Duration::from_millis(100));
+
+            async_std::task::spawn(api.serve(listener));
+
+            let mut stream = TcpStream::connect(addr).await?;
+            stream.write_all(b"GET /api/live HTTP/1.0\r\n").await?;
+
+            let mut response = Vec::new();
+            let len =
+                async_std::io::timeout(Duration::from_secs(1), stream.read_to_end(&mut response))
+                    .await?;
+            assert_eq!(0, len);
+
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn test_param_write_guard() -> Result<()> {
+        async_std::task::block_on(async {
+            let listener = TcpListener::bind("127.0.0.1:0").await?;
+            let addr = listener.local_addr()?;
+
+            let (mut api, param_requests) = HttpApi::new();
+            api.set_writes_enabled(true);
+            api.set_write_guard(Some(WriteGuard::deny_all()));
+
+            async_std::task::spawn(api.serve(listener));
+
+            let client_future = async_std::task::spawn(async move {
+                http_request(
+                    addr,
+                    "PUT /api/param/0x1234 HTTP/1.0\r\nContent-Length: 3\r\n\r\n456",
+                )
+                .await
+            });
+
+            let request = param_requests.recv().await.unwrap();
+
+            let mut rx_buf = Vec::new();
+            extend_from_datagram(&mut rx_buf, 0x0000, 0x7E11, 0x0500, 0, 0);
+
+            let mut lds = LiveDataStream::new(&rx_buf[..], Cursor::new(Vec::new()), 0, 0x0020);
+
+            request.process(&mut lds, 0x7E11).await?;
+
+            let response = client_future.await?;
+
+            assert!(response.starts_with("HTTP/1.0 403 Forbidden\r\n"));
+            assert_eq!(
+                "aa117e2000200006000000000000002a",
+                hex_encode(lds.writer_ref())
+            );
+
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn test_health() -> Result<()> {
+        async_std::task::block_on(async {
+            let listener = TcpListener::bind("127.0.0.1:0").await?;
+            let addr = listener.local_addr()?;
+
+            let health_monitor = HealthMonitor::new();
+            health_monitor.set_connected(true);
+            health_monitor.record_error("hub");
+            health_monitor.set_sink_lag("recorder", Duration::from_millis(1500));
+
+            let (mut api, _param_requests) = HttpApi::new();
+            api.set_health_monitor(Some(health_monitor.clone()));
+
+            async_std::task::spawn(api.serve(listener));
+
+            let response = http_request(addr, "GET /api/health HTTP/1.0\r\n\r\n").await?;
+            assert!(response.starts_with("HTTP/1.0 503 Service Unavailable\r\n"));
+            assert!(response.ends_with("\r\n\r\n{\"connected\":true,\"ready\":false,\"lastData\":null,\"reconnectCount\":0,\"sinkLags\":{\"recorder\":1.5},\"errorCounts\":{\"hub\":1}}"));
+
+            health_monitor.record_data(Utc::now());
+
+            let response = http_request(addr, "GET /api/health HTTP/1.0\r\n\r\n").await?;
+            assert!(response.starts_with("HTTP/1.0 200 OK\r\n"));
+            assert!(response.contains("\"ready\":true"));
+
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn test_events() -> Result<()> {
+        async_std::task::block_on(async {
+            let listener = TcpListener::bind("127.0.0.1:0").await?;
+            let addr = listener.local_addr()?;
+
+            let packet = |temperature: i16| {
+                let mut frame_data = [0u8; 508];
+                frame_data[0..2].copy_from_slice(&temperature.to_le_bytes());
+                Data::Packet(Packet {
+                    header: Header {
+                        timestamp: Utc::now(),
+                        channel: 0,
+                        destination_address: 0x0010,
+                        source_address: 0x7E11,
+                        protocol_version: 0x10,
+                    },
+                    command: 0x0100,
+                    frame_count: 1,
+                    frame_data,
+                })
+            };
+
+            let (api, _param_requests) = HttpApi::new();
+            api.add_data(packet(872)).await;
+
+            async_std::task::spawn(api.clone().serve(listener));
+
+            let mut stream = TcpStream::connect(addr).await?;
+            stream
+                .write_all(b"GET /api/events HTTP/1.0\r\n\r\n")
+                .await?;
+
+            let mut response = Vec::new();
+            let content = read_until(&mut stream, &mut response, "\"value\":87.2").await?;
+            assert!(content.starts_with("HTTP/1.0 200 OK\r\nContent-Type: text/event-stream\r\n"));
+            assert!(content.contains("\r\n\r\nevent: field\ndata: {\"id\":\"00_0010_7E11_10_0100_000_2_0\",\"packet\":\"DeltaSol MX [Controller]\",\"name\":\"Temperature sensor 1\",\"value\":87.2,"));
+
+            // unchanged fields produce no events
+            api.add_data(packet(872)).await;
+            api.add_data(packet(901)).await;
+
+            let content = read_until(&mut stream, &mut response, "\"value\":90.1").await?;
+            let events = content.trim_end().split("\n\n").collect::<Vec<_>>();
+            assert!(events
+                .last()
+                .unwrap()
+                .contains("\"name\":\"Temperature sensor 1\",\"value\":90.1"));
+            assert_eq!(
+                2,
+                content.matches("\"name\":\"Temperature sensor 1\"").count()
+            );
+
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn test_serve_until_shutdown() -> Result<()> {
+        async_std::task::block_on(async {
+            let listener = TcpListener::bind("127.0.0.1:0").await?;
+            let addr = listener.local_addr()?;
+
+            let shutdown = crate::shutdown::Shutdown::new();
+
+            let (api, _param_requests) = HttpApi::new();
+            async_std::task::spawn(api.serve_until_shutdown(listener, shutdown.register("http")));
+
+            let response = http_request(addr, "GET /api/live HTTP/1.0\r\n\r\n").await?;
+            assert!(response.starts_with("HTTP/1.0 200 OK\r\n"));
+
+            let results = shutdown.shutdown().await;
+            assert_eq!(vec![("http".to_string(), Ok(()))], results);
+
+            assert!(TcpStream::connect(addr).await.is_err());
+
+            Ok(())
+        })
+    }
+}
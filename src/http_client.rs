@@ -0,0 +1,581 @@
+use std::{net::SocketAddr, time::Duration};
+
+use async_std::{net::TcpStream, prelude::*};
+
+use crate::{device_information::DeviceInformation, error::Result};
+
+/// The maximum size of the response header.
+const MAX_HEADER_SIZE: usize = 16 * 1024;
+
+const BASE64_CHARS: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+fn base64_encode(bytes: &[u8]) -> String {
+    let mut out = String::with_capacity(bytes.len().div_ceil(3) * 4);
+    for chunk in bytes.chunks(3) {
+        let b = [
+            chunk[0],
+            chunk.get(1).copied().unwrap_or(0),
+            chunk.get(2).copied().unwrap_or(0),
+        ];
+        let n = (u32::from(b[0]) << 16) | (u32::from(b[1]) << 8) | u32::from(b[2]);
+        for idx in 0..4 {
+            if idx <= chunk.len() {
+                out.push(BASE64_CHARS[((n >> (18 - idx * 6)) & 0x3F) as usize] as char);
+            } else {
+                out.push('=');
+            }
+        }
+    }
+    out
+}
+
+/// A response received by the `HttpClient`.
+#[derive(Debug, Clone)]
+pub struct HttpResponse {
+    /// The HTTP status code.
+    pub status: u16,
+
+    /// The response headers in the order they were received.
+    pub headers: Vec<(String, String)>,
+
+    /// The response body.
+    pub body: Vec<u8>,
+}
+
+impl HttpResponse {
+    /// Get the value of the first header matching `name` case-insensitively.
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(key, _)| key.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+
+    /// Get the response body as a string.
+    pub fn body_str(&self) -> Result<&str> {
+        Ok(std::str::from_utf8(&self.body)?)
+    }
+}
+
+/// A minimal HTTP/1.1 client to communicate with the web server of a
+/// VBus-over-TCP device.
+///
+/// Every request is limited by a timeout and the size of the response body
+/// is limited. If keep-alive is enabled, the connection is reused for
+/// subsequent requests as long as the server allows it. If the server
+/// closed a reused connection before responding, the request is sent again
+/// once using a new connection.
+///
+/// # Examples
+///
+/// ```no_run
+/// # fn main() -> async_resol_vbus::Result<()> { async_std::task::block_on(async {
+/// #
+/// use async_resol_vbus::HttpClient;
+///
+/// let mut client = HttpClient::new("192.168.5.217:80".parse()?);
+/// client.set_basic_auth(Some(("admin".into(), "admin".into())));
+///
+/// let response = client.get("/cgi-bin/get_resol_device_information").await?;
+/// println!("{}", response.body_str()?);
+/// #
+/// # Ok(()) }) }
+/// ```
+#[derive(Debug)]
+pub struct HttpClient {
+    addr: SocketAddr,
+    timeout: Duration,
+    basic_auth: Option<(String, String)>,
+    keep_alive: bool,
+    max_body_size: usize,
+    stream: Option<TcpStream>,
+}
+
+impl HttpClient {
+    /// Create a new `HttpClient` for the web server at `addr`.
+    pub fn new(addr: SocketAddr) -> HttpClient {
+        HttpClient {
+            addr,
+            timeout: Duration::from_millis(2000),
+            basic_auth: None,
+            keep_alive: false,
+            max_body_size: 1024 * 1024,
+            stream: None,
+        }
+    }
+
+    /// Set the timeout for a complete request. Defaults to 2 seconds.
+    pub fn set_timeout(&mut self, timeout: Duration) {
+        self.timeout = timeout;
+    }
+
+    /// Set the username and password used for HTTP basic authentication.
+    pub fn set_basic_auth(&mut self, basic_auth: Option<(String, String)>) {
+        self.basic_auth = basic_auth;
+    }
+
+    /// Set whether the connection is kept open between requests.
+    pub fn set_keep_alive(&mut self, keep_alive: bool) {
+        self.keep_alive = keep_alive;
+        if !keep_alive {
+            self.stream = None;
+        }
+    }
+
+    /// Set the maximum size of a response body. Larger responses fail with
+    /// an error. Defaults to 1 MiB.
+    pub fn set_max_body_size(&mut self, max_body_size: usize) {
+        self.max_body_size = max_body_size;
+    }
+
+    /// Get the value of the `Host` header sent with every request.
+    pub(crate) fn host(&self) -> String {
+        let host = match self.addr {
+            SocketAddr::V4(addr) => addr.ip().to_string(),
+            SocketAddr::V6(addr) => format!("[{}]", addr.ip()),
+        };
+        if self.addr.port() == 80 {
+            host
+        } else {
+            format!("{}:{}", host, self.addr.port())
+        }
+    }
+
+    /// Perform a `GET` request.
+    pub async fn get(&mut self, path: &str) -> Result<HttpResponse> {
+        self.request("GET", path, &[]).await
+    }
+
+    /// Perform a request using the given method, path and body.
+    pub async fn request(&mut self, method: &str, path: &str, body: &[u8]) -> Result<HttpResponse> {
+        self.request_with_headers(method, path, &[], body).await
+    }
+
+    /// Perform a request using the given method, path, additional headers
+    /// and body.
+    pub async fn request_with_headers(
+        &mut self,
+        method: &str,
+        path: &str,
+        headers: &[(String, String)],
+        body: &[u8],
+    ) -> Result<HttpResponse> {
+        let result = async_std::future::timeout(
+            self.timeout,
+            self.request_internal(method, path, headers, body),
+        )
+        .await
+        .map_err(|err| err.into())
+        .and_then(|result| result);
+
+        if result.is_err() {
+            self.stream = None;
+        }
+
+        result
+    }
+
+    async fn request_internal(
+        &mut self,
+        method: &str,
+        path: &str,
+        headers: &[(String, String)],
+        body: &[u8],
+    ) -> Result<HttpResponse> {
+        let request = self.build_request(method, path, headers, body);
+
+        if let Some(stream) = self.stream.take() {
+            // the server may have closed the idle connection in the meantime
+            if let Some(response) = self.exchange(stream, &request).await? {
+                return Ok(response);
+            }
+        }
+
+        let stream = TcpStream::connect(self.addr).await?;
+        match self.exchange(stream, &request).await? {
+            Some(response) => Ok(response),
+            None => Err("EOF before HTTP header".into()),
+        }
+    }
+
+    fn build_request(
+        &self,
+        method: &str,
+        path: &str,
+        headers: &[(String, String)],
+        body: &[u8],
+    ) -> Vec<u8> {
+        let host = self.host();
+
+        let mut request =
+            format!(
+            "{} {} HTTP/1.1\r\nHost: {}\r\nUser-Agent: async-resol-vbus.rs\r\nConnection: {}\r\n",
+            method,
+            path,
+            host,
+            if self.keep_alive { "keep-alive" } else { "close" }
+        );
+        if let Some((ref username, ref password)) = self.basic_auth {
+            let credentials = format!("{}:{}", username, password);
+            request.push_str("Authorization: Basic ");
+            request.push_str(&base64_encode(credentials.as_bytes()));
+            request.push_str("\r\n");
+        }
+        for (name, value) in headers {
+            request.push_str(&format!("{}: {}\r\n", name, value));
+        }
+        if !body.is_empty() || method == "POST" || method == "PUT" {
+            request.push_str(&format!("Content-Length: {}\r\n", body.len()));
+        }
+        request.push_str("\r\n");
+
+        let mut request = request.into_bytes();
+        request.extend_from_slice(body);
+        request
+    }
+
+    /// Send the request and receive the response.
+    ///
+    /// Returns `None` if the connection was closed before any response
+    /// byte was received.
+    async fn exchange(
+        &mut self,
+        mut stream: TcpStream,
+        request: &[u8],
+    ) -> Result<Option<HttpResponse>> {
+        let write_result = async {
+            stream.write_all(request).await?;
+            stream.flush().await
+        }
+        .await;
+        if let Err(err) = write_result {
+            return match err.kind() {
+                std::io::ErrorKind::BrokenPipe | std::io::ErrorKind::ConnectionReset => Ok(None),
+                _ => Err(err.into()),
+            };
+        }
+
+        let mut buf = Vec::with_capacity(1024);
+        let body_idx = loop {
+            if let Some(idx) = DeviceInformation::find_http_body_idx(&buf) {
+                break idx;
+            }
+            if buf.len() > MAX_HEADER_SIZE {
+                return Err("HTTP header too large".into());
+            }
+
+            let mut chunk = [0u8; 1024];
+            match stream.read(&mut chunk).await {
+                Ok(0) if buf.is_empty() => return Ok(None),
+                Ok(0) => return Err("EOF before HTTP header".into()),
+                Ok(len) => buf.extend_from_slice(&chunk[0..len]),
+                Err(err) if buf.is_empty() && err.kind() == std::io::ErrorKind::ConnectionReset => {
+                    return Ok(None)
+                }
+                Err(err) => return Err(err.into()),
+            }
+        };
+
+        let header = std::str::from_utf8(&buf[0..body_idx])?;
+        let mut lines = header.lines();
+
+        let status_line = lines.next().unwrap_or("");
+        let status = status_line
+            .split_whitespace()
+            .nth(1)
+            .and_then(|status| status.parse::<u16>().ok())
+            .ok_or("Invalid HTTP status line")?;
+        let is_http10 = status_line.starts_with("HTTP/1.0");
+
+        let headers = lines
+            .filter_map(|line| line.split_once(':'))
+            .map(|(key, value)| (key.trim().to_string(), value.trim().to_string()))
+            .collect::<Vec<_>>();
+
+        let mut response = HttpResponse {
+            status,
+            headers,
+            body: Vec::new(),
+        };
+
+        let content_length = response
+            .header("Content-Length")
+            .and_then(|value| value.parse::<usize>().ok());
+        let is_chunked = response
+            .header("Transfer-Encoding")
+            .map(|value| value.eq_ignore_ascii_case("chunked"))
+            .unwrap_or(false);
+        let is_closing = is_http10
+            || response
+                .header("Connection")
+                .map(|value| value.eq_ignore_ascii_case("close"))
+                .unwrap_or(false);
+
+        let max_body_size = self.max_body_size;
+        let mut rest = buf.split_off(body_idx);
+        let can_reuse = if is_chunked {
+            response.body = read_chunked_body(&mut stream, rest, max_body_size).await?;
+            true
+        } else if let Some(content_length) = content_length {
+            if content_length > max_body_size {
+                return Err(body_too_large());
+            }
+            while rest.len() < content_length {
+                if !read_chunk(&mut stream, &mut rest).await? {
+                    return Err("EOF before end of HTTP body".into());
+                }
+            }
+            rest.truncate(content_length);
+            response.body = rest;
+            true
+        } else {
+            while rest.len() <= max_body_size && read_chunk(&mut stream, &mut rest).await? {}
+            if rest.len() > max_body_size {
+                return Err(body_too_large());
+            }
+            response.body = rest;
+            false
+        };
+
+        if self.keep_alive && can_reuse && !is_closing {
+            self.stream = Some(stream);
+        }
+
+        Ok(Some(response))
+    }
+}
+
+fn body_too_large() -> crate::error::Error {
+    "HTTP body too large".into()
+}
+
+async fn read_chunk(stream: &mut TcpStream, buf: &mut Vec<u8>) -> Result<bool> {
+    let mut chunk = [0u8; 1024];
+    let len = stream.read(&mut chunk).await?;
+    buf.extend_from_slice(&chunk[0..len]);
+    Ok(len > 0)
+}
+
+async fn read_chunked_body(
+    stream: &mut TcpStream,
+    mut buf: Vec<u8>,
+    max_body_size: usize,
+) -> Result<Vec<u8>> {
+    let mut body = Vec::new();
+    loop {
+        let line_end = loop {
+            if let Some(idx) = buf.windows(2).position(|w| w == b"\r\n") {
+                break idx;
+            }
+            if !read_chunk(stream, &mut buf).await? {
+                return Err("EOF in chunked HTTP body".into());
+            }
+        };
+
+        let size_line = std::str::from_utf8(&buf[0..line_end])?;
+        let size_str = size_line.split(';').next().unwrap_or("").trim();
+        let size =
+            usize::from_str_radix(size_str, 16).map_err(|_| "Invalid chunk size in HTTP body")?;
+        if size > max_body_size - body.len() {
+            return Err(body_too_large());
+        }
+
+        while buf.len() < line_end + 2 + size + 2 {
+            if !read_chunk(stream, &mut buf).await? {
+                return Err("EOF in chunked HTTP body".into());
+            }
+        }
+
+        body.extend_from_slice(&buf[line_end + 2..line_end + 2 + size]);
+        buf.drain(0..line_end + 2 + size + 2);
+
+        if size == 0 {
+            break;
+        }
+    }
+
+    Ok(body)
+}
+
+#[cfg(test)]
+mod tests {
+    use async_std::net::TcpListener;
+
+    use super::*;
+
+    #[test]
+    fn test_base64_encode() {
+        assert_eq!("", base64_encode(b""));
+        assert_eq!("Zg==", base64_encode(b"f"));
+        assert_eq!("Zm8=", base64_encode(b"fo"));
+        assert_eq!("Zm9v", base64_encode(b"foo"));
+        assert_eq!("YWRtaW46YWRtaW4=", base64_encode(b"admin:admin"));
+    }
+
+    #[test]
+    fn test_keep_alive_and_chunked() -> Result<()> {
+        async_std::task::block_on(async {
+            let listener = TcpListener::bind("127.0.0.1:0").await?;
+            let addr = listener.local_addr()?;
+
+            let server_future = async_std::task::spawn::<_, Result<Vec<String>>>(async move {
+                let (mut stream, _) = listener.accept().await?;
+
+                let mut requests = Vec::new();
+                let mut buf = Vec::new();
+                let responses: [&[u8]; 2] = [
+                    b"HTTP/1.1 200 OK\r\nContent-Length: 5\r\n\r\nHello",
+                    b"HTTP/1.1 404 Not Found\r\nTransfer-Encoding: chunked\r\n\r\n3\r\nfoo\r\n4;x=y\r\nbar!\r\n0\r\n\r\n",
+                ];
+                for response in responses.iter() {
+                    let idx = loop {
+                        if let Some(idx) = DeviceInformation::find_http_body_idx(&buf) {
+                            break idx;
+                        }
+                        read_chunk(&mut stream, &mut buf).await?;
+                    };
+                    requests.push(String::from_utf8_lossy(&buf[0..idx]).to_string());
+                    buf.drain(0..idx);
+
+                    stream.write_all(response).await?;
+                }
+
+                Ok(requests)
+            });
+
+            let mut client = HttpClient::new(addr);
+            client.set_keep_alive(true);
+            client.set_basic_auth(Some(("admin".into(), "admin".into())));
+
+            let response = client.get("/first").await?;
+            assert_eq!(200, response.status);
+            assert_eq!(Some("5"), response.header("content-length"));
+            assert_eq!("Hello", response.body_str()?);
+
+            let response = client.get("/second").await?;
+            assert_eq!(404, response.status);
+            assert_eq!("foobar!", response.body_str()?);
+
+            let requests = server_future.await?;
+            assert_eq!(2, requests.len());
+            assert!(requests[0].starts_with("GET /first HTTP/1.1\r\n"));
+            assert!(requests[0].contains("Authorization: Basic YWRtaW46YWRtaW4=\r\n"));
+            assert!(requests[0].contains("Connection: keep-alive\r\n"));
+            assert!(requests[1].starts_with("GET /second HTTP/1.1\r\n"));
+
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn test_host() {
+        let client = HttpClient::new("192.168.5.217:80".parse().unwrap());
+        assert_eq!("192.168.5.217", client.host());
+
+        let client = HttpClient::new("[fe80::1]:8080".parse().unwrap());
+        assert_eq!("[fe80::1]:8080", client.host());
+
+        let client = HttpClient::new("[::1]:80".parse().unwrap());
+        assert_eq!("[::1]", client.host());
+    }
+
+    #[test]
+    fn test_stale_keep_alive() -> Result<()> {
+        async_std::task::block_on(async {
+            let listener = TcpListener::bind("127.0.0.1:0").await?;
+            let addr = listener.local_addr()?;
+
+            let server_future = async_std::task::spawn::<_, Result<()>>(async move {
+                for body in ["first", "second"] {
+                    let (mut stream, _) = listener.accept().await?;
+
+                    let mut buf = Vec::new();
+                    while DeviceInformation::find_http_body_idx(&buf).is_none() {
+                        read_chunk(&mut stream, &mut buf).await?;
+                    }
+
+                    let response = format!(
+                        "HTTP/1.1 200 OK\r\nContent-Length: {}\r\n\r\n{}",
+                        body.len(),
+                        body
+                    );
+                    stream.write_all(response.as_bytes()).await?;
+
+                    // close the kept-alive connection
+                }
+                Ok(())
+            });
+
+            let mut client = HttpClient::new(addr);
+            client.set_keep_alive(true);
+
+            assert_eq!("first", client.get("/").await?.body_str()?);
+
+            async_std::task::sleep(Duration::from_millis(50)).await;
+
+            assert_eq!("second", client.get("/").await?.body_str()?);
+
+            server_future.await?;
+
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn test_max_body_size() -> Result<()> {
+        async_std::task::block_on(async {
+            let listener = TcpListener::bind("127.0.0.1:0").await?;
+            let addr = listener.local_addr()?;
+
+            let server_future = async_std::task::spawn::<_, Result<()>>(async move {
+                let responses: [&[u8]; 3] = [
+                    b"HTTP/1.1 200 OK\r\nContent-Length: 11\r\n\r\nHello World",
+                    b"HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\n6\r\nHello \r\n5\r\nWorld\r\n0\r\n\r\n",
+                    b"HTTP/1.1 200 OK\r\nConnection: close\r\n\r\nHello World",
+                ];
+                for response in responses.iter() {
+                    let (mut stream, _) = listener.accept().await?;
+
+                    let mut buf = Vec::new();
+                    while DeviceInformation::find_http_body_idx(&buf).is_none() {
+                        read_chunk(&mut stream, &mut buf).await?;
+                    }
+
+                    stream.write_all(response).await?;
+                }
+                Ok(())
+            });
+
+            let mut client = HttpClient::new(addr);
+            client.set_max_body_size(10);
+
+            for _ in 0..3 {
+                assert_eq!(
+                    Err("HTTP body too large".into()),
+                    client.get("/").await.map(|_| ())
+                );
+            }
+
+            server_future.await?;
+
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn test_timeout() -> Result<()> {
+        async_std::task::block_on(async {
+            let listener = TcpListener::bind("127.0.0.1:0").await?;
+            let addr = listener.local_addr()?;
+
+            let mut client = HttpClient::new(addr);
+            client.set_timeout(Duration::from_millis(50));
+
+            assert!(client.get("/").await.is_err());
+
+            drop(listener);
+
+            Ok(())
+        })
+    }
+}
@@ -0,0 +1,259 @@
+use crate::error::Result;
+
+/// Append `s` to `out` as a quoted and escaped JSON string.
+pub(crate) fn push_json_string(out: &mut String, s: &str) {
+    out.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if (c as u32) < 0x20 => out.push_str(&format!("\\u{:04x}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+}
+
+/// Append `value` to `out` as a JSON number or `null` if it is not finite.
+#[cfg(all(feature = "specification", feature = "recording"))]
+pub(crate) fn push_json_number(out: &mut String, value: Option<f64>) {
+    match value {
+        Some(value) if value.is_finite() => out.push_str(&format!("{}", value)),
+        _ => out.push_str("null"),
+    }
+}
+
+/// A parsed JSON value, see `parse_json`.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum JsonValue {
+    Null,
+    Bool(bool),
+    Number(f64),
+    String(String),
+    Array(Vec<JsonValue>),
+    Object(Vec<(String, JsonValue)>),
+}
+
+impl JsonValue {
+    /// Get the value of the member `key` if `self` is an object.
+    pub(crate) fn get(&self, key: &str) -> Option<&JsonValue> {
+        match self {
+            JsonValue::Object(members) => members.iter().find(|(k, _)| k == key).map(|(_, v)| v),
+            _ => None,
+        }
+    }
+
+    /// Get the string if `self` is a string.
+    pub(crate) fn as_str(&self) -> Option<&str> {
+        match self {
+            JsonValue::String(s) => Some(s),
+            _ => None,
+        }
+    }
+
+    /// Get the number if `self` is a number.
+    pub(crate) fn as_f64(&self) -> Option<f64> {
+        match self {
+            JsonValue::Number(n) => Some(*n),
+            _ => None,
+        }
+    }
+
+    /// Get the items if `self` is an array.
+    pub(crate) fn as_array(&self) -> Option<&[JsonValue]> {
+        match self {
+            JsonValue::Array(items) => Some(items),
+            _ => None,
+        }
+    }
+}
+
+struct JsonParser<'a> {
+    chars: std::iter::Peekable<std::str::Chars<'a>>,
+}
+
+impl<'a> JsonParser<'a> {
+    fn skip_whitespace(&mut self) {
+        while let Some(c) = self.chars.peek() {
+            if c.is_whitespace() {
+                self.chars.next();
+            } else {
+                break;
+            }
+        }
+    }
+
+    fn expect(&mut self, expected: &str) -> Result<()> {
+        for e in expected.chars() {
+            if self.chars.next() != Some(e) {
+                return Err(format!("Expected {:?} in JSON", expected).into());
+            }
+        }
+        Ok(())
+    }
+
+    fn parse_value(&mut self) -> Result<JsonValue> {
+        self.skip_whitespace();
+        let value = match self.chars.peek() {
+            Some('n') => {
+                self.expect("null")?;
+                JsonValue::Null
+            }
+            Some('t') => {
+                self.expect("true")?;
+                JsonValue::Bool(true)
+            }
+            Some('f') => {
+                self.expect("false")?;
+                JsonValue::Bool(false)
+            }
+            Some('"') => JsonValue::String(self.parse_string()?),
+            Some('[') => {
+                self.chars.next();
+                let mut items = Vec::new();
+                self.skip_whitespace();
+                if self.chars.peek() == Some(&']') {
+                    self.chars.next();
+                } else {
+                    loop {
+                        items.push(self.parse_value()?);
+                        self.skip_whitespace();
+                        match self.chars.next() {
+                            Some(',') => {}
+                            Some(']') => break,
+                            _ => return Err("Expected ',' or ']' in JSON array".into()),
+                        }
+                    }
+                }
+                JsonValue::Array(items)
+            }
+            Some('{') => {
+                self.chars.next();
+                let mut members = Vec::new();
+                self.skip_whitespace();
+                if self.chars.peek() == Some(&'}') {
+                    self.chars.next();
+                } else {
+                    loop {
+                        self.skip_whitespace();
+                        let key = self.parse_string()?;
+                        self.skip_whitespace();
+                        self.expect(":")?;
+                        members.push((key, self.parse_value()?));
+                        self.skip_whitespace();
+                        match self.chars.next() {
+                            Some(',') => {}
+                            Some('}') => break,
+                            _ => return Err("Expected ',' or '}' in JSON object".into()),
+                        }
+                    }
+                }
+                JsonValue::Object(members)
+            }
+            Some(_) => {
+                let mut number = String::new();
+                while let Some(&c) = self.chars.peek() {
+                    if c.is_ascii_digit() || "+-.eE".contains(c) {
+                        number.push(c);
+                        self.chars.next();
+                    } else {
+                        break;
+                    }
+                }
+                let number = number.parse().map_err(|_| "Invalid JSON number")?;
+                JsonValue::Number(number)
+            }
+            None => return Err("Unexpected end of JSON".into()),
+        };
+        Ok(value)
+    }
+
+    fn parse_string(&mut self) -> Result<String> {
+        self.expect("\"")?;
+        let mut s = String::new();
+        loop {
+            match self.chars.next() {
+                Some('"') => break,
+                Some('\\') => match self.chars.next() {
+                    Some('n') => s.push('\n'),
+                    Some('r') => s.push('\r'),
+                    Some('t') => s.push('\t'),
+                    Some('b') => s.push('\u{8}'),
+                    Some('f') => s.push('\u{c}'),
+                    Some('u') => {
+                        let hex: String = self.chars.by_ref().take(4).collect();
+                        let code = u32::from_str_radix(&hex, 16)
+                            .map_err(|_| "Invalid JSON unicode escape")?;
+                        s.push(char::from_u32(code).unwrap_or('\u{fffd}'));
+                    }
+                    Some(c) => s.push(c),
+                    None => return Err("Unexpected end of JSON string".into()),
+                },
+                Some(c) => s.push(c),
+                None => return Err("Unexpected end of JSON string".into()),
+            }
+        }
+        Ok(s)
+    }
+}
+
+/// Parse `s` as a single JSON value.
+pub(crate) fn parse_json(s: &str) -> Result<JsonValue> {
+    let mut parser = JsonParser {
+        chars: s.chars().peekable(),
+    };
+    let value = parser.parse_value()?;
+    parser.skip_whitespace();
+    if parser.chars.next().is_some() {
+        return Err("Unexpected trailing characters in JSON".into());
+    }
+    Ok(value)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_push_json_string() {
+        let mut out = String::new();
+        push_json_string(&mut out, "a\"b\\c\nd\u{1}");
+        assert_eq!("\"a\\\"b\\\\c\\nd\\u0001\"", out);
+    }
+
+    #[cfg(all(feature = "specification", feature = "recording"))]
+    #[test]
+    fn test_push_json_number() {
+        let mut out = String::new();
+        push_json_number(&mut out, Some(87.2));
+        out.push(',');
+        push_json_number(&mut out, Some(f64::NAN));
+        out.push(',');
+        push_json_number(&mut out, None);
+        assert_eq!("87.2,null,null", out);
+    }
+
+    #[test]
+    fn test_parse_json() {
+        let value =
+            parse_json(" {\"a\": [1, -2.5e1, true, null], \"b\": \"x\\\"\\u0041\"} ").unwrap();
+
+        assert_eq!(
+            Some(&JsonValue::Array(vec![
+                JsonValue::Number(1.0),
+                JsonValue::Number(-25.0),
+                JsonValue::Bool(true),
+                JsonValue::Null,
+            ])),
+            value.get("a")
+        );
+        assert_eq!(Some("x\"A"), value.get("b").and_then(|v| v.as_str()));
+        assert_eq!(None, value.get("c"));
+
+        assert!(parse_json("{\"a\": 1").is_err());
+        assert!(parse_json("[1] 2").is_err());
+    }
+}
@@ -15,7 +15,20 @@
 //!
 //! ## Planned, but not yet implemented features
 //!
-//! - none (yet)
+//! - Reloading a whole pipeline from a configuration (`reload(config)`).
+//!   This crate has no configuration type to compare, so changes are
+//!   applied per component instead: new filters and sinks are added to a
+//!   running `DataHub` using `subscribe_matching` (and removed by dropping
+//!   their `Receiver`), changed poll targets and intervals are applied
+//!   using `PollScheduler::reload`. `HttpApi`, `ModbusServer` and
+//!   `GatewayBridge` have to be restarted to pick up changed settings, the
+//!   upstream `LiveDataStream` feeding them can be kept open while doing
+//!   so. `SharingServer` owns its upstream connection, restarting it
+//!   reconnects.
+//! - Using the extended data services a device advertises in its
+//!   `features` (e.g. live JSON push channels) instead of the
+//!   VBus-over-TCP service on port 7053. Their protocols are not
+//!   documented, so connections always use port 7053.
 //!
 //!
 //! ## Examples
@@ -30,7 +43,7 @@
 //!
 //! use resol_vbus::{DataSet, RecordingWriter};
 //!
-//! use async_resol_vbus::{Result, LiveDataStream, TcpClientHandshake};
+//! use async_resol_vbus::{connect_live_data_stream, ConnectOptions, Result};
 //!
 //! fn main() -> Result<()> {
 //!     async_std::task::block_on(async {
@@ -43,13 +56,8 @@
 //!
 //!         let stream = TcpStream::connect(addr).await?;
 //!
-//!         let mut hs = TcpClientHandshake::start(stream).await?;
-//!         hs.send_pass_command("vbus").await?;
-//!         let stream = hs.send_data_command().await?;
-//!
-//!         let (reader, writer) = (&stream, &stream);
-//!
-//!         let mut stream = LiveDataStream::new(reader, writer, 0, 0x0020);
+//!         // Perform the handshake and create a `LiveDataStream`
+//!         let mut stream = connect_live_data_stream(stream, &ConnectOptions::new()).await?;
 //!
 //!         while let Some(data) = stream.receive_any_data(60000).await? {
 //!             println!("{}", data.id_string());
@@ -84,10 +92,27 @@ mod error;
 pub use error::Result;
 
 mod device_information;
-pub use device_information::DeviceInformation;
+pub use device_information::{DeviceIdentity, DeviceInformation};
+
+mod http_client;
+pub use http_client::{HttpClient, HttpResponse};
+
+mod device_registry;
+pub use device_registry::{DeviceRegistry, DeviceRegistryEntry};
+
+mod service_probe;
+pub use service_probe::{probe_vbus_service, VbusServiceProbe};
+
+mod network_scanner;
+pub use network_scanner::NetworkScanner;
 
 mod device_discovery;
-pub use device_discovery::DeviceDiscovery;
+pub use device_discovery::{
+    DeviceDiscovery, DeviceDiscoveryBuilder, DiscoveryProgress, DiscoveryReport, DiscoveryRound,
+};
+
+mod handshake_trace;
+pub use handshake_trace::{HandshakeDirection, HandshakeObserver, HandshakeTrace};
 
 mod tcp_client_handshake;
 pub use tcp_client_handshake::TcpClientHandshake;
@@ -96,7 +121,236 @@ mod tcp_server_handshake;
 pub use tcp_server_handshake::TcpServerHandshake;
 
 mod live_data_stream;
-pub use live_data_stream::LiveDataStream;
+pub use live_data_stream::{
+    AppliedParameters, BufferOverflow, BufferOverflowPolicy, LiveDataStream, ParameterOutcome,
+    ReadOnlyWriter, TcpLiveDataStream, ValueIndexLookup, VerifiedWrite,
+};
+
+#[cfg(feature = "frame-reader")]
+mod frame_reader;
+#[cfg(feature = "frame-reader")]
+pub use frame_reader::FrameReader;
+
+mod network_data_set_source;
+pub use network_data_set_source::NetworkDataSetSource;
+
+mod live_data_sender;
+pub use live_data_sender::LiveDataSender;
+
+mod data_stream;
+pub use data_stream::DataStream;
+
+mod data_filter;
+pub use data_filter::DataFilter;
+
+mod data_id;
+pub use data_id::DataId;
+
+mod data_hub;
+pub use data_hub::DataHub;
+
+mod channel_mux;
+pub use channel_mux::{ChannelMux, ChannelMuxWriter};
+
+mod channel_demux;
+pub use channel_demux::ChannelDemux;
+
+mod datagram_responder;
+pub use datagram_responder::{DatagramResponder, ValueHandler};
+
+mod data_builder;
+pub use data_builder::{DatagramBuilder, PacketBuilder};
+
+mod formatted_value;
+pub use formatted_value::FormattedValue;
+
+mod frame_data;
+pub use frame_data::FrameData;
+
+mod transaction_journal;
+pub use transaction_journal::TransactionJournal;
+
+mod transaction_stats;
+pub use transaction_stats::{TransactionStats, TransactionTiming};
+
+mod metrics_hook;
+pub use metrics_hook::MetricsHook;
+
+mod vbus_event;
+pub use vbus_event::{forward_events, ErrorKind, VBusEvent};
+
+mod write_guard;
+pub use write_guard::WriteGuard;
+
+mod write_stall;
+pub use write_stall::WriteStall;
+
+mod device_nak;
+pub use device_nak::{DeviceNak, NakDecoder};
+
+mod shared_live_data_stream;
+pub use shared_live_data_stream::SharedLiveDataStream;
+
+mod packet_injector;
+pub use packet_injector::{PacketInjector, PayloadProvider};
+
+mod poll_scheduler;
+pub use poll_scheduler::{PollResult, PollScheduler, PollTarget};
+
+mod socket_options;
+pub use socket_options::SocketOptions;
+
+mod connect;
+pub use connect::{
+    connect_live_data_stream, connect_split_live_data_stream, connect_tcp_live_data_stream,
+    reconnect_live_data_stream, ConnectOptions, CredentialProvider, HandshakeStep,
+};
+pub use futures_lite::io::{ReadHalf, WriteHalf};
+
+mod device_lease;
+pub use device_lease::DeviceLease;
+
+mod connection_manager;
+pub use connection_manager::{ConnectionManager, ReconnectEvent};
+
+mod serial_options;
+pub use serial_options::{SerialOptions, SerialParity, SerialProfile};
+
+#[cfg(feature = "serial")]
+mod serial_transport;
+#[cfg(feature = "serial")]
+pub use serial_transport::{
+    list_serial_ports, open_serial_port, resolve_serial_port_name, SerialPortCandidate,
+    SerialReader, SerialWriter,
+};
+
+mod connection_spec;
+pub use connection_spec::{
+    quick_connect, quick_connect_with_options, BoxedLiveDataStream, ConnectionSpec,
+};
+
+mod dl2_simulator;
+pub use dl2_simulator::Dl2Simulator;
+
+mod conformance;
+pub use conformance::{ConformanceCheck, ConformanceHarness, ConformanceResult};
+
+mod sharing_server;
+pub use sharing_server::{SharingServer, WriteArbitration};
+
+mod controller_session;
+pub use controller_session::ControllerSession;
+
+mod schedule;
+pub use schedule::ScheduleEntry;
+
+mod relay_control;
+pub use relay_control::{RelayMode, RelayProfile};
+
+mod controller_profile;
+pub use controller_profile::{ControllerProfile, ControllerProfileRegistry, ProfileValue};
+
+mod value_id_hash;
+pub use value_id_hash::value_id_hash_by_id;
+
+mod traffic_map;
+pub use traffic_map::{TrafficEntry, TrafficMap};
+
+mod freshness_tracker;
+pub use freshness_tracker::{FreshnessTracker, PacketFreshness};
+
+mod packet_diff;
+pub use packet_diff::{PacketDiff, PacketWatcher};
+
+#[cfg(all(feature = "specification", feature = "recording"))]
+mod recording_converter;
+#[cfg(all(feature = "specification", feature = "recording"))]
+pub use recording_converter::{ExportFormat, RecordingConverter};
+
+mod shutdown;
+pub use shutdown::{Shutdown, ShutdownSignal};
+
+mod health;
+pub use health::{HealthMonitor, HealthReport};
+
+mod watchdog;
+pub use watchdog::{Heartbeat, RestartReason, Watchdog, WatchdogEvent};
+
+#[cfg(all(feature = "specification", feature = "recording"))]
+mod triggered_recorder;
+#[cfg(all(feature = "specification", feature = "recording"))]
+pub use triggered_recorder::{RecordingTrigger, TriggeredRecorder};
+
+#[cfg(feature = "recording")]
+mod recording_index;
+#[cfg(feature = "recording")]
+pub use recording_index::{IndexedRecording, RecordingIndex, RecordingIndexEntry};
+
+mod retention;
+pub use retention::{RetentionManager, RetentionReport};
+
+#[cfg(feature = "specification")]
+mod home_assistant;
+#[cfg(feature = "specification")]
+pub use home_assistant::{DiscoveryMessage, HomeAssistantDiscovery};
+
+#[cfg(feature = "s3")]
+mod s3_upload;
+#[cfg(feature = "s3")]
+pub use s3_upload::{S3Config, S3UploadSink};
+
+#[cfg(any(feature = "gzip", feature = "zstd"))]
+mod compressed_recording;
+#[cfg(any(feature = "gzip", feature = "zstd"))]
+pub use compressed_recording::{
+    ChunkInfo, CompressedRecordingReader, CompressedRecordingWriter, Compression,
+};
+
+mod json;
+
+#[cfg(feature = "specification")]
+mod spec_cache;
+
+#[cfg(feature = "specification")]
+mod changed_fields;
+
+#[cfg(feature = "serde")]
+mod serializable;
+#[cfg(feature = "serde")]
+pub use serializable::{SerializableData, SerializableDatagram};
+
+mod param_request;
+pub use param_request::ParamRequest;
+
+#[cfg(feature = "otel")]
+mod trace_context;
+#[cfg(feature = "otel")]
+pub use trace_context::TraceContext;
+
+#[cfg(feature = "cli")]
+mod cli;
+#[cfg(feature = "cli")]
+pub use cli::{connection_args, connection_spec_from_matches};
+
+#[cfg(feature = "specification")]
+mod field_gateway;
+#[cfg(feature = "specification")]
+pub use field_gateway::{FieldGateway, GatewayBridge, ParamWriter};
+
+#[cfg(feature = "http-api")]
+mod http_api;
+#[cfg(feature = "http-api")]
+pub use http_api::HttpApi;
+
+#[cfg(feature = "modbus")]
+mod modbus_server;
+#[cfg(feature = "modbus")]
+pub use modbus_server::{ModbusServer, RegisterFormat};
+
+#[cfg(all(target_os = "linux", feature = "systemd"))]
+mod systemd;
+#[cfg(all(target_os = "linux", feature = "systemd"))]
+pub use systemd::{DbusService, SystemdNotifier};
 
 #[cfg(test)]
 mod test_utils;
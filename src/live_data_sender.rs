@@ -0,0 +1,141 @@
+use std::{
+    marker::Unpin,
+    time::{Duration, Instant},
+};
+
+use async_std::{io::Write, prelude::*};
+
+use resol_vbus::{live_data_encoder, Data};
+
+use crate::{error::Result, write_stall::write_all_within};
+
+/// A write-only sender for VBus `Data` items encoded in the live / wire
+/// representation.
+///
+/// It can be used to inject synthetic data (e.g. packets of a virtual
+/// device) into a VBus feed.
+///
+/// # Examples
+///
+/// ```no_run
+/// # fn main() -> async_resol_vbus::Result<()> { async_std::task::block_on(async {
+/// #
+/// use std::time::Duration;
+///
+/// use async_std::net::TcpStream;
+///
+/// use async_resol_vbus::{Data, LiveDataSender, PacketBuilder};
+///
+/// let stream = TcpStream::connect("192.168.5.217:7053").await?;
+/// // ... perform handshake ...
+/// let mut sender = LiveDataSender::new(stream);
+/// sender.set_pacing(Some(Duration::from_millis(100)));
+///
+/// let packet = PacketBuilder::new(0x0010, 0x0100)
+///     .source_address(0x7E50)
+///     .payload(&[0; 8])
+///     .build()?;
+/// sender.send(&Data::Packet(packet)).await?;
+/// #
+/// # Ok(()) }) }
+/// ```
+#[derive(Debug)]
+pub struct LiveDataSender<W: Write + Unpin> {
+    writer: W,
+    pacing: Option<Duration>,
+    last_tx: Option<Instant>,
+    write_timeout: Option<Duration>,
+}
+
+impl<W: Write + Unpin> LiveDataSender<W> {
+    /// Create a new `LiveDataSender`.
+    pub fn new(writer: W) -> LiveDataSender<W> {
+        LiveDataSender {
+            writer,
+            pacing: None,
+            last_tx: None,
+            write_timeout: None,
+        }
+    }
+
+    /// Set the minimum interval between two sent `Data` items.
+    ///
+    /// If set, `send` waits until `pacing` has elapsed since the previous
+    /// item was sent. Defaults to `None`, which sends items immediately.
+    pub fn set_pacing(&mut self, pacing: Option<Duration>) {
+        self.pacing = pacing;
+    }
+
+    /// Set the maximum duration for writing a single `Data` item.
+    ///
+    /// If exceeded, `send` fails with an error providing a `WriteStall`.
+    /// Defaults to `None`, which waits indefinitely.
+    pub fn set_write_timeout(&mut self, write_timeout: Option<Duration>) {
+        self.write_timeout = write_timeout;
+    }
+
+    /// Encode and send `data`.
+    pub async fn send(&mut self, data: &Data) -> Result<()> {
+        if let (Some(pacing), Some(last_tx)) = (self.pacing, self.last_tx) {
+            let elapsed = last_tx.elapsed();
+            if elapsed < pacing {
+                async_std::task::sleep(pacing - elapsed).await;
+            }
+        }
+
+        let len = live_data_encoder::length_from_data(data);
+        let mut bytes = vec![0u8; len];
+        live_data_encoder::bytes_from_data(data, &mut bytes);
+
+        write_all_within(&mut self.writer, &bytes, self.write_timeout).await?;
+        self.writer.flush().await?;
+        self.last_tx = Some(Instant::now());
+
+        Ok(())
+    }
+
+    /// Get a reference to the underlying writer.
+    pub fn get_ref(&self) -> &W {
+        &self.writer
+    }
+
+    /// Consume `self` and return the underlying writer.
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use async_std::io::Cursor;
+
+    use super::*;
+
+    use crate::{
+        test_utils::{hex_encode, simulate_run},
+        PacketBuilder,
+    };
+
+    #[test]
+    fn test_send() {
+        let data = Data::Packet(
+            PacketBuilder::new(0x0010, 0x0100)
+                .source_address(0x7E11)
+                .build()
+                .unwrap(),
+        );
+
+        let mut sender = LiveDataSender::new(Cursor::new(Vec::new()));
+        sender.set_pacing(Some(Duration::from_millis(50)));
+
+        let start = Instant::now();
+        simulate_run(sender.send(&data)).unwrap();
+        simulate_run(sender.send(&data)).unwrap();
+
+        assert!(start.elapsed() >= Duration::from_millis(50));
+        assert_eq!(
+            "aa1000117e100001004faa1000117e100001004f",
+            hex_encode(sender.get_ref())
+        );
+    }
+}
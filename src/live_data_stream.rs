@@ -1,13 +1,48 @@
-use std::{marker::Unpin, time::Duration};
+use std::{
+    cell::RefCell,
+    collections::{BTreeSet, HashMap},
+    marker::Unpin,
+    pin::Pin,
+    sync::Arc,
+    task::{Context, Poll, Waker},
+    time::{Duration, Instant},
+};
 
 use async_std::{
+    channel::Sender,
+    future::Future,
     io::{Read, Write},
+    net::TcpStream,
     prelude::*,
 };
 
-use resol_vbus::{chrono::Utc, live_data_encoder, Data, Datagram, Header, LiveDataBuffer};
+use resol_vbus::{
+    chrono::{DateTime, Utc},
+    live_data_encoder, Data, Datagram, Header, LiveDataBuffer, Packet, Telegram, ToPacketId,
+};
+
+use crate::{
+    controller_session::ControllerSession,
+    data_filter::DataFilter,
+    data_id::DataId,
+    data_stream::DataStream,
+    datagram_responder::DatagramResponder,
+    device_nak::NakDecoder,
+    error::{Error, Result},
+    metrics_hook::MetricsHook,
+    transaction_journal::{JournalEntry, TransactionJournal},
+    transaction_stats::{TransactionStats, TransactionTiming},
+    vbus_event::{emit_event, ErrorKind, VBusEvent},
+    write_guard::{is_transaction_command, is_write_command, write_denied_error, WriteGuard},
+    write_stall::write_before,
+};
 
-use crate::error::Result;
+pub(crate) fn bytes_from_data(data: &Data) -> Vec<u8> {
+    let len = live_data_encoder::length_from_data(data);
+    let mut bytes = vec![0u8; len];
+    live_data_encoder::bytes_from_data(data, &mut bytes);
+    bytes
+}
 
 fn try_as_datagram(data: &Data) -> Option<&Datagram> {
     if data.is_datagram() {
@@ -17,6 +52,131 @@ fn try_as_datagram(data: &Data) -> Option<&Datagram> {
     }
 }
 
+/// What a `LiveDataStream` does if its receive buffer exceeds the maximum
+/// size set using `set_max_buffer_size`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum BufferOverflowPolicy {
+    /// Drop the buffered bytes and continue with the newly received ones.
+    DropOldest,
+
+    /// Drop the buffered bytes and fail with an error.
+    Error,
+}
+
+/// Sent by a `LiveDataStream` when its receive buffer exceeded the maximum
+/// size.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BufferOverflow {
+    /// The time the overflow occurred.
+    pub timestamp: DateTime<Utc>,
+
+    /// The number of bytes that would have been buffered.
+    pub buffered_len: usize,
+
+    /// The policy that was applied.
+    pub policy: BufferOverflowPolicy,
+}
+
+/// The outcome of `LiveDataStream::set_value_by_index_verified`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum VerifiedWrite {
+    /// The value read back matches the requested value.
+    Verified {
+        /// The value read back from the device.
+        value: i32,
+    },
+
+    /// The device stored a different value, e.g. because it clamped the
+    /// requested value to its allowed range.
+    Mismatch {
+        /// The value that was requested to be written.
+        requested: i32,
+        /// The value read back from the device.
+        actual: i32,
+    },
+
+    /// The device did not reply to the write or the read-back.
+    NoReply,
+}
+
+/// The result of `LiveDataStream::get_value_index_by_id_hash`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ValueIndexLookup {
+    /// The device knows the ID hash.
+    Found {
+        /// The index of the value.
+        index: i16,
+    },
+
+    /// The device knows the ID hash, but answered with a regular value
+    /// reply (`0x0100`) instead of `0x1101`. Such devices expect any value
+    /// to be read (e.g. index 0) before the next request to get back in
+    /// sync.
+    ResyncRequired {
+        /// The index of the value.
+        index: i16,
+    },
+
+    /// The device does not know the ID hash.
+    Unknown,
+
+    /// The device did not reply.
+    NoReply,
+}
+
+impl ValueIndexLookup {
+    /// Get the index of the value, if the device knows the ID hash.
+    pub fn index(&self) -> Option<i16> {
+        match *self {
+            ValueIndexLookup::Found { index } | ValueIndexLookup::ResyncRequired { index } => {
+                Some(index)
+            }
+            ValueIndexLookup::Unknown | ValueIndexLookup::NoReply => None,
+        }
+    }
+}
+
+/// The outcome of writing a single value using
+/// `LiveDataStream::apply_parameters`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ParameterOutcome {
+    /// The device acknowledged the value.
+    Written(i32),
+
+    /// The device did not acknowledge the value.
+    NoReply,
+
+    /// The value was not written because a previous value failed.
+    Skipped,
+}
+
+/// The result of `LiveDataStream::apply_parameters`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AppliedParameters {
+    /// Whether the bulk value transaction was committed.
+    pub committed: bool,
+
+    /// The `(index, outcome)` pairs in the order the values were provided.
+    pub outcomes: Vec<(i16, ParameterOutcome)>,
+}
+
+/// The settings of a `LiveDataStream` that are independent of its
+/// connection, carried over by `reconnect_live_data_stream`.
+#[derive(Debug)]
+pub(crate) struct LiveDataStreamSettings {
+    max_buffer_size: Option<usize>,
+    buffer_overflow_policy: BufferOverflowPolicy,
+    buffer_overflow_sender: Option<Sender<BufferOverflow>>,
+    keep_alive_interval: Option<Duration>,
+    write_guard: Option<WriteGuard>,
+    journal: Option<TransactionJournal>,
+    responder: Option<DatagramResponder>,
+    metrics_hook: Option<Arc<dyn MetricsHook>>,
+    nak_decoder: Option<Arc<dyn NakDecoder>>,
+    event_sender: Option<Sender<VBusEvent>>,
+    write_timeout: Option<Duration>,
+}
+
 /// A `Stream`/`Sink` wrapper for RESOL VBus `Data` items encoded in the
 /// live / wire representation.
 ///
@@ -29,6 +189,28 @@ pub struct LiveDataStream<R: Read + Unpin, W: Write + Unpin> {
     channel: u8,
     self_address: u16,
     buf: LiveDataBuffer,
+    buf_len: usize,
+    max_buffer_size: Option<usize>,
+    buffer_overflow_policy: BufferOverflowPolicy,
+    buffer_overflow_sender: Option<Sender<BufferOverflow>>,
+    keep_alive_interval: Option<Duration>,
+    bus_owner: Option<u16>,
+    pending_keep_alive: Option<u16>,
+    last_tx: Instant,
+    pending_tx: Vec<u8>,
+    write_guard: Option<WriteGuard>,
+    value_id_hashes: HashMap<(u16, i16), i32>,
+    declared_protocol_versions: HashMap<u16, u8>,
+    detected_protocol_versions: HashMap<u16, u8>,
+    journal: Option<TransactionJournal>,
+    last_tries: usize,
+    last_transaction_timing: Option<TransactionTiming>,
+    transaction_stats: TransactionStats,
+    responder: Option<DatagramResponder>,
+    metrics_hook: Option<Arc<dyn MetricsHook>>,
+    nak_decoder: Option<Arc<dyn NakDecoder>>,
+    event_sender: Option<Sender<VBusEvent>>,
+    write_timeout: Option<Duration>,
 }
 
 impl<R: Read + Unpin, W: Write + Unpin> LiveDataStream<R, W> {
@@ -40,14 +222,518 @@ impl<R: Read + Unpin, W: Write + Unpin> LiveDataStream<R, W> {
             channel,
             self_address,
             buf: LiveDataBuffer::new(channel),
+            buf_len: 0,
+            max_buffer_size: None,
+            buffer_overflow_policy: BufferOverflowPolicy::DropOldest,
+            buffer_overflow_sender: None,
+            keep_alive_interval: None,
+            bus_owner: None,
+            pending_keep_alive: None,
+            last_tx: Instant::now(),
+            pending_tx: Vec::new(),
+            write_guard: None,
+            value_id_hashes: HashMap::new(),
+            declared_protocol_versions: HashMap::new(),
+            detected_protocol_versions: HashMap::new(),
+            journal: None,
+            last_tries: 0,
+            last_transaction_timing: None,
+            transaction_stats: TransactionStats::default(),
+            responder: None,
+            metrics_hook: None,
+            nak_decoder: None,
+            event_sender: None,
+            write_timeout: None,
+        }
+    }
+
+    /// Set the `MetricsHook` notified about transactions, disconnects and
+    /// errors.
+    pub fn set_metrics_hook(&mut self, metrics_hook: Option<Arc<dyn MetricsHook>>) {
+        self.metrics_hook = metrics_hook;
+    }
+
+    /// Set the `NakDecoder` recognizing the negative replies of the
+    /// addressed devices.
+    ///
+    /// Without it (the default) transactions ignore negative replies and
+    /// end without a reply once their tries are used up.
+    pub fn set_nak_decoder(&mut self, nak_decoder: Option<Arc<dyn NakDecoder>>) {
+        self.nak_decoder = nak_decoder;
+    }
+
+    /// Set a channel receiving a `VBusEvent` for every received `Data`,
+    /// bus offer, verified write, closed connection and error.
+    ///
+    /// Events are dropped if the channel is full or closed.
+    pub fn set_event_sender(&mut self, sender: Option<Sender<VBusEvent>>) {
+        self.event_sender = sender;
+    }
+
+    /// Set the maximum duration for writing a `Data` or a pending answer.
+    ///
+    /// If a write does not complete in time (e.g. because the peer stopped
+    /// reading), the operation fails with an error providing a
+    /// `WriteStall`. The bytes not written yet are dropped, so the
+    /// connection should be closed and re-established afterwards.
+    ///
+    /// Defaults to `None`, which waits indefinitely.
+    pub fn set_write_timeout(&mut self, write_timeout: Option<Duration>) {
+        self.write_timeout = write_timeout;
+    }
+
+    /// Set the `WriteGuard` restricting the value indices that can be
+    /// written.
+    ///
+    /// The guard is checked for every datagram sent using `transceive` or
+    /// `send_data` (and therefore also `set_value_by_index`,
+    /// `set_bulk_value_by_index` and `DatagramBuilder::send`) whose command
+    /// writes a value (`0x02xx` and `0x15xx`), begins (`0x1400`) or commits
+    /// (`0x1402`) a bulk value transaction. Writing a value that is not
+    /// allowed fails with an error without sending anything to the device.
+    ///
+    /// If the guard has rules for value IDs, the ID hash of the index is
+    /// requested from the device before the first write to that index.
+    pub fn set_write_guard(&mut self, write_guard: Option<WriteGuard>) {
+        self.write_guard = write_guard;
+    }
+
+    /// Set the `TransactionJournal` recording every value get / set.
+    pub fn set_journal(&mut self, journal: Option<TransactionJournal>) {
+        self.journal = journal;
+    }
+
+    /// Get the timing of the last transaction that sent data and waited for
+    /// a reply (e.g. `transceive` or `get_value_by_index`).
+    pub fn last_transaction_timing(&self) -> Option<TransactionTiming> {
+        self.last_transaction_timing
+    }
+
+    /// Get the accumulated timings of all transactions since the stream was
+    /// created or `reset_transaction_stats` was called.
+    pub fn transaction_stats(&self) -> TransactionStats {
+        self.transaction_stats
+    }
+
+    /// Reset the accumulated transaction timings.
+    pub fn reset_transaction_stats(&mut self) {
+        self.transaction_stats = TransactionStats::default();
+    }
+
+    /// Set the maximum number of received bytes that are buffered while
+    /// waiting for a complete `Data` item.
+    ///
+    /// Defaults to `None`, which does not limit the buffer. See
+    /// `set_buffer_overflow_policy` for what happens if the limit is hit.
+    pub fn set_max_buffer_size(&mut self, max_buffer_size: Option<usize>) {
+        self.max_buffer_size = max_buffer_size;
+    }
+
+    /// Set what happens if the receive buffer exceeds its maximum size.
+    ///
+    /// Defaults to `BufferOverflowPolicy::DropOldest`.
+    pub fn set_buffer_overflow_policy(&mut self, policy: BufferOverflowPolicy) {
+        self.buffer_overflow_policy = policy;
+    }
+
+    /// Set a channel receiving a `BufferOverflow` every time the receive
+    /// buffer exceeds its maximum size.
+    ///
+    /// Events are dropped if the channel is full or closed.
+    pub fn set_buffer_overflow_sender(&mut self, sender: Option<Sender<BufferOverflow>>) {
+        self.buffer_overflow_sender = sender;
+    }
+
+    /// Feed received bytes into the receive buffer without reading them
+    /// from the underlying reader.
+    ///
+    /// Together with `read_buffered_data` this exposes the decoding path
+    /// synchronously, e.g. for fuzzing or property testing. Bytes that do
+    /// not belong to a valid frame are skipped, so that decoding
+    /// resynchronizes on the next valid frame. The maximum buffer size set
+    /// using `set_max_buffer_size` applies.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # fn main() -> async_resol_vbus::Result<()> {
+    /// use async_resol_vbus::LiveDataStream;
+    ///
+    /// let mut lds = LiveDataStream::new(async_std::io::empty(), async_std::io::sink(), 0, 0x0020);
+    ///
+    /// lds.feed_bytes(&[0xAA; 4096])?;
+    /// assert!(lds.read_buffered_data().is_none());
+    ///
+    /// lds.feed_bytes(&[
+    ///     0xAA, 0x10, 0x00, 0x11, 0x7E, 0x10, 0x00, 0x01, 0x00, 0x4F,
+    /// ])?;
+    /// assert_eq!("00_0010_7E11_10_0100", lds.read_buffered_data().unwrap().id_string());
+    /// # Ok(()) }
+    /// ```
+    pub fn feed_bytes(&mut self, bytes: &[u8]) -> Result<()> {
+        self.extend_buffer(bytes)?;
+        Ok(())
+    }
+
+    /// Decode the next `Data` from the receive buffer without reading from
+    /// the underlying reader.
+    ///
+    /// The `Data` is processed like one received using `receive`, but
+    /// answers queued by the `DatagramResponder` are only sent by the next
+    /// I/O operation.
+    pub fn read_buffered_data(&mut self) -> Option<Data> {
+        let data = self.buf.read_data()?;
+        self.emit_data_events(&data);
+        self.detect_protocol_version(&data);
+        self.queue_answer(&data);
+        Some(data)
+    }
+
+    pub(crate) fn extend_buffer(&mut self, bytes: &[u8]) -> std::io::Result<()> {
+        let max_buffer_size = match self.max_buffer_size {
+            Some(max_buffer_size) => max_buffer_size,
+            None => {
+                self.buf.extend_from_slice(bytes);
+                self.buf_len += bytes.len();
+                return Ok(());
+            }
+        };
+
+        let buffered_len = self.buf_len.saturating_sub(self.buf.offset()) + bytes.len();
+        let bytes = if buffered_len > max_buffer_size {
+            let policy = self.buffer_overflow_policy;
+
+            let overflow = BufferOverflow {
+                timestamp: Utc::now(),
+                buffered_len,
+                policy,
+            };
+            if let Some(ref sender) = self.buffer_overflow_sender {
+                drop(sender.try_send(overflow.clone()));
+            }
+            emit_event(&self.event_sender, overflow.into());
+
+            self.buf = LiveDataBuffer::new(self.channel);
+            self.buf_len = 0;
+
+            if policy == BufferOverflowPolicy::Error {
+                return Err(std::io::Error::other(format!(
+                    "Receive buffer exceeded the maximum size of {} bytes",
+                    max_buffer_size
+                )));
+            }
+
+            &bytes[bytes.len().saturating_sub(max_buffer_size)..]
+        } else {
+            bytes
+        };
+
+        self.buf.extend_from_slice(bytes);
+        self.buf_len += bytes.len();
+        Ok(())
+    }
+
+    /// Set the `DatagramResponder` answering value requests addressed to
+    /// this stream's own address.
+    pub fn set_responder(&mut self, responder: Option<DatagramResponder>) {
+        self.responder = responder;
+    }
+
+    /// Get a mutable reference to the `DatagramResponder`, if any.
+    pub fn responder_mut(&mut self) -> Option<&mut DatagramResponder> {
+        self.responder.as_mut()
+    }
+
+    fn queue_answer(&mut self, data: &Data) {
+        let dgram = match (try_as_datagram(data), self.responder.as_mut()) {
+            (Some(dgram), Some(responder))
+                if dgram.header.destination_address == self.self_address =>
+            {
+                responder
+                    .answer(dgram.command, dgram.param16, dgram.param32)
+                    .map(|(command, value)| {
+                        (dgram.header.source_address, command, dgram.param16, value)
+                    })
+            }
+            _ => None,
+        };
+
+        if let Some((address, command, index, value)) = dgram {
+            let tx_data = Data::Datagram(self.create_datagram(address, command, index, value));
+            self.pending_tx.extend(bytes_from_data(&tx_data));
+        }
+    }
+
+    async fn journal_value_transaction(
+        &mut self,
+        operation: &'static str,
+        tx_dgram: &Datagram,
+        rx_dgram: Option<&Datagram>,
+    ) -> Result<()> {
+        let tries = self.last_tries;
+        if let Some(ref mut journal) = self.journal {
+            let entry = JournalEntry {
+                timestamp: Utc::now(),
+                operation,
+                address: tx_dgram.header.destination_address,
+                index: tx_dgram.param16,
+                subindex: (tx_dgram.command & 0x00FF) as u8,
+                value: if operation == "get" {
+                    None
+                } else {
+                    Some(tx_dgram.param32)
+                },
+                reply: rx_dgram.map(|dgram| dgram.param32),
+                tries,
+            };
+            journal.append(&entry).await?;
+        }
+        Ok(())
+    }
+
+    async fn check_write_allowed(&mut self, data: &Data) -> Result<()> {
+        let dgram = match (data, &self.write_guard) {
+            (Data::Datagram(dgram), Some(guard)) if is_transaction_command(dgram.command) => {
+                return if guard.is_transaction_allowed() {
+                    Ok(())
+                } else {
+                    Err(Error::write_denied(
+                        "Bulk value transactions are not allowed".to_string(),
+                    ))
+                };
+            }
+            (Data::Datagram(dgram), Some(_)) if is_write_command(dgram.command) => dgram,
+            _ => return Ok(()),
+        };
+
+        let address = dgram.header.destination_address;
+        let index = dgram.param16;
+        let has_id_rules = self
+            .write_guard
+            .as_ref()
+            .is_some_and(|guard| guard.has_id_rules());
+        let id_hash = if has_id_rules {
+            self.cached_value_id_hash(address, index).await?
+        } else {
+            None
+        };
+
+        match self.write_guard {
+            Some(ref guard) if !guard.is_allowed_with_id_hash(index, id_hash) => {
+                Err(write_denied_error(index))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    async fn cached_value_id_hash(&mut self, address: u16, index: i16) -> Result<Option<i32>> {
+        if let Some(id_hash) = self.value_id_hashes.get(&(address, index)) {
+            return Ok(Some(*id_hash));
+        }
+
+        let id_hash = self
+            .get_value_id_hash_by_index(address, index)
+            .await?
+            .map(|dgram| dgram.param32);
+        if let Some(id_hash) = id_hash {
+            self.value_id_hashes.insert((address, index), id_hash);
+        }
+        Ok(id_hash)
+    }
+
+    /// Declare the VBus protocol version a peer supports.
+    ///
+    /// Devices that only support protocol version 1.0 (`0x10`) cannot be
+    /// addressed with datagrams, devices using protocol version 3.0 (`0x30`)
+    /// communicate using telegrams instead. Value transactions towards a peer
+    /// declared to support one of those versions fail with an error without
+    /// sending anything to the device. Passing `None` removes the declaration.
+    ///
+    /// Detected versions never block transactions, since most controllers
+    /// send protocol version 1.0 packets but still answer datagrams.
+    pub fn set_peer_protocol_version(&mut self, address: u16, version: Option<u8>) {
+        match version {
+            Some(version) => self.declared_protocol_versions.insert(address, version),
+            None => self.declared_protocol_versions.remove(&address),
+        };
+    }
+
+    /// Get the VBus protocol version of a peer.
+    ///
+    /// Returns the version declared using `set_peer_protocol_version` or,
+    /// if none was declared, the highest version received from that peer
+    /// so far.
+    pub fn peer_protocol_version(&self, address: u16) -> Option<u8> {
+        self.declared_protocol_versions
+            .get(&address)
+            .or_else(|| self.detected_protocol_versions.get(&address))
+            .cloned()
+    }
+
+    fn detect_protocol_version(&mut self, data: &Data) {
+        let header = data.as_header();
+        let version = self
+            .detected_protocol_versions
+            .entry(header.source_address)
+            .or_insert(header.protocol_version);
+        if *version < header.protocol_version {
+            *version = header.protocol_version;
+        }
+    }
+
+    fn create_request_datagram(
+        &self,
+        destination_address: u16,
+        command: u16,
+        param16: i16,
+        param32: i32,
+    ) -> Result<Datagram> {
+        match self.declared_protocol_versions.get(&destination_address) {
+            Some(version) if version & 0xF0 != 0x20 => Err(format!(
+                "Device 0x{:04X} does not support datagrams (protocol version {}.{})",
+                destination_address,
+                version >> 4,
+                version & 0x0F
+            )
+            .into()),
+            _ => Ok(self.create_datagram(destination_address, command, param16, param32)),
         }
     }
 
+    /// Set the interval after which a keep-alive datagram is sent while
+    /// holding the bus.
+    ///
+    /// After `wait_for_free_bus` returned a bus offer, the VBus master waits
+    /// for datagrams from this device. If no datagram is sent for a certain
+    /// amount of time, the master considers the bus to be released and
+    /// resumes its regular operation. If this option is set, all waiting
+    /// methods of the stream (e.g. `receive`) send a harmless "get value"
+    /// datagram for index 0 to the bus owner whenever no other datagram was
+    /// sent within `interval`. The answer to a keep-alive is discarded, so
+    /// that it does not satisfy a concurrent request for index 0. The
+    /// keep-alive stops once `release_bus` is called.
+    ///
+    /// Defaults to `None`, which disables the keep-alive.
+    pub fn set_keep_alive_interval(&mut self, interval: Option<Duration>) {
+        self.keep_alive_interval = interval;
+    }
+
+    /// Get the VBus channel of this stream.
+    pub fn channel(&self) -> u8 {
+        self.channel
+    }
+
+    /// Get the own VBus address of this stream.
+    pub fn self_address(&self) -> u16 {
+        self.self_address
+    }
+
+    /// Send all pending writes.
+    ///
+    /// This includes the release of a `ControllerSession` that was dropped
+    /// while the writer was not ready, which is otherwise only sent by the
+    /// next operation on this stream. Call this before dropping the stream
+    /// or using `into_inner`.
+    pub async fn close(&mut self) -> Result<()> {
+        self.flush_pending_tx().await?;
+        Ok(())
+    }
+
     /// Consume `self` and return the underlying I/O pair.
+    ///
+    /// Pending writes are discarded, see `close`.
     pub fn into_inner(self) -> (R, W) {
         (self.reader, self.writer)
     }
 
+    /// Take the settings that are independent of the connection.
+    pub(crate) fn take_settings(&mut self) -> LiveDataStreamSettings {
+        LiveDataStreamSettings {
+            max_buffer_size: self.max_buffer_size,
+            buffer_overflow_policy: self.buffer_overflow_policy,
+            buffer_overflow_sender: self.buffer_overflow_sender.take(),
+            keep_alive_interval: self.keep_alive_interval,
+            write_guard: self.write_guard.take(),
+            journal: self.journal.take(),
+            responder: self.responder.take(),
+            metrics_hook: self.metrics_hook.take(),
+            nak_decoder: self.nak_decoder.take(),
+            event_sender: self.event_sender.take(),
+            write_timeout: self.write_timeout,
+        }
+    }
+
+    /// Apply the `settings` taken from another `LiveDataStream`.
+    ///
+    /// Settings that are already set on `self` are kept.
+    pub(crate) fn apply_settings(&mut self, settings: LiveDataStreamSettings) {
+        if self.max_buffer_size.is_none() {
+            self.max_buffer_size = settings.max_buffer_size;
+            self.buffer_overflow_policy = settings.buffer_overflow_policy;
+        }
+        if self.buffer_overflow_sender.is_none() {
+            self.buffer_overflow_sender = settings.buffer_overflow_sender;
+        }
+        if self.keep_alive_interval.is_none() {
+            self.keep_alive_interval = settings.keep_alive_interval;
+        }
+        if self.write_guard.is_none() {
+            self.write_guard = settings.write_guard;
+        }
+        if self.journal.is_none() {
+            self.journal = settings.journal;
+        }
+        if self.responder.is_none() {
+            self.responder = settings.responder;
+        }
+        if self.metrics_hook.is_none() {
+            self.metrics_hook = settings.metrics_hook;
+        }
+        if self.nak_decoder.is_none() {
+            self.nak_decoder = settings.nak_decoder;
+        }
+        if self.event_sender.is_none() {
+            self.event_sender = settings.event_sender;
+        }
+        if self.write_timeout.is_none() {
+            self.write_timeout = settings.write_timeout;
+        }
+    }
+
+    /// Consume `self` and return a `Stream` of all received `Data`.
+    ///
+    /// Pending writes (including the release of a dropped
+    /// `ControllerSession`) are sent first, see `close`. The returned
+    /// `DataStream` owns the connection and the data already buffered by
+    /// `self`. It is `Send + 'static` if the I/O pair is, so it can be
+    /// handed to other tasks or frameworks. The stream ends when the reader
+    /// reaches EOF, I/O errors are yielded once before ending it.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # fn main() -> async_resol_vbus::Result<()> { async_std::task::block_on(async {
+    /// #
+    /// use async_std::{net::TcpStream, prelude::*};
+    ///
+    /// use async_resol_vbus::LiveDataStream;
+    ///
+    /// let stream = TcpStream::connect("192.168.5.217:7053").await?;
+    /// // ... perform handshake ...
+    /// let lds = LiveDataStream::from_tcp_stream(stream, 0, 0x0020);
+    ///
+    /// let mut data_stream = lds.into_data_stream().await?;
+    /// while let Some(data) = data_stream.next().await {
+    ///     println!("{}", data?.id_string());
+    /// }
+    /// #
+    /// # Ok(()) }) }
+    /// ```
+    pub async fn into_data_stream(mut self) -> Result<DataStream<R, W>> {
+        self.close().await?;
+        Ok(DataStream::new(self.reader, self.writer, self.buf))
+    }
+
     fn create_datagram(
         &self,
         destination_address: u16,
@@ -69,6 +755,139 @@ impl<R: Read + Unpin, W: Write + Unpin> LiveDataStream<R, W> {
         }
     }
 
+    fn keep_alive_timeout(&self) -> Option<Duration> {
+        match (self.keep_alive_interval, self.bus_owner) {
+            (Some(interval), Some(_)) => Some(interval.saturating_sub(self.last_tx.elapsed())),
+            _ => None,
+        }
+    }
+
+    /// Get the point in time the next keep-alive datagram is due.
+    ///
+    /// Returns `None` if no keep-alive interval is set or the bus is not
+    /// owned by this stream. Keep-alive datagrams are only sent while the
+    /// stream is receiving, so custom event loops should call `receive` or
+    /// `receive_until` at least until this deadline.
+    pub fn keep_alive_deadline(&self) -> Option<Instant> {
+        match (self.keep_alive_interval, self.bus_owner) {
+            (Some(interval), Some(_)) => Some(self.last_tx + interval),
+            _ => None,
+        }
+    }
+
+    async fn write_data_bytes(&mut self, bytes: &[u8]) -> std::io::Result<()> {
+        self.pending_tx.extend_from_slice(bytes);
+        self.flush_pending_tx().await
+    }
+
+    /// Write the pending bytes, removing every chunk from the queue as soon
+    /// as it was written, so that a cancelled write is resumed by the next
+    /// operation instead of being lost or sent twice.
+    async fn flush_pending_tx(&mut self) -> std::io::Result<()> {
+        let timeout = self
+            .write_timeout
+            .map(|timeout| (timeout, Instant::now() + timeout));
+        let total = self.pending_tx.len();
+
+        while !self.pending_tx.is_empty() {
+            let written = total - self.pending_tx.len();
+            let result =
+                match write_before(&mut self.writer, &self.pending_tx, timeout, written, total)
+                    .await
+                {
+                    Ok(0) => Err(std::io::ErrorKind::WriteZero.into()),
+                    result => result,
+                };
+            match result {
+                Ok(len) => {
+                    self.pending_tx.drain(0..len);
+                    self.last_tx = Instant::now();
+                }
+                Err(err) => {
+                    self.pending_tx.clear();
+                    return Err(err);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    async fn send_keep_alive(&mut self) -> std::io::Result<()> {
+        if let Some(address) = self.bus_owner {
+            let tx_data = Data::Datagram(self.create_datagram(address, 0x0300, 0, 0));
+            self.write_data_bytes(&bytes_from_data(&tx_data)).await?;
+            self.pending_keep_alive = Some(address);
+        }
+        Ok(())
+    }
+
+    /// Check whether `data` is the answer to the pending keep-alive and
+    /// clear the pending keep-alive if so.
+    fn take_keep_alive_answer(&mut self, data: &Data) -> bool {
+        let is_answer = match (self.pending_keep_alive, data) {
+            (Some(address), Data::Datagram(dgram)) => {
+                dgram.header.source_address == address
+                    && dgram.header.destination_address == self.self_address
+                    && dgram.command == 0x0100
+                    && dgram.param16 == 0
+            }
+            _ => false,
+        };
+        if is_answer {
+            self.pending_keep_alive = None;
+        }
+        is_answer
+    }
+
+    /// Release the bus without waiting, used when a `ControllerSession` is
+    /// dropped.
+    ///
+    /// The release datagram is written immediately if the writer accepts it
+    /// without blocking. Otherwise the rest is sent by the next operation or
+    /// by `close`.
+    pub(crate) fn release_bus_without_waiting(&mut self, address: u16) {
+        self.bus_owner = None;
+
+        let tx_data = Data::Datagram(self.create_datagram(address, 0x0600, 0, 0));
+        self.pending_tx.extend(bytes_from_data(&tx_data));
+
+        let mut cx = Context::from_waker(Waker::noop());
+        while !self.pending_tx.is_empty() {
+            match Pin::new(&mut self.writer).poll_write(&mut cx, &self.pending_tx) {
+                Poll::Ready(Ok(len)) if len > 0 => {
+                    self.pending_tx.drain(0..len);
+                }
+                _ => break,
+            }
+        }
+        if self.pending_tx.is_empty() {
+            self.last_tx = Instant::now();
+            let _ = Pin::new(&mut self.writer).poll_flush(&mut cx);
+        }
+    }
+
+    fn emit_data_events(&self, data: &Data) {
+        if self.event_sender.is_none() {
+            return;
+        }
+
+        emit_event(
+            &self.event_sender,
+            VBusEvent::DataReceived(DataId::from_data(data)),
+        );
+
+        if let Some(dgram) = try_as_datagram(data) {
+            if dgram.command == 0x0500 {
+                emit_event(
+                    &self.event_sender,
+                    VBusEvent::BusOffered {
+                        address: dgram.header.source_address,
+                    },
+                );
+            }
+        }
+    }
+
     async fn transceive_internal<F>(
         &mut self,
         tx_data: Option<Data>,
@@ -80,16 +899,83 @@ impl<R: Read + Unpin, W: Write + Unpin> LiveDataStream<R, W> {
     where
         F: Fn(&Data) -> bool,
     {
-        let tx_data = match tx_data {
-            Some(ref data) => {
-                let len = live_data_encoder::length_from_data(data);
-                let mut bytes = vec![0u8; len];
-                live_data_encoder::bytes_from_data(data, &mut bytes);
-                Some(bytes)
+        let (operation, kind) = match tx_data {
+            Some(ref tx_data) => {
+                if let Some(ref hook) = self.metrics_hook {
+                    hook.transaction_started(tx_data);
+                }
+                ("transaction", ErrorKind::Transaction)
+            }
+            None => ("receive", ErrorKind::Receive),
+        };
+
+        // negative replies are only looked for in replies to the request
+        let nak_decoder = self.nak_decoder.clone();
+        let tx_dgram = match (&nak_decoder, &tx_data) {
+            (Some(_), Some(Data::Datagram(dgram))) => Some(dgram.clone()),
+            _ => None,
+        };
+        let decode_nak = |data: &Data| match (&nak_decoder, &tx_dgram, try_as_datagram(data)) {
+            (Some(decoder), Some(tx_dgram), Some(dgram))
+                if dgram.header.source_address == tx_dgram.header.destination_address
+                    && dgram.header.destination_address == tx_dgram.header.source_address =>
+            {
+                decoder.decode(tx_dgram, dgram)
             }
-            None => None,
+            _ => None,
         };
 
+        let result = self
+            .transceive_observed(
+                tx_data,
+                max_tries,
+                initial_timeout_ms,
+                timeout_increment_ms,
+                |data| filter(data) || decode_nak(data).is_some(),
+            )
+            .await;
+
+        let result = match result {
+            Ok(Some(data)) if !filter(&data) => match decode_nak(&data) {
+                Some(nak) => Err(Error::from_device_nak(nak)),
+                None => Ok(Some(data)),
+            },
+            result => result,
+        };
+
+        if let Err(ref err) = result {
+            if let Some(ref hook) = self.metrics_hook {
+                hook.error(operation, err.message());
+            }
+            emit_event(
+                &self.event_sender,
+                VBusEvent::Error {
+                    kind,
+                    message: err.message().to_string(),
+                },
+            );
+        }
+
+        result
+    }
+
+    async fn transceive_observed<F>(
+        &mut self,
+        tx_data: Option<Data>,
+        max_tries: usize,
+        initial_timeout_ms: u64,
+        timeout_increment_ms: u64,
+        filter: F,
+    ) -> Result<Option<Data>>
+    where
+        F: Fn(&Data) -> bool,
+    {
+        self.flush_pending_tx().await?;
+
+        let tx_data = tx_data.as_ref().map(bytes_from_data);
+
+        let start = Instant::now();
+        let mut last_tx = start;
         let mut current_try = 0;
         let mut current_timeout_ms = initial_timeout_ms;
 
@@ -99,44 +985,81 @@ impl<R: Read + Unpin, W: Write + Unpin> LiveDataStream<R, W> {
             }
 
             if let Some(ref tx_data) = tx_data {
-                self.writer.write_all(tx_data).await?;
+                self.write_data_bytes(tx_data).await?;
+                last_tx = Instant::now();
+
+                if let (true, Some(hook)) = (current_try > 0, &self.metrics_hook) {
+                    hook.transaction_retried(current_try + 1);
+                }
             }
 
             let result = async_std::io::timeout(Duration::from_millis(current_timeout_ms), async {
                 loop {
-                    let data = loop {
-                        if let Some(data) = self.buf.read_data() {
-                            if filter(&data) {
-                                break Some(data);
-                            }
-                        } else {
-                            break None;
-                        }
-                    };
+                    self.flush_pending_tx().await?;
 
-                    if let Some(data) = data {
-                        break Ok(Some(data));
+                    if let Some(data) = self.read_buffered_data() {
+                        if self.take_keep_alive_answer(&data) {
+                            continue;
+                        }
+                        if filter(&data) {
+                            break Ok(Some(data));
+                        }
+                        continue;
                     }
 
                     let mut buf = [0u8; 256];
-                    let len = self.reader.read(&mut buf).await?;
+                    let len = match self.keep_alive_timeout() {
+                        Some(timeout) => {
+                            match async_std::future::timeout(timeout, self.reader.read(&mut buf))
+                                .await
+                            {
+                                Ok(result) => result?,
+                                Err(_) => {
+                                    self.send_keep_alive().await?;
+                                    continue;
+                                }
+                            }
+                        }
+                        None => self.reader.read(&mut buf).await?,
+                    };
                     if len == 0 {
+                        if let Some(ref hook) = self.metrics_hook {
+                            hook.disconnected();
+                        }
+                        emit_event(&self.event_sender, VBusEvent::ConnectionDown);
                         break Ok(None);
                     }
 
-                    self.buf.extend_from_slice(&buf[0..len]);
+                    self.extend_buffer(&buf[0..len])?;
                 }
             })
             .await;
 
-            if let Ok(data) = result {
-                break data;
+            match result {
+                Ok(data) => break data,
+                Err(err) if err.kind() != std::io::ErrorKind::TimedOut => return Err(err.into()),
+                Err(_) => {}
             }
 
             current_try += 1;
             current_timeout_ms += timeout_increment_ms;
         };
 
+        self.last_tries = (current_try + 1).min(max_tries);
+
+        if tx_data.is_some() {
+            let timing = TransactionTiming {
+                tries: self.last_tries,
+                latency: result.as_ref().map(|_| last_tx.elapsed()),
+                duration: start.elapsed(),
+            };
+            self.transaction_stats.add(&timing);
+            if let Some(ref hook) = self.metrics_hook {
+                hook.transaction_finished(&timing);
+            }
+            self.last_transaction_timing = Some(timing);
+        }
+
         Ok(result)
     }
 
@@ -154,6 +1077,16 @@ impl<R: Read + Unpin, W: Write + Unpin> LiveDataStream<R, W> {
     /// If the `filter` function did not find the matching data within
     /// `timeout_ms` milliseconds, the `receive` method returns with
     /// `None`.
+    ///
+    /// # Cancellation safety
+    ///
+    /// The returned future can be raced against other futures (e.g. using
+    /// `select` or `async_std::future::timeout`) and dropped before it
+    /// completes. Received bytes are moved into the internal buffer without
+    /// an intermediate await point, so data that was not yet passed to the
+    /// `filter` is returned by the next call. Pending writes (keep-alives or
+    /// answers of a `DatagramResponder`) are resumed by the next call, which
+    /// also sends the answer to a request that matched the `filter`.
     pub async fn receive<F>(&mut self, timeout_ms: u64, filter: F) -> Result<Option<Data>>
     where
         F: Fn(&Data) -> bool,
@@ -162,6 +1095,90 @@ impl<R: Read + Unpin, W: Write + Unpin> LiveDataStream<R, W> {
             .await
     }
 
+    /// Receive data from the VBus until the `deadline` has passed.
+    ///
+    /// This works like `receive`, but takes a point in time instead of a
+    /// duration, which makes it easier to share a common deadline (e.g.
+    /// the `keep_alive_deadline`) across multiple calls in a custom event
+    /// loop.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # fn main() -> async_resol_vbus::Result<()> { async_std::task::block_on(async {
+    /// #
+    /// use std::time::{Duration, Instant};
+    ///
+    /// use async_std::net::TcpStream;
+    ///
+    /// use async_resol_vbus::LiveDataStream;
+    ///
+    /// let stream = TcpStream::connect("192.168.5.217:7053").await?;
+    /// // ... perform handshake ...
+    /// let mut lds = LiveDataStream::from_tcp_stream(stream, 0, 0x0020);
+    ///
+    /// let deadline = Instant::now() + Duration::from_secs(10);
+    /// while let Some(data) = lds.receive_until(deadline, |data| data.is_packet()).await? {
+    ///     println!("{}", data.id_string());
+    /// }
+    /// #
+    /// # Ok(()) }) }
+    /// ```
+    pub async fn receive_until<F>(&mut self, deadline: Instant, filter: F) -> Result<Option<Data>>
+    where
+        F: Fn(&Data) -> bool,
+    {
+        let timeout = deadline.saturating_duration_since(Instant::now());
+        self.receive(timeout.as_millis() as u64, filter).await
+    }
+
+    /// Observe the VBus for `timeout_ms` milliseconds and return the
+    /// addresses of all devices that sent data.
+    ///
+    /// The addresses are returned in ascending order. The own address of
+    /// this stream is excluded. The device type of an address can be looked
+    /// up using `Specification::get_device_spec`.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # fn main() -> async_resol_vbus::Result<()> { async_std::task::block_on(async {
+    /// #
+    /// use async_std::net::TcpStream;
+    ///
+    /// use resol_vbus::{Language, Specification, SpecificationFile};
+    ///
+    /// use async_resol_vbus::LiveDataStream;
+    ///
+    /// let stream = TcpStream::connect("192.168.5.217:7053").await?;
+    /// // ... perform handshake ...
+    /// let mut lds = LiveDataStream::from_tcp_stream(stream, 0, 0x0020);
+    ///
+    /// let spec = Specification::from_file(SpecificationFile::new_default(), Language::En);
+    ///
+    /// for address in lds.scan_bus(10000).await? {
+    ///     let device_spec = spec.get_device_spec(0, address, 0x0010);
+    ///     println!("0x{:04X}: {}", address, device_spec.name);
+    /// }
+    /// #
+    /// # Ok(()) }) }
+    /// ```
+    pub async fn scan_bus(&mut self, timeout_ms: u64) -> Result<Vec<u16>> {
+        let self_address = self.self_address;
+        let addresses = RefCell::new(BTreeSet::new());
+
+        self.receive(timeout_ms, |data| {
+            let source_address = data.as_ref().source_address;
+            if source_address != self_address {
+                addresses.borrow_mut().insert(source_address);
+            }
+            false
+        })
+        .await?;
+
+        Ok(addresses.into_inner().into_iter().collect())
+    }
+
     /// Send data to the VBus and wait for a reply.
     ///
     /// This method sends the `tx_data` to the VBus and waits for up to
@@ -193,6 +1210,8 @@ impl<R: Read + Unpin, W: Write + Unpin> LiveDataStream<R, W> {
     where
         F: Fn(&Data) -> bool,
     {
+        self.check_write_allowed(&tx_data).await?;
+
         self.transceive_internal(
             Some(tx_data),
             max_tries,
@@ -203,11 +1222,72 @@ impl<R: Read + Unpin, W: Write + Unpin> LiveDataStream<R, W> {
         .await
     }
 
+    /// Send `data` to the VBus without waiting for a reply.
+    pub async fn send_data(&mut self, data: &Data) -> Result<()> {
+        self.check_write_allowed(data).await?;
+        self.write_data_bytes(&bytes_from_data(data)).await?;
+        Ok(())
+    }
+
+    /// Wait for a packet matching the given packet ID.
+    ///
+    /// The `id` can be a packet ID string like `00_0010_7E11_10_0100`
+    /// (e.g. the `packet_id` of a `PacketSpec`) or a `PacketId`. If no
+    /// matching packet is received within `timeout_ms` milliseconds, this
+    /// method returns with `None`.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # fn main() -> async_resol_vbus::Result<()> { async_std::task::block_on(async {
+    /// #
+    /// use async_std::net::TcpStream;
+    ///
+    /// use async_resol_vbus::LiveDataStream;
+    ///
+    /// let stream = TcpStream::connect("192.168.5.217:7053").await?;
+    /// // ... perform handshake ...
+    /// let mut lds = LiveDataStream::from_tcp_stream(stream, 0, 0x0020);
+    ///
+    /// if let Some(packet) = lds.receive_packet_by_id("00_0010_7E11_10_0100", 10000).await? {
+    ///     println!("{:?}", packet.frame_data);
+    /// }
+    /// #
+    /// # Ok(()) }) }
+    /// ```
+    pub async fn receive_packet_by_id<T>(
+        &mut self,
+        id: &T,
+        timeout_ms: u64,
+    ) -> Result<Option<Packet>>
+    where
+        T: ToPacketId + ?Sized,
+    {
+        let packet_id = id.to_packet_id()?;
+
+        let data = self
+            .receive(timeout_ms, |data| {
+                data.is_packet() && data.as_packet().packet_id() == packet_id
+            })
+            .await?;
+
+        Ok(data.map(|data| data.into_packet()))
+    }
+
     /// Wait for any VBus data.
     pub async fn receive_any_data(&mut self, timeout_ms: u64) -> Result<Option<Data>> {
         self.receive(timeout_ms, |_| true).await
     }
 
+    /// Wait for VBus data matching a `DataFilter`.
+    pub async fn receive_matching(
+        &mut self,
+        timeout_ms: u64,
+        filter: &DataFilter,
+    ) -> Result<Option<Data>> {
+        self.receive(timeout_ms, |data| filter.matches(data)).await
+    }
+
     /// Wait for a datagram that offers VBus control.
     pub async fn wait_for_free_bus(&mut self) -> Result<Option<Datagram>> {
         let rx_data = self
@@ -220,12 +1300,113 @@ impl<R: Read + Unpin, W: Write + Unpin> LiveDataStream<R, W> {
             })
             .await?;
 
-        Ok(rx_data.map(|data| data.into_datagram()))
+        let rx_dgram = rx_data.map(|data| data.into_datagram());
+
+        if let Some(ref dgram) = rx_dgram {
+            self.bus_owner = Some(dgram.header.source_address);
+        }
+
+        Ok(rx_dgram)
+    }
+
+    /// Wait for the VBus device at `address` to offer VBus control and
+    /// return a `ControllerSession` guard for it.
+    ///
+    /// The session gives back bus control when `ControllerSession::release`
+    /// is called. If the session is dropped without being released (e.g.
+    /// because an error was propagated using the `?` operator), the
+    /// release datagram is sent at the beginning of the next operation
+    /// performed on this stream.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # fn main() -> async_resol_vbus::Result<()> { async_std::task::block_on(async {
+    /// #
+    /// use async_std::net::TcpStream;
+    ///
+    /// use async_resol_vbus::LiveDataStream;
+    ///
+    /// let stream = TcpStream::connect("192.168.5.217:7053").await?;
+    /// // ... perform handshake ...
+    /// let mut lds = LiveDataStream::new(stream.clone(), stream, 0, 0x0020);
+    ///
+    /// let mut session = lds.acquire_bus(0x7E11).await?;
+    /// let changeset = session.get_value_by_index(0, 0).await?;
+    /// session.release().await?;
+    /// #
+    /// # Ok(()) }) }
+    /// ```
+    pub async fn acquire_bus(&mut self, address: u16) -> Result<ControllerSession<'_, R, W>> {
+        let rx_data = self
+            .receive(20000, |data| {
+                if let Some(dgram) = try_as_datagram(data) {
+                    dgram.command == 0x0500 && dgram.header.source_address == address
+                } else {
+                    false
+                }
+            })
+            .await?;
+
+        if rx_data.is_none() {
+            return Err(format!("Unable to acquire bus from 0x{:04X}", address).into());
+        }
+
+        self.bus_owner = Some(address);
+
+        Ok(ControllerSession::new(self, address))
+    }
+
+    /// Acquire bus control over the device with the given address, run `f`
+    /// and release the bus afterwards.
+    ///
+    /// In contrast to dropping a `ControllerSession`, the bus is released
+    /// even if `f` returns an error. The error returned by `f` takes
+    /// precedence over an error releasing the bus.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # fn main() -> async_resol_vbus::Result<()> { async_std::task::block_on(async {
+    /// #
+    /// use async_std::net::TcpStream;
+    ///
+    /// use async_resol_vbus::LiveDataStream;
+    ///
+    /// let stream = TcpStream::connect("192.168.5.217:7053").await?;
+    /// // ... perform handshake ...
+    /// let mut lds = LiveDataStream::from_tcp_stream(stream, 0, 0x0020);
+    ///
+    /// let value = lds
+    ///     .with_bus(0x7E11, |session| {
+    ///         Box::pin(async move {
+    ///             let dgram = session.get_value_by_index(0x0081, 0).await?;
+    ///             Ok(dgram.map(|dgram| dgram.param32))
+    ///         })
+    ///     })
+    ///     .await?;
+    /// #
+    /// # Ok(()) }) }
+    /// ```
+    pub async fn with_bus<T, F>(&mut self, address: u16, f: F) -> Result<T>
+    where
+        F: for<'s, 'b> FnOnce(
+            &'s mut ControllerSession<'b, R, W>,
+        ) -> Pin<Box<dyn Future<Output = Result<T>> + 's>>,
+    {
+        let mut session = self.acquire_bus(address).await?;
+        let result = f(&mut session).await;
+        let released = session.release().await;
+        let value = result?;
+        released?;
+        Ok(value)
     }
 
     /// Give back bus control to the regular VBus master.
     pub async fn release_bus(&mut self, address: u16) -> Result<Option<Data>> {
-        let tx_dgram = self.create_datagram(address, 0x0600, 0, 0);
+        self.bus_owner = None;
+
+        let tx_dgram = self.create_request_datagram(address, 0x0600, 0, 0)?;
 
         let tx_data = Data::Datagram(tx_dgram);
 
@@ -237,13 +1418,21 @@ impl<R: Read + Unpin, W: Write + Unpin> LiveDataStream<R, W> {
     }
 
     /// Get a value by its index.
+    ///
+    /// The `index` is transmitted in the 16 bit parameter of the datagram and
+    /// therefore covers the complete value space of a VBus device. Indices
+    /// above `0x7FFF` can be passed by casting them from `u16` to `i16`. The
+    /// `subindex` is OR-ed into the lower byte of the command. Version 2.0 of
+    /// the VBus protocol does not define any command variants with a wider
+    /// subindex.
     pub async fn get_value_by_index(
         &mut self,
         address: u16,
         index: i16,
         subindex: u8,
     ) -> Result<Option<Datagram>> {
-        let tx_dgram = self.create_datagram(address, 0x0300 | u16::from(subindex), index, 0);
+        let tx_dgram =
+            self.create_request_datagram(address, 0x0300 | u16::from(subindex), index, 0)?;
 
         let tx_data = Data::Datagram(tx_dgram.clone());
 
@@ -260,10 +1449,18 @@ impl<R: Read + Unpin, W: Write + Unpin> LiveDataStream<R, W> {
             })
             .await?;
 
-        Ok(rx_data.map(|data| data.into_datagram()))
+        let rx_dgram = rx_data.map(|data| data.into_datagram());
+
+        self.journal_value_transaction("get", &tx_dgram, rx_dgram.as_ref())
+            .await?;
+
+        Ok(rx_dgram)
     }
 
     /// Set a value by its index.
+    ///
+    /// See `get_value_by_index` for details about the `index` and `subindex`
+    /// value ranges.
     pub async fn set_value_by_index(
         &mut self,
         address: u16,
@@ -271,7 +1468,8 @@ impl<R: Read + Unpin, W: Write + Unpin> LiveDataStream<R, W> {
         subindex: u8,
         value: i32,
     ) -> Result<Option<Datagram>> {
-        let tx_dgram = self.create_datagram(address, 0x0200 | u16::from(subindex), index, value);
+        let tx_dgram =
+            self.create_request_datagram(address, 0x0200 | u16::from(subindex), index, value)?;
 
         let tx_data = Data::Datagram(tx_dgram.clone());
 
@@ -288,7 +1486,56 @@ impl<R: Read + Unpin, W: Write + Unpin> LiveDataStream<R, W> {
             })
             .await?;
 
-        Ok(rx_data.map(|data| data.into_datagram()))
+        let rx_dgram = rx_data.map(|data| data.into_datagram());
+
+        self.journal_value_transaction("set", &tx_dgram, rx_dgram.as_ref())
+            .await?;
+
+        Ok(rx_dgram)
+    }
+
+    /// Set a value by its index and verify it by reading it back.
+    ///
+    /// The value read back is considered to match if it differs from the
+    /// requested `value` by at most `tolerance`, which allows for values
+    /// that the device rounds to its internal scaling.
+    pub async fn set_value_by_index_verified(
+        &mut self,
+        address: u16,
+        index: i16,
+        subindex: u8,
+        value: i32,
+        tolerance: i32,
+    ) -> Result<VerifiedWrite> {
+        if self
+            .set_value_by_index(address, index, subindex, value)
+            .await?
+            .is_none()
+        {
+            return Ok(VerifiedWrite::NoReply);
+        }
+
+        let actual = match self.get_value_by_index(address, index, subindex).await? {
+            Some(dgram) => dgram.param32,
+            None => return Ok(VerifiedWrite::NoReply),
+        };
+
+        if (i64::from(actual) - i64::from(value)).abs() <= i64::from(tolerance) {
+            emit_event(
+                &self.event_sender,
+                VBusEvent::WriteVerified {
+                    address,
+                    index,
+                    value: actual,
+                },
+            );
+            Ok(VerifiedWrite::Verified { value: actual })
+        } else {
+            Ok(VerifiedWrite::Mismatch {
+                requested: value,
+                actual,
+            })
+        }
     }
 
     /// Get a value's ID hash by its index.
@@ -297,12 +1544,13 @@ impl<R: Read + Unpin, W: Write + Unpin> LiveDataStream<R, W> {
         address: u16,
         index: i16,
     ) -> Result<Option<Datagram>> {
-        let tx_dgram = self.create_datagram(address, 0x1000, index, 0);
+        let tx_dgram = self.create_request_datagram(address, 0x1000, index, 0)?;
 
         let tx_data = Data::Datagram(tx_dgram.clone());
 
+        // bypasses the `WriteGuard`, which uses this request itself
         let rx_data = self
-            .transceive(tx_data, 3, 500, 500, |data| {
+            .transceive_internal(Some(tx_data), 3, 500, 500, |data| {
                 if let Some(dgram) = try_as_datagram(data) {
                     dgram.header.source_address == tx_dgram.header.destination_address
                         && dgram.header.destination_address == tx_dgram.header.source_address
@@ -318,12 +1566,16 @@ impl<R: Read + Unpin, W: Write + Unpin> LiveDataStream<R, W> {
     }
 
     /// Get a value's index by its ID hash.
+    ///
+    /// Devices report unknown ID hashes as index 0. Some devices answer
+    /// with a regular value reply, which requires reading a value before
+    /// the next request, see `ValueIndexLookup::ResyncRequired`.
     pub async fn get_value_index_by_id_hash(
         &mut self,
         address: u16,
         id_hash: i32,
-    ) -> Result<Option<Datagram>> {
-        let tx_dgram = self.create_datagram(address, 0x1100, 0, id_hash);
+    ) -> Result<ValueIndexLookup> {
+        let tx_dgram = self.create_request_datagram(address, 0x1100, 0, id_hash)?;
 
         let tx_data = Data::Datagram(tx_dgram.clone());
 
@@ -340,12 +1592,23 @@ impl<R: Read + Unpin, W: Write + Unpin> LiveDataStream<R, W> {
             })
             .await?;
 
-        Ok(rx_data.map(|data| data.into_datagram()))
+        let lookup = match rx_data.map(|data| data.into_datagram()) {
+            Some(dgram) if dgram.param16 == 0 => ValueIndexLookup::Unknown,
+            Some(dgram) if dgram.command == 0x0100 => ValueIndexLookup::ResyncRequired {
+                index: dgram.param16,
+            },
+            Some(dgram) => ValueIndexLookup::Found {
+                index: dgram.param16,
+            },
+            None => ValueIndexLookup::NoReply,
+        };
+
+        Ok(lookup)
     }
 
     /// Get the capabilities (part 1) from a VBus device.
     pub async fn get_caps1(&mut self, address: u16) -> Result<Option<Datagram>> {
-        let tx_dgram = self.create_datagram(address, 0x1300, 0, 0);
+        let tx_dgram = self.create_request_datagram(address, 0x1300, 0, 0)?;
 
         let tx_data = Data::Datagram(tx_dgram.clone());
 
@@ -364,13 +1627,63 @@ impl<R: Read + Unpin, W: Write + Unpin> LiveDataStream<R, W> {
         Ok(rx_data.map(|data| data.into_datagram()))
     }
 
+    /// Send a protocol version 3.0 telegram to a VBus device and wait
+    /// for a telegram reply from that device.
+    ///
+    /// The `frame_data` is copied into the telegram and must not exceed
+    /// the number of bytes the frame count encoded in `command` allows.
+    pub async fn transceive_telegram(
+        &mut self,
+        address: u16,
+        command: u8,
+        frame_data: &[u8],
+    ) -> Result<Option<Telegram>> {
+        let frame_count = usize::from(Telegram::frame_count_from_command(command));
+        if frame_data.len() > frame_count * 7 {
+            return Err(format!(
+                "Telegram command 0x{:02X} only allows {} bytes of frame data",
+                command,
+                frame_count * 7
+            )
+            .into());
+        }
+
+        let mut tgram = Telegram {
+            header: Header {
+                timestamp: Utc::now(),
+                channel: self.channel,
+                destination_address: address,
+                source_address: self.self_address,
+                protocol_version: 0x30,
+            },
+            command,
+            frame_data: [0; 21],
+        };
+        tgram.frame_data[0..frame_data.len()].copy_from_slice(frame_data);
+
+        let self_address = self.self_address;
+
+        let rx_data = self
+            .transceive(Data::Telegram(tgram), 3, 500, 500, |data| {
+                if let Data::Telegram(ref tgram) = *data {
+                    tgram.header.source_address == address
+                        && tgram.header.destination_address == self_address
+                } else {
+                    false
+                }
+            })
+            .await?;
+
+        Ok(rx_data.map(|data| data.into_telegram()))
+    }
+
     /// Begin a bulk value transaction.
     pub async fn begin_bulk_value_transaction(
         &mut self,
         address: u16,
         tx_timeout: i32,
     ) -> Result<Option<Datagram>> {
-        let tx_dgram = self.create_datagram(address, 0x1400, 0, tx_timeout);
+        let tx_dgram = self.create_request_datagram(address, 0x1400, 0, tx_timeout)?;
 
         let tx_data = Data::Datagram(tx_dgram.clone());
 
@@ -394,7 +1707,7 @@ impl<R: Read + Unpin, W: Write + Unpin> LiveDataStream<R, W> {
         &mut self,
         address: u16,
     ) -> Result<Option<Datagram>> {
-        let tx_dgram = self.create_datagram(address, 0x1402, 0, 0);
+        let tx_dgram = self.create_request_datagram(address, 0x1402, 0, 0)?;
 
         let tx_data = Data::Datagram(tx_dgram.clone());
 
@@ -418,7 +1731,7 @@ impl<R: Read + Unpin, W: Write + Unpin> LiveDataStream<R, W> {
         &mut self,
         address: u16,
     ) -> Result<Option<Datagram>> {
-        let tx_dgram = self.create_datagram(address, 0x1404, 0, 0);
+        let tx_dgram = self.create_request_datagram(address, 0x1404, 0, 0)?;
 
         let tx_data = Data::Datagram(tx_dgram.clone());
 
@@ -445,7 +1758,8 @@ impl<R: Read + Unpin, W: Write + Unpin> LiveDataStream<R, W> {
         subindex: u8,
         value: i32,
     ) -> Result<Option<Datagram>> {
-        let tx_dgram = self.create_datagram(address, 0x1500 | u16::from(subindex), index, value);
+        let tx_dgram =
+            self.create_request_datagram(address, 0x1500 | u16::from(subindex), index, value)?;
 
         let tx_data = Data::Datagram(tx_dgram.clone());
 
@@ -462,204 +1776,1026 @@ impl<R: Read + Unpin, W: Write + Unpin> LiveDataStream<R, W> {
             })
             .await?;
 
-        Ok(rx_data.map(|data| data.into_datagram()))
+        let rx_dgram = rx_data.map(|data| data.into_datagram());
+
+        self.journal_value_transaction("setBulk", &tx_dgram, rx_dgram.as_ref())
+            .await?;
+
+        Ok(rx_dgram)
+    }
+
+    /// Write multiple values within a bulk value transaction.
+    ///
+    /// The transaction is begun using `tx_timeout`, then all `(index, value)`
+    /// pairs are written in order. If every value was acknowledged, the
+    /// transaction is committed. Otherwise the remaining values are skipped
+    /// and the transaction is rolled back.
+    pub async fn apply_parameters(
+        &mut self,
+        address: u16,
+        values: &[(i16, i32)],
+        tx_timeout: i32,
+    ) -> Result<AppliedParameters> {
+        if self
+            .begin_bulk_value_transaction(address, tx_timeout)
+            .await?
+            .is_none()
+        {
+            return Err("Unable to begin bulk value transaction".into());
+        }
+
+        self.write_bulk_values(address, values).await
+    }
+
+    /// Write multiple values within a bulk value transaction that was
+    /// already begun, then commit or roll it back.
+    ///
+    /// See `apply_parameters` for details. The transaction is also rolled
+    /// back if writing a value fails with an error.
+    pub(crate) async fn write_bulk_values(
+        &mut self,
+        address: u16,
+        values: &[(i16, i32)],
+    ) -> Result<AppliedParameters> {
+        let mut outcomes = Vec::with_capacity(values.len());
+        let mut failed = false;
+        for &(index, value) in values {
+            let outcome = if failed {
+                ParameterOutcome::Skipped
+            } else {
+                match self.set_bulk_value_by_index(address, index, 0, value).await {
+                    Ok(Some(dgram)) => ParameterOutcome::Written(dgram.param32),
+                    Ok(None) => ParameterOutcome::NoReply,
+                    Err(err) => {
+                        drop(self.rollback_bulk_value_transaction(address).await);
+                        return Err(err);
+                    }
+                }
+            };
+
+            if outcome == ParameterOutcome::NoReply {
+                failed = true;
+            }
+
+            outcomes.push((index, outcome));
+        }
+
+        let committed = if failed {
+            self.rollback_bulk_value_transaction(address).await?;
+            false
+        } else if self.commit_bulk_value_transaction(address).await?.is_some() {
+            true
+        } else {
+            self.rollback_bulk_value_transaction(address).await?;
+            false
+        };
+
+        Ok(AppliedParameters {
+            committed,
+            outcomes,
+        })
+    }
+}
+
+/// A `LiveDataStream` that owns the reading and writing halves of a `TcpStream`.
+///
+/// Unlike a `LiveDataStream` borrowing the stream (e.g. using
+/// `(&stream, &stream)`), this type is `'static` and can be moved into
+/// spawned tasks.
+pub type TcpLiveDataStream = LiveDataStream<TcpStream, TcpStream>;
+
+impl LiveDataStream<TcpStream, TcpStream> {
+    /// Create a new `TcpLiveDataStream` from a `TcpStream`.
+    ///
+    /// The `TcpStream` is cloned internally to get owned reading and
+    /// writing halves.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # fn main() -> async_resol_vbus::Result<()> { async_std::task::block_on(async {
+    /// #
+    /// use async_std::net::{SocketAddr, TcpStream};
+    ///
+    /// use async_resol_vbus::{LiveDataStream, TcpClientHandshake};
+    ///
+    /// let address = "192.168.5.217:7053".parse::<SocketAddr>()?;
+    /// let stream = TcpStream::connect(address).await?;
+    /// let mut hs = TcpClientHandshake::start(stream).await?;
+    /// hs.send_pass_command("vbus").await?;
+    /// let stream = hs.send_data_command().await?;
+    ///
+    /// let mut lds = LiveDataStream::from_tcp_stream(stream, 0, 0x0020);
+    ///
+    /// async_std::task::spawn(async move {
+    ///     while let Ok(Some(data)) = lds.receive_any_data(60000).await {
+    ///         println!("{}", data.id_string());
+    ///     }
+    /// });
+    /// #
+    /// # Ok(()) }) }
+    /// ```
+    pub fn from_tcp_stream(stream: TcpStream, channel: u8, self_address: u16) -> TcpLiveDataStream {
+        LiveDataStream::new(stream.clone(), stream, channel, self_address)
+    }
+}
+
+/// A writer rejecting all writes, used by `LiveDataStream::read_only`.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ReadOnlyWriter;
+
+impl Write for ReadOnlyWriter {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        _cx: &mut Context<'_>,
+        _buf: &[u8],
+    ) -> Poll<std::io::Result<usize>> {
+        Poll::Ready(Err(std::io::Error::other(
+            "Unable to send data using a read-only LiveDataStream",
+        )))
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn poll_close(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+        Poll::Ready(Ok(()))
+    }
+}
+
+impl<R: Read + Unpin> LiveDataStream<R, ReadOnlyWriter> {
+    /// Create a receive-only `LiveDataStream` from any reader, e.g. the
+    /// output of `socat` or `nc` piped into stdin.
+    ///
+    /// All methods that need to send data to the VBus fail with an error.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # fn main() -> async_resol_vbus::Result<()> { async_std::task::block_on(async {
+    /// #
+    /// use async_resol_vbus::LiveDataStream;
+    ///
+    /// let mut lds = LiveDataStream::read_only(async_std::io::stdin(), 0);
+    ///
+    /// while let Some(data) = lds.receive_any_data(60000).await? {
+    ///     println!("{}", data.id_string());
+    /// }
+    /// #
+    /// # Ok(()) }) }
+    /// ```
+    pub fn read_only(reader: R, channel: u8) -> LiveDataStream<R, ReadOnlyWriter> {
+        LiveDataStream::new(reader, ReadOnlyWriter, channel, 0x0020)
     }
 }
 
 #[cfg(test)]
 impl<R: Read + Unpin, W: Write + Unpin> LiveDataStream<R, W> {
-    pub fn writer_ref(&self) -> &W {
+    pub(crate) fn writer_ref(&self) -> &W {
         &self.writer
     }
-}
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    };
+
+    use async_std::io::{Cursor, ReadExt};
+
+    use resol_vbus::PacketId;
+
+    use super::*;
+
+    use crate::{
+        data_builder::DatagramBuilder,
+        device_nak::DeviceNak,
+        test_utils::{
+            extend_from_data, extend_from_datagram, extend_with_empty_packet, hex_encode,
+            simulate_run, PendingReader,
+        },
+        write_stall::WriteStall,
+    };
+
+    #[test]
+    fn test_wait_for_free_bus() {
+        let mut rx_buf = Vec::new();
+        let tx_buf = Cursor::new(Vec::new());
+
+        extend_with_empty_packet(&mut rx_buf, 0x0010, 0x7E11, 0x0100);
+        extend_from_datagram(&mut rx_buf, 0x0000, 0x7E11, 0x0500, 0, 0);
+
+        let mut lds = LiveDataStream::new(&rx_buf[..], tx_buf, 0, 0x0020);
+
+        let data = simulate_run(lds.wait_for_free_bus()).unwrap();
+
+        assert_eq!("", hex_encode(lds.writer_ref()));
+        assert_eq!(
+            "aa0000117e200005000000000000004b",
+            hex_encode(&data.unwrap())
+        );
+    }
+
+    #[test]
+    fn test_release_bus() {
+        let mut rx_buf = Vec::new();
+        let tx_buf = Cursor::new(Vec::new());
+
+        extend_from_datagram(&mut rx_buf, 0x0020, 0x7E11, 0x0100, 0, 0);
+        extend_with_empty_packet(&mut rx_buf, 0x0010, 0x7E11, 0x0100);
+
+        let mut lds = LiveDataStream::new(&rx_buf[..], tx_buf, 0, 0x0020);
+
+        let data = simulate_run(lds.release_bus(0x7E11)).unwrap();
+
+        assert_eq!(
+            "aa117e2000200006000000000000002a",
+            hex_encode(lds.writer_ref())
+        );
+        assert_eq!("aa1000117e100001004f", hex_encode(&data.unwrap()));
+    }
+
+    #[test]
+    fn test_keep_alive() {
+        let mut rx_buf = Vec::new();
+        let tx_buf = Cursor::new(Vec::new());
+
+        extend_from_datagram(&mut rx_buf, 0x0000, 0x7E11, 0x0500, 0, 0);
+
+        let reader = (&rx_buf[..]).chain(PendingReader);
+
+        let mut lds = LiveDataStream::new(reader, tx_buf, 0, 0x0020);
+        lds.set_keep_alive_interval(Some(Duration::from_millis(40)));
+
+        simulate_run(lds.wait_for_free_bus()).unwrap();
+
+        let data = simulate_run(lds.receive(100, |_| false)).unwrap();
+
+        assert_eq!(None, data);
+
+        let tx_hex = hex_encode(lds.writer_ref());
+        assert!(tx_hex.len() >= 64);
+        assert_eq!("aa117e2000200003000000000000002d", &tx_hex[0..32]);
+        assert_eq!("aa117e2000200003000000000000002d", &tx_hex[32..64]);
+    }
+
+    #[test]
+    fn test_keep_alive_answer() {
+        let mut rx_buf = Vec::new();
+        let tx_buf = Cursor::new(Vec::new());
+
+        extend_from_datagram(&mut rx_buf, 0x0020, 0x7E11, 0x0100, 0x0000, 1);
+        extend_from_datagram(&mut rx_buf, 0x0020, 0x7E11, 0x0100, 0x0000, 2);
+
+        let mut lds = LiveDataStream::new(&rx_buf[..], tx_buf, 0, 0x0020);
+        lds.pending_keep_alive = Some(0x7E11);
+
+        let data = simulate_run(lds.get_value_by_index(0x7E11, 0, 0)).unwrap();
+
+        assert_eq!(2, data.unwrap().param32);
+        assert_eq!(None, lds.pending_keep_alive);
+    }
+
+    #[test]
+    fn test_get_value_by_index() {
+        let mut rx_buf = Vec::new();
+        let tx_buf = Cursor::new(Vec::new());
+
+        extend_with_empty_packet(&mut rx_buf, 0x0010, 0x7E11, 0x0100);
+        extend_from_datagram(&mut rx_buf, 0x0021, 0x7E11, 0x0156, 0x1234, 0x789abcde);
+        extend_from_datagram(&mut rx_buf, 0x0020, 0x7E10, 0x0156, 0x1234, 0x789abcde);
+        extend_from_datagram(&mut rx_buf, 0x0020, 0x7E11, 0x0157, 0x1234, 0x789abcde);
+        extend_from_datagram(&mut rx_buf, 0x0020, 0x7E11, 0x0156, 0x1235, 0x789abcde);
+        extend_from_datagram(&mut rx_buf, 0x0020, 0x7E11, 0x0156, 0x1234, 0x789abcde);
+
+        let mut lds = LiveDataStream::new(&rx_buf[..], tx_buf, 0, 0x0020);
+
+        let data = simulate_run(lds.get_value_by_index(0x7E11, 0x1234, 0x56)).unwrap();
+
+        assert_eq!(
+            "aa117e20002056033412000000000011",
+            hex_encode(lds.writer_ref())
+        );
+        assert_eq!(
+            "aa2000117e20560134125e3c1a781c4b",
+            hex_encode(&data.unwrap())
+        );
+    }
+
+    #[test]
+    fn test_nak_decoder() {
+        struct TestNakDecoder;
+
+        impl NakDecoder for TestNakDecoder {
+            fn decode(&self, request: &Datagram, reply: &Datagram) -> Option<DeviceNak> {
+                if reply.command == 0x7F00 && reply.param16 == request.param16 {
+                    Some(DeviceNak {
+                        code: reply.param32,
+                        meaning: Some("Unknown index".to_string()),
+                    })
+                } else {
+                    None
+                }
+            }
+        }
+
+        let mut rx_buf = Vec::new();
+        // a negative reply from another device and one for another index
+        extend_from_datagram(&mut rx_buf, 0x0020, 0x7E10, 0x7F00, 0x1234, 1);
+        extend_from_datagram(&mut rx_buf, 0x0020, 0x7E11, 0x7F00, 0x1235, 1);
+        extend_from_datagram(&mut rx_buf, 0x0020, 0x7E11, 0x7F00, 0x1234, 1);
+
+        let mut lds = LiveDataStream::new(&rx_buf[..], Cursor::new(Vec::new()), 0, 0x0020);
+        lds.set_nak_decoder(Some(Arc::new(TestNakDecoder)));
+
+        let err = simulate_run(lds.get_value_by_index(0x7E11, 0x1234, 0)).unwrap_err();
+        assert_eq!(
+            Some(&DeviceNak {
+                code: 1,
+                meaning: Some("Unknown index".to_string()),
+            }),
+            err.device_nak()
+        );
+        assert_eq!(
+            "Request rejected by the device (code 1: Unknown index)",
+            err.to_string()
+        );
+
+        // without a decoder the negative reply is ignored
+        let mut lds = LiveDataStream::new(&rx_buf[..], Cursor::new(Vec::new()), 0, 0x0020);
+
+        let data = simulate_run(lds.get_value_by_index(0x7E11, 0x1234, 0)).unwrap();
+        assert!(data.is_none());
+    }
+
+    #[test]
+    fn test_get_value_by_index_timing() {
+        let mut rx_buf = Vec::new();
+        let tx_buf = Cursor::new(Vec::new());
+
+        extend_with_empty_packet(&mut rx_buf, 0x0010, 0x7E11, 0x0100);
+        extend_from_datagram(&mut rx_buf, 0x0020, 0x7E11, 0x0156, 0x1234, 0x789abcde);
+
+        let mut lds = LiveDataStream::new(&rx_buf[..], tx_buf, 0, 0x0020);
+
+        assert_eq!(None, lds.last_transaction_timing());
+
+        let data = simulate_run(lds.get_value_by_index(0x7E11, 0x1234, 0x56)).unwrap();
+        assert!(data.is_some());
+
+        let timing = lds.last_transaction_timing().unwrap();
+        assert_eq!(1, timing.tries);
+        assert!(timing.latency.is_some());
+        assert_eq!(1, lds.transaction_stats().count);
+        assert_eq!(0, lds.transaction_stats().no_reply_count);
+    }
+
+    #[test]
+    fn test_metrics_hook() {
+        #[derive(Default)]
+        struct TestHook {
+            events: std::sync::Mutex<Vec<String>>,
+        }
+
+        impl MetricsHook for TestHook {
+            fn disconnected(&self) {
+                self.events.lock().unwrap().push("disconnected".into());
+            }
+
+            fn transaction_started(&self, request: &Data) {
+                let event = format!("started {}", request.id_string());
+                self.events.lock().unwrap().push(event);
+            }
+
+            fn transaction_retried(&self, tries: usize) {
+                self.events
+                    .lock()
+                    .unwrap()
+                    .push(format!("retried {}", tries));
+            }
+
+            fn transaction_finished(&self, timing: &TransactionTiming) {
+                let event = format!("finished {} {}", timing.tries, timing.latency.is_some());
+                self.events.lock().unwrap().push(event);
+            }
+        }
+
+        let hook = Arc::new(TestHook::default());
+
+        let mut lds = LiveDataStream::new(PendingReader, Cursor::new(Vec::new()), 0, 0x0020);
+        lds.set_metrics_hook(Some(hook.clone()));
+
+        let tx_data = Data::Datagram(lds.create_datagram(0x7E11, 0x0300, 0x1234, 0));
+        let data = simulate_run(lds.transceive(tx_data, 3, 10, 0, |_| false)).unwrap();
+        assert_eq!(None, data);
+
+        let mut lds = LiveDataStream::new(&b""[..], Cursor::new(Vec::new()), 0, 0x0020);
+        lds.set_metrics_hook(Some(hook.clone()));
+
+        let data = simulate_run(lds.receive_any_data(10)).unwrap();
+        assert_eq!(None, data);
+
+        assert_eq!(
+            vec![
+                "started 00_7E11_0020_20_0300_0000",
+                "retried 2",
+                "retried 3",
+                "finished 3 false",
+                "disconnected",
+            ],
+            *hook.events.lock().unwrap()
+        );
+    }
+
+    #[test]
+    fn test_event_sender() {
+        let mut rx_buf = Vec::new();
+        extend_with_empty_packet(&mut rx_buf, 0x0010, 0x7E11, 0x0100);
+        extend_from_datagram(&mut rx_buf, 0x0000, 0x7E11, 0x0500, 0, 0);
+
+        let (sender, receiver) = async_std::channel::unbounded();
+
+        let mut lds = LiveDataStream::new(&rx_buf[..], Cursor::new(Vec::new()), 0, 0x0020);
+        lds.set_event_sender(Some(sender));
+
+        while simulate_run(lds.receive_any_data(100)).unwrap().is_some() {}
+
+        let mut events = Vec::new();
+        while let Ok(event) = receiver.try_recv() {
+            events.push(event);
+        }
+        assert_eq!(
+            vec![
+                VBusEvent::DataReceived("00_0010_7E11_10_0100".parse().unwrap()),
+                VBusEvent::DataReceived("00_0000_7E11_20_0500_0000".parse().unwrap()),
+                VBusEvent::BusOffered { address: 0x7E11 },
+                VBusEvent::ConnectionDown,
+            ],
+            events
+        );
+    }
+
+    #[test]
+    fn test_set_value_by_index() {
+        let mut rx_buf = Vec::new();
+        let tx_buf = Cursor::new(Vec::new());
+
+        extend_with_empty_packet(&mut rx_buf, 0x0010, 0x7E11, 0x0100);
+        extend_from_datagram(&mut rx_buf, 0x0021, 0x7E11, 0x0156, 0x1234, 0x789abcde);
+        extend_from_datagram(&mut rx_buf, 0x0020, 0x7E10, 0x0156, 0x1234, 0x789abcde);
+        extend_from_datagram(&mut rx_buf, 0x0020, 0x7E11, 0x0157, 0x1234, 0x789abcde);
+        extend_from_datagram(&mut rx_buf, 0x0020, 0x7E11, 0x0156, 0x1235, 0x789abcde);
+        extend_from_datagram(&mut rx_buf, 0x0020, 0x7E11, 0x0156, 0x1234, 0x789abcde);
+
+        let mut lds = LiveDataStream::new(&rx_buf[..], tx_buf, 0, 0x0020);
+
+        let data = simulate_run(lds.set_value_by_index(0x7E11, 0x1234, 0x56, 0x789abcde)).unwrap();
+
+        assert_eq!(
+            "aa117e200020560234125e3c1a781c4a",
+            hex_encode(lds.writer_ref())
+        );
+        assert_eq!(
+            "aa2000117e20560134125e3c1a781c4b",
+            hex_encode(&data.unwrap())
+        );
+    }
+
+    #[test]
+    fn test_set_value_by_index_verified() {
+        let mut rx_buf = Vec::new();
+
+        extend_from_datagram(&mut rx_buf, 0x0020, 0x7E11, 0x0100, 0x1234, 50);
+        extend_from_datagram(&mut rx_buf, 0x0020, 0x7E11, 0x0100, 0x1234, 50);
+        extend_from_datagram(&mut rx_buf, 0x0020, 0x7E11, 0x0100, 0x1234, 50);
+        extend_from_datagram(&mut rx_buf, 0x0020, 0x7E11, 0x0100, 0x1234, 50);
+
+        let mut lds = LiveDataStream::new(&rx_buf[..], Cursor::new(Vec::new()), 0, 0x0020);
+
+        let result = simulate_run(lds.set_value_by_index_verified(0x7E11, 0x1234, 0, 60, 0));
+        assert_eq!(
+            Ok(VerifiedWrite::Mismatch {
+                requested: 60,
+                actual: 50
+            }),
+            result
+        );
+
+        let result = simulate_run(lds.set_value_by_index_verified(0x7E11, 0x1234, 0, 49, 1));
+        assert_eq!(Ok(VerifiedWrite::Verified { value: 50 }), result);
+
+        let result = simulate_run(lds.set_value_by_index_verified(0x7E11, 0x1234, 0, 50, 0));
+        assert_eq!(Ok(VerifiedWrite::NoReply), result);
+    }
+
+    #[test]
+    fn test_apply_parameters() {
+        let mut rx_buf = Vec::new();
+
+        extend_from_datagram(&mut rx_buf, 0x0020, 0x7E11, 0x1401, 0, 0);
+        extend_from_datagram(&mut rx_buf, 0x0020, 0x7E11, 0x1600, 0x0010, 1);
+        extend_from_datagram(&mut rx_buf, 0x0020, 0x7E11, 0x1600, 0x0011, 2);
+        extend_from_datagram(&mut rx_buf, 0x0020, 0x7E11, 0x1403, 0, 0);
+
+        let mut lds = LiveDataStream::new(&rx_buf[..], Cursor::new(Vec::new()), 0, 0x0020);
+
+        let result =
+            simulate_run(lds.apply_parameters(0x7E11, &[(0x0010, 1), (0x0011, 2)], 10)).unwrap();
+
+        assert_eq!(
+            AppliedParameters {
+                committed: true,
+                outcomes: vec![
+                    (0x0010, ParameterOutcome::Written(1)),
+                    (0x0011, ParameterOutcome::Written(2)),
+                ],
+            },
+            result
+        );
+    }
+
+    #[test]
+    fn test_apply_parameters_rollback() {
+        let mut rx_buf = Vec::new();
+
+        extend_from_datagram(&mut rx_buf, 0x0020, 0x7E11, 0x1401, 0, 0);
+        extend_from_datagram(&mut rx_buf, 0x0020, 0x7E11, 0x1600, 0x0010, 1);
+
+        let mut lds = LiveDataStream::new(&rx_buf[..], Cursor::new(Vec::new()), 0, 0x0020);
+
+        let values = [(0x0010, 1), (0x0011, 2), (0x0012, 3)];
+        let result = simulate_run(lds.apply_parameters(0x7E11, &values, 10)).unwrap();
+
+        assert_eq!(
+            AppliedParameters {
+                committed: false,
+                outcomes: vec![
+                    (0x0010, ParameterOutcome::Written(1)),
+                    (0x0011, ParameterOutcome::NoReply),
+                    (0x0012, ParameterOutcome::Skipped),
+                ],
+            },
+            result
+        );
+
+        let tx = hex_encode(lds.writer_ref());
+        assert!(tx.contains("aa117e200020041400"));
+    }
+
+    #[test]
+    fn test_scan_bus() {
+        let mut rx_buf = Vec::new();
+
+        extend_with_empty_packet(&mut rx_buf, 0x0010, 0x7E11, 0x0100);
+        extend_from_datagram(&mut rx_buf, 0x0000, 0x7E11, 0x0500, 0, 0);
+        extend_with_empty_packet(&mut rx_buf, 0x0010, 0x4212, 0x0100);
+        extend_from_datagram(&mut rx_buf, 0x7E11, 0x0020, 0x0300, 0, 0);
+        extend_with_empty_packet(&mut rx_buf, 0x0015, 0x2211, 0x0100);
+
+        let mut lds = LiveDataStream::new(&rx_buf[..], Cursor::new(Vec::new()), 0, 0x0020);
+
+        let addresses = simulate_run(lds.scan_bus(1000)).unwrap();
+
+        assert_eq!(vec![0x2211, 0x4212, 0x7E11], addresses);
+    }
+
+    #[test]
+    fn test_receive_packet_by_id() {
+        let mut rx_buf = Vec::new();
+
+        extend_with_empty_packet(&mut rx_buf, 0x0010, 0x4212, 0x0100);
+        extend_from_datagram(&mut rx_buf, 0x0010, 0x7E11, 0x0100, 0, 0);
+        extend_with_empty_packet(&mut rx_buf, 0x0010, 0x7E11, 0x0100);
+
+        let mut lds = LiveDataStream::new(&rx_buf[..], Cursor::new(Vec::new()), 0, 0x0020);
+
+        let packet = simulate_run(lds.receive_packet_by_id("00_0010_7E11_10_0100", 1000))
+            .unwrap()
+            .unwrap();
+
+        assert_eq!(0x7E11, packet.header.source_address);
+        assert_eq!(0x0100, packet.command);
+
+        let result =
+            simulate_run(lds.receive_packet_by_id(&PacketId(0, 0x0010, 0x4212, 0x0100), 1000));
+
+        assert_eq!(None, result.unwrap().map(|packet| packet.id_string()));
+
+        let result = simulate_run(lds.receive_packet_by_id("invalid", 1000));
+
+        assert!(result.is_err());
+    }
 
-#[cfg(test)]
-mod tests {
-    use async_std::io::Cursor;
+    #[test]
+    fn test_receive_matching() -> Result<()> {
+        let mut rx_buf = Vec::new();
 
-    use resol_vbus::Packet;
+        extend_with_empty_packet(&mut rx_buf, 0x0010, 0x4212, 0x0100);
+        extend_from_datagram(&mut rx_buf, 0x0010, 0x7E11, 0x0100, 0, 0);
+        extend_with_empty_packet(&mut rx_buf, 0x0010, 0x7E11, 0x0100);
 
-    use super::*;
+        let mut lds = LiveDataStream::new(&rx_buf[..], Cursor::new(Vec::new()), 0, 0x0020);
 
-    fn extend_from_data(buf: &mut Vec<u8>, data: &Data) {
-        let len = live_data_encoder::length_from_data(data);
-        let idx = buf.len();
-        buf.resize(idx + len, 0);
-        live_data_encoder::bytes_from_data(data, &mut buf[idx..]);
-    }
+        let filter = DataFilter::parse("src=0x7E11&proto=0x10")?;
+        let data = simulate_run(lds.receive_matching(1000, &filter))?.unwrap();
 
-    fn extend_with_empty_packet(
-        buf: &mut Vec<u8>,
-        destination_address: u16,
-        source_address: u16,
-        command: u16,
-    ) {
-        let data = Data::Packet(Packet {
-            header: Header {
-                timestamp: Utc::now(),
-                channel: 0,
-                destination_address,
-                source_address,
-                protocol_version: 0x20,
-            },
-            command,
-            frame_count: 0,
-            frame_data: [0; 508],
-        });
-        extend_from_data(buf, &data);
-    }
+        assert_eq!("00_0010_7E11_10_0100", data.id_string());
 
-    fn extend_from_datagram(
-        buf: &mut Vec<u8>,
-        destination_address: u16,
-        source_address: u16,
-        command: u16,
-        param16: i16,
-        param32: i32,
-    ) {
-        let data = Data::Datagram(Datagram {
-            header: Header {
-                timestamp: Utc::now(),
-                channel: 0,
-                destination_address,
-                source_address,
-                protocol_version: 0x20,
-            },
-            command,
-            param16,
-            param32,
-        });
-        extend_from_data(buf, &data);
+        Ok(())
     }
 
-    fn simulate_run<T, F: Future<Output = T>>(f: F) -> T {
-        async_std::task::block_on(f)
-    }
+    #[test]
+    fn test_responder() {
+        let mut rx_buf = Vec::new();
+
+        extend_from_datagram(&mut rx_buf, 0x0020, 0x7E11, 0x0300, 0x1234, 0);
+        extend_from_datagram(&mut rx_buf, 0x0021, 0x7E11, 0x0300, 0x1234, 0);
+        extend_from_datagram(&mut rx_buf, 0x0020, 0x7E11, 0x0300, 0x1235, 0);
+        extend_from_datagram(&mut rx_buf, 0x0020, 0x7E11, 0x0200, 0x1234, 0x789abcde);
+
+        let mut responder = DatagramResponder::new();
+        responder.set_value(0x1234, 0, 42);
+
+        let mut lds = LiveDataStream::new(&rx_buf[..], Cursor::new(Vec::new()), 0, 0x0020);
+        lds.set_responder(Some(responder));
+
+        let data = simulate_run(lds.receive(1000, |_| false)).unwrap();
 
-    trait ToBytes {
-        fn to_bytes(&self) -> Vec<u8>;
+        assert!(data.is_none());
+        assert_eq!(
+            "aa117e200020000134122a000000003faa117e200020000134125e3c1a781c21",
+            hex_encode(lds.writer_ref())
+        );
+        assert_eq!(
+            Some(0x789abcde),
+            lds.responder_mut().unwrap().value(0x1234, 0)
+        );
     }
 
-    fn hex_encode<T: ToBytes>(value: &T) -> String {
-        let buf = value.to_bytes();
-        buf.iter()
-            .map(|b| format!("{:02x}", b))
-            .collect::<Vec<String>>()
-            .concat()
+    struct StallingWriter {
+        budget: Arc<AtomicUsize>,
+        written: Cursor<Vec<u8>>,
     }
 
-    impl ToBytes for Cursor<Vec<u8>> {
-        fn to_bytes(&self) -> Vec<u8> {
-            self.get_ref().clone()
+    impl Write for StallingWriter {
+        fn poll_write(
+            mut self: Pin<&mut Self>,
+            _cx: &mut Context<'_>,
+            buf: &[u8],
+        ) -> Poll<std::io::Result<usize>> {
+            let len = buf.len().min(self.budget.load(Ordering::SeqCst));
+            if len == 0 {
+                return Poll::Pending;
+            }
+            self.budget.fetch_sub(len, Ordering::SeqCst);
+            self.written.get_mut().extend_from_slice(&buf[0..len]);
+            Poll::Ready(Ok(len))
         }
-    }
 
-    impl ToBytes for Data {
-        fn to_bytes(&self) -> Vec<u8> {
-            let len = live_data_encoder::length_from_data(self);
-            let mut buf = Vec::new();
-            buf.resize(len, 0);
-            live_data_encoder::bytes_from_data(self, &mut buf);
-            buf
+        fn poll_flush(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+            Poll::Ready(Ok(()))
         }
-    }
 
-    impl ToBytes for Datagram {
-        fn to_bytes(&self) -> Vec<u8> {
-            Data::Datagram(self.clone()).to_bytes()
+        fn poll_close(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+            Poll::Ready(Ok(()))
         }
     }
 
     #[test]
-    fn test_wait_for_free_bus() {
-        let mut rx_buf = Vec::new();
-        let tx_buf = Cursor::new(Vec::new());
-
-        extend_with_empty_packet(&mut rx_buf, 0x0010, 0x7E11, 0x0100);
-        extend_from_datagram(&mut rx_buf, 0x0000, 0x7E11, 0x0500, 0, 0);
+    fn test_write_timeout() {
+        let writer = StallingWriter {
+            budget: Arc::new(AtomicUsize::new(10)),
+            written: Cursor::new(Vec::new()),
+        };
 
-        let mut lds = LiveDataStream::new(&rx_buf[..], tx_buf, 0, 0x0020);
+        let mut lds = LiveDataStream::new(PendingReader, writer, 0, 0x0020);
+        lds.set_write_timeout(Some(Duration::from_millis(20)));
 
-        let data = simulate_run(lds.wait_for_free_bus()).unwrap();
+        let tx_data = Data::Datagram(lds.create_datagram(0x7E11, 0x0300, 0x1234, 0));
+        let err = simulate_run(lds.send_data(&tx_data)).unwrap_err();
 
-        assert_eq!("", hex_encode(lds.writer_ref()));
+        let stall = WriteStall {
+            written: 10,
+            total: 16,
+            timeout: Duration::from_millis(20),
+        };
+        assert_eq!(Some(&stall), err.write_stall());
+        assert!(stall.is_partial());
         assert_eq!(
-            "aa0000117e200005000000000000004b",
-            hex_encode(&data.unwrap())
+            "Write stalled after 10 of 16 bytes within 20ms",
+            err.message()
         );
+        assert_eq!(10, lds.writer_ref().written.get_ref().len());
     }
 
     #[test]
-    fn test_release_bus() {
+    fn test_receive_cancellation() {
         let mut rx_buf = Vec::new();
-        let tx_buf = Cursor::new(Vec::new());
 
-        extend_from_datagram(&mut rx_buf, 0x0020, 0x7E11, 0x0100, 0, 0);
+        extend_from_datagram(&mut rx_buf, 0x0020, 0x7E11, 0x0300, 0x1234, 0);
         extend_with_empty_packet(&mut rx_buf, 0x0010, 0x7E11, 0x0100);
 
-        let mut lds = LiveDataStream::new(&rx_buf[..], tx_buf, 0, 0x0020);
+        let mut responder = DatagramResponder::new();
+        responder.set_value(0x1234, 0, 42);
 
-        let data = simulate_run(lds.release_bus(0x7E11)).unwrap();
+        let budget = Arc::new(AtomicUsize::new(5));
+        let writer = StallingWriter {
+            budget: budget.clone(),
+            written: Cursor::new(Vec::new()),
+        };
+
+        let mut lds = LiveDataStream::new(rx_buf.chain(PendingReader), writer, 0, 0x0020);
+        lds.set_responder(Some(responder));
+
+        simulate_run(async {
+            let result = async_std::future::timeout(
+                Duration::from_millis(50),
+                lds.receive(1000, |data| data.is_packet()),
+            )
+            .await;
+            assert!(result.is_err());
+
+            budget.store(usize::MAX, Ordering::SeqCst);
+
+            let data = lds
+                .receive(1000, |data| data.is_packet())
+                .await
+                .unwrap()
+                .unwrap();
+            assert_eq!("00_0010_7E11_10_0100", data.id_string());
+
+            let deadline = Instant::now() + Duration::from_millis(50);
+            let data = lds.receive_until(deadline, |_| true).await.unwrap();
+            assert!(data.is_none());
+        });
 
         assert_eq!(
-            "aa117e2000200006000000000000002a",
-            hex_encode(lds.writer_ref())
+            "aa117e200020000134122a000000003f",
+            hex_encode(&lds.writer_ref().written)
         );
-        assert_eq!("aa1000117e100001004f", hex_encode(&data.unwrap()));
     }
 
     #[test]
-    fn test_get_value_by_index() {
-        let mut rx_buf = Vec::new();
-        let tx_buf = Cursor::new(Vec::new());
+    fn test_keep_alive_deadline() {
+        let rx_buf = Vec::new();
+        let mut lds = LiveDataStream::new(&rx_buf[..], Cursor::new(Vec::new()), 0, 0x0020);
+        assert_eq!(None, lds.keep_alive_deadline());
 
+        lds.set_keep_alive_interval(Some(Duration::from_secs(10)));
+        assert_eq!(None, lds.keep_alive_deadline());
+
+        lds.bus_owner = Some(0x7E11);
+        assert_eq!(
+            Some(lds.last_tx + Duration::from_secs(10)),
+            lds.keep_alive_deadline()
+        );
+    }
+
+    #[test]
+    fn test_max_buffer_size() {
+        let mut rx_buf = vec![0xAA, 0x10, 0x00, 0x11, 0x7E, 0x10, 0x00, 0x01, 0x7F, 0x50];
+        rx_buf.resize(256, 0);
         extend_with_empty_packet(&mut rx_buf, 0x0010, 0x7E11, 0x0100);
-        extend_from_datagram(&mut rx_buf, 0x0021, 0x7E11, 0x0156, 0x1234, 0x789abcde);
-        extend_from_datagram(&mut rx_buf, 0x0020, 0x7E10, 0x0156, 0x1234, 0x789abcde);
-        extend_from_datagram(&mut rx_buf, 0x0020, 0x7E11, 0x0157, 0x1234, 0x789abcde);
-        extend_from_datagram(&mut rx_buf, 0x0020, 0x7E11, 0x0156, 0x1235, 0x789abcde);
-        extend_from_datagram(&mut rx_buf, 0x0020, 0x7E11, 0x0156, 0x1234, 0x789abcde);
 
-        let mut lds = LiveDataStream::new(&rx_buf[..], tx_buf, 0, 0x0020);
+        let mut lds = LiveDataStream::new(&rx_buf[..], Cursor::new(Vec::new()), 0, 0x0020);
 
-        let data = simulate_run(lds.get_value_by_index(0x7E11, 0x1234, 0x56)).unwrap();
+        let data = simulate_run(lds.receive_any_data(1000)).unwrap();
+
+        assert!(data.is_none());
+
+        let (sender, receiver) = async_std::channel::unbounded();
+
+        let mut lds = LiveDataStream::new(&rx_buf[..], Cursor::new(Vec::new()), 0, 0x0020);
+        lds.set_max_buffer_size(Some(100));
+        lds.set_buffer_overflow_sender(Some(sender.clone()));
+
+        let data = simulate_run(lds.receive_any_data(1000)).unwrap();
+
+        assert_eq!("00_0010_7E11_10_0100", data.unwrap().id_string());
+
+        let event = receiver.try_recv().unwrap();
+        assert_eq!(256, event.buffered_len);
+        assert_eq!(BufferOverflowPolicy::DropOldest, event.policy);
+        assert!(receiver.try_recv().is_err());
+
+        let mut lds = LiveDataStream::new(&rx_buf[..], Cursor::new(Vec::new()), 0, 0x0020);
+        lds.set_max_buffer_size(Some(100));
+        lds.set_buffer_overflow_policy(BufferOverflowPolicy::Error);
+        lds.set_buffer_overflow_sender(Some(sender));
+
+        let result = simulate_run(lds.receive_any_data(1000));
 
         assert_eq!(
-            "aa117e20002056033412000000000011",
-            hex_encode(lds.writer_ref())
+            Some("Receive buffer exceeded the maximum size of 100 bytes".into()),
+            result.err()
         );
         assert_eq!(
-            "aa2000117e20560134125e3c1a781c4b",
-            hex_encode(&data.unwrap())
+            BufferOverflowPolicy::Error,
+            receiver.try_recv().unwrap().policy
         );
+
+        // enabling the limit after data was already received
+        let mut rx_buf = Vec::new();
+        extend_with_empty_packet(&mut rx_buf, 0x0010, 0x7E11, 0x0100);
+        rx_buf.resize(256, 0);
+        extend_with_empty_packet(&mut rx_buf, 0x0010, 0x7E11, 0x0100);
+
+        let mut lds = LiveDataStream::new(&rx_buf[..], Cursor::new(Vec::new()), 0, 0x0020);
+
+        let data = simulate_run(lds.receive_any_data(1000)).unwrap();
+
+        assert_eq!("00_0010_7E11_10_0100", data.unwrap().id_string());
+
+        lds.set_max_buffer_size(Some(100));
+
+        let data = simulate_run(lds.receive_any_data(1000)).unwrap();
+
+        assert_eq!("00_0010_7E11_10_0100", data.unwrap().id_string());
     }
 
     #[test]
-    fn test_set_value_by_index() {
+    fn test_resynchronization() {
+        // xorshift64, deterministic so that failures are reproducible
+        let mut rng_state = 0x2545_F491_4F6C_DD1Du64;
+        let mut next_random = move |max: usize| {
+            rng_state ^= rng_state << 13;
+            rng_state ^= rng_state >> 7;
+            rng_state ^= rng_state << 17;
+            (rng_state % max as u64) as usize
+        };
+
+        let mut lds = LiveDataStream::new(async_std::io::empty(), async_std::io::sink(), 0, 0x0020);
+
+        let mut received = Vec::new();
+        let mut read_all = |lds: &mut LiveDataStream<_, _>| {
+            while let Some(data) = lds.read_buffered_data() {
+                if let Data::Datagram(ref dgram) = data {
+                    if dgram.header.source_address == 0x7E11 && dgram.command == 0x0100 {
+                        received.push(dgram.param32);
+                    }
+                }
+            }
+        };
+
+        for round in 0..200 {
+            let mut bytes = Vec::new();
+            match next_random(3) {
+                0 => bytes.resize(next_random(1000), 0xAA),
+                1 => bytes.extend((0..next_random(1000)).map(|_| next_random(256) as u8)),
+                _ => {}
+            }
+            extend_from_datagram(&mut bytes, 0x0020, 0x7E11, 0x0100, 0, round);
+
+            // enabling the limit on a stream that already received data
+            // must not miscalculate the buffered length
+            if round == 100 {
+                lds.set_max_buffer_size(Some(2048));
+            }
+
+            let mut start = 0;
+            while start < bytes.len() {
+                let end = (start + 1 + next_random(64)).min(bytes.len());
+                lds.feed_bytes(&bytes[start..end]).unwrap();
+                start = end;
+                read_all(&mut lds);
+            }
+        }
+
+        // complete any frame candidate started by the garbage
+        lds.feed_bytes(&[0; 1024]).unwrap();
+        read_all(&mut lds);
+
+        let mut expected = 0..200;
+        let mut next_expected = expected.next();
+        for value in received {
+            if Some(value) == next_expected {
+                next_expected = expected.next();
+            }
+        }
+        assert_eq!(None, next_expected);
+    }
+
+    #[test]
+    fn test_write_guard() {
         let mut rx_buf = Vec::new();
-        let tx_buf = Cursor::new(Vec::new());
+        extend_from_datagram(&mut rx_buf, 0x0020, 0x7E11, 0x0100, 0x0010, 1);
 
-        extend_with_empty_packet(&mut rx_buf, 0x0010, 0x7E11, 0x0100);
-        extend_from_datagram(&mut rx_buf, 0x0021, 0x7E11, 0x0156, 0x1234, 0x789abcde);
-        extend_from_datagram(&mut rx_buf, 0x0020, 0x7E10, 0x0156, 0x1234, 0x789abcde);
-        extend_from_datagram(&mut rx_buf, 0x0020, 0x7E11, 0x0157, 0x1234, 0x789abcde);
-        extend_from_datagram(&mut rx_buf, 0x0020, 0x7E11, 0x0156, 0x1235, 0x789abcde);
-        extend_from_datagram(&mut rx_buf, 0x0020, 0x7E11, 0x0156, 0x1234, 0x789abcde);
+        let mut lds = LiveDataStream::new(&rx_buf[..], Cursor::new(Vec::new()), 0, 0x0020);
 
-        let mut lds = LiveDataStream::new(&rx_buf[..], tx_buf, 0, 0x0020);
+        let mut guard = WriteGuard::deny_all();
+        guard.allow(0x0010..=0x001F);
+        lds.set_write_guard(Some(guard));
 
-        let data = simulate_run(lds.set_value_by_index(0x7E11, 0x1234, 0x56, 0x789abcde)).unwrap();
+        let result = simulate_run(lds.set_value_by_index(0x7E11, 0x1234, 0, 1));
+        assert_eq!(Err(write_denied_error(0x1234)), result.map(|_| ()));
+
+        let result = simulate_run(lds.set_bulk_value_by_index(0x7E11, 0x0020, 0, 1));
+        assert!(result.is_err());
+        assert_eq!("", hex_encode(lds.writer_ref()));
 
+        let dgram = simulate_run(lds.set_value_by_index(0x7E11, 0x0010, 0, 1))
+            .unwrap()
+            .unwrap();
+        assert_eq!(1, dgram.param32);
+
+        let tx_dgram = DatagramBuilder::new(0x7E11, 0x0200)
+            .source_address(0x0020)
+            .param16(0x1234)
+            .build()
+            .unwrap();
+        let result = simulate_run(lds.send_data(&Data::Datagram(tx_dgram.clone())));
+        assert!(result.is_err());
+        let result = simulate_run(lds.transceive(Data::Datagram(tx_dgram), 1, 10, 0, |_| true));
+        assert!(result.is_err());
+
+        let result = simulate_run(
+            DatagramBuilder::new(0x7E11, 0x1501)
+                .param16(0x1234)
+                .send(&mut lds),
+        );
+        assert!(result.is_err());
+
+        // only the `set_value_by_index` request was sent
         assert_eq!(
-            "aa117e200020560234125e3c1a781c4a",
+            "aa117e2000200002100001000000001d",
             hex_encode(lds.writer_ref())
         );
+    }
+
+    #[test]
+    fn test_into_data_stream_sends_pending_release() {
+        let mut lds = LiveDataStream::new(&b""[..], Cursor::new(Vec::new()), 0, 0x0020);
+        let release = Data::Datagram(lds.create_datagram(0x7E11, 0x0600, 0, 0));
+        lds.pending_tx.extend(bytes_from_data(&release));
+
+        let data_stream = simulate_run(lds.into_data_stream()).unwrap();
+
+        let (_, writer) = data_stream.into_inner();
+        assert_eq!("aa117e2000200006000000000000002a", hex_encode(&writer));
+    }
+
+    #[test]
+    fn test_write_guard_transactions() {
+        let mut rx_buf = Vec::new();
+        extend_from_datagram(&mut rx_buf, 0x0020, 0x7E11, 0x1403, 0, 0);
+
+        let mut lds = LiveDataStream::new(&rx_buf[..], Cursor::new(Vec::new()), 0, 0x0020);
+        lds.set_write_guard(Some(WriteGuard::deny_all()));
+
+        let result = simulate_run(lds.commit_bulk_value_transaction(0x7E11));
+        assert_eq!(
+            Err(Error::write_denied(
+                "Bulk value transactions are not allowed".to_string()
+            )),
+            result.map(|_| ())
+        );
+        assert_eq!("", hex_encode(lds.writer_ref()));
+
+        let mut guard = WriteGuard::deny_all();
+        guard.allow_transactions();
+        lds.set_write_guard(Some(guard));
+
+        let dgram = simulate_run(lds.commit_bulk_value_transaction(0x7E11))
+            .unwrap()
+            .unwrap();
+        assert_eq!(0x1403, dgram.command);
         assert_eq!(
-            "aa2000117e20560134125e3c1a781c4b",
-            hex_encode(&data.unwrap())
+            "aa117e2000200214000000000000001a",
+            hex_encode(lds.writer_ref())
         );
     }
 
+    #[test]
+    fn test_write_guard_id_rules() {
+        let mut rx_buf = Vec::new();
+        extend_from_datagram(&mut rx_buf, 0x0020, 0x7E11, 0x0100, 0x0010, 0x23FD_9A37);
+        extend_from_datagram(&mut rx_buf, 0x0020, 0x7E11, 0x0100, 0x0010, 1);
+        extend_from_datagram(&mut rx_buf, 0x0020, 0x7E11, 0x0100, 0x0010, 2);
+        extend_from_datagram(&mut rx_buf, 0x0020, 0x7E11, 0x0100, 0x0020, 0x0001_3884);
+
+        let mut lds = LiveDataStream::new(&rx_buf[..], Cursor::new(Vec::new()), 0, 0x0020);
+
+        let mut guard = WriteGuard::deny_all();
+        guard.allow_id("Relais_Handbetrieb");
+        lds.set_write_guard(Some(guard));
+
+        // the ID hash is only requested once
+        let dgram = simulate_run(lds.set_value_by_index(0x7E11, 0x0010, 0, 1))
+            .unwrap()
+            .unwrap();
+        assert_eq!(1, dgram.param32);
+        let dgram = simulate_run(lds.set_value_by_index(0x7E11, 0x0010, 0, 2))
+            .unwrap()
+            .unwrap();
+        assert_eq!(2, dgram.param32);
+
+        let result = simulate_run(lds.set_value_by_index(0x7E11, 0x0020, 0, 3));
+        assert_eq!(Err(write_denied_error(0x0020)), result.map(|_| ()));
+    }
+
     #[test]
     fn test_get_value_id_hash_by_index() {
         let mut rx_buf = Vec::new();
@@ -700,16 +2836,34 @@ mod tests {
 
         let mut lds = LiveDataStream::new(&rx_buf[..], tx_buf, 0, 0x0020);
 
-        let data = simulate_run(lds.get_value_index_by_id_hash(0x7E11, 0x789abcde)).unwrap();
+        let lookup = simulate_run(lds.get_value_index_by_id_hash(0x7E11, 0x789abcde)).unwrap();
 
         assert_eq!(
             "aa117e200020001100005e3c1a781c57",
             hex_encode(lds.writer_ref())
         );
-        assert_eq!(
-            "aa2000117e20000134125e3c1a781c21",
-            hex_encode(&data.unwrap())
-        );
+        assert_eq!(ValueIndexLookup::ResyncRequired { index: 0x1234 }, lookup);
+        assert_eq!(Some(0x1234), lookup.index());
+    }
+
+    #[test]
+    fn test_value_index_lookup() {
+        let mut rx_buf = Vec::new();
+
+        extend_from_datagram(&mut rx_buf, 0x0020, 0x7E11, 0x1101, 0x1234, 0x11111111);
+        extend_from_datagram(&mut rx_buf, 0x0020, 0x7E11, 0x1101, 0x0000, 0x22222222);
+
+        let mut lds = LiveDataStream::new(&rx_buf[..], Cursor::new(Vec::new()), 0, 0x0020);
+
+        let lookup = simulate_run(lds.get_value_index_by_id_hash(0x7E11, 0x11111111));
+        assert_eq!(Ok(ValueIndexLookup::Found { index: 0x1234 }), lookup);
+
+        let lookup = simulate_run(lds.get_value_index_by_id_hash(0x7E11, 0x22222222)).unwrap();
+        assert_eq!(ValueIndexLookup::Unknown, lookup);
+        assert_eq!(None, lookup.index());
+
+        let lookup = simulate_run(lds.get_value_index_by_id_hash(0x7E11, 0x33333333));
+        assert_eq!(Ok(ValueIndexLookup::NoReply), lookup);
     }
 
     #[test]
@@ -838,4 +2992,142 @@ mod tests {
             hex_encode(&data.unwrap())
         );
     }
+
+    #[test]
+    fn test_peer_protocol_version() {
+        let mut rx_buf = Vec::new();
+        let tx_buf = Cursor::new(Vec::new());
+
+        extend_with_empty_packet(&mut rx_buf, 0x0010, 0x7E11, 0x0100);
+
+        let mut lds = LiveDataStream::new(&rx_buf[..], tx_buf, 0, 0x0020);
+
+        assert_eq!(None, lds.peer_protocol_version(0x7E11));
+
+        simulate_run(lds.receive_any_data(100)).unwrap();
+
+        assert_eq!(Some(0x10), lds.peer_protocol_version(0x7E11));
+
+        lds.set_peer_protocol_version(0x7E11, Some(0x10));
+
+        let result = simulate_run(lds.get_value_by_index(0x7E11, 0x1234, 0));
+
+        assert!(result.is_err());
+        assert_eq!("", hex_encode(lds.writer_ref()));
+
+        lds.set_peer_protocol_version(0x7E11, None);
+
+        assert_eq!(Some(0x10), lds.peer_protocol_version(0x7E11));
+    }
+
+    #[test]
+    fn test_transceive_telegram() {
+        let mut rx_buf = Vec::new();
+        let tx_buf = Cursor::new(Vec::new());
+
+        let mut tgram = Telegram {
+            header: Header {
+                timestamp: Utc::now(),
+                channel: 0,
+                destination_address: 0x0020,
+                source_address: 0x7E11,
+                protocol_version: 0x30,
+            },
+            command: 0x25,
+            frame_data: [0; 21],
+        };
+        tgram.frame_data[0..7].copy_from_slice(&[1, 2, 3, 4, 5, 6, 7]);
+        extend_from_datagram(&mut rx_buf, 0x0020, 0x7E11, 0x0100, 0, 0);
+        extend_from_data(&mut rx_buf, &Data::Telegram(tgram));
+
+        let mut lds = LiveDataStream::new(&rx_buf[..], tx_buf, 0, 0x0020);
+
+        let result = simulate_run(lds.transceive_telegram(0x7E11, 0x05, &[0; 1]));
+        assert!(result.is_err());
+
+        let tgram = simulate_run(lds.transceive_telegram(0x7E11, 0x25, &[7, 6, 5]))
+            .unwrap()
+            .unwrap();
+
+        assert_eq!(&[1, 2, 3, 4, 5, 6, 7], &tgram.frame_data[0..7]);
+        assert_eq!(Some(0x30), lds.peer_protocol_version(0x7E11));
+        assert!(hex_encode(lds.writer_ref()).starts_with("aa117e200030"));
+    }
+
+    #[test]
+    fn test_read_only() {
+        let mut rx_buf = Vec::new();
+
+        extend_with_empty_packet(&mut rx_buf, 0x0010, 0x7E11, 0x0100);
+
+        let mut lds = LiveDataStream::read_only(&rx_buf[..], 0);
+
+        let data = simulate_run(lds.receive_any_data(100)).unwrap();
+        assert_eq!("aa1000117e100001004f", hex_encode(&data.unwrap()));
+
+        let result = simulate_run(lds.get_value_by_index(0x7E11, 0x1234, 0));
+        assert_eq!(
+            Some("Unable to send data using a read-only LiveDataStream".into()),
+            result.err()
+        );
+    }
+
+    #[test]
+    fn test_journal() -> Result<()> {
+        let path = std::env::temp_dir().join(format!(
+            "async-resol-vbus-journal-{}.jsonl",
+            std::process::id()
+        ));
+
+        let mut rx_buf = Vec::new();
+        extend_from_datagram(&mut rx_buf, 0x0020, 0x7E11, 0x0156, 0x1234, 0x0042);
+
+        let mut lds = LiveDataStream::new(&rx_buf[..], Cursor::new(Vec::new()), 0, 0x0020);
+        lds.set_journal(Some(simulate_run(TransactionJournal::open(&path))?));
+
+        simulate_run(lds.set_value_by_index(0x7E11, 0x1234, 0x56, 0x0042))?;
+        simulate_run(lds.get_value_by_index(0x7E11, 0x1235, 0))?;
+
+        let content = std::fs::read_to_string(&path)?;
+        std::fs::remove_file(&path)?;
+
+        let lines = content.lines().collect::<Vec<_>>();
+        assert_eq!(2, lines.len());
+        assert!(lines[0].contains(
+            "\"operation\":\"set\",\"address\":32273,\"index\":4660,\"subindex\":86,\"value\":66,\"outcome\":\"ok\",\"reply\":66,\"tries\":1}"
+        ));
+        assert!(lines[1].contains(
+            "\"operation\":\"get\",\"address\":32273,\"index\":4661,\"subindex\":0,\"outcome\":\"noReply\",\"tries\":1}"
+        ));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_from_tcp_stream() -> Result<()> {
+        use async_std::net::{SocketAddr, TcpListener};
+
+        async_std::task::block_on(async {
+            let addr = "127.0.0.1:0".parse::<SocketAddr>()?;
+            let listener = TcpListener::bind(&addr).await?;
+            let addr = listener.local_addr()?;
+
+            let stream = TcpStream::connect(addr).await?;
+            let (mut server_stream, _) = listener.accept().await?;
+
+            let mut lds = LiveDataStream::from_tcp_stream(stream, 0, 0x0020);
+
+            let client_future =
+                async_std::task::spawn(async move { lds.receive_any_data(1000).await });
+
+            let mut rx_buf = Vec::new();
+            extend_from_datagram(&mut rx_buf, 0x0020, 0x7E11, 0x0100, 0x1234, 0x5678);
+            server_stream.write_all(&rx_buf).await?;
+
+            let data = client_future.await?.unwrap();
+            assert_eq!(0x5678, data.as_datagram().param32);
+
+            Ok(())
+        })
+    }
 }
@@ -0,0 +1,79 @@
+use resol_vbus::Data;
+
+use crate::transaction_stats::TransactionTiming;
+
+/// Hook points to collect metrics without depending on a specific metrics
+/// framework.
+///
+/// All methods have a no-op default implementation, so implementations
+/// only override the ones they are interested in. The hook is shared
+/// using an `Arc`, so the methods take `&self` and must be cheap: they are
+/// called inline, e.g. while a transaction is in progress.
+///
+/// # Examples
+///
+/// ```no_run
+/// # fn main() -> async_resol_vbus::Result<()> { async_std::task::block_on(async {
+/// #
+/// use std::sync::{
+///     atomic::{AtomicUsize, Ordering},
+///     Arc,
+/// };
+///
+/// use async_resol_vbus::{
+///     connect_tcp_live_data_stream, ConnectOptions, MetricsHook, TransactionTiming,
+/// };
+///
+/// #[derive(Default)]
+/// struct Counters {
+///     retries: AtomicUsize,
+/// }
+///
+/// impl MetricsHook for Counters {
+///     fn transaction_retried(&self, _tries: usize) {
+///         self.retries.fetch_add(1, Ordering::Relaxed);
+///     }
+///
+///     fn transaction_finished(&self, timing: &TransactionTiming) {
+///         // statsd.timing("vbus.transaction", timing.duration)...
+///     }
+/// }
+///
+/// let counters = Arc::new(Counters::default());
+///
+/// let mut options = ConnectOptions::new();
+/// options.set_metrics_hook(Some(counters.clone()));
+///
+/// let mut lds = connect_tcp_live_data_stream("192.168.5.217:7053", &options).await?;
+/// #
+/// # Ok(()) }) }
+/// ```
+pub trait MetricsHook: Send + Sync {
+    /// Called after a connection was established.
+    fn connected(&self) {}
+
+    /// Called after the peer closed the connection.
+    fn disconnected(&self) {}
+
+    /// Called before the request of a transaction is sent for the first
+    /// time.
+    fn transaction_started(&self, _request: &Data) {}
+
+    /// Called every time the request of a transaction is sent again because
+    /// no reply was received. `tries` is the number of times it was sent
+    /// so far.
+    fn transaction_retried(&self, _tries: usize) {}
+
+    /// Called after a transaction completed, with or without a reply.
+    fn transaction_finished(&self, _timing: &TransactionTiming) {}
+
+    /// Called if an `operation` (`"connect"`, `"transaction"` or
+    /// `"receive"`) failed with an error.
+    fn error(&self, _operation: &str, _message: &str) {}
+}
+
+impl std::fmt::Debug for dyn MetricsHook {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("MetricsHook")
+    }
+}
@@ -0,0 +1,629 @@
+use async_std::{
+    channel::{Receiver, Sender},
+    net::{TcpListener, TcpStream},
+    prelude::*,
+    sync::{Arc, Mutex},
+};
+
+use resol_vbus::{Data, DataSet, Language};
+
+use crate::{
+    error::Result,
+    param_request::{request_param, ParamRequest},
+    shutdown::ShutdownSignal,
+    spec_cache::default_specification,
+};
+
+/// Modbus exception code for an unsupported function code.
+const ILLEGAL_FUNCTION: u8 = 0x01;
+
+/// Modbus exception code for an unmapped or read-only register address.
+const ILLEGAL_DATA_ADDRESS: u8 = 0x02;
+
+/// Modbus exception code for a malformed request.
+const ILLEGAL_DATA_VALUE: u8 = 0x03;
+
+/// Modbus exception code for a failed parameter transaction.
+const SERVER_DEVICE_FAILURE: u8 = 0x04;
+
+/// Modbus exception code for a request addressed to another unit ID.
+const GATEWAY_TARGET_FAILED: u8 = 0x0B;
+
+/// The encoding of a value mapped to Modbus registers.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum RegisterFormat {
+    /// A signed 16-bit integer occupying one register.
+    Int16,
+
+    /// A signed 32-bit integer occupying two registers, high word first.
+    Int32,
+}
+
+impl RegisterFormat {
+    fn register_count(self) -> u16 {
+        match self {
+            RegisterFormat::Int16 => 1,
+            RegisterFormat::Int32 => 2,
+        }
+    }
+
+    /// The value reported for fields that were not received yet.
+    fn missing_value(self) -> i32 {
+        match self {
+            RegisterFormat::Int16 => i32::from(i16::MIN),
+            RegisterFormat::Int32 => i32::MIN,
+        }
+    }
+
+    fn encode(self, value: i64) -> Vec<u16> {
+        match self {
+            RegisterFormat::Int16 => {
+                let value = value.clamp(i64::from(i16::MIN), i64::from(i16::MAX)) as i16;
+                vec![value as u16]
+            }
+            RegisterFormat::Int32 => {
+                let value = value.clamp(i64::from(i32::MIN), i64::from(i32::MAX)) as u32;
+                vec![(value >> 16) as u16, value as u16]
+            }
+        }
+    }
+
+    fn decode(self, registers: &[u16]) -> i32 {
+        match self {
+            RegisterFormat::Int16 => i32::from(registers[0] as i16),
+            RegisterFormat::Int32 => {
+                ((u32::from(registers[0]) << 16) | u32::from(registers[1])) as i32
+            }
+        }
+    }
+}
+
+/// A value mapped to one or more consecutive registers.
+#[derive(Debug, Clone)]
+struct RegisterMapping {
+    address: u16,
+    id: String,
+    format: RegisterFormat,
+    writable: bool,
+}
+
+impl RegisterMapping {
+    fn end(&self) -> u32 {
+        u32::from(self.address) + u32::from(self.format.register_count())
+    }
+
+    fn overlaps(&self, start: u32, end: u32) -> bool {
+        u32::from(self.address) < end && start < self.end()
+    }
+}
+
+/// A Modbus-TCP server providing access to live data and parameters of a
+/// VBus device.
+///
+/// Fields of the live data are mapped to input registers using
+/// `add_input_register`, parameters are mapped to holding registers using
+/// `add_holding_register`. Registers contain the raw integer values, i.e.
+/// a temperature of `45.5 °C` with a precision of 1 is reported as `455`.
+/// Fields that were not received yet are reported as the smallest value
+/// of the `RegisterFormat` (`0x8000` or `0x80000000`).
+///
+/// The following function codes are supported:
+///
+/// - `0x03`: Read Holding Registers
+/// - `0x04`: Read Input Registers
+/// - `0x06`: Write Single Register
+/// - `0x10`: Write Multiple Registers
+///
+/// Like the `HttpApi`, the `ModbusServer` does not own the
+/// `LiveDataStream`. The application feeds received `Data` into it using
+/// `add_data` and processes the `ParamRequest`s received from the
+/// `Receiver` returned by `ModbusServer::new`, so that parameter
+/// transactions are serialized with all other operations performed on the
+/// stream.
+///
+/// This type is only available if the `modbus` feature is enabled.
+///
+/// # Examples
+///
+/// ```no_run
+/// # fn main() -> async_resol_vbus::Result<()> { async_std::task::block_on(async {
+/// #
+/// use async_std::net::{TcpListener, TcpStream};
+///
+/// use async_resol_vbus::{LiveDataStream, ModbusServer, RegisterFormat};
+///
+/// let stream = TcpStream::connect("192.168.5.217:7053").await?;
+/// // ... perform handshake ...
+/// let mut lds = LiveDataStream::new(stream.clone(), stream, 0, 0x0020);
+///
+/// let (mut server, param_requests) = ModbusServer::new();
+/// server.add_input_register(0, "00_0010_7E11_10_0100_000_2_0", RegisterFormat::Int16)?;
+/// server.add_holding_register(100, "Relais_Handbetrieb_R1", RegisterFormat::Int16, true)?;
+///
+/// let listener = TcpListener::bind("0.0.0.0:502").await?;
+/// async_std::task::spawn(server.clone().serve(listener));
+///
+/// loop {
+///     while let Ok(request) = param_requests.try_recv() {
+///         request.process(&mut lds, 0x7E11).await?;
+///     }
+///
+///     if let Some(data) = lds.receive_any_data(1000).await? {
+///         server.add_data(data).await;
+///     }
+/// }
+/// #
+/// # }) }
+/// ```
+#[derive(Debug, Clone)]
+pub struct ModbusServer {
+    data_set: Arc<Mutex<DataSet>>,
+    param_sender: Sender<ParamRequest>,
+    unit_id: Option<u8>,
+    input_registers: Vec<RegisterMapping>,
+    holding_registers: Vec<RegisterMapping>,
+}
+
+impl ModbusServer {
+    /// Create a new `ModbusServer` and the `Receiver` for its
+    /// `ParamRequest`s.
+    pub fn new() -> (ModbusServer, Receiver<ParamRequest>) {
+        let (param_sender, param_receiver) = async_std::channel::bounded(10);
+
+        let server = ModbusServer {
+            data_set: Arc::new(Mutex::new(DataSet::new())),
+            param_sender,
+            unit_id: None,
+            input_registers: Vec::new(),
+            holding_registers: Vec::new(),
+        };
+
+        (server, param_receiver)
+    }
+
+    /// Set the unit ID requests must be addressed to.
+    ///
+    /// Defaults to `None`, which accepts requests for any unit ID.
+    pub fn set_unit_id(&mut self, unit_id: Option<u8>) {
+        self.unit_id = unit_id;
+    }
+
+    /// Map the field with the given packet field ID to the input registers
+    /// starting at `address`.
+    pub fn add_input_register(
+        &mut self,
+        address: u16,
+        packet_field_id: &str,
+        format: RegisterFormat,
+    ) -> Result<()> {
+        let mapping = RegisterMapping {
+            address,
+            id: packet_field_id.to_string(),
+            format,
+            writable: false,
+        };
+        add_mapping(&mut self.input_registers, mapping)
+    }
+
+    /// Map the parameter with the given index (decimal or `0x` prefixed
+    /// hexadecimal) or value ID to the holding registers starting at
+    /// `address`.
+    ///
+    /// Writing the registers is rejected unless `writable` is `true`.
+    pub fn add_holding_register(
+        &mut self,
+        address: u16,
+        param_id: &str,
+        format: RegisterFormat,
+        writable: bool,
+    ) -> Result<()> {
+        let mapping = RegisterMapping {
+            address,
+            id: param_id.to_string(),
+            format,
+            writable,
+        };
+        add_mapping(&mut self.holding_registers, mapping)
+    }
+
+    /// Add a received `Data` to the accumulated `DataSet`.
+    pub async fn add_data(&self, data: Data) {
+        let mut data_set = self.data_set.lock().await;
+        data_set.timestamp = data.as_ref().timestamp;
+        data_set.add_data(data);
+    }
+
+    /// Accept and handle Modbus-TCP connections until an error occurs.
+    pub async fn serve(self, listener: TcpListener) -> Result<()> {
+        loop {
+            let (stream, _) = listener.accept().await?;
+
+            let server = self.clone();
+            async_std::task::spawn(async move {
+                drop(server.handle_connection(stream).await);
+            });
+        }
+    }
+
+    /// Accept and handle Modbus-TCP connections until the shutdown is
+    /// triggered.
+    pub async fn serve_until_shutdown(self, listener: TcpListener, signal: ShutdownSignal) {
+        let result = signal.run_until(self.serve(listener)).await;
+        signal.complete(result.unwrap_or(Ok(())));
+    }
+
+    async fn handle_connection(&self, mut stream: TcpStream) -> Result<()> {
+        loop {
+            let mut header = [0u8; 7];
+            if let Err(err) = stream.read_exact(&mut header).await {
+                return match err.kind() {
+                    std::io::ErrorKind::UnexpectedEof => Ok(()),
+                    _ => Err(err.into()),
+                };
+            }
+
+            let protocol_id = u16::from_be_bytes([header[2], header[3]]);
+            let length = usize::from(u16::from_be_bytes([header[4], header[5]]));
+            if protocol_id != 0 || !(2..=254).contains(&length) {
+                return Err("Invalid Modbus-TCP header".into());
+            }
+
+            let unit_id = header[6];
+
+            let mut pdu = vec![0u8; length - 1];
+            stream.read_exact(&mut pdu).await?;
+
+            let response = match self.unit_id {
+                Some(expected) if expected != unit_id => {
+                    vec![pdu[0] | 0x80, GATEWAY_TARGET_FAILED]
+                }
+                _ => match self.handle_pdu(&pdu).await {
+                    Ok(response) => response,
+                    Err(code) => vec![pdu[0] | 0x80, code],
+                },
+            };
+
+            let mut frame = Vec::with_capacity(7 + response.len());
+            frame.extend_from_slice(&header[0..4]);
+            frame.extend_from_slice(&(response.len() as u16 + 1).to_be_bytes());
+            frame.push(unit_id);
+            frame.extend_from_slice(&response);
+
+            stream.write_all(&frame).await?;
+        }
+    }
+
+    /// Handle a request PDU, returning the response PDU or the exception
+    /// code.
+    async fn handle_pdu(&self, pdu: &[u8]) -> std::result::Result<Vec<u8>, u8> {
+        let word = |idx: usize| -> std::result::Result<u16, u8> {
+            match pdu.get(idx..idx + 2) {
+                Some(bytes) => Ok(u16::from_be_bytes([bytes[0], bytes[1]])),
+                None => Err(ILLEGAL_DATA_VALUE),
+            }
+        };
+
+        match pdu[0] {
+            0x03 | 0x04 => {
+                let address = word(1)?;
+                let count = word(3)?;
+                if !(1..=125).contains(&count) {
+                    return Err(ILLEGAL_DATA_VALUE);
+                }
+
+                let registers = if pdu[0] == 0x03 {
+                    self.read_holding_registers(address, count).await?
+                } else {
+                    self.read_input_registers(address, count).await?
+                };
+
+                let mut response = vec![pdu[0], (count * 2) as u8];
+                for register in registers {
+                    response.extend_from_slice(&register.to_be_bytes());
+                }
+                Ok(response)
+            }
+            0x06 => {
+                let address = word(1)?;
+                let value = word(3)?;
+
+                self.write_holding_registers(address, &[value]).await?;
+
+                Ok(pdu[0..5].to_vec())
+            }
+            0x10 => {
+                let address = word(1)?;
+                let count = word(3)?;
+                if !(1..=123).contains(&count) || pdu.get(5) != Some(&((count * 2) as u8)) {
+                    return Err(ILLEGAL_DATA_VALUE);
+                }
+
+                let values = (0..usize::from(count))
+                    .map(|idx| word(6 + idx * 2))
+                    .collect::<std::result::Result<Vec<_>, _>>()?;
+
+                self.write_holding_registers(address, &values).await?;
+
+                Ok(pdu[0..5].to_vec())
+            }
+            _ => Err(ILLEGAL_FUNCTION),
+        }
+    }
+
+    async fn read_input_registers(
+        &self,
+        address: u16,
+        count: u16,
+    ) -> std::result::Result<Vec<u16>, u8> {
+        let data_set = self.data_set.lock().await.clone();
+
+        let spec = default_specification(Language::En);
+
+        let mut values = Vec::new();
+        for mapping in overlapping(&self.input_registers, address, count) {
+            let value = spec
+                .fields_in_data_set(&data_set)
+                .find(|field| field.field_spec().packet_field_id == mapping.id)
+                .and_then(|field| *field.raw_value_i64())
+                .unwrap_or_else(|| i64::from(mapping.format.missing_value()));
+            values.push((mapping, value));
+        }
+
+        collect_registers(&values, address, count)
+    }
+
+    async fn read_holding_registers(
+        &self,
+        address: u16,
+        count: u16,
+    ) -> std::result::Result<Vec<u16>, u8> {
+        let mut values = Vec::new();
+        for mapping in overlapping(&self.holding_registers, address, count) {
+            let (_, value) = request_param(&self.param_sender, &mapping.id, None, None)
+                .await
+                .map_err(|_| SERVER_DEVICE_FAILURE)?;
+            values.push((mapping, i64::from(value)));
+        }
+
+        collect_registers(&values, address, count)
+    }
+
+    async fn write_holding_registers(
+        &self,
+        address: u16,
+        registers: &[u16],
+    ) -> std::result::Result<(), u8> {
+        let start = u32::from(address);
+        let end = start + registers.len() as u32;
+
+        let mappings = overlapping(&self.holding_registers, address, registers.len() as u16);
+
+        let covered = mappings
+            .iter()
+            .map(|mapping| u32::from(mapping.format.register_count()))
+            .sum::<u32>();
+        let is_valid = mappings.iter().all(|mapping| {
+            mapping.writable && u32::from(mapping.address) >= start && mapping.end() <= end
+        });
+        if !is_valid || covered != end - start {
+            return Err(ILLEGAL_DATA_ADDRESS);
+        }
+
+        for mapping in mappings {
+            let offset = usize::from(mapping.address - address);
+            let count = usize::from(mapping.format.register_count());
+            let value = mapping.format.decode(&registers[offset..offset + count]);
+
+            request_param(&self.param_sender, &mapping.id, Some(value), None)
+                .await
+                .map_err(|_| SERVER_DEVICE_FAILURE)?;
+        }
+
+        Ok(())
+    }
+}
+
+fn add_mapping(mappings: &mut Vec<RegisterMapping>, mapping: RegisterMapping) -> Result<()> {
+    if mapping.end() > 0x10000 {
+        return Err(format!("Register {} exceeds the address range", mapping.address).into());
+    }
+
+    let start = u32::from(mapping.address);
+    if mappings
+        .iter()
+        .any(|existing| existing.overlaps(start, mapping.end()))
+    {
+        return Err(format!("Register {} overlaps an existing mapping", mapping.address).into());
+    }
+
+    mappings.push(mapping);
+    mappings.sort_by_key(|mapping| mapping.address);
+    Ok(())
+}
+
+fn overlapping(mappings: &[RegisterMapping], address: u16, count: u16) -> Vec<&RegisterMapping> {
+    let start = u32::from(address);
+    let end = start + u32::from(count);
+    mappings
+        .iter()
+        .filter(|mapping| mapping.overlaps(start, end))
+        .collect()
+}
+
+/// Assemble the requested register range from the values of the
+/// overlapping mappings, failing if any register is not mapped.
+fn collect_registers(
+    values: &[(&RegisterMapping, i64)],
+    address: u16,
+    count: u16,
+) -> std::result::Result<Vec<u16>, u8> {
+    let mut registers = vec![None; usize::from(count)];
+    for (mapping, value) in values {
+        let words = mapping.format.encode(*value);
+        for (idx, word) in words.into_iter().enumerate() {
+            let register = u32::from(mapping.address) + idx as u32;
+            if let Some(offset) = register.checked_sub(u32::from(address)) {
+                if let Some(slot) = registers.get_mut(offset as usize) {
+                    *slot = Some(word);
+                }
+            }
+        }
+    }
+
+    registers
+        .into_iter()
+        .map(|register| register.ok_or(ILLEGAL_DATA_ADDRESS))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use async_std::{io::Cursor, net::SocketAddr};
+
+    use resol_vbus::{chrono::Utc, Header, Packet};
+
+    use super::*;
+
+    use crate::{
+        live_data_stream::LiveDataStream,
+        test_utils::{extend_from_datagram, extend_with_empty_packet},
+    };
+
+    async fn modbus_request(addr: SocketAddr, unit_id: u8, pdu: &[u8]) -> Result<Vec<u8>> {
+        let mut stream = TcpStream::connect(addr).await?;
+
+        let mut frame = vec![0x12, 0x34, 0x00, 0x00];
+        frame.extend_from_slice(&(pdu.len() as u16 + 1).to_be_bytes());
+        frame.push(unit_id);
+        frame.extend_from_slice(pdu);
+        stream.write_all(&frame).await?;
+
+        let mut header = [0u8; 7];
+        stream.read_exact(&mut header).await?;
+        assert_eq!([0x12, 0x34, 0x00, 0x00], header[0..4]);
+        assert_eq!(unit_id, header[6]);
+
+        let length = usize::from(u16::from_be_bytes([header[4], header[5]]));
+        let mut response = vec![0u8; length - 1];
+        stream.read_exact(&mut response).await?;
+        Ok(response)
+    }
+
+    #[test]
+    fn test_register_format() {
+        assert_eq!(vec![0xFFFF], RegisterFormat::Int16.encode(-1));
+        assert_eq!(vec![0x7FFF], RegisterFormat::Int16.encode(100000));
+        assert_eq!(vec![0x0001, 0x86A0], RegisterFormat::Int32.encode(100000));
+        assert_eq!(-1, RegisterFormat::Int16.decode(&[0xFFFF]));
+        assert_eq!(100000, RegisterFormat::Int32.decode(&[0x0001, 0x86A0]));
+    }
+
+    #[test]
+    fn test_add_mapping() -> Result<()> {
+        let (mut server, _param_requests) = ModbusServer::new();
+        server.add_input_register(0, "a", RegisterFormat::Int32)?;
+        server.add_input_register(2, "b", RegisterFormat::Int16)?;
+        assert_eq!(
+            Err("Register 1 overlaps an existing mapping".into()),
+            server.add_input_register(1, "c", RegisterFormat::Int16)
+        );
+        assert_eq!(
+            Err("Register 65535 exceeds the address range".into()),
+            server.add_holding_register(0xFFFF, "d", RegisterFormat::Int32, false)
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_input_registers() -> Result<()> {
+        async_std::task::block_on(async {
+            let listener = TcpListener::bind("127.0.0.1:0").await?;
+            let addr = listener.local_addr()?;
+
+            let (mut server, _param_requests) = ModbusServer::new();
+            server.set_unit_id(Some(1));
+            server.add_input_register(0, "00_0010_7E11_10_0100_000_2_0", RegisterFormat::Int16)?;
+            server.add_input_register(1, "00_0010_7E11_10_0100_002_2_0", RegisterFormat::Int32)?;
+
+            let mut frame_data = [0u8; 508];
+            frame_data[0..2].copy_from_slice(&872i16.to_le_bytes());
+            server
+                .add_data(Data::Packet(Packet {
+                    header: Header {
+                        timestamp: Utc::now(),
+                        channel: 0,
+                        destination_address: 0x0010,
+                        source_address: 0x7E11,
+                        protocol_version: 0x10,
+                    },
+                    command: 0x0100,
+                    frame_count: 1,
+                    frame_data,
+                }))
+                .await;
+
+            async_std::task::spawn(server.serve(listener));
+
+            let response = modbus_request(addr, 1, &[0x04, 0x00, 0x00, 0x00, 0x03]).await?;
+            assert_eq!(
+                vec![0x04, 0x06, 0x03, 0x68, 0x00, 0x00, 0x00, 0x00],
+                response
+            );
+
+            let response = modbus_request(addr, 1, &[0x04, 0x00, 0x02, 0x00, 0x02]).await?;
+            assert_eq!(vec![0x84, ILLEGAL_DATA_ADDRESS], response);
+
+            let response = modbus_request(addr, 2, &[0x04, 0x00, 0x00, 0x00, 0x01]).await?;
+            assert_eq!(vec![0x84, GATEWAY_TARGET_FAILED], response);
+
+            let response = modbus_request(addr, 1, &[0x01, 0x00, 0x00, 0x00, 0x01]).await?;
+            assert_eq!(vec![0x81, ILLEGAL_FUNCTION], response);
+
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn test_holding_registers() -> Result<()> {
+        async_std::task::block_on(async {
+            let listener = TcpListener::bind("127.0.0.1:0").await?;
+            let addr = listener.local_addr()?;
+
+            let (mut server, param_requests) = ModbusServer::new();
+            server.add_holding_register(100, "0x1234", RegisterFormat::Int16, true)?;
+            server.add_holding_register(101, "0x1235", RegisterFormat::Int16, false)?;
+
+            async_std::task::spawn(server.serve(listener));
+
+            let response = modbus_request(addr, 1, &[0x06, 0x00, 0x65, 0x00, 0x01]).await?;
+            assert_eq!(vec![0x86, ILLEGAL_DATA_ADDRESS], response);
+
+            let client_future = async_std::task::spawn(async move {
+                modbus_request(addr, 1, &[0x06, 0x00, 0x64, 0x01, 0xC8]).await
+            });
+
+            let request = param_requests.recv().await.unwrap();
+            assert_eq!("0x1234", request.id());
+            assert_eq!(Some(456), request.value());
+
+            let mut rx_buf = Vec::new();
+            extend_from_datagram(&mut rx_buf, 0x0000, 0x7E11, 0x0500, 0, 0);
+            extend_from_datagram(&mut rx_buf, 0x0020, 0x7E11, 0x0100, 0x1234, 456);
+            extend_with_empty_packet(&mut rx_buf, 0x0010, 0x7E11, 0x0100);
+
+            let mut lds = LiveDataStream::new(&rx_buf[..], Cursor::new(Vec::new()), 0, 0x0020);
+
+            request.process(&mut lds, 0x7E11).await?;
+
+            let response = client_future.await?;
+            assert_eq!(vec![0x06, 0x00, 0x64, 0x01, 0xC8], response);
+
+            drop(param_requests);
+
+            let response = modbus_request(addr, 1, &[0x03, 0x00, 0x64, 0x00, 0x01]).await?;
+            assert_eq!(vec![0x83, SERVER_DEVICE_FAILURE], response);
+
+            Ok(())
+        })
+    }
+}
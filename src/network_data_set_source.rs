@@ -0,0 +1,172 @@
+use std::{
+    marker::Unpin,
+    net::SocketAddr,
+    time::{Duration, Instant},
+};
+
+use async_std::{
+    io::{Read, Write},
+    net::TcpStream,
+};
+
+use resol_vbus::{chrono::Utc, DataSet};
+
+use crate::{
+    connect::{connect_tcp_live_data_stream, ConnectOptions},
+    error::Result,
+    live_data_stream::LiveDataStream,
+};
+
+/// Reads `DataSet`s from a live VBus connection, similar to reading them
+/// from a recording using `RecordingReader::read_data_set`.
+///
+/// All `Data` received is accumulated into a `DataSet`, which is returned
+/// once per `interval`, timestamped with the time it was returned.
+///
+/// # Examples
+///
+/// ```no_run
+/// # fn main() -> async_resol_vbus::Result<()> { async_std::task::block_on(async {
+/// #
+/// use std::time::Duration;
+///
+/// use async_resol_vbus::{ConnectOptions, NetworkDataSetSource};
+///
+/// let address = "192.168.5.217:7053".parse()?;
+/// let mut source =
+///     NetworkDataSetSource::connect(address, &ConnectOptions::new(), Duration::from_secs(10)).await?;
+///
+/// while let Some(data_set) = source.read_data_set().await? {
+///     println!("{}: {} data", data_set.timestamp, data_set.len());
+/// }
+/// #
+/// # Ok(()) }) }
+/// ```
+#[derive(Debug)]
+pub struct NetworkDataSetSource<R: Read + Unpin, W: Write + Unpin> {
+    lds: LiveDataStream<R, W>,
+    interval: Duration,
+    max_age: Option<Duration>,
+    data_set: DataSet,
+    deadline: Option<Instant>,
+    done: bool,
+}
+
+impl NetworkDataSetSource<TcpStream, TcpStream> {
+    /// Connect to the VBus-over-TCP service at `address` and create a
+    /// `NetworkDataSetSource` using the resulting `LiveDataStream`.
+    pub async fn connect(
+        address: SocketAddr,
+        options: &ConnectOptions,
+        interval: Duration,
+    ) -> Result<NetworkDataSetSource<TcpStream, TcpStream>> {
+        let lds = connect_tcp_live_data_stream(address, options).await?;
+        Ok(NetworkDataSetSource::new(lds, interval))
+    }
+}
+
+impl<R: Read + Unpin, W: Write + Unpin> NetworkDataSetSource<R, W> {
+    /// Create a new `NetworkDataSetSource` returning a `DataSet` every
+    /// `interval`.
+    pub fn new(lds: LiveDataStream<R, W>, interval: Duration) -> NetworkDataSetSource<R, W> {
+        NetworkDataSetSource {
+            lds,
+            interval,
+            max_age: None,
+            data_set: DataSet::new(),
+            deadline: None,
+            done: false,
+        }
+    }
+
+    /// Set the maximum age of `Data` included in the returned `DataSet`s.
+    ///
+    /// Defaults to `None`, which keeps the last received `Data` of every
+    /// kind, even if the device stopped sending it.
+    pub fn set_max_age(&mut self, max_age: Option<Duration>) {
+        self.max_age = max_age;
+    }
+
+    /// Receive data until the next interval has passed and return the
+    /// accumulated `DataSet`.
+    ///
+    /// Returns `None` after the connection was closed and the remaining
+    /// data was returned.
+    pub async fn read_data_set(&mut self) -> Result<Option<DataSet>> {
+        if self.done {
+            return Ok(None);
+        }
+
+        let now = Instant::now();
+        let deadline = match self.deadline {
+            Some(deadline) if deadline > now => deadline,
+            _ => now + self.interval,
+        };
+
+        loop {
+            match self.lds.receive_until(deadline, |_| true).await? {
+                Some(data) => self.data_set.add_data(data),
+                None => {
+                    self.done = Instant::now() < deadline;
+                    break;
+                }
+            }
+        }
+
+        self.deadline = Some(deadline + self.interval);
+
+        let timestamp = Utc::now();
+        if let Some(max_age) = self.max_age {
+            if let Ok(max_age) = resol_vbus::chrono::Duration::from_std(max_age) {
+                self.data_set.remove_data_older_than(timestamp - max_age);
+            }
+        }
+
+        if self.done && self.data_set.is_empty() {
+            return Ok(None);
+        }
+
+        let mut data_set = self.data_set.clone();
+        data_set.timestamp = timestamp;
+        Ok(Some(data_set))
+    }
+
+    /// Consume `self` and return the underlying `LiveDataStream`.
+    pub fn into_inner(self) -> LiveDataStream<R, W> {
+        self.lds
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use async_std::io::Cursor;
+
+    use super::*;
+
+    use crate::test_utils::{extend_from_datagram, extend_with_empty_packet, simulate_run};
+
+    #[test]
+    fn test_read_data_set() {
+        let mut rx_buf = Vec::new();
+        extend_with_empty_packet(&mut rx_buf, 0x0010, 0x7E11, 0x0100);
+        extend_from_datagram(&mut rx_buf, 0x0000, 0x7E11, 0x0500, 0, 0);
+        extend_with_empty_packet(&mut rx_buf, 0x0010, 0x7E11, 0x0100);
+
+        let lds = LiveDataStream::new(&rx_buf[..], Cursor::new(Vec::new()), 0, 0x0020);
+
+        let mut source = NetworkDataSetSource::new(lds, Duration::from_secs(10));
+
+        let data_set = simulate_run(source.read_data_set()).unwrap().unwrap();
+
+        let ids = data_set
+            .iter()
+            .map(|data| data.id_string())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            vec!["00_0010_7E11_10_0100", "00_0000_7E11_20_0500_0000"],
+            ids
+        );
+
+        assert!(simulate_run(source.read_data_set()).unwrap().is_none());
+    }
+}
@@ -0,0 +1,242 @@
+use std::{
+    net::{Ipv4Addr, SocketAddr, SocketAddrV4},
+    time::Duration,
+};
+
+use async_std::net::TcpStream;
+
+use crate::{device_information::DeviceInformation, error::Result, service_probe::read_line};
+
+/// Scans a range of IPv4 addresses for VBus-over-TCP services.
+///
+/// This is a fallback for networks where the broadcasts used by
+/// `DeviceDiscovery` are blocked (e.g. by some WiFi access points). Every
+/// host in the range is connected on port 7053 and must greet with a
+/// `+HELLO` banner to be considered a VBus-over-TCP device.
+///
+/// # Examples
+///
+/// ```no_run
+/// # fn main() -> async_resol_vbus::Result<()> { async_std::task::block_on(async {
+/// #
+/// use async_resol_vbus::NetworkScanner;
+///
+/// let scanner = NetworkScanner::from_cidr("192.168.5.0/24")?;
+/// let devices = scanner.scan_devices().await?;
+/// #
+/// # Ok(()) }) }
+/// ```
+#[derive(Debug, Clone)]
+pub struct NetworkScanner {
+    network: Ipv4Addr,
+    prefix_len: u8,
+    port: u16,
+    connect_timeout: Duration,
+    concurrency: usize,
+    fetch_port: u16,
+    fetch_timeout: Duration,
+}
+
+impl NetworkScanner {
+    /// Create a new `NetworkScanner` for the given network.
+    pub fn new(network: Ipv4Addr, prefix_len: u8) -> Result<NetworkScanner> {
+        if prefix_len > 32 {
+            return Err(format!("Invalid network prefix length {}", prefix_len).into());
+        }
+
+        Ok(NetworkScanner {
+            network,
+            prefix_len,
+            port: 7053,
+            connect_timeout: Duration::from_millis(500),
+            concurrency: 32,
+            fetch_port: 80,
+            fetch_timeout: Duration::from_millis(2000),
+        })
+    }
+
+    /// Create a new `NetworkScanner` for a network in CIDR notation
+    /// (e.g. `192.168.5.0/24`).
+    pub fn from_cidr(cidr: &str) -> Result<NetworkScanner> {
+        let (network, prefix_len) = match cidr.find('/') {
+            Some(idx) => (&cidr[0..idx], &cidr[idx + 1..]),
+            None => (cidr, "32"),
+        };
+
+        let network = network.parse::<Ipv4Addr>()?;
+        let prefix_len = prefix_len
+            .parse::<u8>()
+            .map_err(|_| format!("Invalid network prefix length {:?}", prefix_len))?;
+
+        NetworkScanner::new(network, prefix_len)
+    }
+
+    /// Set the port the VBus-over-TCP service is expected on.
+    pub fn set_port(&mut self, port: u16) {
+        self.port = port;
+    }
+
+    /// Set the timeout for connecting to a host and receiving its banner.
+    pub fn set_connect_timeout(&mut self, timeout: Duration) {
+        self.connect_timeout = timeout;
+    }
+
+    /// Set the maximum number of hosts that are probed at the same time.
+    pub fn set_concurrency(&mut self, concurrency: usize) {
+        self.concurrency = concurrency.max(1);
+    }
+
+    /// Set the port number used for fetching the device information.
+    pub fn set_fetch_port(&mut self, port: u16) {
+        self.fetch_port = port;
+    }
+
+    /// Set the timeout used for fetching the device information.
+    pub fn set_fetch_timeout(&mut self, timeout: Duration) {
+        self.fetch_timeout = timeout;
+    }
+
+    /// Get the host addresses within the network.
+    ///
+    /// For networks with more than two addresses the network and broadcast
+    /// addresses are excluded.
+    pub fn hosts(&self) -> Vec<Ipv4Addr> {
+        let mask = match self.prefix_len {
+            0 => 0,
+            len => u32::MAX << (32 - len),
+        };
+        let first = u32::from(self.network) & mask;
+        let last = first | !mask;
+
+        let (first, last) = if last - first >= 2 {
+            (first + 1, last - 1)
+        } else {
+            (first, last)
+        };
+
+        (first..=last).map(Ipv4Addr::from).collect()
+    }
+
+    /// Scan the network and return the addresses of all VBus-over-TCP services.
+    pub async fn scan_addresses(&self) -> Result<Vec<SocketAddr>> {
+        let mut addresses = Vec::new();
+        for chunk in self.hosts().chunks(self.concurrency) {
+            let tasks = chunk
+                .iter()
+                .map(|host| {
+                    let address = SocketAddr::V4(SocketAddrV4::new(*host, self.port));
+                    let timeout = self.connect_timeout;
+                    async_std::task::spawn(async move {
+                        has_vbus_banner(address, timeout).await.then_some(address)
+                    })
+                })
+                .collect::<Vec<_>>();
+
+            for task in tasks {
+                if let Some(address) = task.await {
+                    addresses.push(address);
+                }
+            }
+        }
+
+        Ok(addresses)
+    }
+
+    /// Scan the network and return the device information of all
+    /// VBus-over-TCP devices.
+    pub async fn scan_devices(&self) -> Result<Vec<DeviceInformation>> {
+        let addresses = self.scan_addresses().await?;
+
+        let mut devices = Vec::with_capacity(addresses.len());
+        for mut address in addresses {
+            address.set_port(self.fetch_port);
+
+            if let Ok(device) = DeviceInformation::fetch(address, self.fetch_timeout).await {
+                devices.push(device);
+            }
+        }
+
+        Ok(devices)
+    }
+}
+
+async fn has_vbus_banner(address: SocketAddr, timeout: Duration) -> bool {
+    let result = async_std::future::timeout(timeout, async {
+        let mut stream = TcpStream::connect(address).await?;
+        read_line(&mut stream).await
+    })
+    .await;
+
+    match result {
+        Ok(Ok(Some(banner))) => banner.starts_with("+HELLO"),
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use async_std::{net::TcpListener, prelude::*};
+
+    use super::*;
+
+    use crate::test_utils::create_webserver;
+
+    #[test]
+    fn test_hosts() -> Result<()> {
+        let scanner = NetworkScanner::from_cidr("192.168.5.17/30")?;
+        assert_eq!(
+            vec![
+                Ipv4Addr::new(192, 168, 5, 17),
+                Ipv4Addr::new(192, 168, 5, 18)
+            ],
+            scanner.hosts()
+        );
+
+        assert_eq!(254, NetworkScanner::from_cidr("10.0.0.0/24")?.hosts().len());
+        assert_eq!(1, NetworkScanner::from_cidr("10.0.0.7")?.hosts().len());
+        assert!(NetworkScanner::from_cidr("10.0.0.0/33").is_err());
+        assert!(NetworkScanner::from_cidr("10.0.0/24").is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_scan() -> Result<()> {
+        async_std::task::block_on(async {
+            let vbus_socket = TcpListener::bind("127.0.0.1:0").await?;
+            let vbus_addr = vbus_socket.local_addr()?;
+
+            let web_socket = TcpListener::bind("127.0.0.1:0").await?;
+            let web_addr = web_socket.local_addr()?;
+
+            let vbus_future = async_std::task::spawn::<_, Result<()>>(async move {
+                loop {
+                    let (mut stream, _) = vbus_socket.accept().await?;
+                    stream.write_all(b"+HELLO\n").await?;
+                }
+            });
+
+            let web_future =
+                async_std::task::spawn(async move { create_webserver(web_socket).await });
+
+            let mut scanner = NetworkScanner::from_cidr("127.0.0.1/32")?;
+            scanner.set_port(vbus_addr.port());
+            scanner.set_connect_timeout(Duration::from_millis(200));
+            scanner.set_fetch_port(web_addr.port());
+
+            assert_eq!(vec![vbus_addr], scanner.scan_addresses().await?);
+
+            let devices = scanner.scan_devices().await?;
+            assert_eq!(1, devices.len());
+            assert_eq!(Some("DL2"), devices[0].product.as_deref());
+
+            scanner.set_port(web_addr.port());
+            assert!(scanner.scan_addresses().await?.is_empty());
+
+            drop(vbus_future);
+            drop(web_future);
+
+            Ok(())
+        })
+    }
+}
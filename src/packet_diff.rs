@@ -0,0 +1,279 @@
+use std::{collections::HashMap, marker::Unpin, ops::Range};
+
+use async_std::io::{Read, Write};
+
+use resol_vbus::{Data, Packet, PacketId};
+
+#[cfg(feature = "specification")]
+use resol_vbus::Specification;
+
+use crate::{error::Result, live_data_stream::LiveDataStream};
+
+/// The differences between the frame data of two packets with the same ID.
+///
+/// # Examples
+///
+/// ```
+/// # fn main() -> async_resol_vbus::Result<()> {
+/// use async_resol_vbus::{Header, Packet, PacketDiff};
+///
+/// let old = Packet {
+///     header: Header::default(),
+///     command: 0x0100,
+///     frame_count: 2,
+///     frame_data: [0u8; 508],
+/// };
+///
+/// let mut new = old.clone();
+/// new.frame_data[5] = 1;
+/// new.frame_data[6] = 2;
+///
+/// let diff = PacketDiff::new(&old, &new)?;
+/// assert_eq!(5..7, diff.ranges()[0]);
+/// assert_eq!(vec![1], diff.frames());
+/// # Ok(()) }
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct PacketDiff {
+    packet_id: PacketId,
+    ranges: Vec<Range<usize>>,
+}
+
+impl PacketDiff {
+    /// Compare the valid frame data of two packets.
+    ///
+    /// If the frame counts differ, the bytes only present in one of the
+    /// packets are considered changed. Returns an error if the packets'
+    /// IDs differ.
+    pub fn new(old: &Packet, new: &Packet) -> Result<PacketDiff> {
+        let packet_id = new.packet_id();
+        if old.packet_id() != packet_id {
+            return Err("Packet IDs do not match".into());
+        }
+
+        let old_len = old.valid_frame_data_len();
+        let new_len = new.valid_frame_data_len();
+
+        let mut ranges: Vec<Range<usize>> = Vec::new();
+        for idx in 0..old_len.max(new_len) {
+            let changed =
+                idx >= old_len || idx >= new_len || old.frame_data[idx] != new.frame_data[idx];
+            if changed {
+                match ranges.last_mut() {
+                    Some(range) if range.end == idx => range.end = idx + 1,
+                    _ => ranges.push(idx..idx + 1),
+                }
+            }
+        }
+
+        Ok(PacketDiff { packet_id, ranges })
+    }
+
+    /// Get the ID of the compared packets.
+    pub fn packet_id(&self) -> PacketId {
+        self.packet_id
+    }
+
+    /// Check whether the frame data is unchanged.
+    pub fn is_empty(&self) -> bool {
+        self.ranges.is_empty()
+    }
+
+    /// Get the byte ranges within the frame data that changed.
+    pub fn ranges(&self) -> &[Range<usize>] {
+        &self.ranges
+    }
+
+    /// Get the indices of the frames (4 bytes each) that changed.
+    pub fn frames(&self) -> Vec<usize> {
+        let mut frames: Vec<usize> = Vec::new();
+        for range in &self.ranges {
+            for frame in (range.start / 4)..=((range.end - 1) / 4) {
+                if frames.last() != Some(&frame) {
+                    frames.push(frame);
+                }
+            }
+        }
+        frames
+    }
+
+    /// Get the packet field IDs of all known fields affected by the changes.
+    #[cfg(feature = "specification")]
+    pub fn packet_field_ids(&self, spec: &Specification) -> Vec<String> {
+        let packet_spec = spec.get_packet_spec_by_id(self.packet_id);
+
+        packet_spec
+            .fields
+            .iter()
+            .filter(|field| {
+                field.parts.iter().any(|part| {
+                    let offset = part.offset as usize;
+                    self.ranges.iter().any(|range| range.contains(&offset))
+                })
+            })
+            .map(|field| field.packet_field_id.clone())
+            .collect()
+    }
+}
+
+/// Watches a `LiveDataStream` for changes in the packets' frame data.
+///
+/// The watcher remembers the last packet for every packet ID and reports
+/// the differences whenever a packet with the same ID is received again.
+/// This helps to find out which bytes of a packet are affected by a
+/// changed setting.
+///
+/// # Examples
+///
+/// ```no_run
+/// # fn main() -> async_resol_vbus::Result<()> { async_std::task::block_on(async {
+/// #
+/// use async_std::net::TcpStream;
+///
+/// use async_resol_vbus::{LiveDataStream, PacketWatcher};
+///
+/// let stream = TcpStream::connect("192.168.5.217:7053").await?;
+/// // ... perform handshake ...
+/// let mut lds = LiveDataStream::from_tcp_stream(stream, 0, 0x0020);
+///
+/// let mut watcher = PacketWatcher::new();
+/// while let Some((packet, diff)) = watcher.next_diff(&mut lds, 60000).await? {
+///     println!("{}: {:?}", packet.id_string(), diff.ranges());
+/// }
+/// #
+/// # Ok(()) }) }
+/// ```
+#[derive(Debug, Default)]
+pub struct PacketWatcher {
+    packets: HashMap<PacketId, Packet>,
+}
+
+impl PacketWatcher {
+    /// Create a new `PacketWatcher`.
+    pub fn new() -> PacketWatcher {
+        PacketWatcher {
+            packets: HashMap::new(),
+        }
+    }
+
+    /// Remember `data` if it is a packet and return the differences to the
+    /// previously seen packet with the same ID.
+    ///
+    /// Returns `None` for non-packet data and the first packet of every ID.
+    pub fn push(&mut self, data: &Data) -> Option<PacketDiff> {
+        if !data.is_packet() {
+            return None;
+        }
+
+        let packet = data.as_packet();
+        let old = self.packets.insert(packet.packet_id(), packet.clone())?;
+
+        PacketDiff::new(&old, packet).ok()
+    }
+
+    /// Receive data from `stream` until a packet with changed frame data
+    /// is found.
+    ///
+    /// Returns `None` if no data was received within `timeout_ms`.
+    pub async fn next_diff<R: Read + Unpin, W: Write + Unpin>(
+        &mut self,
+        stream: &mut LiveDataStream<R, W>,
+        timeout_ms: u64,
+    ) -> Result<Option<(Packet, PacketDiff)>> {
+        while let Some(data) = stream.receive_any_data(timeout_ms).await? {
+            if let Some(diff) = self.push(&data) {
+                if !diff.is_empty() {
+                    return Ok(Some((data.into_packet(), diff)));
+                }
+            }
+        }
+
+        Ok(None)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use async_std::io::Cursor;
+
+    use resol_vbus::Header;
+
+    use super::*;
+
+    use crate::test_utils::{extend_from_data, simulate_run};
+
+    fn packet(frame_count: u8, bytes: &[(usize, u8)]) -> Packet {
+        let mut frame_data = [0u8; 508];
+        for (idx, value) in bytes {
+            frame_data[*idx] = *value;
+        }
+
+        Packet {
+            header: Header {
+                destination_address: 0x0010,
+                source_address: 0x7E11,
+                protocol_version: 0x10,
+                ..Header::default()
+            },
+            command: 0x0100,
+            frame_count,
+            frame_data,
+        }
+    }
+
+    #[test]
+    fn test_packet_diff() {
+        let old = packet(2, &[(1, 1)]);
+        let new = packet(3, &[(0, 1), (3, 1), (4, 1)]);
+
+        let diff = PacketDiff::new(&old, &new).unwrap();
+
+        assert_eq!(&[0..2, 3..5, 8..12], diff.ranges());
+        assert_eq!(vec![0, 1, 2], diff.frames());
+        assert!(PacketDiff::new(&old, &old).unwrap().is_empty());
+
+        let mut other = packet(2, &[]);
+        other.command = 0x0200;
+        assert_eq!(
+            Err("Packet IDs do not match".into()),
+            PacketDiff::new(&old, &other)
+        );
+    }
+
+    #[cfg(feature = "specification")]
+    #[test]
+    fn test_packet_field_ids() {
+        use resol_vbus::{Language, SpecificationFile};
+
+        let old = packet(2, &[(1, 1)]);
+
+        let spec = Specification::from_file(SpecificationFile::new_default(), Language::En);
+        let ids = PacketDiff::new(&old, &packet(2, &[(1, 1), (2, 1)]))
+            .unwrap()
+            .packet_field_ids(&spec);
+        assert_eq!(vec!["00_0010_7E11_10_0100_002_2_0".to_string()], ids);
+    }
+
+    #[test]
+    fn test_packet_watcher() {
+        let mut rx_buf = Vec::new();
+        extend_from_data(&mut rx_buf, &Data::Packet(packet(1, &[(0, 1)])));
+        extend_from_data(&mut rx_buf, &Data::Packet(packet(1, &[(0, 1)])));
+        extend_from_data(&mut rx_buf, &Data::Packet(packet(1, &[(0, 2)])));
+
+        let mut lds = LiveDataStream::new(&rx_buf[..], Cursor::new(Vec::new()), 0, 0x0020);
+
+        let mut watcher = PacketWatcher::new();
+
+        let (packet, diff) = simulate_run(watcher.next_diff(&mut lds, 100))
+            .unwrap()
+            .unwrap();
+        assert_eq!(2, packet.frame_data[0]);
+        assert_eq!(1, diff.ranges().len());
+        assert_eq!(0..1, diff.ranges()[0]);
+
+        assert!(simulate_run(watcher.next_diff(&mut lds, 100))
+            .unwrap()
+            .is_none());
+    }
+}
@@ -0,0 +1,255 @@
+use std::{
+    marker::Unpin,
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+
+use async_std::io::{Read, Write};
+
+use resol_vbus::{Data, PacketId, ToPacketId};
+
+use crate::{
+    data_builder::PacketBuilder, error::Result, shared_live_data_stream::SharedLiveDataStream,
+};
+
+/// A callback producing the frame data payload of an injected packet.
+pub type PayloadProvider = Box<dyn FnMut() -> Vec<u8> + Send>;
+
+struct InjectedPacket {
+    packet_id: PacketId,
+    interval: Duration,
+    provider: PayloadProvider,
+    due: Instant,
+}
+
+/// Periodically sends configured packets, emulating a data-producing VBus
+/// device.
+///
+/// Every packet is identified by its packet ID and sent every `interval`,
+/// using the payload returned by its provider at the time of sending. A
+/// jitter can be configured to randomly shift every emission, so that
+/// multiple emulated devices do not stay in lockstep. Use it together with a
+/// `DatagramResponder` to fully emulate a controller.
+///
+/// # Examples
+///
+/// ```no_run
+/// # fn main() -> async_resol_vbus::Result<()> { async_std::task::block_on(async {
+/// #
+/// use std::time::Duration;
+///
+/// use async_std::net::TcpStream;
+///
+/// use async_resol_vbus::{LiveDataStream, PacketInjector, SharedLiveDataStream};
+///
+/// let stream = TcpStream::connect("192.168.5.217:7053").await?;
+/// // ... perform handshake ...
+/// let lds = SharedLiveDataStream::new(LiveDataStream::from_tcp_stream(stream, 0, 0x7E11));
+///
+/// let mut injector = PacketInjector::new();
+/// injector.set_jitter(Duration::from_millis(100));
+/// injector.add_packet("00_0010_7E11_10_0100", Duration::from_secs(1), || {
+///     vec![0xC7, 0x01, 0x00, 0x00]
+/// })?;
+///
+/// async_std::task::spawn(injector.run(lds));
+/// #
+/// # Ok(()) }) }
+/// ```
+#[derive(Default)]
+pub struct PacketInjector {
+    packets: Vec<InjectedPacket>,
+    jitter: Duration,
+    rng_state: u64,
+}
+
+impl PacketInjector {
+    /// Create a new `PacketInjector` without packets.
+    pub fn new() -> PacketInjector {
+        PacketInjector::default()
+    }
+
+    /// Set the maximum random deviation from the interval of every emission.
+    ///
+    /// Defaults to zero, which sends packets exactly every `interval`.
+    pub fn set_jitter(&mut self, jitter: Duration) {
+        self.jitter = jitter;
+    }
+
+    /// Add a packet. It is sent for the first time as soon as `run` starts.
+    ///
+    /// The `id` can be a packet ID string like `00_0010_7E11_10_0100` or a
+    /// `PacketId`.
+    pub fn add_packet<T, F>(&mut self, id: &T, interval: Duration, provider: F) -> Result<()>
+    where
+        T: ToPacketId + ?Sized,
+        F: FnMut() -> Vec<u8> + Send + 'static,
+    {
+        self.packets.push(InjectedPacket {
+            packet_id: id.to_packet_id()?,
+            interval,
+            provider: Box::new(provider),
+            due: Instant::now(),
+        });
+        Ok(())
+    }
+
+    /// Send the packets until an error occurs.
+    ///
+    /// Returns immediately if no packets were added.
+    pub async fn run<R: Read + Unpin, W: Write + Unpin>(
+        mut self,
+        stream: SharedLiveDataStream<R, W>,
+    ) -> Result<()> {
+        while !self.packets.is_empty() {
+            let next = self.packets.iter().map(|packet| packet.due).min().unwrap();
+            let now = Instant::now();
+            if next > now {
+                async_std::task::sleep(next - now).await;
+            }
+
+            self.send_due(&stream).await?;
+        }
+
+        Ok(())
+    }
+
+    async fn send_due<R: Read + Unpin, W: Write + Unpin>(
+        &mut self,
+        stream: &SharedLiveDataStream<R, W>,
+    ) -> Result<()> {
+        let now = Instant::now();
+
+        let mut lds = stream.lock().await;
+
+        for index in 0..self.packets.len() {
+            if self.packets[index].due > now {
+                continue;
+            }
+
+            let offset = self.next_jitter_offset();
+
+            let packet = &mut self.packets[index];
+            packet.due = match offset {
+                Some((offset, true)) => now + packet.interval + offset,
+                Some((offset, false)) => now + packet.interval.saturating_sub(offset),
+                None => now + packet.interval,
+            };
+
+            let PacketId(channel, destination_address, source_address, command) = packet.packet_id;
+            let payload = (packet.provider)();
+
+            let packet = PacketBuilder::new(destination_address, command)
+                .channel(channel)
+                .source_address(source_address)
+                .payload(&payload)
+                .build()?;
+
+            lds.send_data(&Data::Packet(packet)).await?;
+        }
+
+        Ok(())
+    }
+
+    /// Get a random offset up to the jitter and whether it is added to or
+    /// subtracted from the interval.
+    fn next_jitter_offset(&mut self) -> Option<(Duration, bool)> {
+        let jitter = self.jitter.as_micros() as u64;
+        if jitter == 0 {
+            return None;
+        }
+
+        if self.rng_state == 0 {
+            self.rng_state = SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|duration| duration.as_nanos() as u64)
+                .unwrap_or(0)
+                | 1;
+        }
+
+        // xorshift64
+        let mut x = self.rng_state;
+        x ^= x << 13;
+        x ^= x >> 7;
+        x ^= x << 17;
+        self.rng_state = x;
+
+        let offset = Duration::from_micros((x >> 1) % (jitter + 1));
+        Some((offset, x & 1 != 0))
+    }
+}
+
+impl std::fmt::Debug for PacketInjector {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let packets = self
+            .packets
+            .iter()
+            .map(|packet| (packet.packet_id.packet_id_string(), packet.interval))
+            .collect::<Vec<_>>();
+        f.debug_struct("PacketInjector")
+            .field("packets", &packets)
+            .field("jitter", &self.jitter)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use async_std::io::Cursor;
+
+    use super::*;
+
+    use crate::{live_data_stream::LiveDataStream, test_utils::hex_encode};
+
+    #[test]
+    fn test_packet_injector() -> Result<()> {
+        let lds = LiveDataStream::new(&[][..], Cursor::new(Vec::new()), 0, 0x0020);
+        let stream = SharedLiveDataStream::new(lds);
+
+        let mut counter = 0u8;
+
+        let mut injector = PacketInjector::new();
+        injector.set_jitter(Duration::from_millis(10));
+        injector.add_packet("00_0010_7E11_10_0100", Duration::from_secs(60), move || {
+            counter += 1;
+            vec![counter, 0, 0, 0]
+        })?;
+        injector.add_packet(
+            &PacketId(0, 0x0015, 0x7E11, 0x0100),
+            Duration::from_secs(60),
+            Vec::new,
+        )?;
+
+        assert!(injector
+            .add_packet("invalid", Duration::from_secs(1), Vec::new)
+            .is_err());
+
+        async_std::task::block_on(async {
+            let result = async_std::future::timeout(
+                Duration::from_millis(100),
+                injector.run(stream.clone()),
+            )
+            .await;
+            assert!(result.is_err());
+
+            let lds = stream.lock().await;
+            assert_eq!(
+                "aa1000117e100001014e01000000007eaa1500117e100001004a",
+                hex_encode(lds.writer_ref())
+            );
+        });
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_jitter() {
+        let mut injector = PacketInjector::new();
+        assert_eq!(None, injector.next_jitter_offset());
+
+        injector.set_jitter(Duration::from_millis(5));
+        for _ in 0..100 {
+            let (offset, _) = injector.next_jitter_offset().unwrap();
+            assert!(offset <= Duration::from_millis(5));
+        }
+    }
+}
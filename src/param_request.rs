@@ -0,0 +1,156 @@
+use std::marker::Unpin;
+
+use async_std::{
+    channel::Sender,
+    io::{Read, Write},
+};
+
+#[cfg(feature = "otel")]
+use crate::trace_context::TraceContext;
+use crate::{
+    error::Result,
+    live_data_stream::LiveDataStream,
+    write_guard::{write_denied_error, WriteGuard},
+};
+
+/// A request to get or set a parameter, created by the `HttpApi` or the
+/// `ModbusServer` and processed by the task owning the `LiveDataStream`.
+#[derive(Debug)]
+pub struct ParamRequest {
+    id: String,
+    value: Option<i32>,
+    reply: Sender<Result<(i16, i32)>>,
+    write_guard: Option<WriteGuard>,
+    #[cfg(feature = "otel")]
+    trace_context: Option<TraceContext>,
+}
+
+impl ParamRequest {
+    /// Get the index or value ID of the requested parameter.
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+
+    /// Get the value to set or `None` if the value should only be read.
+    pub fn value(&self) -> Option<i32> {
+        self.value
+    }
+
+    /// Get the `TraceContext` that was active when the request was
+    /// created.
+    #[cfg(feature = "otel")]
+    pub fn trace_context(&self) -> Option<&TraceContext> {
+        self.trace_context.as_ref()
+    }
+
+    /// Perform the request by acquiring the bus of the VBus device at
+    /// `address` and send the result back to the `HttpApi`.
+    pub async fn process<R: Read + Unpin, W: Write + Unpin>(
+        self,
+        stream: &mut LiveDataStream<R, W>,
+        address: u16,
+    ) -> Result<()> {
+        let result = self.perform(stream, address).await;
+        drop(self.reply.send(result).await);
+        Ok(())
+    }
+
+    async fn perform<R: Read + Unpin, W: Write + Unpin>(
+        &self,
+        stream: &mut LiveDataStream<R, W>,
+        address: u16,
+    ) -> Result<(i16, i32)> {
+        let mut session = stream.acquire_bus(address).await?;
+
+        let index = match parse_index(&self.id) {
+            Some(index) => index,
+            None => match session.get_value_index_by_id(&self.id).await? {
+                Some(index) => index,
+                None => return Err(format!("Unknown value ID {:?}", self.id).into()),
+            },
+        };
+
+        if let (Some(_), Some(guard)) = (self.value, &self.write_guard) {
+            let id_hash = if guard.has_id_rules() {
+                session
+                    .get_value_id_hash_by_index(index)
+                    .await?
+                    .map(|dgram| dgram.param32)
+            } else {
+                None
+            };
+            if !guard.is_allowed_with_id_hash(index, id_hash) {
+                return Err(write_denied_error(index));
+            }
+        }
+
+        let rx_dgram = match self.value {
+            Some(value) => session.set_value_by_index(index, 0, value).await?,
+            None => session.get_value_by_index(index, 0).await?,
+        };
+
+        session.release().await?;
+
+        match rx_dgram {
+            Some(dgram) if dgram.command == 0x0100 => Ok((index, dgram.param32)),
+            _ => Err(format!("No reply for value index 0x{:04X}", index).into()),
+        }
+    }
+}
+
+fn parse_index(id: &str) -> Option<i16> {
+    if let Some(hex) = id.strip_prefix("0x") {
+        u16::from_str_radix(hex, 16).ok().map(|index| index as i16)
+    } else if id.starts_with(|c: char| c.is_ascii_digit()) {
+        id.parse::<u16>().ok().map(|index| index as i16)
+    } else {
+        None
+    }
+}
+
+/// Send a `ParamRequest` to the task owning the `LiveDataStream` and wait
+/// for its result.
+///
+/// If a `write_guard` is given, writes it denies fail before anything is
+/// sent to the device.
+#[cfg(feature = "specification")]
+pub(crate) async fn request_param(
+    sender: &Sender<ParamRequest>,
+    id: &str,
+    value: Option<i32>,
+    write_guard: Option<&WriteGuard>,
+) -> Result<(i16, i32)> {
+    let (reply, reply_receiver) = async_std::channel::bounded(1);
+
+    let request = ParamRequest {
+        id: id.to_string(),
+        value,
+        reply,
+        write_guard: write_guard.cloned(),
+        #[cfg(feature = "otel")]
+        trace_context: TraceContext::current(),
+    };
+
+    if sender.send(request).await.is_err() {
+        return Err("Parameter requests are not processed".into());
+    }
+
+    match reply_receiver.recv().await {
+        Ok(result) => result,
+        Err(_) => Err("Parameter request was dropped".into()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_index() {
+        assert_eq!(Some(0x1234), parse_index("0x1234"));
+        assert_eq!(Some(-1), parse_index("0xFFFF"));
+        assert_eq!(Some(42), parse_index("42"));
+        assert_eq!(None, parse_index("Relais_Handbetrieb"));
+        assert_eq!(None, parse_index("0xZZ"));
+    }
+}
@@ -0,0 +1,418 @@
+use std::{
+    marker::Unpin,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use async_std::{
+    channel::{Receiver, Sender},
+    io::{Read, Write},
+};
+
+use resol_vbus::chrono::{DateTime, Utc};
+
+use crate::{
+    controller_session::ControllerSession, error::Result,
+    shared_live_data_stream::SharedLiveDataStream,
+};
+
+/// A value polled periodically by a `PollScheduler`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PollTarget {
+    /// The VBus address of the device.
+    pub address: u16,
+
+    /// The value index, resolved from `id` if the target was created
+    /// using `PollTarget::by_id`.
+    pub index: Option<i16>,
+
+    /// The value ID, if the target was created using `PollTarget::by_id`.
+    pub id: Option<String>,
+
+    /// The value subindex.
+    pub subindex: u8,
+
+    /// The interval between two reads.
+    pub interval: Duration,
+}
+
+impl PollTarget {
+    /// Create a target reading a value by its index.
+    pub fn by_index(address: u16, index: i16, interval: Duration) -> PollTarget {
+        PollTarget {
+            address,
+            index: Some(index),
+            id: None,
+            subindex: 0,
+            interval,
+        }
+    }
+
+    /// Create a target reading a value by its value ID.
+    ///
+    /// The index is looked up once using
+    /// `ControllerSession::get_value_index_by_id`.
+    pub fn by_id(address: u16, id: &str, interval: Duration) -> PollTarget {
+        PollTarget {
+            address,
+            index: None,
+            id: Some(id.to_string()),
+            subindex: 0,
+            interval,
+        }
+    }
+
+    /// Check whether both targets read the same value, regardless of the
+    /// interval.
+    fn is_same_value(&self, other: &PollTarget) -> bool {
+        self.address == other.address
+            && self.subindex == other.subindex
+            && match (&self.id, &other.id) {
+                (Some(id), Some(other_id)) => id == other_id,
+                (None, None) => self.index == other.index,
+                _ => false,
+            }
+    }
+}
+
+/// The result of reading a `PollTarget`.
+#[derive(Debug)]
+pub struct PollResult {
+    /// The target that was read.
+    pub target: PollTarget,
+
+    /// The time the value was read.
+    pub timestamp: DateTime<Utc>,
+
+    /// The value or the error that occurred while reading it.
+    pub value: Result<i32>,
+}
+
+/// Periodically reads values that are not broadcast in packets.
+///
+/// Every time one or more `PollTarget`s are due, the scheduler locks the
+/// `SharedLiveDataStream`, acquires the bus of the respective device, reads
+/// all due values of that device and releases the bus again. The results
+/// are sent to a channel.
+///
+/// The targets can be replaced using `reload` on a clone of the scheduler
+/// while it is running, without interrupting the connection.
+///
+/// # Examples
+///
+/// ```no_run
+/// # fn main() -> async_resol_vbus::Result<()> { async_std::task::block_on(async {
+/// #
+/// use std::time::Duration;
+///
+/// use async_std::net::TcpStream;
+///
+/// use async_resol_vbus::{LiveDataStream, PollScheduler, PollTarget, SharedLiveDataStream};
+///
+/// let stream = TcpStream::connect("192.168.5.217:7053").await?;
+/// // ... perform handshake ...
+/// let lds = SharedLiveDataStream::new(LiveDataStream::from_tcp_stream(stream, 0, 0x0020));
+///
+/// let mut scheduler = PollScheduler::new();
+/// scheduler.add_target(PollTarget::by_id(0x7E11, "Relais_Handbetrieb", Duration::from_secs(60)));
+///
+/// let (sender, receiver) = async_std::channel::unbounded();
+/// async_std::task::spawn(scheduler.run(lds, sender));
+///
+/// while let Ok(result) = receiver.recv().await {
+///     println!("{:?}: {:?}", result.target.id, result.value);
+/// }
+/// #
+/// # Ok(()) }) }
+/// ```
+#[derive(Debug, Clone)]
+pub struct PollScheduler {
+    targets: Arc<Mutex<Vec<(PollTarget, Instant)>>>,
+    reload_sender: Sender<()>,
+    reload_receiver: Receiver<()>,
+}
+
+impl PollScheduler {
+    /// Create a new `PollScheduler` without targets.
+    pub fn new() -> PollScheduler {
+        let (reload_sender, reload_receiver) = async_std::channel::bounded(1);
+        PollScheduler {
+            targets: Arc::new(Mutex::new(Vec::new())),
+            reload_sender,
+            reload_receiver,
+        }
+    }
+
+    /// Add a target. It is read for the first time as soon as `run` starts.
+    pub fn add_target(&mut self, target: PollTarget) {
+        self.targets.lock().unwrap().push((target, Instant::now()));
+    }
+
+    /// Replace all targets, e.g. after the configuration changed.
+    ///
+    /// Targets that were already scheduled keep their schedule, adjusted to
+    /// a changed interval. New targets are read as soon as possible and
+    /// targets that are no longer listed are not read anymore.
+    pub fn reload(&self, targets: Vec<PollTarget>) {
+        let now = Instant::now();
+
+        let mut current = self.targets.lock().unwrap();
+        let reloaded = targets
+            .into_iter()
+            .map(|mut target| {
+                let existing = current
+                    .iter()
+                    .find(|(existing, _)| existing.is_same_value(&target));
+                let due = match existing {
+                    Some((existing, due)) => {
+                        if target.index.is_none() {
+                            target.index = existing.index;
+                        }
+                        due.checked_sub(existing.interval)
+                            .map_or(now, |last| last + target.interval)
+                    }
+                    None => now,
+                };
+                (target, due)
+            })
+            .collect();
+        *current = reloaded;
+        drop(current);
+
+        let _ = self.reload_sender.try_send(());
+    }
+
+    /// Poll the targets until the receiving side of `sender` is closed or
+    /// no targets are left.
+    pub async fn run<R: Read + Unpin, W: Write + Unpin>(
+        self,
+        stream: SharedLiveDataStream<R, W>,
+        sender: Sender<PollResult>,
+    ) -> Result<()> {
+        while !sender.is_closed() {
+            let next = match self
+                .targets
+                .lock()
+                .unwrap()
+                .iter()
+                .map(|(_, due)| *due)
+                .min()
+            {
+                Some(next) => next,
+                None => break,
+            };
+            let now = Instant::now();
+            if next > now {
+                let reloaded = self.reload_receiver.recv();
+                if async_std::future::timeout(next - now, reloaded)
+                    .await
+                    .is_ok()
+                {
+                    continue;
+                }
+            }
+
+            for result in self.poll_due(&stream).await {
+                if sender.send(result).await.is_err() {
+                    return Ok(());
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    async fn poll_due<R: Read + Unpin, W: Write + Unpin>(
+        &self,
+        stream: &SharedLiveDataStream<R, W>,
+    ) -> Vec<PollResult> {
+        let now = Instant::now();
+        let due_targets = {
+            let targets = self.targets.lock().unwrap();
+            let address = match targets.iter().find(|(_, due)| *due <= now) {
+                Some((target, _)) => target.address,
+                None => return Vec::new(),
+            };
+            targets
+                .iter()
+                .filter(|(target, due)| target.address == address && *due <= now)
+                .map(|(target, _)| target.clone())
+                .collect::<Vec<_>>()
+        };
+        let address = due_targets[0].address;
+
+        let mut results = Vec::new();
+
+        let mut lds = stream.lock().await;
+        let mut session = lds.acquire_bus(address).await.ok();
+
+        for mut target in due_targets {
+            let value = match session {
+                Some(ref mut session) => read_target(session, &mut target).await,
+                None => Err(format!("Unable to acquire bus from 0x{:04X}", address).into()),
+            };
+
+            results.push(PollResult {
+                target,
+                timestamp: Utc::now(),
+                value,
+            });
+        }
+
+        if let Some(session) = session {
+            drop(session.release().await);
+        }
+
+        // targets might have been reloaded in the meantime
+        let mut targets = self.targets.lock().unwrap();
+        for result in results.iter() {
+            let scheduled = targets
+                .iter_mut()
+                .find(|(target, _)| target.is_same_value(&result.target));
+            if let Some((target, due)) = scheduled {
+                *due = now + target.interval;
+                if target.index.is_none() {
+                    target.index = result.target.index;
+                }
+            }
+        }
+
+        results
+    }
+}
+
+impl Default for PollScheduler {
+    fn default() -> PollScheduler {
+        PollScheduler::new()
+    }
+}
+
+async fn read_target<R: Read + Unpin, W: Write + Unpin>(
+    session: &mut ControllerSession<'_, R, W>,
+    target: &mut PollTarget,
+) -> Result<i32> {
+    let index = match (target.index, target.id.as_deref()) {
+        (Some(index), _) => index,
+        (None, Some(id)) => match session.get_value_index_by_id(id).await? {
+            Some(index) => {
+                target.index = Some(index);
+                index
+            }
+            None => return Err(format!("Unknown value ID {:?}", id).into()),
+        },
+        (None, None) => return Err("Target without index or ID".into()),
+    };
+
+    match session.get_value_by_index(index, target.subindex).await? {
+        Some(dgram) => Ok(dgram.param32),
+        None => Err(format!("No reply for value index 0x{:04X}", index).into()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use async_std::io::Cursor;
+
+    use super::*;
+
+    use crate::{
+        live_data_stream::LiveDataStream,
+        test_utils::{extend_from_datagram, extend_with_empty_packet},
+        value_id_hash::value_id_hash_by_id,
+    };
+
+    #[test]
+    fn test_poll_scheduler() {
+        let mut rx_buf = Vec::new();
+        extend_from_datagram(&mut rx_buf, 0x0000, 0x7E11, 0x0500, 0, 0);
+        extend_from_datagram(&mut rx_buf, 0x0020, 0x7E11, 0x0100, 0x0010, 42);
+        extend_from_datagram(
+            &mut rx_buf,
+            0x0020,
+            0x7E11,
+            0x1101,
+            0x0011,
+            value_id_hash_by_id("Foo"),
+        );
+        extend_from_datagram(&mut rx_buf, 0x0020, 0x7E11, 0x0100, 0x0011, 43);
+        extend_with_empty_packet(&mut rx_buf, 0x0010, 0x7E11, 0x0100);
+
+        let lds = LiveDataStream::new(Cursor::new(rx_buf), Cursor::new(Vec::new()), 0, 0x0020);
+        let stream = SharedLiveDataStream::new(lds);
+
+        let mut scheduler = PollScheduler::new();
+        scheduler.add_target(PollTarget::by_index(
+            0x7E11,
+            0x0010,
+            Duration::from_secs(60),
+        ));
+        scheduler.add_target(PollTarget::by_id(0x7E11, "Foo", Duration::from_secs(60)));
+
+        let (sender, receiver) = async_std::channel::unbounded();
+
+        async_std::task::block_on(async {
+            let run_future = async_std::task::spawn(scheduler.run(stream, sender));
+
+            let result = receiver.recv().await.unwrap();
+            assert_eq!(Some(0x0010), result.target.index);
+            assert_eq!(Ok(42), result.value);
+
+            let result = receiver.recv().await.unwrap();
+            assert_eq!(Some(0x0011), result.target.index);
+            assert_eq!(Some("Foo"), result.target.id.as_deref());
+            assert_eq!(Ok(43), result.value);
+
+            drop(receiver);
+            drop(run_future.cancel().await);
+        });
+    }
+
+    #[test]
+    fn test_reload() {
+        let mut rx_buf = Vec::new();
+        extend_from_datagram(&mut rx_buf, 0x0000, 0x7E11, 0x0500, 0, 0);
+        extend_from_datagram(&mut rx_buf, 0x0020, 0x7E11, 0x0100, 0x0010, 42);
+        extend_with_empty_packet(&mut rx_buf, 0x0010, 0x7E11, 0x0100);
+        extend_from_datagram(&mut rx_buf, 0x0000, 0x7E11, 0x0500, 0, 0);
+        extend_from_datagram(&mut rx_buf, 0x0020, 0x7E11, 0x0100, 0x0011, 43);
+        extend_with_empty_packet(&mut rx_buf, 0x0010, 0x7E11, 0x0100);
+
+        let lds = LiveDataStream::new(Cursor::new(rx_buf), Cursor::new(Vec::new()), 0, 0x0020);
+        let stream = SharedLiveDataStream::new(lds);
+
+        let target = PollTarget::by_index(0x7E11, 0x0010, Duration::from_secs(60));
+
+        let mut scheduler = PollScheduler::new();
+        scheduler.add_target(target.clone());
+
+        let (sender, receiver) = async_std::channel::unbounded();
+
+        async_std::task::block_on(async {
+            let run_future = async_std::task::spawn(scheduler.clone().run(stream, sender));
+
+            let result = receiver.recv().await.unwrap();
+            assert_eq!(Some(0x0010), result.target.index);
+            assert_eq!(Ok(42), result.value);
+
+            let mut changed = target.clone();
+            changed.interval = Duration::from_secs(120);
+            scheduler.reload(vec![
+                changed,
+                PollTarget::by_index(0x7E11, 0x0011, Duration::from_secs(60)),
+            ]);
+
+            // only the new target is due, the changed one keeps its schedule
+            let result = receiver.recv().await.unwrap();
+            assert_eq!(Some(0x0011), result.target.index);
+            assert_eq!(Ok(43), result.value);
+
+            let targets = scheduler.targets.lock().unwrap().clone();
+            assert_eq!(2, targets.len());
+            assert!(targets[0].1 > targets[1].1 + Duration::from_secs(59));
+            drop(targets);
+
+            drop(receiver);
+            drop(run_future.cancel().await);
+        });
+    }
+}
@@ -0,0 +1,375 @@
+use std::{collections::HashMap, marker::Unpin, path::Path};
+
+use async_std::{io::Write, prelude::*};
+
+use resol_vbus::{
+    chrono::{DateTime, Local, Utc},
+    DataSet, RecordingReader, Specification,
+};
+
+use crate::{
+    error::Result,
+    json::{push_json_number, push_json_string},
+};
+
+/// The output format of a `RecordingConverter`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum ExportFormat {
+    /// Comma separated values, one row per `DataSet`, using localized values.
+    Csv,
+
+    /// A JSON array containing one object per `DataSet`, using numeric values.
+    Json,
+}
+
+struct Column {
+    packet_field_id: String,
+    name: String,
+}
+
+/// Converts VBus recordings into CSV or JSON.
+///
+/// The converter reads one or more recordings (e.g. `.vbus` files), decodes
+/// the contained `DataSet`s using a `Specification` and writes one row per
+/// `DataSet` into the provided writer. The values of packets are accumulated
+/// over time, so that every row contains the latest known value of every
+/// selected field.
+///
+/// # Examples
+///
+/// ```no_run
+/// # fn main() -> async_resol_vbus::Result<()> { async_std::task::block_on(async {
+/// #
+/// use resol_vbus::{Language, Specification, SpecificationFile};
+///
+/// use async_resol_vbus::{ExportFormat, RecordingConverter};
+///
+/// let spec = Specification::from_file(SpecificationFile::new_default(), Language::En);
+///
+/// let mut converter = RecordingConverter::new(&spec);
+/// converter.set_format(ExportFormat::Json);
+/// converter.set_use_local_timezone(true);
+///
+/// let mut output = async_std::fs::File::create("20210101.json").await?;
+/// converter.convert_files(&["20210101.vbus"], &mut output).await?;
+/// #
+/// # Ok(()) }) }
+/// ```
+#[derive(Debug)]
+pub struct RecordingConverter<'a> {
+    spec: &'a Specification,
+    format: ExportFormat,
+    packet_field_ids: Option<Vec<String>>,
+    min_timestamp: Option<DateTime<Utc>>,
+    max_timestamp: Option<DateTime<Utc>>,
+    use_local_timezone: bool,
+}
+
+impl<'a> RecordingConverter<'a> {
+    /// Create a new `RecordingConverter` using the given `Specification`.
+    pub fn new(spec: &'a Specification) -> RecordingConverter<'a> {
+        RecordingConverter {
+            spec,
+            format: ExportFormat::Csv,
+            packet_field_ids: None,
+            min_timestamp: None,
+            max_timestamp: None,
+            use_local_timezone: false,
+        }
+    }
+
+    /// Set the output format. Defaults to `ExportFormat::Csv`.
+    pub fn set_format(&mut self, format: ExportFormat) {
+        self.format = format;
+    }
+
+    /// Set the packet field IDs (e.g. `"00_0010_7E11_10_0100_000_2_0"`) to
+    /// export.
+    ///
+    /// The fields are exported in the given order. If `None` (the default)
+    /// is provided, all fields found in the recordings are exported.
+    pub fn set_packet_field_ids(&mut self, ids: Option<Vec<String>>) {
+        self.packet_field_ids = ids;
+    }
+
+    /// Only export `DataSet`s with a timestamp equal to or after `timestamp`.
+    pub fn set_min_timestamp(&mut self, timestamp: Option<DateTime<Utc>>) {
+        self.min_timestamp = timestamp;
+    }
+
+    /// Only export `DataSet`s with a timestamp before `timestamp`.
+    pub fn set_max_timestamp(&mut self, timestamp: Option<DateTime<Utc>>) {
+        self.max_timestamp = timestamp;
+    }
+
+    /// Set whether timestamps are written in the local timezone instead of UTC.
+    pub fn set_use_local_timezone(&mut self, use_local_timezone: bool) {
+        self.use_local_timezone = use_local_timezone;
+    }
+
+    /// Read the recording files in the given order and write the converted
+    /// output to `writer`.
+    ///
+    /// Returns the number of exported `DataSet`s.
+    pub async fn convert_files<P, W>(&self, paths: &[P], writer: &mut W) -> Result<usize>
+    where
+        P: AsRef<Path>,
+        W: Write + Unpin,
+    {
+        let mut bytes = Vec::new();
+        for path in paths {
+            let mut file_bytes = async_std::fs::read(path.as_ref()).await?;
+            bytes.append(&mut file_bytes);
+        }
+
+        self.convert_bytes(&bytes, writer).await
+    }
+
+    /// Convert the recording `bytes` and write the output to `writer`.
+    ///
+    /// Returns the number of exported `DataSet`s.
+    pub async fn convert_bytes<W: Write + Unpin>(
+        &self,
+        bytes: &[u8],
+        writer: &mut W,
+    ) -> Result<usize> {
+        let columns = self.columns(bytes)?;
+
+        let mut rr = RecordingReader::new(bytes);
+        rr.set_min_max_timestamps(self.min_timestamp, self.max_timestamp);
+
+        let mut output = String::new();
+        match self.format {
+            ExportFormat::Csv => {
+                push_csv_value(&mut output, "Timestamp");
+                for column in &columns {
+                    output.push(',');
+                    push_csv_value(&mut output, &column.name);
+                }
+                output.push_str("\r\n");
+            }
+            ExportFormat::Json => output.push('['),
+        }
+
+        let mut accumulated = DataSet::new();
+        let mut count = 0;
+        while let Some(data_set) = rr.read_data_set()? {
+            accumulated.timestamp = data_set.timestamp;
+            accumulated.add_data_set(data_set);
+
+            let mut values = HashMap::new();
+            for field in self.spec.fields_in_data_set(&accumulated) {
+                let text = format!("{}", field.fmt_raw_value(false));
+                values.insert(
+                    field.field_spec().packet_field_id.clone(),
+                    (text, field.raw_value_f64()),
+                );
+            }
+
+            let timestamp = self.format_timestamp(&accumulated.timestamp);
+
+            match self.format {
+                ExportFormat::Csv => {
+                    push_csv_value(&mut output, &timestamp);
+                    for column in &columns {
+                        output.push(',');
+                        if let Some((text, _)) = values.get(&column.packet_field_id) {
+                            push_csv_value(&mut output, text);
+                        }
+                    }
+                    output.push_str("\r\n");
+                }
+                ExportFormat::Json => {
+                    if count > 0 {
+                        output.push(',');
+                    }
+                    output.push_str("{\"timestamp\":");
+                    push_json_string(&mut output, &timestamp);
+                    output.push_str(",\"fields\":{");
+                    for (idx, column) in columns.iter().enumerate() {
+                        if idx > 0 {
+                            output.push(',');
+                        }
+                        push_json_string(&mut output, &column.packet_field_id);
+                        output.push(':');
+                        let value = values
+                            .get(&column.packet_field_id)
+                            .and_then(|(_, value)| *value);
+                        push_json_number(&mut output, value);
+                    }
+                    output.push_str("}}");
+                }
+            }
+
+            writer.write_all(output.as_bytes()).await?;
+            output.clear();
+
+            count += 1;
+        }
+
+        if self.format == ExportFormat::Json {
+            output.push_str("]\n");
+        }
+
+        writer.write_all(output.as_bytes()).await?;
+        writer.flush().await?;
+
+        Ok(count)
+    }
+
+    fn columns(&self, bytes: &[u8]) -> Result<Vec<Column>> {
+        let mut topology = RecordingReader::new(bytes).read_topology_data_set()?;
+        topology.sort();
+
+        let known_columns = self
+            .spec
+            .fields_in_data_set(&topology)
+            .map(|field| Column {
+                packet_field_id: field.field_spec().packet_field_id.clone(),
+                name: format!("{} - {}", field.packet_spec().name, field.field_spec().name),
+            })
+            .collect::<Vec<_>>();
+
+        let columns = match self.packet_field_ids {
+            Some(ref ids) => ids
+                .iter()
+                .map(|id| {
+                    let name = known_columns
+                        .iter()
+                        .find(|column| &column.packet_field_id == id)
+                        .map(|column| column.name.clone())
+                        .unwrap_or_else(|| id.clone());
+
+                    Column {
+                        packet_field_id: id.clone(),
+                        name,
+                    }
+                })
+                .collect(),
+            None => known_columns,
+        };
+
+        Ok(columns)
+    }
+
+    fn format_timestamp(&self, timestamp: &DateTime<Utc>) -> String {
+        if self.use_local_timezone {
+            timestamp.with_timezone(&Local).to_rfc3339()
+        } else {
+            timestamp.to_rfc3339()
+        }
+    }
+}
+
+fn push_csv_value(out: &mut String, value: &str) {
+    if value.contains([',', '"', '\r', '\n']) {
+        out.push('"');
+        out.push_str(&value.replace('"', "\"\""));
+        out.push('"');
+    } else {
+        out.push_str(value);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use async_std::io::Cursor;
+
+    use resol_vbus::{
+        utils::utc_timestamp, Data, Header, Language, Packet, RecordingWriter, SpecificationFile,
+    };
+
+    use super::*;
+
+    use crate::test_utils::simulate_run;
+
+    fn create_recording() -> Vec<u8> {
+        let mut rw = RecordingWriter::new(Vec::new());
+
+        for (idx, temperature) in [872i16, -12, 255].iter().enumerate() {
+            let timestamp = utc_timestamp(1485688933 + 60 * idx as i64);
+
+            let mut frame_data = [0u8; 508];
+            frame_data[0..2].copy_from_slice(&temperature.to_le_bytes());
+
+            let mut data_set = DataSet::new();
+            data_set.timestamp = timestamp;
+            data_set.add_data(Data::Packet(Packet {
+                header: Header {
+                    timestamp,
+                    channel: 0,
+                    destination_address: 0x0010,
+                    source_address: 0x7E11,
+                    protocol_version: 0x10,
+                },
+                command: 0x0100,
+                frame_count: 1,
+                frame_data,
+            }));
+
+            rw.write_data_set(&data_set).unwrap();
+        }
+
+        rw.get_ref().clone()
+    }
+
+    #[test]
+    fn test_csv() {
+        let spec = Specification::from_file(SpecificationFile::new_default(), Language::De);
+        let bytes = create_recording();
+
+        let mut converter = RecordingConverter::new(&spec);
+        converter.set_packet_field_ids(Some(vec![
+            "00_0010_7E11_10_0100_000_2_0".to_string(),
+            "00_0010_7E11_10_0100_999_2_0".to_string(),
+        ]));
+        converter.set_min_timestamp(Some(utc_timestamp(1485688933 + 60)));
+
+        let mut output = Cursor::new(Vec::new());
+        let count = simulate_run(converter.convert_bytes(&bytes, &mut output)).unwrap();
+
+        assert_eq!(2, count);
+        assert_eq!(
+            "Timestamp,DeltaSol MX [Regler] - Temperatur Sensor 1,00_0010_7E11_10_0100_999_2_0\r\n2017-01-29T11:23:13+00:00,\"-1,2\",\r\n2017-01-29T11:24:13+00:00,\"25,5\",\r\n",
+            std::str::from_utf8(output.get_ref()).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_json() {
+        let spec = Specification::from_file(SpecificationFile::new_default(), Language::En);
+        let bytes = create_recording();
+
+        let mut converter = RecordingConverter::new(&spec);
+        converter.set_format(ExportFormat::Json);
+        converter.set_packet_field_ids(Some(vec!["00_0010_7E11_10_0100_000_2_0".to_string()]));
+        converter.set_max_timestamp(Some(utc_timestamp(1485688933 + 60)));
+
+        let mut output = Cursor::new(Vec::new());
+        let count = simulate_run(converter.convert_bytes(&bytes, &mut output)).unwrap();
+
+        assert_eq!(1, count);
+        assert_eq!(
+            "[{\"timestamp\":\"2017-01-29T11:22:13+00:00\",\"fields\":{\"00_0010_7E11_10_0100_000_2_0\":87.2}}]\n",
+            std::str::from_utf8(output.get_ref()).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_all_fields() {
+        let spec = Specification::from_file(SpecificationFile::new_default(), Language::En);
+        let bytes = create_recording();
+
+        let converter = RecordingConverter::new(&spec);
+
+        let mut output = Cursor::new(Vec::new());
+        let count = simulate_run(converter.convert_bytes(&bytes, &mut output)).unwrap();
+
+        assert_eq!(3, count);
+
+        let output = std::str::from_utf8(output.get_ref()).unwrap();
+        let header = output.lines().next().unwrap();
+        assert!(header.starts_with("Timestamp,DeltaSol MX [Controller] - Temperature sensor 1,"));
+    }
+}
@@ -0,0 +1,397 @@
+use std::{
+    io::{ErrorKind, SeekFrom},
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use async_std::{fs::File, prelude::*};
+
+use resol_vbus::{
+    chrono::{DateTime, TimeZone, Utc},
+    recording_decoder::{length_from_bytes, timestamp_from_checked_bytes},
+    DataSet, RecordingReader, StreamBlobLength,
+};
+
+use crate::error::Result;
+
+const INDEX_MAGIC: &[u8; 4] = b"VBIX";
+
+/// A `DataSet` position known to a `RecordingIndex`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RecordingIndexEntry {
+    /// The timestamp of the `DataSet`.
+    pub timestamp: DateTime<Utc>,
+
+    /// The offset of the `DataSet` in the recording.
+    pub offset: u64,
+}
+
+/// An index mapping timestamps to offsets in a recording.
+///
+/// The index contains the first `DataSet` of the recording and every
+/// `DataSet` that is at least the granularity later than the previously
+/// indexed one. It assumes that the `DataSet`s are ordered by their
+/// timestamps, like the recordings written by a `RecordingWriter` from live
+/// data are.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RecordingIndex {
+    granularity: Duration,
+    entries: Vec<RecordingIndexEntry>,
+    length: u64,
+}
+
+impl RecordingIndex {
+    /// Create a new empty `RecordingIndex`.
+    pub fn new(granularity: Duration) -> RecordingIndex {
+        RecordingIndex {
+            granularity,
+            entries: Vec::new(),
+            length: 0,
+        }
+    }
+
+    /// Get the indexed `DataSet` positions.
+    pub fn entries(&self) -> &[RecordingIndexEntry] {
+        &self.entries
+    }
+
+    /// Get the number of bytes of the recording covered by the index.
+    pub fn length(&self) -> u64 {
+        self.length
+    }
+
+    /// Index the `bytes` appended to the recording after the covered length.
+    ///
+    /// A trailing incomplete record is not covered and will be indexed by
+    /// the next call.
+    pub fn extend(&mut self, bytes: &[u8]) {
+        let granularity = resol_vbus::chrono::Duration::from_std(self.granularity)
+            .unwrap_or(resol_vbus::chrono::Duration::MAX);
+
+        let mut pos = 0;
+        loop {
+            match length_from_bytes(&bytes[pos..]) {
+                StreamBlobLength::BlobLength(length) => {
+                    if bytes[pos + 1] == 0x44 {
+                        let timestamp = timestamp_from_checked_bytes(&bytes[pos + 6..pos + 14]);
+                        let is_indexed = match self.entries.last() {
+                            Some(entry) => timestamp - entry.timestamp >= granularity,
+                            None => true,
+                        };
+                        if is_indexed {
+                            self.entries.push(RecordingIndexEntry {
+                                timestamp,
+                                offset: self.length + pos as u64,
+                            });
+                        }
+                    }
+                    pos += length;
+                }
+                StreamBlobLength::Partial => break,
+                StreamBlobLength::Malformed => pos += 1,
+            }
+        }
+
+        self.length += pos as u64;
+    }
+
+    /// Get the byte range of the recording that contains all `DataSet`s
+    /// with a timestamp at or after `start` and before `end`.
+    pub fn range(&self, start: DateTime<Utc>, end: DateTime<Utc>) -> (u64, u64) {
+        let start_idx = self
+            .entries
+            .partition_point(|entry| entry.timestamp < start);
+        let start_offset = match start_idx.checked_sub(1) {
+            Some(idx) => self.entries[idx].offset,
+            None => 0,
+        };
+
+        let end_offset = match self.entries.iter().find(|entry| entry.timestamp >= end) {
+            Some(entry) => entry.offset,
+            None => self.length,
+        };
+
+        (start_offset, end_offset.max(start_offset))
+    }
+
+    fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(20 + self.entries.len() * 16);
+        bytes.extend_from_slice(INDEX_MAGIC);
+        bytes.extend_from_slice(&(self.granularity.as_millis() as u64).to_le_bytes());
+        bytes.extend_from_slice(&self.length.to_le_bytes());
+        for entry in &self.entries {
+            bytes.extend_from_slice(&entry.timestamp.timestamp_millis().to_le_bytes());
+            bytes.extend_from_slice(&entry.offset.to_le_bytes());
+        }
+        bytes
+    }
+
+    fn from_bytes(bytes: &[u8]) -> Result<RecordingIndex> {
+        if bytes.len() < 20 || &bytes[0..4] != INDEX_MAGIC || !(bytes.len() - 20).is_multiple_of(16)
+        {
+            return Err("Invalid recording index".into());
+        }
+
+        let u64_at = |idx: usize| u64::from_le_bytes(bytes[idx..idx + 8].try_into().unwrap());
+
+        let entries = (20..bytes.len())
+            .step_by(16)
+            .map(|idx| {
+                let millis = u64_at(idx) as i64;
+                match Utc.timestamp_millis_opt(millis).single() {
+                    Some(timestamp) => Ok(RecordingIndexEntry {
+                        timestamp,
+                        offset: u64_at(idx + 8),
+                    }),
+                    None => Err("Invalid recording index".into()),
+                }
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        Ok(RecordingIndex {
+            granularity: Duration::from_millis(u64_at(4)),
+            entries,
+            length: u64_at(12),
+        })
+    }
+}
+
+/// Answers time range queries on a recording file using a `RecordingIndex`.
+///
+/// The index is stored in a sidecar file next to the recording (the
+/// recording's path with an additional `.idx` extension). It is built on
+/// the first `open` and extended on later `open`s and `refresh`es if the
+/// recording has grown in the meantime. Range queries then only read the
+/// part of the recording that contains the requested `DataSet`s.
+///
+/// Failing to write the sidecar file (e.g. for recordings on read-only
+/// storage) is not an error, the index is rebuilt on the next `open`
+/// instead.
+///
+/// # Examples
+///
+/// ```no_run
+/// # fn main() -> async_resol_vbus::Result<()> { async_std::task::block_on(async {
+/// #
+/// use async_resol_vbus::{chrono::{Duration, Utc}, IndexedRecording};
+///
+/// let recording = IndexedRecording::open("20200601_packets.vbus").await?;
+///
+/// let end = Utc::now();
+/// for data_set in recording.read_range(end - Duration::hours(1), end).await? {
+///     println!("{}: {} data", data_set.timestamp, data_set.iter().count());
+/// }
+/// #
+/// # Ok(()) }) }
+/// ```
+#[derive(Debug)]
+pub struct IndexedRecording {
+    path: PathBuf,
+    index: RecordingIndex,
+}
+
+impl IndexedRecording {
+    /// Open a recording file, indexing it with a granularity of one minute.
+    pub async fn open<P: AsRef<Path>>(path: P) -> Result<IndexedRecording> {
+        IndexedRecording::open_with_granularity(path, Duration::from_secs(60)).await
+    }
+
+    /// Open a recording file, indexing it with the given granularity.
+    ///
+    /// A finer granularity reduces the amount of data read for a range
+    /// query at the cost of a larger index.
+    pub async fn open_with_granularity<P: AsRef<Path>>(
+        path: P,
+        granularity: Duration,
+    ) -> Result<IndexedRecording> {
+        let path = path.as_ref().to_path_buf();
+
+        let index = match async_std::fs::read(index_path(&path)).await {
+            Ok(bytes) => match RecordingIndex::from_bytes(&bytes) {
+                Ok(index) if index.granularity == granularity => index,
+                _ => RecordingIndex::new(granularity),
+            },
+            Err(err) if err.kind() == ErrorKind::NotFound => RecordingIndex::new(granularity),
+            Err(err) => return Err(err.into()),
+        };
+
+        let mut recording = IndexedRecording { path, index };
+        recording.refresh().await?;
+        Ok(recording)
+    }
+
+    /// Get the path of the recording file.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Get the `RecordingIndex`.
+    pub fn index(&self) -> &RecordingIndex {
+        &self.index
+    }
+
+    /// Index the data appended to the recording since it was indexed last.
+    pub async fn refresh(&mut self) -> Result<()> {
+        let mut file = File::open(&self.path).await?;
+        let file_length = file.metadata().await?.len();
+
+        if file_length < self.index.length {
+            self.index = RecordingIndex::new(self.index.granularity);
+        } else if file_length == self.index.length {
+            return Ok(());
+        }
+
+        let mut bytes = Vec::new();
+        file.seek(SeekFrom::Start(self.index.length)).await?;
+        file.read_to_end(&mut bytes).await?;
+        self.index.extend(&bytes);
+
+        let mut tmp_path = index_path(&self.path).into_os_string();
+        tmp_path.push(".tmp");
+
+        if async_std::fs::write(&tmp_path, self.index.to_bytes())
+            .await
+            .is_ok()
+        {
+            drop(async_std::fs::rename(&tmp_path, index_path(&self.path)).await);
+        }
+
+        Ok(())
+    }
+
+    /// Read all `DataSet`s with a timestamp at or after `start` and before
+    /// `end`.
+    pub async fn read_range(
+        &self,
+        start: DateTime<Utc>,
+        end: DateTime<Utc>,
+    ) -> Result<Vec<DataSet>> {
+        let (start_offset, end_offset) = self.index.range(start, end);
+
+        let mut bytes = vec![0; (end_offset - start_offset) as usize];
+        let mut file = File::open(&self.path).await?;
+        file.seek(SeekFrom::Start(start_offset)).await?;
+        file.read_exact(&mut bytes).await?;
+
+        let mut rr = RecordingReader::new(&bytes[..]);
+        rr.set_min_max_timestamps(Some(start), Some(end));
+
+        let mut data_sets = Vec::new();
+        while let Some(data_set) = rr.read_data_set()? {
+            data_sets.push(data_set);
+        }
+        Ok(data_sets)
+    }
+}
+
+fn index_path(path: &Path) -> PathBuf {
+    let mut index_path = path.to_path_buf().into_os_string();
+    index_path.push(".idx");
+    index_path.into()
+}
+
+#[cfg(test)]
+mod tests {
+    use resol_vbus::{utils::utc_timestamp, Data, Header, Packet, RecordingWriter};
+
+    use super::*;
+
+    fn write_data_sets(rw: &mut RecordingWriter<Vec<u8>>, offsets: std::ops::Range<i64>) {
+        for offset in offsets {
+            let timestamp = utc_timestamp(1485688933 + offset * 10);
+
+            let mut data_set = DataSet::new();
+            data_set.timestamp = timestamp;
+            data_set.add_data(Data::Packet(Packet {
+                header: Header {
+                    timestamp,
+                    channel: 0,
+                    destination_address: 0x0010,
+                    source_address: 0x7E11,
+                    protocol_version: 0x10,
+                },
+                command: 0x0100,
+                frame_count: 1,
+                frame_data: [0; 508],
+            }));
+            rw.write_data_set(&data_set).unwrap();
+        }
+    }
+
+    fn timestamps(data_sets: &[DataSet]) -> Vec<i64> {
+        data_sets
+            .iter()
+            .map(|data_set| (data_set.timestamp.timestamp() - 1485688933) / 10)
+            .collect()
+    }
+
+    #[test]
+    fn test_recording_index() {
+        let mut rw = RecordingWriter::new(Vec::new());
+        write_data_sets(&mut rw, 0..30);
+        let bytes = rw.get_ref();
+
+        let mut index = RecordingIndex::new(Duration::from_secs(60));
+        index.extend(&bytes[..bytes.len() - 1]);
+        index.extend(&bytes[index.length() as usize..]);
+        assert_eq!(bytes.len() as u64, index.length());
+        assert_eq!(5, index.entries().len());
+        assert_eq!(utc_timestamp(1485688993), index.entries()[1].timestamp);
+
+        let (start, end) = index.range(utc_timestamp(1485688993), utc_timestamp(1485689053));
+        assert_eq!(index.entries()[0].offset, start);
+        assert_eq!(index.entries()[2].offset, end);
+
+        assert_eq!(
+            Ok(index.clone()),
+            RecordingIndex::from_bytes(&index.to_bytes())
+        );
+        assert_eq!(
+            Err("Invalid recording index".into()),
+            RecordingIndex::from_bytes(b"VBIX")
+        );
+    }
+
+    #[test]
+    fn test_indexed_recording() -> Result<()> {
+        async_std::task::block_on(async {
+            let path = std::env::temp_dir().join(format!(
+                "async-resol-vbus-indexed-{}.vbus",
+                std::process::id()
+            ));
+
+            let mut rw = RecordingWriter::new(Vec::new());
+            write_data_sets(&mut rw, 0..20);
+            async_std::fs::write(&path, rw.get_ref()).await?;
+
+            let mut recording = IndexedRecording::open(&path).await?;
+            assert_eq!(4, recording.index().entries().len());
+
+            let data_sets = recording
+                .read_range(
+                    utc_timestamp(1485688933 + 55),
+                    utc_timestamp(1485688933 + 120),
+                )
+                .await?;
+            assert_eq!(vec![6, 7, 8, 9, 10, 11], timestamps(&data_sets));
+
+            write_data_sets(&mut rw, 20..30);
+            async_std::fs::write(&path, rw.get_ref()).await?;
+            recording.refresh().await?;
+            assert_eq!(5, recording.index().entries().len());
+
+            let recording = IndexedRecording::open(&path).await?;
+            assert_eq!(rw.get_ref().len() as u64, recording.index().length());
+
+            let data_sets = recording
+                .read_range(utc_timestamp(1485688933 + 280), utc_timestamp(1485689933))
+                .await?;
+            assert_eq!(vec![28, 29], timestamps(&data_sets));
+
+            async_std::fs::remove_file(&path).await?;
+            async_std::fs::remove_file(index_path(&path)).await?;
+
+            Ok(())
+        })
+    }
+}
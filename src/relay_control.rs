@@ -0,0 +1,142 @@
+use crate::error::Result;
+
+/// The operating mode of a relay, see `ControllerSession::set_relay_mode`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RelayMode {
+    /// The relay is manually switched off.
+    Off,
+
+    /// The relay is manually switched on.
+    On,
+
+    /// The relay is controlled by the controller's program.
+    Auto,
+}
+
+/// The value indices of a single relay.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct RelayIndices {
+    mode_index: i16,
+    state_index: i16,
+}
+
+/// Describes where a controller model stores its relay settings.
+///
+/// Every relay has a manual mode value, which selects the `RelayMode`, and
+/// a state value, which reports the current output (e.g. the speed in
+/// percent). The values used to encode the modes differ between controller
+/// models and default to `0` for `Off`, `1` for `On` and `2` for `Auto`.
+///
+/// Relays are numbered starting at 1 in the order they were added, matching
+/// the "R1", "R2", ... labels printed on most controllers.
+///
+/// # Examples
+///
+/// ```
+/// use async_resol_vbus::{RelayMode, RelayProfile};
+///
+/// let mut profile = RelayProfile::new();
+/// profile.add_relay(0x1000, 0x0200);
+/// profile.add_relay(0x1001, 0x0201);
+///
+/// assert_eq!(2, profile.relay_count());
+/// assert_eq!(2, profile.mode_value(RelayMode::Auto));
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RelayProfile {
+    relays: Vec<RelayIndices>,
+    mode_values: [i32; 3],
+}
+
+impl RelayProfile {
+    /// Create a new `RelayProfile` without relays.
+    pub fn new() -> RelayProfile {
+        RelayProfile {
+            relays: Vec::new(),
+            mode_values: [0, 1, 2],
+        }
+    }
+
+    /// Add a relay using its manual mode and state value indices.
+    pub fn add_relay(&mut self, mode_index: i16, state_index: i16) {
+        self.relays.push(RelayIndices {
+            mode_index,
+            state_index,
+        });
+    }
+
+    /// Set the values used to encode the `RelayMode`s.
+    pub fn set_mode_values(&mut self, off: i32, on: i32, auto: i32) {
+        self.mode_values = [off, on, auto];
+    }
+
+    /// Get the number of relays.
+    pub fn relay_count(&self) -> usize {
+        self.relays.len()
+    }
+
+    /// Get the value encoding a `RelayMode`.
+    pub fn mode_value(&self, mode: RelayMode) -> i32 {
+        match mode {
+            RelayMode::Off => self.mode_values[0],
+            RelayMode::On => self.mode_values[1],
+            RelayMode::Auto => self.mode_values[2],
+        }
+    }
+
+    /// Decode a `RelayMode` from its value.
+    pub fn mode_from_value(&self, value: i32) -> Result<RelayMode> {
+        [RelayMode::Off, RelayMode::On, RelayMode::Auto]
+            .iter()
+            .copied()
+            .find(|mode| self.mode_value(*mode) == value)
+            .ok_or_else(|| format!("Unknown relay mode value {}", value).into())
+    }
+
+    fn relay(&self, relay: usize) -> Result<&RelayIndices> {
+        match relay.checked_sub(1).and_then(|idx| self.relays.get(idx)) {
+            Some(indices) => Ok(indices),
+            None => Err(format!("Unknown relay R{}", relay).into()),
+        }
+    }
+
+    /// Get the manual mode value index of a relay.
+    pub fn mode_index(&self, relay: usize) -> Result<i16> {
+        Ok(self.relay(relay)?.mode_index)
+    }
+
+    /// Get the state value index of a relay.
+    pub fn state_index(&self, relay: usize) -> Result<i16> {
+        Ok(self.relay(relay)?.state_index)
+    }
+}
+
+impl Default for RelayProfile {
+    fn default() -> Self {
+        RelayProfile::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_relay_profile() {
+        let mut profile = RelayProfile::new();
+        profile.add_relay(0x1000, 0x0200);
+        profile.set_mode_values(4, 5, 6);
+
+        assert_eq!(Ok(0x1000), profile.mode_index(1));
+        assert_eq!(Ok(0x0200), profile.state_index(1));
+        assert_eq!(Err("Unknown relay R0".into()), profile.mode_index(0));
+        assert_eq!(Err("Unknown relay R2".into()), profile.state_index(2));
+
+        assert_eq!(5, profile.mode_value(RelayMode::On));
+        assert_eq!(Ok(RelayMode::Auto), profile.mode_from_value(6));
+        assert_eq!(
+            Err("Unknown relay mode value 2".into()),
+            profile.mode_from_value(2)
+        );
+    }
+}
@@ -0,0 +1,416 @@
+use std::{
+    io::ErrorKind,
+    path::{Path, PathBuf},
+    time::{Duration, SystemTime},
+};
+
+use async_std::{channel::Sender, prelude::*};
+
+#[cfg(any(feature = "gzip", feature = "zstd"))]
+use resol_vbus::RecordingReader;
+
+#[cfg(any(feature = "gzip", feature = "zstd"))]
+use crate::compressed_recording::{CompressedRecordingWriter, Compression};
+use crate::error::Result;
+
+const COMPRESSED_EXTENSIONS: [&str; 2] = ["gz", "zst"];
+
+/// The result of a single run of a `RetentionManager`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RetentionReport {
+    /// The recordings that were deleted.
+    pub deleted: Vec<PathBuf>,
+
+    /// The recordings that were replaced by a compressed copy.
+    pub compressed: Vec<PathBuf>,
+
+    /// The number of bytes freed by deleting and compressing recordings.
+    pub reclaimed_bytes: u64,
+}
+
+#[derive(Debug)]
+struct RecordingFile {
+    path: PathBuf,
+    length: u64,
+    modified: SystemTime,
+    #[cfg(any(feature = "gzip", feature = "zstd"))]
+    is_compressed: bool,
+}
+
+/// Enforces age and size limits on a directory of recordings.
+///
+/// Every run lists the recordings in the directory (all files with the
+/// recording extension, optionally followed by a `.gz` or `.zst`
+/// extension for compressed recordings) and
+///
+/// - deletes recordings that were last modified longer than the maximum
+///   age ago,
+/// - compresses recordings that were last modified longer than the
+///   compression age ago (requires the `gzip` or `zstd` feature), and
+/// - deletes the oldest recordings while the total size exceeds the
+///   maximum size. The most recently modified recording is never deleted
+///   this way, since it is most likely still being written.
+///
+/// Index sidecar files (see `IndexedRecording`) of deleted and compressed
+/// recordings are deleted as well.
+///
+/// # Examples
+///
+/// ```no_run
+/// # fn main() -> async_resol_vbus::Result<()> { async_std::task::block_on(async {
+/// #
+/// use std::time::Duration;
+///
+/// use async_resol_vbus::RetentionManager;
+///
+/// let mut manager = RetentionManager::new("/var/lib/vbus/recordings");
+/// manager.set_max_age(Some(Duration::from_secs(90 * 86400)));
+/// manager.set_max_size(Some(2 * 1024 * 1024 * 1024));
+///
+/// let report = manager.run_once().await?;
+/// println!("Reclaimed {} bytes", report.reclaimed_bytes);
+/// #
+/// # Ok(()) }) }
+/// ```
+#[derive(Debug)]
+pub struct RetentionManager {
+    directory: PathBuf,
+    extension: String,
+    max_age: Option<Duration>,
+    max_size: Option<u64>,
+    #[cfg(any(feature = "gzip", feature = "zstd"))]
+    compression: Option<(Duration, Compression)>,
+    interval: Duration,
+    sender: Option<Sender<RetentionReport>>,
+}
+
+impl RetentionManager {
+    /// Create a new `RetentionManager` for the recordings in `directory`.
+    ///
+    /// The recording extension defaults to `vbus`, the interval between
+    /// runs to one hour. No limits are enforced by default.
+    pub fn new<P: AsRef<Path>>(directory: P) -> RetentionManager {
+        RetentionManager {
+            directory: directory.as_ref().to_path_buf(),
+            extension: "vbus".to_string(),
+            max_age: None,
+            max_size: None,
+            #[cfg(any(feature = "gzip", feature = "zstd"))]
+            compression: None,
+            interval: Duration::from_secs(3600),
+            sender: None,
+        }
+    }
+
+    /// Set the extension of the recordings, without the leading dot.
+    pub fn set_extension(&mut self, extension: &str) {
+        self.extension = extension.to_string();
+    }
+
+    /// Set the age after which recordings are deleted.
+    pub fn set_max_age(&mut self, max_age: Option<Duration>) {
+        self.max_age = max_age;
+    }
+
+    /// Set the total size of all recordings above which the oldest ones are
+    /// deleted.
+    pub fn set_max_size(&mut self, max_size: Option<u64>) {
+        self.max_size = max_size;
+    }
+
+    /// Set the age after which recordings are compressed and the
+    /// `Compression` to use.
+    ///
+    /// Compressed recordings can be read using a
+    /// `CompressedRecordingReader`.
+    #[cfg(any(feature = "gzip", feature = "zstd"))]
+    pub fn set_compression(&mut self, compression: Option<(Duration, Compression)>) {
+        self.compression = compression;
+    }
+
+    /// Set the interval between two runs of `run`.
+    pub fn set_interval(&mut self, interval: Duration) {
+        self.interval = interval;
+    }
+
+    /// Set the `Sender` that receives the report of every run of `run`.
+    pub fn set_sender(&mut self, sender: Option<Sender<RetentionReport>>) {
+        self.sender = sender;
+    }
+
+    fn is_compressed_recording(&self, name: &str) -> Option<bool> {
+        let (stem, extension) = name.rsplit_once('.')?;
+        if extension == self.extension {
+            Some(false)
+        } else if COMPRESSED_EXTENSIONS.contains(&extension)
+            && stem.rsplit_once('.').map(|(_, ext)| ext) == Some(self.extension.as_str())
+        {
+            Some(true)
+        } else {
+            None
+        }
+    }
+
+    async fn list_recordings(&self) -> Result<Vec<RecordingFile>> {
+        let mut recordings = Vec::new();
+
+        let mut entries = async_std::fs::read_dir(&self.directory).await?;
+        while let Some(entry) = entries.next().await {
+            let entry = entry?;
+            let metadata = entry.metadata().await?;
+            if !metadata.is_file() {
+                continue;
+            }
+
+            let name = entry.file_name();
+            let is_compressed = name.to_str().and_then(|n| self.is_compressed_recording(n));
+            if is_compressed.is_none() {
+                continue;
+            }
+
+            recordings.push(RecordingFile {
+                path: entry.path().into(),
+                length: metadata.len(),
+                modified: metadata.modified()?,
+                #[cfg(any(feature = "gzip", feature = "zstd"))]
+                is_compressed: is_compressed == Some(true),
+            });
+        }
+
+        recordings.sort_by(|a, b| a.modified.cmp(&b.modified).then(a.path.cmp(&b.path)));
+        Ok(recordings)
+    }
+
+    async fn delete(&self, recording: &RecordingFile, report: &mut RetentionReport) -> Result<()> {
+        async_std::fs::remove_file(&recording.path).await?;
+        remove_index(&recording.path).await?;
+
+        report.deleted.push(recording.path.clone());
+        report.reclaimed_bytes += recording.length;
+        Ok(())
+    }
+
+    #[cfg(any(feature = "gzip", feature = "zstd"))]
+    async fn compress(
+        &self,
+        recording: &mut RecordingFile,
+        compression: Compression,
+        report: &mut RetentionReport,
+    ) -> Result<()> {
+        let bytes = async_std::fs::read(&recording.path).await?;
+
+        let mut writer = CompressedRecordingWriter::new(Vec::new(), compression);
+        let mut rr = RecordingReader::new(&bytes[..]);
+        while let Some(data_set) = rr.read_data_set()? {
+            writer.write_data_set(&data_set)?;
+        }
+        let compressed = writer.finish()?;
+
+        let mut path = recording.path.clone().into_os_string();
+        path.push(match compression {
+            #[cfg(feature = "gzip")]
+            Compression::Gzip => ".gz",
+            #[cfg(feature = "zstd")]
+            Compression::Zstd => ".zst",
+        });
+        let path = PathBuf::from(path);
+
+        let mut tmp_path = path.clone().into_os_string();
+        tmp_path.push(".tmp");
+
+        async_std::fs::write(&tmp_path, &compressed).await?;
+        async_std::fs::rename(&tmp_path, &path).await?;
+        async_std::fs::remove_file(&recording.path).await?;
+        remove_index(&recording.path).await?;
+
+        report.compressed.push(recording.path.clone());
+        report.reclaimed_bytes += recording.length.saturating_sub(compressed.len() as u64);
+
+        recording.path = path;
+        recording.length = compressed.len() as u64;
+        recording.is_compressed = true;
+        Ok(())
+    }
+
+    /// Apply the limits to the recordings once.
+    pub async fn run_once(&self) -> Result<RetentionReport> {
+        let now = SystemTime::now();
+        let age_of = |recording: &RecordingFile| {
+            now.duration_since(recording.modified)
+                .unwrap_or(Duration::ZERO)
+        };
+
+        let mut report = RetentionReport::default();
+        let mut recordings = Vec::new();
+
+        for recording in self.list_recordings().await? {
+            match self.max_age {
+                Some(max_age) if age_of(&recording) > max_age => {
+                    self.delete(&recording, &mut report).await?
+                }
+                _ => recordings.push(recording),
+            }
+        }
+
+        #[cfg(any(feature = "gzip", feature = "zstd"))]
+        if let Some((compress_age, compression)) = self.compression {
+            for recording in recordings.iter_mut() {
+                if !recording.is_compressed && age_of(recording) > compress_age {
+                    self.compress(recording, compression, &mut report).await?;
+                }
+            }
+        }
+
+        if let Some(max_size) = self.max_size {
+            let mut total_size = recordings.iter().map(|r| r.length).sum::<u64>();
+            let deletable = recordings.len().saturating_sub(1);
+            for recording in recordings.iter().take(deletable) {
+                if total_size <= max_size {
+                    break;
+                }
+                self.delete(recording, &mut report).await?;
+                total_size -= recording.length;
+            }
+        }
+
+        Ok(report)
+    }
+
+    /// Apply the limits every interval until an error occurs.
+    pub async fn run(self) -> Result<()> {
+        loop {
+            let report = self.run_once().await?;
+            if let Some(ref sender) = self.sender {
+                drop(sender.try_send(report));
+            }
+
+            async_std::task::sleep(self.interval).await;
+        }
+    }
+}
+
+async fn remove_index(path: &Path) -> Result<()> {
+    let mut index_path = path.to_path_buf().into_os_string();
+    index_path.push(".idx");
+
+    match async_std::fs::remove_file(&index_path).await {
+        Err(err) if err.kind() != ErrorKind::NotFound => Err(err.into()),
+        _ => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs::File;
+
+    use super::*;
+
+    fn create_file(directory: &Path, name: &str, content: &[u8], age: u64) -> PathBuf {
+        let path = directory.join(name);
+        std::fs::write(&path, content).unwrap();
+        File::options()
+            .write(true)
+            .open(&path)
+            .unwrap()
+            .set_modified(SystemTime::now() - Duration::from_secs(age * 86400))
+            .unwrap();
+        path
+    }
+
+    #[test]
+    fn test_retention_manager() -> Result<()> {
+        async_std::task::block_on(async {
+            let directory = std::env::temp_dir()
+                .join(format!("async-resol-vbus-retention-{}", std::process::id()));
+            std::fs::create_dir_all(&directory)?;
+
+            let expired = create_file(&directory, "20200101.vbus", &[0; 100], 40);
+            create_file(&directory, "20200101.vbus.idx", &[0; 10], 40);
+            let oldest = create_file(&directory, "20200201.vbus.gz", &[0; 100], 20);
+            let older = create_file(&directory, "20200202.vbus", &[0; 100], 19);
+            let newest = create_file(&directory, "20200203.vbus", &[0; 1000], 0);
+            let other = create_file(&directory, "notes.txt", &[0; 1000], 100);
+
+            let mut manager = RetentionManager::new(&directory);
+            manager.set_max_age(Some(Duration::from_secs(30 * 86400)));
+            manager.set_max_size(Some(500));
+
+            let report = manager.run_once().await?;
+            assert_eq!(
+                vec![expired.clone(), oldest.clone(), older.clone()],
+                report.deleted
+            );
+            assert!(report.compressed.is_empty());
+            assert_eq!(300, report.reclaimed_bytes);
+
+            assert!(!expired.exists());
+            assert!(!directory.join("20200101.vbus.idx").exists());
+            assert!(newest.exists());
+            assert!(other.exists());
+
+            assert_eq!(RetentionReport::default(), manager.run_once().await?);
+
+            std::fs::remove_dir_all(&directory)?;
+
+            Ok(())
+        })
+    }
+
+    #[cfg(feature = "gzip")]
+    #[test]
+    fn test_compression() -> Result<()> {
+        use resol_vbus::{utils::utc_timestamp, Data, DataSet, Header, Packet, RecordingWriter};
+
+        use crate::compressed_recording::CompressedRecordingReader;
+
+        async_std::task::block_on(async {
+            let directory = std::env::temp_dir().join(format!(
+                "async-resol-vbus-retention-compression-{}",
+                std::process::id()
+            ));
+            std::fs::create_dir_all(&directory)?;
+
+            let mut rw = RecordingWriter::new(Vec::new());
+            for offset in 0..50 {
+                let timestamp = utc_timestamp(1485688933 + offset);
+                let mut data_set = DataSet::new();
+                data_set.timestamp = timestamp;
+                data_set.add_data(Data::Packet(Packet {
+                    header: Header {
+                        timestamp,
+                        channel: 0,
+                        destination_address: 0x0010,
+                        source_address: 0x7E11,
+                        protocol_version: 0x10,
+                    },
+                    command: 0x0100,
+                    frame_count: 1,
+                    frame_data: [0; 508],
+                }));
+                rw.write_data_set(&data_set)?;
+            }
+
+            let path = create_file(&directory, "20200101.vbus", rw.get_ref(), 2);
+
+            let mut manager = RetentionManager::new(&directory);
+            manager.set_compression(Some((Duration::from_secs(86400), Compression::Gzip)));
+
+            let report = manager.run_once().await?;
+            assert_eq!(vec![path.clone()], report.compressed);
+            assert!(report.reclaimed_bytes > 0);
+            assert!(!path.exists());
+
+            let file = File::open(directory.join("20200101.vbus.gz"))?;
+            let mut reader = CompressedRecordingReader::new(file);
+            let mut count = 0;
+            while reader.read_data_set()?.is_some() {
+                count += 1;
+            }
+            assert_eq!(50, count);
+
+            std::fs::remove_dir_all(&directory)?;
+
+            Ok(())
+        })
+    }
+}
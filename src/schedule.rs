@@ -0,0 +1,159 @@
+use resol_vbus::chrono::Weekday;
+
+use crate::error::Result;
+
+const WEEKDAYS: [Weekday; 7] = [
+    Weekday::Mon,
+    Weekday::Tue,
+    Weekday::Wed,
+    Weekday::Thu,
+    Weekday::Fri,
+    Weekday::Sat,
+    Weekday::Sun,
+];
+
+/// A single switching period of a weekly time program.
+///
+/// Time programs (e.g. of heating circuits) are stored in a block of
+/// consecutive value indices: every day of the week, starting with Monday,
+/// has a fixed number of slots, and every slot holds one period. The start
+/// and end of a period are stored as minutes since midnight in the lower
+/// and upper 16 bits of the slot's value. Unused slots have a start equal
+/// to their end.
+///
+/// # Examples
+///
+/// ```
+/// use async_resol_vbus::{chrono::Weekday, ScheduleEntry};
+///
+/// let entry = ScheduleEntry {
+///     day: Weekday::Mon,
+///     start: 6 * 60,
+///     end: 22 * 60,
+/// };
+///
+/// assert_eq!(0x0528_0168, entry.to_value());
+/// assert_eq!(Some(entry), ScheduleEntry::from_value(Weekday::Mon, 0x0528_0168));
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ScheduleEntry {
+    /// The day of the week.
+    pub day: Weekday,
+
+    /// The start of the period in minutes since midnight.
+    pub start: u16,
+
+    /// The end of the period in minutes since midnight, up to 1440.
+    pub end: u16,
+}
+
+impl ScheduleEntry {
+    /// Decode a `ScheduleEntry` from the value of a slot.
+    ///
+    /// Returns `None` for unused slots.
+    pub fn from_value(day: Weekday, value: i32) -> Option<ScheduleEntry> {
+        let start = (value & 0xFFFF) as u16;
+        let end = ((value >> 16) & 0xFFFF) as u16;
+        if start < end {
+            Some(ScheduleEntry { day, start, end })
+        } else {
+            None
+        }
+    }
+
+    /// Encode the `ScheduleEntry` into the value of a slot.
+    pub fn to_value(&self) -> i32 {
+        (i32::from(self.end) << 16) | i32::from(self.start)
+    }
+}
+
+/// Decode the slot values of a weekly schedule.
+pub(crate) fn schedule_from_values(values: &[i32], slots_per_day: usize) -> Vec<ScheduleEntry> {
+    values
+        .chunks(slots_per_day)
+        .zip(WEEKDAYS.iter())
+        .flat_map(|(slots, day)| {
+            slots
+                .iter()
+                .filter_map(move |value| ScheduleEntry::from_value(*day, *value))
+        })
+        .collect()
+}
+
+/// Encode a weekly schedule into the values of all its slots.
+pub(crate) fn schedule_to_values(
+    entries: &[ScheduleEntry],
+    slots_per_day: usize,
+) -> Result<Vec<i32>> {
+    let mut values = vec![0; slots_per_day * 7];
+    let mut used_slots = [0; 7];
+
+    for entry in entries {
+        if entry.start >= entry.end || entry.end > 1440 {
+            return Err(format!("Invalid schedule entry {:?}", entry).into());
+        }
+
+        let day = entry.day.num_days_from_monday() as usize;
+        if used_slots[day] >= slots_per_day {
+            return Err(format!(
+                "Schedule exceeds {} entries on {}",
+                slots_per_day, entry.day
+            )
+            .into());
+        }
+
+        values[day * slots_per_day + used_slots[day]] = entry.to_value();
+        used_slots[day] += 1;
+    }
+
+    Ok(values)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_schedule_values() {
+        let entries = vec![
+            ScheduleEntry {
+                day: Weekday::Mon,
+                start: 360,
+                end: 480,
+            },
+            ScheduleEntry {
+                day: Weekday::Mon,
+                start: 1020,
+                end: 1320,
+            },
+            ScheduleEntry {
+                day: Weekday::Sun,
+                start: 0,
+                end: 1440,
+            },
+        ];
+
+        let values = schedule_to_values(&entries, 2).unwrap();
+        let mut expected = vec![0; 14];
+        expected[0] = 0x01E0_0168;
+        expected[1] = 0x0528_03FC;
+        expected[12] = 0x05A0_0000;
+        assert_eq!(expected, values);
+        assert_eq!(entries, schedule_from_values(&values, 2));
+
+        assert_eq!(
+            Err("Schedule exceeds 1 entries on Mon".into()),
+            schedule_to_values(&entries, 1)
+        );
+
+        let invalid = ScheduleEntry {
+            day: Weekday::Tue,
+            start: 600,
+            end: 1500,
+        };
+        assert_eq!(
+            Err("Invalid schedule entry ScheduleEntry { day: Tue, start: 600, end: 1500 }".into()),
+            schedule_to_values(&[invalid], 1)
+        );
+    }
+}
@@ -0,0 +1,268 @@
+use std::time::Duration;
+
+use crate::error::Result;
+
+/// The kind of serial connection to the VBus, see
+/// `SerialOptions::set_profile`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SerialProfile {
+    /// A VBus/USB adapter or a controller's built-in USB port.
+    Usb,
+
+    /// A direct UART connection, e.g. a VBus adapter HAT on a Raspberry Pi.
+    Uart,
+}
+
+/// The parity of a serial port, see `SerialOptions::set_parity`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SerialParity {
+    /// No parity bit, used by the VBus.
+    None,
+
+    /// Even parity.
+    Even,
+
+    /// Odd parity.
+    Odd,
+}
+
+/// Settings used to open a serial port, see `ConnectionSpec::Serial`.
+///
+/// The port is always configured for 8 data bits, 1 stop bit and no flow
+/// control. Configuring the port requires the `serial` feature. Without
+/// it the port is opened as a plain file and must already be configured
+/// (e.g. using `stty`), so only the default settings are accepted.
+///
+/// # Examples
+///
+/// ```
+/// use std::time::Duration;
+///
+/// use async_resol_vbus::{SerialOptions, SerialProfile};
+///
+/// let mut options = SerialOptions::new();
+/// assert_eq!(Some(9600), options.baud_rate());
+///
+/// // detect the baud rate from the received frames
+/// options.set_baud_rate(None);
+/// options.set_max_framing_errors(1024);
+///
+/// // use the UART of a Raspberry Pi and reopen it if it fails
+/// options.set_profile(SerialProfile::Uart);
+/// options.set_reconnect_interval(Some(Duration::from_secs(2)));
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct SerialOptions {
+    baud_rate: Option<u32>,
+    max_framing_errors: usize,
+    profile: SerialProfile,
+    parity: SerialParity,
+    rts: Option<bool>,
+    dtr: Option<bool>,
+    reconnect_interval: Option<Duration>,
+}
+
+impl SerialOptions {
+    /// Create a new `SerialOptions` using 9600 baud.
+    pub fn new() -> SerialOptions {
+        SerialOptions::default()
+    }
+
+    /// Get the baud rate, `None` if it is detected automatically.
+    pub fn baud_rate(&self) -> Option<u32> {
+        self.baud_rate
+    }
+
+    /// Set the baud rate, `None` to detect it automatically.
+    ///
+    /// Auto-baud detection tries the common baud rates (9600 first) until
+    /// valid VBus frames are received.
+    pub fn set_baud_rate(&mut self, baud_rate: Option<u32>) {
+        self.baud_rate = baud_rate;
+    }
+
+    /// Get the number of consecutive bytes not belonging to a valid frame
+    /// after which the port is reopened.
+    pub fn max_framing_errors(&self) -> usize {
+        self.max_framing_errors
+    }
+
+    /// Set the number of consecutive bytes not belonging to a valid frame
+    /// after which the port is reopened (and the baud rate detected again,
+    /// if it is detected automatically). Defaults to 4096.
+    pub fn set_max_framing_errors(&mut self, max_framing_errors: usize) {
+        self.max_framing_errors = max_framing_errors;
+    }
+
+    /// Get the kind of serial connection.
+    pub fn profile(&self) -> SerialProfile {
+        self.profile
+    }
+
+    /// Set the kind of serial connection. It selects the ports tried for
+    /// the `auto` path: USB serial adapters for `Usb` (the default) and the
+    /// UARTs of a Raspberry Pi (`/dev/serial0`, `/dev/ttyAMA0` and
+    /// `/dev/ttyS0`) for `Uart`.
+    pub fn set_profile(&mut self, profile: SerialProfile) {
+        self.profile = profile;
+    }
+
+    /// Get the parity.
+    pub fn parity(&self) -> SerialParity {
+        self.parity
+    }
+
+    /// Set the parity. Defaults to `SerialParity::None`, which is used by
+    /// the VBus.
+    pub fn set_parity(&mut self, parity: SerialParity) {
+        self.parity = parity;
+    }
+
+    /// Get the level the RTS line is set to, `None` if it is left unchanged.
+    pub fn rts(&self) -> Option<bool> {
+        self.rts
+    }
+
+    /// Set the level of the RTS line after opening the port, e.g. to power
+    /// an adapter. `None` (the default) leaves it unchanged.
+    pub fn set_rts(&mut self, level: Option<bool>) {
+        self.rts = level;
+    }
+
+    /// Get the level the DTR line is set to, `None` if it is left unchanged.
+    pub fn dtr(&self) -> Option<bool> {
+        self.dtr
+    }
+
+    /// Set the level of the DTR line after opening the port. `None` (the
+    /// default) leaves it unchanged.
+    pub fn set_dtr(&mut self, level: Option<bool>) {
+        self.dtr = level;
+    }
+
+    /// Get the interval between two attempts to reopen a failed port.
+    pub fn reconnect_interval(&self) -> Option<Duration> {
+        self.reconnect_interval
+    }
+
+    /// Set the interval between two attempts to reopen the port after
+    /// reading from it failed, e.g. because the adapter was unplugged. The
+    /// `auto` path is resolved again for every attempt. While the port is
+    /// reopened, writing fails with an error.
+    ///
+    /// `None` (the default) ends the stream instead.
+    pub fn set_reconnect_interval(&mut self, interval: Option<Duration>) {
+        self.reconnect_interval = interval;
+    }
+
+    /// Apply a connection URL parameter, returning `false` for unknown
+    /// keys.
+    pub(crate) fn set_url_param(&mut self, key: &str, value: &str) -> Result<bool> {
+        match key {
+            "baud" => {
+                self.baud_rate = if value == "auto" {
+                    None
+                } else {
+                    Some(
+                        value
+                            .parse()
+                            .map_err(|_| format!("Invalid baud rate {:?}", value))?,
+                    )
+                };
+            }
+            "max_framing_errors" => {
+                self.max_framing_errors = value
+                    .parse()
+                    .map_err(|_| format!("Invalid max framing errors {:?}", value))?;
+            }
+            "profile" => {
+                self.profile = match value {
+                    "usb" => SerialProfile::Usb,
+                    "uart" => SerialProfile::Uart,
+                    _ => return Err(format!("Invalid serial profile {:?}", value).into()),
+                };
+            }
+            "parity" => {
+                self.parity = match value {
+                    "none" => SerialParity::None,
+                    "even" => SerialParity::Even,
+                    "odd" => SerialParity::Odd,
+                    _ => return Err(format!("Invalid parity {:?}", value).into()),
+                };
+            }
+            "rts" => self.rts = parse_level("RTS", value)?,
+            "dtr" => self.dtr = parse_level("DTR", value)?,
+            "reconnect" => {
+                let millis = value
+                    .parse()
+                    .map_err(|_| format!("Invalid reconnect interval {:?}", value))?;
+                self.reconnect_interval = Some(Duration::from_millis(millis));
+            }
+            _ => return Ok(false),
+        }
+        Ok(true)
+    }
+
+    /// Get the connection URL parameters for all non-default settings.
+    pub(crate) fn url_params(&self) -> Vec<String> {
+        let default = SerialOptions::default();
+        let mut params = Vec::new();
+        if self.baud_rate != default.baud_rate {
+            match self.baud_rate {
+                Some(baud_rate) => params.push(format!("baud={}", baud_rate)),
+                None => params.push("baud=auto".to_string()),
+            }
+        }
+        if self.max_framing_errors != default.max_framing_errors {
+            params.push(format!("max_framing_errors={}", self.max_framing_errors));
+        }
+        if self.profile == SerialProfile::Uart {
+            params.push("profile=uart".to_string());
+        }
+        match self.parity {
+            SerialParity::None => {}
+            SerialParity::Even => params.push("parity=even".to_string()),
+            SerialParity::Odd => params.push("parity=odd".to_string()),
+        }
+        if let Some(level) = self.rts {
+            params.push(format!("rts={}", format_level(level)));
+        }
+        if let Some(level) = self.dtr {
+            params.push(format!("dtr={}", format_level(level)));
+        }
+        if let Some(interval) = self.reconnect_interval {
+            params.push(format!("reconnect={}", interval.as_millis()));
+        }
+        params
+    }
+}
+
+impl Default for SerialOptions {
+    fn default() -> SerialOptions {
+        SerialOptions {
+            baud_rate: Some(9600),
+            max_framing_errors: 4096,
+            profile: SerialProfile::Usb,
+            parity: SerialParity::None,
+            rts: None,
+            dtr: None,
+            reconnect_interval: None,
+        }
+    }
+}
+
+fn parse_level(name: &str, value: &str) -> Result<Option<bool>> {
+    match value {
+        "on" => Ok(Some(true)),
+        "off" => Ok(Some(false)),
+        _ => Err(format!("Invalid {} level {:?}", name, value).into()),
+    }
+}
+
+fn format_level(level: bool) -> &'static str {
+    if level {
+        "on"
+    } else {
+        "off"
+    }
+}
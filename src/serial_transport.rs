@@ -0,0 +1,678 @@
+use std::{
+    io::{self, Read as _},
+    pin::Pin,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
+    },
+    task::{Context, Poll},
+    time::{Duration, Instant},
+};
+
+use async_std::io::{Read, Write};
+
+use blocking::Unblock;
+
+use resol_vbus::{live_data_decoder, StreamBlobLength};
+
+use serialport::{
+    DataBits, FlowControl, Parity, SerialPort, SerialPortInfo, SerialPortType, StopBits,
+};
+
+use crate::{
+    error::Result,
+    serial_options::{SerialOptions, SerialParity, SerialProfile},
+};
+
+/// USB vendor / product IDs of adapters commonly used to access the VBus.
+const KNOWN_ADAPTERS: &[(u16, u16, &str)] = &[
+    (0x0403, 0x6001, "FTDI FT232R"),
+    (0x0403, 0x6015, "FTDI FT231X"),
+    (0x10C4, 0xEA60, "Silicon Labs CP210x"),
+];
+
+/// The baud rates tried during auto-baud detection, most common first.
+const BAUD_RATES: &[u32] = &[9600, 19200, 38400, 57600, 115200, 4800];
+
+/// The time to wait for valid frames at every baud rate.
+const BAUD_DETECTION_WINDOW: Duration = Duration::from_secs(5);
+
+/// The UART devices of a Raspberry Pi, tried in this order by the `Uart`
+/// profile's `auto` mode.
+const UART_PORT_NAMES: &[&str] = &["/dev/serial0", "/dev/ttyAMA0", "/dev/ttyS0"];
+
+/// The read timeout of the port, which determines how quickly the reader
+/// notices that the stream was dropped.
+const READ_TIMEOUT: Duration = Duration::from_millis(500);
+
+/// A serial port together with the reason why it might be a VBus adapter,
+/// see `list_serial_ports`.
+///
+/// This type is only available if the `serial` feature is enabled.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SerialPortCandidate {
+    /// The name of the port, e.g. `/dev/ttyUSB0` or `COM3`.
+    pub port_name: String,
+
+    /// A human readable description of the port, e.g. its USB IDs and
+    /// product name.
+    pub description: String,
+
+    /// How likely the port is a VBus adapter: `3` for RESOL / VBus
+    /// products, `2` for commonly used USB serial adapters, `1` for other
+    /// USB ports and `0` for all other ports.
+    pub score: u8,
+}
+
+fn describe_usb_port(vid: u16, pid: u16, names: &str) -> (String, u8) {
+    let known = KNOWN_ADAPTERS
+        .iter()
+        .find(|(known_vid, known_pid, _)| *known_vid == vid && *known_pid == pid);
+
+    let score = if names.contains("RESOL") || names.contains("VBus") {
+        3
+    } else if known.is_some() {
+        2
+    } else {
+        1
+    };
+
+    let description = format!(
+        "USB {:04X}:{:04X} {}",
+        vid,
+        pid,
+        known.map(|(_, _, name)| *name).unwrap_or(names)
+    );
+
+    (description.trim().to_string(), score)
+}
+
+fn describe_port(info: &SerialPortInfo) -> (String, u8) {
+    match &info.port_type {
+        SerialPortType::UsbPort(usb) => {
+            let names = format!(
+                "{} {}",
+                usb.manufacturer.as_deref().unwrap_or(""),
+                usb.product.as_deref().unwrap_or("")
+            );
+            describe_usb_port(usb.vid, usb.pid, names.trim())
+        }
+        SerialPortType::PciPort => ("PCI".to_string(), 0),
+        SerialPortType::BluetoothPort => ("Bluetooth".to_string(), 0),
+        SerialPortType::Unknown => ("Unknown".to_string(), 0),
+    }
+}
+
+/// List all serial ports, most likely VBus adapters first.
+///
+/// This function is only available if the `serial` feature is enabled.
+///
+/// # Examples
+///
+/// ```no_run
+/// # fn main() -> async_resol_vbus::Result<()> {
+/// use async_resol_vbus::list_serial_ports;
+///
+/// for candidate in list_serial_ports()? {
+///     println!("{}\t{}", candidate.port_name, candidate.description);
+/// }
+/// # Ok(()) }
+/// ```
+pub fn list_serial_ports() -> Result<Vec<SerialPortCandidate>> {
+    let mut candidates = serialport::available_ports()?
+        .into_iter()
+        // macOS lists every port twice, prefer the call-out device
+        .filter(|info| !info.port_name.starts_with("/dev/tty."))
+        .map(|info| {
+            let (description, score) = describe_port(&info);
+            SerialPortCandidate {
+                port_name: info.port_name,
+                description,
+                score,
+            }
+        })
+        .collect::<Vec<_>>();
+
+    candidates.sort_by_key(|candidate| std::cmp::Reverse(candidate.score));
+
+    Ok(candidates)
+}
+
+/// Resolve the path of a serial port: `auto` picks the most likely VBus
+/// adapter returned by `list_serial_ports` for the `Usb` profile or the
+/// first existing UART for the `Uart` profile, see
+/// `SerialOptions::set_profile`. All other paths are returned unchanged.
+///
+/// Fails if `auto` is used, but no matching port exists.
+///
+/// This function is only available if the `serial` feature is enabled.
+pub fn resolve_serial_port_name(path: &str, profile: SerialProfile) -> Result<String> {
+    if path != "auto" {
+        return Ok(path.to_string());
+    }
+
+    match profile {
+        SerialProfile::Usb => match list_serial_ports()?.into_iter().next() {
+            Some(candidate) if candidate.score > 0 => Ok(candidate.port_name),
+            _ => Err("No USB serial adapter found".into()),
+        },
+        SerialProfile::Uart => match UART_PORT_NAMES
+            .iter()
+            .find(|name| std::path::Path::new(name).exists())
+        {
+            Some(name) => Ok(name.to_string()),
+            None => Err("No UART found".into()),
+        },
+    }
+}
+
+/// Counts the valid frames and the framing errors in the received bytes.
+#[derive(Debug, Default)]
+struct FrameScanner {
+    buf: Vec<u8>,
+    frame_count: usize,
+    framing_errors: usize,
+}
+
+impl FrameScanner {
+    fn extend_from_slice(&mut self, bytes: &[u8]) {
+        self.buf.extend_from_slice(bytes);
+
+        let mut start = 0;
+        while start < self.buf.len() {
+            match live_data_decoder::length_from_bytes(&self.buf[start..]) {
+                StreamBlobLength::BlobLength(len) => {
+                    self.frame_count += 1;
+                    self.framing_errors = 0;
+                    start += len;
+                }
+                StreamBlobLength::Partial => break,
+                StreamBlobLength::Malformed => {
+                    self.framing_errors += 1;
+                    start += 1;
+                }
+            }
+        }
+        self.buf.drain(0..start);
+    }
+}
+
+fn open_port(
+    port_name: &str,
+    baud_rate: u32,
+    options: &SerialOptions,
+) -> Result<Box<dyn SerialPort>> {
+    let parity = match options.parity() {
+        SerialParity::None => Parity::None,
+        SerialParity::Even => Parity::Even,
+        SerialParity::Odd => Parity::Odd,
+    };
+
+    let mut port = serialport::new(port_name, baud_rate)
+        .data_bits(DataBits::Eight)
+        .parity(parity)
+        .stop_bits(StopBits::One)
+        .flow_control(FlowControl::None)
+        .timeout(READ_TIMEOUT)
+        .open()?;
+
+    if let Some(level) = options.rts() {
+        port.write_request_to_send(level)?;
+    }
+    if let Some(level) = options.dtr() {
+        port.write_data_terminal_ready(level)?;
+    }
+
+    Ok(port)
+}
+
+/// Try the `BAUD_RATES` until valid frames are received.
+fn detect_baud_rate(port_name: &str, options: &SerialOptions) -> Result<u32> {
+    for baud_rate in BAUD_RATES.iter().copied() {
+        let mut port = open_port(port_name, baud_rate, options)?;
+
+        let mut scanner = FrameScanner::default();
+        let mut buf = [0; 4096];
+        let start = Instant::now();
+        while start.elapsed() < BAUD_DETECTION_WINDOW && scanner.frame_count < 2 {
+            match port.read(&mut buf) {
+                Ok(size) => scanner.extend_from_slice(&buf[0..size]),
+                Err(err) if err.kind() == io::ErrorKind::TimedOut => {}
+                Err(err) => return Err(err.into()),
+            }
+        }
+
+        if scanner.frame_count >= 2 {
+            return Ok(baud_rate);
+        }
+    }
+
+    Err(format!("Unable to detect baud rate on {}", port_name).into())
+}
+
+/// Resolve `path` and open the port, detecting the baud rate if
+/// necessary.
+fn open_configured_port(path: &str, options: &SerialOptions) -> Result<Box<dyn SerialPort>> {
+    let port_name = resolve_serial_port_name(path, options.profile())?;
+    let baud_rate = match options.baud_rate() {
+        Some(baud_rate) => baud_rate,
+        None => detect_baud_rate(&port_name, options)?,
+    };
+    open_port(&port_name, baud_rate, options)
+}
+
+/// The port used by the writer. It is replaced whenever the reader reopens
+/// the port.
+type SharedPort = Arc<Mutex<Option<Box<dyn SerialPort>>>>;
+
+/// The blocking read side of a serial port, reopening it if too many
+/// framing errors occur or, if enabled, reading fails.
+struct PortReader {
+    path: String,
+    options: SerialOptions,
+    port: Option<Box<dyn SerialPort>>,
+    writer_port: SharedPort,
+    scanner: FrameScanner,
+    closed: Arc<AtomicBool>,
+}
+
+impl PortReader {
+    /// Close all handles, the port is opened exclusively.
+    fn close(&mut self) {
+        self.port = None;
+        *self.writer_port.lock().unwrap() = None;
+        self.scanner = FrameScanner::default();
+    }
+
+    fn open(&mut self) -> io::Result<()> {
+        let port = open_configured_port(&self.path, &self.options)
+            .map_err(|err| io::Error::other(err.to_string()))?;
+        *self.writer_port.lock().unwrap() = Some(port.try_clone()?);
+        self.port = Some(port);
+        Ok(())
+    }
+
+    /// Reopen the port after reading failed with `err`, retrying every
+    /// reconnect interval until it succeeds or the reader is dropped.
+    fn reconnect(&mut self, err: io::Error) -> io::Result<()> {
+        let interval = match self.options.reconnect_interval() {
+            Some(interval) => interval,
+            None => return Err(err),
+        };
+
+        self.close();
+        while !self.closed.load(Ordering::SeqCst) {
+            std::thread::sleep(interval);
+            if self.open().is_ok() {
+                break;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl io::Read for PortReader {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        loop {
+            if self.closed.load(Ordering::SeqCst) {
+                return Ok(0);
+            }
+
+            let port = match self.port.as_mut() {
+                Some(port) => port,
+                None => return Err(io::ErrorKind::NotConnected.into()),
+            };
+
+            match port.read(buf) {
+                Ok(0) => self.reconnect(io::ErrorKind::UnexpectedEof.into())?,
+                Ok(size) => {
+                    self.scanner.extend_from_slice(&buf[0..size]);
+                    if self.scanner.framing_errors > self.options.max_framing_errors() {
+                        self.close();
+                        if let Err(err) = self.open() {
+                            self.reconnect(err)?;
+                        }
+                    }
+                    return Ok(size);
+                }
+                Err(err) if err.kind() == io::ErrorKind::TimedOut => {}
+                Err(err) => self.reconnect(err)?,
+            }
+        }
+    }
+}
+
+/// The blocking write side of a serial port.
+struct PortWriter {
+    port: SharedPort,
+}
+
+impl io::Write for PortWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match self.port.lock().unwrap().as_mut() {
+            Some(port) => port.write(buf),
+            None => Err(io::ErrorKind::NotConnected.into()),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match self.port.lock().unwrap().as_mut() {
+            Some(port) => port.flush(),
+            None => Err(io::ErrorKind::NotConnected.into()),
+        }
+    }
+}
+
+/// The read half of a serial port, see `open_serial_port`.
+///
+/// Dropping it stops the blocking reader within the read timeout.
+///
+/// This type is only available if the `serial` feature is enabled.
+pub struct SerialReader {
+    inner: Unblock<PortReader>,
+    closed: Arc<AtomicBool>,
+}
+
+impl std::fmt::Debug for SerialReader {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("SerialReader").finish_non_exhaustive()
+    }
+}
+
+impl Read for SerialReader {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<io::Result<usize>> {
+        Pin::new(&mut self.inner).poll_read(cx, buf)
+    }
+}
+
+impl Drop for SerialReader {
+    fn drop(&mut self) {
+        self.closed.store(true, Ordering::SeqCst);
+    }
+}
+
+/// The write half of a serial port, see `open_serial_port`.
+///
+/// This type is only available if the `serial` feature is enabled.
+pub struct SerialWriter {
+    inner: Unblock<PortWriter>,
+}
+
+impl std::fmt::Debug for SerialWriter {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("SerialWriter").finish_non_exhaustive()
+    }
+}
+
+impl Write for SerialWriter {
+    fn poll_write(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        Pin::new(&mut self.inner).poll_write(cx, buf)
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.inner).poll_flush(cx)
+    }
+
+    fn poll_close(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.inner).poll_close(cx)
+    }
+}
+
+/// Open and configure a serial port, returning its read and write half.
+///
+/// `path` is resolved using `resolve_serial_port_name` and the port is
+/// configured according to `options`, detecting the baud rate if
+/// necessary. If more than `SerialOptions::max_framing_errors` consecutive
+/// bytes do not belong to a valid frame, the port is reopened. If a
+/// reconnect interval is set, the port is also reopened after reading from
+/// it failed, e.g. because the adapter was unplugged and plugged back in.
+///
+/// This is used by `ConnectionSpec::connect` for serial ports and can be
+/// used directly to forward the raw bytes, e.g. to VBus-over-TCP clients.
+///
+/// This function is only available if the `serial` feature is enabled.
+///
+/// # Examples
+///
+/// ```no_run
+/// # fn main() -> async_resol_vbus::Result<()> { async_std::task::block_on(async {
+/// #
+/// use async_resol_vbus::{open_serial_port, LiveDataStream, SerialOptions};
+///
+/// let mut options = SerialOptions::new();
+/// options.set_baud_rate(None);
+///
+/// let (reader, writer) = open_serial_port("auto", &options).await?;
+/// let mut lds = LiveDataStream::new(reader, writer, 0, 0x0020);
+/// #
+/// # Ok(()) }) }
+/// ```
+pub async fn open_serial_port(
+    path: &str,
+    options: &SerialOptions,
+) -> Result<(SerialReader, SerialWriter)> {
+    let path = path.to_string();
+    let options = options.clone();
+    async_std::task::spawn_blocking(move || open_serial_port_blocking(&path, &options)).await
+}
+
+fn open_serial_port_blocking(
+    path: &str,
+    options: &SerialOptions,
+) -> Result<(SerialReader, SerialWriter)> {
+    let port = open_configured_port(path, options)?;
+    let writer_port = Arc::new(Mutex::new(Some(port.try_clone()?)));
+    let closed = Arc::new(AtomicBool::new(false));
+
+    let reader = PortReader {
+        path: path.to_string(),
+        options: options.clone(),
+        port: Some(port),
+        writer_port: writer_port.clone(),
+        scanner: FrameScanner::default(),
+        closed: closed.clone(),
+    };
+    let writer = PortWriter { port: writer_port };
+
+    let reader = SerialReader {
+        inner: Unblock::new(reader),
+        closed,
+    };
+    let writer = SerialWriter {
+        inner: Unblock::new(writer),
+    };
+    Ok((reader, writer))
+}
+
+#[cfg(test)]
+mod tests {
+    use async_std::prelude::*;
+
+    use crate::{live_data_stream::LiveDataStream, test_utils::extend_from_datagram};
+
+    use super::*;
+
+    /// Create a pseudo terminal, returning its master side and the path of
+    /// its slave side.
+    #[cfg(unix)]
+    fn open_pty() -> (serialport::TTYPort, String) {
+        let (master, slave) = serialport::TTYPort::pair().unwrap();
+        let path = slave.name().unwrap();
+        (master, path)
+    }
+
+    #[test]
+    fn test_frame_scanner() {
+        let mut bytes = vec![0x00, 0x55];
+        extend_from_datagram(&mut bytes, 0x0020, 0x7E11, 0x0100, 0x1234, 0x5678);
+
+        let mut scanner = FrameScanner::default();
+        scanner.extend_from_slice(&bytes[0..10]);
+        assert_eq!(0, scanner.frame_count);
+        assert_eq!(2, scanner.framing_errors);
+
+        scanner.extend_from_slice(&bytes[10..]);
+        assert_eq!(1, scanner.frame_count);
+        assert_eq!(0, scanner.framing_errors);
+        assert!(scanner.buf.is_empty());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_open_serial_port() -> Result<()> {
+        use std::io::{Read as _, Write as _};
+
+        async_std::task::block_on(async {
+            let (mut master, path) = open_pty();
+
+            let mut options = SerialOptions::new();
+            options.set_baud_rate(None);
+
+            // feed the auto-baud detection and the stream, writing fails
+            // while the port is reopened
+            let feeder = std::thread::spawn(move || {
+                let mut bytes = Vec::new();
+                extend_from_datagram(&mut bytes, 0x0020, 0x7E11, 0x0100, 0x1234, 0x5678);
+                for _ in 0..20 {
+                    master.write_all(&bytes).ok();
+                    std::thread::sleep(Duration::from_millis(100));
+                }
+                master
+            });
+
+            let (reader, mut writer) = open_serial_port(&path, &options).await?;
+
+            writer.write_all(&[0xAA, 0x10, 0x00]).await?;
+            writer.flush().await?;
+
+            let mut lds = LiveDataStream::new(reader, writer, 0, 0x0020);
+
+            let data = lds.receive_any_data(1000).await?.unwrap();
+            assert_eq!(0x5678, data.as_datagram().param32);
+
+            let mut master = feeder.join().unwrap();
+
+            let mut buf = [0; 3];
+            master.read_exact(&mut buf)?;
+            assert_eq!([0xAA, 0x10, 0x00], buf);
+
+            Ok(())
+        })
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_framing_recovery() -> Result<()> {
+        use std::io::Write as _;
+
+        async_std::task::block_on(async {
+            let (mut master, path) = open_pty();
+
+            let mut options = SerialOptions::new();
+            options.set_max_framing_errors(16);
+
+            let (reader, writer) = open_serial_port(&path, &options).await?;
+            let mut lds = LiveDataStream::new(reader, writer, 0, 0x0020);
+
+            let feeder = std::thread::spawn(move || {
+                master.write_all(&[0x55; 64]).unwrap();
+                std::thread::sleep(Duration::from_millis(500));
+
+                let mut bytes = Vec::new();
+                extend_from_datagram(&mut bytes, 0x0020, 0x7E11, 0x0100, 0x1234, 0x5678);
+                master.write_all(&bytes).unwrap();
+                master
+            });
+
+            let data = lds.receive_any_data(2000).await?.unwrap();
+            assert_eq!(0x5678, data.as_datagram().param32);
+
+            drop(feeder.join().unwrap());
+
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn test_describe_usb_port() {
+        assert_eq!(
+            ("USB 0403:6001 FTDI FT232R".to_string(), 2),
+            describe_usb_port(0x0403, 0x6001, "FTDI FT232R USB UART")
+        );
+        assert_eq!(
+            ("USB 1FEF:2018 RESOL VBus/USB".to_string(), 3),
+            describe_usb_port(0x1FEF, 0x2018, "RESOL VBus/USB")
+        );
+        assert_eq!(
+            ("USB 1234:5678 Other".to_string(), 1),
+            describe_usb_port(0x1234, 0x5678, "Other")
+        );
+    }
+
+    #[test]
+    fn test_resolve_serial_port_name() {
+        assert_eq!(
+            Ok("/dev/ttyUSB0".to_string()),
+            resolve_serial_port_name("/dev/ttyUSB0", SerialProfile::Usb)
+        );
+        assert_eq!(
+            Ok("/dev/ttyUSB0".to_string()),
+            resolve_serial_port_name("/dev/ttyUSB0", SerialProfile::Uart)
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_reconnect() -> Result<()> {
+        use std::io::Write as _;
+
+        async_std::task::block_on(async {
+            // a symlink simulates the device node of a replugged adapter
+            let link = std::env::temp_dir()
+                .join(format!("async-resol-vbus-reconnect-{}", std::process::id()));
+
+            let (master, path) = open_pty();
+            std::os::unix::fs::symlink(&path, &link)?;
+
+            let mut options = SerialOptions::new();
+            options.set_reconnect_interval(Some(Duration::from_millis(100)));
+
+            let (reader, writer) = open_serial_port(link.to_str().unwrap(), &options).await?;
+            let mut lds = LiveDataStream::new(reader, writer, 0, 0x0020);
+
+            // unplug
+            drop(master);
+            std::thread::sleep(Duration::from_millis(300));
+
+            // plug back in
+            let (mut master, path) = open_pty();
+            std::fs::remove_file(&link)?;
+            std::os::unix::fs::symlink(&path, &link)?;
+
+            let feeder = std::thread::spawn(move || {
+                let mut bytes = Vec::new();
+                extend_from_datagram(&mut bytes, 0x0020, 0x7E11, 0x0100, 0x1234, 0x5678);
+                for _ in 0..20 {
+                    master.write_all(&bytes).ok();
+                    std::thread::sleep(Duration::from_millis(100));
+                }
+            });
+
+            let data = lds.receive_any_data(2000).await?.unwrap();
+            assert_eq!(0x5678, data.as_datagram().param32);
+
+            feeder.join().unwrap();
+            std::fs::remove_file(&link)?;
+
+            Ok(())
+        })
+    }
+}
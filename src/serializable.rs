@@ -0,0 +1,339 @@
+use resol_vbus::{
+    chrono::{DateTime, Utc},
+    Data, Datagram, Header, Packet, Telegram,
+};
+
+use serde::{de::Error as _, Deserialize, Deserializer, Serialize, Serializer};
+
+#[derive(Serialize, Deserialize)]
+struct HeaderRepr {
+    timestamp: String,
+    channel: u8,
+    destination_address: u16,
+    source_address: u16,
+    protocol_version: u8,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+enum DataRepr {
+    Packet {
+        #[serde(flatten)]
+        header: HeaderRepr,
+        command: u16,
+        frame_data: String,
+    },
+    Datagram {
+        #[serde(flatten)]
+        header: HeaderRepr,
+        command: u16,
+        param16: i16,
+        param32: i32,
+    },
+    Telegram {
+        #[serde(flatten)]
+        header: HeaderRepr,
+        command: u8,
+        frame_data: String,
+    },
+}
+
+fn header_to_repr(header: &Header) -> HeaderRepr {
+    HeaderRepr {
+        timestamp: header.timestamp.to_rfc3339(),
+        channel: header.channel,
+        destination_address: header.destination_address,
+        source_address: header.source_address,
+        protocol_version: header.protocol_version,
+    }
+}
+
+fn header_from_repr<E: serde::de::Error>(repr: HeaderRepr) -> Result<Header, E> {
+    let timestamp = DateTime::parse_from_rfc3339(&repr.timestamp)
+        .map_err(E::custom)?
+        .with_timezone(&Utc);
+
+    Ok(Header {
+        timestamp,
+        channel: repr.channel,
+        destination_address: repr.destination_address,
+        source_address: repr.source_address,
+        protocol_version: repr.protocol_version,
+    })
+}
+
+fn hex_encode(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+fn hex_decode_into<E: serde::de::Error>(s: &str, buf: &mut [u8]) -> Result<usize, E> {
+    if !s.len().is_multiple_of(2) || s.len() / 2 > buf.len() {
+        return Err(E::custom("invalid frame data length"));
+    }
+
+    for (idx, byte) in buf.iter_mut().take(s.len() / 2).enumerate() {
+        let digits = s
+            .get(idx * 2..idx * 2 + 2)
+            .ok_or_else(|| E::custom("invalid frame data"))?;
+        *byte = u8::from_str_radix(digits, 16).map_err(E::custom)?;
+    }
+
+    Ok(s.len() / 2)
+}
+
+fn data_to_repr(data: &Data) -> DataRepr {
+    match data {
+        Data::Packet(packet) => DataRepr::Packet {
+            header: header_to_repr(&packet.header),
+            command: packet.command,
+            frame_data: hex_encode(packet.valid_frame_data()),
+        },
+        Data::Datagram(dgram) => DataRepr::Datagram {
+            header: header_to_repr(&dgram.header),
+            command: dgram.command,
+            param16: dgram.param16,
+            param32: dgram.param32,
+        },
+        Data::Telegram(tgram) => {
+            let len = Telegram::frame_count_from_command(tgram.command) as usize * 7;
+            DataRepr::Telegram {
+                header: header_to_repr(&tgram.header),
+                command: tgram.command,
+                frame_data: hex_encode(&tgram.frame_data[0..len]),
+            }
+        }
+    }
+}
+
+fn data_from_repr<E: serde::de::Error>(repr: DataRepr) -> Result<Data, E> {
+    let data = match repr {
+        DataRepr::Packet {
+            header,
+            command,
+            frame_data,
+        } => {
+            let mut buf = [0u8; 508];
+            let len = hex_decode_into::<E>(&frame_data, &mut buf)?;
+            if !len.is_multiple_of(4) {
+                return Err(E::custom("frame data length must be a multiple of 4"));
+            }
+
+            Data::Packet(Packet {
+                header: header_from_repr(header)?,
+                command,
+                frame_count: (len / 4) as u8,
+                frame_data: buf,
+            })
+        }
+        DataRepr::Datagram {
+            header,
+            command,
+            param16,
+            param32,
+        } => Data::Datagram(Datagram {
+            header: header_from_repr(header)?,
+            command,
+            param16,
+            param32,
+        }),
+        DataRepr::Telegram {
+            header,
+            command,
+            frame_data,
+        } => {
+            let mut buf = [0u8; 21];
+            hex_decode_into::<E>(&frame_data, &mut buf)?;
+
+            Data::Telegram(Telegram {
+                header: header_from_repr(header)?,
+                command,
+                frame_data: buf,
+            })
+        }
+    };
+
+    Ok(data)
+}
+
+/// A wrapper around `Data` implementing `serde::Serialize` and
+/// `serde::Deserialize`.
+///
+/// The `Data` is represented as a map containing a `type` discriminator
+/// (`"packet"`, `"datagram"` or `"telegram"`), the header fields, the
+/// timestamp formatted according to RFC 3339 and the type-specific fields.
+/// The valid part of the frame data is represented as a hex string.
+///
+/// This type is only available if the `serde` feature is enabled.
+///
+/// # Examples
+///
+/// ```
+/// use async_resol_vbus::{Data, Datagram, Header, SerializableData};
+///
+/// let data = Data::Datagram(Datagram {
+///     header: Header::default(),
+///     command: 0x0100,
+///     param16: 0x1234,
+///     param32: 0x56789abc,
+/// });
+///
+/// let wrapper = SerializableData::from(data);
+/// assert!(wrapper.0.is_datagram());
+/// ```
+#[derive(Debug, Clone)]
+pub struct SerializableData(pub Data);
+
+impl From<Data> for SerializableData {
+    fn from(data: Data) -> SerializableData {
+        SerializableData(data)
+    }
+}
+
+impl From<SerializableData> for Data {
+    fn from(wrapper: SerializableData) -> Data {
+        wrapper.0
+    }
+}
+
+impl Serialize for SerializableData {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        data_to_repr(&self.0).serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for SerializableData {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<SerializableData, D::Error> {
+        let repr = DataRepr::deserialize(deserializer)?;
+        Ok(SerializableData(data_from_repr(repr)?))
+    }
+}
+
+/// A wrapper around `Datagram` implementing `serde::Serialize` and
+/// `serde::Deserialize`.
+///
+/// The representation is identical to the one of `SerializableData`.
+///
+/// This type is only available if the `serde` feature is enabled.
+#[derive(Debug, Clone)]
+pub struct SerializableDatagram(pub Datagram);
+
+impl From<Datagram> for SerializableDatagram {
+    fn from(dgram: Datagram) -> SerializableDatagram {
+        SerializableDatagram(dgram)
+    }
+}
+
+impl From<SerializableDatagram> for Datagram {
+    fn from(wrapper: SerializableDatagram) -> Datagram {
+        wrapper.0
+    }
+}
+
+impl Serialize for SerializableDatagram {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let dgram = &self.0;
+        DataRepr::Datagram {
+            header: header_to_repr(&dgram.header),
+            command: dgram.command,
+            param16: dgram.param16,
+            param32: dgram.param32,
+        }
+        .serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for SerializableDatagram {
+    fn deserialize<D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<SerializableDatagram, D::Error> {
+        match data_from_repr(DataRepr::deserialize(deserializer)?)? {
+            Data::Datagram(dgram) => Ok(SerializableDatagram(dgram)),
+            _ => Err(D::Error::custom("expected a datagram")),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use resol_vbus::utils::utc_timestamp;
+
+    use super::*;
+
+    fn header() -> Header {
+        Header {
+            timestamp: utc_timestamp(1485688933),
+            channel: 1,
+            destination_address: 0x0010,
+            source_address: 0x7E11,
+            protocol_version: 0x10,
+        }
+    }
+
+    #[test]
+    fn test_packet() {
+        let mut frame_data = [0u8; 508];
+        frame_data[0..8].copy_from_slice(&[1, 2, 3, 4, 5, 6, 7, 8]);
+
+        let data = Data::Packet(Packet {
+            header: header(),
+            command: 0x0100,
+            frame_count: 2,
+            frame_data,
+        });
+
+        let json = serde_json::to_string(&SerializableData(data.clone())).unwrap();
+
+        assert_eq!(
+            "{\"type\":\"packet\",\"timestamp\":\"2017-01-29T11:22:13+00:00\",\"channel\":1,\"destination_address\":16,\"source_address\":32273,\"protocol_version\":16,\"command\":256,\"frame_data\":\"0102030405060708\"}",
+            json
+        );
+
+        let result = serde_json::from_str::<SerializableData>(&json).unwrap().0;
+
+        assert_eq!(data, result);
+        assert_eq!(2, result.as_packet().frame_count);
+        assert_eq!(&frame_data[..], &result.as_packet().frame_data[..]);
+    }
+
+    #[test]
+    fn test_datagram() {
+        let dgram = Datagram {
+            header: Header {
+                protocol_version: 0x20,
+                ..header()
+            },
+            command: 0x0100,
+            param16: 0x1234,
+            param32: -2,
+        };
+
+        let json = serde_json::to_string(&SerializableDatagram(dgram.clone())).unwrap();
+
+        assert_eq!(
+            "{\"type\":\"datagram\",\"timestamp\":\"2017-01-29T11:22:13+00:00\",\"channel\":1,\"destination_address\":16,\"source_address\":32273,\"protocol_version\":32,\"command\":256,\"param16\":4660,\"param32\":-2}",
+            json
+        );
+
+        let result = serde_json::from_str::<SerializableData>(&json).unwrap().0;
+        assert_eq!(
+            dgram.header.timestamp,
+            result.as_datagram().header.timestamp
+        );
+        assert_eq!(Data::Datagram(dgram), result);
+        assert_eq!(-2, result.as_datagram().param32);
+
+        let result = serde_json::from_str::<SerializableDatagram>(&json)
+            .unwrap()
+            .0;
+        assert_eq!(0x1234, result.param16);
+    }
+
+    #[test]
+    fn test_invalid() {
+        let json = "{\"type\":\"packet\",\"timestamp\":\"2017-01-29T11:22:13+00:00\",\"channel\":1,\"destination_address\":16,\"source_address\":32273,\"protocol_version\":16,\"command\":256,\"frame_data\":\"010203\"}";
+        assert!(serde_json::from_str::<SerializableData>(json).is_err());
+
+        let json = json.replace("\"type\":\"packet\"", "\"type\":\"unknown\"");
+        assert!(serde_json::from_str::<SerializableData>(&json).is_err());
+    }
+}
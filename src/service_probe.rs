@@ -0,0 +1,162 @@
+use std::{net::SocketAddr, time::Duration};
+
+use async_std::{
+    io::{Read, Write},
+    net::TcpStream,
+    prelude::*,
+};
+
+use crate::error::Result;
+
+/// The result of `probe_vbus_service`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct VbusServiceProbe {
+    /// Whether the host greeted with a VBus-over-TCP `+HELLO` banner.
+    pub is_vbus: bool,
+
+    /// The first line sent by the host, without the line ending.
+    pub banner: Option<String>,
+
+    /// Whether the service requires a `PASS` command before `DATA`.
+    ///
+    /// `None` if the host is not a VBus-over-TCP service or did not reply.
+    pub password_required: Option<bool>,
+}
+
+pub(crate) async fn read_line<S: Read + Unpin>(stream: &mut S) -> Result<Option<String>> {
+    let mut line = Vec::new();
+    let mut buf = [0u8; 1];
+    loop {
+        if stream.read(&mut buf).await? == 0 {
+            break;
+        }
+        if buf[0] == b'\n' {
+            break;
+        }
+        line.push(buf[0]);
+    }
+
+    if line.is_empty() {
+        Ok(None)
+    } else {
+        let line = String::from_utf8_lossy(&line);
+        Ok(Some(line.trim_end_matches('\r').to_string()))
+    }
+}
+
+async fn probe<S: Read + Write + Unpin>(mut stream: S) -> Result<VbusServiceProbe> {
+    let banner = read_line(&mut stream).await?;
+    let is_vbus = banner.as_deref().is_some_and(|b| b.starts_with("+HELLO"));
+
+    let password_required = if is_vbus {
+        stream.write_all(b"DATA\r\n").await?;
+        match read_line(&mut stream).await? {
+            Some(reply) if reply.starts_with('+') => Some(false),
+            Some(reply) if reply.starts_with('-') => Some(true),
+            _ => None,
+        }
+    } else {
+        None
+    };
+
+    Ok(VbusServiceProbe {
+        is_vbus,
+        banner,
+        password_required,
+    })
+}
+
+/// Check whether the host at `address` speaks VBus-over-TCP.
+///
+/// The greeting banner is read and a `DATA` command is sent without a
+/// prior `PASS` command to find out whether a password is required. The
+/// connection is closed afterwards, so no full handshake is performed.
+///
+/// # Examples
+///
+/// ```no_run
+/// # fn main() -> async_resol_vbus::Result<()> { async_std::task::block_on(async {
+/// #
+/// use std::time::Duration;
+///
+/// use async_resol_vbus::probe_vbus_service;
+///
+/// let address = "192.168.5.217:7053".parse()?;
+/// let probe = probe_vbus_service(address, Duration::from_secs(2)).await?;
+/// if probe.is_vbus {
+///     println!("{:?}, password required: {:?}", probe.banner, probe.password_required);
+/// }
+/// #
+/// # Ok(()) }) }
+/// ```
+pub async fn probe_vbus_service(
+    address: SocketAddr,
+    timeout: Duration,
+) -> Result<VbusServiceProbe> {
+    async_std::future::timeout(timeout, async {
+        let stream = TcpStream::connect(address).await?;
+        probe(stream).await
+    })
+    .await?
+}
+
+#[cfg(test)]
+mod tests {
+    use async_std::net::TcpListener;
+
+    use super::*;
+
+    use crate::TcpServerHandshake;
+
+    #[test]
+    fn test_probe_vbus_service() -> Result<()> {
+        async_std::task::block_on(async {
+            let listener = TcpListener::bind("127.0.0.1:0").await?;
+            let address = listener.local_addr()?;
+
+            let server_future = async_std::task::spawn::<_, Result<()>>(async move {
+                let (stream, _) = listener.accept().await?;
+                let hs = TcpServerHandshake::start(stream).await?;
+                hs.receive_data_command().await?;
+
+                let (stream, _) = listener.accept().await?;
+                let mut hs = TcpServerHandshake::start(stream).await?;
+                let result = hs
+                    .receive_command(|command, _| async move {
+                        if command == "PASS" {
+                            Ok(())
+                        } else {
+                            Err("-ERROR: Need password\r\n")
+                        }
+                    })
+                    .await;
+                assert!(result.is_err());
+
+                let (mut stream, _) = listener.accept().await?;
+                stream.write_all(b"SSH-2.0-OpenSSH\r\n").await?;
+
+                Ok(())
+            });
+
+            let timeout = Duration::from_secs(2);
+
+            let probe = probe_vbus_service(address, timeout).await?;
+            assert!(probe.is_vbus);
+            assert_eq!(Some("+HELLO"), probe.banner.as_deref());
+            assert_eq!(Some(false), probe.password_required);
+
+            let probe = probe_vbus_service(address, timeout).await?;
+            assert!(probe.is_vbus);
+            assert_eq!(Some(true), probe.password_required);
+
+            let probe = probe_vbus_service(address, timeout).await?;
+            assert!(!probe.is_vbus);
+            assert_eq!(Some("SSH-2.0-OpenSSH"), probe.banner.as_deref());
+            assert_eq!(None, probe.password_required);
+
+            server_future.await?;
+
+            Ok(())
+        })
+    }
+}
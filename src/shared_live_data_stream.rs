@@ -0,0 +1,158 @@
+use std::{marker::Unpin, sync::Arc};
+
+use async_std::{
+    io::{Read, Write},
+    sync::{Mutex, MutexGuard},
+};
+
+use resol_vbus::{Data, Datagram};
+
+use crate::{error::Result, live_data_stream::LiveDataStream};
+
+/// A cloneable handle to a `LiveDataStream` shared between multiple tasks.
+///
+/// All clones refer to the same `LiveDataStream`, which is protected by an
+/// asynchronous mutex. Every method locks the stream for the duration of a
+/// single transaction, so that transactions from different tasks do not
+/// interleave on the wire. Tasks waiting for the lock are served in the
+/// order they started waiting.
+///
+/// If the stream's reader and writer are `Send` (like for a
+/// `TcpLiveDataStream`), the handle is `Send + Sync` and can be used from
+/// spawned tasks.
+///
+/// # Examples
+///
+/// ```no_run
+/// # fn main() -> async_resol_vbus::Result<()> { async_std::task::block_on(async {
+/// #
+/// use async_std::net::{SocketAddr, TcpStream};
+///
+/// use async_resol_vbus::{connect_live_data_stream, ConnectOptions, SharedLiveDataStream};
+///
+/// let address = "192.168.5.217:7053".parse::<SocketAddr>()?;
+/// let stream = TcpStream::connect(address).await?;
+/// let lds = connect_live_data_stream(stream, &ConnectOptions::new()).await?;
+///
+/// let shared = SharedLiveDataStream::new(lds);
+///
+/// let other = shared.clone();
+/// async_std::task::spawn(async move {
+///     let value = other.get_value_by_index(0x7E11, 0x0123, 0).await;
+/// });
+///
+/// // Use the lock for transactions consisting of multiple steps
+/// let mut lds = shared.lock().await;
+/// let session = lds.acquire_bus(0x7E11).await?;
+/// session.release().await?;
+/// #
+/// # Ok(()) }) }
+/// ```
+#[derive(Debug)]
+pub struct SharedLiveDataStream<R: Read + Unpin, W: Write + Unpin> {
+    inner: Arc<Mutex<LiveDataStream<R, W>>>,
+}
+
+impl<R: Read + Unpin, W: Write + Unpin> SharedLiveDataStream<R, W> {
+    /// Create a new `SharedLiveDataStream` taking ownership of `stream`.
+    pub fn new(stream: LiveDataStream<R, W>) -> SharedLiveDataStream<R, W> {
+        SharedLiveDataStream {
+            inner: Arc::new(Mutex::new(stream)),
+        }
+    }
+
+    /// Lock the underlying `LiveDataStream` for exclusive use.
+    ///
+    /// Other tasks using this handle have to wait until the returned guard
+    /// is dropped.
+    pub async fn lock(&self) -> MutexGuard<'_, LiveDataStream<R, W>> {
+        self.inner.lock().await
+    }
+
+    /// Receive any `Data`, see `LiveDataStream::receive_any_data`.
+    pub async fn receive_any_data(&self, timeout_ms: u64) -> Result<Option<Data>> {
+        self.lock().await.receive_any_data(timeout_ms).await
+    }
+
+    /// Get a value by its index, see `LiveDataStream::get_value_by_index`.
+    pub async fn get_value_by_index(
+        &self,
+        address: u16,
+        index: i16,
+        subindex: u8,
+    ) -> Result<Option<Datagram>> {
+        self.lock()
+            .await
+            .get_value_by_index(address, index, subindex)
+            .await
+    }
+
+    /// Set a value by its index, see `LiveDataStream::set_value_by_index`.
+    pub async fn set_value_by_index(
+        &self,
+        address: u16,
+        index: i16,
+        subindex: u8,
+        value: i32,
+    ) -> Result<Option<Datagram>> {
+        self.lock()
+            .await
+            .set_value_by_index(address, index, subindex, value)
+            .await
+    }
+}
+
+impl<R: Read + Unpin, W: Write + Unpin> Clone for SharedLiveDataStream<R, W> {
+    fn clone(&self) -> SharedLiveDataStream<R, W> {
+        SharedLiveDataStream {
+            inner: self.inner.clone(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use async_std::{io::Cursor, net::TcpStream};
+
+    use crate::test_utils::{extend_from_datagram, hex_encode, simulate_run};
+
+    use super::*;
+
+    fn assert_send_sync<T: Send + Sync + Clone + 'static>() {}
+
+    #[test]
+    fn test_send_sync() {
+        assert_send_sync::<SharedLiveDataStream<TcpStream, TcpStream>>();
+    }
+
+    #[test]
+    fn test_shared() {
+        let mut rx_buf = Vec::new();
+        let tx_buf = Cursor::new(Vec::new());
+
+        extend_from_datagram(&mut rx_buf, 0x0020, 0x7E11, 0x0100, 0x1234, 0x11);
+        extend_from_datagram(&mut rx_buf, 0x0020, 0x7E11, 0x0100, 0x1235, 0x22);
+
+        let shared = SharedLiveDataStream::new(LiveDataStream::new(&rx_buf[..], tx_buf, 0, 0x0020));
+        let other = shared.clone();
+
+        simulate_run(async {
+            let dgram = shared.get_value_by_index(0x7E11, 0x1234, 0).await?.unwrap();
+            assert_eq!(0x11, dgram.param32);
+
+            let dgram = other
+                .set_value_by_index(0x7E11, 0x1235, 0, 0x22)
+                .await?
+                .unwrap();
+            assert_eq!(0x22, dgram.param32);
+
+            assert_eq!(
+                "aa117e20002000033412000000000067aa117e20002000023512220000000045",
+                hex_encode(shared.lock().await.writer_ref())
+            );
+
+            Result::Ok(())
+        })
+        .unwrap();
+    }
+}
@@ -0,0 +1,969 @@
+use std::{
+    net::Shutdown,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use async_std::{
+    channel::{Receiver, Sender},
+    net::{TcpListener, TcpStream},
+    prelude::*,
+    sync::{Mutex, MutexGuard},
+};
+
+use resol_vbus::{
+    chrono::Utc,
+    live_data_decoder::{data_from_bytes, length_from_bytes},
+    Data, StreamBlobLength,
+};
+
+use crate::{
+    error::Result, shutdown::ShutdownSignal, tcp_server_handshake::TcpServerHandshake,
+    write_guard::WriteGuard,
+};
+
+/// Decides which downstream clients of a `SharingServer` may send data to
+/// the upstream connection.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum WriteArbitration {
+    /// Data sent by any client is forwarded.
+    AllowAll,
+
+    /// Only data sent by the client that has been connected the longest is
+    /// forwarded. Once it disconnects, the next client takes over.
+    FirstClient,
+
+    /// Data sent by clients is discarded.
+    ReadOnly,
+
+    /// Clients take turns in sending data.
+    ///
+    /// A turn starts when the upstream connection offers the bus (datagram
+    /// command `0x0500`) and is granted to the next client in round-robin
+    /// order that has data to send. The turn ends when that client releases
+    /// the bus (datagram command `0x0600`), disconnects or has not sent any
+    /// data for the turn timeout. Data sent by other clients is queued until
+    /// their turn, so that their transactions are not interleaved. Queued
+    /// data older than the turn timeout is dropped. Clients queueing more
+    /// frames than allowed by `SharingServer::set_max_pending_frames` are
+    /// disconnected.
+    Fair,
+}
+
+/// The state of the `WriteArbitration::Fair` turns.
+#[derive(Debug, Default)]
+struct TurnState {
+    /// The client holding the current turn and the time of its last frame.
+    holder: Option<(usize, Instant)>,
+
+    /// The client that held the previous turn.
+    last_holder: Option<usize>,
+
+    /// The queued frames of clients waiting for their turn and the time
+    /// they were queued.
+    pending: Vec<(usize, Instant, Vec<u8>)>,
+}
+
+impl TurnState {
+    fn release(&mut self) {
+        if let Some((id, _)) = self.holder.take() {
+            self.last_holder = Some(id);
+        }
+    }
+
+    /// Drop the frames queued for longer than `timeout`.
+    fn drop_expired(&mut self, timeout: Duration) {
+        self.pending
+            .retain(|(_, queued, _)| queued.elapsed() < timeout);
+    }
+
+    /// Grant the turn to the next client with queued frames and return
+    /// its ID and the frames to forward.
+    ///
+    /// If those frames release the bus, the turn ends right away and the
+    /// frames following the release stay queued for the next turn.
+    fn grant_next(&mut self) -> Option<(usize, Vec<Vec<u8>>)> {
+        let next = {
+            let mut ids = self
+                .pending
+                .iter()
+                .map(|(id, _, _)| *id)
+                .collect::<Vec<_>>();
+            ids.sort_unstable();
+            ids.dedup();
+
+            match self.last_holder {
+                Some(last) => ids
+                    .iter()
+                    .find(|id| **id > last)
+                    .or_else(|| ids.first())
+                    .copied(),
+                None => ids.first().copied(),
+            }
+        };
+
+        let next = next?;
+        self.holder = Some((next, Instant::now()));
+
+        let (granted, pending): (Vec<_>, Vec<_>) =
+            self.pending.drain(..).partition(|(id, _, _)| *id == next);
+        self.pending = pending;
+
+        let mut granted = granted.into_iter();
+        let mut frames = Vec::new();
+        for (_, _, frame) in &mut granted {
+            let is_release = datagram_command(&frame) == Some(0x0600);
+            frames.push(frame);
+            if is_release {
+                self.release();
+                break;
+            }
+        }
+        self.pending.extend(granted);
+
+        Some((next, frames))
+    }
+}
+
+/// A client connected to a running `SharingServer`.
+#[derive(Debug)]
+struct Client {
+    id: usize,
+    stream: TcpStream,
+
+    /// The queue of bytes written to the client by its writer task.
+    sender: Sender<Arc<[u8]>>,
+}
+
+/// The state shared between the tasks of a running `SharingServer`.
+#[derive(Debug)]
+struct Shared {
+    server: SharingServer,
+    clients: Mutex<Vec<Client>>,
+    turn: Mutex<TurnState>,
+    upstream: Mutex<TcpStream>,
+}
+
+/// Shares a single upstream VBus-over-TCP connection with multiple local
+/// clients.
+///
+/// The server accepts VBus-over-TCP clients (e.g. RSC, RPT or other tools)
+/// on a local `TcpListener`. All data received from the upstream
+/// connection is forwarded to every client. Data sent by clients is split
+/// into complete frames, which are forwarded to the upstream connection and
+/// the other clients according to the configured `WriteArbitration`.
+///
+/// Every client is written to by its own task. Clients that do not keep up
+/// with the data (e.g. because they stopped reading) are disconnected, so
+/// that they cannot stall the upstream connection or the other clients.
+///
+/// # Examples
+///
+/// ```no_run
+/// # fn main() -> async_resol_vbus::Result<()> { async_std::task::block_on(async {
+/// #
+/// use async_std::net::{TcpListener, TcpStream};
+///
+/// use async_resol_vbus::{connect_live_data_stream, ConnectOptions, SharingServer, WriteArbitration};
+///
+/// let stream = TcpStream::connect("192.168.5.217:7053").await?;
+/// let lds = connect_live_data_stream(stream, &ConnectOptions::new()).await?;
+/// let (upstream, _) = lds.into_inner();
+///
+/// let listener = TcpListener::bind("127.0.0.1:7053").await?;
+///
+/// let mut server = SharingServer::new();
+/// server.set_password(Some("vbus".into()));
+/// server.set_write_arbitration(WriteArbitration::Fair);
+/// server.run(listener, upstream).await?;
+/// #
+/// # Ok(()) }) }
+/// ```
+#[derive(Debug)]
+pub struct SharingServer {
+    password: Option<String>,
+    write_arbitration: WriteArbitration,
+    turn_timeout: Duration,
+    client_queue_capacity: usize,
+    client_write_timeout: Duration,
+    max_pending_frames: usize,
+    write_guard: Option<WriteGuard>,
+}
+
+impl SharingServer {
+    /// Create a new `SharingServer`.
+    pub fn new() -> SharingServer {
+        SharingServer {
+            password: None,
+            write_arbitration: WriteArbitration::AllowAll,
+            turn_timeout: Duration::from_secs(5),
+            client_queue_capacity: 256,
+            client_write_timeout: Duration::from_secs(5),
+            max_pending_frames: 64,
+            write_guard: None,
+        }
+    }
+
+    /// Set the password clients have to provide using the `PASS` command.
+    ///
+    /// Defaults to `None`, which accepts clients with any or no password.
+    pub fn set_password(&mut self, password: Option<String>) {
+        self.password = password;
+    }
+
+    /// Set the `WriteArbitration` between clients.
+    ///
+    /// Defaults to `WriteArbitration::AllowAll`.
+    pub fn set_write_arbitration(&mut self, write_arbitration: WriteArbitration) {
+        self.write_arbitration = write_arbitration;
+    }
+
+    /// Set the time after which an idle client loses its turn when using
+    /// `WriteArbitration::Fair`.
+    ///
+    /// Defaults to 5 seconds.
+    pub fn set_turn_timeout(&mut self, turn_timeout: Duration) {
+        self.turn_timeout = turn_timeout;
+    }
+
+    /// Set the number of chunks of data queued for each client.
+    ///
+    /// A client whose queue is full when new data arrives is disconnected.
+    ///
+    /// Defaults to 256.
+    pub fn set_client_queue_capacity(&mut self, capacity: usize) {
+        self.client_queue_capacity = capacity;
+    }
+
+    /// Set the time after which a client is disconnected if writing a chunk
+    /// of data to it does not complete.
+    ///
+    /// Defaults to 5 seconds.
+    pub fn set_client_write_timeout(&mut self, write_timeout: Duration) {
+        self.client_write_timeout = write_timeout;
+    }
+
+    /// Set the number of frames queued for each client waiting for its turn
+    /// when using `WriteArbitration::Fair`.
+    ///
+    /// A client sending more frames before its turn starts is disconnected.
+    ///
+    /// Defaults to 64.
+    pub fn set_max_pending_frames(&mut self, max_pending_frames: usize) {
+        self.max_pending_frames = max_pending_frames;
+    }
+
+    /// Set the `WriteGuard` restricting the values clients can write.
+    ///
+    /// Datagrams sent by clients that write a denied value index or begin
+    /// or commit a denied bulk value transaction are discarded before the
+    /// `WriteArbitration` is applied. Client frames are forwarded without
+    /// performing transactions of their own, so the ID hash of a written
+    /// index is unknown and ID rules are applied as described in
+    /// `WriteGuard::is_allowed`.
+    ///
+    /// Defaults to `None`, which forwards all datagrams.
+    pub fn set_write_guard(&mut self, write_guard: Option<WriteGuard>) {
+        self.write_guard = write_guard;
+    }
+
+    /// Check whether the `WriteGuard` allows forwarding a frame sent by a
+    /// client. Datagrams that cannot be decoded are not forwarded.
+    fn is_client_frame_allowed(&self, frame: &[u8]) -> bool {
+        match self.write_guard {
+            Some(ref guard) if datagram_command(frame).is_some() => {
+                match data_from_bytes(Utc::now(), 0, frame) {
+                    Some(Data::Datagram(dgram)) => guard.is_datagram_allowed(&dgram),
+                    _ => false,
+                }
+            }
+            _ => true,
+        }
+    }
+
+    /// Accept clients on `listener` and share the `upstream` connection,
+    /// which must already have completed its handshake.
+    ///
+    /// Returns after the upstream connection was closed, disconnecting all
+    /// clients.
+    pub async fn run(self, listener: TcpListener, upstream: TcpStream) -> Result<()> {
+        self.run_with_signal(listener, upstream, None).await
+    }
+
+    /// Like `run`, but also returns once the shutdown is triggered.
+    ///
+    /// After the shutdown was triggered, the listener is closed, all
+    /// clients are disconnected and the result is reported to the
+    /// `ShutdownSignal`.
+    pub async fn run_until_shutdown(
+        self,
+        listener: TcpListener,
+        upstream: TcpStream,
+        signal: ShutdownSignal,
+    ) {
+        let result = self
+            .run_with_signal(listener, upstream, Some(&signal))
+            .await;
+        signal.complete(result);
+    }
+
+    async fn run_with_signal(
+        self,
+        listener: TcpListener,
+        upstream: TcpStream,
+        signal: Option<&ShutdownSignal>,
+    ) -> Result<()> {
+        let shared = Arc::new(Shared {
+            server: self,
+            clients: Mutex::new(Vec::new()),
+            turn: Mutex::new(TurnState::default()),
+            upstream: Mutex::new(upstream.clone()),
+        });
+
+        let accept_task = async_std::task::spawn(accept_clients(shared.clone(), listener));
+
+        let result = match signal {
+            Some(signal) => signal
+                .run_until(forward_upstream(&shared, upstream))
+                .await
+                .unwrap_or(Ok(())),
+            None => forward_upstream(&shared, upstream).await,
+        };
+
+        accept_task.cancel().await;
+
+        for client in shared.clients.lock().await.drain(..) {
+            let _ = client.stream.shutdown(Shutdown::Both);
+        }
+
+        result
+    }
+
+    async fn accept_client(&self, stream: TcpStream) -> Result<TcpStream> {
+        TcpServerHandshake::start(stream)
+            .await?
+            .receive_pass_and_data_commands(self.password.as_deref())
+            .await
+    }
+}
+
+impl Default for SharingServer {
+    fn default() -> Self {
+        SharingServer::new()
+    }
+}
+
+impl Shared {
+    /// Forward a frame sent by client `id` to the upstream connection and
+    /// the other clients.
+    async fn forward_frame(&self, id: usize, frame: &[u8]) -> Result<()> {
+        let upstream = self.upstream.lock().await;
+        self.forward_frames(id, &[frame], upstream).await
+    }
+
+    /// Like `forward_frame`, but for multiple frames and using an already
+    /// locked upstream connection.
+    ///
+    /// Locking the upstream connection before releasing the turn keeps
+    /// the frames in order without holding the turn during the write.
+    async fn forward_frames<F: AsRef<[u8]>>(
+        &self,
+        id: usize,
+        frames: &[F],
+        mut upstream: MutexGuard<'_, TcpStream>,
+    ) -> Result<()> {
+        for frame in frames {
+            upstream.write_all(frame.as_ref()).await?;
+        }
+        drop(upstream);
+
+        for frame in frames {
+            broadcast(&self.clients, Some(id), frame.as_ref()).await;
+        }
+        Ok(())
+    }
+
+    async fn handle_client_frame(&self, id: usize, frame: Vec<u8>) -> Result<()> {
+        if !self.server.is_client_frame_allowed(&frame) {
+            return Ok(());
+        }
+
+        match self.server.write_arbitration {
+            WriteArbitration::AllowAll => self.forward_frame(id, &frame).await,
+            WriteArbitration::FirstClient => {
+                let first_id = self.clients.lock().await.first().map(|client| client.id);
+                if first_id == Some(id) {
+                    self.forward_frame(id, &frame).await
+                } else {
+                    Ok(())
+                }
+            }
+            WriteArbitration::ReadOnly => Ok(()),
+            WriteArbitration::Fair => {
+                let mut turn = self.turn.lock().await;
+                if let Some((holder, last_frame)) = turn.holder {
+                    if holder != id && last_frame.elapsed() >= self.server.turn_timeout {
+                        turn.release();
+                    }
+                }
+
+                match turn.holder {
+                    Some((holder, _)) if holder == id => {
+                        turn.holder = Some((id, Instant::now()));
+                        if datagram_command(&frame) == Some(0x0600) {
+                            turn.release();
+                        }
+
+                        let upstream = self.upstream.lock().await;
+                        drop(turn);
+                        self.forward_frames(id, &[frame], upstream).await
+                    }
+                    _ => {
+                        turn.drop_expired(self.server.turn_timeout);
+
+                        let pending_count = turn
+                            .pending
+                            .iter()
+                            .filter(|(client_id, _, _)| *client_id == id)
+                            .count();
+                        if pending_count >= self.server.max_pending_frames {
+                            return Err("Too many frames queued for the next turn".into());
+                        }
+
+                        turn.pending.push((id, Instant::now(), frame));
+                        Ok(())
+                    }
+                }
+            }
+        }
+    }
+
+    /// Start the next turn if the upstream connection offered the bus.
+    async fn handle_upstream_frame(&self, frame: &[u8]) -> Result<()> {
+        if self.server.write_arbitration != WriteArbitration::Fair
+            || datagram_command(frame) != Some(0x0500)
+        {
+            return Ok(());
+        }
+
+        let mut turn = self.turn.lock().await;
+        if let Some((_, last_frame)) = turn.holder {
+            if last_frame.elapsed() >= self.server.turn_timeout {
+                turn.release();
+            }
+        }
+        turn.drop_expired(self.server.turn_timeout);
+
+        if turn.holder.is_some() {
+            return Ok(());
+        }
+
+        match turn.grant_next() {
+            Some((id, frames)) => {
+                let upstream = self.upstream.lock().await;
+                drop(turn);
+                self.forward_frames(id, &frames, upstream).await
+            }
+            None => Ok(()),
+        }
+    }
+
+    async fn remove_client(&self, id: usize) {
+        self.clients.lock().await.retain(|client| {
+            if client.id == id {
+                let _ = client.stream.shutdown(Shutdown::Both);
+            }
+            client.id != id
+        });
+
+        let mut turn = self.turn.lock().await;
+        if turn.holder.map(|(holder, _)| holder) == Some(id) {
+            turn.release();
+        }
+        turn.pending.retain(|(client_id, _, _)| *client_id != id);
+    }
+}
+
+async fn forward_upstream(shared: &Shared, mut upstream: TcpStream) -> Result<()> {
+    let mut buf = Vec::new();
+    let mut read_buf = [0u8; 4096];
+    loop {
+        let len = upstream.read(&mut read_buf).await?;
+        if len == 0 {
+            break Ok(());
+        }
+
+        broadcast(&shared.clients, None, &read_buf[0..len]).await;
+
+        buf.extend_from_slice(&read_buf[0..len]);
+        for frame in take_frames(&mut buf) {
+            shared.handle_upstream_frame(&frame).await?;
+        }
+    }
+}
+
+async fn accept_clients(shared: Arc<Shared>, listener: TcpListener) -> Result<()> {
+    let mut next_id = 0;
+    loop {
+        let (stream, _) = listener.accept().await?;
+
+        let id = next_id;
+        next_id += 1;
+
+        let shared = shared.clone();
+        async_std::task::spawn(async move {
+            if let Ok(stream) = shared.server.accept_client(stream).await {
+                let (sender, receiver) =
+                    async_std::channel::bounded(shared.server.client_queue_capacity);
+                shared.clients.lock().await.push(Client {
+                    id,
+                    stream: stream.clone(),
+                    sender,
+                });
+
+                async_std::task::spawn(write_client(
+                    stream.clone(),
+                    receiver,
+                    shared.server.client_write_timeout,
+                ));
+
+                let _ = forward_client(&shared, id, stream).await;
+
+                // also ends the writer task by closing its queue
+                shared.remove_client(id).await;
+            }
+        });
+    }
+}
+
+async fn forward_client(shared: &Shared, id: usize, mut stream: TcpStream) -> Result<()> {
+    let mut buf = Vec::new();
+    let mut read_buf = [0u8; 1024];
+    loop {
+        let len = stream.read(&mut read_buf).await?;
+        if len == 0 {
+            break Ok(());
+        }
+
+        buf.extend_from_slice(&read_buf[0..len]);
+        for frame in take_frames(&mut buf) {
+            shared.handle_client_frame(id, frame).await?;
+        }
+    }
+}
+
+/// Write the bytes queued for a client, disconnecting it if a write does
+/// not complete within `write_timeout`.
+async fn write_client(
+    mut stream: TcpStream,
+    receiver: Receiver<Arc<[u8]>>,
+    write_timeout: Duration,
+) {
+    while let Ok(bytes) = receiver.recv().await {
+        if async_std::io::timeout(write_timeout, stream.write_all(&bytes))
+            .await
+            .is_err()
+        {
+            let _ = stream.shutdown(Shutdown::Both);
+            break;
+        }
+    }
+}
+
+/// Remove all complete frames from `buf`, skipping bytes that do not
+/// belong to a valid frame.
+fn take_frames(buf: &mut Vec<u8>) -> Vec<Vec<u8>> {
+    let mut frames = Vec::new();
+    let mut start = 0;
+    while start < buf.len() {
+        match length_from_bytes(&buf[start..]) {
+            StreamBlobLength::BlobLength(len) => {
+                frames.push(buf[start..start + len].to_vec());
+                start += len;
+            }
+            StreamBlobLength::Partial => break,
+            StreamBlobLength::Malformed => start += 1,
+        }
+    }
+    buf.drain(0..start);
+    frames
+}
+
+/// Get the command of a datagram frame.
+fn datagram_command(frame: &[u8]) -> Option<u16> {
+    if frame.len() >= 8 && frame[5] == 0x20 {
+        Some(u16::from_le_bytes([frame[6], frame[7]]))
+    } else {
+        None
+    }
+}
+
+/// Queue `bytes` for all clients except `sender`, disconnecting clients
+/// whose queue is full or whose writer task has ended.
+async fn broadcast(clients: &Mutex<Vec<Client>>, sender: Option<usize>, bytes: &[u8]) {
+    let bytes = Arc::<[u8]>::from(bytes);
+
+    clients.lock().await.retain(|client| {
+        if Some(client.id) == sender || client.sender.try_send(bytes.clone()).is_ok() {
+            true
+        } else {
+            let _ = client.stream.shutdown(Shutdown::Both);
+            false
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use resol_vbus::Data;
+
+    use super::*;
+
+    use crate::{
+        connect::{connect_live_data_stream, ConnectOptions},
+        data_builder::DatagramBuilder,
+        shutdown::Shutdown,
+        test_utils::{extend_from_datagram, extend_with_empty_packet},
+    };
+
+    #[test]
+    fn test_sharing_server() -> Result<()> {
+        async_std::task::block_on(async {
+            let device_listener = TcpListener::bind("127.0.0.1:0").await?;
+            let upstream = TcpStream::connect(device_listener.local_addr()?).await?;
+            let (mut device, _) = device_listener.accept().await?;
+
+            let listener = TcpListener::bind("127.0.0.1:0").await?;
+            let addr = listener.local_addr()?;
+
+            let mut server = SharingServer::new();
+            server.set_password(Some("secret".into()));
+            server.set_write_arbitration(WriteArbitration::FirstClient);
+            let server_future = async_std::task::spawn(server.run(listener, upstream));
+
+            let mut options = ConnectOptions::new();
+            options.set_password(Some("wrong".into()));
+            let stream = TcpStream::connect(addr).await?;
+            assert!(connect_live_data_stream(stream, &options).await.is_err());
+
+            options.set_password(Some("secret".into()));
+            let stream = TcpStream::connect(addr).await?;
+            let mut lds1 = connect_live_data_stream(stream, &options).await?;
+            async_std::task::sleep(Duration::from_millis(50)).await;
+            let stream = TcpStream::connect(addr).await?;
+            let mut lds2 = connect_live_data_stream(stream, &options).await?;
+            async_std::task::sleep(Duration::from_millis(50)).await;
+
+            let mut buf = Vec::new();
+            extend_with_empty_packet(&mut buf, 0x0010, 0x7E11, 0x0100);
+            device.write_all(&buf).await?;
+
+            for lds in [&mut lds1, &mut lds2] {
+                let data = lds.receive_any_data(1000).await?.unwrap();
+                assert_eq!("00_0010_7E11_10_0100", data.id_string());
+            }
+
+            let datagram = DatagramBuilder::new(0x7E11, 0x0300)
+                .source_address(0x0021)
+                .build()?;
+            lds2.send_data(&Data::Datagram(datagram)).await?;
+
+            let datagram = DatagramBuilder::new(0x7E11, 0x0300)
+                .source_address(0x0020)
+                .param16(0x1234)
+                .build()?;
+            lds1.send_data(&Data::Datagram(datagram)).await?;
+
+            let data = lds2.receive_any_data(1000).await?.unwrap();
+            assert_eq!(0x0020, data.as_ref().source_address);
+
+            let mut buf = [0u8; 16];
+            device.read_exact(&mut buf).await?;
+            assert_eq!(&[0xAA, 0x11, 0x7E, 0x20, 0x00, 0x20], &buf[0..6]);
+
+            drop(device);
+            server_future.await?;
+
+            assert!(lds1.receive_any_data(1000).await?.is_none());
+
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn test_fair_write_arbitration() -> Result<()> {
+        async_std::task::block_on(async {
+            let device_listener = TcpListener::bind("127.0.0.1:0").await?;
+            let upstream = TcpStream::connect(device_listener.local_addr()?).await?;
+            let (mut device, _) = device_listener.accept().await?;
+
+            let listener = TcpListener::bind("127.0.0.1:0").await?;
+            let addr = listener.local_addr()?;
+
+            let mut server = SharingServer::new();
+            server.set_write_arbitration(WriteArbitration::Fair);
+            async_std::task::spawn(server.run(listener, upstream));
+
+            let options = ConnectOptions::new();
+            let stream = TcpStream::connect(addr).await?;
+            let mut lds1 = connect_live_data_stream(stream, &options).await?;
+            async_std::task::sleep(Duration::from_millis(50)).await;
+            let stream = TcpStream::connect(addr).await?;
+            let mut lds2 = connect_live_data_stream(stream, &options).await?;
+            async_std::task::sleep(Duration::from_millis(50)).await;
+
+            for (lds, address) in [(&mut lds2, 0x0021), (&mut lds1, 0x0020)] {
+                let datagram = DatagramBuilder::new(0x7E11, 0x0300)
+                    .source_address(address)
+                    .build()?;
+                lds.send_data(&Data::Datagram(datagram)).await?;
+                let datagram = DatagramBuilder::new(0x7E11, 0x0600)
+                    .source_address(address)
+                    .build()?;
+                lds.send_data(&Data::Datagram(datagram)).await?;
+            }
+            async_std::task::sleep(Duration::from_millis(50)).await;
+
+            let mut bus_offer = Vec::new();
+            extend_from_datagram(&mut bus_offer, 0x0000, 0x7E11, 0x0500, 0, 0);
+
+            for address in [0x20, 0x21] {
+                device.write_all(&bus_offer).await?;
+
+                let mut buf = [0u8; 32];
+                device.read_exact(&mut buf).await?;
+                assert_eq!(
+                    &[0xAA, 0x11, 0x7E, address, 0x00, 0x20, 0x00, 0x03],
+                    &buf[0..8]
+                );
+                assert_eq!(
+                    &[0xAA, 0x11, 0x7E, address, 0x00, 0x20, 0x00, 0x06],
+                    &buf[16..24]
+                );
+            }
+
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn test_expired_pending_frames() -> Result<()> {
+        async_std::task::block_on(async {
+            let device_listener = TcpListener::bind("127.0.0.1:0").await?;
+            let upstream = TcpStream::connect(device_listener.local_addr()?).await?;
+            let (mut device, _) = device_listener.accept().await?;
+
+            let listener = TcpListener::bind("127.0.0.1:0").await?;
+            let addr = listener.local_addr()?;
+
+            let mut server = SharingServer::new();
+            server.set_write_arbitration(WriteArbitration::Fair);
+            server.set_turn_timeout(Duration::from_millis(100));
+            async_std::task::spawn(server.run(listener, upstream));
+
+            let stream = TcpStream::connect(addr).await?;
+            let (mut client, _) = connect_live_data_stream(stream, &ConnectOptions::new())
+                .await?
+                .into_inner();
+
+            for (index, delay) in [(0x0001, 150), (0x0002, 10)] {
+                let mut frame = Vec::new();
+                extend_from_datagram(&mut frame, 0x7E11, 0x0020, 0x0300, index, 0);
+                client.write_all(&frame).await?;
+                async_std::task::sleep(Duration::from_millis(delay)).await;
+            }
+
+            let mut bus_offer = Vec::new();
+            extend_from_datagram(&mut bus_offer, 0x0000, 0x7E11, 0x0500, 0, 0);
+            device.write_all(&bus_offer).await?;
+
+            // the first frame was queued longer than the turn timeout
+            let mut buf = [0u8; 16];
+            async_std::io::timeout(Duration::from_millis(1000), device.read_exact(&mut buf))
+                .await?;
+            assert_eq!(&[0x02, 0x00], &buf[8..10]);
+
+            let result =
+                async_std::io::timeout(Duration::from_millis(100), device.read(&mut buf)).await;
+            assert_eq!(std::io::ErrorKind::TimedOut, result.unwrap_err().kind());
+
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn test_write_guard() -> Result<()> {
+        async_std::task::block_on(async {
+            let device_listener = TcpListener::bind("127.0.0.1:0").await?;
+            let upstream = TcpStream::connect(device_listener.local_addr()?).await?;
+            let (mut device, _) = device_listener.accept().await?;
+
+            let listener = TcpListener::bind("127.0.0.1:0").await?;
+            let addr = listener.local_addr()?;
+
+            let mut guard = WriteGuard::deny_all();
+            guard.allow(0x0010..=0x001F);
+
+            let mut server = SharingServer::new();
+            server.set_write_guard(Some(guard));
+            async_std::task::spawn(server.run(listener, upstream));
+
+            let stream = TcpStream::connect(addr).await?;
+            let mut lds = connect_live_data_stream(stream, &ConnectOptions::new()).await?;
+            async_std::task::sleep(Duration::from_millis(50)).await;
+
+            for (command, index) in [(0x0200, 0x1234), (0x1400, 0), (0x0200, 0x0010)] {
+                let datagram = DatagramBuilder::new(0x7E11, command)
+                    .param16(index)
+                    .build()?;
+                lds.send_data(&Data::Datagram(datagram)).await?;
+            }
+
+            let mut buf = [0u8; 16];
+            device.read_exact(&mut buf).await?;
+            assert_eq!(
+                &[0xAA, 0x11, 0x7E, 0x20, 0x00, 0x20, 0x00, 0x02],
+                &buf[0..8]
+            );
+            assert_eq!(&[0x10, 0x00], &buf[8..10]);
+
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn test_max_pending_frames() -> Result<()> {
+        async_std::task::block_on(async {
+            let device_listener = TcpListener::bind("127.0.0.1:0").await?;
+            let upstream = TcpStream::connect(device_listener.local_addr()?).await?;
+            let (_device, _) = device_listener.accept().await?;
+
+            let listener = TcpListener::bind("127.0.0.1:0").await?;
+            let addr = listener.local_addr()?;
+
+            let mut server = SharingServer::new();
+            server.set_write_arbitration(WriteArbitration::Fair);
+            server.set_max_pending_frames(2);
+            async_std::task::spawn(server.run(listener, upstream));
+
+            let stream = TcpStream::connect(addr).await?;
+            let (mut client, _) = connect_live_data_stream(stream, &ConnectOptions::new())
+                .await?
+                .into_inner();
+
+            let mut frame = Vec::new();
+            extend_from_datagram(&mut frame, 0x7E11, 0x0020, 0x0300, 0, 0);
+
+            let mut buf = [0u8; 16];
+            client.write_all(&frame).await?;
+            client.write_all(&frame).await?;
+            let result =
+                async_std::io::timeout(Duration::from_millis(100), client.read(&mut buf)).await;
+            assert_eq!(std::io::ErrorKind::TimedOut, result.unwrap_err().kind());
+
+            // the third queued frame disconnects the client
+            client.write_all(&frame).await?;
+            let len =
+                async_std::io::timeout(Duration::from_millis(1000), client.read(&mut buf)).await?;
+            assert_eq!(0, len);
+
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn test_stalled_client() -> Result<()> {
+        async_std::task::block_on(async {
+            let device_listener = TcpListener::bind("127.0.0.1:0").await?;
+            let upstream = TcpStream::connect(device_listener.local_addr()?).await?;
+            let (mut device, _) = device_listener.accept().await?;
+
+            let listener = TcpListener::bind("127.0.0.1:0").await?;
+            let addr = listener.local_addr()?;
+
+            let mut server = SharingServer::new();
+            server.set_client_queue_capacity(64);
+            server.set_client_write_timeout(Duration::from_millis(200));
+            async_std::task::spawn(server.run(listener, upstream));
+
+            let options = ConnectOptions::new();
+            let stream = TcpStream::connect(addr).await?;
+            let (mut stalled, _) = connect_live_data_stream(stream, &options)
+                .await?
+                .into_inner();
+            let stream = TcpStream::connect(addr).await?;
+            let (mut reader, _) = connect_live_data_stream(stream, &options)
+                .await?
+                .into_inner();
+            async_std::task::sleep(Duration::from_millis(50)).await;
+
+            // more than the socket buffers of the stalled client can hold
+            let total = 16 * 1024 * 1024;
+            async_std::task::spawn(async move {
+                let chunk = [0u8; 16384];
+                for _ in 0..total / chunk.len() {
+                    device.write_all(&chunk).await?;
+                    async_std::task::sleep(Duration::from_micros(500)).await;
+                }
+                Result::Ok(device)
+            });
+
+            let mut buf = vec![0u8; 65536];
+            let mut received = 0;
+            while received < total {
+                let len =
+                    async_std::io::timeout(Duration::from_secs(5), reader.read(&mut buf)).await?;
+                assert_ne!(0, len);
+                received += len;
+            }
+
+            // the stalled client was disconnected and only gets the data
+            // written to it before
+            let mut stalled_received = 0;
+            loop {
+                let len =
+                    async_std::io::timeout(Duration::from_secs(5), stalled.read(&mut buf)).await?;
+                if len == 0 {
+                    break;
+                }
+                stalled_received += len;
+            }
+            assert!(stalled_received < total);
+
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn test_run_until_shutdown() -> Result<()> {
+        async_std::task::block_on(async {
+            let device_listener = TcpListener::bind("127.0.0.1:0").await?;
+            let upstream = TcpStream::connect(device_listener.local_addr()?).await?;
+            let (_device, _) = device_listener.accept().await?;
+
+            let listener = TcpListener::bind("127.0.0.1:0").await?;
+            let addr = listener.local_addr()?;
+
+            let shutdown = Shutdown::new();
+            let server = SharingServer::new();
+            async_std::task::spawn(server.run_until_shutdown(
+                listener,
+                upstream,
+                shutdown.register("sharing"),
+            ));
+
+            let stream = TcpStream::connect(addr).await?;
+            let mut lds = connect_live_data_stream(stream, &ConnectOptions::new()).await?;
+            async_std::task::sleep(Duration::from_millis(50)).await;
+
+            let results = shutdown.shutdown().await;
+            assert_eq!(vec![("sharing".to_string(), Ok(()))], results);
+
+            assert!(lds.receive_any_data(1000).await?.is_none());
+
+            Ok(())
+        })
+    }
+}
@@ -0,0 +1,210 @@
+use std::{
+    future::Future,
+    pin::Pin,
+    sync::{Arc, Mutex},
+    task::Poll,
+};
+
+use async_std::channel::{Receiver, Sender};
+
+use crate::error::Result;
+
+type Component = (String, Receiver<Result<()>>);
+
+/// Coordinates the graceful shutdown of multiple components.
+///
+/// Every long-running component (e.g. the `HttpApi` server or a task
+/// processing a `LiveDataStream`) registers itself using `register` and
+/// receives a `ShutdownSignal`. Calling `shutdown` notifies all signals and
+/// waits for every component to report its result, so that an application
+/// can stop accepting clients, release the bus and flush its files before
+/// exiting.
+///
+/// # Examples
+///
+/// ```no_run
+/// # fn main() -> async_resol_vbus::Result<()> { async_std::task::block_on(async {
+/// #
+/// use async_resol_vbus::{LiveDataStream, Shutdown};
+///
+/// let shutdown = Shutdown::new();
+///
+/// let signal = shutdown.register("live data");
+/// async_std::task::spawn(async move {
+///     while !signal.is_triggered() {
+///         // ... process live data ...
+///     }
+///
+///     // ... release the bus ...
+///
+///     signal.complete(Ok(()));
+/// });
+///
+/// // ... wait for SIGTERM ...
+///
+/// for (name, result) in shutdown.shutdown().await {
+///     println!("{}: {:?}", name, result);
+/// }
+/// #
+/// # Ok(()) }) }
+/// ```
+#[derive(Debug, Clone)]
+pub struct Shutdown {
+    trigger_sender: Sender<()>,
+    trigger_receiver: Receiver<()>,
+    components: Arc<Mutex<Vec<Component>>>,
+}
+
+impl Shutdown {
+    /// Create a new `Shutdown` instance.
+    pub fn new() -> Shutdown {
+        let (trigger_sender, trigger_receiver) = async_std::channel::bounded(1);
+
+        Shutdown {
+            trigger_sender,
+            trigger_receiver,
+            components: Arc::new(Mutex::new(Vec::new())),
+        }
+    }
+
+    /// Register a component by name and return its `ShutdownSignal`.
+    pub fn register(&self, name: &str) -> ShutdownSignal {
+        let (result_sender, result_receiver) = async_std::channel::bounded(1);
+
+        self.components
+            .lock()
+            .unwrap()
+            .push((name.to_string(), result_receiver));
+
+        ShutdownSignal {
+            trigger: self.trigger_receiver.clone(),
+            result_sender,
+        }
+    }
+
+    /// Check whether `shutdown` was called.
+    pub fn is_triggered(&self) -> bool {
+        self.trigger_sender.is_closed()
+    }
+
+    /// Notify all registered components and wait for their results.
+    ///
+    /// The results are returned in the order of registration. A component
+    /// that dropped its `ShutdownSignal` without calling `complete` is
+    /// considered to have finished successfully.
+    pub async fn shutdown(&self) -> Vec<(String, Result<()>)> {
+        self.trigger_sender.close();
+
+        let components = std::mem::take(&mut *self.components.lock().unwrap());
+
+        let mut results = Vec::with_capacity(components.len());
+        for (name, result_receiver) in components {
+            let result = result_receiver.recv().await.unwrap_or(Ok(()));
+            results.push((name, result));
+        }
+
+        results
+    }
+}
+
+impl Default for Shutdown {
+    fn default() -> Shutdown {
+        Shutdown::new()
+    }
+}
+
+/// The component side of a `Shutdown`, see `Shutdown::register`.
+#[derive(Debug)]
+pub struct ShutdownSignal {
+    trigger: Receiver<()>,
+    result_sender: Sender<Result<()>>,
+}
+
+impl ShutdownSignal {
+    /// Check whether the shutdown was triggered.
+    pub fn is_triggered(&self) -> bool {
+        self.trigger.is_closed()
+    }
+
+    /// Wait until the shutdown is triggered.
+    pub async fn wait(&self) {
+        while self.trigger.recv().await.is_ok() {}
+    }
+
+    /// Run `future` until it completes or the shutdown is triggered.
+    ///
+    /// Returns `None` if the shutdown was triggered first.
+    pub async fn run_until<F: Future>(&self, future: F) -> Option<F::Output> {
+        let mut future = Box::pin(future);
+        let mut wait = Box::pin(self.wait());
+
+        async_std::future::poll_fn(|cx| {
+            if let Poll::Ready(output) = Pin::new(&mut future).poll(cx) {
+                Poll::Ready(Some(output))
+            } else if Pin::new(&mut wait).poll(cx).is_ready() {
+                Poll::Ready(None)
+            } else {
+                Poll::Pending
+            }
+        })
+        .await
+    }
+
+    /// Report the result of the component's shutdown.
+    pub fn complete(self, result: Result<()>) {
+        drop(self.result_sender.try_send(result));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+
+    #[test]
+    fn test_shutdown() {
+        async_std::task::block_on(async {
+            let shutdown = Shutdown::new();
+
+            let signal = shutdown.register("first");
+            let first = async_std::task::spawn(async move {
+                let result = signal.run_until(async_std::future::pending::<()>()).await;
+                assert!(signal.is_triggered());
+                signal.complete(Err("Failed".into()));
+                result
+            });
+
+            let signal = shutdown.register("second");
+            async_std::task::spawn(async move {
+                signal.wait().await;
+                async_std::task::sleep(Duration::from_millis(10)).await;
+                drop(signal);
+            });
+
+            assert!(!shutdown.is_triggered());
+
+            let results = shutdown.shutdown().await;
+
+            assert!(shutdown.is_triggered());
+            assert_eq!(
+                vec![
+                    ("first".to_string(), Err("Failed".into())),
+                    ("second".to_string(), Ok(())),
+                ],
+                results
+            );
+            assert_eq!(None, first.await);
+        });
+    }
+
+    #[test]
+    fn test_run_until_completes() {
+        async_std::task::block_on(async {
+            let shutdown = Shutdown::new();
+            let signal = shutdown.register("test");
+
+            assert_eq!(Some(42), signal.run_until(async { 42 }).await);
+        });
+    }
+}
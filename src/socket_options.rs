@@ -0,0 +1,146 @@
+use std::time::Duration;
+
+use async_std::net::TcpStream;
+
+use socket2::{SockRef, TcpKeepalive};
+
+use crate::error::Result;
+
+/// Socket tuning applied to a `TcpStream`, see
+/// `ConnectOptions::set_socket_options`.
+///
+/// Long-lived connections that are idle in one direction (e.g. a logger
+/// only receiving data) can be silently dropped by NAT routers. Enabling
+/// TCP keepalive makes the OS send probes that keep the NAT mapping alive
+/// and detect dead peers. Disabling Nagle's algorithm using `nodelay`
+/// sends small datagrams immediately instead of coalescing them.
+///
+/// All options default to `None`, which keeps the OS defaults.
+///
+/// # Examples
+///
+/// ```no_run
+/// # fn main() -> async_resol_vbus::Result<()> { async_std::task::block_on(async {
+/// #
+/// use std::time::Duration;
+///
+/// use async_resol_vbus::{connect_tcp_live_data_stream, ConnectOptions, SocketOptions};
+///
+/// let mut socket_options = SocketOptions::new();
+/// socket_options.set_keepalive(Some(Duration::from_secs(60)));
+/// socket_options.set_keepalive_interval(Some(Duration::from_secs(10)));
+/// socket_options.set_nodelay(Some(true));
+///
+/// let mut options = ConnectOptions::new();
+/// options.set_socket_options(socket_options);
+///
+/// let mut lds = connect_tcp_live_data_stream("192.168.5.217:7053", &options).await?;
+/// #
+/// # Ok(()) }) }
+/// ```
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SocketOptions {
+    keepalive: Option<Duration>,
+    keepalive_interval: Option<Duration>,
+    nodelay: Option<bool>,
+    recv_buffer_size: Option<usize>,
+    send_buffer_size: Option<usize>,
+}
+
+impl SocketOptions {
+    /// Create a new `SocketOptions` keeping all OS defaults.
+    pub fn new() -> SocketOptions {
+        SocketOptions::default()
+    }
+
+    /// Enable `SO_KEEPALIVE`, sending the first probe after the connection
+    /// was idle for the given duration.
+    pub fn set_keepalive(&mut self, keepalive: Option<Duration>) {
+        self.keepalive = keepalive;
+    }
+
+    /// Set the interval between two keepalive probes.
+    ///
+    /// Only used if keepalive is enabled. Ignored on platforms that do not
+    /// support configuring it.
+    pub fn set_keepalive_interval(&mut self, interval: Option<Duration>) {
+        self.keepalive_interval = interval;
+    }
+
+    /// Enable or disable `TCP_NODELAY`.
+    pub fn set_nodelay(&mut self, nodelay: Option<bool>) {
+        self.nodelay = nodelay;
+    }
+
+    /// Set the size of the socket's receive buffer (`SO_RCVBUF`).
+    pub fn set_recv_buffer_size(&mut self, size: Option<usize>) {
+        self.recv_buffer_size = size;
+    }
+
+    /// Set the size of the socket's send buffer (`SO_SNDBUF`).
+    pub fn set_send_buffer_size(&mut self, size: Option<usize>) {
+        self.send_buffer_size = size;
+    }
+
+    /// Apply the options to `stream`.
+    pub fn apply(&self, stream: &TcpStream) -> Result<()> {
+        let socket = SockRef::from(stream);
+
+        if let Some(time) = self.keepalive {
+            #[allow(unused_mut)]
+            let mut keepalive = TcpKeepalive::new().with_time(time);
+            #[cfg(any(
+                target_os = "android",
+                target_os = "freebsd",
+                target_os = "ios",
+                target_os = "linux",
+                target_os = "macos",
+                target_os = "netbsd",
+                target_os = "windows",
+            ))]
+            if let Some(interval) = self.keepalive_interval {
+                keepalive = keepalive.with_interval(interval);
+            }
+            socket.set_tcp_keepalive(&keepalive)?;
+        }
+        if let Some(nodelay) = self.nodelay {
+            socket.set_tcp_nodelay(nodelay)?;
+        }
+        if let Some(size) = self.recv_buffer_size {
+            socket.set_recv_buffer_size(size)?;
+        }
+        if let Some(size) = self.send_buffer_size {
+            socket.set_send_buffer_size(size)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use async_std::net::TcpListener;
+
+    use super::*;
+
+    #[test]
+    fn test_apply() -> Result<()> {
+        async_std::task::block_on(async {
+            let listener = TcpListener::bind("127.0.0.1:0").await?;
+            let stream = TcpStream::connect(listener.local_addr()?).await?;
+
+            let mut options = SocketOptions::new();
+            options.set_keepalive(Some(Duration::from_secs(60)));
+            options.set_keepalive_interval(Some(Duration::from_secs(10)));
+            options.set_nodelay(Some(true));
+            options.set_recv_buffer_size(Some(32 * 1024));
+            options.apply(&stream)?;
+
+            let socket = SockRef::from(&stream);
+            assert!(socket.keepalive()?);
+            assert!(socket.tcp_nodelay()?);
+            assert!(socket.recv_buffer_size()? >= 32 * 1024);
+
+            Ok(())
+        })
+    }
+}
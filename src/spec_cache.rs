@@ -0,0 +1,46 @@
+use std::{cell::RefCell, rc::Rc};
+
+use resol_vbus::{Language, Specification, SpecificationFile};
+
+thread_local! {
+    static SPECIFICATIONS: RefCell<Vec<Rc<Specification>>> = const { RefCell::new(Vec::new()) };
+}
+
+/// Get the `Specification` of the embedded VBus specification file for the
+/// given `language`.
+///
+/// Parsing the specification file is expensive, but a `Specification`
+/// cannot be shared between threads. It is therefore parsed once per thread
+/// and language. The returned `Specification` must not be held across an
+/// `.await`.
+pub(crate) fn default_specification(language: Language) -> Rc<Specification> {
+    SPECIFICATIONS.with(|specs| {
+        let mut specs = specs.borrow_mut();
+        match specs.iter().find(|spec| spec.language() == language) {
+            Some(spec) => spec.clone(),
+            None => {
+                let spec = Rc::new(Specification::from_file(
+                    SpecificationFile::new_default(),
+                    language,
+                ));
+                specs.push(spec.clone());
+                spec
+            }
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_default_specification() {
+        let spec_en = default_specification(Language::En);
+        let spec_de = default_specification(Language::De);
+
+        assert_eq!(Language::En, spec_en.language());
+        assert_eq!(Language::De, spec_de.language());
+        assert!(Rc::ptr_eq(&spec_en, &default_specification(Language::En)));
+    }
+}
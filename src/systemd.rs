@@ -0,0 +1,437 @@
+use std::{
+    os::{linux::net::SocketAddrExt, unix::net::SocketAddr},
+    path::Path,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use async_std::os::unix::net::UnixDatagram;
+
+use resol_vbus::{Data, DataSet, Language};
+
+use zbus::object_server::SignalEmitter;
+
+use crate::{changed_fields::changed_fields, error::Result, spec_cache::default_specification};
+
+/// Sends service status notifications to systemd.
+///
+/// Implements the `sd_notify` protocol, so that a gateway running as a
+/// `Type=notify` service can report its readiness and keep the service
+/// manager's watchdog (`WatchdogSec=`) satisfied.
+///
+/// This type is only available on Linux if the `systemd` feature is
+/// enabled.
+///
+/// # Examples
+///
+/// ```no_run
+/// # fn main() -> async_resol_vbus::Result<()> { async_std::task::block_on(async {
+/// #
+/// use async_resol_vbus::SystemdNotifier;
+///
+/// if let Some(notifier) = SystemdNotifier::from_env().await? {
+///     // ... connect to the VBus device ...
+///     notifier.ready().await?;
+///     async_std::task::spawn(async move { notifier.run_watchdog().await });
+/// }
+/// #
+/// # Ok(()) }) }
+/// ```
+#[derive(Debug)]
+pub struct SystemdNotifier {
+    socket: UnixDatagram,
+    watchdog_interval: Option<Duration>,
+}
+
+impl SystemdNotifier {
+    /// Create a `SystemdNotifier` for the socket in the `NOTIFY_SOCKET`
+    /// environment variable.
+    ///
+    /// Returns `None` if the process was not started by systemd. The
+    /// watchdog interval is half of the timeout in the `WATCHDOG_USEC`
+    /// environment variable, if it is meant for this process.
+    pub async fn from_env() -> Result<Option<SystemdNotifier>> {
+        let path = match std::env::var("NOTIFY_SOCKET") {
+            Ok(path) => path,
+            Err(_) => return Ok(None),
+        };
+
+        let mut notifier = SystemdNotifier::connect(path).await?;
+
+        let is_own_watchdog = match std::env::var("WATCHDOG_PID") {
+            Ok(pid) => pid.parse::<u32>().ok() == Some(std::process::id()),
+            Err(_) => true,
+        };
+        if is_own_watchdog {
+            notifier.watchdog_interval = std::env::var("WATCHDOG_USEC")
+                .ok()
+                .and_then(|usec| usec.parse::<u64>().ok())
+                .map(|usec| Duration::from_micros(usec / 2));
+        }
+
+        Ok(Some(notifier))
+    }
+
+    /// Create a `SystemdNotifier` for the socket at `path`.
+    ///
+    /// A leading `@` refers to a socket in the abstract namespace.
+    pub async fn connect<P: AsRef<Path>>(path: P) -> Result<SystemdNotifier> {
+        let path = path.as_ref();
+
+        let socket = std::os::unix::net::UnixDatagram::unbound()?;
+        match path.to_str().and_then(|path| path.strip_prefix('@')) {
+            Some(name) => socket.connect_addr(&SocketAddr::from_abstract_name(name)?)?,
+            None => socket.connect(path)?,
+        }
+
+        Ok(SystemdNotifier {
+            socket: socket.into(),
+            watchdog_interval: None,
+        })
+    }
+
+    /// Get the interval in which `watchdog` must be called.
+    pub fn watchdog_interval(&self) -> Option<Duration> {
+        self.watchdog_interval
+    }
+
+    /// Send a raw notification, e.g. `"RELOADING=1"`.
+    pub async fn notify(&self, state: &str) -> Result<()> {
+        self.socket.send(state.as_bytes()).await?;
+        Ok(())
+    }
+
+    /// Report that the service finished starting up.
+    pub async fn ready(&self) -> Result<()> {
+        self.notify("READY=1").await
+    }
+
+    /// Report that the service is shutting down.
+    pub async fn stopping(&self) -> Result<()> {
+        self.notify("STOPPING=1").await
+    }
+
+    /// Report a free-form status text, e.g. `"Connected to 192.168.5.217"`.
+    pub async fn status(&self, status: &str) -> Result<()> {
+        self.notify(&format!("STATUS={}", status.replace('\n', " ")))
+            .await
+    }
+
+    /// Reset the service manager's watchdog timer.
+    pub async fn watchdog(&self) -> Result<()> {
+        self.notify("WATCHDOG=1").await
+    }
+
+    /// Call `watchdog` every watchdog interval until an error occurs.
+    ///
+    /// Returns immediately if the watchdog is not enabled.
+    pub async fn run_watchdog(&self) -> Result<()> {
+        if let Some(interval) = self.watchdog_interval {
+            loop {
+                self.watchdog().await?;
+                async_std::task::sleep(interval).await;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(Debug, Default)]
+struct DbusState {
+    data_set: DataSet,
+    connected: bool,
+}
+
+struct LiveData {
+    state: Arc<Mutex<DbusState>>,
+    language: Language,
+}
+
+impl LiveData {
+    fn values(&self) -> Vec<(String, String, f64, String)> {
+        let spec = default_specification(self.language);
+        let state = self.state.lock().unwrap();
+        spec.fields_in_data_set(&state.data_set)
+            .map(|field| {
+                (
+                    field.field_spec().packet_field_id.clone(),
+                    field.field_spec().name.clone(),
+                    field.raw_value_f64().unwrap_or(f64::NAN),
+                    field.field_spec().unit_text.trim().to_string(),
+                )
+            })
+            .collect()
+    }
+}
+
+#[zbus::interface(name = "de.resol.VBus1.LiveData")]
+impl LiveData {
+    /// Whether the connection to the VBus device is established.
+    #[zbus(property)]
+    fn connected(&self) -> bool {
+        self.state.lock().unwrap().connected
+    }
+
+    /// The RFC 3339 timestamp of the last received `Data`.
+    #[zbus(property)]
+    fn last_data(&self) -> String {
+        let state = self.state.lock().unwrap();
+        if state.data_set.as_data_slice().is_empty() {
+            String::new()
+        } else {
+            state.data_set.timestamp.to_rfc3339()
+        }
+    }
+
+    /// Get the `(id, name, value, unit)` of all known fields.
+    fn get_values(&self) -> Vec<(String, String, f64, String)> {
+        self.values()
+    }
+
+    /// Get the value of a field by its packet field ID.
+    fn get_value(&self, id: &str) -> zbus::fdo::Result<f64> {
+        self.values()
+            .into_iter()
+            .find(|(field_id, _, _, _)| field_id == id)
+            .map(|(_, _, value, _)| value)
+            .ok_or_else(|| zbus::fdo::Error::InvalidArgs(format!("Unknown field {:?}", id)))
+    }
+
+    /// Emitted with the packet field IDs of all fields whose values
+    /// changed.
+    #[zbus(signal)]
+    async fn values_changed(emitter: &SignalEmitter<'_>, ids: Vec<String>) -> zbus::Result<()>;
+}
+
+/// The D-Bus object path of the `DbusService`.
+const OBJECT_PATH: &str = "/de/resol/VBus1";
+
+/// Publishes live data and the connection state on D-Bus.
+///
+/// The service registers a well-known name on the session or system bus
+/// and provides the object `/de/resol/VBus1` with the
+/// `de.resol.VBus1.LiveData` interface:
+///
+/// - `Connected` and `LastData` properties (with change notifications)
+/// - `GetValues()` returning the `(id, name, value, unit)` of all fields
+///   and `GetValue(id)` returning a single value
+/// - a `ValuesChanged(ids)` signal listing the packet field IDs of all
+///   fields whose values changed
+///
+/// Like the `HttpApi`, the `DbusService` does not own the `LiveDataStream`.
+/// The application feeds received `Data` into it using `add_data`.
+///
+/// This type is only available on Linux if the `systemd` feature is
+/// enabled.
+///
+/// # Examples
+///
+/// ```no_run
+/// # fn main() -> async_resol_vbus::Result<()> { async_std::task::block_on(async {
+/// #
+/// use async_std::net::TcpStream;
+///
+/// use async_resol_vbus::{DbusService, Language, LiveDataStream};
+///
+/// let service = DbusService::system("de.resol.VBusGateway", Language::En).await?;
+///
+/// let stream = TcpStream::connect("192.168.5.217:7053").await?;
+/// // ... perform handshake ...
+/// let mut lds = LiveDataStream::from_tcp_stream(stream, 0, 0x0020);
+/// service.set_connected(true).await?;
+///
+/// while let Some(data) = lds.receive_any_data(60000).await? {
+///     service.add_data(data).await?;
+/// }
+/// #
+/// # Ok(()) }) }
+/// ```
+#[derive(Debug)]
+pub struct DbusService {
+    connection: zbus::Connection,
+    state: Arc<Mutex<DbusState>>,
+    language: Language,
+}
+
+impl DbusService {
+    async fn connect(
+        builder: zbus::connection::Builder<'_>,
+        name: &str,
+        language: Language,
+    ) -> Result<DbusService> {
+        let state = Arc::new(Mutex::new(DbusState::default()));
+
+        let live_data = LiveData {
+            state: state.clone(),
+            language,
+        };
+
+        let connection = builder
+            .name(name)?
+            .serve_at(OBJECT_PATH, live_data)?
+            .build()
+            .await?;
+
+        Ok(DbusService {
+            connection,
+            state,
+            language,
+        })
+    }
+
+    /// Register the service with the well-known `name` on the session bus.
+    pub async fn session(name: &str, language: Language) -> Result<DbusService> {
+        DbusService::connect(zbus::connection::Builder::session()?, name, language).await
+    }
+
+    /// Register the service with the well-known `name` on the system bus.
+    pub async fn system(name: &str, language: Language) -> Result<DbusService> {
+        DbusService::connect(zbus::connection::Builder::system()?, name, language).await
+    }
+
+    async fn interface(&self) -> Result<zbus::object_server::InterfaceRef<LiveData>> {
+        Ok(self
+            .connection
+            .object_server()
+            .interface::<_, LiveData>(OBJECT_PATH)
+            .await?)
+    }
+
+    /// Report whether the connection to the VBus device is established.
+    pub async fn set_connected(&self, connected: bool) -> Result<()> {
+        let was_connected = std::mem::replace(&mut self.state.lock().unwrap().connected, connected);
+
+        if was_connected != connected {
+            let iface = self.interface().await?;
+            iface
+                .get()
+                .await
+                .connected_changed(iface.signal_emitter())
+                .await?;
+        }
+
+        Ok(())
+    }
+
+    /// Add a received `Data`, notifying about all changed values.
+    pub async fn add_data(&self, data: Data) -> Result<()> {
+        let changed_ids = {
+            let mut state = self.state.lock().unwrap();
+            let changed_ids = changed_field_ids(&state.data_set, &data, self.language);
+            state.data_set.timestamp = data.as_ref().timestamp;
+            state.data_set.add_data(data);
+            changed_ids
+        };
+
+        let iface = self.interface().await?;
+        iface
+            .get()
+            .await
+            .last_data_changed(iface.signal_emitter())
+            .await?;
+        if !changed_ids.is_empty() {
+            LiveData::values_changed(iface.signal_emitter(), changed_ids).await?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Get the packet field IDs of all fields of `data` whose values differ
+/// from the `Data` with the same ID in `data_set`.
+fn changed_field_ids(data_set: &DataSet, data: &Data, language: Language) -> Vec<String> {
+    let id = data.id_string();
+    let previous = data_set.iter().find(|previous| previous.id_string() == id);
+
+    let mut changed_ids = Vec::new();
+    changed_fields(previous, data, &default_specification(language), |field| {
+        changed_ids.push(field.field_spec().packet_field_id.clone());
+    });
+    changed_ids
+}
+
+#[cfg(test)]
+mod tests {
+    use resol_vbus::{chrono::Utc, Header, Packet};
+
+    use super::*;
+
+    fn packet(temperature: i16) -> Data {
+        let mut frame_data = [0u8; 508];
+        frame_data[0..2].copy_from_slice(&temperature.to_le_bytes());
+        Data::Packet(Packet {
+            header: Header {
+                timestamp: Utc::now(),
+                channel: 0,
+                destination_address: 0x0010,
+                source_address: 0x7E11,
+                protocol_version: 0x10,
+            },
+            command: 0x0100,
+            frame_count: 1,
+            frame_data,
+        })
+    }
+
+    #[test]
+    fn test_systemd_notifier() -> Result<()> {
+        async_std::task::block_on(async {
+            let path = std::env::temp_dir().join(format!(
+                "async-resol-vbus-notify-{}.sock",
+                std::process::id()
+            ));
+            let server = UnixDatagram::bind(&path).await?;
+
+            let notifier = SystemdNotifier::connect(&path).await?;
+            assert_eq!(None, notifier.watchdog_interval());
+            notifier.ready().await?;
+            notifier.status("Connected\nto device").await?;
+            notifier.run_watchdog().await?;
+
+            let mut buf = [0u8; 64];
+            let len = server.recv(&mut buf).await?;
+            assert_eq!(b"READY=1", &buf[0..len]);
+            let len = server.recv(&mut buf).await?;
+            assert_eq!(b"STATUS=Connected to device", &buf[0..len]);
+
+            async_std::fs::remove_file(&path).await?;
+
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn test_live_data() {
+        let live_data = LiveData {
+            state: Arc::new(Mutex::new(DbusState::default())),
+            language: Language::En,
+        };
+        assert_eq!("", live_data.last_data());
+
+        let data = packet(872);
+        {
+            let mut state = live_data.state.lock().unwrap();
+            let changed_ids = changed_field_ids(&state.data_set, &data, Language::En);
+            assert!(changed_ids.contains(&"00_0010_7E11_10_0100_000_2_0".to_string()));
+            state.data_set.add_data(data);
+
+            let changed_ids = changed_field_ids(&state.data_set, &packet(872), Language::En);
+            assert!(changed_ids.is_empty());
+            let changed_ids = changed_field_ids(&state.data_set, &packet(901), Language::En);
+            assert_eq!(
+                vec!["00_0010_7E11_10_0100_000_2_0".to_string()],
+                changed_ids
+            );
+        }
+
+        assert_eq!(
+            Ok(87.2),
+            live_data.get_value("00_0010_7E11_10_0100_000_2_0")
+        );
+        assert!(live_data.get_value("unknown").is_err());
+        let values = live_data.get_values();
+        assert_eq!("Temperature sensor 1", values[0].1);
+        assert_eq!("°C", values[0].3);
+    }
+}
@@ -1,13 +1,25 @@
-use async_std::{net::TcpStream, prelude::*};
+use std::marker::Unpin;
+
+use async_std::{
+    io::{Read, Write},
+    net::TcpStream,
+    prelude::*,
+};
 
 use resol_vbus::BlobBuffer;
 
-use crate::error::Result;
+use crate::{
+    error::{Error, Result},
+    handshake_trace::{HandshakeDirection, HandshakeObserver, ObserverSlot},
+};
 
 /// Handles the client-side of the [VBus-over-TCP][1] handshake.
 ///
 /// [1]: http://danielwippermann.github.io/resol-vbus/vbus-over-tcp.html
 ///
+/// The handshake is performed over a `TcpStream` by default, but any other
+/// duplex stream implementing `Read` and `Write` can be used as well.
+///
 /// # Examples
 ///
 /// ```no_run
@@ -27,17 +39,28 @@ use crate::error::Result;
 /// # Ok(()) }) }
 /// ```
 #[derive(Debug)]
-pub struct TcpClientHandshake {
-    stream: TcpStream,
+pub struct TcpClientHandshake<S = TcpStream> {
+    stream: S,
     buf: BlobBuffer,
+    observer: ObserverSlot,
 }
 
-impl TcpClientHandshake {
+impl<S: Read + Write + Unpin> TcpClientHandshake<S> {
     /// Start the handshake by waiting for the initial greeting reply from the service.
-    pub async fn start(stream: TcpStream) -> Result<TcpClientHandshake> {
+    pub async fn start(stream: S) -> Result<TcpClientHandshake<S>> {
+        TcpClientHandshake::start_with_observer(stream, None).await
+    }
+
+    /// Start the handshake like `start`, passing every command and reply
+    /// line including the initial greeting to `observer`.
+    pub async fn start_with_observer(
+        stream: S,
+        observer: Option<HandshakeObserver>,
+    ) -> Result<TcpClientHandshake<S>> {
         let mut hs = TcpClientHandshake {
             stream,
             buf: BlobBuffer::new(),
+            observer: ObserverSlot::new(observer),
         };
 
         hs.read_reply().await?;
@@ -45,15 +68,17 @@ impl TcpClientHandshake {
         Ok(hs)
     }
 
-    /// Consume `self` and return the underlying `TcpStream`.
-    pub fn into_inner(self) -> TcpStream {
+    /// Consume `self` and return the underlying stream.
+    pub fn into_inner(self) -> S {
         self.stream
     }
 
-    async fn read_reply(&mut self) -> Result<()> {
+    async fn read_reply_byte(&mut self) -> Result<u8> {
         let first_byte = loop {
             if let Some(idx) = self.buf.iter().position(|b| *b == 10) {
                 let first_byte = self.buf[0];
+                let line = String::from_utf8_lossy(&self.buf[0..idx]).into_owned();
+                self.observer.notify(HandshakeDirection::Received, &line);
                 self.buf.consume(idx + 1);
 
                 break first_byte;
@@ -68,23 +93,33 @@ impl TcpClientHandshake {
             self.buf.extend_from_slice(&buf[0..len]);
         };
 
+        Ok(first_byte)
+    }
+
+    async fn read_reply(&mut self) -> Result<()> {
+        let first_byte = self.read_reply_byte().await?;
         if first_byte == b'+' {
             Ok(())
         } else if first_byte == b'-' {
-            Err("Negative reply".into())
+            Err(Error::negative_reply("Negative reply".to_string()))
         } else {
             Err("Unexpected reply".into())
         }
     }
 
-    async fn send_command(&mut self, cmd: &str, args: Option<&str>) -> Result<()> {
+    async fn write_command(&mut self, cmd: &str, args: Option<&str>) -> Result<()> {
         let cmd = match args {
             Some(args) => format!("{} {}\r\n", cmd, args),
             None => format!("{}\r\n", cmd),
         };
 
+        self.observer.notify(HandshakeDirection::Sent, &cmd);
         self.stream.write_all(cmd.as_bytes()).await?;
+        Ok(())
+    }
 
+    async fn send_command(&mut self, cmd: &str, args: Option<&str>) -> Result<()> {
+        self.write_command(cmd, args).await?;
         self.read_reply().await
     }
 
@@ -98,6 +133,16 @@ impl TcpClientHandshake {
         self.send_command("PASS", Some(password)).await
     }
 
+    /// Send the `PASS` command and return whether the password was accepted.
+    pub(crate) async fn try_pass_command(&mut self, password: &str) -> Result<bool> {
+        self.write_command("PASS", Some(password)).await?;
+        match self.read_reply_byte().await? {
+            b'+' => Ok(true),
+            b'-' => Ok(false),
+            _ => Err("Unexpected reply".into()),
+        }
+    }
+
     /// Send the `CHANNEL` command and wait for the reply.
     pub async fn send_channel_command(&mut self, channel: u8) -> Result<()> {
         self.send_command("CHANNEL", Some(&format!("{}", channel)))
@@ -106,13 +151,20 @@ impl TcpClientHandshake {
 
     /// Send the `DATA` command and wait for the reply.
     ///
-    /// This function returns the underlying `TcpStream` since the handshake is complete
+    /// This function returns the underlying stream since the handshake is complete
     /// after sending this command.
-    pub async fn send_data_command(mut self) -> Result<TcpStream> {
+    pub async fn send_data_command(mut self) -> Result<S> {
         self.send_command("DATA", None).await?;
         Ok(self.stream)
     }
 
+    /// Like `send_data_command`, but also returns the bytes that were
+    /// received after the reply.
+    pub(crate) async fn send_data_command_buffered(mut self) -> Result<(S, Vec<u8>)> {
+        self.send_command("DATA", None).await?;
+        Ok((self.stream, self.buf.to_vec()))
+    }
+
     /// Send the `QUIT` command and wait for the reply.
     pub async fn send_quit_command(mut self) -> Result<()> {
         self.send_command("QUIT", None).await?;
@@ -169,4 +221,78 @@ mod tests {
             Ok(())
         })
     }
+
+    #[test]
+    fn test_observer() -> Result<()> {
+        use std::sync::{Arc, Mutex};
+
+        use crate::handshake_trace::HandshakeTrace;
+
+        async_std::task::block_on(async {
+            let listener = TcpListener::bind("127.0.0.1:0").await?;
+            let addr = listener.local_addr()?;
+
+            let server_traces = Arc::new(Mutex::new(Vec::<HandshakeTrace>::new()));
+            let client_traces = Arc::new(Mutex::new(Vec::<HandshakeTrace>::new()));
+
+            let traces = server_traces.clone();
+            let server_future = async_std::task::spawn::<_, Result<()>>(async move {
+                let (stream, _) = listener.accept().await?;
+
+                let observer = Box::new(move |trace: &HandshakeTrace| {
+                    traces.lock().unwrap().push(trace.clone());
+                });
+                let mut hs =
+                    TcpServerHandshake::start_with_observer(stream, Some(observer)).await?;
+                hs.receive_pass_command().await?;
+                hs.receive_data_command().await?;
+
+                Ok(())
+            });
+
+            let stream = TcpStream::connect(addr).await?;
+
+            let traces = client_traces.clone();
+            let observer = Box::new(move |trace: &HandshakeTrace| {
+                traces.lock().unwrap().push(trace.clone());
+            });
+            let mut hs = TcpClientHandshake::start_with_observer(stream, Some(observer)).await?;
+            hs.send_pass_command("vbus").await?;
+            hs.send_data_command().await?;
+
+            server_future.await?;
+
+            let lines = |traces: &Arc<Mutex<Vec<HandshakeTrace>>>| {
+                traces
+                    .lock()
+                    .unwrap()
+                    .iter()
+                    .map(|trace| format!("{:?} {}", trace.direction, trace.line))
+                    .collect::<Vec<_>>()
+            };
+
+            assert_eq!(
+                vec![
+                    "Received +HELLO",
+                    "Sent PASS vbus",
+                    "Received +OK",
+                    "Sent DATA",
+                    "Received +OK",
+                ],
+                lines(&client_traces)
+            );
+            assert_eq!(
+                vec![
+                    "Sent +HELLO",
+                    "Received PASS vbus",
+                    "Sent +OK",
+                    "Received DATA",
+                    "Sent +OK",
+                ],
+                lines(&server_traces)
+            );
+
+            Ok(())
+        })
+    }
 }
@@ -4,7 +4,10 @@ use async_std::{net::TcpStream, prelude::*};
 
 use resol_vbus::BlobBuffer;
 
-use crate::error::Result;
+use crate::{
+    error::Result,
+    handshake_trace::{HandshakeDirection, HandshakeObserver, ObserverSlot},
+};
 
 pub type FutureResult<T> = std::result::Result<T, &'static str>;
 
@@ -36,14 +39,25 @@ pub type FutureResult<T> = std::result::Result<T, &'static str>;
 pub struct TcpServerHandshake {
     stream: TcpStream,
     buf: BlobBuffer,
+    observer: ObserverSlot,
 }
 
 impl TcpServerHandshake {
     /// Start the VBus-over-TCP handshake as the server side.
     pub async fn start(stream: TcpStream) -> Result<TcpServerHandshake> {
+        TcpServerHandshake::start_with_observer(stream, None).await
+    }
+
+    /// Start the handshake like `start`, passing every command and reply
+    /// line including the initial greeting to `observer`.
+    pub async fn start_with_observer(
+        stream: TcpStream,
+        observer: Option<HandshakeObserver>,
+    ) -> Result<TcpServerHandshake> {
         let mut hs = TcpServerHandshake {
             stream,
             buf: BlobBuffer::new(),
+            observer: ObserverSlot::new(observer),
         };
 
         hs.send_reply("+HELLO\r\n").await?;
@@ -57,6 +71,7 @@ impl TcpServerHandshake {
     }
 
     async fn send_reply(&mut self, reply: &str) -> Result<()> {
+        self.observer.notify(HandshakeDirection::Sent, reply);
         self.stream.write_all(reply.as_bytes()).await?;
         Ok(())
     }
@@ -65,6 +80,7 @@ impl TcpServerHandshake {
         let line = loop {
             if let Some(idx) = self.buf.iter().position(|b| *b == 10) {
                 let string = std::str::from_utf8(&self.buf[0..idx])?.to_string();
+                self.observer.notify(HandshakeDirection::Received, &string);
 
                 self.buf.consume(idx + 1);
 
@@ -99,6 +115,7 @@ impl TcpServerHandshake {
     ///   to resolve the `receive_command` `Future`.
     /// - `Err(reply)` if the validation failed. The `reply` is send
     ///   back to the client and the command reception is repeated.
+    #[allow(clippy::needless_borrow)]
     pub async fn receive_command<V, R, T>(&mut self, validator: V) -> Result<T>
     where
         V: Fn(String, Option<String>) -> R,
@@ -259,4 +276,41 @@ impl TcpServerHandshake {
 
         Ok(self.stream)
     }
+
+    /// Accept `PASS` commands until a `DATA` command is received.
+    ///
+    /// If `password` is `None`, any or no password is accepted.
+    pub(crate) async fn receive_pass_and_data_commands(
+        mut self,
+        password: Option<&str>,
+    ) -> Result<TcpStream> {
+        let mut authenticated = password.is_none();
+        loop {
+            let is_data = self
+                .receive_command(|command, args| {
+                    let result = match command.as_str() {
+                        "PASS" => match (password, args) {
+                            (Some(password), Some(args)) if password != args => {
+                                Err("-ERROR: Wrong password\r\n")
+                            }
+                            (_, Some(_)) => Ok(false),
+                            (_, None) => Err("-ERROR Expected argument\r\n"),
+                        },
+                        "DATA" if authenticated => Ok(true),
+                        "DATA" => Err("-ERROR: Need password\r\n"),
+                        _ => Err("-ERROR: Unknown command\r\n"),
+                    };
+
+                    async move { result }
+                })
+                .await?;
+
+            if is_data {
+                break;
+            }
+            authenticated = true;
+        }
+
+        Ok(self.stream)
+    }
 }
@@ -1,7 +1,14 @@
-use async_std::{net::TcpListener, prelude::*};
+use async_std::{
+    io::{Cursor, Read},
+    net::TcpListener,
+    prelude::*,
+};
+
+use resol_vbus::{chrono::Utc, live_data_encoder, Data, Datagram, Header, Packet};
 
 use crate::{DeviceInformation, Result};
 
+#[allow(clippy::slow_vector_initialization)]
 pub(crate) async fn create_webserver(web_socket: TcpListener) -> Result<()> {
     loop {
         let (mut stream, _) = web_socket.accept().await?;
@@ -31,3 +38,103 @@ pub(crate) async fn create_webserver(web_socket: TcpListener) -> Result<()> {
         drop(stream);
     }
 }
+
+pub(crate) fn extend_from_data(buf: &mut Vec<u8>, data: &Data) {
+    let len = live_data_encoder::length_from_data(data);
+    let idx = buf.len();
+    buf.resize(idx + len, 0);
+    live_data_encoder::bytes_from_data(data, &mut buf[idx..]);
+}
+
+pub(crate) fn extend_with_empty_packet(
+    buf: &mut Vec<u8>,
+    destination_address: u16,
+    source_address: u16,
+    command: u16,
+) {
+    let data = Data::Packet(Packet {
+        header: Header {
+            timestamp: Utc::now(),
+            channel: 0,
+            destination_address,
+            source_address,
+            protocol_version: 0x20,
+        },
+        command,
+        frame_count: 0,
+        frame_data: [0; 508],
+    });
+    extend_from_data(buf, &data);
+}
+
+pub(crate) fn extend_from_datagram(
+    buf: &mut Vec<u8>,
+    destination_address: u16,
+    source_address: u16,
+    command: u16,
+    param16: i16,
+    param32: i32,
+) {
+    let data = Data::Datagram(Datagram {
+        header: Header {
+            timestamp: Utc::now(),
+            channel: 0,
+            destination_address,
+            source_address,
+            protocol_version: 0x20,
+        },
+        command,
+        param16,
+        param32,
+    });
+    extend_from_data(buf, &data);
+}
+
+pub(crate) struct PendingReader;
+
+impl Read for PendingReader {
+    fn poll_read(
+        self: std::pin::Pin<&mut Self>,
+        _cx: &mut std::task::Context<'_>,
+        _buf: &mut [u8],
+    ) -> std::task::Poll<std::io::Result<usize>> {
+        std::task::Poll::Pending
+    }
+}
+
+pub(crate) fn simulate_run<T, F: Future<Output = T>>(f: F) -> T {
+    async_std::task::block_on(f)
+}
+
+pub(crate) trait ToBytes {
+    fn to_bytes(&self) -> Vec<u8>;
+}
+
+pub(crate) fn hex_encode<T: ToBytes>(value: &T) -> String {
+    let buf = value.to_bytes();
+    buf.iter()
+        .map(|b| format!("{:02x}", b))
+        .collect::<Vec<String>>()
+        .concat()
+}
+
+impl ToBytes for Cursor<Vec<u8>> {
+    fn to_bytes(&self) -> Vec<u8> {
+        self.get_ref().clone()
+    }
+}
+
+impl ToBytes for Data {
+    fn to_bytes(&self) -> Vec<u8> {
+        let len = live_data_encoder::length_from_data(self);
+        let mut buf = vec![0; len];
+        live_data_encoder::bytes_from_data(self, &mut buf);
+        buf
+    }
+}
+
+impl ToBytes for Datagram {
+    fn to_bytes(&self) -> Vec<u8> {
+        Data::Datagram(self.clone()).to_bytes()
+    }
+}
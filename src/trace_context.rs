@@ -0,0 +1,145 @@
+use opentelemetry::{
+    trace::{SpanContext, SpanId, TraceContextExt, TraceFlags, TraceId, TraceState},
+    Context,
+};
+
+use crate::error::Result;
+
+/// The OpenTelemetry trace and span IDs attached as metadata to messages
+/// passed between tasks or sent to other systems.
+///
+/// The IDs are captured from the active OpenTelemetry `Context` when a
+/// message is created, e.g. a `ParamRequest` or a `DiscoveryMessage`. The
+/// receiving side can continue the trace using `context`. The VBus wire
+/// format is not changed: forwarding the context to other systems (e.g.
+/// as an MQTT user property) is up to the application, for which the W3C
+/// `traceparent` representation is provided.
+///
+/// # Examples
+///
+/// ```
+/// # fn main() -> async_resol_vbus::Result<()> {
+/// use async_resol_vbus::TraceContext;
+///
+/// let traceparent = "00-0af7651916cd43dd8448eb211c80319c-b7ad6b7169203331-01";
+///
+/// let trace_context = TraceContext::from_traceparent(traceparent)?;
+/// assert_eq!(traceparent, trace_context.traceparent());
+///
+/// let _guard = trace_context.context().attach();
+/// assert_eq!(Some(trace_context), TraceContext::current());
+/// # Ok(()) }
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct TraceContext {
+    span_context: SpanContext,
+}
+
+impl TraceContext {
+    /// Get the `TraceContext` of the active span, or `None` if there is
+    /// no valid span.
+    pub fn current() -> Option<TraceContext> {
+        let context = Context::current();
+        let span_context = context.span().span_context().clone();
+        if span_context.is_valid() {
+            Some(TraceContext { span_context })
+        } else {
+            None
+        }
+    }
+
+    /// Parse a W3C `traceparent` value like
+    /// `00-0af7651916cd43dd8448eb211c80319c-b7ad6b7169203331-01`.
+    pub fn from_traceparent(traceparent: &str) -> Result<TraceContext> {
+        let invalid = || format!("Invalid traceparent {:?}", traceparent).into();
+
+        let parts = traceparent.split('-').collect::<Vec<_>>();
+        let (trace_id, span_id, flags) = match parts[..] {
+            ["00", trace_id, span_id, flags]
+                if trace_id.len() == 32 && span_id.len() == 16 && flags.len() == 2 =>
+            {
+                (trace_id, span_id, flags)
+            }
+            _ => return Err(invalid()),
+        };
+
+        let trace_id = TraceId::from_hex(trace_id).map_err(|_| invalid())?;
+        let span_id = SpanId::from_hex(span_id).map_err(|_| invalid())?;
+        let flags = u8::from_str_radix(flags, 16).map_err(|_| invalid())?;
+
+        let span_context = SpanContext::new(
+            trace_id,
+            span_id,
+            TraceFlags::new(flags),
+            true,
+            TraceState::default(),
+        );
+        if !span_context.is_valid() {
+            return Err(invalid());
+        }
+
+        Ok(TraceContext { span_context })
+    }
+
+    /// Format as a W3C `traceparent` value.
+    pub fn traceparent(&self) -> String {
+        format!(
+            "00-{}-{}-{:02x}",
+            self.span_context.trace_id(),
+            self.span_context.span_id(),
+            self.span_context.trace_flags().to_u8()
+        )
+    }
+
+    /// Get the underlying `SpanContext`.
+    pub fn span_context(&self) -> &SpanContext {
+        &self.span_context
+    }
+
+    /// Get a `Context` with this trace context as the remote parent, to
+    /// continue the trace on the receiving side.
+    pub fn context(&self) -> Context {
+        Context::current().with_remote_span_context(self.span_context.clone())
+    }
+}
+
+impl From<SpanContext> for TraceContext {
+    fn from(span_context: SpanContext) -> TraceContext {
+        TraceContext { span_context }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_traceparent() -> Result<()> {
+        assert_eq!(None, TraceContext::current());
+
+        let traceparent = "00-0af7651916cd43dd8448eb211c80319c-b7ad6b7169203331-01";
+        let trace_context = TraceContext::from_traceparent(traceparent)?;
+        assert_eq!(traceparent, trace_context.traceparent());
+        assert!(trace_context.span_context().is_sampled());
+
+        {
+            let _guard = trace_context.context().attach();
+            assert_eq!(Some(trace_context.clone()), TraceContext::current());
+        }
+        assert_eq!(None, TraceContext::current());
+
+        for traceparent in [
+            "01-0af7651916cd43dd8448eb211c80319c-b7ad6b7169203331-01",
+            "00-0af7651916cd43dd8448eb211c80319c-b7ad6b7169203331",
+            "00-00000000000000000000000000000000-b7ad6b7169203331-01",
+            "00-0af7651916cd43dd8448eb211c80319c-b7ad6b71692033zz-01",
+        ] {
+            assert_eq!(
+                Err(format!("Invalid traceparent {:?}", traceparent).into()),
+                TraceContext::from_traceparent(traceparent)
+            );
+        }
+
+        Ok(())
+    }
+}
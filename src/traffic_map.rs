@@ -0,0 +1,226 @@
+use std::{cell::RefCell, collections::BTreeMap, marker::Unpin};
+
+use async_std::io::{Read, Write};
+
+use resol_vbus::{
+    chrono::{DateTime, Utc},
+    Data,
+};
+
+use crate::{error::Result, live_data_stream::LiveDataStream};
+
+/// The traffic statistics of a single kind of `Data`, see `TrafficMap`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TrafficEntry {
+    /// The ID string of the `Data`, e.g. `"00_0010_7E11_10_0100"`.
+    pub id: String,
+
+    /// The VBus channel.
+    pub channel: u8,
+
+    /// The destination address.
+    pub destination_address: u16,
+
+    /// The source address.
+    pub source_address: u16,
+
+    /// The protocol version.
+    pub protocol_version: u8,
+
+    /// The command.
+    pub command: u16,
+
+    /// The frame count of the last packet, `0` for datagrams and telegrams.
+    pub frame_count: u8,
+
+    /// The number of times this `Data` was seen.
+    pub count: usize,
+
+    /// The timestamp of the first occurrence.
+    pub first_seen: DateTime<Utc>,
+
+    /// The timestamp of the last occurrence.
+    pub last_seen: DateTime<Utc>,
+}
+
+impl TrafficEntry {
+    /// Get the average number of occurrences per minute.
+    ///
+    /// Returns `None` if the `Data` was only seen once.
+    pub fn rate_per_minute(&self) -> Option<f64> {
+        let millis = (self.last_seen - self.first_seen).num_milliseconds();
+        if self.count > 1 && millis > 0 {
+            Some((self.count - 1) as f64 * 60000.0 / millis as f64)
+        } else {
+            None
+        }
+    }
+}
+
+/// Passively collects which `Data` is sent over a VBus.
+///
+/// # Examples
+///
+/// ```no_run
+/// # fn main() -> async_resol_vbus::Result<()> { async_std::task::block_on(async {
+/// #
+/// use async_std::net::TcpStream;
+///
+/// use async_resol_vbus::{LiveDataStream, TrafficMap};
+///
+/// let stream = TcpStream::connect("192.168.5.217:7053").await?;
+/// // ... perform handshake ...
+/// let mut lds = LiveDataStream::from_tcp_stream(stream, 0, 0x0020);
+///
+/// let mut map = TrafficMap::new();
+/// map.observe(&mut lds, 60000).await?;
+///
+/// for entry in map.entries() {
+///     println!("{}: {} times, {:?} per minute", entry.id, entry.count, entry.rate_per_minute());
+/// }
+/// #
+/// # Ok(()) }) }
+/// ```
+#[derive(Debug, Default)]
+pub struct TrafficMap {
+    entries: BTreeMap<String, TrafficEntry>,
+}
+
+impl TrafficMap {
+    /// Create an empty `TrafficMap`.
+    pub fn new() -> TrafficMap {
+        TrafficMap {
+            entries: BTreeMap::new(),
+        }
+    }
+
+    /// Add a `Data` to the statistics.
+    pub fn add_data(&mut self, data: &Data) {
+        let header = data.as_ref();
+        let (command, frame_count) = match data {
+            Data::Packet(packet) => (packet.command, packet.frame_count),
+            Data::Datagram(dgram) => (dgram.command, 0),
+            Data::Telegram(tgram) => (u16::from(tgram.command), 0),
+        };
+
+        let entry = self
+            .entries
+            .entry(data.id_string())
+            .or_insert_with(|| TrafficEntry {
+                id: data.id_string(),
+                channel: header.channel,
+                destination_address: header.destination_address,
+                source_address: header.source_address,
+                protocol_version: header.protocol_version,
+                command,
+                frame_count,
+                count: 0,
+                first_seen: header.timestamp,
+                last_seen: header.timestamp,
+            });
+
+        entry.frame_count = frame_count;
+        entry.count += 1;
+        entry.last_seen = header.timestamp;
+    }
+
+    /// Receive data from `stream` for `timeout_ms` milliseconds and add it
+    /// to the statistics.
+    pub async fn observe<R: Read + Unpin, W: Write + Unpin>(
+        &mut self,
+        stream: &mut LiveDataStream<R, W>,
+        timeout_ms: u64,
+    ) -> Result<()> {
+        let map = RefCell::new(self);
+
+        stream
+            .receive(timeout_ms, |data| {
+                map.borrow_mut().add_data(data);
+                false
+            })
+            .await?;
+
+        Ok(())
+    }
+
+    /// Get the collected entries, sorted by their ID strings.
+    pub fn entries(&self) -> Vec<&TrafficEntry> {
+        self.entries.values().collect()
+    }
+
+    /// Get all distinct `(source_address, destination_address)` pairs.
+    pub fn address_pairs(&self) -> Vec<(u16, u16)> {
+        let mut pairs = self
+            .entries
+            .values()
+            .map(|entry| (entry.source_address, entry.destination_address))
+            .collect::<Vec<_>>();
+        pairs.sort_unstable();
+        pairs.dedup();
+        pairs
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use async_std::io::Cursor;
+
+    use super::*;
+
+    use crate::test_utils::{extend_from_datagram, extend_with_empty_packet, simulate_run};
+
+    #[test]
+    fn test_observe() {
+        let mut rx_buf = Vec::new();
+
+        extend_with_empty_packet(&mut rx_buf, 0x0010, 0x7E11, 0x0100);
+        extend_from_datagram(&mut rx_buf, 0x0000, 0x7E11, 0x0500, 0, 0);
+        extend_with_empty_packet(&mut rx_buf, 0x0010, 0x7E11, 0x0100);
+        extend_with_empty_packet(&mut rx_buf, 0x0015, 0x7E11, 0x0100);
+
+        let mut lds = LiveDataStream::new(&rx_buf[..], Cursor::new(Vec::new()), 0, 0x0020);
+
+        let mut map = TrafficMap::new();
+        simulate_run(map.observe(&mut lds, 1000)).unwrap();
+
+        let entries = map.entries();
+        assert_eq!(3, entries.len());
+        assert_eq!("00_0000_7E11_20_0500_0000", entries[0].id);
+        assert_eq!(0x0500, entries[0].command);
+        assert_eq!(0x20, entries[0].protocol_version);
+        assert_eq!("00_0010_7E11_10_0100", entries[1].id);
+        assert_eq!(2, entries[1].count);
+        assert_eq!(0x0100, entries[1].command);
+        assert_eq!("00_0015_7E11_10_0100", entries[2].id);
+        assert_eq!(1, entries[2].count);
+        assert_eq!(None, entries[2].rate_per_minute());
+
+        assert_eq!(
+            vec![(0x7E11, 0x0000), (0x7E11, 0x0010), (0x7E11, 0x0015)],
+            map.address_pairs()
+        );
+    }
+
+    #[test]
+    fn test_rate_per_minute() {
+        use resol_vbus::utils::utc_timestamp;
+
+        let mut entry = TrafficEntry {
+            id: String::new(),
+            channel: 0,
+            destination_address: 0x0010,
+            source_address: 0x7E11,
+            protocol_version: 0x10,
+            command: 0x0100,
+            frame_count: 0,
+            count: 5,
+            first_seen: utc_timestamp(1485688933),
+            last_seen: utc_timestamp(1485688933 + 120),
+        };
+
+        assert_eq!(Some(2.0), entry.rate_per_minute());
+
+        entry.count = 1;
+        assert_eq!(None, entry.rate_per_minute());
+    }
+}
@@ -0,0 +1,128 @@
+use std::path::Path;
+
+use async_std::{fs::File, prelude::*};
+
+use resol_vbus::chrono::{DateTime, Utc};
+
+use crate::{error::Result, json::push_json_string};
+
+/// A single value transaction recorded in a `TransactionJournal`.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct JournalEntry {
+    pub timestamp: DateTime<Utc>,
+    pub operation: &'static str,
+    pub address: u16,
+    pub index: i16,
+    pub subindex: u8,
+    pub value: Option<i32>,
+    pub reply: Option<i32>,
+    pub tries: usize,
+}
+
+impl JournalEntry {
+    fn to_json(&self) -> String {
+        let mut line = String::new();
+        line.push_str("{\"timestamp\":");
+        push_json_string(&mut line, &self.timestamp.to_rfc3339());
+        line.push_str(",\"operation\":");
+        push_json_string(&mut line, self.operation);
+        line.push_str(&format!(
+            ",\"address\":{},\"index\":{},\"subindex\":{}",
+            self.address, self.index, self.subindex
+        ));
+        if let Some(value) = self.value {
+            line.push_str(&format!(",\"value\":{}", value));
+        }
+        match self.reply {
+            Some(reply) => line.push_str(&format!(",\"outcome\":\"ok\",\"reply\":{}", reply)),
+            None => line.push_str(",\"outcome\":\"noReply\""),
+        }
+        line.push_str(&format!(",\"tries\":{}}}\n", self.tries));
+        line
+    }
+}
+
+/// An append-only journal of value transactions in JSON Lines format.
+///
+/// If set using `LiveDataStream::set_journal`, every value get / set
+/// (including bulk value sets) is appended as a single JSON object per
+/// line, providing an audit trail of configuration changes. Each object
+/// contains the `timestamp`, `operation` (`get`, `set` or `setBulk`),
+/// `address`, `index`, `subindex`, the requested `value` (for sets), the
+/// `outcome` (`ok` or `noReply`), the `reply` value and the number of
+/// `tries`.
+///
+/// # Examples
+///
+/// ```no_run
+/// # fn main() -> async_resol_vbus::Result<()> { async_std::task::block_on(async {
+/// #
+/// use async_std::net::TcpStream;
+///
+/// use async_resol_vbus::{LiveDataStream, TransactionJournal};
+///
+/// let stream = TcpStream::connect("192.168.5.217:7053").await?;
+/// // ... perform handshake ...
+/// let mut lds = LiveDataStream::from_tcp_stream(stream, 0, 0x0020);
+/// lds.set_journal(Some(TransactionJournal::open("transactions.jsonl").await?));
+/// #
+/// # Ok(()) }) }
+/// ```
+#[derive(Debug)]
+pub struct TransactionJournal {
+    file: File,
+}
+
+impl TransactionJournal {
+    /// Open the journal file at `path` for appending, creating it if necessary.
+    pub async fn open<P: AsRef<Path>>(path: P) -> Result<TransactionJournal> {
+        let file = async_std::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path.as_ref())
+            .await?;
+
+        Ok(TransactionJournal { file })
+    }
+
+    pub(crate) async fn append(&mut self, entry: &JournalEntry) -> Result<()> {
+        self.file.write_all(entry.to_json().as_bytes()).await?;
+        self.file.flush().await?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use resol_vbus::chrono::TimeZone;
+
+    use super::*;
+
+    #[test]
+    fn test_to_json() {
+        let mut entry = JournalEntry {
+            timestamp: Utc.timestamp_opt(1_000, 0).unwrap(),
+            operation: "set",
+            address: 0x7E11,
+            index: 0x1234,
+            subindex: 0,
+            value: Some(-5),
+            reply: Some(-5),
+            tries: 2,
+        };
+
+        assert_eq!(
+            "{\"timestamp\":\"1970-01-01T00:16:40+00:00\",\"operation\":\"set\",\"address\":32273,\"index\":4660,\"subindex\":0,\"value\":-5,\"outcome\":\"ok\",\"reply\":-5,\"tries\":2}\n",
+            entry.to_json()
+        );
+
+        entry.operation = "get";
+        entry.value = None;
+        entry.reply = None;
+
+        assert_eq!(
+            "{\"timestamp\":\"1970-01-01T00:16:40+00:00\",\"operation\":\"get\",\"address\":32273,\"index\":4660,\"subindex\":0,\"outcome\":\"noReply\",\"tries\":2}\n",
+            entry.to_json()
+        );
+    }
+}
@@ -0,0 +1,99 @@
+use std::time::Duration;
+
+/// The timing of a single transaction, see
+/// `LiveDataStream::last_transaction_timing`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TransactionTiming {
+    /// The number of times the request was sent.
+    pub tries: usize,
+
+    /// The time between sending the request for the last time and receiving
+    /// the matching reply, or `None` if no reply was received.
+    pub latency: Option<Duration>,
+
+    /// The total duration of the transaction including all tries.
+    pub duration: Duration,
+}
+
+/// Accumulated timings of all transactions, see
+/// `LiveDataStream::transaction_stats`.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct TransactionStats {
+    /// The number of transactions.
+    pub count: usize,
+
+    /// The number of transactions without a reply.
+    pub no_reply_count: usize,
+
+    /// The number of times a request had to be sent again.
+    pub retry_count: usize,
+
+    /// The shortest latency of all replied transactions.
+    pub min_latency: Option<Duration>,
+
+    /// The longest latency of all replied transactions.
+    pub max_latency: Option<Duration>,
+
+    /// The sum of the latencies of all replied transactions.
+    pub total_latency: Duration,
+}
+
+impl TransactionStats {
+    /// Get the average latency of all replied transactions.
+    pub fn average_latency(&self) -> Option<Duration> {
+        let replied_count = self.count - self.no_reply_count;
+        if replied_count > 0 {
+            Some(self.total_latency / replied_count as u32)
+        } else {
+            None
+        }
+    }
+
+    pub(crate) fn add(&mut self, timing: &TransactionTiming) {
+        self.count += 1;
+        self.retry_count += timing.tries.saturating_sub(1);
+
+        match timing.latency {
+            Some(latency) => {
+                self.min_latency = Some(self.min_latency.map_or(latency, |min| min.min(latency)));
+                self.max_latency = Some(self.max_latency.map_or(latency, |max| max.max(latency)));
+                self.total_latency += latency;
+            }
+            None => self.no_reply_count += 1,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_add() {
+        let mut stats = TransactionStats::default();
+        assert_eq!(None, stats.average_latency());
+
+        stats.add(&TransactionTiming {
+            tries: 1,
+            latency: Some(Duration::from_millis(40)),
+            duration: Duration::from_millis(40),
+        });
+        stats.add(&TransactionTiming {
+            tries: 2,
+            latency: Some(Duration::from_millis(20)),
+            duration: Duration::from_millis(520),
+        });
+        stats.add(&TransactionTiming {
+            tries: 3,
+            latency: None,
+            duration: Duration::from_millis(3000),
+        });
+
+        assert_eq!(3, stats.count);
+        assert_eq!(1, stats.no_reply_count);
+        assert_eq!(3, stats.retry_count);
+        assert_eq!(Some(Duration::from_millis(20)), stats.min_latency);
+        assert_eq!(Some(Duration::from_millis(40)), stats.max_latency);
+        assert_eq!(Some(Duration::from_millis(30)), stats.average_latency());
+    }
+}
@@ -0,0 +1,364 @@
+use std::{collections::VecDeque, io::Write, time::Duration};
+
+use resol_vbus::{
+    chrono::{DateTime, Duration as ChronoDuration, Utc},
+    Data, DataSet, RecordingWriter, Specification,
+};
+
+use crate::error::Result;
+
+/// A condition that starts the recording of a `TriggeredRecorder`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum RecordingTrigger {
+    /// Fires when the value of the packet field rises above the threshold.
+    FieldAbove {
+        /// The packet field ID, e.g. `"00_0010_7E11_10_0100_000_2_0"`.
+        packet_field_id: String,
+        /// The threshold value.
+        threshold: f64,
+    },
+
+    /// Fires when the value of the packet field falls below the threshold.
+    FieldBelow {
+        /// The packet field ID, e.g. `"00_0010_7E11_10_0100_000_2_0"`.
+        packet_field_id: String,
+        /// The threshold value.
+        threshold: f64,
+    },
+
+    /// Fires when a datagram with the given source address and command is
+    /// received.
+    Datagram {
+        /// The source address of the datagram.
+        source_address: u16,
+        /// The command of the datagram.
+        command: u16,
+    },
+}
+
+fn to_chrono_duration(duration: Duration) -> ChronoDuration {
+    ChronoDuration::from_std(duration).unwrap_or(ChronoDuration::MAX)
+}
+
+/// Records `Data` only around events.
+///
+/// The recorder keeps the `Data` of the last "pre-trigger" duration in
+/// memory. Once a `RecordingTrigger` fires (or `trigger` is called), the
+/// buffered `Data` is written to the `RecordingWriter`, followed by all
+/// `Data` received within the "post-trigger" duration. Every additional
+/// trigger during that time extends the recording.
+///
+/// # Examples
+///
+/// ```no_run
+/// # fn main() -> async_resol_vbus::Result<()> { async_std::task::block_on(async {
+/// #
+/// use std::{fs::File, time::Duration};
+///
+/// use async_std::net::TcpStream;
+///
+/// use resol_vbus::{Language, RecordingWriter, Specification, SpecificationFile};
+///
+/// use async_resol_vbus::{LiveDataStream, RecordingTrigger, TriggeredRecorder};
+///
+/// let spec = Specification::from_file(SpecificationFile::new_default(), Language::En);
+///
+/// let rw = RecordingWriter::new(File::create("events.vbus")?);
+/// let mut recorder = TriggeredRecorder::new(&spec, rw);
+/// recorder.set_pre_trigger_duration(Duration::from_secs(600));
+/// recorder.set_post_trigger_duration(Duration::from_secs(300));
+/// recorder.add_trigger(RecordingTrigger::FieldAbove {
+///     packet_field_id: "00_0010_7E11_10_0100_000_2_0".into(),
+///     threshold: 90.0,
+/// });
+///
+/// let stream = TcpStream::connect("192.168.5.217:7053").await?;
+/// // ... perform handshake ...
+/// let mut lds = LiveDataStream::from_tcp_stream(stream, 0, 0x0020);
+///
+/// while let Some(data) = lds.receive_any_data(60000).await? {
+///     recorder.add_data(data)?;
+/// }
+/// #
+/// # Ok(()) }) }
+/// ```
+#[derive(Debug)]
+pub struct TriggeredRecorder<'a, W: Write> {
+    spec: &'a Specification,
+    writer: RecordingWriter<W>,
+    triggers: Vec<(RecordingTrigger, bool)>,
+    pre_trigger_duration: ChronoDuration,
+    post_trigger_duration: ChronoDuration,
+    buffer: VecDeque<Data>,
+    last_timestamp: Option<DateTime<Utc>>,
+    record_until: Option<DateTime<Utc>>,
+}
+
+impl<'a, W: Write> TriggeredRecorder<'a, W> {
+    /// Create a new `TriggeredRecorder` writing to the `RecordingWriter`.
+    ///
+    /// The pre- and post-trigger durations default to 5 minutes.
+    pub fn new(spec: &'a Specification, writer: RecordingWriter<W>) -> TriggeredRecorder<'a, W> {
+        TriggeredRecorder {
+            spec,
+            writer,
+            triggers: Vec::new(),
+            pre_trigger_duration: ChronoDuration::minutes(5),
+            post_trigger_duration: ChronoDuration::minutes(5),
+            buffer: VecDeque::new(),
+            last_timestamp: None,
+            record_until: None,
+        }
+    }
+
+    /// Set the duration of `Data` kept in memory before a trigger fires.
+    pub fn set_pre_trigger_duration(&mut self, duration: Duration) {
+        self.pre_trigger_duration = to_chrono_duration(duration);
+    }
+
+    /// Set the duration of `Data` recorded after a trigger fired.
+    pub fn set_post_trigger_duration(&mut self, duration: Duration) {
+        self.post_trigger_duration = to_chrono_duration(duration);
+    }
+
+    /// Add a trigger condition.
+    pub fn add_trigger(&mut self, trigger: RecordingTrigger) {
+        self.triggers.push((trigger, false));
+    }
+
+    /// Get a reference to the underlying `RecordingWriter`.
+    pub fn get_ref(&self) -> &RecordingWriter<W> {
+        &self.writer
+    }
+
+    /// Get a mutable reference to the underlying `RecordingWriter`.
+    pub fn get_mut(&mut self) -> &mut RecordingWriter<W> {
+        &mut self.writer
+    }
+
+    /// Check whether the recorder is currently writing `Data`.
+    pub fn is_recording(&self) -> bool {
+        match (self.record_until, self.last_timestamp) {
+            (Some(record_until), Some(timestamp)) => timestamp <= record_until,
+            _ => false,
+        }
+    }
+
+    /// Fire a trigger manually, e.g. in response to an API call.
+    ///
+    /// The buffered `Data` is written immediately.
+    pub fn trigger(&mut self) -> Result<()> {
+        let timestamp = self.last_timestamp.unwrap_or_else(Utc::now);
+        self.record_until = Some(timestamp + self.post_trigger_duration);
+        self.flush_buffer()
+    }
+
+    /// Process a received `Data`.
+    ///
+    /// Returns whether a trigger fired for this `Data`.
+    pub fn add_data(&mut self, data: Data) -> Result<bool> {
+        let timestamp = data.as_ref().timestamp;
+        self.last_timestamp = Some(timestamp);
+
+        let triggered = self.check_triggers(&data);
+        if triggered {
+            self.record_until = Some(timestamp + self.post_trigger_duration);
+        }
+
+        self.buffer.push_back(data);
+
+        let min_timestamp = timestamp - self.pre_trigger_duration;
+        while let Some(data) = self.buffer.front() {
+            if data.as_ref().timestamp >= min_timestamp {
+                break;
+            }
+            self.buffer.pop_front();
+        }
+
+        if self.is_recording() {
+            self.flush_buffer()?;
+        }
+
+        Ok(triggered)
+    }
+
+    fn check_triggers(&mut self, data: &Data) -> bool {
+        let mut values = None;
+
+        let mut triggered = false;
+        let spec = self.spec;
+        for (trigger, was_active) in self.triggers.iter_mut() {
+            let trigger = &*trigger;
+            let is_active = match trigger {
+                RecordingTrigger::FieldAbove {
+                    packet_field_id,
+                    threshold,
+                }
+                | RecordingTrigger::FieldBelow {
+                    packet_field_id,
+                    threshold,
+                } => {
+                    let values = values.get_or_insert_with(|| {
+                        let mut data_set = DataSet::new();
+                        data_set.add_data(data.clone());
+                        spec.fields_in_data_set(&data_set)
+                            .map(|field| {
+                                (
+                                    field.field_spec().packet_field_id.clone(),
+                                    field.raw_value_f64(),
+                                )
+                            })
+                            .collect::<Vec<_>>()
+                    });
+
+                    let value = values
+                        .iter()
+                        .find(|(id, _)| id == packet_field_id)
+                        .and_then(|(_, value)| *value);
+
+                    match value {
+                        Some(value) => match trigger {
+                            RecordingTrigger::FieldAbove { .. } => value > *threshold,
+                            _ => value < *threshold,
+                        },
+                        // keep the state for data not containing the field
+                        None => *was_active,
+                    }
+                }
+                RecordingTrigger::Datagram {
+                    source_address,
+                    command,
+                } => {
+                    data.is_datagram()
+                        && data.as_ref().source_address == *source_address
+                        && data.as_datagram().command == *command
+                }
+            };
+
+            let is_datagram_trigger = matches!(trigger, RecordingTrigger::Datagram { .. });
+            if is_active && (is_datagram_trigger || !*was_active) {
+                triggered = true;
+            }
+            *was_active = is_active;
+        }
+
+        triggered
+    }
+
+    fn flush_buffer(&mut self) -> Result<()> {
+        while let Some(data) = self.buffer.pop_front() {
+            let mut data_set = DataSet::new();
+            data_set.timestamp = data.as_ref().timestamp;
+            data_set.add_data(data);
+            self.writer.write_data_set(&data_set)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use resol_vbus::{
+        utils::utc_timestamp, Datagram, Header, Language, Packet, RecordingReader,
+        SpecificationFile,
+    };
+
+    use super::*;
+
+    fn packet(offset: i64, temperature: i16) -> Data {
+        let mut frame_data = [0u8; 508];
+        frame_data[0..2].copy_from_slice(&temperature.to_le_bytes());
+
+        Data::Packet(Packet {
+            header: Header {
+                timestamp: utc_timestamp(1485688933 + offset),
+                channel: 0,
+                destination_address: 0x0010,
+                source_address: 0x7E11,
+                protocol_version: 0x10,
+            },
+            command: 0x0100,
+            frame_count: 1,
+            frame_data,
+        })
+    }
+
+    fn recorded_timestamps(bytes: &[u8]) -> Vec<i64> {
+        let mut rr = RecordingReader::new(bytes);
+        let mut timestamps = Vec::new();
+        while let Some(data_set) = rr.read_data_set().unwrap() {
+            timestamps.push(data_set.timestamp.timestamp() - 1485688933);
+        }
+        timestamps
+    }
+
+    #[test]
+    fn test_field_trigger() {
+        let spec = Specification::from_file(SpecificationFile::new_default(), Language::En);
+
+        let mut recorder = TriggeredRecorder::new(&spec, RecordingWriter::new(Vec::new()));
+        recorder.set_pre_trigger_duration(Duration::from_secs(20));
+        recorder.set_post_trigger_duration(Duration::from_secs(20));
+        recorder.add_trigger(RecordingTrigger::FieldAbove {
+            packet_field_id: "00_0010_7E11_10_0100_000_2_0".into(),
+            threshold: 90.0,
+        });
+
+        let temperatures = [500, 600, 700, 950, 960, 800, 700, 600, 500, 990];
+        let mut fired = Vec::new();
+        for (idx, temperature) in temperatures.iter().enumerate() {
+            if recorder
+                .add_data(packet(idx as i64 * 10, *temperature))
+                .unwrap()
+            {
+                fired.push(idx);
+            }
+        }
+
+        assert_eq!(vec![3, 9], fired);
+        assert!(recorder.is_recording());
+        assert_eq!(
+            vec![10, 20, 30, 40, 50, 70, 80, 90],
+            recorded_timestamps(recorder.get_ref().get_ref())
+        );
+    }
+
+    #[test]
+    fn test_datagram_and_manual_trigger() {
+        let spec = Specification::from_file(SpecificationFile::new_default(), Language::En);
+
+        let mut recorder = TriggeredRecorder::new(&spec, RecordingWriter::new(Vec::new()));
+        recorder.set_pre_trigger_duration(Duration::from_secs(5));
+        recorder.set_post_trigger_duration(Duration::from_secs(0));
+        recorder.add_trigger(RecordingTrigger::Datagram {
+            source_address: 0x7E11,
+            command: 0x0500,
+        });
+
+        assert!(!recorder.add_data(packet(0, 0)).unwrap());
+        assert!(!recorder.add_data(packet(10, 0)).unwrap());
+
+        let dgram = Data::Datagram(Datagram {
+            header: Header {
+                timestamp: utc_timestamp(1485688933 + 12),
+                destination_address: 0x0000,
+                source_address: 0x7E11,
+                protocol_version: 0x20,
+                ..Header::default()
+            },
+            command: 0x0500,
+            param16: 0,
+            param32: 0,
+        });
+        assert!(recorder.add_data(dgram).unwrap());
+        assert!(!recorder.add_data(packet(20, 0)).unwrap());
+        assert!(!recorder.add_data(packet(30, 0)).unwrap());
+
+        recorder.trigger().unwrap();
+
+        assert_eq!(
+            vec![10, 12, 30],
+            recorded_timestamps(recorder.get_ref().get_ref())
+        );
+    }
+}
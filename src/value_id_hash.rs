@@ -0,0 +1,33 @@
+/// Calculate the ID hash of a value's ID string.
+///
+/// VBus devices do not transmit the names of their values. Instead they
+/// allow to look up the ID hash of a value by its index (and vice versa).
+/// This function calculates the ID hash for a known value ID so that it
+/// can be compared to the ID hashes reported by the device, see
+/// `ControllerSession::get_value_id_table`.
+///
+/// # Examples
+///
+/// ```
+/// use async_resol_vbus::value_id_hash_by_id;
+///
+/// assert_eq!(0x0001_3884, value_id_hash_by_id("Foo"));
+/// ```
+pub fn value_id_hash_by_id(id: &str) -> i32 {
+    id.chars().fold(0, |acc, c| {
+        acc.wrapping_mul(0x21).wrapping_add(c as i32) & 0x7fffffff
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_value_id_hash_by_id() {
+        assert_eq!(0, value_id_hash_by_id(""));
+        assert_eq!(0x41, value_id_hash_by_id("A"));
+        assert_eq!(0x0000_08A3, value_id_hash_by_id("AB"));
+        assert_eq!(0x23FD_9A37, value_id_hash_by_id("Relais_Handbetrieb"));
+    }
+}
@@ -0,0 +1,228 @@
+use std::net::SocketAddr;
+
+use async_std::channel::{Receiver, Sender};
+
+use crate::{
+    connection_manager::ReconnectEvent, data_id::DataId, device_discovery::DiscoveryProgress,
+    freshness_tracker::PacketFreshness, live_data_stream::BufferOverflow,
+    retention::RetentionReport, watchdog::WatchdogEvent,
+};
+
+/// The operation that failed, see `VBusEvent::Error`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ErrorKind {
+    /// Establishing a connection failed.
+    Connect,
+
+    /// A transaction (e.g. getting or setting a value) failed.
+    Transaction,
+
+    /// Receiving data failed.
+    Receive,
+}
+
+/// A notification of one of the crate's subsystems.
+///
+/// The `LiveDataStream` and the `ConnectOptions` send these events to
+/// the `Sender` passed to their `set_event_sender` method. The events of
+/// subsystems with their own event types (e.g. the `Watchdog`) can be
+/// forwarded to the same channel using `forward_events`, so that an
+/// application can handle all notifications in a single loop.
+///
+/// # Examples
+///
+/// ```no_run
+/// # fn main() -> async_resol_vbus::Result<()> { async_std::task::block_on(async {
+/// #
+/// use async_resol_vbus::{
+///     connect_tcp_live_data_stream, forward_events, ConnectOptions, VBusEvent, Watchdog,
+/// };
+///
+/// let (sender, events) = async_std::channel::bounded(100);
+///
+/// let (watchdog_sender, watchdog_events) = async_std::channel::bounded(10);
+/// let mut watchdog = Watchdog::new();
+/// watchdog.set_sender(Some(watchdog_sender));
+/// async_std::task::spawn(forward_events(watchdog_events, sender.clone()));
+///
+/// let mut options = ConnectOptions::new();
+/// options.set_event_sender(Some(sender));
+/// let mut lds = connect_tcp_live_data_stream("192.168.5.217:7053", &options).await?;
+///
+/// async_std::task::spawn(async move {
+///     while let Ok(event) = events.recv().await {
+///         match event {
+///             VBusEvent::ConnectionDown => println!("Connection lost"),
+///             VBusEvent::Watchdog(event) => println!("Restarted {}", event.name),
+///             _ => {}
+///         }
+///     }
+/// });
+/// #
+/// # Ok(()) }) }
+/// ```
+#[derive(Debug, PartialEq)]
+pub enum VBusEvent {
+    /// A connection was established.
+    ConnectionUp,
+
+    /// The peer closed the connection.
+    ConnectionDown,
+
+    /// A `Data` with the contained ID was received.
+    DataReceived(DataId),
+
+    /// A device was found by a `DeviceDiscovery`.
+    DeviceDiscovered(SocketAddr),
+
+    /// The controller at the contained address offered the bus.
+    BusOffered {
+        /// The address of the controller.
+        address: u16,
+    },
+
+    /// A value written using `LiveDataStream::set_value_by_index_verified`
+    /// was read back successfully.
+    WriteVerified {
+        /// The address of the controller.
+        address: u16,
+
+        /// The index of the value.
+        index: i16,
+
+        /// The value read back from the controller.
+        value: i32,
+    },
+
+    /// A `ConnectionManager` attempt, see `ReconnectEvent`.
+    Reconnect(ReconnectEvent),
+
+    /// A `Watchdog` restarted a component.
+    Watchdog(WatchdogEvent),
+
+    /// The receive buffer of a `LiveDataStream` overflowed.
+    BufferOverflow(BufferOverflow),
+
+    /// The freshness of a packet changed, see `FreshnessTracker`.
+    PacketFreshness(PacketFreshness),
+
+    /// A `RetentionManager` run completed.
+    Retention(RetentionReport),
+
+    /// An operation failed.
+    Error {
+        /// The operation that failed.
+        kind: ErrorKind,
+
+        /// The error message.
+        message: String,
+    },
+}
+
+impl VBusEvent {
+    /// Get the `DeviceDiscovered` event for a `DiscoveryProgress`, or
+    /// `None` for progress not related to a single device.
+    pub fn from_discovery_progress(progress: &DiscoveryProgress) -> Option<VBusEvent> {
+        match progress {
+            DiscoveryProgress::AddressFound(address) => Some(VBusEvent::DeviceDiscovered(*address)),
+            _ => None,
+        }
+    }
+}
+
+impl From<ReconnectEvent> for VBusEvent {
+    fn from(event: ReconnectEvent) -> VBusEvent {
+        VBusEvent::Reconnect(event)
+    }
+}
+
+impl From<WatchdogEvent> for VBusEvent {
+    fn from(event: WatchdogEvent) -> VBusEvent {
+        VBusEvent::Watchdog(event)
+    }
+}
+
+impl From<BufferOverflow> for VBusEvent {
+    fn from(overflow: BufferOverflow) -> VBusEvent {
+        VBusEvent::BufferOverflow(overflow)
+    }
+}
+
+impl From<PacketFreshness> for VBusEvent {
+    fn from(freshness: PacketFreshness) -> VBusEvent {
+        VBusEvent::PacketFreshness(freshness)
+    }
+}
+
+impl From<RetentionReport> for VBusEvent {
+    fn from(report: RetentionReport) -> VBusEvent {
+        VBusEvent::Retention(report)
+    }
+}
+
+/// Forward all events of a subsystem-specific channel as `VBusEvent`s
+/// until either side of the channels is closed.
+pub async fn forward_events<T: Into<VBusEvent>>(receiver: Receiver<T>, sender: Sender<VBusEvent>) {
+    while let Ok(event) = receiver.recv().await {
+        if sender.send(event.into()).await.is_err() {
+            break;
+        }
+    }
+}
+
+/// Send `event` if an event `Sender` is set, dropping it if the channel
+/// is full.
+pub(crate) fn emit_event(sender: &Option<Sender<VBusEvent>>, event: VBusEvent) {
+    if let Some(ref sender) = sender {
+        drop(sender.try_send(event));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use crate::watchdog::RestartReason;
+
+    use super::*;
+
+    #[test]
+    fn test_forward_events() {
+        async_std::task::block_on(async {
+            let (watchdog_sender, watchdog_receiver) = async_std::channel::unbounded();
+            let (sender, receiver) = async_std::channel::unbounded();
+
+            let task = async_std::task::spawn(forward_events(watchdog_receiver, sender));
+
+            watchdog_sender
+                .send(WatchdogEvent {
+                    name: "logger".into(),
+                    reason: RestartReason::Stalled(Duration::from_secs(1)),
+                    restarts: 1,
+                })
+                .await
+                .unwrap();
+            drop(watchdog_sender);
+            task.await;
+
+            match receiver.recv().await.unwrap() {
+                VBusEvent::Watchdog(event) => assert_eq!("logger", event.name),
+                event => panic!("Unexpected event {:?}", event),
+            }
+            assert!(receiver.recv().await.is_err());
+        });
+
+        let address = "192.168.5.217:80".parse().unwrap();
+        assert_eq!(
+            Some(VBusEvent::DeviceDiscovered(address)),
+            VBusEvent::from_discovery_progress(&DiscoveryProgress::AddressFound(address))
+        );
+        assert_eq!(
+            None,
+            VBusEvent::from_discovery_progress(&DiscoveryProgress::RoundStarted {
+                round: 1,
+                rounds: 3
+            })
+        );
+    }
+}
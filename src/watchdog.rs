@@ -0,0 +1,327 @@
+use std::{
+    future::Future,
+    pin::Pin,
+    sync::{Arc, Mutex},
+    task::Poll,
+    time::{Duration, Instant},
+};
+
+use async_std::channel::Sender;
+
+use crate::error::Result;
+
+type ComponentFuture = Pin<Box<dyn Future<Output = Result<()>> + Send>>;
+
+type ComponentFactory = Box<dyn FnMut(Heartbeat) -> ComponentFuture + Send>;
+
+/// Used by a component supervised by a `Watchdog` to report progress.
+#[derive(Debug, Clone)]
+pub struct Heartbeat {
+    last_beat: Arc<Mutex<Instant>>,
+}
+
+impl Heartbeat {
+    fn new() -> Heartbeat {
+        Heartbeat {
+            last_beat: Arc::new(Mutex::new(Instant::now())),
+        }
+    }
+
+    /// Report that the component made progress, e.g. wrote data.
+    pub fn beat(&self) {
+        *self.last_beat.lock().unwrap() = Instant::now();
+    }
+
+    /// Get the time since the last progress was reported.
+    pub fn idle_time(&self) -> Duration {
+        self.last_beat.lock().unwrap().elapsed()
+    }
+}
+
+/// The reason a `Watchdog` restarted a component.
+#[derive(Debug, PartialEq)]
+pub enum RestartReason {
+    /// The component did not report progress for the contained duration.
+    Stalled(Duration),
+
+    /// The component's future completed with the contained result.
+    Exited(Result<()>),
+}
+
+/// Sent by a `Watchdog` every time it restarts a component.
+#[derive(Debug, PartialEq)]
+pub struct WatchdogEvent {
+    /// The name of the component.
+    pub name: String,
+
+    /// The reason for the restart.
+    pub reason: RestartReason,
+
+    /// The number of times the component was restarted so far.
+    pub restarts: usize,
+}
+
+struct Component {
+    name: String,
+    max_idle: Duration,
+    factory: ComponentFactory,
+    heartbeat: Heartbeat,
+    future: Option<ComponentFuture>,
+    restarts: usize,
+}
+
+impl Component {
+    fn start(&mut self) {
+        self.heartbeat = Heartbeat::new();
+        self.future = Some((self.factory)(self.heartbeat.clone()));
+    }
+}
+
+/// Supervises long-running components (e.g. a recorder or a `DataHub`)
+/// and restarts them if they stop making progress.
+///
+/// Every component is added with a factory that creates its future from a
+/// `Heartbeat`. The component calls `Heartbeat::beat` whenever it makes
+/// progress. If it does not do so for longer than its maximum idle time,
+/// or if its future completes, the future is dropped and a new one is
+/// created using the factory. Every restart is sent to the optional
+/// `Sender` as a `WatchdogEvent`.
+///
+/// All component futures are polled by the future returned from `run`, so
+/// dropping that future (e.g. using `ShutdownSignal::run_until`) stops all
+/// components.
+///
+/// # Examples
+///
+/// ```no_run
+/// # fn main() -> async_resol_vbus::Result<()> { async_std::task::block_on(async {
+/// #
+/// use std::time::Duration;
+///
+/// use async_std::net::TcpStream;
+///
+/// use async_resol_vbus::{LiveDataStream, Watchdog};
+///
+/// let mut watchdog = Watchdog::new();
+///
+/// watchdog.add_component("logger", Duration::from_secs(300), |heartbeat| {
+///     Box::pin(async move {
+///         let stream = TcpStream::connect("192.168.5.217:7053").await?;
+///         // ... perform handshake ...
+///         let mut lds = LiveDataStream::from_tcp_stream(stream, 0, 0x0020);
+///         while let Some(data) = lds.receive_any_data(60000).await? {
+///             // ... write `data` to a file ...
+///             heartbeat.beat();
+///         }
+///         Ok(())
+///     })
+/// });
+///
+/// watchdog.run().await;
+/// #
+/// # Ok(()) }) }
+/// ```
+pub struct Watchdog {
+    components: Vec<Component>,
+    check_interval: Duration,
+    sender: Option<Sender<WatchdogEvent>>,
+}
+
+impl Watchdog {
+    /// Create a new `Watchdog` without components.
+    pub fn new() -> Watchdog {
+        Watchdog {
+            components: Vec::new(),
+            check_interval: Duration::from_secs(1),
+            sender: None,
+        }
+    }
+
+    /// Set the interval in which the components are checked for progress.
+    ///
+    /// Defaults to one second.
+    pub fn set_check_interval(&mut self, check_interval: Duration) {
+        self.check_interval = check_interval;
+    }
+
+    /// Set the `Sender` that receives every restart.
+    ///
+    /// Defaults to `None`.
+    pub fn set_sender(&mut self, sender: Option<Sender<WatchdogEvent>>) {
+        self.sender = sender;
+    }
+
+    /// Add a component that is restarted if it does not report progress
+    /// for longer than `max_idle`.
+    pub fn add_component<F>(&mut self, name: &str, max_idle: Duration, factory: F)
+    where
+        F: FnMut(Heartbeat) -> ComponentFuture + Send + 'static,
+    {
+        self.components.push(Component {
+            name: name.to_string(),
+            max_idle,
+            factory: Box::new(factory),
+            heartbeat: Heartbeat::new(),
+            future: None,
+            restarts: 0,
+        });
+    }
+
+    fn restart(&mut self, idx: usize, reason: RestartReason) {
+        let component = &mut self.components[idx];
+        component.restarts += 1;
+        component.start();
+
+        if let Some(ref sender) = self.sender {
+            drop(sender.try_send(WatchdogEvent {
+                name: component.name.clone(),
+                reason,
+                restarts: component.restarts,
+            }));
+        }
+    }
+
+    /// Start all components and supervise them forever.
+    pub async fn run(mut self) {
+        for component in self.components.iter_mut() {
+            component.start();
+        }
+
+        let mut timer = Box::pin(async_std::task::sleep(self.check_interval));
+
+        async_std::future::poll_fn(move |cx| loop {
+            let mut restarted = false;
+
+            for idx in 0..self.components.len() {
+                let component = &mut self.components[idx];
+                let result = match component.future.as_mut() {
+                    Some(future) => match future.as_mut().poll(cx) {
+                        Poll::Ready(result) => result,
+                        Poll::Pending => continue,
+                    },
+                    None => continue,
+                };
+
+                self.restart(idx, RestartReason::Exited(result));
+                restarted = true;
+            }
+
+            if timer.as_mut().poll(cx).is_ready() {
+                for idx in 0..self.components.len() {
+                    let component = &self.components[idx];
+                    let idle = component.heartbeat.idle_time();
+                    if idle > component.max_idle {
+                        self.restart(idx, RestartReason::Stalled(idle));
+                    }
+                }
+
+                timer = Box::pin(async_std::task::sleep(self.check_interval));
+                restarted = true;
+            }
+
+            // poll the restarted futures and the new timer to register wakers
+            if !restarted {
+                return Poll::Pending;
+            }
+        })
+        .await
+    }
+}
+
+impl Default for Watchdog {
+    fn default() -> Watchdog {
+        Watchdog::new()
+    }
+}
+
+impl std::fmt::Debug for Watchdog {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let names = self
+            .components
+            .iter()
+            .map(|component| &component.name)
+            .collect::<Vec<_>>();
+        f.debug_struct("Watchdog")
+            .field("components", &names)
+            .field("check_interval", &self.check_interval)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use super::*;
+
+    #[test]
+    fn test_watchdog() {
+        let (sender, receiver) = async_std::channel::unbounded();
+
+        let mut watchdog = Watchdog::new();
+        watchdog.set_check_interval(Duration::from_millis(10));
+        watchdog.set_sender(Some(sender));
+
+        watchdog.add_component("healthy", Duration::from_millis(100), |heartbeat| {
+            Box::pin(async move {
+                loop {
+                    async_std::task::sleep(Duration::from_millis(5)).await;
+                    heartbeat.beat();
+                }
+            })
+        });
+
+        watchdog.add_component("stalled", Duration::from_millis(100), |_| {
+            Box::pin(async_std::future::pending())
+        });
+
+        let starts = Arc::new(AtomicUsize::new(0));
+        let failing_starts = starts.clone();
+        watchdog.add_component("failing", Duration::from_secs(60), move |_| {
+            let start = failing_starts.fetch_add(1, Ordering::SeqCst);
+            Box::pin(async move {
+                if start < 2 {
+                    Err("Failed".into())
+                } else {
+                    async_std::future::pending().await
+                }
+            })
+        });
+
+        async_std::task::block_on(async {
+            let result =
+                async_std::future::timeout(Duration::from_millis(250), watchdog.run()).await;
+            assert!(result.is_err());
+        });
+
+        assert_eq!(3, starts.load(Ordering::SeqCst));
+
+        let mut events = Vec::new();
+        while let Ok(event) = receiver.try_recv() {
+            events.push(event);
+        }
+
+        let failing = events
+            .iter()
+            .filter(|event| event.name == "failing")
+            .collect::<Vec<_>>();
+        assert_eq!(2, failing.len());
+        assert_eq!(
+            RestartReason::Exited(Err("Failed".into())),
+            failing[0].reason
+        );
+        assert_eq!(2, failing[1].restarts);
+
+        let stalled = events
+            .iter()
+            .filter(|event| event.name == "stalled")
+            .collect::<Vec<_>>();
+        assert!(!stalled.is_empty());
+        match stalled[0].reason {
+            RestartReason::Stalled(idle) => assert!(idle > Duration::from_millis(100)),
+            ref reason => panic!("Unexpected reason {:?}", reason),
+        }
+
+        assert!(events.iter().all(|event| event.name != "healthy"));
+    }
+}
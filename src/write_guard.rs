@@ -0,0 +1,226 @@
+use std::ops::RangeInclusive;
+
+use resol_vbus::Datagram;
+
+use crate::{error::Error, value_id_hash::value_id_hash_by_id};
+
+/// Check whether a datagram command sent by this side writes a value (set
+/// value and set bulk value).
+pub(crate) fn is_write_command(command: u16) -> bool {
+    matches!(command & 0xFF00, 0x0200 | 0x1500)
+}
+
+/// Check whether a datagram command begins or commits a bulk value
+/// transaction.
+pub(crate) fn is_transaction_command(command: u16) -> bool {
+    matches!(command, 0x1400 | 0x1402)
+}
+
+/// Restricts the value indices a `LiveDataStream` is allowed to write.
+///
+/// Indices are checked against the deny-list first and the allow-list
+/// second. Indices are handled as `u16` so that ranges above `0x7FFF` can
+/// be expressed naturally.
+///
+/// Values can also be allowed or denied by their ID. Since devices only
+/// report the ID hash of a value index, the IDs are converted using
+/// `value_id_hash_by_id` and have to match exactly (no wildcards). The
+/// `LiveDataStream` looks up the ID hash of the written index from the
+/// device before writing, see `LiveDataStream::set_write_guard`.
+///
+/// Beginning and committing bulk value transactions is allowed or denied
+/// as a whole, since those commands do not refer to a value index.
+///
+/// # Examples
+///
+/// ```
+/// use async_resol_vbus::WriteGuard;
+///
+/// let mut guard = WriteGuard::deny_all();
+/// guard.allow(0x1000..=0x1FFF);
+/// guard.deny(0x1234..=0x1234);
+///
+/// assert!(guard.is_allowed(0x1000));
+/// assert!(!guard.is_allowed(0x1234));
+/// assert!(!guard.is_allowed(0x0010));
+///
+/// guard.allow_id("Relais_Handbetrieb");
+/// assert!(guard.is_allowed_with_id_hash(0x0010, Some(0x23FD_9A37)));
+///
+/// assert!(!guard.is_transaction_allowed());
+/// guard.allow_transactions();
+/// assert!(guard.is_transaction_allowed());
+/// ```
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct WriteGuard {
+    allowed: Option<Vec<RangeInclusive<u16>>>,
+    denied: Vec<RangeInclusive<u16>>,
+    allowed_id_hashes: Vec<i32>,
+    denied_id_hashes: Vec<i32>,
+    transactions_denied: bool,
+}
+
+impl WriteGuard {
+    /// Create a `WriteGuard` allowing all indices that are not denied.
+    pub fn allow_all() -> WriteGuard {
+        WriteGuard {
+            allowed: None,
+            denied: Vec::new(),
+            allowed_id_hashes: Vec::new(),
+            denied_id_hashes: Vec::new(),
+            transactions_denied: false,
+        }
+    }
+
+    /// Create a `WriteGuard` denying all indices that are not allowed.
+    pub fn deny_all() -> WriteGuard {
+        WriteGuard {
+            allowed: Some(Vec::new()),
+            denied: Vec::new(),
+            allowed_id_hashes: Vec::new(),
+            denied_id_hashes: Vec::new(),
+            transactions_denied: true,
+        }
+    }
+
+    /// Allow writing the indices in the range.
+    ///
+    /// This has no effect for a guard created using `allow_all`.
+    pub fn allow(&mut self, indices: RangeInclusive<u16>) {
+        if let Some(ref mut allowed) = self.allowed {
+            allowed.push(indices);
+        }
+    }
+
+    /// Deny writing the indices in the range.
+    pub fn deny(&mut self, indices: RangeInclusive<u16>) {
+        self.denied.push(indices);
+    }
+
+    /// Allow writing the value with the given ID.
+    ///
+    /// This has no effect for a guard created using `allow_all`.
+    pub fn allow_id(&mut self, id: &str) {
+        if self.allowed.is_some() {
+            self.allowed_id_hashes.push(value_id_hash_by_id(id));
+        }
+    }
+
+    /// Deny writing the value with the given ID.
+    pub fn deny_id(&mut self, id: &str) {
+        self.denied_id_hashes.push(value_id_hash_by_id(id));
+    }
+
+    /// Allow beginning and committing bulk value transactions.
+    pub fn allow_transactions(&mut self) {
+        self.transactions_denied = false;
+    }
+
+    /// Deny beginning and committing bulk value transactions.
+    pub fn deny_transactions(&mut self) {
+        self.transactions_denied = true;
+    }
+
+    /// Check whether beginning and committing bulk value transactions is
+    /// allowed.
+    ///
+    /// Defaults to `true` for a guard created using `allow_all` and to
+    /// `false` for one created using `deny_all`.
+    pub fn is_transaction_allowed(&self) -> bool {
+        !self.transactions_denied
+    }
+
+    /// Check whether the guard contains rules for value IDs.
+    pub fn has_id_rules(&self) -> bool {
+        !self.allowed_id_hashes.is_empty() || !self.denied_id_hashes.is_empty()
+    }
+
+    /// Check whether writing the index is allowed.
+    ///
+    /// The ID hash of the index is treated as unknown, see
+    /// `is_allowed_with_id_hash`.
+    pub fn is_allowed(&self, index: i16) -> bool {
+        self.is_allowed_with_id_hash(index, None)
+    }
+
+    /// Check whether writing the index with the given ID hash is allowed.
+    ///
+    /// If the ID hash is unknown, the index is denied as soon as any ID
+    /// is denied, since it cannot be ruled out that it belongs to that ID.
+    pub fn is_allowed_with_id_hash(&self, index: i16, id_hash: Option<i32>) -> bool {
+        let index = index as u16;
+        let id_denied = match id_hash {
+            Some(id_hash) => self.denied_id_hashes.contains(&id_hash),
+            None => !self.denied_id_hashes.is_empty(),
+        };
+        if id_denied || self.denied.iter().any(|range| range.contains(&index)) {
+            false
+        } else {
+            match self.allowed {
+                Some(ref allowed) => {
+                    allowed.iter().any(|range| range.contains(&index))
+                        || id_hash.is_some_and(|id_hash| self.allowed_id_hashes.contains(&id_hash))
+                }
+                None => true,
+            }
+        }
+    }
+
+    /// Check whether sending the datagram is allowed without knowing the
+    /// ID hash of the written index, see `is_allowed`.
+    pub(crate) fn is_datagram_allowed(&self, dgram: &Datagram) -> bool {
+        if is_transaction_command(dgram.command) {
+            self.is_transaction_allowed()
+        } else if is_write_command(dgram.command) {
+            self.is_allowed(dgram.param16)
+        } else {
+            true
+        }
+    }
+}
+
+/// Create the error returned for a write the `WriteGuard` denied.
+pub(crate) fn write_denied_error(index: i16) -> Error {
+    Error::write_denied(format!(
+        "Writing value index 0x{:04X} is not allowed",
+        index as u16
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_allow_all() {
+        let mut guard = WriteGuard::allow_all();
+        guard.deny(0x8000..=0xFFFF);
+        guard.allow(0x9000..=0x9000);
+
+        assert!(guard.is_allowed(0x0000));
+        assert!(guard.is_allowed(0x7FFF));
+        assert!(!guard.is_allowed(0x8000u16 as i16));
+        assert!(!guard.is_allowed(0x9000u16 as i16));
+    }
+
+    #[test]
+    fn test_id_rules() {
+        let mut guard = WriteGuard::deny_all();
+        guard.allow(0x0010..=0x001F);
+        guard.allow_id("Relais_Handbetrieb");
+        assert!(guard.has_id_rules());
+
+        assert!(guard.is_allowed(0x0010));
+        assert!(!guard.is_allowed(0x0020));
+        assert!(guard.is_allowed_with_id_hash(0x0020, Some(0x23FD_9A37)));
+        assert!(!guard.is_allowed_with_id_hash(0x0020, Some(0x0001_3884)));
+
+        let mut guard = WriteGuard::allow_all();
+        guard.deny_id("Foo");
+        assert!(guard.has_id_rules());
+
+        assert!(!guard.is_allowed(0x0010));
+        assert!(!guard.is_allowed_with_id_hash(0x0010, Some(0x0001_3884)));
+        assert!(guard.is_allowed_with_id_hash(0x0010, Some(0x23FD_9A37)));
+    }
+}
@@ -0,0 +1,84 @@
+use std::{marker::Unpin, time::Duration};
+
+use async_std::{io::Write, prelude::*};
+
+/// Details of a write that did not complete within the write timeout, see
+/// `LiveDataStream::set_write_timeout`.
+///
+/// The error returned in that case provides it using `write_stall`. A
+/// stalled write leaves the peer with an incomplete frame, so the
+/// connection should be closed and re-established.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WriteStall {
+    /// The number of bytes written before the write stalled.
+    pub written: usize,
+
+    /// The number of bytes that should have been written.
+    pub total: usize,
+
+    /// The write timeout that elapsed.
+    pub timeout: Duration,
+}
+
+impl WriteStall {
+    /// Check whether some, but not all bytes were written.
+    pub fn is_partial(&self) -> bool {
+        self.written > 0
+    }
+}
+
+impl std::fmt::Display for WriteStall {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Write stalled after {} of {} bytes within {:?}",
+            self.written, self.total, self.timeout
+        )
+    }
+}
+
+impl std::error::Error for WriteStall {}
+
+/// Write a chunk of `bytes`, failing with a `WriteStall` if the `deadline`
+/// passed. `written` and `total` describe the progress of the whole write.
+pub(crate) async fn write_before<W: Write + Unpin>(
+    writer: &mut W,
+    bytes: &[u8],
+    timeout: Option<(Duration, std::time::Instant)>,
+    written: usize,
+    total: usize,
+) -> std::io::Result<usize> {
+    let (timeout, deadline) = match timeout {
+        Some(timeout) => timeout,
+        None => return writer.write(bytes).await,
+    };
+
+    let remaining = deadline.saturating_duration_since(std::time::Instant::now());
+    match async_std::future::timeout(remaining, writer.write(bytes)).await {
+        Ok(result) => result,
+        Err(_) => Err(std::io::Error::other(WriteStall {
+            written,
+            total,
+            timeout,
+        })),
+    }
+}
+
+/// Write all `bytes`, failing with a `WriteStall` if that takes longer
+/// than `timeout`.
+pub(crate) async fn write_all_within<W: Write + Unpin>(
+    writer: &mut W,
+    bytes: &[u8],
+    timeout: Option<Duration>,
+) -> std::io::Result<()> {
+    let timeout = timeout.map(|timeout| (timeout, std::time::Instant::now() + timeout));
+
+    let mut written = 0;
+    while written < bytes.len() {
+        match write_before(writer, &bytes[written..], timeout, written, bytes.len()).await? {
+            0 => return Err(std::io::ErrorKind::WriteZero.into()),
+            len => written += len,
+        }
+    }
+    Ok(())
+}